@@ -0,0 +1,704 @@
+//! Формат age v1 (<https://age-encryption.org/v1>) с получателями X25519 —
+//! ровно столько, сколько нужно `pm share`: файл, который pm шифрует,
+//! открывается утилитой `age -d -i key.txt`, и наоборот.
+//!
+//! ```text
+//! age-encryption.org/v1
+//! -> X25519 <эфемерный открытый ключ>
+//! <ключ файла, зашифрованный для получателя>
+//! --- <HMAC заголовка>
+//! <nonce 16 байт><STREAM: блоки по 64 КиБ, ChaCha20-Poly1305>
+//! ```
+//!
+//! Ключи в текстовом виде — bech32: `age1...` (открытый) и
+//! `AGE-SECRET-KEY-1...` (секретный). Другие типы получателей (scrypt, ssh)
+//! не поддерживаются: их строфы при расшифровке пропускаются.
+
+use anyhow::{Context, Result, anyhow};
+use base64::{Engine as _, engine::general_purpose};
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::Sha256;
+use subtle::ConstantTimeEq;
+use zeroize::Zeroize;
+
+const INTRO: &str = "age-encryption.org/v1";
+const X25519_LABEL: &[u8] = b"age-encryption.org/v1/X25519";
+const RECIPIENT_HRP: &str = "age";
+const IDENTITY_HRP: &str = "age-secret-key-";
+const FILE_KEY_LEN: usize = 16;
+const PAYLOAD_NONCE_LEN: usize = 16;
+const CHUNK: usize = 64 * 1024;
+const TAG_LEN: usize = 16;
+/// Тело строфы переносится по 64 символа base64.
+const COLUMNS: usize = 64;
+const ARMOR_BEGIN: &str = "-----BEGIN AGE ENCRYPTED FILE-----";
+const ARMOR_END: &str = "-----END AGE ENCRYPTED FILE-----";
+
+/// Открытый ключ получателя, `age1...`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Recipient([u8; 32]);
+
+/// Секретный ключ, `AGE-SECRET-KEY-1...`.
+pub struct Identity([u8; 32]);
+
+impl Drop for Identity {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl std::str::FromStr for Recipient {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let key = bech32::decode(RECIPIENT_HRP, s.trim())
+            .map_err(|e| anyhow!("invalid age recipient {s:?}: {e}"))?;
+        Ok(Recipient(key))
+    }
+}
+
+impl std::fmt::Display for Recipient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&bech32::encode(RECIPIENT_HRP, &self.0))
+    }
+}
+
+impl Identity {
+    pub fn generate() -> Self {
+        let mut key = [0u8; 32];
+        rand::rngs::OsRng.fill_bytes(&mut key);
+        Identity(key)
+    }
+
+    pub fn parse(s: &str) -> Result<Self> {
+        // Ошибка bech32 не должна цитировать сам секрет
+        let key = bech32::decode(IDENTITY_HRP, s.trim())
+            .map_err(|e| anyhow!("invalid age identity: {e}"))?;
+        Ok(Identity(key))
+    }
+
+    /// Прочитать файл ключей в формате age-keygen: по ключу на строку,
+    /// пустые строки и `#`-комментарии пропускаются.
+    pub fn read_file(text: &str) -> Result<Vec<Self>> {
+        let keys = text
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(Identity::parse)
+            .collect::<Result<Vec<_>>>()?;
+        if keys.is_empty() {
+            return Err(anyhow!("no AGE-SECRET-KEY-1... lines in the identity file"));
+        }
+        Ok(keys)
+    }
+
+    pub fn to_public(&self) -> Recipient {
+        Recipient(x25519::scalarmult(&self.0, &x25519::BASEPOINT))
+    }
+
+    /// `AGE-SECRET-KEY-1...` для файла ключа.
+    pub fn to_secret_string(&self) -> String {
+        bech32::encode(IDENTITY_HRP, &self.0).to_uppercase()
+    }
+}
+
+fn hkdf(ikm: &[u8], salt: &[u8], info: &[u8]) -> [u8; 32] {
+    let mut extract = <Hmac<Sha256> as Mac>::new_from_slice(salt).expect("HMAC takes any key");
+    extract.update(ikm);
+    let prk = extract.finalize().into_bytes();
+    let mut expand = <Hmac<Sha256> as Mac>::new_from_slice(&prk).expect("HMAC takes any key");
+    expand.update(info);
+    expand.update(&[1]);
+    expand.finalize().into_bytes().into()
+}
+
+fn b64(data: &[u8]) -> String {
+    general_purpose::STANDARD_NO_PAD.encode(data)
+}
+
+fn unb64(s: &str) -> Result<Vec<u8>> {
+    general_purpose::STANDARD_NO_PAD
+        .decode(s)
+        .map_err(|_| anyhow!("age header: invalid base64"))
+}
+
+fn aead(key: &[u8; 32]) -> ChaCha20Poly1305 {
+    ChaCha20Poly1305::new(Key::from_slice(key))
+}
+
+fn header_mac(file_key: &[u8], header: &[u8]) -> Hmac<Sha256> {
+    let key = hkdf(file_key, &[], b"header");
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&key).expect("HMAC takes any key");
+    mac.update(header);
+    mac
+}
+
+/// Строфа `-> X25519 <ключ>` с ключом файла для `recipient`.
+fn wrap(file_key: &[u8], recipient: &Recipient) -> Result<String> {
+    let ephemeral = Identity::generate();
+    let share = ephemeral.to_public();
+    let shared = x25519::scalarmult(&ephemeral.0, &recipient.0);
+    let mut salt = share.0.to_vec();
+    salt.extend_from_slice(&recipient.0);
+    let key = hkdf(&shared, &salt, X25519_LABEL);
+    let body = aead(&key)
+        .encrypt(Nonce::from_slice(&[0; 12]), file_key)
+        .map_err(|_| anyhow!("age: cannot wrap the file key"))?;
+    let body = b64(&body);
+    let mut stanza = format!("-> X25519 {}\n", b64(&share.0));
+    // Последняя строка тела всегда короче 64 символов, хоть бы и пустая
+    for line in body.as_bytes().chunks(COLUMNS) {
+        stanza.push_str(std::str::from_utf8(line)?);
+        stanza.push('\n');
+    }
+    if body.len().is_multiple_of(COLUMNS) {
+        stanza.push('\n');
+    }
+    Ok(stanza)
+}
+
+/// Ключ файла из строфы X25519, если она для `identity`.
+fn unwrap(args: &[&str], body: &[u8], identity: &Identity) -> Option<Vec<u8>> {
+    let [share] = args else { return None };
+    let share: [u8; 32] = unb64(share).ok()?.try_into().ok()?;
+    let shared = x25519::scalarmult(&identity.0, &share);
+    if shared.iter().all(|&b| b == 0) {
+        return None;
+    }
+    let mut salt = share.to_vec();
+    salt.extend_from_slice(&identity.to_public().0);
+    let key = hkdf(&shared, &salt, X25519_LABEL);
+    let file_key = aead(&key).decrypt(Nonce::from_slice(&[0; 12]), body).ok()?;
+    (file_key.len() == FILE_KEY_LEN).then_some(file_key)
+}
+
+fn stream_nonce(counter: u64, last: bool) -> [u8; 12] {
+    let mut nonce = [0u8; 12];
+    nonce[3..11].copy_from_slice(&counter.to_be_bytes());
+    nonce[11] = u8::from(last);
+    nonce
+}
+
+/// Зашифровать `plaintext` для `recipients` в двоичный файл age.
+pub fn encrypt(recipients: &[Recipient], plaintext: &[u8]) -> Result<Vec<u8>> {
+    if recipients.is_empty() {
+        return Err(anyhow!("age: at least one recipient is required"));
+    }
+    let mut file_key = [0u8; FILE_KEY_LEN];
+    rand::rngs::OsRng.fill_bytes(&mut file_key);
+
+    let mut header = format!("{INTRO}\n");
+    for recipient in recipients {
+        header.push_str(&wrap(&file_key, recipient)?);
+    }
+    header.push_str("---");
+    let mac = header_mac(&file_key, header.as_bytes())
+        .finalize()
+        .into_bytes();
+    let mut out = format!("{header} {}\n", b64(&mac)).into_bytes();
+
+    let mut nonce = [0u8; PAYLOAD_NONCE_LEN];
+    rand::rngs::OsRng.fill_bytes(&mut nonce);
+    out.extend_from_slice(&nonce);
+    let cipher = aead(&hkdf(&file_key, &nonce, b"payload"));
+    file_key.zeroize();
+
+    let chunks: Vec<&[u8]> = if plaintext.is_empty() {
+        vec![&[]]
+    } else {
+        plaintext.chunks(CHUNK).collect()
+    };
+    for (i, chunk) in chunks.iter().enumerate() {
+        let nonce = stream_nonce(i as u64, i + 1 == chunks.len());
+        let ct = cipher
+            .encrypt(Nonce::from_slice(&nonce), *chunk)
+            .map_err(|_| anyhow!("age: encryption failed"))?;
+        out.extend_from_slice(&ct);
+    }
+    Ok(out)
+}
+
+/// Расшифровать файл age (двоичный или в ASCII-броне) одним из `identities`.
+pub fn decrypt(identities: &[Identity], data: &[u8]) -> Result<Vec<u8>> {
+    let dearmored;
+    let data = if data.starts_with(ARMOR_BEGIN.as_bytes()) {
+        dearmored = dearmor(data)?;
+        &dearmored[..]
+    } else {
+        data
+    };
+
+    let mut lines = Vec::new();
+    let mut pos = 0;
+    loop {
+        let end = data[pos..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .ok_or_else(|| anyhow!("not an age file: header is truncated"))?;
+        let line =
+            std::str::from_utf8(&data[pos..pos + end]).map_err(|_| anyhow!("not an age file"))?;
+        lines.push((pos, line));
+        pos += end + 1;
+        if line.starts_with("---") {
+            break;
+        }
+    }
+    if lines.first().map(|(_, l)| *l) != Some(INTRO) {
+        return Err(anyhow!("not an age v1 file"));
+    }
+
+    let mut file_key = None;
+    let mut i = 1;
+    while i < lines.len() - 1 {
+        let stanza = lines[i].1;
+        let Some(rest) = stanza.strip_prefix("-> ") else {
+            return Err(anyhow!("age header: unexpected line {stanza:?}"));
+        };
+        let mut body = String::new();
+        loop {
+            i += 1;
+            let line = lines
+                .get(i)
+                .filter(|(_, l)| !l.starts_with("---"))
+                .ok_or_else(|| anyhow!("age header: stanza without a body"))?
+                .1;
+            body.push_str(line);
+            if line.len() < COLUMNS {
+                break;
+            }
+        }
+        i += 1;
+        let mut args = rest.split(' ');
+        if args.next() == Some("X25519") && file_key.is_none() {
+            let args: Vec<&str> = args.collect();
+            let body = unb64(&body)?;
+            file_key = identities.iter().find_map(|id| unwrap(&args, &body, id));
+        }
+    }
+    let mut file_key = file_key.ok_or_else(|| {
+        anyhow!("none of the identities can open this file (it was encrypted to other recipients)")
+    })?;
+
+    let (mac_pos, mac_line) = lines[lines.len() - 1];
+    let mac = mac_line
+        .strip_prefix("--- ")
+        .ok_or_else(|| anyhow!("age header: missing MAC"))?;
+    let header = &data[..mac_pos + 3];
+    let expected = header_mac(&file_key, header).finalize().into_bytes();
+    if !bool::from(unb64(mac)?.ct_eq(&expected)) {
+        return Err(anyhow!("age header: MAC mismatch, the file was modified"));
+    }
+
+    let payload = &data[pos..];
+    if payload.len() < PAYLOAD_NONCE_LEN {
+        return Err(anyhow!("age: payload is truncated"));
+    }
+    let (nonce, mut rest) = payload.split_at(PAYLOAD_NONCE_LEN);
+    let cipher = aead(&hkdf(&file_key, nonce, b"payload"));
+    file_key.zeroize();
+
+    let mut out = Vec::with_capacity(rest.len());
+    let mut counter = 0u64;
+    loop {
+        let last = rest.len() <= CHUNK + TAG_LEN;
+        let (chunk, tail) = rest.split_at(rest.len().min(CHUNK + TAG_LEN));
+        let plain = cipher
+            .decrypt(Nonce::from_slice(&stream_nonce(counter, last)), chunk)
+            .map_err(|_| anyhow!("age: payload is corrupted or truncated"))?;
+        if last && plain.is_empty() && counter > 0 {
+            return Err(anyhow!("age: unexpected empty final chunk"));
+        }
+        out.extend_from_slice(&plain);
+        if last {
+            return Ok(out);
+        }
+        rest = tail;
+        counter += 1;
+    }
+}
+
+/// Снять ASCII-броню (`age -a`): base64 между строками BEGIN и END.
+fn dearmor(data: &[u8]) -> Result<Vec<u8>> {
+    let text = std::str::from_utf8(data).context("age armor is not text")?;
+    let body: String = text
+        .lines()
+        .map(str::trim)
+        .skip_while(|l| *l != ARMOR_BEGIN)
+        .skip(1)
+        .take_while(|l| *l != ARMOR_END)
+        .collect();
+    general_purpose::STANDARD
+        .decode(body)
+        .map_err(|_| anyhow!("age armor: invalid base64"))
+}
+
+/// Bech32 (BIP 173) для ключей age: только 32-байтные данные.
+mod bech32 {
+    const CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+    const GEN: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+
+    fn polymod(values: &[u8]) -> u32 {
+        let mut chk: u32 = 1;
+        for &v in values {
+            let b = chk >> 25;
+            chk = ((chk & 0x1ffffff) << 5) ^ u32::from(v);
+            for (i, g) in GEN.iter().enumerate() {
+                if (b >> i) & 1 == 1 {
+                    chk ^= g;
+                }
+            }
+        }
+        chk
+    }
+
+    fn hrp_expand(hrp: &str) -> Vec<u8> {
+        let mut v: Vec<u8> = hrp.bytes().map(|c| c >> 5).collect();
+        v.push(0);
+        v.extend(hrp.bytes().map(|c| c & 31));
+        v
+    }
+
+    fn regroup(data: &[u8], from: u32, to: u32, pad: bool) -> Option<Vec<u8>> {
+        let (mut acc, mut bits, mut out) = (0u32, 0u32, Vec::new());
+        let max = (1 << to) - 1;
+        for &v in data {
+            acc = (acc << from) | u32::from(v);
+            bits += from;
+            while bits >= to {
+                bits -= to;
+                out.push(((acc >> bits) & max) as u8);
+            }
+        }
+        if pad {
+            if bits > 0 {
+                out.push(((acc << (to - bits)) & max) as u8);
+            }
+        } else if bits >= from || (acc << (to - bits)) & max != 0 {
+            return None;
+        }
+        Some(out)
+    }
+
+    pub fn encode(hrp: &str, data: &[u8; 32]) -> String {
+        let data = regroup(data, 8, 5, true).expect("padding always succeeds");
+        let mut values = hrp_expand(hrp);
+        values.extend_from_slice(&data);
+        values.extend_from_slice(&[0; 6]);
+        let pm = polymod(&values) ^ 1;
+        let checksum = (0..6).map(|i| ((pm >> (5 * (5 - i))) & 31) as u8);
+        let mut s = format!("{hrp}1");
+        s.extend(
+            data.into_iter()
+                .chain(checksum)
+                .map(|v| CHARSET[v as usize] as char),
+        );
+        s
+    }
+
+    pub fn decode(hrp: &str, s: &str) -> Result<[u8; 32], &'static str> {
+        if s.chars().any(|c| c.is_ascii_uppercase()) && s.chars().any(|c| c.is_ascii_lowercase()) {
+            return Err("mixed case");
+        }
+        let s = s.to_ascii_lowercase();
+        let (got_hrp, rest) = s.rsplit_once('1').ok_or("no separator")?;
+        if got_hrp != hrp {
+            return Err("wrong prefix");
+        }
+        let values = rest
+            .bytes()
+            .map(|c| CHARSET.iter().position(|&x| x == c).map(|p| p as u8))
+            .collect::<Option<Vec<u8>>>()
+            .ok_or("invalid character")?;
+        if values.len() < 6 {
+            return Err("too short");
+        }
+        let mut check = hrp_expand(hrp);
+        check.extend_from_slice(&values);
+        if polymod(&check) != 1 {
+            return Err("bad checksum");
+        }
+        regroup(&values[..values.len() - 6], 5, 8, false)
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or("wrong key length")
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn checksum_ok(s: &str) -> bool {
+            let s = s.to_ascii_lowercase();
+            let (hrp, rest) = s.rsplit_once('1').unwrap();
+            let mut values = hrp_expand(hrp);
+            values.extend(
+                rest.bytes()
+                    .map(|c| CHARSET.iter().position(|&x| x == c).unwrap() as u8),
+            );
+            polymod(&values) == 1
+        }
+
+        #[test]
+        fn checksums_match_bip173_vectors() {
+            for s in [
+                "A12UEL5L",
+                "a12uel5l",
+                "an83characterlonghumanreadablepartthatcontainsthenumber1andtheexcludedcharactersbio1tt5tgs",
+                "abcdef1qpzry9x8gf2tvdw0s3jn54khce6mua7lmqqqxw",
+                "11qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqc8247j",
+                "split1checkupstagehandshakeupstreamerranterredcaperred2y9e3w",
+                "?1ezyfcl",
+            ] {
+                assert!(checksum_ok(s), "{s}");
+            }
+            assert!(!checksum_ok(
+                "split1checkupstagehandshakeupstreamerranterredcaperred2y9e2w"
+            ));
+        }
+
+        #[test]
+        fn decode_rejects_bip173_invalid_strings() {
+            assert_eq!(decode("x", "pzry9x0s0muk"), Err("no separator"));
+            assert_eq!(decode("x", "x1b4n0q5v"), Err("invalid character"));
+            assert_eq!(decode("li", "li1dgmt3"), Err("too short"));
+            // Контрольная сумма посчитана по HRP в верхнем регистре
+            assert_eq!(decode("a", "A1G7SGD8"), Err("bad checksum"));
+            assert_eq!(decode("a", "A12uEL5L"), Err("mixed case"));
+            // Верная строка, но данных нет, а ключу нужно 32 байта
+            assert_eq!(decode("a", "a12uel5l"), Err("wrong key length"));
+        }
+
+        #[test]
+        fn encode_round_trips() {
+            let key: [u8; 32] = std::array::from_fn(|i| i as u8 * 7);
+            let s = encode("age", &key);
+            assert!(checksum_ok(&s));
+            assert_eq!(decode("age", &s), Ok(key));
+            assert_eq!(decode("age", &s.to_uppercase()), Ok(key));
+        }
+    }
+}
+
+/// X25519 (RFC 7748): лестница Монтгомери в арифметике по модулю 2^255 - 19,
+/// 16 «цифр» по 16 бит, как в TweetNaCl. Ветвлений по секрету нет.
+mod x25519 {
+    type Gf = [i64; 16];
+
+    pub const BASEPOINT: [u8; 32] = {
+        let mut p = [0u8; 32];
+        p[0] = 9;
+        p
+    };
+    const A24: Gf = [0xdb41, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+
+    fn carry(o: &mut Gf) {
+        for i in 0..16 {
+            o[i] += 1 << 16;
+            let c = o[i] >> 16;
+            if i < 15 {
+                o[i + 1] += c - 1;
+            } else {
+                o[0] += 38 * (c - 1);
+            }
+            o[i] -= c << 16;
+        }
+    }
+
+    /// Поменять `p` и `q` местами, если `b == 1`.
+    fn swap(p: &mut Gf, q: &mut Gf, b: i64) {
+        let mask = !(b - 1);
+        for i in 0..16 {
+            let t = mask & (p[i] ^ q[i]);
+            p[i] ^= t;
+            q[i] ^= t;
+        }
+    }
+
+    fn pack(n: &Gf) -> [u8; 32] {
+        let mut t = *n;
+        carry(&mut t);
+        carry(&mut t);
+        carry(&mut t);
+        for _ in 0..2 {
+            let mut m: Gf = [0; 16];
+            m[0] = t[0] - 0xffed;
+            for i in 1..15 {
+                m[i] = t[i] - 0xffff - ((m[i - 1] >> 16) & 1);
+                m[i - 1] &= 0xffff;
+            }
+            m[15] = t[15] - 0x7fff - ((m[14] >> 16) & 1);
+            let b = (m[15] >> 16) & 1;
+            m[14] &= 0xffff;
+            swap(&mut t, &mut m, 1 - b);
+        }
+        let mut o = [0u8; 32];
+        for i in 0..16 {
+            o[2 * i] = (t[i] & 0xff) as u8;
+            o[2 * i + 1] = (t[i] >> 8) as u8;
+        }
+        o
+    }
+
+    fn unpack(n: &[u8; 32]) -> Gf {
+        let mut o: Gf = [0; 16];
+        for i in 0..16 {
+            o[i] = i64::from(n[2 * i]) + (i64::from(n[2 * i + 1]) << 8);
+        }
+        o[15] &= 0x7fff;
+        o
+    }
+
+    fn add(a: &Gf, b: &Gf) -> Gf {
+        std::array::from_fn(|i| a[i] + b[i])
+    }
+
+    fn sub(a: &Gf, b: &Gf) -> Gf {
+        std::array::from_fn(|i| a[i] - b[i])
+    }
+
+    fn mul(a: &Gf, b: &Gf) -> Gf {
+        let mut t = [0i64; 31];
+        for i in 0..16 {
+            for j in 0..16 {
+                t[i + j] += a[i] * b[j];
+            }
+        }
+        for i in 0..15 {
+            t[i] += 38 * t[i + 16];
+        }
+        let mut o: Gf = std::array::from_fn(|i| t[i]);
+        carry(&mut o);
+        carry(&mut o);
+        o
+    }
+
+    fn inverse(i: &Gf) -> Gf {
+        let mut c = *i;
+        for a in (0..=253).rev() {
+            c = mul(&c, &c);
+            if a != 2 && a != 4 {
+                c = mul(&c, i);
+            }
+        }
+        c
+    }
+
+    pub fn scalarmult(scalar: &[u8; 32], point: &[u8; 32]) -> [u8; 32] {
+        let mut z = *scalar;
+        z[31] = (z[31] & 127) | 64;
+        z[0] &= 248;
+        let x = unpack(point);
+        let mut a: Gf = [0; 16];
+        let mut b = x;
+        let mut c: Gf = [0; 16];
+        let mut d: Gf = [0; 16];
+        a[0] = 1;
+        d[0] = 1;
+        for i in (0..=254).rev() {
+            let r = i64::from((z[i >> 3] >> (i & 7)) & 1);
+            swap(&mut a, &mut b, r);
+            swap(&mut c, &mut d, r);
+            let mut e = add(&a, &c);
+            a = sub(&a, &c);
+            c = add(&b, &d);
+            b = sub(&b, &d);
+            d = mul(&e, &e);
+            let f = mul(&a, &a);
+            a = mul(&c, &a);
+            c = mul(&b, &e);
+            e = add(&a, &c);
+            a = sub(&a, &c);
+            b = mul(&a, &a);
+            c = sub(&d, &f);
+            a = mul(&c, &A24);
+            a = add(&a, &d);
+            c = mul(&c, &a);
+            a = mul(&d, &f);
+            d = mul(&b, &x);
+            b = mul(&e, &e);
+            swap(&mut a, &mut b, r);
+            swap(&mut c, &mut d, r);
+        }
+        z.fill(0);
+        pack(&mul(&a, &inverse(&c)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unhex(s: &str) -> [u8; 32] {
+        let bytes: Vec<u8> = (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect();
+        bytes.try_into().unwrap()
+    }
+
+    #[test]
+    fn x25519_matches_rfc7748_scalar_vectors() {
+        // RFC 7748, раздел 5.2
+        let cases = [
+            (
+                "a546e36bf0527c9d3b16154b82465edd62144c0ac1fc5a18506a2244ba449ac4",
+                "e6db6867583030db3594c1a424b15f7c726624ec26b3353b10a903a6d0ab1c4c",
+                "c3da55379de9c6908e94ea4df28d084f32eccf03491c71f754b4075577a28552",
+            ),
+            (
+                "4b66e9d4d1b4673c5ad22691957d6af5c11b6421e0ea01d42ca4169e7918ba0d",
+                "e5210f12786811d3f4b7959d0538ae2c31dbe7106fc03c3efc4cd549c715a493",
+                "95cbde9476e8907d7aade45cb4b873f88b595a68799fa152e6f8f7647aac7957",
+            ),
+        ];
+        for (scalar, point, expected) in cases {
+            assert_eq!(
+                x25519::scalarmult(&unhex(scalar), &unhex(point)),
+                unhex(expected)
+            );
+        }
+        // Первая итерация k = u = 9
+        assert_eq!(
+            x25519::scalarmult(&x25519::BASEPOINT, &x25519::BASEPOINT),
+            unhex("422c8e7a6227d7bca1350b3e2bb7279f7897b87bb6854b783c60e80311ae3079")
+        );
+    }
+    #[test]
+    fn x25519_matches_rfc7748_key_agreement() {
+        // RFC 7748, раздел 6.1
+        let alice = unhex("77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a");
+        let bob = unhex("5dab087e624a8a4b79e17f8b83800ee66f3bb1292618b6fd1c2f8b27ff88e0eb");
+        let alice_pub = x25519::scalarmult(&alice, &x25519::BASEPOINT);
+        let bob_pub = x25519::scalarmult(&bob, &x25519::BASEPOINT);
+        assert_eq!(
+            alice_pub,
+            unhex("8520f0098930a754748b7ddcb43ef75a0dbf3a0d26381af4eba4a98eaa9b4e6a")
+        );
+        assert_eq!(
+            bob_pub,
+            unhex("de9edb7d7b7dc1b4d35b61c2ece435373f8343c85b78674dadfc7e146f882b4f")
+        );
+        let shared = unhex("4a5d9d5ba4ce2de1728e3bf480350f25e07e21c947d19e3376f09b3c1e161742");
+        assert_eq!(x25519::scalarmult(&alice, &bob_pub), shared);
+        assert_eq!(x25519::scalarmult(&bob, &alice_pub), shared);
+    }
+
+    #[test]
+    fn age_key_pair_matches_the_reference_implementation() {
+        // Ключ из примеров filippo.io/age
+        let identity = Identity::parse(
+            "AGE-SECRET-KEY-184JMZMVQH3E6U0PSL869004Y3U2NYV7R30EU99CSEDNPH02YUVFSZW44VU",
+        )
+        .unwrap();
+        assert_eq!(
+            identity.to_public().to_string(),
+            "age1cy0su9fwf3gf9mw868g5yut09p6nytfmmnktexz2ya5uqg9vl9sss4euqm"
+        );
+        assert_eq!(
+            identity.to_secret_string(),
+            "AGE-SECRET-KEY-184JMZMVQH3E6U0PSL869004Y3U2NYV7R30EU99CSEDNPH02YUVFSZW44VU"
+        );
+    }
+}
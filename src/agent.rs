@@ -0,0 +1,347 @@
+//! `pm agent`: держать мастер-ключ в памяти фонового процесса, а не в файле.
+//!
+//! Агент слушает `$XDG_RUNTIME_DIR/pm-agent-<отпечаток>.sock` (только для
+//! владельца); отпечаток — от пути хранилища, у каждого хранилища свой агент.
+//! Протокол: запрос и ответ — 4 байта длины (big-endian) и данные.
+//! Запросы: `get` — ответ 32 байта ключа и 32 байта отпечатка обёрнутого
+//! ключа из config.json, каким он был при запуске агента; `stop` — агент
+//! затирает ключ и завершается, ответ пустой. Ключ с чужим отпечатком
+//! (после `pm rekey`, `pm passwd`, `pm backup restore`) клиент не берёт. Агент сам выходит после простоя
+//! (`session-ttl`) и по жёсткому пределу (`session-max`).
+//!
+//! Ключ агенту передаётся через stdin дочернего процесса, в аргументах
+//! и окружении его нет. На платформах без Unix-сокетов остаётся файловый кэш.
+
+use crate::config::Config;
+use crate::crypto::MasterKey;
+use anyhow::Result;
+
+#[cfg(unix)]
+pub use imp::{agent_forget, agent_get, agent_get_for, run, start, stop};
+
+#[cfg(unix)]
+mod imp {
+    use super::*;
+    use crate::session::{session_ttl, unlock_interactive};
+    use crate::store::{store_root, store_tag};
+    use anyhow::{Context, anyhow};
+    use std::io::{Read, Write};
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::PathBuf;
+    use std::time::{Duration, Instant};
+    use zeroize::Zeroize;
+
+    const MAX_MESSAGE: u32 = 64;
+
+    fn socket_path() -> Result<PathBuf> {
+        socket_path_for(&store_tag()?)
+    }
+
+    fn socket_path_for(tag: &str) -> Result<PathBuf> {
+        let dir = std::env::var("XDG_RUNTIME_DIR")
+            .map_err(|_| anyhow!("pm agent needs XDG_RUNTIME_DIR to be set"))?;
+        Ok(PathBuf::from(dir).join(format!("pm-agent-{tag}.sock")))
+    }
+
+    fn send(stream: &mut UnixStream, payload: &[u8]) -> std::io::Result<()> {
+        stream.write_all(&(payload.len() as u32).to_be_bytes())?;
+        stream.write_all(payload)
+    }
+
+    fn recv(stream: &mut UnixStream) -> std::io::Result<Vec<u8>> {
+        let mut len = [0u8; 4];
+        stream.read_exact(&mut len)?;
+        let len = u32::from_be_bytes(len);
+        if len > MAX_MESSAGE {
+            return Err(std::io::Error::other("agent message too long"));
+        }
+        let mut buf = vec![0u8; len as usize];
+        stream.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    fn request(cmd: &[u8]) -> Option<Vec<u8>> {
+        request_at(&socket_path().ok()?, cmd)
+    }
+
+    fn request_at(socket: &std::path::Path, cmd: &[u8]) -> Option<Vec<u8>> {
+        let mut stream = UnixStream::connect(socket).ok()?;
+        stream.set_read_timeout(Some(Duration::from_secs(2))).ok()?;
+        stream
+            .set_write_timeout(Some(Duration::from_secs(2)))
+            .ok()?;
+        send(&mut stream, cmd).ok()?;
+        recv(&mut stream).ok()
+    }
+
+    /// Отпечаток обёрнутого мастер-ключа: меняется вместе с config.json
+    /// при замене ключа, пароля или всего хранилища.
+    fn wrapped_key_id(cfg: &Config) -> [u8; 32] {
+        use sha2::{Digest, Sha256};
+
+        let mut hasher = Sha256::new();
+        hasher.update(cfg.enc.master_key_nonce.as_bytes());
+        hasher.update(b"\0");
+        hasher.update(cfg.enc.encrypted_master_key.as_bytes());
+        hasher.finalize().into()
+    }
+
+    /// Ключ от запущенного агента, если он есть и подходит к `cfg`.
+    pub fn agent_get(cfg: &Config) -> Option<MasterKey> {
+        agent_get_for(&store_tag().ok()?, cfg)
+    }
+
+    /// Ключ от агента хранилища с отпечатком `tag` (см. `store_tag_for`).
+    /// Агента, который держит ключ от прежнего config.json, останавливаем.
+    pub fn agent_get_for(tag: &str, cfg: &Config) -> Option<MasterKey> {
+        let socket = socket_path_for(tag).ok()?;
+        let mut reply = request_at(&socket, b"get")?;
+        let mk = match reply.split_at_checked(32) {
+            Some((key, id)) if id == wrapped_key_id(cfg) => key.try_into().ok(),
+            _ => {
+                crate::log::info(format_args!(
+                    "pm agent holds a key for another config.json; stopping it"
+                ));
+                request_at(&socket, b"stop");
+                None
+            }
+        };
+        reply.zeroize();
+        mk
+    }
+
+    /// Попросить агента забыть ключ и завершиться. `true`, если агент был.
+    pub fn agent_forget() -> bool {
+        request(b"stop").is_some()
+    }
+
+    /// Ключ в заблокированной (не уходящей в swap) памяти; затирается при drop.
+    struct LockedKey(Box<MasterKey>);
+
+    impl LockedKey {
+        fn new(mk: MasterKey) -> Self {
+            let key = LockedKey(Box::new(mk));
+            // SAFETY: буфер живёт в куче до drop; ошибку mlock (лимиты) терпим
+            unsafe { libc::mlock(key.0.as_ptr().cast(), key.0.len()) };
+            key
+        }
+    }
+
+    impl Drop for LockedKey {
+        fn drop(&mut self) {
+            self.0.zeroize();
+            // SAFETY: тот же буфер, что и в mlock
+            unsafe { libc::munlock(self.0.as_ptr().cast(), self.0.len()) };
+        }
+    }
+
+    /// Собеседник — тот же пользователь. Права на сокет это и так
+    /// гарантируют, но на Linux проверяем явно.
+    #[cfg(target_os = "linux")]
+    fn peer_allowed(stream: &UnixStream) -> bool {
+        use std::os::unix::io::AsRawFd;
+        // SAFETY: ucred — POD, getsockopt лишь заполняет его
+        let mut cred: libc::ucred = unsafe { std::mem::zeroed() };
+        let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+        let ok = unsafe {
+            libc::getsockopt(
+                stream.as_raw_fd(),
+                libc::SOL_SOCKET,
+                libc::SO_PEERCRED,
+                (&mut cred as *mut libc::ucred).cast(),
+                &mut len,
+            )
+        } == 0;
+        // SAFETY: getuid не может завершиться ошибкой
+        ok && cred.uid == unsafe { libc::getuid() }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn peer_allowed(_stream: &UnixStream) -> bool {
+        true
+    }
+
+    /// Обслужить одно соединение. `true` — агенту пора завершаться.
+    fn handle(mut stream: UnixStream, key: &LockedKey, key_id: &[u8; 32]) -> bool {
+        if !peer_allowed(&stream) {
+            return false;
+        }
+        let _ = stream.set_read_timeout(Some(Duration::from_secs(2)));
+        match recv(&mut stream).as_deref() {
+            Ok(b"get") => {
+                let mut reply = [0u8; 64];
+                reply[..32].copy_from_slice(&key.0[..]);
+                reply[32..].copy_from_slice(key_id);
+                let _ = send(&mut stream, &reply);
+                reply.zeroize();
+                false
+            }
+            Ok(b"stop") => {
+                let _ = send(&mut stream, b"");
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Занять сокет; чужой живой агент — ошибка, брошенный файл удаляем.
+    fn bind(path: &std::path::Path) -> Result<UnixListener> {
+        if path.exists() {
+            if UnixStream::connect(path).is_ok() {
+                return Err(anyhow!("an agent is already running ({})", path.display()));
+            }
+            std::fs::remove_file(path)?;
+        }
+        let listener =
+            UnixListener::bind(path).with_context(|| format!("cannot bind {}", path.display()))?;
+        crate::perms::restrict(path)?;
+        Ok(listener)
+    }
+
+    /// Тело фонового процесса: ключ читается из stdin.
+    pub fn run(cfg: &Config) -> Result<()> {
+        let mut buf = [0u8; 32];
+        std::io::stdin().read_exact(&mut buf)?;
+        let key = LockedKey::new(buf);
+        buf.zeroize();
+        let key_id = wrapped_key_id(cfg);
+
+        // Не оставлять ключ в core-файлах и не пускать ptrace от того же пользователя
+        #[cfg(target_os = "linux")]
+        // SAFETY: prctl с целочисленными аргументами
+        unsafe {
+            libc::prctl(libc::PR_SET_DUMPABLE, 0, 0, 0, 0)
+        };
+
+        let path = socket_path()?;
+        let listener = bind(&path)?;
+        listener.set_nonblocking(true)?;
+
+        let idle = Duration::from_secs(session_ttl(false, cfg.settings.session.ttl_secs));
+        let hard_cap = cfg.settings.session.max_secs.map(Duration::from_secs);
+        let started = Instant::now();
+        let mut last_used = started;
+
+        loop {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    let _ = stream.set_nonblocking(false);
+                    last_used = Instant::now();
+                    if handle(stream, &key, &key_id) {
+                        break;
+                    }
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                    std::thread::sleep(Duration::from_millis(100));
+                }
+                Err(_) => break,
+            }
+            if last_used.elapsed() > idle || hard_cap.is_some_and(|cap| started.elapsed() > cap) {
+                break;
+            }
+        }
+
+        let _ = std::fs::remove_file(&path);
+        Ok(())
+    }
+
+    /// pm agent: спросить пароль и запустить фоновый процесс.
+    pub fn start(cfg: &Config) -> Result<()> {
+        use std::os::unix::process::CommandExt;
+        use std::process::{Command, Stdio};
+
+        if session_ttl(false, cfg.settings.session.ttl_secs) == 0 {
+            return Err(anyhow!(
+                "session caching is disabled (session-ttl 0); the agent would exit immediately"
+            ));
+        }
+        let path = socket_path()?;
+        if UnixStream::connect(&path).is_ok() {
+            return Err(anyhow!("an agent is already running ({})", path.display()));
+        }
+
+        let mut mk = unlock_interactive(cfg)?;
+        let mut cmd = Command::new(std::env::current_exe()?);
+        // Хранилище из --store или профиль дочерний процесс иначе не узнает
+        match crate::profiles::active_name() {
+            Some(profile) => cmd.args(["--profile", profile]),
+            None => cmd.env("PM_STORE_DIR", store_root()?),
+        };
+        cmd.args(["agent", "--foreground"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        // SAFETY: setsid безопасен между fork и exec
+        unsafe {
+            cmd.pre_exec(|| {
+                libc::setsid();
+                Ok(())
+            });
+        }
+        let mut child = cmd.spawn().context("cannot start the agent process")?;
+        let sent = child
+            .stdin
+            .take()
+            .map(|mut stdin| stdin.write_all(&mk))
+            .unwrap_or(Ok(()));
+        mk.zeroize();
+        sent?;
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline {
+            if let Some(mut got) = agent_get(cfg) {
+                got.zeroize();
+                println!("Agent started (pid {}).", child.id());
+                return Ok(());
+            }
+            if let Some(status) = child.try_wait()? {
+                return Err(anyhow!("agent exited during startup ({status})"));
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        Err(anyhow!("agent did not come up within 5 seconds"))
+    }
+
+    /// pm agent --stop
+    pub fn stop() -> Result<()> {
+        if agent_forget() {
+            println!("Agent stopped.");
+        } else {
+            println!("No agent running.");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(unix))]
+pub fn agent_get(_cfg: &Config) -> Option<MasterKey> {
+    None
+}
+
+#[cfg(not(unix))]
+pub fn agent_get_for(_tag: &str, _cfg: &Config) -> Option<MasterKey> {
+    None
+}
+
+#[cfg(not(unix))]
+pub fn agent_forget() -> bool {
+    false
+}
+
+#[cfg(not(unix))]
+pub fn start(_cfg: &Config) -> Result<()> {
+    Err(anyhow::anyhow!(
+        "pm agent needs Unix domain sockets; the session file cache is used instead"
+    ))
+}
+
+#[cfg(not(unix))]
+pub fn run(cfg: &Config) -> Result<()> {
+    start(cfg)
+}
+
+#[cfg(not(unix))]
+pub fn stop() -> Result<()> {
+    println!("No agent running.");
+    Ok(())
+}
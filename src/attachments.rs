@@ -0,0 +1,395 @@
+//! Вложения: небольшие файлы при записи — коды восстановления, PDF с
+//! резервными кодами 2FA (`pm attach add/list/get/rm`).
+//!
+//! Содержимое не входит в JSON записи, чтобы тот оставался маленьким: каждое
+//! вложение — отдельный файл `store/<путь>.attachments/<имя>.enc` в той же
+//! обёртке, что и записи. Присоединённые данные — `<путь>.attachments/<имя>`,
+//! так что файл не подложить под другое имя или к другой записи. В самой
+//! записи — только имена и размеры (`Entry::attachments`).
+//!
+//! Вложения следуют за записью в `pm mv`, `pm cp`, `pm rm` (и в корзину),
+//! `pm rekey`, попадают в бэкапы и в `pm export`.
+
+use crate::config::Config;
+use crate::crypto::MasterKey;
+use crate::entry::{AttachmentMeta, Entry};
+use crate::git::auto_commit;
+use crate::hooks::{HookAction, run_hooks};
+use crate::progress::human_size;
+use crate::session::get_master_key_with_cache;
+use crate::store::{
+    ATTACHMENTS_SUFFIX, attachments_dir_in, entry_exists, is_windows_device, load_blob, load_entry,
+    nfc, save_blob, save_entry, store_root, validate_entry_path, write_atomic_private,
+};
+use anyhow::{Context, Result, anyhow};
+use base64::{Engine as _, engine::general_purpose};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+/// Имя файла на диске — `<имя>.enc`, ему тоже нужно уложиться в 255 байт.
+const MAX_NAME_LEN: usize = 200;
+
+/// Проверить имя вложения и вернуть его в NFC: одно имя файла, без каталогов.
+pub fn validate_name(name: &str) -> Result<String> {
+    let name = nfc(name).into_owned();
+    let invalid = |why: &str| anyhow!("invalid attachment name {name:?}: {why}");
+    if name.trim().is_empty() {
+        return Err(invalid("empty name"));
+    }
+    if name == "." || name == ".." {
+        return Err(invalid("`.` and `..` are not allowed"));
+    }
+    if name.contains(['/', '\\']) {
+        return Err(invalid("must be a file name, not a path"));
+    }
+    if name.chars().any(char::is_control) {
+        return Err(invalid("control characters are not allowed"));
+    }
+    if name.len() > MAX_NAME_LEN {
+        return Err(invalid("longer than 200 bytes"));
+    }
+    if is_windows_device(&name) {
+        return Err(invalid("reserved name"));
+    }
+    Ok(name)
+}
+
+/// Присоединённые данные вложения `name` записи `path`.
+fn aad(path: &str, name: &str) -> String {
+    format!("{path}{ATTACHMENTS_SUFFIX}/{name}")
+}
+
+fn blob_file(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!("{name}.enc"))
+}
+
+fn store_dir() -> Result<PathBuf> {
+    Ok(store_root()?.join("store"))
+}
+
+/// Содержимое вложения `name` записи `path` хранилища `store_dir`.
+pub fn read_in(store_dir: &Path, path: &str, name: &str, mk: &MasterKey) -> Result<Vec<u8>> {
+    let file = blob_file(&attachments_dir_in(store_dir, path)?, name);
+    load_blob(&file, &aad(path, name), mk)
+        .with_context(|| format!("cannot read attachment {name} of {path}"))
+}
+
+/// Содержимое всех вложений записи `path` в порядке `entry.attachments`.
+pub fn read_all(path: &str, entry: &Entry, mk: &MasterKey) -> Result<Vec<(String, Vec<u8>)>> {
+    read_all_in(&store_dir()?, path, entry, mk)
+}
+
+/// То же для хранилища `store_dir`.
+pub fn read_all_in(
+    store_dir: &Path,
+    path: &str,
+    entry: &Entry,
+    mk: &MasterKey,
+) -> Result<Vec<(String, Vec<u8>)>> {
+    entry
+        .attachments
+        .iter()
+        .map(|a| Ok((a.name.clone(), read_in(store_dir, path, &a.name, mk)?)))
+        .collect()
+}
+
+/// Заменить все вложения записи `path` на `files` (`pm mv`, `pm cp`, импорт):
+/// прежние файлы удаляются, новые шифруются под путь `path`. Возвращает
+/// список для `Entry::attachments`.
+pub fn replace_all(
+    path: &str,
+    files: &[(String, Vec<u8>)],
+    mk: &MasterKey,
+) -> Result<Vec<AttachmentMeta>> {
+    replace_all_in(&store_dir()?, path, files, mk)
+}
+
+/// То же для хранилища `store_dir`.
+pub fn replace_all_in(
+    store_dir: &Path,
+    path: &str,
+    files: &[(String, Vec<u8>)],
+    mk: &MasterKey,
+) -> Result<Vec<AttachmentMeta>> {
+    crate::lock::ensure_writable()?;
+    let dir = attachments_dir_in(store_dir, path)?;
+    if dir.is_dir() {
+        std::fs::remove_dir_all(&dir)
+            .with_context(|| format!("cannot delete {}", dir.display()))?;
+    }
+    let mut metas = Vec::new();
+    for (name, data) in files {
+        let name = validate_name(name)?;
+        save_blob(&blob_file(&dir, &name), &aad(path, &name), data, mk)?;
+        metas.push(AttachmentMeta {
+            name,
+            size: data.len() as u64,
+        });
+    }
+    Ok(metas)
+}
+
+/// Имена вложений, файлы которых лежат в каталоге `dir`.
+fn names_in(dir: &Path) -> Result<Vec<String>> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut names = Vec::new();
+    for item in std::fs::read_dir(dir)? {
+        let file_name = item?.file_name();
+        if let Some(name) = file_name.to_str().and_then(|n| n.strip_suffix(".enc")) {
+            names.push(name.to_string());
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+/// Имена вложений записи `path`, файлы которых есть в `store_dir`.
+pub fn names_on_disk(store_dir: &Path, path: &str) -> Result<Vec<String>> {
+    names_in(&attachments_dir_in(store_dir, path)?)
+}
+
+/// Перешифровать вложения записи `path` из `src_dir` в `dest_dir` ключом
+/// `new_key` (`pm rekey`, корзина).
+pub fn reencrypt_dir(
+    src_dir: &Path,
+    dest_dir: &Path,
+    path: &str,
+    old_key: &MasterKey,
+    new_key: &MasterKey,
+) -> Result<()> {
+    for name in names_in(src_dir)? {
+        let data = load_blob(&blob_file(src_dir, &name), &aad(path, &name), old_key)?;
+        save_blob(
+            &blob_file(dest_dir, &name),
+            &aad(path, &name),
+            &data,
+            new_key,
+        )?;
+    }
+    Ok(())
+}
+
+/// `pm rekey`: вложения записи `path` — в каталог записей `dest_store`.
+pub fn reencrypt(
+    path: &str,
+    old_key: &MasterKey,
+    new_key: &MasterKey,
+    dest_store: &Path,
+) -> Result<()> {
+    let store_dir = store_dir()?;
+    let src = attachments_dir_in(&store_dir, path)?;
+    let dest = dest_store.join(src.strip_prefix(&store_dir)?);
+    reencrypt_dir(&src, &dest, path, old_key, new_key)
+}
+
+/// Лежит ли файл (путь относительно корня хранилища, через `/`) в каталоге вложений.
+pub fn is_attachment_file(rel: &str) -> bool {
+    rel.split('/')
+        .rev()
+        .skip(1)
+        .any(|part| part.ends_with(ATTACHMENTS_SUFFIX))
+}
+
+/// Вложение в экспорте: имя, размер и содержимое в base64.
+#[derive(Serialize, Deserialize)]
+pub struct ExportedAttachment {
+    pub name: String,
+    pub size: u64,
+    pub data: String,
+}
+
+impl ExportedAttachment {
+    pub fn decode(&self) -> Result<(String, Vec<u8>)> {
+        let data = general_purpose::STANDARD
+            .decode(&self.data)
+            .with_context(|| format!("attachment {} is not valid base64", self.name))?;
+        Ok((self.name.clone(), data))
+    }
+}
+
+/// Вложения записи `path` для экспорта.
+pub fn export(path: &str, entry: &Entry, mk: &MasterKey) -> Result<Vec<ExportedAttachment>> {
+    Ok(read_all(path, entry, mk)?
+        .into_iter()
+        .map(|(name, data)| ExportedAttachment {
+            name,
+            size: data.len() as u64,
+            data: general_purpose::STANDARD.encode(data),
+        })
+        .collect())
+}
+
+/// Запись `path` с проверкой, что она есть.
+fn open_entry(path: &str) -> Result<(String, Config, MasterKey, Entry)> {
+    let path = validate_entry_path(path)?;
+    if !entry_exists(&path)? {
+        return Err(anyhow!("Entry not found: {path}"));
+    }
+    let config = Config::load()?;
+    let mk = get_master_key_with_cache(&config)?;
+    let entry = load_entry(&path, &mk)?;
+    Ok((path, config, mk, entry))
+}
+
+/// Сохранить изменённый список вложений.
+fn save_changed(path: &str, mk: &MasterKey, entry: &mut Entry) -> Result<()> {
+    entry.attachments.sort_by(|a, b| a.name.cmp(&b.name));
+    entry.updated_at = OffsetDateTime::now_utc().format(&Rfc3339)?;
+    save_entry(path, entry, mk)
+}
+
+/// Закоммитить запись вместе с её вложениями и запустить хуки — когда все
+/// файлы уже на месте.
+fn finish_change(path: &str, config: &Config) {
+    auto_commit(config, HookAction::Edit, path);
+    run_hooks(config, HookAction::Edit, path);
+}
+
+/// pm attach add <path> <file> [--name N] [--force]
+pub fn attach_add(path: &str, file: &Path, name: Option<&str>, force: bool) -> Result<()> {
+    let name = match name {
+        Some(name) => name.to_string(),
+        None => file
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .ok_or_else(|| anyhow!("{} has no file name; pass --name", file.display()))?,
+    };
+    let name = validate_name(&name)?;
+    let (path, config, mk, mut entry) = open_entry(path)?;
+    if !force && entry.attachments.iter().any(|a| a.name == name) {
+        return Err(anyhow!(
+            "{path} already has an attachment named {name} (use --force to replace it)"
+        ));
+    }
+
+    let limit = config.settings.attachments.max_bytes;
+    let meta =
+        std::fs::metadata(file).with_context(|| format!("cannot read {}", file.display()))?;
+    if !meta.is_file() {
+        return Err(anyhow!("{} is not a regular file", file.display()));
+    }
+    // Размер проверяем до чтения, и ещё раз после: файл мог вырасти
+    let too_big = |size: u64| {
+        anyhow!(
+            "{} is {}; attachments are limited to {} (pm config set attachments.max_bytes)",
+            file.display(),
+            human_size(size),
+            human_size(limit)
+        )
+    };
+    if meta.len() > limit {
+        return Err(too_big(meta.len()));
+    }
+    let data = std::fs::read(file).with_context(|| format!("cannot read {}", file.display()))?;
+    let size = data.len() as u64;
+    if size > limit {
+        return Err(too_big(size));
+    }
+
+    crate::lock::ensure_writable()?;
+    let dir = attachments_dir_in(&store_dir()?, &path)?;
+    save_blob(&blob_file(&dir, &name), &aad(&path, &name), &data, &mk)?;
+    entry.attachments.retain(|a| a.name != name);
+    entry.attachments.push(AttachmentMeta {
+        name: name.clone(),
+        size,
+    });
+    save_changed(&path, &mk, &mut entry)?;
+    finish_change(&path, &config);
+    crate::output::done(
+        "attach",
+        &path,
+        format!("Attached {name} ({}) to {path}", human_size(size)),
+    );
+    Ok(())
+}
+
+/// pm attach list <path>
+pub fn attach_list(path: &str) -> Result<()> {
+    let (path, _, _, entry) = open_entry(path)?;
+    crate::output::emit(&entry.attachments, || {
+        if entry.attachments.is_empty() {
+            println!("{path} has no attachments.");
+        }
+        let width = entry
+            .attachments
+            .iter()
+            .map(|a| a.name.chars().count())
+            .max()
+            .unwrap_or(0);
+        for a in &entry.attachments {
+            println!("{:<width$}  {}", a.name, human_size(a.size));
+        }
+        Ok(())
+    })
+}
+
+/// pm attach get <path> <name> [--output FILE] [--force]
+pub fn attach_get(path: &str, name: &str, output: Option<&Path>, force: bool) -> Result<()> {
+    let name = validate_name(name)?;
+    // `--output -` — тот же stdout, а не файл с именем «-»
+    let output = output.filter(|out| *out != Path::new("-"));
+    if let Some(out) = output
+        && out.exists()
+        && !force
+    {
+        return Err(anyhow!(
+            "{} already exists (use --force to overwrite)",
+            out.display()
+        ));
+    }
+    let (path, config, mk, entry) = open_entry(path)?;
+    if !entry.attachments.iter().any(|a| a.name == name) {
+        return Err(anyhow!("{path} has no attachment named {name}"));
+    }
+    let data = read_in(&store_dir()?, &path, &name, &mk)?;
+    crate::recent::record_access(&config, &mk, &path, "attach");
+
+    match output {
+        Some(out) => {
+            write_atomic_private(out, &data)?;
+            crate::output::done(
+                "get",
+                &path,
+                format!("Saved {name} from {path} to {}", out.display()),
+            );
+        }
+        None => {
+            let mut stdout = std::io::stdout().lock();
+            stdout.write_all(&data)?;
+            stdout.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// pm attach rm <path> <name>
+pub fn attach_rm(path: &str, name: &str) -> Result<()> {
+    let name = validate_name(name)?;
+    let (path, config, mk, mut entry) = open_entry(path)?;
+    if !entry.attachments.iter().any(|a| a.name == name) {
+        return Err(anyhow!("{path} has no attachment named {name}"));
+    }
+    entry.attachments.retain(|a| a.name != name);
+    save_changed(&path, &mk, &mut entry)?;
+
+    // Файл удаляем после записи: сбой оставит лишний файл, а не пустую ссылку
+    let dir = attachments_dir_in(&store_dir()?, &path)?;
+    let file = blob_file(&dir, &name);
+    if file.exists() {
+        std::fs::remove_file(&file).with_context(|| format!("cannot delete {}", file.display()))?;
+    }
+    let _ = std::fs::remove_dir(&dir);
+    finish_change(&path, &config);
+    crate::output::done(
+        "detach",
+        &path,
+        format!("Removed attachment {name} from {path}"),
+    );
+    Ok(())
+}
@@ -0,0 +1,447 @@
+//! `pm audit`: проверка качества паролей без сети.
+//!
+//! Расшифровывает все записи и ищет короткие пароли, пароли из одного класса
+//! символов, один пароль на нескольких записях, давно не менявшиеся записи
+//! и логины с URL, но без имени пользователя. Каждому паролю ставится
+//! собственная грубая оценка стойкости 0–4 по числу попыток перебора; с
+//! zxcvbn она не совместима, хотя пороги похожи. Сами пароли никогда не
+//! печатаются — только пути и находки.
+
+use crate::config::{AuditSettings, Config};
+use crate::entry::Entry;
+use crate::session::get_master_key_with_cache;
+use crate::store::{decrypt_all, list_entries};
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+/// Самые частые пароли из утечек: встретив такой корень, перебор
+/// начинается с него.
+const COMMON: &[&str] = &[
+    "password",
+    "passw0rd",
+    "qwerty",
+    "qwertyuiop",
+    "asdfgh",
+    "zxcvbn",
+    "letmein",
+    "welcome",
+    "iloveyou",
+    "monkey",
+    "dragon",
+    "football",
+    "baseball",
+    "master",
+    "sunshine",
+    "princess",
+    "shadow",
+    "superman",
+    "trustno1",
+    "login",
+    "admin",
+    "secret",
+    "abc123",
+    "123456",
+    "111111",
+    "000000",
+    "654321",
+];
+
+/// Насколько серьёзна находка; в таблице серьёзные идут первыми.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+}
+
+impl Severity {
+    fn label(self) -> &'static str {
+        match self {
+            Severity::Low => "LOW",
+            Severity::Medium => "MEDIUM",
+            Severity::High => "HIGH",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Finding {
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// Итог по одной записи.
+#[derive(Debug, Serialize)]
+pub struct EntryReport {
+    pub path: String,
+    pub score: u8,
+    pub severity: Option<Severity>,
+    pub findings: Vec<Finding>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditReport {
+    pub checked: usize,
+    /// Все проверенные записи, сначала самые проблемные.
+    pub entries: Vec<EntryReport>,
+    /// Группы путей с одинаковым паролем.
+    pub reused: Vec<Vec<String>>,
+}
+
+/// Параметры проверки: настройки `audit.*`, переопределённые флагами.
+pub struct AuditOptions {
+    pub min_length: usize,
+    pub max_age_days: u64,
+}
+
+impl From<&AuditSettings> for AuditOptions {
+    fn from(s: &AuditSettings) -> Self {
+        AuditOptions {
+            min_length: s.min_length,
+            max_age_days: s.max_age_days,
+        }
+    }
+}
+
+/// Стойкость пароля 0–4 по log10 числа попыток: меньше 10³ — 0,
+/// 10⁶ — 1, 10⁸ — 2, 10¹⁰ — 3, больше — 4.
+pub fn strength_score(password: &str) -> u8 {
+    match guesses_log10(password) {
+        g if g < 3.0 => 0,
+        g if g < 6.0 => 1,
+        g if g < 8.0 => 2,
+        g if g < 10.0 => 3,
+        _ => 4,
+    }
+}
+
+/// Размер алфавита по встреченным классам символов.
+fn pool_size(chars: &[char]) -> f64 {
+    let mut pool = 0.0;
+    if chars.iter().any(|c| c.is_ascii_lowercase()) {
+        pool += 26.0;
+    }
+    if chars.iter().any(|c| c.is_ascii_uppercase()) {
+        pool += 26.0;
+    }
+    if chars.iter().any(|c| c.is_ascii_digit()) {
+        pool += 10.0;
+    }
+    if chars.iter().any(|c| c.is_ascii_punctuation() || *c == ' ') {
+        pool += 33.0;
+    }
+    if chars.iter().any(|c| !c.is_ascii()) {
+        pool += 100.0;
+    }
+    pool
+}
+
+/// Длина с поправкой на повторы (`aaaa`) и последовательности (`abcd`, `4321`):
+/// такой символ почти ничего не добавляет к перебору.
+fn effective_length(chars: &[char]) -> f64 {
+    let mut len = 0.0;
+    for (i, &c) in chars.iter().enumerate() {
+        let predictable = i > 0 && {
+            let diff = c as i64 - chars[i - 1] as i64;
+            diff.abs() <= 1
+        };
+        len += if predictable { 0.1 } else { 1.0 };
+    }
+    len
+}
+
+fn brute_force_log10(chars: &[char]) -> f64 {
+    if chars.is_empty() {
+        return 0.0;
+    }
+    effective_length(chars) * pool_size(chars).log10()
+}
+
+/// Обратная замена «leet»-символов: `p@ssw0rd` → `password`.
+fn unleet(c: char) -> char {
+    match c {
+        '0' => 'o',
+        '1' | '!' => 'i',
+        '3' => 'e',
+        '4' | '@' => 'a',
+        '5' | '$' => 's',
+        '7' => 't',
+        c => c.to_ascii_lowercase(),
+    }
+}
+
+/// log10 числа попыток: перебор по алфавиту или, если внутри пароля есть
+/// словарное слово, номер слова в словаре плюс перебор остатка.
+fn guesses_log10(password: &str) -> f64 {
+    let chars: Vec<char> = password.chars().collect();
+    let mut best = brute_force_log10(&chars);
+
+    let plain: String = chars.iter().map(|&c| unleet(c)).collect();
+    let words = crate::crypto::WORDLIST.lines().map(str::trim);
+    for (rank, word) in COMMON.iter().copied().chain(words).enumerate() {
+        if word.len() < 4 {
+            continue;
+        }
+        let Some(start) = plain.find(word) else {
+            continue;
+        };
+        let start = plain[..start].chars().count();
+        let end = start + word.chars().count();
+        let rest: Vec<char> = chars[..start]
+            .iter()
+            .chain(&chars[end..])
+            .copied()
+            .collect();
+        // Частые пароли идут первыми; заглавные буквы и замены — ещё пара вариантов
+        let mut guesses = ((rank + 1) as f64).log10() + brute_force_log10(&rest);
+        if chars[start..end].iter().any(|c| c.is_ascii_uppercase()) {
+            guesses += 0.3;
+        }
+        if chars[start..end].iter().any(|c| !c.is_ascii_lowercase()) {
+            guesses += 0.3;
+        }
+        best = best.min(guesses);
+    }
+    best
+}
+
+/// Сколько классов символов (строчные, заглавные, цифры, прочее) в пароле.
+fn char_classes(password: &str) -> usize {
+    let has = |f: fn(&char) -> bool| password.chars().any(|c| f(&c));
+    [
+        has(char::is_ascii_lowercase),
+        has(char::is_ascii_uppercase),
+        has(char::is_ascii_digit),
+        has(|c| !c.is_ascii_alphanumeric()),
+    ]
+    .iter()
+    .filter(|&&b| b)
+    .count()
+}
+
+/// Находки по одной записи, кроме повторов пароля: их видно только по всем сразу.
+fn check_entry(path: &str, entry: &Entry, opts: &AuditOptions, now: OffsetDateTime) -> EntryReport {
+    let score = strength_score(&entry.password);
+    let mut findings = Vec::new();
+    let mut add = |severity, message: String| findings.push(Finding { severity, message });
+
+    if score <= 2 {
+        let severity = if score <= 1 {
+            Severity::High
+        } else {
+            Severity::Medium
+        };
+        add(severity, format!("weak password (strength {score}/4)"));
+    }
+    let len = entry.password.chars().count();
+    if len < opts.min_length {
+        add(
+            Severity::Medium,
+            format!("short password ({len} < {} characters)", opts.min_length),
+        );
+    }
+    if char_classes(&entry.password) == 1 {
+        add(Severity::Medium, "only one character class".to_string());
+    }
+    if opts.max_age_days > 0
+        && let Ok(updated) = OffsetDateTime::parse(&entry.updated_at, &Rfc3339)
+    {
+        let days = (now - updated).whole_days();
+        if days > opts.max_age_days as i64 {
+            add(Severity::Low, format!("not updated in {days} days"));
+        }
+    }
+    let has_url = entry.url.as_deref().is_some_and(|u| !u.is_empty());
+    let has_user = entry.username.as_deref().is_some_and(|u| !u.is_empty());
+    if entry.kind.is_login() && has_url && !has_user {
+        add(Severity::Low, "URL set but username is empty".to_string());
+    }
+    EntryReport {
+        path: path.to_string(),
+        score,
+        severity: None,
+        findings,
+    }
+}
+
+/// Проверить все записи хранилища.
+pub fn run_audit(opts: &AuditOptions) -> Result<AuditReport> {
+    let config = Config::load()?;
+    let mk = get_master_key_with_cache(&config)?;
+    let now = OffsetDateTime::now_utc();
+
+    let mut entries = Vec::new();
+    let mut by_password: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (path, entry) in decrypt_all(&mk, list_entries()?) {
+        let entry = match entry {
+            Ok(e) => e,
+            Err(e) => {
+                eprintln!("Warning: cannot decrypt {path}: {e}");
+                continue;
+            }
+        };
+        if entry.password.is_empty() {
+            continue;
+        }
+        entries.push(check_entry(&path, &entry, opts, now));
+        by_password.entry(entry.password).or_default().push(path);
+    }
+
+    let reused: Vec<Vec<String>> = by_password.into_values().filter(|p| p.len() > 1).collect();
+    for group in &reused {
+        for report in entries.iter_mut().filter(|r| group.contains(&r.path)) {
+            let others: Vec<&str> = group
+                .iter()
+                .filter(|p| **p != report.path)
+                .map(String::as_str)
+                .collect();
+            report.findings.push(Finding {
+                severity: Severity::High,
+                message: format!("password reused in {}", others.join(", ")),
+            });
+        }
+    }
+
+    let checked = entries.len();
+    for report in &mut entries {
+        report
+            .findings
+            .sort_by_key(|f| std::cmp::Reverse(f.severity));
+        report.severity = report.findings.first().map(|f| f.severity);
+    }
+    entries.sort_by(|a, b| (b.severity, a.score, &a.path).cmp(&(a.severity, b.score, &b.path)));
+    Ok(AuditReport {
+        checked,
+        entries,
+        reused,
+    })
+}
+
+fn print_report(report: &AuditReport) {
+    let flagged: Vec<&EntryReport> = report
+        .entries
+        .iter()
+        .filter(|r| r.severity.is_some())
+        .collect();
+    if flagged.is_empty() {
+        println!("Checked {} passwords: no problems found.", report.checked);
+        return;
+    }
+    let width = flagged
+        .iter()
+        .map(|r| r.path.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max("PATH".len());
+    println!(
+        "{:<8} {:<8} {:<width$}  FINDINGS",
+        "SEVERITY", "STRENGTH", "PATH"
+    );
+    for r in &flagged {
+        let severity = r.severity.map_or("", Severity::label);
+        let findings: Vec<&str> = r.findings.iter().map(|f| f.message.as_str()).collect();
+        println!(
+            "{severity:<8} {:<8} {:<width$}  {}",
+            format!("{}/4", r.score),
+            r.path,
+            findings.join("; ")
+        );
+    }
+    if !report.reused.is_empty() {
+        println!();
+        println!("Reused passwords:");
+        for group in &report.reused {
+            println!("  {}", group.join(", "));
+        }
+    }
+    println!();
+    println!(
+        "Checked {} passwords, {} with findings.",
+        report.checked,
+        flagged.len()
+    );
+}
+
+/// pm audit [--min-length N] [--max-age-days N] [--fail-under SCORE]
+pub fn audit(
+    min_length: Option<usize>,
+    max_age_days: Option<u64>,
+    fail_under: Option<u8>,
+) -> Result<()> {
+    let config = Config::load()?;
+    let mut opts = AuditOptions::from(&config.settings.audit);
+    if let Some(n) = min_length {
+        opts.min_length = n;
+    }
+    if let Some(n) = max_age_days {
+        opts.max_age_days = n;
+    }
+
+    let report = run_audit(&opts)?;
+    crate::output::emit(&report, || {
+        print_report(&report);
+        Ok(())
+    })?;
+
+    if let Some(threshold) = fail_under {
+        let below = report
+            .entries
+            .iter()
+            .filter(|r| r.score < threshold)
+            .count();
+        if below > 0 {
+            return Err(anyhow!("{below} passwords score below {threshold}"));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scores_are_pinned() {
+        // Оценка своя, не zxcvbn: при смене эвристик таблицу правят осознанно
+        let table = [
+            ("", 0),
+            ("password", 0),
+            ("p@ssw0rd", 0),
+            ("aaaaaaaa", 0),
+            ("abcd1234", 1),
+            ("P@ssw0rd2024", 1),
+            ("Xk9#mQ2$vL7!pR4w", 4),
+            ("correct-horse-battery-staple", 4),
+        ];
+        for (password, score) in table {
+            assert_eq!(strength_score(password), score, "{password:?}");
+        }
+    }
+
+    #[test]
+    fn guesses_follow_the_heuristics() {
+        let g = guesses_log10;
+        // Частый пароль — первая попытка; leet-замены добавляют лишь пару вариантов
+        assert_eq!(g("password"), 0.0);
+        assert!((g("p@ssw0rd") - 0.3).abs() < 1e-9);
+        assert!((g("Password") - 0.6).abs() < 1e-9);
+        // Повторы и последовательности почти ничего не стоят
+        assert!(g("aaaaaaaa") < g("abcdefgh") + 1.0);
+        assert!(g("aaaaaaaa") < 3.0);
+        assert!(g("abcd1234") < g("ab7dx2k4"));
+        // 16 случайных символов из четырёх классов: 16·log10(95)
+        assert!((g("Xk9#mQ2$vL7!pR4w") - 16.0 * 95f64.log10()).abs() < 1e-9);
+        assert!(g("correct-horse-battery-staple") > 10.0);
+    }
+
+    #[test]
+    fn char_classes_counts_each_class_once() {
+        assert_eq!(char_classes("aaaa"), 1);
+        assert_eq!(char_classes("abcd1234"), 2);
+        assert_eq!(char_classes("Xk9#"), 4);
+    }
+}
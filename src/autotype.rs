@@ -0,0 +1,256 @@
+//! Автонабор: `pm menu` печатает поля записи в окно в фокусе, как rofi-pass.
+//!
+//! - `wtype` — Wayland (wlroots);
+//! - `ydotool` — через uinput, работает везде, где запущен `ydotoold`;
+//! - `xdotool` — X11.
+//!
+//! Порядок перебора: `PM_AUTOTYPE` (список имён через запятую), иначе на
+//! Wayland — wtype и ydotool, на X11 — xdotool. Текст передаётся утилите
+//! через stdin, а не аргументом: аргументы видны всем в списке процессов.
+
+use crate::clipboard::{has_env, in_path, pipe_to};
+use crate::entry_otp::take_code;
+use crate::password_store::PasswordStore;
+use crate::recent::record_access;
+use crate::show::Field;
+use anyhow::{Result, anyhow};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// Клавиша в последовательности: `:tab`, `:enter`, `:space`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Tab,
+    Enter,
+    Space,
+}
+
+/// Шаг последовательности `--sequence`: поле записи или клавиша.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step {
+    /// Имя поля как в `pm clip --field`: `username`, `password`, `otp`, ...
+    Field(String),
+    Key(Key),
+}
+
+/// Последовательность по умолчанию: логин, Tab, пароль.
+pub const DEFAULT_SEQUENCE: &str = "user :tab pass";
+
+/// Разобрать `user :tab pass :enter`. `user` и `pass` — сокращения
+/// `username` и `password`, остальные слова — имена полей.
+pub fn parse_sequence(sequence: &str) -> Result<Vec<Step>> {
+    let steps: Vec<Step> = sequence
+        .split_whitespace()
+        .map(|word| {
+            Ok(match word {
+                ":tab" => Step::Key(Key::Tab),
+                ":enter" => Step::Key(Key::Enter),
+                ":space" => Step::Key(Key::Space),
+                key if key.starts_with(':') => {
+                    return Err(anyhow!(
+                        "unknown key {key:?} in --sequence (expected :tab, :enter or :space)"
+                    ));
+                }
+                "user" => Step::Field("username".to_string()),
+                "pass" => Step::Field("password".to_string()),
+                field => Step::Field(field.to_string()),
+            })
+        })
+        .collect::<Result<_>>()?;
+    if steps.is_empty() {
+        return Err(anyhow!("--sequence is empty"));
+    }
+    Ok(steps)
+}
+
+pub trait TypeBackend {
+    /// Имя для `PM_AUTOTYPE` и сообщений.
+    fn name(&self) -> &'static str;
+    /// Есть ли утилита и нужный дисплей.
+    fn available(&self) -> bool;
+    fn type_text(&self, text: &str) -> Result<()>;
+    fn key(&self, key: Key) -> Result<()>;
+}
+
+const NAMES: &[&str] = &["wtype", "ydotool", "xdotool"];
+
+fn backend(name: &str) -> Option<Box<dyn TypeBackend>> {
+    Some(match name {
+        "wtype" => Box::new(Wtype),
+        "ydotool" => Box::new(Ydotool),
+        "xdotool" => Box::new(Xdotool),
+        _ => return None,
+    })
+}
+
+/// Бэкенды в порядке перебора.
+fn candidates() -> Result<Vec<Box<dyn TypeBackend>>> {
+    if let Some(list) = std::env::var("PM_AUTOTYPE").ok().filter(|v| !v.is_empty()) {
+        return list
+            .split(',')
+            .map(str::trim)
+            .filter(|n| !n.is_empty())
+            .map(|n| {
+                backend(n).ok_or_else(|| {
+                    anyhow!(
+                        "PM_AUTOTYPE: unknown autotype backend {n:?} (expected {})",
+                        NAMES.join(", ")
+                    )
+                })
+            })
+            .collect();
+    }
+    let names: &[&str] = if has_env("WAYLAND_DISPLAY") {
+        &["wtype", "ydotool"]
+    } else {
+        &["xdotool", "ydotool"]
+    };
+    Ok(names.iter().filter_map(|n| backend(n)).collect())
+}
+
+/// Первый доступный бэкенд.
+pub fn select() -> Result<Box<dyn TypeBackend>> {
+    candidates()?
+        .into_iter()
+        .find(|b| b.available())
+        .ok_or_else(|| {
+            anyhow!(
+                "No autotype tool available: install wtype or ydotool (Wayland) or xdotool (X11),\n\
+             or copy instead with `pm menu <path> --clip`"
+            )
+        })
+}
+
+/// Набрать `steps`; значения полей уже подставлены через `value`. Поля
+/// берутся все до начала набора, чтобы ошибка не оборвала его на середине.
+/// Перед набором и между шагами — пауза `delay`. Возвращает имя бэкенда.
+pub fn type_sequence(
+    steps: &[Step],
+    mut value: impl FnMut(&str) -> Result<String>,
+    delay: Duration,
+) -> Result<&'static str> {
+    let mut texts = Vec::new();
+    for step in steps {
+        if let Step::Field(name) = step {
+            texts.push(value(name)?);
+        }
+    }
+    let backend = select()?;
+    crate::log::info(format_args!("autotype: typing with {}", backend.name()));
+    let mut texts = texts.iter();
+    for step in steps {
+        std::thread::sleep(delay);
+        match step {
+            Step::Field(_) => backend.type_text(texts.next().map_or("", String::as_str))?,
+            Step::Key(key) => backend.key(*key)?,
+        }
+    }
+    Ok(backend.name())
+}
+
+/// pm menu PATH: набрать поля записи по последовательности `sequence`.
+/// Код OTP берётся через `take_code` (HOTP сдвигает счётчик). Возвращает
+/// имя бэкенда.
+pub fn type_entry(path: &str, sequence: &str, delay: Duration) -> Result<&'static str> {
+    let steps = parse_sequence(sequence)?;
+
+    let store = PasswordStore::open_current()?;
+    let mut entry = store.show(path)?;
+    let backend = type_sequence(
+        &steps,
+        |name| match name.parse::<Field>() {
+            Ok(Field::Otp) if entry.otp.is_some() => take_code(&store, path, &mut entry),
+            Ok(field) => field.value(&entry, path),
+        },
+        delay,
+    )?;
+    record_access(store.config(), store.master_key()?, path, "type");
+    Ok(backend)
+}
+
+fn run(cmd: &str, args: &[&str]) -> Result<()> {
+    let status = Command::new(cmd)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(|e| anyhow!("failed to run {cmd}: {e}"))?;
+    if !status.success() {
+        return Err(anyhow!("{cmd} exited with status {status}"));
+    }
+    Ok(())
+}
+
+struct Wtype;
+
+impl TypeBackend for Wtype {
+    fn name(&self) -> &'static str {
+        "wtype"
+    }
+    fn available(&self) -> bool {
+        has_env("WAYLAND_DISPLAY") && in_path("wtype")
+    }
+    fn type_text(&self, text: &str) -> Result<()> {
+        pipe_to("wtype", &["-"], text)
+    }
+    fn key(&self, key: Key) -> Result<()> {
+        match key {
+            Key::Tab => run("wtype", &["-k", "Tab"]),
+            Key::Enter => run("wtype", &["-k", "Return"]),
+            Key::Space => run("wtype", &["-k", "space"]),
+        }
+    }
+}
+
+struct Ydotool;
+
+impl TypeBackend for Ydotool {
+    fn name(&self) -> &'static str {
+        "ydotool"
+    }
+    fn available(&self) -> bool {
+        in_path("ydotool")
+    }
+    fn type_text(&self, text: &str) -> Result<()> {
+        pipe_to("ydotool", &["type", "--file", "-"], text)
+    }
+    fn key(&self, key: Key) -> Result<()> {
+        // Коды клавиш Linux (input-event-codes.h): нажать и отпустить
+        let code = match key {
+            Key::Tab => 15,
+            Key::Enter => 28,
+            Key::Space => 57,
+        };
+        run(
+            "ydotool",
+            &["key", &format!("{code}:1"), &format!("{code}:0")],
+        )
+    }
+}
+
+struct Xdotool;
+
+impl TypeBackend for Xdotool {
+    fn name(&self) -> &'static str {
+        "xdotool"
+    }
+    fn available(&self) -> bool {
+        has_env("DISPLAY") && in_path("xdotool")
+    }
+    fn type_text(&self, text: &str) -> Result<()> {
+        pipe_to(
+            "xdotool",
+            &["type", "--clearmodifiers", "--file", "-"],
+            text,
+        )
+    }
+    fn key(&self, key: Key) -> Result<()> {
+        let name = match key {
+            Key::Tab => "Tab",
+            Key::Enter => "Return",
+            Key::Space => "space",
+        };
+        run("xdotool", &["key", "--clearmodifiers", name])
+    }
+}
@@ -1,16 +1,23 @@
-use crate::store::store_root;
-use anyhow::{Result, anyhow};
+use crate::config::{BackupSettings, Config};
+use crate::crypto::unlock_master_key;
+use crate::output;
+use crate::progress::{Progress, human_size};
+use crate::prompt::prompt_password_hidden;
+use crate::session::clear_session;
+use crate::store::{create_private_dir_all, list_entries, store_root, write_atomic_private};
+use anyhow::{Context, Result, anyhow};
 use std::fs::File;
 use std::io::{Read, Write};
-use std::path::Path;
-use time::OffsetDateTime;
+use std::path::{Component, Path, PathBuf};
+use time::{OffsetDateTime, PrimitiveDateTime, format_description};
 
 /// Создать бэкап:
 ///   pm backup create
 ///   pm backup create my_backup
 ///   pm backup create my_backup.tar.gz
 ///   pm backup create my_backup.zip
-pub fn backup_create(optional_path: Option<String>) -> Result<()> {
+///   pm backup create --encrypt
+pub fn backup_create(optional_path: Option<String>, encrypt: bool) -> Result<()> {
     let root = store_root()?;
     if !root.exists() {
         return Err(anyhow!(
@@ -22,6 +29,25 @@ pub fn backup_create(optional_path: Option<String>) -> Result<()> {
         .format(&time::format_description::well_known::Rfc3339)?
         .replace(':', "-");
 
+    if encrypt {
+        // tar.gz собирается в памяти и на диск попадает только зашифрованным
+        let path = match optional_path.as_deref().map(str::trim) {
+            None | Some("") => format!("backup_{timestamp}.pmb"),
+            Some(p) if p.ends_with(".pmb") => p.to_string(),
+            Some(p) => format!("{p}.pmb"),
+        };
+        let config = Config::load()?;
+        let passphrase = backup_passphrase(true)?;
+        let mut progress = Progress::new("Backing up", Some(archived_files(&root)?.len()));
+        let archive = write_tar_gz(Vec::new(), &root, &mut progress)?;
+        let sealed = crate::pmx::seal(SEALED_MAGIC, &passphrase, &config.kdf, &archive)?;
+        write_atomic_private(Path::new(&path), &sealed)?;
+        let stats = progress.stats(sealed.len() as u64);
+        progress.finish(format!("Backed up {stats}"));
+        println!("Encrypted backup created at {path}");
+        return Ok(());
+    }
+
     // Определяем имя файла
     let path = match optional_path {
         None => format!("backup_{}.zip", timestamp),
@@ -43,35 +69,147 @@ pub fn backup_create(optional_path: Option<String>) -> Result<()> {
     };
 
     // Выбор формата по расширению
+    let mut progress = Progress::new("Backing up", Some(archived_files(&root)?.len()));
     if path.ends_with(".zip") {
-        backup_zip(&path, &root)?;
+        backup_zip(&path, &root, &mut progress)?;
     } else {
-        backup_tar_gz(&path, &root)?;
+        backup_tar_gz(Path::new(&path), &root, &mut progress)?;
     }
+    let stats = progress.stats(std::fs::metadata(&path)?.len());
+    progress.finish(format!("Backed up {stats}"));
 
     println!("Backup created at {}", path);
     Ok(())
 }
 
-fn backup_tar_gz(path: &str, root: &Path) -> Result<()> {
+/// Контрольные суммы всех файлов архива, в формате `sha256sum`.
+const MANIFEST: &str = "MANIFEST.sha256";
+
+/// Файлы корня, которые в бэкап не попадают: кэш сессии (в нём мастер-ключ,
+/// пусть и зашифрованный) и блокировки.
+const NOT_ARCHIVED: &[&str] = &["session.json", "session.lock", ".lock"];
+
+/// Магия бэкапа, зашифрованного парольной фразой (`.pmb`); контейнер тот же,
+/// что у `.pmx` (см. `pmx.rs`), внутри — tar.gz.
+const SEALED_MAGIC: &[u8; 4] = b"PMB\0";
+
+fn archived(rel: &str) -> bool {
+    rel != MANIFEST && !NOT_ARCHIVED.contains(&rel)
+}
+
+/// Парольная фраза `.pmb`: из `PM_BACKUP_PASSPHRASE` (для скриптов) или запросом.
+fn backup_passphrase(confirm: bool) -> Result<String> {
+    if let Some(passphrase) = std::env::var("PM_BACKUP_PASSPHRASE")
+        .ok()
+        .filter(|p| !p.is_empty())
+    {
+        return Ok(passphrase);
+    }
+    let passphrase = prompt_password_hidden("Backup passphrase: ")?;
+    if confirm {
+        if passphrase.is_empty() {
+            return Err(anyhow!("backup passphrase cannot be empty"));
+        }
+        if prompt_password_hidden("Confirm backup passphrase: ")? != passphrase {
+            return Err(anyhow!("Passphrases do not match"));
+        }
+    }
+    Ok(passphrase)
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+
+    Sha256::digest(data)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Путь внутри архива: компоненты через `/` на любой системе.
+fn rel_name(rel: &Path) -> String {
+    let parts: Vec<_> = rel
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect();
+    parts.join("/")
+}
+
+/// Файлы каталога `dir` (пути относительно него, через `/`) по порядку.
+fn files_under(dir: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let mut files = Vec::new();
+    for entry in walkdir::WalkDir::new(dir).sort_by_file_name() {
+        let entry = entry?;
+        if entry.file_type().is_file() {
+            let rel = rel_name(entry.path().strip_prefix(dir)?);
+            files.push((rel, entry.path().to_path_buf()));
+        }
+    }
+    Ok(files)
+}
+
+/// Файлы хранилища `root`, которые попадают в бэкап.
+fn archived_files(root: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let mut files = files_under(root)?;
+    files.retain(|(rel, _)| archived(rel));
+    Ok(files)
+}
+
+/// `MANIFEST.sha256` для хранилища `root`: `<sha256>  <путь>` на строку.
+fn manifest(root: &Path) -> Result<String> {
+    let mut out = String::new();
+    for (rel, file) in archived_files(root)? {
+        out.push_str(&format!("{}  {rel}\n", sha256_hex(&std::fs::read(file)?)));
+    }
+    Ok(out)
+}
+
+fn backup_tar_gz(path: &Path, root: &Path, progress: &mut Progress) -> Result<()> {
+    write_tar_gz(File::create(path)?, root, progress)?;
+    Ok(())
+}
+
+fn write_tar_gz<W: Write>(out: W, root: &Path, progress: &mut Progress) -> Result<W> {
     use flate2::Compression;
     use flate2::write::GzEncoder;
     use tar::Builder;
+    use walkdir::WalkDir;
 
-    let file = File::create(path)?;
-    let encoder = GzEncoder::new(file, Compression::default());
+    let encoder = GzEncoder::new(out, Compression::default());
     let mut builder = Builder::new(encoder);
 
     // Пакуем содержимое хранилища под префиксом "pm-store"
-    builder.append_dir_all("pm-store", root)?;
+    for entry in WalkDir::new(root).sort_by_file_name() {
+        let entry = entry?;
+        let rel = rel_name(entry.path().strip_prefix(root)?);
+        let name = if rel.is_empty() {
+            "pm-store".to_string()
+        } else {
+            format!("pm-store/{rel}")
+        };
+        if entry.file_type().is_dir() {
+            builder.append_dir(&name, entry.path())?;
+        } else if entry.file_type().is_file() && archived(&rel) {
+            builder.append_path_with_name(entry.path(), &name)?;
+            progress.tick(&rel, entry.metadata()?.len());
+        }
+    }
+    let manifest = manifest(root)?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(manifest.len() as u64);
+    header.set_mode(0o600);
+    header.set_mtime(OffsetDateTime::now_utc().unix_timestamp().max(0) as u64);
+    header.set_cksum();
+    builder.append_data(
+        &mut header,
+        format!("pm-store/{MANIFEST}"),
+        manifest.as_bytes(),
+    )?;
     let encoder = builder.into_inner()?;
-    encoder.finish()?;
-
-    Ok(())
+    Ok(encoder.finish()?)
 }
 
-fn backup_zip(path: &str, root: &Path) -> Result<()> {
-    use walkdir::WalkDir;
+fn backup_zip(path: &str, root: &Path, progress: &mut Progress) -> Result<()> {
     use zip::CompressionMethod;
     use zip::ZipWriter;
     use zip::write::FileOptions;
@@ -80,22 +218,782 @@ fn backup_zip(path: &str, root: &Path) -> Result<()> {
     let mut zip = ZipWriter::new(file);
     let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
 
-    for entry in WalkDir::new(root) {
-        let entry = entry?;
-        let entry_path = entry.path();
-        if entry.file_type().is_file() {
-            let rel_path = entry_path
-                .strip_prefix(root)?
-                .to_string_lossy()
-                .into_owned();
-            zip.start_file(rel_path, options)?;
-            let mut f = File::open(entry_path)?;
-            let mut buf = Vec::new();
-            f.read_to_end(&mut buf)?;
-            zip.write_all(&buf)?;
-        }
+    for (rel_path, file) in archived_files(root)? {
+        zip.start_file(rel_path.as_str(), options)?;
+        // Потоком: большие вложения не должны целиком ложиться в память
+        let size = std::io::copy(&mut File::open(&file)?, &mut zip)?;
+        progress.tick(&rel_path, size);
     }
 
+    zip.start_file(MANIFEST, options)?;
+    zip.write_all(manifest(root)?.as_bytes())?;
     zip.finish()?;
     Ok(())
 }
+
+/// Распаковать архив бэкапа (zip, tar.gz, tar.zst) в `dest`.
+/// Пути с `..`, абсолютные пути и ссылки отвергаются (zip-slip / tar-slip).
+pub fn extract_archive(archive: &Path, dest: &Path, progress: &mut Progress) -> Result<()> {
+    let name = archive.to_string_lossy();
+    create_private_dir_all(dest)?;
+
+    if name.ends_with(".zip") {
+        extract_zip(archive, dest, progress)
+    } else if name.ends_with(".tar.zst") || name.ends_with(".tzst") {
+        let file = File::open(archive)?;
+        extract_tar(zstd::stream::read::Decoder::new(file)?, dest, progress)
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") || name.ends_with(".gz") {
+        let file = File::open(archive)?;
+        extract_tar(flate2::read::GzDecoder::new(file), dest, progress)
+    } else {
+        Err(anyhow!(
+            "Unsupported backup format: {name} (expected .zip, .tar.gz or .tar.zst)"
+        ))
+    }
+}
+
+/// Распаковать бэкап любого вида: `.pmb` узнаётся по магии, а не по имени,
+/// и сначала расшифровывается парольной фразой.
+fn extract_backup(archive: &Path, dest: &Path, progress: &mut Progress) -> Result<()> {
+    let mut magic = [0u8; 4];
+    let sealed = File::open(archive)?.read_exact(&mut magic).is_ok() && &magic == SEALED_MAGIC;
+    if !sealed {
+        return extract_archive(archive, dest, progress);
+    }
+    let data = std::fs::read(archive)?;
+    let tar_gz = crate::pmx::open(SEALED_MAGIC, "backup", &data, &backup_passphrase(false)?)?;
+    create_private_dir_all(dest)?;
+    extract_tar(
+        flate2::read::GzDecoder::new(tar_gz.as_slice()),
+        dest,
+        progress,
+    )
+}
+
+/// Файл из архива: сразу 0600, как у `write_atomic_private`, а не по umask —
+/// до переноса на место секреты лежат в каталоге рядом с хранилищем.
+fn create_private_file(path: &Path) -> std::io::Result<File> {
+    let mut opts = std::fs::OpenOptions::new();
+    opts.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        opts.mode(0o600);
+    }
+    opts.open(path)
+}
+
+/// Относительный путь без `..`, корня и префиксов диска.
+fn safe_relative_path(p: &Path) -> Result<PathBuf> {
+    let mut out = PathBuf::new();
+    for c in p.components() {
+        match c {
+            Component::Normal(part) => out.push(part),
+            Component::CurDir => {}
+            _ => return Err(anyhow!("unsafe path in archive: {}", p.display())),
+        }
+    }
+    Ok(out)
+}
+
+fn extract_tar<R: Read>(reader: R, dest: &Path, progress: &mut Progress) -> Result<()> {
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let rel = safe_relative_path(&entry.path()?)?;
+        let target = dest.join(&rel);
+        match entry.header().entry_type() {
+            tar::EntryType::Directory => create_private_dir_all(&target)?,
+            tar::EntryType::Regular => {
+                if let Some(parent) = target.parent() {
+                    create_private_dir_all(parent)?;
+                }
+                let mut out = create_private_file(&target)?;
+                let size = std::io::copy(&mut entry, &mut out)?;
+                if !rel.ends_with(MANIFEST) {
+                    progress.tick(&rel.to_string_lossy(), size);
+                }
+            }
+            other => {
+                return Err(anyhow!(
+                    "unsupported entry type {other:?} in archive: {}",
+                    rel.display()
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn extract_zip(archive: &Path, dest: &Path, progress: &mut Progress) -> Result<()> {
+    let mut zip = zip::ZipArchive::new(File::open(archive)?)?;
+    // Счётчик — файлы хранилища, как при создании: без каталогов и описи
+    progress.set_total(
+        zip.file_names()
+            .filter(|n| !n.ends_with('/') && !Path::new(n).ends_with(MANIFEST))
+            .count(),
+    );
+    for i in 0..zip.len() {
+        let mut file = zip.by_index(i)?;
+        let rel = safe_relative_path(Path::new(file.name()))?;
+        let target = dest.join(&rel);
+        if file.is_dir() {
+            create_private_dir_all(&target)?;
+            continue;
+        }
+        if let Some(parent) = target.parent() {
+            create_private_dir_all(parent)?;
+        }
+        let mut out = create_private_file(&target)?;
+        let size = std::io::copy(&mut file, &mut out)?;
+        if !rel.ends_with(MANIFEST) {
+            progress.tick(&rel.to_string_lossy(), size);
+        }
+    }
+    Ok(())
+}
+
+/// Распаковать бэкап в `staging` и вернуть каталог хранилища в нём.
+/// `MANIFEST.sha256` нужен только для проверки и в хранилище не попадает.
+fn unpack_store(archive: &Path, staging: &Path, progress: &mut Progress) -> Result<PathBuf> {
+    extract_backup(archive, staging, progress)?;
+    let dir =
+        find_store_dir(staging).ok_or_else(|| anyhow!("Backup does not contain a config.json"))?;
+    let manifest = dir.join(MANIFEST);
+    if manifest.exists() {
+        std::fs::remove_file(manifest)?;
+    }
+    Ok(dir)
+}
+
+/// Каталог хранилища внутри распакованного архива:
+/// tar.gz пакуется с префиксом `pm-store/`, zip — без него.
+fn find_store_dir(extracted: &Path) -> Option<PathBuf> {
+    [extracted.join("pm-store"), extracted.to_path_buf()]
+        .into_iter()
+        .find(|d| d.join("config.json").is_file())
+}
+
+/// pm init --from-backup ARCHIVE
+///
+/// Разворачивает бэкап на месте нового хранилища и проверяет, что мастер-пароль
+/// его открывает. При любой ошибке частично восстановленное хранилище удаляется.
+pub fn init_from_backup(archive: &Path) -> Result<()> {
+    let root = store_root()?;
+    if root.exists() {
+        return Err(anyhow!(
+            "Store already exists at {}; refusing to overwrite it (`pm backup restore --force` replaces it)",
+            root.display()
+        ));
+    }
+    if !archive.is_file() {
+        return Err(anyhow!("Backup not found: {}", archive.display()));
+    }
+
+    let staging = root.with_file_name(format!("pm-store.restore-{}", std::process::id()));
+    let mut progress = Progress::new("Restoring", None);
+    let result = unpack_store(archive, &staging, &mut progress).and_then(|dir| {
+        if let Some(parent) = root.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::rename(&dir, &root).context("cannot move the new store into place")?;
+        crate::perms::harden_store_root(&root)
+    });
+    let _ = std::fs::remove_dir_all(&staging);
+    result?;
+    let stats = progress.stats(std::fs::metadata(archive)?.len());
+    progress.finish(format!("Unpacked {stats}"));
+
+    if let Err(e) = verify_restored_store() {
+        let _ = std::fs::remove_dir_all(&root);
+        return Err(e.context("restore rolled back"));
+    }
+
+    let count = list_entries()?.len();
+    println!(
+        "Restored store at {} from {} ({count} entries)",
+        root.display(),
+        archive.display()
+    );
+    Ok(())
+}
+
+/// pm backup restore ARCHIVE [--force]
+///
+/// Распаковывает бэкап во временный каталог рядом с хранилищем, проверяет его
+/// и подменяет хранилище переименованием. Старое хранилище не удаляется,
+/// а сохраняется как `pm-store.bak-<время>`.
+pub fn backup_restore(archive: &Path, force: bool) -> Result<()> {
+    let root = store_root()?;
+    if !archive.is_file() {
+        return Err(anyhow!("Backup not found: {}", archive.display()));
+    }
+    if root.exists() && !force {
+        return Err(anyhow!(
+            "Store already exists at {}; use --force to replace it (the current store will be kept as a .bak copy)",
+            root.display()
+        ));
+    }
+    if let Some(parent) = root.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let staging = root.with_file_name(format!("pm-store.restore-{}", std::process::id()));
+    let mut progress = Progress::new("Restoring", None);
+    let result = unpack_store(archive, &staging, &mut progress).and_then(|dir| {
+        check_restored_dir(&dir)?;
+        swap_into_place(&dir, &root)
+    });
+    let _ = std::fs::remove_dir_all(&staging);
+    let previous = result?;
+    let stats = progress.stats(std::fs::metadata(archive)?.len());
+    progress.finish(format!("Unpacked {stats}"));
+
+    crate::perms::harden_store_root(&root)?;
+    // Закэшированный ключ относится к прежнему хранилищу
+    clear_session()?;
+
+    let count = list_entries()?.len();
+    println!(
+        "Restored {count} entries from {} into {}",
+        archive.display(),
+        root.display()
+    );
+    if let Some(prev) = previous {
+        println!("Previous store preserved at {}", prev.display());
+    }
+    Ok(())
+}
+
+/// Распакованный бэкап похож на хранилище: есть разбираемый config.json и store/.
+fn check_restored_dir(dir: &Path) -> Result<()> {
+    let data = std::fs::read_to_string(dir.join("config.json"))?;
+    serde_json::from_str::<Config>(&data).context("config.json in the backup is invalid")?;
+    if !dir.join("store").is_dir() {
+        return Err(anyhow!("Backup does not contain a store/ directory"));
+    }
+    Ok(())
+}
+
+/// Переименовать текущее хранилище в `.bak-<время>` и поставить `dir` на его место.
+/// Возвращает путь сохранённой копии, если хранилище было.
+pub fn swap_into_place(dir: &Path, root: &Path) -> Result<Option<PathBuf>> {
+    let previous = if root.exists() {
+        let stamp = OffsetDateTime::now_utc().format(time::macros::format_description!(
+            "[year][month][day]-[hour][minute][second]"
+        ))?;
+        let mut bak = root.with_file_name(format!("pm-store.bak-{stamp}"));
+        let mut n = 1;
+        while bak.exists() {
+            bak = root.with_file_name(format!("pm-store.bak-{stamp}-{n}"));
+            n += 1;
+        }
+        std::fs::rename(root, &bak).context("cannot move the current store aside")?;
+        Some(bak)
+    } else {
+        None
+    };
+
+    if let Err(e) = std::fs::rename(dir, root) {
+        if let Some(ref bak) = previous {
+            let _ = std::fs::rename(bak, root);
+        }
+        return Err(anyhow::Error::from(e).context("cannot move the new store into place"));
+    }
+    Ok(previous)
+}
+
+fn verify_restored_store() -> Result<()> {
+    let config = Config::load().context("restored config.json is invalid")?;
+    // Как и остальные команды: PM_MASTER_PASSWORD или --password-fd, иначе запрос
+    let master_password = match crate::session::master_password_from_env() {
+        Some(pw) => pw,
+        None => prompt_password_hidden("Master password of the backup: ")?,
+    };
+    unlock_master_key(
+        &master_password,
+        &config,
+        crate::keyfile::current()?.as_ref(),
+    )?;
+    Ok(())
+}
+
+/// Имя архива `pm backup auto`: `pm-backup-20240101T020000Z.tar.gz`.
+/// Ротация трогает только файлы, имя которых целиком совпадает с шаблоном.
+const AUTO_NAME: &str = r"^pm-backup-\d{8}T\d{6}Z\.tar\.gz$";
+const AUTO_STAMP: &str = "[year][month][day]T[hour][minute][second]Z";
+
+/// Архив, созданный `pm backup auto`.
+pub struct AutoBackup {
+    pub file: PathBuf,
+    pub created: OffsetDateTime,
+    pub size: u64,
+}
+
+/// Каталог `backup.dir`; по умолчанию — `pm-backups` рядом с хранилищем.
+pub fn backup_dir(settings: &BackupSettings) -> Result<PathBuf> {
+    let root = store_root()?;
+    let base = root.parent().unwrap_or(Path::new("."));
+    Ok(base.join(settings.dir.as_deref().unwrap_or("pm-backups")))
+}
+
+fn auto_stamp(name: &str) -> Option<OffsetDateTime> {
+    let stamp = name.strip_prefix("pm-backup-")?.strip_suffix(".tar.gz")?;
+    let format = format_description::parse(AUTO_STAMP).ok()?;
+    Some(PrimitiveDateTime::parse(stamp, &format).ok()?.assume_utc())
+}
+
+/// Архивы `pm backup auto` в `dir`, от новых к старым. Чужие файлы,
+/// каталоги и ссылки пропускаются.
+pub fn list_auto(dir: &Path) -> Result<Vec<AutoBackup>> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let pattern = crate::regex::Regex::new(AUTO_NAME, false)?;
+    let mut backups = Vec::new();
+    for item in std::fs::read_dir(dir).with_context(|| format!("cannot read {}", dir.display()))? {
+        let item = item?;
+        let name = item.file_name();
+        let Some(name) = name.to_str().filter(|n| pattern.is_match(n)) else {
+            continue;
+        };
+        let meta = item.path().symlink_metadata()?;
+        let Some(created) = auto_stamp(name).filter(|_| meta.is_file()) else {
+            continue;
+        };
+        backups.push(AutoBackup {
+            file: item.path(),
+            created,
+            size: meta.len(),
+        });
+    }
+    backups.sort_by_key(|b| std::cmp::Reverse(b.created));
+    Ok(backups)
+}
+
+/// Какие из `backups` (от новых к старым) оставить: самый новый всегда,
+/// плюс самый новый за каждый из последних `keep_daily` дней, `keep_weekly`
+/// недель (ISO) и `keep_monthly` месяцев, в которых вообще есть архивы.
+fn retained(backups: &[AutoBackup], keep: &BackupSettings) -> Vec<bool> {
+    type Period = fn(OffsetDateTime) -> (i32, u16);
+    let periods: [(u64, Period); 3] = [
+        (keep.keep_daily, |t| (t.year(), t.ordinal())),
+        (keep.keep_weekly, |t| {
+            let (year, week, _) = t.to_iso_week_date();
+            (year, week.into())
+        }),
+        (keep.keep_monthly, |t| {
+            (t.year(), u8::from(t.month()).into())
+        }),
+    ];
+    let mut kept = vec![false; backups.len()];
+    if let Some(newest) = kept.first_mut() {
+        *newest = true;
+    }
+    for (limit, period) in periods {
+        let mut last = None;
+        let mut count = 0;
+        for (i, backup) in backups.iter().enumerate() {
+            if count == limit {
+                break;
+            }
+            let p = period(backup.created);
+            if last != Some(p) {
+                last = Some(p);
+                kept[i] = true;
+                count += 1;
+            }
+        }
+    }
+    kept
+}
+
+/// Удалить (или только найти, если `dry_run`) архивы сверх политики хранения.
+fn prune(dir: &Path, keep: &BackupSettings, dry_run: bool) -> Result<Vec<AutoBackup>> {
+    let backups = list_auto(dir)?;
+    let kept = retained(&backups, keep);
+    let mut pruned = Vec::new();
+    for (backup, kept) in backups.into_iter().zip(kept) {
+        if kept {
+            continue;
+        }
+        if !dry_run {
+            std::fs::remove_file(&backup.file)
+                .with_context(|| format!("cannot delete {}", backup.file.display()))?;
+            crate::log::info(format_args!("pruned {}", backup.file.display()));
+        }
+        pruned.push(backup);
+    }
+    Ok(pruned)
+}
+
+/// pm backup auto: архив с отметкой времени в `backup.dir` и ротация.
+/// Для cron: при успехе ничего не печатает (с `-v` — что сделано).
+pub fn backup_auto() -> Result<()> {
+    let config = Config::load()?;
+    let root = store_root()?;
+    let dir = backup_dir(&config.settings.backup)?;
+    create_private_dir_all(&dir).with_context(|| format!("cannot create {}", dir.display()))?;
+
+    let stamp = OffsetDateTime::now_utc().format(&format_description::parse(AUTO_STAMP)?)?;
+    let name = format!("pm-backup-{stamp}.tar.gz");
+    let path = dir.join(&name);
+    if path.exists() {
+        return Err(anyhow!("{} already exists", path.display()));
+    }
+    // Недописанный архив не должен попасть под шаблон и в ротацию
+    let partial = dir.join(format!(".{name}.partial"));
+    // Для cron: полосы и итога нет, итог — в `-v`
+    let mut progress = Progress::hidden();
+    let result = backup_tar_gz(&partial, &root, &mut progress)
+        .and_then(|_| crate::perms::restrict(&partial))
+        .and_then(|_| Ok(std::fs::rename(&partial, &path)?));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&partial);
+    }
+    result.with_context(|| format!("cannot write {}", path.display()))?;
+    crate::log::info(format_args!(
+        "backup written to {} ({})",
+        path.display(),
+        progress.stats(std::fs::metadata(&path)?.len())
+    ));
+
+    let pruned = prune(&dir, &config.settings.backup, false)?;
+    output::emit(
+        &serde_json::json!({
+            "ok": true,
+            "file": path.display().to_string(),
+            "pruned": pruned.iter().map(|b| b.file.display().to_string()).collect::<Vec<_>>(),
+        }),
+        || Ok(()),
+    )
+}
+
+/// pm backup prune [--dry-run]
+pub fn backup_prune(dry_run: bool) -> Result<()> {
+    let config = Config::load()?;
+    let dir = backup_dir(&config.settings.backup)?;
+    let pruned = prune(&dir, &config.settings.backup, dry_run)?;
+    output::emit(
+        &serde_json::json!({
+            "ok": true,
+            "dry_run": dry_run,
+            "pruned": pruned.iter().map(|b| b.file.display().to_string()).collect::<Vec<_>>(),
+        }),
+        || {
+            if dry_run {
+                for backup in &pruned {
+                    println!("would delete {}", backup.file.display());
+                }
+                if pruned.is_empty() {
+                    println!("Nothing to prune in {}.", dir.display());
+                }
+            }
+            Ok(())
+        },
+    )
+}
+
+/// pm backup list
+pub fn backup_list() -> Result<()> {
+    let config = Config::load()?;
+    let dir = backup_dir(&config.settings.backup)?;
+    let backups = list_auto(&dir)?;
+    let now = OffsetDateTime::now_utc();
+    output::emit(
+        &serde_json::json!({
+            "dir": dir.display().to_string(),
+            "backups": backups
+                .iter()
+                .map(|b| {
+                    serde_json::json!({
+                        "file": b.file.display().to_string(),
+                        "created_at": b
+                            .created
+                            .format(&time::format_description::well_known::Rfc3339)
+                            .unwrap_or_default(),
+                        "size": b.size,
+                    })
+                })
+                .collect::<Vec<_>>(),
+        }),
+        || {
+            if backups.is_empty() {
+                println!("No backups in {}.", dir.display());
+                return Ok(());
+            }
+            println!("Backups in {}:", dir.display());
+            for b in &backups {
+                let name = b.file.file_name().unwrap_or_default().to_string_lossy();
+                println!(
+                    "  {name}  {:>10}  {}",
+                    human_size(b.size),
+                    human_age(now - b.created)
+                );
+            }
+            Ok(())
+        },
+    )
+}
+
+fn human_age(age: time::Duration) -> String {
+    let minutes = age.whole_minutes().max(0);
+    match minutes {
+        0..60 => format!("{minutes}m ago"),
+        60..2880 => format!("{}h ago", minutes / 60),
+        _ => format!("{}d ago", minutes / 1440),
+    }
+}
+
+/// Итог `pm backup verify`.
+#[derive(serde::Serialize)]
+struct Verification {
+    files: usize,
+    entries: usize,
+    /// Были ли в архиве контрольные суммы
+    manifest: bool,
+    /// Сколько записей и вложений расшифровано (`--deep`)
+    decrypted: Option<usize>,
+    decrypted_attachments: Option<usize>,
+    failed: Vec<FailedFile>,
+}
+
+#[derive(serde::Serialize)]
+struct FailedFile {
+    path: String,
+    error: String,
+}
+
+impl Verification {
+    fn fail(&mut self, path: &str, error: impl std::fmt::Display) {
+        self.failed.push(FailedFile {
+            path: path.to_string(),
+            error: error.to_string(),
+        });
+    }
+}
+
+/// pm backup verify ARCHIVE [--deep]
+///
+/// Распаковывает архив во временный каталог и проверяет: config.json,
+/// обёртки всех `.enc` записей и корзины, `MANIFEST.sha256`; с `deep` —
+/// ещё и расшифровывает каждую запись мастер-паролем бэкапа.
+pub fn backup_verify(archive: &Path, deep: bool) -> Result<()> {
+    if !archive.is_file() {
+        return Err(anyhow!("Backup not found: {}", archive.display()));
+    }
+    let staging = std::env::temp_dir().join(format!("pm-verify-{}", std::process::id()));
+    create_private_dir_all(&staging)?;
+    let result = extract_backup(archive, &staging, &mut Progress::hidden())
+        .with_context(|| format!("cannot read {}", archive.display()))
+        .and_then(|_| {
+            let dir = find_store_dir(&staging)
+                .ok_or_else(|| anyhow!("Backup does not contain a config.json"))?;
+            verify_store_dir(&dir, deep)
+        });
+    let _ = std::fs::remove_dir_all(&staging);
+    let report = result?;
+
+    if !report.manifest {
+        eprintln!(
+            "Warning: {} has no {MANIFEST} (made by an older pm); checksums were not verified",
+            archive.display()
+        );
+    }
+    output::emit(&report, || {
+        for f in &report.failed {
+            println!("FAILED {}: {}", f.path, f.error);
+        }
+        if report.failed.is_empty() {
+            let mut checks = vec![format!("{} files", report.files)];
+            checks.push(format!("{} entries", report.entries));
+            if report.manifest {
+                checks.push("checksums match".to_string());
+            }
+            if let Some(n) = report.decrypted {
+                checks.push(format!("{n} entry files decrypted"));
+            }
+            if let Some(n) = report.decrypted_attachments.filter(|n| *n > 0) {
+                checks.push(format!("{n} attachments decrypted"));
+            }
+            println!("{}: OK ({})", archive.display(), checks.join(", "));
+        }
+        Ok(())
+    })?;
+    if !report.failed.is_empty() {
+        return Err(anyhow!(
+            "{} of {} files in {} failed verification",
+            report.failed.len(),
+            report.files,
+            archive.display()
+        ));
+    }
+    Ok(())
+}
+
+fn verify_store_dir(dir: &Path, deep: bool) -> Result<Verification> {
+    let files = files_under(dir)?;
+    let mut report = Verification {
+        files: files.iter().filter(|(rel, _)| rel != MANIFEST).count(),
+        entries: 0,
+        manifest: false,
+        decrypted: None,
+        decrypted_attachments: None,
+        failed: Vec::new(),
+    };
+
+    let config = std::fs::read_to_string(dir.join("config.json"))
+        .map_err(anyhow::Error::from)
+        .and_then(|data| Ok(serde_json::from_str::<Config>(&data)?));
+    if let Err(e) = &config {
+        report.fail("config.json", e);
+    }
+
+    // Файлы записей — в store/ и trash/; index.enc в корне устроен иначе
+    for (rel, file) in &files {
+        let is_entry =
+            rel.ends_with(".enc") && (rel.starts_with("store/") || rel.starts_with("trash/"));
+        if is_entry {
+            if rel.starts_with("store/") && !crate::attachments::is_attachment_file(rel) {
+                report.entries += 1;
+            }
+            if let Err(e) = crate::store::check_envelope_file(file) {
+                report.fail(rel, e);
+            }
+        }
+    }
+    if report.entries == 0 {
+        report.fail("store/", "no .enc entry files");
+    }
+
+    let manifest_file = dir.join(MANIFEST);
+    if manifest_file.is_file() {
+        report.manifest = true;
+        check_manifest(
+            &std::fs::read_to_string(&manifest_file)?,
+            &files,
+            &mut report,
+        );
+    }
+
+    if deep && let Ok(config) = &config {
+        let mk = crate::session::unlock_interactive(config)?;
+        let store_dir = dir.join("store");
+        let mut targets = Vec::new();
+        for path in crate::store::list_entries_in(&store_dir)? {
+            let file = crate::store::entry_file_path_in(&store_dir, &path)?;
+            targets.push((format!("store/{path}.enc"), file, path));
+        }
+        for item in crate::trash::list(dir)? {
+            let name = item.file.file_name().unwrap_or_default().to_string_lossy();
+            targets.push((format!("trash/{name}"), item.file.clone(), item.path));
+        }
+        let mut decrypted = 0;
+        for (rel, file, path) in targets {
+            // Уже отмеченный файл второй раз не перечисляем
+            if report.failed.iter().any(|f| f.path == rel) {
+                continue;
+            }
+            match crate::store::decrypt_entry_file(&file, &path, &mk) {
+                Ok(_) => decrypted += 1,
+                Err(e) => report.fail(&rel, e),
+            }
+        }
+        report.decrypted = Some(decrypted);
+
+        let mut attachments = 0;
+        for path in crate::store::list_entries_in(&store_dir)? {
+            for name in crate::attachments::names_on_disk(&store_dir, &path)? {
+                let rel = format!(
+                    "store/{path}{}/{name}.enc",
+                    crate::store::ATTACHMENTS_SUFFIX
+                );
+                if report.failed.iter().any(|f| f.path == rel) {
+                    continue;
+                }
+                match crate::attachments::read_in(&store_dir, &path, &name, &mk) {
+                    Ok(_) => attachments += 1,
+                    Err(e) => report.fail(&rel, format_args!("{e:#}")),
+                }
+            }
+        }
+        report.decrypted_attachments = Some(attachments);
+    }
+    Ok(report)
+}
+
+/// Сверить `MANIFEST.sha256` с файлами архива: каждый файл описан
+/// и совпадает, лишних строк нет.
+fn check_manifest(manifest: &str, files: &[(String, PathBuf)], report: &mut Verification) {
+    let mut expected = std::collections::BTreeMap::new();
+    for (n, line) in manifest.lines().enumerate() {
+        match line.split_once("  ") {
+            Some((hash, rel)) if hash.len() == 64 => {
+                expected.insert(rel, hash);
+            }
+            _ => report.fail(MANIFEST, format_args!("line {} is malformed", n + 1)),
+        }
+    }
+    for (rel, file) in files {
+        if rel == MANIFEST {
+            continue;
+        }
+        match expected.remove(rel.as_str()) {
+            None => report.fail(rel, format_args!("not listed in {MANIFEST}")),
+            Some(hash) => match std::fs::read(file) {
+                Ok(data) if sha256_hex(&data) == hash => {}
+                Ok(_) => report.fail(rel, "checksum mismatch"),
+                Err(e) => report.fail(rel, e),
+            },
+        }
+    }
+    for rel in expected.into_keys() {
+        report.fail(rel, format_args!("listed in {MANIFEST} but missing"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn extracted_files_are_private_before_the_swap() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("pm-backup-perms-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        // Архив с правами 0644/0755, как у чужого tar
+        let archive = dir.join("backup.tar.gz");
+        let gz = flate2::write::GzEncoder::new(
+            File::create(&archive).unwrap(),
+            flate2::Compression::fast(),
+        );
+        let mut tar = tar::Builder::new(gz);
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Directory);
+        header.set_mode(0o755);
+        header.set_size(0);
+        tar.append_data(&mut header, "pm-store/store", std::io::empty())
+            .unwrap();
+        let secret = b"{\"version\":1}";
+        let mut header = tar::Header::new_gnu();
+        header.set_mode(0o644);
+        header.set_size(secret.len() as u64);
+        tar.append_data(&mut header, "pm-store/store/work/gh.enc", &secret[..])
+            .unwrap();
+        tar.into_inner().unwrap().finish().unwrap();
+
+        let staging = dir.join("staging");
+        extract_archive(&archive, &staging, &mut Progress::hidden()).unwrap();
+        let mode = |p: &Path| std::fs::metadata(p).unwrap().permissions().mode() & 0o777;
+        for d in ["", "pm-store", "pm-store/store", "pm-store/store/work"] {
+            assert_eq!(mode(&staging.join(d)), 0o700, "{d}");
+        }
+        assert_eq!(mode(&staging.join("pm-store/store/work/gh.enc")), 0o600);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
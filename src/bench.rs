@@ -0,0 +1,257 @@
+//! `pm bench`: замеры KDF и шифрования на этой машине.
+//!
+//! Часы передаются снаружи (`Clock`), а сводка строится чистой функцией
+//! `format_summary`, так что форматирование не зависит от реального времени.
+
+use crate::config::{Config, KdfParams};
+use crate::crypto::{DEFAULT_KDF_TARGET_MS, MasterKey, decrypt_entry, derive_kek, encrypt_entry};
+use anyhow::Result;
+use base64::{Engine as _, engine::general_purpose};
+use rand::RngCore;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+/// Источник времени для замеров.
+pub trait Clock {
+    /// Время от произвольной, но фиксированной точки отсчёта.
+    fn now(&self) -> Duration;
+}
+
+struct SystemClock(Instant);
+
+impl SystemClock {
+    fn new() -> Self {
+        SystemClock(Instant::now())
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.0.elapsed()
+    }
+}
+
+/// Сколько времени заняло `f`.
+fn measure<T>(clock: &dyn Clock, f: impl FnOnce() -> T) -> (Duration, T) {
+    let start = clock.now();
+    let v = f();
+    (clock.now().saturating_sub(start), v)
+}
+
+/// Пресеты Argon2id для сравнения: память в МиБ, итерации.
+const KDF_PRESETS: &[(u32, u32)] = &[(64, 3), (128, 3), (256, 3)];
+/// К этому времени разблокировки стоит стремиться.
+const KDF_TARGET_MS: f64 = DEFAULT_KDF_TARGET_MS as f64;
+const ENTRY_SIZE: usize = 512;
+const SYNTHETIC_ENTRIES: usize = 100;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct KdfTiming {
+    pub memory_mib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+    pub ms: f64,
+    pub current: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct BenchReport {
+    pub kdf: Vec<KdfTiming>,
+    /// Шифрование записей размером `entry_bytes`: записей в секунду и МБ/с
+    pub entry_bytes: usize,
+    pub encrypt_per_sec: f64,
+    pub decrypt_mb_per_sec: f64,
+    /// Запись, обход и расшифровка `synthetic_entries` файлов во временном каталоге
+    pub synthetic_entries: usize,
+    pub list_decrypt_ms: f64,
+    pub recommendation: String,
+}
+
+fn ms(d: Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
+
+/// Совет по параметрам KDF на основе замеров.
+pub fn recommend(kdf: &[KdfTiming]) -> String {
+    let Some(current) = kdf.iter().find(|k| k.current) else {
+        return "no store found; run `pm init` to use the default KDF".to_string();
+    };
+    if current.ms < KDF_TARGET_MS / 2.0 {
+        let better = kdf
+            .iter()
+            .filter(|k| !k.current && k.memory_mib > current.memory_mib)
+            .min_by(|a, b| {
+                (a.ms - KDF_TARGET_MS)
+                    .abs()
+                    .total_cmp(&(b.ms - KDF_TARGET_MS).abs())
+            });
+        if let Some(b) = better {
+            return format!(
+                "current KDF: {:.0}ms — consider increasing memory to {} MiB for ~{:.0}ms (`pm config upgrade-kdf`)",
+                current.ms, b.memory_mib, b.ms
+            );
+        }
+    }
+    if current.ms > KDF_TARGET_MS * 3.0 {
+        return format!(
+            "current KDF: {:.0}ms — every unlock is slow; consider lowering memory or iterations",
+            current.ms
+        );
+    }
+    format!("current KDF: {:.0}ms — looks reasonable", current.ms)
+}
+
+/// Текстовая таблица для человека.
+pub fn format_summary(r: &BenchReport) -> String {
+    let mut out = String::new();
+    out.push_str(&format!("{:<32}{:>7}\n", "Argon2id", "time"));
+    for k in &r.kdf {
+        let label = format!(
+            "{} MiB, t={}, p={}{}",
+            k.memory_mib,
+            k.iterations,
+            k.parallelism,
+            if k.current { " (current)" } else { "" }
+        );
+        out.push_str(&format!("  {label:<30}{:>7.0} ms\n", k.ms));
+    }
+    out.push_str(&format!(
+        "XChaCha20-Poly1305 ({} B entries): {:.0} encrypts/s, {:.1} MB/s decrypt\n",
+        r.entry_bytes, r.encrypt_per_sec, r.decrypt_mb_per_sec
+    ));
+    out.push_str(&format!(
+        "List + decrypt {} entries: {:.1} ms\n",
+        r.synthetic_entries, r.list_decrypt_ms
+    ));
+    out.push_str(&r.recommendation);
+    out.push('\n');
+    out
+}
+
+fn time_kdf(clock: &dyn Clock, params: &KdfParams) -> Result<Duration> {
+    let (d, res) = measure(clock, || derive_kek("benchmark-password", params));
+    res?;
+    Ok(d)
+}
+
+fn bench_cipher(clock: &dyn Clock, key: &MasterKey) -> Result<(f64, f64)> {
+    let mut data = vec![0u8; ENTRY_SIZE];
+    rand::thread_rng().fill_bytes(&mut data);
+
+    let rounds = 2000;
+    let (enc_time, sealed) = measure(clock, || -> Result<Vec<(String, String)>> {
+        (0..rounds)
+            .map(|_| encrypt_entry(key, &data, &[]))
+            .collect()
+    });
+    let sealed = sealed?;
+    let (dec_time, res) = measure(clock, || -> Result<()> {
+        for (n, c) in &sealed {
+            decrypt_entry(key, n, c, &[])?;
+        }
+        Ok(())
+    });
+    res?;
+
+    let enc_per_sec = rounds as f64 / enc_time.as_secs_f64().max(1e-9);
+    let dec_mb = (rounds * ENTRY_SIZE) as f64 / 1e6 / dec_time.as_secs_f64().max(1e-9);
+    Ok((enc_per_sec, dec_mb))
+}
+
+/// Записать 100 синтетических файлов во временный каталог, затем обойти и расшифровать.
+fn bench_list_decrypt(clock: &dyn Clock, key: &MasterKey) -> Result<Duration> {
+    let dir = std::env::temp_dir().join(format!("pm-bench-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+
+    let result = (|| -> Result<Duration> {
+        let mut data = vec![b' '; ENTRY_SIZE];
+        for i in 0..SYNTHETIC_ENTRIES {
+            rand::thread_rng().fill_bytes(&mut data[..32]);
+            let (n, c) = encrypt_entry(key, &data, &[])?;
+            std::fs::write(dir.join(format!("{i}.enc")), format!("{n}\n{c}"))?;
+        }
+
+        let (d, res) = measure(clock, || -> Result<()> {
+            for f in std::fs::read_dir(&dir)? {
+                let s = std::fs::read_to_string(f?.path())?;
+                let (n, c) = s.split_once('\n').unwrap_or_default();
+                decrypt_entry(key, n, c, &[])?;
+            }
+            Ok(())
+        });
+        res?;
+        Ok(d)
+    })();
+
+    let _ = std::fs::remove_dir_all(&dir);
+    result
+}
+
+pub fn run(clock: &dyn Clock) -> Result<BenchReport> {
+    let salt = general_purpose::STANDARD.encode([0u8; 16]);
+    let current = Config::load().ok().map(|c| c.kdf);
+
+    let mut kdf = Vec::new();
+    if let Some(ref cur) = current {
+        kdf.push(KdfTiming {
+            memory_mib: cur.memory_mib,
+            iterations: cur.iterations,
+            parallelism: cur.parallelism,
+            ms: ms(time_kdf(clock, cur)?),
+            current: true,
+        });
+    }
+    for &(memory_mib, iterations) in KDF_PRESETS {
+        if current
+            .as_ref()
+            .is_some_and(|c| c.memory_mib == memory_mib && c.iterations == iterations)
+        {
+            continue;
+        }
+        let params = KdfParams {
+            algo: "argon2id".to_string(),
+            memory_mib,
+            iterations,
+            parallelism: 1,
+            salt: salt.clone(),
+            keyfile: false,
+        };
+        kdf.push(KdfTiming {
+            memory_mib,
+            iterations,
+            parallelism: 1,
+            ms: ms(time_kdf(clock, &params)?),
+            current: false,
+        });
+    }
+
+    let mut key = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut key);
+    let (encrypt_per_sec, decrypt_mb_per_sec) = bench_cipher(clock, &key)?;
+    let list_decrypt = bench_list_decrypt(clock, &key)?;
+
+    let recommendation = recommend(&kdf);
+    Ok(BenchReport {
+        kdf,
+        entry_bytes: ENTRY_SIZE,
+        encrypt_per_sec,
+        decrypt_mb_per_sec,
+        synthetic_entries: SYNTHETIC_ENTRIES,
+        list_decrypt_ms: ms(list_decrypt),
+        recommendation,
+    })
+}
+
+/// pm bench [--json]
+pub fn bench(json: bool) -> Result<()> {
+    if !json {
+        eprintln!("Benchmarking, this takes a few seconds...");
+    }
+    let report = run(&SystemClock::new())?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print!("{}", format_summary(&report));
+    }
+    Ok(())
+}
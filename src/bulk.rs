@@ -0,0 +1,272 @@
+use crate::config::Config;
+use crate::entry::Entry;
+use crate::git::auto_commit;
+use crate::hooks::{HookAction, run_hooks};
+use crate::session::get_master_key_with_cache;
+use crate::store::{decrypt_all, list_entries, save_entry};
+use anyhow::{Result, anyhow};
+use time::OffsetDateTime;
+
+/// Поля, которые можно менять массово. Пароль сюда намеренно не входит.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum BulkField {
+    Username,
+    Url,
+    Notes,
+}
+
+impl BulkField {
+    fn name(self) -> &'static str {
+        match self {
+            BulkField::Username => "username",
+            BulkField::Url => "url",
+            BulkField::Notes => "notes",
+        }
+    }
+
+    fn parse(name: &str) -> Result<Self> {
+        match name.trim() {
+            "username" => Ok(BulkField::Username),
+            "url" => Ok(BulkField::Url),
+            "notes" => Ok(BulkField::Notes),
+            "password" => Err(anyhow!("password cannot be changed in bulk")),
+            other => Err(anyhow!(
+                "unknown field '{other}', expected username/url/notes"
+            )),
+        }
+    }
+
+    fn get(self, e: &Entry) -> Option<&String> {
+        match self {
+            BulkField::Username => e.username.as_ref(),
+            BulkField::Url => e.url.as_ref(),
+            BulkField::Notes => e.notes.as_ref(),
+        }
+    }
+
+    fn set(self, e: &mut Entry, value: Option<String>) {
+        match self {
+            BulkField::Username => e.username = value,
+            BulkField::Url => e.url = value,
+            BulkField::Notes => e.notes = value,
+        }
+    }
+}
+
+/// Разобрать спецификацию вида `old=new`.
+///
+/// Разделитель — первый неэкранированный `=`; `\=` даёт литеральный `=`,
+/// `\\` — обратный слэш. Пустая правая часть допустима (удалить подстроку),
+/// пустая левая — нет.
+pub fn parse_replace_spec(spec: &str) -> Result<(String, String)> {
+    let mut left = String::new();
+    let mut right = String::new();
+    let mut seen_sep = false;
+    let mut chars = spec.chars();
+
+    while let Some(c) = chars.next() {
+        let target = if seen_sep { &mut right } else { &mut left };
+        match c {
+            '\\' => match chars.next() {
+                Some(n @ ('=' | '\\')) => target.push(n),
+                Some(n) => {
+                    target.push('\\');
+                    target.push(n);
+                }
+                None => target.push('\\'),
+            },
+            '=' if !seen_sep => seen_sep = true,
+            _ => target.push(c),
+        }
+    }
+
+    if !seen_sep {
+        return Err(anyhow!("invalid spec '{spec}': expected OLD=NEW"));
+    }
+    if left.is_empty() {
+        return Err(anyhow!("invalid spec '{spec}': left side cannot be empty"));
+    }
+    Ok((left, right))
+}
+
+enum Change {
+    Replace(BulkField, String, String),
+    Set(BulkField, String),
+}
+
+impl Change {
+    /// Применить к записи, вернуть (поле, было, стало), если что-то поменялось.
+    fn apply(&self, e: &mut Entry) -> Option<(BulkField, String, String)> {
+        let (field, new) = match self {
+            Change::Replace(field, from, to) => {
+                let old = field.get(e)?;
+                if !old.contains(from.as_str()) {
+                    return None;
+                }
+                (*field, old.replace(from.as_str(), to))
+            }
+            Change::Set(field, value) => (*field, value.clone()),
+        };
+
+        let old = field.get(e).cloned().unwrap_or_default();
+        if old == new {
+            return None;
+        }
+        field.set(
+            e,
+            if new.is_empty() {
+                None
+            } else {
+                Some(new.clone())
+            },
+        );
+        Some((field, old, new))
+    }
+}
+
+/// pm bulk set --prefix P [--field F --replace OLD=NEW] [--set F=VALUE]... [--dry-run]
+pub fn bulk_set(
+    prefix: &str,
+    field: Option<BulkField>,
+    replace: Option<String>,
+    sets: Vec<String>,
+    dry_run: bool,
+) -> Result<()> {
+    let mut changes = Vec::new();
+    if let (Some(field), Some(spec)) = (field, replace) {
+        let (from, to) = parse_replace_spec(&spec)?;
+        changes.push(Change::Replace(field, from, to));
+    }
+    for spec in &sets {
+        let (name, value) = parse_replace_spec(spec)?;
+        changes.push(Change::Set(BulkField::parse(&name)?, value));
+    }
+    if changes.is_empty() {
+        return Err(anyhow!(
+            "nothing to do: pass --field with --replace, or --set"
+        ));
+    }
+
+    let config = Config::load()?;
+    let mk = get_master_key_with_cache(&config)?;
+
+    let prefix = prefix.trim_end_matches('/');
+    let pref_slash = format!("{prefix}/");
+    let paths: Vec<String> = list_entries()?
+        .into_iter()
+        .filter(|e| prefix.is_empty() || e == prefix || e.starts_with(&pref_slash))
+        .collect();
+
+    let now = OffsetDateTime::now_utc().format(&time::format_description::well_known::Rfc3339)?;
+
+    let mut changed = 0;
+    let mut unchanged = 0;
+    let mut failures = Vec::new();
+
+    for (path, entry) in decrypt_all(&mk, paths) {
+        let mut entry = match entry {
+            Ok(e) => e,
+            Err(e) => {
+                failures.push((path, e));
+                continue;
+            }
+        };
+
+        let diffs: Vec<_> = changes.iter().filter_map(|c| c.apply(&mut entry)).collect();
+        if diffs.is_empty() {
+            unchanged += 1;
+            println!("unchanged {path}");
+            continue;
+        }
+
+        changed += 1;
+        println!("changed   {path}");
+        for (field, old, new) in &diffs {
+            println!("    {}: {old:?} -> {new:?}", field.name());
+        }
+
+        if !dry_run {
+            entry.updated_at = now.clone();
+            match save_entry(&path, &entry, &mk) {
+                Ok(()) => {
+                    auto_commit(&config, HookAction::Edit, &path);
+                    run_hooks(&config, HookAction::Edit, &path);
+                }
+                Err(e) => {
+                    changed -= 1;
+                    failures.push((path, e));
+                }
+            }
+        }
+    }
+
+    let verb = if dry_run { "would change" } else { "changed" };
+    println!(
+        "{changed} {verb}, {unchanged} unchanged, {} failed",
+        failures.len()
+    );
+
+    if !failures.is_empty() {
+        for (path, e) in &failures {
+            eprintln!("Failed {path}: {e}");
+        }
+        return Err(anyhow!("{} entries failed", failures.len()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(s: &str) -> (String, String) {
+        parse_replace_spec(s).unwrap()
+    }
+
+    fn pair(a: &str, b: &str) -> (String, String) {
+        (a.to_string(), b.to_string())
+    }
+
+    #[test]
+    fn replace_spec_splits_on_the_first_equals() {
+        assert_eq!(
+            spec("old-sso.corp.com=new-sso.corp.com"),
+            pair("old-sso.corp.com", "new-sso.corp.com")
+        );
+        // Справа `=` уже не разделитель
+        assert_eq!(spec("a=b=c"), pair("a", "b=c"));
+        assert_eq!(spec("key=?x=1&y=2"), pair("key", "?x=1&y=2"));
+    }
+
+    #[test]
+    fn replace_spec_escapes() {
+        assert_eq!(spec(r"a\=b=c"), pair("a=b", "c"));
+        assert_eq!(spec(r"a=b\=c"), pair("a", "b=c"));
+        assert_eq!(spec(r"a\\=b"), pair(r"a\", "b"));
+        assert_eq!(spec(r"a\\\=b=c"), pair(r"a\=b", "c"));
+        // Прочие `\x` остаются как есть, и хвостовой `\` тоже
+        assert_eq!(spec(r"C:\dir=D:\dir"), pair(r"C:\dir", r"D:\dir"));
+        assert_eq!(spec(r"a=b\"), pair("a", r"b\"));
+    }
+
+    #[test]
+    fn replace_spec_empty_sides() {
+        // Пустая замена — удалить подстроку
+        assert_eq!(spec("-old="), pair("-old", ""));
+        assert_eq!(spec(r"\=="), pair("=", ""));
+        let err = |s: &str| parse_replace_spec(s).unwrap_err().to_string();
+        assert_eq!(
+            err("=new"),
+            "invalid spec '=new': left side cannot be empty"
+        );
+        assert_eq!(
+            err("no-separator"),
+            "invalid spec 'no-separator': expected OLD=NEW"
+        );
+        assert_eq!(
+            err(r"only\=escaped"),
+            r"invalid spec 'only\=escaped': expected OLD=NEW"
+        );
+        assert_eq!(err(""), "invalid spec '': expected OLD=NEW");
+    }
+}
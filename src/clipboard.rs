@@ -1,77 +1,458 @@
+//! Буфер обмена: несколько способов (бэкендов) положить туда значение.
+//!
+//! - `wl-copy` — Wayland (wl-clipboard), умеет `--paste-once` и primary;
+//! - `xclip`, `xsel` — X11, xsel — если xclip не установлен;
+//! - `native` — системный API через copypasta (macOS, Windows);
+//! - `osc52` — escape-последовательность OSC 52 в терминал: работает по SSH,
+//!   если терминал её поддерживает; прочитать буфер обратно нельзя.
+//!
+//! Порядок перебора: `--osc52`, иначе `PM_CLIPBOARD` (список имён через
+//! запятую), иначе по умолчанию — OSC 52 первым в SSH-сессии (`$SSH_TTY`),
+//! затем системный API и утилиты Wayland/X11.
+
 use anyhow::{Result, anyhow};
+use copypasta::{ClipboardContext, ClipboardProvider};
 use std::process::{Command, Stdio};
 
-#[cfg(not(target_os = "linux"))]
-use copypasta::{ClipboardContext, ClipboardProvider};
+/// Как копировать: флаги `pm clip`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CopyOptions {
+    /// Стереть после первой вставки (wl-copy `--paste-once`, xclip `-loops 1`)
+    pub once: bool,
+    /// Primary selection (вставка средней кнопкой) вместо обычного буфера
+    pub primary: bool,
+    /// Только OSC 52
+    pub osc52: bool,
+}
 
-/// Linux: используем wl-copy (Wayland) или xclip (X11).
-#[cfg(target_os = "linux")]
-pub fn copy_to_clipboard(value: &str) -> Result<()> {
-    let has_wayland = std::env::var("WAYLAND_DISPLAY").is_ok();
-    let has_x11 = std::env::var("DISPLAY").is_ok();
+pub trait ClipboardBackend {
+    /// Имя для `PM_CLIPBOARD`, `pm doctor` и сообщений.
+    fn name(&self) -> &'static str;
+    /// Можно ли им пользоваться здесь: есть дисплей (терминал) и утилита.
+    fn available(&self) -> bool;
+    /// Поддерживает ли он `opts` (`--once`, `--primary`).
+    fn supports(&self, opts: CopyOptions) -> bool;
+    fn copy(&self, value: &str, opts: CopyOptions) -> Result<()>;
+    fn paste(&self, primary: bool) -> Result<String>;
+    fn clear(&self, primary: bool) -> Result<()>;
+}
+
+const NAMES: &[&str] = &["wl-copy", "xclip", "xsel", "native", "osc52"];
+
+fn backend(name: &str) -> Option<Box<dyn ClipboardBackend>> {
+    Some(match name {
+        "wl-copy" => Box::new(WlCopy),
+        "xclip" => Box::new(Xclip),
+        "xsel" => Box::new(Xsel),
+        "native" => Box::new(Native),
+        "osc52" => Box::new(Osc52),
+        _ => return None,
+    })
+}
 
-    // Если вообще нет ни X11, ни Wayland — скорее всего чистый tty
-    if !has_wayland && !has_x11 {
-        return Err(anyhow!(
-            "No GUI clipboard detected (no DISPLAY or WAYLAND_DISPLAY). \
-             You might be in a tty. Use:\n  pm show <path> --password-only | xclip -selection clipboard"
-        ));
+/// Бэкенды в порядке перебора.
+fn candidates(opts: CopyOptions) -> Result<Vec<Box<dyn ClipboardBackend>>> {
+    if opts.osc52 {
+        return Ok(vec![Box::new(Osc52)]);
+    }
+    if let Some(list) = std::env::var("PM_CLIPBOARD").ok().filter(|v| !v.is_empty()) {
+        return list
+            .split(',')
+            .map(str::trim)
+            .filter(|n| !n.is_empty())
+            .map(|n| {
+                backend(n).ok_or_else(|| {
+                    anyhow!(
+                        "PM_CLIPBOARD: unknown clipboard backend {n:?} (expected {})",
+                        NAMES.join(", ")
+                    )
+                })
+            })
+            .collect();
     }
+    let mut names = Vec::new();
+    if std::env::var_os("SSH_TTY").is_some() {
+        names.push("osc52");
+    }
+    names.extend(["native", "wl-copy", "xclip", "xsel"]);
+    Ok(names.into_iter().filter_map(backend).collect())
+}
+
+/// Первый подходящий бэкенд — его покажет `pm doctor`.
+pub fn select(opts: CopyOptions) -> Result<Box<dyn ClipboardBackend>> {
+    usable(opts)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| unavailable(opts))
+}
+
+fn usable(opts: CopyOptions) -> Result<Vec<Box<dyn ClipboardBackend>>> {
+    Ok(candidates(opts)?
+        .into_iter()
+        .filter(|b| b.available() && b.supports(opts))
+        .collect())
+}
 
-    // Wayland: сначала пробуем wl-copy
-    if has_wayland {
-        if try_pipe_to("wl-copy", &[], value).is_ok() {
-            return Ok(());
+fn unavailable(opts: CopyOptions) -> anyhow::Error {
+    if opts.osc52 {
+        return anyhow!("--osc52 needs a terminal to write the escape sequence to");
+    }
+    if opts.once || opts.primary {
+        let what = if opts.once { "--once" } else { "--primary" };
+        if candidates(opts).is_ok_and(|c| c.iter().any(|b| b.available())) {
+            return anyhow!("{what} is not supported by the available clipboard tools");
         }
     }
+    anyhow!(
+        "No clipboard available: no DISPLAY or WAYLAND_DISPLAY, or wl-copy/xclip/xsel are not installed.\n\
+         Over SSH try --osc52 (or PM_CLIPBOARD=osc52), or use:\n  \
+         pm show <path> --password-only | xclip -selection clipboard"
+    )
+}
 
-    // X11: пробуем xclip
-    if has_x11 {
-        if try_pipe_to("xclip", &["-selection", "clipboard"], value).is_ok() {
-            return Ok(());
+/// Скопировать `value`; возвращает имя сработавшего бэкенда. Если бэкенд
+/// не справился, пробуется следующий.
+pub fn copy_with(value: &str, opts: CopyOptions) -> Result<&'static str> {
+    let mut last_err = None;
+    for b in usable(opts)? {
+        match b.copy(value, opts) {
+            Ok(()) => {
+                crate::log::info(format_args!("clipboard: copied with {}", b.name()));
+                return Ok(b.name());
+            }
+            Err(e) => {
+                crate::log::info(format_args!("clipboard: {} failed: {e}", b.name()));
+                last_err = Some(e);
+            }
         }
     }
+    Err(last_err.unwrap_or_else(|| unavailable(opts)))
+}
 
-    Err(anyhow!(
-        "Failed to copy to clipboard: wl-copy/xclip not available or failed.\n\
-         Try installing `wl-clipboard` or `xclip`, or use:\n\
-         pm show <path> --password-only | xclip -selection clipboard"
-    ))
+pub fn copy_to_clipboard(value: &str) -> Result<()> {
+    copy_with(value, CopyOptions::default()).map(|_| ())
 }
 
-#[cfg(target_os = "linux")]
-fn try_pipe_to(cmd: &str, args: &[&str], value: &str) -> Result<()> {
+/// Скопировать секрет и стереть его через `clipboard.timeout_secs`.
+pub fn copy_secret(value: &str, opts: CopyOptions) -> Result<()> {
+    let backend = copy_with(value, opts)?;
+    let timeout = crate::config::Config::load()
+        .map(|c| c.settings.clipboard)
+        .unwrap_or_default()
+        .timeout_secs;
+    if let Err(e) = clear_later(value, timeout, backend, opts.primary) {
+        eprintln!("Warning: the clipboard will not be cleared automatically: {e}");
+    }
+    Ok(())
+}
+
+/// Есть ли `program` в `PATH`.
+pub(crate) fn in_path(program: &str) -> bool {
+    std::env::var_os("PATH").is_some_and(|paths| {
+        std::env::split_paths(&paths).any(|dir| {
+            let file = dir.join(program);
+            file.is_file() || (cfg!(windows) && file.with_extension("exe").is_file())
+        })
+    })
+}
+
+pub(crate) fn has_env(name: &str) -> bool {
+    std::env::var_os(name).is_some_and(|v| !v.is_empty())
+}
+
+pub(crate) fn pipe_to(cmd: &str, args: &[&str], value: &str) -> Result<()> {
+    use std::io::Write;
+
     let mut child = Command::new(cmd)
         .args(args)
         .stdin(Stdio::piped())
         .stdout(Stdio::null())
         .stderr(Stdio::null()) // глушим болтовню утилиты
         .spawn()
-        .map_err(|e| anyhow!("failed to spawn {}: {e}", cmd))?;
+        .map_err(|e| anyhow!("failed to spawn {cmd}: {e}"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(value.as_bytes())?;
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(anyhow!("{cmd} exited with status {status}"));
+    }
+    Ok(())
+}
 
-    {
-        use std::io::Write;
-        if let Some(stdin) = child.stdin.as_mut() {
-            stdin.write_all(value.as_bytes())?;
+fn read_from(cmd: &str, args: &[&str]) -> Result<String> {
+    let out = Command::new(cmd)
+        .args(args)
+        .stderr(Stdio::null())
+        .output()
+        .map_err(|e| anyhow!("failed to run {cmd}: {e}"))?;
+    if !out.status.success() {
+        return Err(anyhow!("{cmd} exited with status {}", out.status));
+    }
+    Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+}
+
+struct WlCopy;
+
+impl ClipboardBackend for WlCopy {
+    fn name(&self) -> &'static str {
+        "wl-copy"
+    }
+    fn available(&self) -> bool {
+        has_env("WAYLAND_DISPLAY") && in_path("wl-copy")
+    }
+    fn supports(&self, _opts: CopyOptions) -> bool {
+        true
+    }
+    fn copy(&self, value: &str, opts: CopyOptions) -> Result<()> {
+        let mut args = Vec::new();
+        if opts.once {
+            args.push("--paste-once");
         }
+        if opts.primary {
+            args.push("--primary");
+        }
+        pipe_to("wl-copy", &args, value)
+    }
+    fn paste(&self, primary: bool) -> Result<String> {
+        let mut args = vec!["--no-newline"];
+        if primary {
+            args.push("--primary");
+        }
+        read_from("wl-paste", &args)
+    }
+    fn clear(&self, primary: bool) -> Result<()> {
+        let mut args = vec!["--clear"];
+        if primary {
+            args.push("--primary");
+        }
+        read_from("wl-copy", &args).map(|_| ())
     }
+}
 
-    let status = child.wait()?;
-    if !status.success() {
-        return Err(anyhow!("{} exited with status {}", cmd, status));
+fn x_selection(primary: bool) -> &'static str {
+    if primary { "primary" } else { "clipboard" }
+}
+
+struct Xclip;
+
+impl ClipboardBackend for Xclip {
+    fn name(&self) -> &'static str {
+        "xclip"
+    }
+    fn available(&self) -> bool {
+        has_env("DISPLAY") && in_path("xclip")
+    }
+    fn supports(&self, _opts: CopyOptions) -> bool {
+        true
+    }
+    fn copy(&self, value: &str, opts: CopyOptions) -> Result<()> {
+        let mut args = vec!["-selection", x_selection(opts.primary)];
+        if opts.once {
+            args.extend(["-loops", "1"]);
+        }
+        pipe_to("xclip", &args, value)
     }
+    fn paste(&self, primary: bool) -> Result<String> {
+        read_from("xclip", &["-selection", x_selection(primary), "-o"])
+    }
+    fn clear(&self, primary: bool) -> Result<()> {
+        pipe_to("xclip", &["-selection", x_selection(primary)], "")
+    }
+}
 
-    Ok(())
+struct Xsel;
+
+impl Xsel {
+    fn selection_flag(primary: bool) -> &'static str {
+        if primary { "--primary" } else { "--clipboard" }
+    }
 }
 
-/// Не-Linux (Windows/macOS и прочие): используем copypasta.
-#[cfg(not(target_os = "linux"))]
-pub fn copy_to_clipboard(value: &str) -> Result<()> {
-    let mut ctx =
-        ClipboardContext::new().map_err(|e| anyhow!("Failed to initialize clipboard: {e}"))?;
+impl ClipboardBackend for Xsel {
+    fn name(&self) -> &'static str {
+        "xsel"
+    }
+    fn available(&self) -> bool {
+        has_env("DISPLAY") && in_path("xsel")
+    }
+    fn supports(&self, opts: CopyOptions) -> bool {
+        !opts.once
+    }
+    fn copy(&self, value: &str, opts: CopyOptions) -> Result<()> {
+        pipe_to(
+            "xsel",
+            &[Self::selection_flag(opts.primary), "--input"],
+            value,
+        )
+    }
+    fn paste(&self, primary: bool) -> Result<String> {
+        read_from("xsel", &[Self::selection_flag(primary), "--output"])
+    }
+    fn clear(&self, primary: bool) -> Result<()> {
+        read_from("xsel", &[Self::selection_flag(primary), "--clear"]).map(|_| ())
+    }
+}
+
+/// Системный буфер macOS и Windows: содержимое живёт и после выхода pm.
+struct Native;
+
+impl Native {
+    fn context() -> Result<ClipboardContext> {
+        ClipboardContext::new().map_err(|e| anyhow!("Failed to initialize clipboard: {e}"))
+    }
+}
+
+impl ClipboardBackend for Native {
+    fn name(&self) -> &'static str {
+        "native"
+    }
+    fn available(&self) -> bool {
+        // На Linux и BSD это X11-буфер copypasta: он пропадает вместе с процессом
+        cfg!(any(target_os = "macos", windows))
+    }
+    fn supports(&self, opts: CopyOptions) -> bool {
+        !opts.once && !opts.primary
+    }
+    fn copy(&self, value: &str, _opts: CopyOptions) -> Result<()> {
+        Self::context()?
+            .set_contents(value.to_string())
+            .map_err(|e| anyhow!("Failed to copy to clipboard: {e}"))
+    }
+    fn paste(&self, _primary: bool) -> Result<String> {
+        Self::context()?
+            .get_contents()
+            .map_err(|e| anyhow!("Failed to read the clipboard: {e}"))
+    }
+    fn clear(&self, _primary: bool) -> Result<()> {
+        self.copy("", CopyOptions::default())
+    }
+}
+
+/// OSC 52: терминал сам кладёт данные в буфер своей машины.
+struct Osc52;
+
+impl Osc52 {
+    fn sequence(value: &str, primary: bool) -> String {
+        use base64::{Engine as _, engine::general_purpose};
+
+        let target = if primary { "p" } else { "c" };
+        let seq = format!(
+            "\x1b]52;{target};{}\x07",
+            general_purpose::STANDARD.encode(value)
+        );
+        // tmux пропускает последовательность наружу только в такой обёртке
+        if has_env("TMUX") {
+            format!("\x1bPtmux;{}\x1b\\", seq.replace('\x1b', "\x1b\x1b"))
+        } else {
+            seq
+        }
+    }
+
+    #[cfg(unix)]
+    fn terminal() -> std::io::Result<std::fs::File> {
+        std::fs::OpenOptions::new().write(true).open("/dev/tty")
+    }
+}
+
+impl ClipboardBackend for Osc52 {
+    fn name(&self) -> &'static str {
+        "osc52"
+    }
+    fn available(&self) -> bool {
+        #[cfg(unix)]
+        {
+            Self::terminal().is_ok()
+        }
+        #[cfg(not(unix))]
+        {
+            use std::io::IsTerminal;
+            std::io::stderr().is_terminal()
+        }
+    }
+    fn supports(&self, opts: CopyOptions) -> bool {
+        !opts.once
+    }
+    fn copy(&self, value: &str, opts: CopyOptions) -> Result<()> {
+        use std::io::Write;
+
+        let seq = Self::sequence(value, opts.primary);
+        #[cfg(unix)]
+        Self::terminal()?.write_all(seq.as_bytes())?;
+        #[cfg(not(unix))]
+        std::io::stderr().write_all(seq.as_bytes())?;
+        Ok(())
+    }
+    fn paste(&self, _primary: bool) -> Result<String> {
+        Err(anyhow!("OSC 52 cannot read the clipboard back"))
+    }
+    fn clear(&self, primary: bool) -> Result<()> {
+        self.copy(
+            "",
+            CopyOptions {
+                primary,
+                ..CopyOptions::default()
+            },
+        )
+    }
+}
+
+fn digest_hex(value: &str) -> String {
+    use sha2::{Digest, Sha256};
+    Sha256::digest(value.as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
 
-    ctx.set_contents(value.to_string())
-        .map_err(|e| anyhow!("Failed to copy to clipboard: {e}"))?;
+/// Очистить буфер обмена через `secs` секунд, если в нём всё ещё `value`.
+/// Ждёт отдельный процесс (`pm clipboard-clear`), чтобы команда завершилась
+/// сразу; ему передаётся только хэш значения, и через stdin, а не аргументом.
+/// `backend` — тот, которым копировали: очищать нужно тот же буфер.
+pub fn clear_later(value: &str, secs: u64, backend: &str, primary: bool) -> Result<()> {
+    use std::io::Write;
 
+    if secs == 0 {
+        return Ok(());
+    }
+    if backend == "osc52" {
+        return Err(anyhow!("OSC 52 cannot read the clipboard back"));
+    }
+    let mut cmd = Command::new(std::env::current_exe()?);
+    cmd.args(["clipboard-clear", "--after", &secs.to_string()])
+        .args(["--backend", backend])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    if primary {
+        cmd.arg("--primary");
+    }
+    // Своя группа процессов: Ctrl-C в терминале не должен отменить очистку
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut cmd, 0);
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| anyhow!("failed to spawn the clipboard cleaner: {e}"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(digest_hex(value).as_bytes())?;
+    }
+    Ok(())
+}
+
+/// `pm clipboard-clear`: подождать и стереть буфер, если в нём то, что
+/// скопировал pm (хэш приходит в stdin). Новое содержимое не трогаем.
+pub fn clear_if_unchanged(secs: u64, backend: Option<&str>, primary: bool) -> Result<()> {
+    use std::io::Read;
+
+    let backend = match backend {
+        Some(name) => self::backend(name).ok_or_else(|| anyhow!("unknown backend {name}"))?,
+        None => select(CopyOptions::default())?,
+    };
+    let mut expected = String::new();
+    std::io::stdin().read_to_string(&mut expected)?;
+    std::thread::sleep(std::time::Duration::from_secs(secs));
+    if digest_hex(&backend.paste(primary)?) == expected.trim() {
+        backend.clear(primary)?;
+    }
     Ok(())
 }
@@ -0,0 +1,389 @@
+//! Скрипты автодополнения для `pm completions <shell>`.
+//!
+//! Скрипт строится по дереву команд clap: подкоманды и флаги — статически,
+//! а пути записей — динамически через скрытую `pm __complete-paths PREFIX`.
+//! Она только перечисляет файлы хранилища: без расшифровки и без пароля.
+//! Пути дополняются по уровням, как в `pass`: `wo` → `work/` → `work/github`.
+
+use crate::store::list_entries;
+use anyhow::Result;
+use clap::Command;
+use std::collections::BTreeSet;
+use std::fmt::Write as _;
+use std::io::Write as _;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Powershell,
+}
+
+/// Позиционные аргументы, в которых ожидается путь записи.
+const ENTRY_ARGS: &[&str] = &["path", "src", "dst", "prefix"];
+
+/// Команда (или подкоманда) в том виде, в каком она нужна скриптам.
+struct Spec {
+    /// Все написания пути до команды, включая алиасы: `otp add`, ...
+    keys: Vec<String>,
+    /// `--long` и `-s` флаги
+    opts: Vec<String>,
+    /// Дополнять ли пути записей
+    paths: bool,
+    /// Видимые подкоманды (имя, описание)
+    subs: Vec<(String, String)>,
+}
+
+fn visible_subcommands(cmd: &Command) -> impl Iterator<Item = &Command> {
+    cmd.get_subcommands()
+        .filter(|c| !c.is_hide_set() && c.get_name() != "help")
+}
+
+fn about(cmd: &Command) -> String {
+    cmd.get_about()
+        .map(|a| a.to_string())
+        .unwrap_or_default()
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn spec(cmd: &Command, keys: Vec<String>) -> Spec {
+    let mut opts = Vec::new();
+    for arg in cmd.get_arguments().filter(|a| !a.is_hide_set()) {
+        if let Some(long) = arg.get_long() {
+            opts.push(format!("--{long}"));
+        }
+        if let Some(short) = arg.get_short() {
+            opts.push(format!("-{short}"));
+        }
+    }
+    opts.push("--help".to_string());
+    Spec {
+        keys,
+        opts,
+        paths: cmd
+            .get_positionals()
+            .any(|a| ENTRY_ARGS.contains(&a.get_id().as_str())),
+        subs: visible_subcommands(cmd)
+            .map(|c| (c.get_name().to_string(), about(c)))
+            .collect(),
+    }
+}
+
+/// Все команды дерева, корень — с пустым ключом.
+fn collect(cmd: &Command, keys: Vec<String>, out: &mut Vec<Spec>) {
+    out.push(spec(cmd, keys.clone()));
+    for sub in visible_subcommands(cmd) {
+        let names: Vec<&str> = std::iter::once(sub.get_name())
+            .chain(sub.get_all_aliases())
+            .collect();
+        let sub_keys = keys
+            .iter()
+            .flat_map(|k| {
+                names.iter().map(move |n| match k.as_str() {
+                    "" => n.to_string(),
+                    k => format!("{k} {n}"),
+                })
+            })
+            .collect();
+        collect(sub, sub_keys, out);
+    }
+}
+
+/// pm completions SHELL
+pub fn completions(shell: Shell, cmd: &Command) -> Result<()> {
+    let bin = cmd.get_name().to_string();
+    let mut specs = Vec::new();
+    collect(cmd, vec![String::new()], &mut specs);
+    let script = match shell {
+        Shell::Bash => bash(&bin, &specs)?,
+        Shell::Zsh => zsh(&bin, &specs)?,
+        Shell::Fish => fish(&bin, &specs)?,
+        Shell::Powershell => powershell(&bin, &specs)?,
+    };
+    print!("{script}");
+    Ok(())
+}
+
+/// pm __complete-paths [PREFIX] [-0]: записи и папки следующего уровня под PREFIX.
+pub fn complete_paths(prefix: &str, print0: bool) -> Result<()> {
+    // Нет хранилища — нечего предлагать, но и ругаться в автодополнении незачем
+    let entries = list_entries().unwrap_or_default();
+    let mut out = BTreeSet::new();
+    for entry in entries.iter().filter(|e| e.starts_with(prefix)) {
+        match entry[prefix.len()..].find('/') {
+            Some(i) => out.insert(entry[..prefix.len() + i + 1].to_string()),
+            None => out.insert(entry.clone()),
+        };
+    }
+    let sep = if print0 { '\0' } else { '\n' };
+    let mut stdout = std::io::stdout().lock();
+    for candidate in out {
+        write!(stdout, "{candidate}{sep}")?;
+    }
+    Ok(())
+}
+
+fn quote_keys(keys: &[String]) -> String {
+    keys.iter()
+        .map(|k| format!("\"{k}\""))
+        .collect::<Vec<_>>()
+        .join("|")
+}
+
+fn bash(bin: &str, specs: &[Spec]) -> Result<String> {
+    let mut s = String::new();
+    let nested: Vec<String> = specs
+        .iter()
+        .filter(|sp| !sp.subs.is_empty() && !sp.keys[0].is_empty())
+        .flat_map(|sp| sp.keys.clone())
+        .collect();
+    writeln!(s, "_{bin}_nested() {{")?;
+    writeln!(s, "    case \"$1\" in")?;
+    writeln!(s, "        {}) return 0 ;;", quote_keys(&nested))?;
+    writeln!(s, "    esac")?;
+    writeln!(s, "    return 1")?;
+    writeln!(s, "}}\n")?;
+
+    writeln!(s, "_{bin}() {{")?;
+    writeln!(
+        s,
+        "    local cur=${{COMP_WORDS[COMP_CWORD]}} key=\"\" opts=\"\" subs=\"\" paths=\"\" i w"
+    )?;
+    writeln!(s, "    for ((i = 1; i < COMP_CWORD; i++)); do")?;
+    writeln!(s, "        w=${{COMP_WORDS[i]}}")?;
+    writeln!(s, "        [[ $w == -* ]] && continue")?;
+    writeln!(
+        s,
+        "        if [[ -z $key ]] || _{bin}_nested \"$key\"; then key=\"${{key:+$key }}$w\"; else break; fi"
+    )?;
+    writeln!(s, "    done")?;
+    writeln!(s, "    case \"$key\" in")?;
+    for sp in specs {
+        writeln!(s, "        {})", quote_keys(&sp.keys))?;
+        writeln!(s, "            opts=\"{}\"", sp.opts.join(" "))?;
+        if !sp.subs.is_empty() {
+            let subs: Vec<&str> = sp.subs.iter().map(|(n, _)| n.as_str()).collect();
+            writeln!(s, "            subs=\"{}\"", subs.join(" "))?;
+        }
+        if sp.paths {
+            writeln!(s, "            paths=1")?;
+        }
+        writeln!(s, "            ;;")?;
+    }
+    writeln!(s, "    esac")?;
+    writeln!(s, "    if [[ $cur == -* ]]; then")?;
+    writeln!(
+        s,
+        "        mapfile -t COMPREPLY < <(compgen -W \"$opts\" -- \"$cur\")"
+    )?;
+    writeln!(s, "    elif [[ -n $subs ]]; then")?;
+    writeln!(
+        s,
+        "        mapfile -t COMPREPLY < <(compgen -W \"$subs\" -- \"$cur\")"
+    )?;
+    writeln!(s, "    elif [[ -n $paths ]]; then")?;
+    writeln!(
+        s,
+        "        mapfile -t COMPREPLY < <(\"${{COMP_WORDS[0]}}\" __complete-paths \"$cur\" 2>/dev/null)"
+    )?;
+    writeln!(
+        s,
+        "        [[ ${{#COMPREPLY[@]}} -eq 1 && ${{COMPREPLY[0]}} == */ ]] && compopt -o nospace"
+    )?;
+    writeln!(s, "    fi")?;
+    writeln!(s, "}}\n")?;
+    writeln!(s, "complete -o default -F _{bin} {bin}")?;
+    Ok(s)
+}
+
+fn zsh(bin: &str, specs: &[Spec]) -> Result<String> {
+    let mut s = String::new();
+    writeln!(s, "#compdef {bin}\n")?;
+    writeln!(s, "_{bin}() {{")?;
+    writeln!(
+        s,
+        "    local cur=${{words[CURRENT]}} key=\"\" opts=\"\" subs=\"\" paths=\"\" nested=0 i w"
+    )?;
+    writeln!(s, "    local -a cands")?;
+    writeln!(s, "    for ((i = 2; i < CURRENT; i++)); do")?;
+    writeln!(s, "        w=${{words[i]}}")?;
+    writeln!(s, "        [[ $w == -* ]] && continue")?;
+    writeln!(s, "        nested=0")?;
+    writeln!(s, "        case \"$key\" in")?;
+    let nested: Vec<String> = specs
+        .iter()
+        .filter(|sp| !sp.subs.is_empty())
+        .flat_map(|sp| sp.keys.clone())
+        .collect();
+    writeln!(s, "            {}) nested=1 ;;", quote_keys(&nested))?;
+    writeln!(s, "        esac")?;
+    writeln!(
+        s,
+        "        if (( nested )); then key=\"${{key:+$key }}$w\"; else break; fi"
+    )?;
+    writeln!(s, "    done")?;
+    writeln!(s, "    case \"$key\" in")?;
+    for sp in specs {
+        writeln!(s, "        {})", quote_keys(&sp.keys))?;
+        writeln!(s, "            opts=\"{}\"", sp.opts.join(" "))?;
+        if !sp.subs.is_empty() {
+            let subs: Vec<&str> = sp.subs.iter().map(|(n, _)| n.as_str()).collect();
+            writeln!(s, "            subs=\"{}\"", subs.join(" "))?;
+        }
+        if sp.paths {
+            writeln!(s, "            paths=1")?;
+        }
+        writeln!(s, "            ;;")?;
+    }
+    writeln!(s, "    esac")?;
+    writeln!(s, "    if [[ $cur == -* ]]; then")?;
+    writeln!(s, "        compadd -- ${{=opts}}")?;
+    writeln!(s, "    elif [[ -n $subs ]]; then")?;
+    writeln!(s, "        compadd -- ${{=subs}}")?;
+    writeln!(s, "    elif [[ -n $paths ]]; then")?;
+    writeln!(
+        s,
+        "        cands=(\"${{(@f)$(${{words[1]}} __complete-paths \"$cur\" 2>/dev/null)}}\")"
+    )?;
+    writeln!(s, "        compadd -S '' -- ${{(M)cands:#*/}}")?;
+    writeln!(s, "        compadd -- ${{cands:#*/}}")?;
+    writeln!(s, "    else")?;
+    writeln!(s, "        _files")?;
+    writeln!(s, "    fi")?;
+    writeln!(s, "}}\n")?;
+    writeln!(s, "if [[ $funcstack[1] == _{bin} ]]; then")?;
+    writeln!(s, "    _{bin} \"$@\"")?;
+    writeln!(s, "else")?;
+    writeln!(s, "    compdef _{bin} {bin}")?;
+    writeln!(s, "fi")?;
+    Ok(s)
+}
+
+fn fish_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('\'', "\\'")
+}
+
+fn fish(bin: &str, specs: &[Spec]) -> Result<String> {
+    let mut s = String::new();
+    // Ключ команды — это просто последовательность слов; fish проверяет её
+    // своей функцией, чтобы флаги между словами не мешали
+    writeln!(s, "function __{bin}_key")?;
+    writeln!(s, "    set -l words (commandline -opc)[2..-1]")?;
+    writeln!(s, "    string join ' ' -- (string match -v -- '-*' $words)")?;
+    writeln!(s, "end\n")?;
+    writeln!(s, "function __{bin}_at")?;
+    writeln!(s, "    set -l key (__{bin}_key)")?;
+    writeln!(s, "    contains -- \"$key\" $argv")?;
+    writeln!(s, "end\n")?;
+    writeln!(s, "function __{bin}_after")?;
+    writeln!(s, "    set -l key (__{bin}_key)")?;
+    writeln!(s, "    for k in $argv")?;
+    writeln!(
+        s,
+        "        if test \"$key\" = \"$k\"; or string match -q -- \"$k *\" \"$key\"; return 0; end"
+    )?;
+    writeln!(s, "    end")?;
+    writeln!(s, "    return 1")?;
+    writeln!(s, "end\n")?;
+
+    let quoted = |keys: &[String]| {
+        keys.iter()
+            .map(|k| format!("'{}'", fish_escape(k)))
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    for sp in specs {
+        let keys = quoted(&sp.keys);
+        for (name, about) in &sp.subs {
+            writeln!(
+                s,
+                "complete -c {bin} -f -n \"__{bin}_at {keys}\" -a '{}' -d '{}'",
+                fish_escape(name),
+                fish_escape(about)
+            )?;
+        }
+        for opt in &sp.opts {
+            let flag = match opt.strip_prefix("--") {
+                Some(long) => format!("-l {long}"),
+                None => format!("-s {}", &opt[1..]),
+            };
+            writeln!(s, "complete -c {bin} -n \"__{bin}_after {keys}\" {flag}")?;
+        }
+        if sp.paths {
+            writeln!(
+                s,
+                "complete -c {bin} -f -n \"__{bin}_after {keys}\" -a \"({bin} __complete-paths (commandline -ct) 2>/dev/null)\""
+            )?;
+        }
+    }
+    Ok(s)
+}
+
+fn powershell(bin: &str, specs: &[Spec]) -> Result<String> {
+    let mut s = String::new();
+    writeln!(s, "$__{bin}Specs = @{{")?;
+    for sp in specs {
+        let subs: Vec<&str> = sp.subs.iter().map(|(n, _)| n.as_str()).collect();
+        for key in &sp.keys {
+            writeln!(
+                s,
+                "    '{key}' = @{{ Opts = '{}'; Subs = '{}'; Paths = ${} }}",
+                sp.opts.join(" "),
+                subs.join(" "),
+                sp.paths
+            )?;
+        }
+    }
+    writeln!(s, "}}\n")?;
+    writeln!(
+        s,
+        "Register-ArgumentCompleter -Native -CommandName {bin} -ScriptBlock {{"
+    )?;
+    writeln!(
+        s,
+        "    param($wordToComplete, $commandAst, $cursorPosition)"
+    )?;
+    writeln!(s, "    $key = ''")?;
+    writeln!(
+        s,
+        "    foreach ($el in $commandAst.CommandElements | Select-Object -Skip 1) {{"
+    )?;
+    writeln!(s, "        $w = \"$el\"")?;
+    writeln!(
+        s,
+        "        if ($el.Extent.StartOffset -ge $cursorPosition -or $w -eq $wordToComplete) {{ break }}"
+    )?;
+    writeln!(s, "        if ($w.StartsWith('-')) {{ continue }}")?;
+    writeln!(
+        s,
+        "        if ($key -eq '' -or $__{bin}Specs[$key].Subs) {{ $key = ($key + ' ' + $w).Trim() }} else {{ break }}"
+    )?;
+    writeln!(s, "    }}")?;
+    writeln!(s, "    $spec = $__{bin}Specs[$key]")?;
+    writeln!(s, "    if (-not $spec) {{ return }}")?;
+    writeln!(s, "    if ($wordToComplete.StartsWith('-')) {{")?;
+    writeln!(s, "        $cands = $spec.Opts -split ' '")?;
+    writeln!(s, "    }} elseif ($spec.Subs) {{")?;
+    writeln!(s, "        $cands = $spec.Subs -split ' '")?;
+    writeln!(s, "    }} elseif ($spec.Paths) {{")?;
+    writeln!(
+        s,
+        "        $cands = & {bin} __complete-paths $wordToComplete 2>$null"
+    )?;
+    writeln!(s, "    }} else {{ return }}")?;
+    writeln!(
+        s,
+        "    $cands | Where-Object {{ $_ -like \"$wordToComplete*\" }} | ForEach-Object {{"
+    )?;
+    writeln!(
+        s,
+        "        [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)"
+    )?;
+    writeln!(s, "    }}")?;
+    writeln!(s, "}}")?;
+    Ok(s)
+}
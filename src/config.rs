@@ -1,6 +1,7 @@
 use crate::store::store_root;
+use anyhow::anyhow;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct KdfParams {
@@ -9,6 +10,9 @@ pub struct KdfParams {
     pub iterations: u32,
     pub parallelism: u32,
     pub salt: String, // base64
+    /// В KEK подмешан ключевой файл (см. `keyfile`); сам файл здесь не упоминается
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub keyfile: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -18,19 +22,296 @@ pub struct EncConfig {
     pub encrypted_master_key: String, // base64
 }
 
+/// Текущая версия формата config.json.
+pub const CONFIG_VERSION: u32 = 1;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Config {
     pub version: u32,
     pub kdf: KdfParams,
     pub enc: EncConfig,
+    #[serde(default)]
+    pub settings: Settings,
+}
+
+/// Пользовательские настройки. Все поля необязательны, чтобы старые
+/// config.json без этой секции продолжали читаться.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Settings {
+    pub warnings: WarningSettings,
+    pub display: DisplaySettings,
+    pub serve: ServeSettings,
+    pub history: HistorySettings,
+    pub hooks: HookSettings,
+    pub unlock: UnlockSettings,
+    pub session: SessionSettings,
+    pub git: GitSettings,
+    pub clipboard: ClipboardSettings,
+    pub generate: GenerateSettings,
+    pub audit: AuditSettings,
+    pub backup: BackupSettings,
+    pub attachments: AttachmentSettings,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct WarningSettings {
+    /// Идентификаторы предупреждений, которые не нужно показывать
+    /// (например, "secret-redirect")
+    pub suppress: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct DisplaySettings {
+    /// Когда печатать пароль только с `--reveal`: `true` — и в `pm show`, и в
+    /// `--json`; не задано — только в `pm show`; `false` — никогда
+    pub conceal_password: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct HistorySettings {
+    /// Вести зашифрованный журнал обращений для `pm recent`
+    pub enabled: bool,
+}
+
+impl Default for HistorySettings {
+    fn default() -> Self {
+        HistorySettings { enabled: true }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct UnlockSettings {
+    /// Брать мастер-пароль из системной связки ключей (`pm keychain store`)
+    pub keychain: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct SessionSettings {
+    /// Сколько секунд держать мастер-ключ в кэше; 0 — не кэшировать вовсе
+    pub ttl_secs: Option<u64>,
+    /// Жёсткий предел с момента ввода пароля, как бы активно ни пользовались
+    pub max_secs: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct GitSettings {
+    /// Коммитить каждое изменение записи, если хранилище — git-репозиторий
+    pub auto_commit: bool,
+    /// Путь записи для `pm git-credential`; `None` — `git/{host}`
+    pub credential_template: Option<String>,
+}
+
+impl Default for GitSettings {
+    fn default() -> Self {
+        GitSettings {
+            auto_commit: true,
+            credential_template: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ClipboardSettings {
+    /// Через сколько секунд стереть скопированное из буфера обмена; 0 — не стирать
+    pub timeout_secs: u64,
+}
+
+impl Default for ClipboardSettings {
+    fn default() -> Self {
+        ClipboardSettings { timeout_secs: 45 }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct GenerateSettings {
+    /// Длина пароля для `pm generate` и `pm add --generate` без явной длины
+    pub length: usize,
+    /// Включать символы (флаг `--no-symbols` всё равно их убирает)
+    pub symbols: bool,
+}
+
+impl Default for GenerateSettings {
+    fn default() -> Self {
+        GenerateSettings {
+            length: 24,
+            symbols: true,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct AuditSettings {
+    /// `pm audit` отмечает пароли короче этого
+    pub min_length: usize,
+    /// ...и записи, не менявшиеся дольше стольких дней (0 — не проверять)
+    pub max_age_days: u64,
+}
+
+impl Default for AuditSettings {
+    fn default() -> Self {
+        AuditSettings {
+            min_length: 12,
+            max_age_days: 365,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct BackupSettings {
+    /// Каталог для `pm backup auto`; относительный путь — от каталога, где
+    /// лежит хранилище. `None` — `pm-backups` рядом с хранилищем
+    pub dir: Option<String>,
+    /// Сколько последних дней, недель и месяцев хранить по одному архиву
+    pub keep_daily: u64,
+    pub keep_weekly: u64,
+    pub keep_monthly: u64,
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        BackupSettings {
+            dir: None,
+            keep_daily: 7,
+            keep_weekly: 4,
+            keep_monthly: 12,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct AttachmentSettings {
+    /// Наибольший размер одного вложения в байтах
+    pub max_bytes: u64,
+}
+
+impl Default for AttachmentSettings {
+    fn default() -> Self {
+        AttachmentSettings {
+            max_bytes: 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct HookSettings {
+    /// Исполняемые файлы, запускаемые после изменения хранилища
+    pub post_add: Option<String>,
+    pub post_edit: Option<String>,
+    pub post_rm: Option<String>,
+    pub post_any: Option<String>,
+    /// Через сколько секунд зависший хук принудительно завершается
+    pub timeout_secs: u64,
+}
+
+impl Default for HookSettings {
+    fn default() -> Self {
+        HookSettings {
+            post_add: None,
+            post_edit: None,
+            post_rm: None,
+            post_any: None,
+            timeout_secs: 10,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct ServeSettings {
+    /// Токены для `pm serve`; сами токены не хранятся, только их SHA-256
+    pub tokens: Vec<ApiToken>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ApiToken {
+    pub id: String,   // первые 8 hex-символов хеша, для list/revoke
+    pub hash: String, // sha256 hex
+    pub scopes: Vec<String>,
+    pub created_at: String,
+}
+
+impl Settings {
+    /// Скрывать ли пароль в выводе для скриптов (`pm show --json`): по
+    /// умолчанию нет. Приоритет: флаг `--reveal` > переменная `PM_CONCEAL` >
+    /// настройки > по умолчанию.
+    pub fn conceal_password(&self, reveal_flag: bool) -> bool {
+        resolve_conceal(
+            reveal_flag,
+            std::env::var("PM_CONCEAL").ok().as_deref(),
+            self.display.conceal_password == Some(true),
+        )
+    }
+
+    /// Скрывать ли пароль в выводе `pm show` для человека: по умолчанию да.
+    /// Приоритет тот же.
+    pub fn mask_password_in_show(&self, reveal_flag: bool) -> bool {
+        resolve_conceal(
+            reveal_flag,
+            std::env::var("PM_CONCEAL").ok().as_deref(),
+            self.display.conceal_password != Some(false),
+        )
+    }
+}
+
+fn resolve_conceal(reveal_flag: bool, env: Option<&str>, setting: bool) -> bool {
+    if reveal_flag {
+        return false;
+    }
+    match env.map(|v| v.trim().to_lowercase()).as_deref() {
+        Some("1" | "true" | "yes" | "on") => true,
+        Some("0" | "false" | "no" | "off") => false,
+        _ => setting,
+    }
 }
 
 impl Config {
     pub fn load() -> anyhow::Result<Self> {
-        let path = config_path()?;
-        let data = std::fs::read_to_string(&path)?;
-        let cfg: Config = serde_json::from_str(&data)?;
-        Ok(cfg)
+        Self::load_from(&config_path()?)
+    }
+
+    pub fn load_from(path: &Path) -> anyhow::Result<Self> {
+        crate::log::debug(format_args!("reading {}", path.display()));
+        let data = std::fs::read_to_string(path)?;
+        let mut cfg: serde_json::Value = serde_json::from_str(&data)?;
+        migrate_legacy_settings(&mut cfg);
+        Ok(serde_json::from_value(cfg)?)
+    }
+}
+
+/// `settings.ui.show_reveals_by_default = true` прежних версий — то же, что
+/// `display.conceal_password = false`. Секция `ui` при следующей записи уйдёт.
+fn migrate_legacy_settings(config: &mut serde_json::Value) {
+    use serde_json::Value;
+
+    let Some(settings) = config.get_mut("settings").and_then(Value::as_object_mut) else {
+        return;
+    };
+    let Some(ui) = settings.remove("ui") else {
+        return;
+    };
+    if ui.get("show_reveals_by_default") != Some(&Value::Bool(true)) {
+        return;
+    }
+    let display = settings
+        .entry("display")
+        .or_insert_with(|| serde_json::json!({}));
+    if let Some(display) = display.as_object_mut()
+        && display.get("conceal_password").is_none_or(Value::is_null)
+    {
+        display.insert("conceal_password".into(), Value::Bool(false));
     }
 }
 
@@ -40,8 +321,349 @@ pub fn config_path() -> anyhow::Result<PathBuf> {
     Ok(root.join("config.json"))
 }
 
-pub fn save_config(cfg: &Config, path: &PathBuf) -> anyhow::Result<()> {
+/// Записать конфиг атомарно: от него зависит доступ ко всем записям.
+pub fn save_config(cfg: &Config, path: &Path) -> anyhow::Result<()> {
     let s = serde_json::to_string_pretty(cfg)?;
-    std::fs::write(path, s)?;
+    crate::store::write_atomic_private(path, s.as_bytes())
+}
+
+/// Тип значения настройки: по нему проверяется ввод `pm config set`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SettingKind {
+    Bool,
+    /// `true`, `false` или `default`
+    OptionalBool,
+    Integer,
+    /// Число или `default` (сбросить к встроенному значению)
+    OptionalInteger,
+    /// Строка или `none`
+    OptionalString,
+    /// Список через запятую из допустимых значений; `none` — пустой
+    List(&'static [&'static str]),
+}
+
+/// Настройки, доступные через `pm config get/set/list`. Ключ — путь в
+/// секции `settings` config.json через точку.
+const SETTING_KEYS: &[(&str, SettingKind, &str)] = &[
+    (
+        "clipboard.timeout_secs",
+        SettingKind::Integer,
+        "clear the clipboard after this many seconds (0 = never)",
+    ),
+    (
+        "generate.length",
+        SettingKind::Integer,
+        "default length for pm generate and pm add --generate",
+    ),
+    (
+        "generate.symbols",
+        SettingKind::Bool,
+        "include symbols in generated passwords",
+    ),
+    (
+        "audit.min_length",
+        SettingKind::Integer,
+        "pm audit flags passwords shorter than this",
+    ),
+    (
+        "audit.max_age_days",
+        SettingKind::Integer,
+        "pm audit flags entries not updated for this many days (0 = never)",
+    ),
+    (
+        "session.ttl_secs",
+        SettingKind::OptionalInteger,
+        "cache the master key this long after the last use (0 = never)",
+    ),
+    (
+        "session.max_secs",
+        SettingKind::OptionalInteger,
+        "ask for the password again this long after unlocking, however busy",
+    ),
+    (
+        "display.conceal_password",
+        SettingKind::OptionalBool,
+        "need --reveal for the password: true = in show and --json, default = in show, false = never",
+    ),
+    (
+        "git.auto_commit",
+        SettingKind::Bool,
+        "commit every change when the store is a git repository",
+    ),
+    (
+        "git.credential_template",
+        SettingKind::OptionalString,
+        "entry path for pm git-credential: {protocol}, {host}, {path}, {username}",
+    ),
+    (
+        "backup.dir",
+        SettingKind::OptionalString,
+        "where pm backup auto writes archives (default: pm-backups next to the store)",
+    ),
+    (
+        "backup.keep_daily",
+        SettingKind::Integer,
+        "pm backup auto keeps the newest archive of this many days",
+    ),
+    (
+        "backup.keep_weekly",
+        SettingKind::Integer,
+        "...and of this many ISO weeks",
+    ),
+    (
+        "backup.keep_monthly",
+        SettingKind::Integer,
+        "...and of this many months",
+    ),
+    (
+        "attachments.max_bytes",
+        SettingKind::Integer,
+        "largest file pm attach add accepts, in bytes",
+    ),
+    (
+        "warnings.suppress",
+        SettingKind::List(crate::envcheck::WARNING_IDS),
+        "warnings not to print, comma-separated (see pm doctor)",
+    ),
+    (
+        "history.enabled",
+        SettingKind::Bool,
+        "keep the encrypted access log for pm recent",
+    ),
+    (
+        "hooks.timeout_secs",
+        SettingKind::Integer,
+        "kill a hook after this many seconds",
+    ),
+    (
+        "hooks.post_add",
+        SettingKind::OptionalString,
+        "program to run after pm add",
+    ),
+    (
+        "hooks.post_edit",
+        SettingKind::OptionalString,
+        "program to run after an entry changes",
+    ),
+    (
+        "hooks.post_rm",
+        SettingKind::OptionalString,
+        "program to run after pm rm",
+    ),
+    (
+        "hooks.post_any",
+        SettingKind::OptionalString,
+        "program to run after any change",
+    ),
+];
+
+/// Прежние имена ключей `pm config set`.
+const SETTING_ALIASES: &[(&str, &str)] = &[
+    ("session-ttl", "session.ttl_secs"),
+    ("session-max", "session.max_secs"),
+    ("git-auto-commit", "git.auto_commit"),
+];
+
+fn setting_spec(key: &str) -> anyhow::Result<(&'static str, SettingKind)> {
+    let key = SETTING_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == key)
+        .map_or(key, |(_, canonical)| canonical);
+    SETTING_KEYS
+        .iter()
+        .find(|(k, _, _)| *k == key)
+        .map(|(k, kind, _)| (*k, *kind))
+        .ok_or_else(|| anyhow!("unknown setting {key:?}; `pm config list` shows the known ones"))
+}
+
+/// Разобрать значение по типу настройки.
+fn parse_setting(key: &str, kind: SettingKind, raw: &str) -> anyhow::Result<serde_json::Value> {
+    use serde_json::Value;
+
+    let integer = |raw: &str| {
+        raw.parse::<u64>()
+            .map(Value::from)
+            .map_err(|_| anyhow!("{key}: expected integer, got {raw:?}"))
+    };
+    let boolean = |raw: &str| match raw {
+        "true" | "on" | "yes" => Some(Value::Bool(true)),
+        "false" | "off" | "no" => Some(Value::Bool(false)),
+        _ => None,
+    };
+    match kind {
+        SettingKind::Bool => {
+            boolean(raw).ok_or_else(|| anyhow!("{key}: expected true or false, got {raw:?}"))
+        }
+        SettingKind::OptionalBool => match raw {
+            "default" | "none" => Ok(Value::Null),
+            _ => boolean(raw)
+                .ok_or_else(|| anyhow!("{key}: expected true, false or \"default\", got {raw:?}")),
+        },
+        SettingKind::Integer => integer(raw),
+        SettingKind::OptionalInteger => match raw {
+            "default" | "none" => Ok(Value::Null),
+            _ => integer(raw)
+                .map_err(|_| anyhow!("{key}: expected integer or \"default\", got {raw:?}")),
+        },
+        SettingKind::OptionalString => match raw {
+            "" | "none" => Ok(Value::Null),
+            _ => Ok(Value::from(raw)),
+        },
+        SettingKind::List(allowed) => {
+            let mut items = Vec::new();
+            for item in raw.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+                if item == "none" {
+                    continue;
+                }
+                if !allowed.contains(&item) {
+                    return Err(anyhow!(
+                        "{key}: unknown value {item:?} (expected {})",
+                        allowed.join(", ")
+                    ));
+                }
+                if !items.contains(&item) {
+                    items.push(item);
+                }
+            }
+            Ok(Value::from(items))
+        }
+    }
+}
+
+fn setting_pointer(key: &str) -> String {
+    format!("/{}", key.replace('.', "/"))
+}
+
+/// Значение настройки как JSON (`null` — не задано).
+pub fn setting_value(settings: &Settings, key: &str) -> anyhow::Result<serde_json::Value> {
+    let (key, _) = setting_spec(key)?;
+    let all = serde_json::to_value(settings)?;
+    Ok(all
+        .pointer(&setting_pointer(key))
+        .cloned()
+        .unwrap_or_default())
+}
+
+/// Изменить настройку с проверкой типа. Возвращает каноническое имя ключа.
+pub fn set_setting(settings: &mut Settings, key: &str, raw: &str) -> anyhow::Result<&'static str> {
+    let (key, kind) = setting_spec(key)?;
+    let value = parse_setting(key, kind, raw)?;
+    let mut all = serde_json::to_value(&*settings)?;
+    *all.pointer_mut(&setting_pointer(key))
+        .ok_or_else(|| anyhow!("setting {key} is missing from the settings schema"))? = value;
+    *settings = serde_json::from_value(all).map_err(|e| anyhow!("{key}: {e}"))?;
+    Ok(key)
+}
+
+/// Значение для человека: не заданное число — `default`, строка — `none`.
+fn display_setting(key: &str, value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Null => match setting_spec(key) {
+            Ok((_, SettingKind::OptionalString)) => "none".to_string(),
+            _ => "default".to_string(),
+        },
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Array(items) if items.is_empty() => "none".to_string(),
+        serde_json::Value::Array(items) => items
+            .iter()
+            .filter_map(|v| v.as_str())
+            .collect::<Vec<_>>()
+            .join(","),
+        v => v.to_string(),
+    }
+}
+
+/// pm config get <key>
+pub fn config_get(key: &str) -> anyhow::Result<()> {
+    let cfg = Config::load()?;
+    let value = setting_value(&cfg.settings, key)?;
+    crate::output::emit(
+        &serde_json::json!({ "key": setting_spec(key)?.0, "value": value }),
+        || {
+            println!("{}", display_setting(key, &value));
+            Ok(())
+        },
+    )
+}
+
+/// pm config list
+pub fn config_list() -> anyhow::Result<()> {
+    let cfg = Config::load()?;
+    let mut values = serde_json::Map::new();
+    for (key, _, _) in SETTING_KEYS {
+        values.insert(key.to_string(), setting_value(&cfg.settings, key)?);
+    }
+    crate::output::emit(&serde_json::json!({ "settings": values }), || {
+        let width = SETTING_KEYS
+            .iter()
+            .map(|(k, _, _)| k.len())
+            .max()
+            .unwrap_or(0);
+        for (key, _, about) in SETTING_KEYS {
+            let value = display_setting(key, &values[*key]);
+            println!("{key:<width$} = {value:<10} # {about}");
+        }
+        Ok(())
+    })
+}
+
+/// pm config set <key> <value>: меняется только секция `settings`,
+/// kdf и enc переписываются как были; запись атомарная.
+pub fn config_set(key: &str, value: &str) -> anyhow::Result<()> {
+    let mut cfg = Config::load()?;
+    let key = set_setting(&mut cfg.settings, key, value)?;
+    save_config(&cfg, &config_path()?)?;
+    println!(
+        "{key} = {}",
+        display_setting(key, &setting_value(&cfg.settings, key)?)
+    );
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conceal_precedence_flag_env_setting_default() {
+        // Встроенное значение: в --json не скрывать, в pm show скрывать
+        let settings = Settings::default();
+        assert!(!settings.conceal_password(false));
+        assert!(settings.mask_password_in_show(false));
+        // Настройка
+        assert!(resolve_conceal(false, None, true));
+        // Переменная сильнее настройки в обе стороны; мусор в ней игнорируется
+        assert!(!resolve_conceal(false, Some("0"), true));
+        assert!(!resolve_conceal(false, Some(" OFF "), true));
+        assert!(resolve_conceal(false, Some("yes"), false));
+        assert!(resolve_conceal(false, Some("maybe"), true));
+        assert!(!resolve_conceal(false, Some(""), false));
+        // Флаг сильнее всего
+        assert!(!resolve_conceal(true, Some("1"), true));
+    }
+
+    #[test]
+    fn legacy_show_reveals_by_default_becomes_conceal_false() {
+        let load = |settings: serde_json::Value| {
+            let mut config = serde_json::json!({ "settings": settings });
+            migrate_legacy_settings(&mut config);
+            config["settings"].clone()
+        };
+        assert_eq!(
+            load(serde_json::json!({ "ui": { "show_reveals_by_default": true } })),
+            serde_json::json!({ "display": { "conceal_password": false } })
+        );
+        assert_eq!(
+            load(serde_json::json!({ "ui": { "show_reveals_by_default": false } })),
+            serde_json::json!({})
+        );
+        // Явная новая настройка важнее старой
+        assert_eq!(
+            load(serde_json::json!({
+                "ui": { "show_reveals_by_default": true },
+                "display": { "conceal_password": true },
+            })),
+            serde_json::json!({ "display": { "conceal_password": true } })
+        );
+    }
+}
@@ -0,0 +1,184 @@
+//! `pm git-credential get|store|erase`: помощник учётных данных для git.
+//!
+//! git пишет в stdin строки `key=value` до пустой строки и читает ответ из
+//! stdout (`git help credential`). Запись ищется по шаблону
+//! `git.credential_template` (по умолчанию `git/{host}`). Нет записи — пустой
+//! ответ и код 0: git спросит пользователя сам и потом пришлёт `store`.
+//!
+//! stdout принадлежит протоколу: мастер-пароль спрашивается через терминал
+//! (`/dev/tty`), сообщения и ошибки идут в stderr.
+
+use crate::config::Config;
+use crate::git::auto_commit;
+use crate::hooks::{HookAction, run_hooks};
+use crate::session::get_master_key_with_cache;
+use crate::store::{
+    entry_exists, load_entry, normalize_entry_path, save_entry, store_root, validate_entry_path,
+};
+use anyhow::{Result, anyhow};
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+const DEFAULT_TEMPLATE: &str = "git/{host}";
+
+/// Операция помощника, как её называет git.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Operation {
+    Get,
+    Store,
+    Erase,
+}
+
+/// Описание учётных данных от git: `protocol`, `host`, `path`, `username`,
+/// `password` и прочее, что он пришлёт.
+pub struct Request(HashMap<String, String>);
+
+impl Request {
+    /// Прочитать `key=value` до пустой строки или конца ввода.
+    pub fn read(input: impl BufRead) -> Result<Self> {
+        let mut fields = HashMap::new();
+        for line in input.lines() {
+            let line = line?;
+            let line = line.trim_end_matches('\r');
+            if line.is_empty() {
+                break;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| anyhow!("git-credential: expected key=value, got {line:?}"))?;
+            fields.insert(key.to_string(), value.to_string());
+        }
+        Ok(Request(fields))
+    }
+
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0
+            .get(key)
+            .map(String::as_str)
+            .filter(|v| !v.is_empty())
+    }
+
+    /// Путь записи по шаблону; пустые подстановки не оставляют пустых
+    /// компонентов (`git/{host}/{path}` без `path` — это `git/{host}`).
+    pub fn entry_path(&self, template: &str) -> Result<String> {
+        let host = self
+            .get("host")
+            .ok_or_else(|| anyhow!("git-credential: git did not send a host"))?;
+        let mut path = template.to_string();
+        for key in ["protocol", "host", "path", "username"] {
+            let value = if key == "host" {
+                // `example.com:8443` — двоеточие в имени файла не везде допустимо
+                host.replace(':', "_")
+            } else {
+                self.get(key).unwrap_or_default().to_string()
+            };
+            path = path.replace(&format!("{{{key}}}"), &value);
+        }
+        validate_entry_path(&normalize_entry_path(&path)?)
+    }
+
+    /// URL для поля `url` новой записи.
+    fn url(&self) -> Option<String> {
+        let host = self.get("host")?;
+        let protocol = self.get("protocol").unwrap_or("https");
+        Some(match self.get("path") {
+            Some(path) => format!("{protocol}://{host}/{path}"),
+            None => format!("{protocol}://{host}"),
+        })
+    }
+}
+
+/// pm git-credential <get|store|erase>
+pub fn git_credential(op: Operation) -> Result<()> {
+    let request = Request::read(std::io::stdin().lock())?;
+    let config = Config::load()?;
+    let template = config
+        .settings
+        .git
+        .credential_template
+        .as_deref()
+        .unwrap_or(DEFAULT_TEMPLATE);
+    let path = request.entry_path(template)?;
+    crate::log::info(format_args!("git-credential {op:?}: {path}"));
+
+    let exists = entry_exists(&path)?;
+    match op {
+        Operation::Get if exists => get(&config, &request, &path),
+        Operation::Store => store(&config, &request, &path, exists),
+        Operation::Erase if exists => erase(&config, &request, &path),
+        // Записи нет: git спросит пользователя
+        Operation::Get | Operation::Erase => Ok(()),
+    }
+}
+
+fn get(config: &Config, request: &Request, path: &str) -> Result<()> {
+    let mk = get_master_key_with_cache(config)?;
+    let entry = load_entry(path, &mk)?;
+    // Логин из записи под другим именем — не тот, что git просит
+    if let (Some(wanted), Some(have)) = (request.get("username"), entry.username.as_deref())
+        && wanted != have
+    {
+        return Ok(());
+    }
+    crate::recent::record_access(config, &mk, path, "git-credential");
+
+    let mut out = std::io::stdout().lock();
+    if let Some(username) = entry.username.as_deref().or(request.get("username")) {
+        writeln!(out, "username={username}")?;
+    }
+    writeln!(out, "password={}", entry.password)?;
+    out.flush()?;
+    Ok(())
+}
+
+fn store(config: &Config, request: &Request, path: &str, exists: bool) -> Result<()> {
+    let password = request
+        .get("password")
+        .ok_or_else(|| anyhow!("git-credential store: git did not send a password"))?;
+    let mk = get_master_key_with_cache(config)?;
+    let now = OffsetDateTime::now_utc().format(&Rfc3339)?;
+
+    let (mut entry, action) = if exists {
+        (load_entry(path, &mk)?, HookAction::Edit)
+    } else {
+        let mut entry = crate::import::new_entry(path.rsplit('/').next().unwrap_or(path))?;
+        entry.url = request.url();
+        (entry, HookAction::Add)
+    };
+    let same_user = request
+        .get("username")
+        .is_none_or(|u| entry.username.as_deref() == Some(u));
+    if exists && entry.password == password && same_user {
+        return Ok(());
+    }
+    entry.set_password(password.to_string(), &now);
+    if let Some(username) = request.get("username") {
+        entry.username = Some(username.to_string());
+    }
+    entry.updated_at = now;
+    save_entry(path, &entry, &mk)?;
+    auto_commit(config, action, path);
+    run_hooks(config, action, path);
+    crate::log::info(format_args!("saved git credentials to {path}"));
+    Ok(())
+}
+
+/// git отверг учётные данные: запись уходит в корзину, но только если это
+/// те самые логин и пароль — иначе её уже поправили.
+fn erase(config: &Config, request: &Request, path: &str) -> Result<()> {
+    let mk = get_master_key_with_cache(config)?;
+    let entry = load_entry(path, &mk)?;
+    let matches = |key: &str, have: Option<&str>| request.get(key).is_none_or(|v| Some(v) == have);
+    if !matches("username", entry.username.as_deref())
+        || !matches("password", Some(&entry.password))
+    {
+        return Ok(());
+    }
+    crate::trash::move_to_trash(&store_root()?, path)?;
+    auto_commit(config, HookAction::Rm, path);
+    run_hooks(config, HookAction::Rm, path);
+    crate::log::info(format_args!("moved {path} to the trash"));
+    Ok(())
+}
@@ -1,46 +1,145 @@
-use crate::config::{Config, EncConfig, KdfParams};
+use crate::config::{CONFIG_VERSION, Config, EncConfig, KdfParams, Settings};
 use anyhow::anyhow;
 use argon2::{Algorithm, Argon2, Params, Version};
 use base64::{Engine as _, engine::general_purpose};
 use chacha20poly1305::{
     KeyInit, XChaCha20Poly1305, XNonce,
-    aead::{Aead, AeadCore, OsRng},
+    aead::{Aead, AeadCore, OsRng, Payload},
 };
+use hmac::{Hmac, Mac};
 use rand::RngCore;
+use sha2::Sha256;
+use subtle::ConstantTimeEq;
 use thiserror::Error;
 
 pub type MasterKey = [u8; 32];
 
+/// SHA-256 содержимого ключевого файла.
+pub type KeyfileHash = [u8; 32];
+
 #[derive(Debug, Error)]
 pub enum CryptoError {
     #[error("invalid master password")]
     InvalidMasterPassword,
+    #[error("invalid master password or keyfile")]
+    InvalidMasterPasswordOrKeyfile,
+    #[error(
+        "keyfile required: this store is locked with a keyfile; pass --keyfile PATH or set PM_KEYFILE"
+    )]
+    KeyfileRequired,
     #[error(transparent)]
     Other(#[from] anyhow::Error),
 }
 
-/// Генерируем новый master key, шифруем его KEK'ом из мастер-пароля
-/// и возвращаем готовый Config (kdf + enc).
-pub fn generate_new_config(master_password: &str) -> anyhow::Result<Config> {
-    // 1. Генерируем случайный MasterKey (MK)
-    let mut mk = [0u8; 32];
-    rand::thread_rng().fill_bytes(&mut mk);
+/// Минимум Argon2id по рекомендации OWASP: 19 MiB, 2 прохода.
+pub const MIN_KDF_MEMORY_MIB: u32 = 19;
+pub const MIN_KDF_ITERATIONS: u32 = 2;
+/// Больше калибровка памяти не берёт: дальше растут только проходы.
+pub const MAX_KDF_MEMORY_MIB: u32 = 1024;
+/// Проходов, пока память не упёрлась в потолок.
+const CALIBRATED_ITERATIONS: u32 = 3;
+/// К этому времени одной деривации стремится калибровка.
+pub const DEFAULT_KDF_TARGET_MS: u32 = 500;
+
+/// Цель калибровки KDF.
+#[derive(Clone, Copy, Debug)]
+pub struct KdfTarget {
+    /// Желаемое время одной деривации KEK, мс.
+    pub target_ms: u32,
+    /// Память задана явно — подбираются только проходы.
+    pub memory_mib: Option<u32>,
+}
+
+impl Default for KdfTarget {
+    fn default() -> Self {
+        KdfTarget {
+            target_ms: DEFAULT_KDF_TARGET_MS,
+            memory_mib: None,
+        }
+    }
+}
+
+/// Память и проходы под бюджет `target_ms` при скорости `ms_per_unit`
+/// (мс на MiB·проход). Сначала растёт память, после потолка — проходы.
+fn kdf_cost_for(target: &KdfTarget, ms_per_unit: f64) -> (u32, u32) {
+    let budget = target.target_ms as f64 / ms_per_unit.max(1e-6);
+    let passes = |memory: u32, max: u32| {
+        ((budget / memory as f64).round() as u32).clamp(MIN_KDF_ITERATIONS, max)
+    };
+    if let Some(memory) = target.memory_mib {
+        return (memory, passes(memory, u32::MAX));
+    }
+    let memory = (budget / CALIBRATED_ITERATIONS as f64) as u32;
+    if memory > MAX_KDF_MEMORY_MIB {
+        (MAX_KDF_MEMORY_MIB, passes(MAX_KDF_MEMORY_MIB, u32::MAX))
+    } else if memory < MIN_KDF_MEMORY_MIB {
+        (
+            MIN_KDF_MEMORY_MIB,
+            passes(MIN_KDF_MEMORY_MIB, CALIBRATED_ITERATIONS),
+        )
+    } else {
+        (memory, CALIBRATED_ITERATIONS)
+    }
+}
+
+/// Подобрать параметры Argon2id под эту машину: несколько пробных дериваций,
+/// после каждой память и проходы пересчитываются по замеренной скорости.
+/// Соль — свежая.
+pub fn calibrate_kdf(target: &KdfTarget) -> anyhow::Result<KdfParams> {
+    if target.target_ms == 0 {
+        return Err(anyhow!("KDF target time must be positive"));
+    }
+    if let Some(m) = target.memory_mib
+        && !(MIN_KDF_MEMORY_MIB..=MAX_KDF_MEMORY_MIB).contains(&m)
+    {
+        return Err(anyhow!(
+            "KDF memory must be between {MIN_KDF_MEMORY_MIB} and {MAX_KDF_MEMORY_MIB} MiB"
+        ));
+    }
 
-    // 2. KDF параметры (пока жёстко, потом можно сделать авто-бенчмарк)
     let mut salt_bytes = [0u8; 16];
     rand::thread_rng().fill_bytes(&mut salt_bytes);
-    let salt_b64 = general_purpose::STANDARD.encode(&salt_bytes);
-
-    let kdf = KdfParams {
+    let mut kdf = KdfParams {
         algo: "argon2id".to_string(),
-        memory_mib: 32,
-        iterations: 3,
+        memory_mib: target.memory_mib.unwrap_or(MIN_KDF_MEMORY_MIB),
+        iterations: 1,
         parallelism: 1,
-        salt: salt_b64,
+        salt: general_purpose::STANDARD.encode(salt_bytes),
+        keyfile: false,
     };
+    for _ in 0..3 {
+        let start = std::time::Instant::now();
+        derive_kek("calibration", &kdf)?;
+        let ms = start.elapsed().as_secs_f64() * 1000.0;
+        let (memory_mib, iterations) =
+            kdf_cost_for(target, ms / (kdf.memory_mib * kdf.iterations) as f64);
+        if (memory_mib, iterations) == (kdf.memory_mib, kdf.iterations) {
+            break;
+        }
+        kdf.memory_mib = memory_mib;
+        kdf.iterations = iterations;
+    }
+    Ok(kdf)
+}
+
+/// Генерируем новый master key, шифруем его KEK'ом из мастер-пароля
+/// (и ключевого файла, если он задан; параметры KDF подбираются под `target`)
+/// и возвращаем готовый Config.
+pub fn generate_new_config(
+    master_password: &str,
+    target: &KdfTarget,
+    keyfile: Option<&KeyfileHash>,
+) -> anyhow::Result<Config> {
+    // 1. Генерируем случайный MasterKey (MK)
+    let mut mk = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut mk);
+
+    // 2. KDF параметры — по замерам на этой машине
+    let mut kdf = calibrate_kdf(target)?;
+    kdf.keyfile = keyfile.is_some();
 
     // 3. Производим KEK из мастер-пароля
-    let kek = derive_kek(master_password, &kdf)?;
+    let kek = derive_store_kek(master_password, &kdf, keyfile)?;
 
     // 4. Шифруем MK KEK'ом (XChaCha20-Poly1305)
     let (nonce_b64, ct_b64) = encrypt_with_key(&kek, &mk)?;
@@ -52,25 +151,104 @@ pub fn generate_new_config(master_password: &str) -> anyhow::Result<Config> {
     };
 
     Ok(Config {
-        version: 1,
+        version: CONFIG_VERSION,
         kdf,
         enc,
+        settings: Settings::default(),
     })
 }
 
-/// Расшифровка master key из config по мастер-паролю.
-pub fn unlock_master_key(master_password: &str, cfg: &Config) -> Result<MasterKey, CryptoError> {
-    let kek = derive_kek(master_password, &cfg.kdf)?;
+/// Перешифровать master key под новый мастер-пароль: свежая соль,
+/// параметры KDF из `cfg` (включая флаг ключевого файла). Сам MK не меняется,
+/// поэтому записи остаются читаемыми.
+pub fn rewrap_master_key(
+    cfg: &mut Config,
+    master_key: &MasterKey,
+    new_password: &str,
+    keyfile: Option<&KeyfileHash>,
+) -> anyhow::Result<()> {
+    let mut salt_bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut salt_bytes);
+    cfg.kdf.salt = general_purpose::STANDARD.encode(salt_bytes);
+
+    let kek = derive_store_kek(new_password, &cfg.kdf, keyfile)?;
+    let (nonce_b64, ct_b64) = encrypt_with_key(&kek, master_key)?;
+    cfg.enc.master_key_nonce = nonce_b64;
+    cfg.enc.encrypted_master_key = ct_b64;
+    Ok(())
+}
+
+/// Зашифровать master key произвольным 32-байтным ключом (например, ключом сессии).
+pub fn wrap_master_key(key: &[u8; 32], master_key: &MasterKey) -> anyhow::Result<(String, String)> {
+    encrypt_with_key(key, master_key)
+}
+
+/// Обратное к `wrap_master_key`.
+pub fn unwrap_master_key(
+    key: &[u8; 32],
+    nonce_b64: &str,
+    ct_b64: &str,
+) -> Result<MasterKey, CryptoError> {
+    decrypt_with_key(key, nonce_b64, ct_b64)
+}
+
+/// Расшифровка master key из config по мастер-паролю и, если хранилище
+/// того требует, ключевому файлу.
+pub fn unlock_master_key(
+    master_password: &str,
+    cfg: &Config,
+    keyfile: Option<&KeyfileHash>,
+) -> Result<MasterKey, CryptoError> {
+    let kek = derive_store_kek(master_password, &cfg.kdf, keyfile)?;
     let mk = decrypt_with_key(
         &kek,
         &cfg.enc.master_key_nonce,
         &cfg.enc.encrypted_master_key,
-    )?;
-    Ok(mk)
+    );
+    match mk {
+        Err(CryptoError::InvalidMasterPassword) if cfg.kdf.keyfile => {
+            Err(CryptoError::InvalidMasterPasswordOrKeyfile)
+        }
+        mk => mk,
+    }
+}
+
+/// KEK хранилища: Argon2id от мастер-пароля, а при `kdf.keyfile` — ещё
+/// HKDF-SHA256 над выходом Argon2id и хэшем ключевого файла.
+/// Ключевой файл у хранилища без флага не используется.
+pub fn derive_store_kek(
+    master_password: &str,
+    kdf: &KdfParams,
+    keyfile: Option<&KeyfileHash>,
+) -> Result<[u8; 32], CryptoError> {
+    if !kdf.keyfile {
+        return Ok(derive_kek(master_password, kdf)?);
+    }
+    let keyfile = keyfile.ok_or(CryptoError::KeyfileRequired)?;
+    let mut ikm = derive_kek(master_password, kdf)?.to_vec();
+    ikm.extend_from_slice(keyfile);
+    let salt = general_purpose::STANDARD
+        .decode(&kdf.salt)
+        .map_err(|e| CryptoError::Other(e.into()))?;
+    Ok(hkdf_sha256(&salt, &ikm, b"pm keyfile kek v1"))
+}
+
+/// HKDF-SHA256 (RFC 5869) с одним блоком вывода — 32 байта.
+fn hkdf_sha256(salt: &[u8], ikm: &[u8], info: &[u8]) -> [u8; 32] {
+    let mut extract =
+        <Hmac<Sha256> as Mac>::new_from_slice(salt).expect("HMAC accepts keys of any length");
+    extract.update(ikm);
+    let prk = extract.finalize().into_bytes();
+
+    let mut expand =
+        <Hmac<Sha256> as Mac>::new_from_slice(&prk).expect("HMAC accepts keys of any length");
+    expand.update(info);
+    expand.update(&[1]);
+    expand.finalize().into_bytes().into()
 }
 
 /// Деривация KEK из мастер-пароля и KDF-параметров (Argon2id).
-fn derive_kek(master_password: &str, kdf: &KdfParams) -> anyhow::Result<[u8; 32]> {
+pub fn derive_kek(master_password: &str, kdf: &KdfParams) -> anyhow::Result<[u8; 32]> {
     let salt_bytes = general_purpose::STANDARD.decode(&kdf.salt)?;
 
     let params = Params::new(
@@ -83,10 +261,18 @@ fn derive_kek(master_password: &str, kdf: &KdfParams) -> anyhow::Result<[u8; 32]
 
     let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params);
 
+    let started = std::time::Instant::now();
     let mut out = [0u8; 32];
     argon2
         .hash_password_into(master_password.as_bytes(), &salt_bytes, &mut out)
         .map_err(|e| anyhow!("argon2 error: {e}"))?;
+    crate::log::info(format_args!(
+        "argon2id ({} MiB, {} iterations, {} lanes) took {} ms",
+        kdf.memory_mib,
+        kdf.iterations,
+        kdf.parallelism,
+        started.elapsed().as_millis()
+    ));
 
     Ok(out)
 }
@@ -94,6 +280,17 @@ fn derive_kek(master_password: &str, kdf: &KdfParams) -> anyhow::Result<[u8; 32]
 /// Шифрование произвольных данных с помощью заданного 32-байтного ключа.
 /// Возвращает (nonce_b64, ciphertext_b64).
 fn encrypt_with_key(key_bytes: &[u8; 32], plaintext: &[u8]) -> anyhow::Result<(String, String)> {
+    encrypt_with_aad(key_bytes, plaintext, &[])
+}
+
+/// Шифрование с присоединёнными данными: `aad` не шифруется, но входит
+/// в тег, и расшифровать можно только с тем же `aad`. Пустой `aad`
+/// даёт тот же шифртекст, что и шифрование без него.
+fn encrypt_with_aad(
+    key_bytes: &[u8; 32],
+    plaintext: &[u8],
+    aad: &[u8],
+) -> anyhow::Result<(String, String)> {
     let key = chacha20poly1305::Key::from_slice(key_bytes);
     let cipher = XChaCha20Poly1305::new(key);
 
@@ -101,10 +298,16 @@ fn encrypt_with_key(key_bytes: &[u8; 32], plaintext: &[u8]) -> anyhow::Result<(S
     let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
 
     let ciphertext = cipher
-        .encrypt(&nonce, plaintext)
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: plaintext,
+                aad,
+            },
+        )
         .map_err(|e| anyhow!("encrypt error: {e}"))?;
 
-    let nonce_b64 = general_purpose::STANDARD.encode(&nonce);
+    let nonce_b64 = general_purpose::STANDARD.encode(nonce);
     let ct_b64 = general_purpose::STANDARD.encode(&ciphertext);
 
     Ok((nonce_b64, ct_b64))
@@ -141,16 +344,28 @@ fn decrypt_with_key(
     Ok(mk)
 }
 
-/// Шифрование JSON-записи master key'ом.
-pub fn encrypt_entry(master_key: &MasterKey, data: &[u8]) -> anyhow::Result<(String, String)> {
-    encrypt_with_key(master_key, data)
+/// Шифр, которым `encrypt_entry` шифрует записи.
+pub const ENTRY_ALGO: &str = "xchacha20-poly1305";
+
+/// Шифры, которые умеет расшифровывать эта сборка.
+pub const SUPPORTED_ENTRY_ALGOS: &[&str] = &[ENTRY_ALGO];
+
+/// Шифрование JSON-записи master key'ом. `aad` привязывает шифртекст
+/// к контексту (для записей — к пути); там, где привязка не нужна, — `&[]`.
+pub fn encrypt_entry(
+    master_key: &MasterKey,
+    data: &[u8],
+    aad: &[u8],
+) -> anyhow::Result<(String, String)> {
+    encrypt_with_aad(master_key, data, aad)
 }
 
-/// Дешифрование JSON-записи master key'ом.
+/// Дешифрование JSON-записи master key'ом с тем же `aad`, что при шифровании.
 pub fn decrypt_entry(
     master_key: &MasterKey,
     nonce_b64: &str,
     ct_b64: &str,
+    aad: &[u8],
 ) -> anyhow::Result<Vec<u8>> {
     let key = chacha20poly1305::Key::from_slice(master_key);
     let cipher = XChaCha20Poly1305::new(key);
@@ -160,39 +375,173 @@ pub fn decrypt_entry(
     let ciphertext = general_purpose::STANDARD.decode(ct_b64)?;
 
     let plaintext = cipher
-        .decrypt(nonce, ciphertext.as_ref())
+        .decrypt(
+            nonce,
+            Payload {
+                msg: &ciphertext,
+                aad,
+            },
+        )
         .map_err(|e| anyhow!("decrypt error: {e}"))?;
     Ok(plaintext)
 }
 
-/// Простая генерация пароля (позже можно сделать более кастомизируемой).
+/// Сравнить секреты за постоянное время (`pm verify-password`); длина
+/// при этом не скрывается.
+pub fn secrets_equal(a: &str, b: &str) -> bool {
+    bool::from(a.as_bytes().ct_eq(b.as_bytes()))
+}
+
+const UPPER: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const LOWER: &str = "abcdefghijklmnopqrstuvwxyz";
+const DIGITS: &str = "0123456789";
+pub(crate) const SYMBOLS: &str = "!@#$%^&*()-_=+[]{};:,.<>?/";
+
+/// Список слов для парольных фраз: 2048 распространённых английских слов
+/// по одному в строке, т.е. 11 бит энтропии на слово.
+pub(crate) const WORDLIST: &str = include_str!("wordlist.txt");
+
+/// Парольная фраза в стиле diceware: `words` слов из `WORDLIST`,
+/// выбранных равномерно из `OsRng`.
+pub fn generate_passphrase(
+    words: usize,
+    separator: &str,
+    capitalize: bool,
+) -> anyhow::Result<String> {
+    use rand::seq::SliceRandom;
+
+    if words == 0 {
+        return Err(anyhow!("passphrase must have at least one word"));
+    }
+    let list: Vec<&str> = WORDLIST.lines().collect();
+    let mut rng = OsRng;
+    let picked: Vec<String> = (0..words)
+        .map(|_| {
+            let word = *list.choose(&mut rng).expect("wordlist is not empty");
+            if capitalize {
+                let mut chars = word.chars();
+                chars
+                    .next()
+                    .map(|c| c.to_uppercase().chain(chars).collect())
+                    .unwrap_or_default()
+            } else {
+                word.to_string()
+            }
+        })
+        .collect();
+    Ok(picked.join(separator))
+}
+
+/// Генерация пароля из выбранных классов символов.
+///
+/// Символы выбираются из `OsRng` равномерно (без смещения по модулю),
+/// и в пароле всегда есть хотя бы один символ каждого включённого класса:
+/// сначала по одному из каждого, затем остальное из общего набора, и перемешиваем.
 pub fn generate_password(
     len: usize,
     upper: bool,
     lower: bool,
     digits: bool,
+    symbols: bool,
 ) -> anyhow::Result<String> {
-    let mut chars = String::new();
-    if upper {
-        chars.push_str("ABCDEFGHIJKLMNOPQRSTUVWXYZ");
+    use rand::distributions::{Distribution, Uniform};
+    use rand::seq::SliceRandom;
+
+    let classes: Vec<&[u8]> = [
+        (upper, UPPER),
+        (lower, LOWER),
+        (digits, DIGITS),
+        (symbols, SYMBOLS),
+    ]
+    .into_iter()
+    .filter(|(on, _)| *on)
+    .map(|(_, set)| set.as_bytes())
+    .collect();
+    if classes.is_empty() {
+        return Err(anyhow!("at least one character class must be enabled"));
     }
-    if lower {
-        chars.push_str("abcdefghijklmnopqrstuvwxyz");
+    if len < classes.len() {
+        return Err(anyhow!(
+            "password length {len} is too short to include all {} enabled character classes",
+            classes.len()
+        ));
     }
-    if digits {
-        chars.push_str("0123456789");
+
+    let mut rng = OsRng;
+    let all = classes.concat();
+    let mut out: Vec<u8> = classes
+        .iter()
+        .map(|class| *class.choose(&mut rng).expect("class is not empty"))
+        .collect();
+    let pick = Uniform::from(0..all.len());
+    out.extend((out.len()..len).map(|_| all[pick.sample(&mut rng)]));
+    out.shuffle(&mut rng);
+
+    Ok(String::from_utf8(out).expect("character sets are ASCII"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secrets_equal_cases() {
+        assert!(secrets_equal("s3cret", "s3cret"));
+        assert!(secrets_equal("", ""));
+        assert!(secrets_equal("пароль", "пароль"));
+        // Та же длина, разница в первом и в последнем байте
+        assert!(!secrets_equal("s3cret", "x3cret"));
+        assert!(!secrets_equal("s3cret", "s3crex"));
+        // Разная длина, в том числе префикс
+        assert!(!secrets_equal("s3cret", "s3cre"));
+        assert!(!secrets_equal("s3cret", "s3cret "));
+        assert!(!secrets_equal("", "s"));
     }
-    // базовый набор символов
-    chars.push_str("!@#$%^&*()-_=+[]{};:,.<>?/");
 
-    let chars: Vec<char> = chars.chars().collect();
-    let mut rng = rand::thread_rng();
-    let mut out = String::with_capacity(len);
+    #[test]
+    fn generated_passwords_cover_every_enabled_class() {
+        let classes = [UPPER, LOWER, DIGITS, SYMBOLS];
+        for mask in 1..16u8 {
+            let on = [mask & 1 != 0, mask & 2 != 0, mask & 4 != 0, mask & 8 != 0];
+            for len in [4, 5, 16, 64] {
+                for _ in 0..50 {
+                    let pw = generate_password(len, on[0], on[1], on[2], on[3]).unwrap();
+                    assert_eq!(pw.len(), len);
+                    for (enabled, class) in on.iter().zip(classes) {
+                        let present = pw.chars().any(|c| class.contains(c));
+                        assert_eq!(present, *enabled, "{pw:?} (mask {mask:04b})");
+                    }
+                }
+            }
+        }
+    }
 
-    for _ in 0..len {
-        let idx = (rng.next_u32() as usize) % chars.len();
-        out.push(chars[idx]);
+    #[test]
+    fn generated_passwords_use_the_whole_alphabet() {
+        let alphabet = [UPPER, LOWER, DIGITS, SYMBOLS].concat();
+        let mut seen = std::collections::HashMap::new();
+        for _ in 0..2000 {
+            for c in generate_password(24, true, true, true, true)
+                .unwrap()
+                .chars()
+            {
+                *seen.entry(c).or_insert(0usize) += 1;
+            }
+        }
+        assert_eq!(seen.len(), alphabet.len());
+        // 48 000 символов на 88 вариантов — в среднем ~545 на символ
+        assert!(seen.values().all(|&n| n > 300), "{seen:?}");
     }
 
-    Ok(out)
+    #[test]
+    fn generate_password_rejects_impossible_requests() {
+        assert!(generate_password(16, false, false, false, false).is_err());
+        assert!(generate_password(3, true, true, true, true).is_err());
+        assert_eq!(
+            generate_password(0, true, false, false, false)
+                .unwrap_err()
+                .to_string(),
+            "password length 0 is too short to include all 1 enabled character classes"
+        );
+    }
 }
@@ -0,0 +1,144 @@
+//! Минимальный CSV по RFC 4180: поля в кавычках могут содержать запятые,
+//! переводы строк и удвоенные кавычки. Этого хватает для экспортов
+//! браузеров и менеджеров паролей и для `pm export --format csv`.
+
+use anyhow::{Result, anyhow};
+
+/// Разобрать весь текст в строки полей. BOM в начале и `\r\n` допускаются.
+pub fn parse(text: &str) -> Result<Vec<Vec<String>>> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                _ => {
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    field.push(c);
+                }
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() => in_quotes = true,
+            ',' => row.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+                line += 1;
+            }
+            _ => field.push(c),
+        }
+    }
+    if in_quotes {
+        return Err(anyhow!("CSV: unterminated quoted field (line {line})"));
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    // Пустые строки (например, в конце файла) не считаются записями
+    rows.retain(|r| !(r.len() == 1 && r[0].is_empty()));
+    Ok(rows)
+}
+
+/// Одна строка CSV с экранированием и `\n` в конце.
+pub fn write_row<S: AsRef<str>>(fields: &[S]) -> String {
+    let mut out = fields
+        .iter()
+        .map(|f| {
+            let f = f.as_ref();
+            if f.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", f.replace('"', "\"\""))
+            } else {
+                f.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    out.push('\n');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(text: &str) -> Vec<Vec<String>> {
+        parse(text).unwrap()
+    }
+
+    #[test]
+    fn quoted_fields_keep_commas_newlines_and_quotes() {
+        assert_eq!(
+            rows("name,notes\n\"a,b\",\"line 1\nline 2\"\n\"say \"\"hi\"\"\",\"\"\n"),
+            [
+                vec!["name", "notes"],
+                vec!["a,b", "line 1\nline 2"],
+                vec!["say \"hi\"", ""],
+            ]
+        );
+    }
+
+    #[test]
+    fn crlf_bom_and_blank_lines_are_accepted() {
+        assert_eq!(
+            rows("\u{feff}name,password\r\nx,\"p\r\nq\"\r\n\r\ny,\r\n"),
+            [vec!["name", "password"], vec!["x", "p\r\nq"], vec!["y", ""],]
+        );
+    }
+
+    #[test]
+    fn last_row_without_newline_and_empty_fields() {
+        assert_eq!(rows("a,,c\n,,"), [vec!["a", "", "c"], vec!["", "", ""]]);
+        assert!(rows("").is_empty());
+    }
+
+    #[test]
+    fn quotes_inside_an_unquoted_field_are_literal() {
+        assert_eq!(rows("a\"b,c\n"), [vec!["a\"b", "c"]]);
+    }
+
+    #[test]
+    fn unterminated_quote_is_an_error() {
+        let err = parse("name,notes\nx,\"open\nstill open\n").unwrap_err();
+        assert!(
+            err.to_string().contains("unterminated quoted field"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn write_row_escapes_only_when_needed() {
+        assert_eq!(write_row(&["plain", "a b"]), "plain,a b\n");
+        assert_eq!(
+            write_row(&["a,b", "say \"hi\"", "x\ny", "cr\r"]),
+            "\"a,b\",\"say \"\"hi\"\"\",\"x\ny\",\"cr\r\"\n"
+        );
+    }
+
+    #[test]
+    fn written_rows_parse_back() {
+        let original = vec![
+            vec!["name", "password", "notes"],
+            vec!["a,b", " spaced ", "line 1\r\nline 2"],
+            vec!["quote\"d", "\"\"", ""],
+            vec!["", "é,ü", "trailing\n"],
+        ];
+        let text: String = original.iter().map(|r| write_row(r)).collect();
+        assert_eq!(rows(&text), original);
+    }
+}
@@ -0,0 +1,345 @@
+//! `pm doctor`: диагностика хранилища.
+//!
+//! Каждая проверка даёт строку итоговой таблицы (pass / warn / fail), а
+//! подробности по отдельным файлам печатаются перед таблицей. Секреты не
+//! выводятся; код возврата ненулевой, если хоть одна проверка — fail, чтобы
+//! `pm doctor` можно было запускать из cron.
+
+use crate::config::Config;
+use crate::crypto::{MIN_KDF_ITERATIONS, MIN_KDF_MEMORY_MIB, SUPPORTED_ENTRY_ALGOS};
+use crate::perms;
+use crate::session::{get_master_key_with_cache, session_key_path, session_path};
+use crate::store::{
+    PATH_BOUND_VERSION, check_entry_envelope, decrypt_all, entry_file_info, list_entries,
+    store_root,
+};
+use anyhow::{Result, anyhow};
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl Status {
+    fn label(self) -> &'static str {
+        match self {
+            Status::Pass => "pass",
+            Status::Warn => "warn",
+            Status::Fail => "fail",
+        }
+    }
+}
+
+struct Check {
+    name: &'static str,
+    status: Status,
+    detail: String,
+}
+
+impl Check {
+    fn new(name: &'static str, status: Status, detail: impl Into<String>) -> Self {
+        Check {
+            name,
+            status,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Все пути хранилища, права на которые стоит проверить.
+fn store_paths(root: &Path) -> Vec<PathBuf> {
+    walkdir::WalkDir::new(root)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| !e.path_is_symlink())
+        .map(|e| e.into_path())
+        .collect()
+}
+
+/// Временный файл, оставшийся от прерванной атомарной записи.
+fn is_tmp_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .is_some_and(|n| n.starts_with('.') && n.contains(".tmp-"))
+}
+
+fn check_config() -> (Check, Option<Config>) {
+    let config = match Config::load() {
+        Ok(c) => c,
+        Err(e) => return (Check::new("config", Status::Fail, e.to_string()), None),
+    };
+    let mut problems = Vec::new();
+    if config.kdf.algo != "argon2id" {
+        problems.push(format!("unsupported KDF {}", config.kdf.algo));
+    }
+    if !SUPPORTED_ENTRY_ALGOS.contains(&config.enc.algo.as_str()) {
+        problems.push(format!("unsupported cipher {}", config.enc.algo));
+    }
+    let check = if problems.is_empty() {
+        Check::new(
+            "config",
+            Status::Pass,
+            format!("{}, {}", config.kdf.algo, config.enc.algo),
+        )
+    } else {
+        Check::new("config", Status::Fail, problems.join("; "))
+    };
+    (check, Some(config))
+}
+
+fn check_kdf(config: &Config) -> Check {
+    let kdf = &config.kdf;
+    let params = format!(
+        "{} MiB, {} iterations, parallelism {}",
+        kdf.memory_mib, kdf.iterations, kdf.parallelism
+    );
+    if kdf.memory_mib < MIN_KDF_MEMORY_MIB || kdf.iterations < MIN_KDF_ITERATIONS {
+        Check::new(
+            "kdf strength",
+            Status::Warn,
+            format!(
+                "{params}; recommended at least {MIN_KDF_MEMORY_MIB} MiB and {MIN_KDF_ITERATIONS} iterations; run `pm config upgrade-kdf`"
+            ),
+        )
+    } else {
+        Check::new("kdf strength", Status::Pass, params)
+    }
+}
+
+fn check_envelopes(paths: &[String]) -> Check {
+    let mut bad = 0;
+    for path in paths {
+        if let Err(e) = check_entry_envelope(path) {
+            println!("FAIL  {path}: {e}");
+            bad += 1;
+        }
+    }
+    if bad == 0 {
+        Check::new(
+            "entry files",
+            Status::Pass,
+            format!("{} files well-formed", paths.len()),
+        )
+    } else {
+        Check::new(
+            "entry files",
+            Status::Fail,
+            format!("{bad} of {} files malformed", paths.len()),
+        )
+    }
+}
+
+/// Файлы старых форматов расшифруются и под чужим именем: подмену
+/// одного файла другим заметит только v3+.
+fn check_path_binding(paths: &[String]) -> Check {
+    let unbound = paths
+        .iter()
+        .filter(|p| entry_file_info(p).is_ok_and(|i| i.version < PATH_BOUND_VERSION))
+        .count();
+    if unbound == 0 {
+        Check::new(
+            "path binding",
+            Status::Pass,
+            "all entries bound to their path",
+        )
+    } else {
+        Check::new(
+            "path binding",
+            Status::Warn,
+            format!(
+                "{unbound} of {} entries use an older format not bound to their path; run `pm migrate`",
+                paths.len()
+            ),
+        )
+    }
+}
+
+fn check_decryption(config: &Config, paths: Vec<String>) -> Check {
+    let mk = match get_master_key_with_cache(config) {
+        Ok(mk) => mk,
+        Err(e) => {
+            return Check::new(
+                "decryption",
+                Status::Warn,
+                format!("skipped, store not unlocked: {e}"),
+            );
+        }
+    };
+    let total = paths.len();
+    let mut bad = 0;
+    for (path, res) in decrypt_all(&mk, paths) {
+        if let Err(e) = res {
+            println!("FAIL  {path}: {e}");
+            bad += 1;
+        }
+    }
+    if bad == 0 {
+        Check::new(
+            "decryption",
+            Status::Pass,
+            format!("{total} entries decrypt"),
+        )
+    } else {
+        Check::new(
+            "decryption",
+            Status::Fail,
+            format!(
+                "{bad} of {total} entries do not decrypt (corrupted, renamed or from another store)"
+            ),
+        )
+    }
+}
+
+/// Лишние файлы в `store/` и брошенные временные файлы во всём хранилище.
+fn check_stray_files(root: &Path, paths: &[PathBuf]) -> [Check; 2] {
+    let store_dir = root.join("store");
+    let mut stray = 0;
+    let mut tmp = 0;
+    for path in paths.iter().filter(|p| p.is_file()) {
+        if is_tmp_file(path) {
+            println!("WARN  {}: leftover temporary file", path.display());
+            tmp += 1;
+        } else if path.starts_with(&store_dir) && path.extension().is_none_or(|e| e != "enc") {
+            println!("WARN  {}: not an entry file", path.display());
+            stray += 1;
+        }
+    }
+    let summary = |n: usize, what: &str| match n {
+        0 => (Status::Pass, "none".to_string()),
+        n => (Status::Warn, format!("{n} {what}")),
+    };
+    let (s1, d1) = summary(stray, "non-.enc files in store/");
+    let (s2, d2) = summary(tmp, "leftover .tmp files");
+    [
+        Check::new("stray files", s1, d1),
+        Check::new("temp files", s2, d2),
+    ]
+}
+
+/// Те же проверки окружения, что печатаются при запуске, с пояснениями;
+/// заглушённые в `warnings.suppress` тоже показываются, но помечаются.
+fn check_environment() -> Check {
+    let suppressed = crate::envcheck::suppressed();
+    let findings = crate::envcheck::detect(&crate::envcheck::collect_facts(false));
+    for w in &findings {
+        let muted = if suppressed.iter().any(|s| s == w.id) {
+            " (suppressed)"
+        } else {
+            ""
+        };
+        println!("WARN  {} [{}]{muted}", w.message, w.id);
+        println!("      {}", w.explanation);
+    }
+    match findings.len() {
+        0 => Check::new("environment", Status::Pass, "no findings"),
+        n => Check::new("environment", Status::Warn, format!("{n} findings")),
+    }
+}
+
+/// Каким способом `pm clip` положит значение в буфер обмена.
+fn check_clipboard() -> Check {
+    match crate::clipboard::select(Default::default()) {
+        Ok(backend) => Check::new("clipboard", Status::Pass, backend.name()),
+        Err(e) => {
+            let reason = e.to_string();
+            let first = reason.lines().next().unwrap_or_default().to_string();
+            Check::new("clipboard", Status::Warn, first)
+        }
+    }
+}
+
+/// Права на файлы хранилища; с `fix` — исправить.
+fn check_permissions(name: &'static str, paths: &[PathBuf], fix: bool) -> Check {
+    let mut too_open = 0;
+    let mut fixed = 0;
+    for path in paths {
+        let problem = match perms::check(path) {
+            Ok(p) => p,
+            Err(e) => {
+                println!("WARN  {}: cannot check permissions: {e}", path.display());
+                continue;
+            }
+        };
+        let Some(problem) = problem else {
+            continue;
+        };
+        too_open += 1;
+        if fix {
+            match perms::restrict(path) {
+                Ok(()) => {
+                    fixed += 1;
+                    println!("FIXED {}: {problem}", path.display());
+                }
+                Err(e) => println!("FAIL  {}: {problem}; fix failed: {e}", path.display()),
+            }
+        } else {
+            println!("FAIL  {}: {problem}", path.display());
+        }
+    }
+
+    if too_open == 0 {
+        Check::new(name, Status::Pass, format!("{} paths ok", paths.len()))
+    } else if !fix {
+        Check::new(
+            name,
+            Status::Fail,
+            format!("{too_open} paths too open; run `pm doctor --fix-permissions`"),
+        )
+    } else if fixed < too_open {
+        Check::new(
+            name,
+            Status::Fail,
+            format!("{} paths could not be fixed", too_open - fixed),
+        )
+    } else {
+        Check::new(name, Status::Pass, format!("fixed {fixed} paths"))
+    }
+}
+
+/// pm doctor [--fix-permissions]
+pub fn doctor(fix_permissions: bool) -> Result<()> {
+    let root = store_root()?;
+    if !root.exists() {
+        return Err(anyhow!("Store not found at {}", root.display()));
+    }
+    let files = store_paths(&root);
+
+    let mut checks = Vec::new();
+    let (config_check, config) = check_config();
+    checks.push(config_check);
+    if let Some(ref config) = config {
+        checks.push(check_kdf(config));
+    }
+    let entries = list_entries()?;
+    checks.push(check_envelopes(&entries));
+    checks.push(check_path_binding(&entries));
+    if let Some(ref config) = config {
+        checks.push(check_decryption(config, entries));
+    }
+    checks.extend(check_stray_files(&root, &files));
+    checks.push(check_permissions("permissions", &files, fix_permissions));
+
+    let session: Vec<PathBuf> = [session_path().ok(), Some(session_key_path())]
+        .into_iter()
+        .flatten()
+        .filter(|p| p.exists() && !p.starts_with(&root))
+        .collect();
+    checks.push(check_permissions("session", &session, fix_permissions));
+    checks.push(check_environment());
+    checks.push(check_clipboard());
+
+    let width = checks.iter().map(|c| c.name.len()).max().unwrap_or(0);
+    println!();
+    for c in &checks {
+        println!("{:<width$}  {:<4}  {}", c.name, c.status.label(), c.detail);
+    }
+
+    let failed = checks.iter().filter(|c| c.status == Status::Fail).count();
+    if failed > 0 {
+        return Err(anyhow!("{failed} checks failed"));
+    }
+    Ok(())
+}
@@ -0,0 +1,415 @@
+//! Создание и изменение записей: `pm add`, `pm regen`, `pm edit`,
+//! а также `pm generate`.
+//!
+//! Не заданное флагами спрашивается в терминале; после сохранения
+//! срабатывают git-автокоммит и хуки. Пароль возвращается вызывающему:
+//! в буфер обмена или на экран его выводит CLI.
+
+use crate::config::Config;
+use crate::crypto::{generate_passphrase, generate_password};
+use crate::entry::{CustomField, ENTRY_SCHEMA_VERSION, Entry, EntryKind, WifiSecurity, parse_tags};
+use crate::git::auto_commit;
+use crate::hooks::{HookAction, run_hooks};
+use crate::password_store::PasswordStore;
+use crate::prompt::{prompt_edit, prompt_password_hidden, prompt_string, read_secret};
+use crate::store::{ensure_store_dirs, entry_exists, validate_entry_path};
+use crate::timespec::parse_expiry;
+use crate::wifi::prompt_wifi_config;
+use anyhow::{Result, bail};
+use std::io::IsTerminal;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+#[derive(clap::Args, Debug)]
+pub struct PassphraseArgs {
+    /// Generate a diceware-style passphrase of N words instead of random characters
+    #[arg(long, value_name = "N")]
+    pub words: Option<usize>,
+    /// Word separator for --words
+    #[arg(long, default_value = "-", requires = "words")]
+    pub separator: String,
+    /// Capitalize each word of the passphrase
+    #[arg(long, requires = "words")]
+    pub capitalize: bool,
+}
+
+impl PassphraseArgs {
+    /// Парольная фраза, если задан `--words`.
+    pub fn generate(&self) -> Option<Result<String>> {
+        self.words
+            .map(|n| generate_passphrase(n, &self.separator, self.capitalize))
+    }
+}
+
+/// Поля `pm add`, заданные флагами: соответствующий вопрос не задаётся.
+#[derive(clap::Args, Debug)]
+pub struct AddFieldArgs {
+    /// Username (skips the prompt)
+    #[arg(long)]
+    pub username: Option<String>,
+    /// URL (skips the prompt)
+    #[arg(long)]
+    pub url: Option<String>,
+    /// Notes (skips the prompt)
+    #[arg(long)]
+    pub notes: Option<String>,
+    /// Expiry date, YYYY-MM-DD or RFC3339; empty for none (skips the prompt)
+    #[arg(long, value_name = "DATE")]
+    pub expires: Option<String>,
+    /// Comma-separated tags, e.g. "shared,2fa-enabled" (skips the prompt)
+    #[arg(long, value_name = "TAGS")]
+    pub tags: Option<String>,
+    /// Read the password from the first line of stdin (hidden prompt if stdin is a terminal)
+    #[arg(long, conflicts_with = "generate")]
+    pub password_stdin: bool,
+    /// Generate the password (LEN characters, generate.length by default; --words for a passphrase)
+    #[arg(long, value_name = "LEN", num_args = 0..=1)]
+    pub generate: Option<Option<usize>>,
+}
+
+impl AddFieldArgs {
+    /// Задан хоть один флаг для неинтерактивного `pm add`.
+    fn scripted(&self) -> bool {
+        let fields = [
+            &self.username,
+            &self.url,
+            &self.notes,
+            &self.expires,
+            &self.tags,
+        ];
+        fields.iter().any(|f| f.is_some()) || self.password_stdin || self.generate.is_some()
+    }
+}
+
+/// Сохранённая запись и её пароль.
+pub struct Saved {
+    pub password: String,
+    /// Пароль придуман pm, а не введён
+    pub generated: bool,
+}
+
+/// Новый пароль по настройкам `generate.*`: `length` символов или, с
+/// `--words`, парольная фраза.
+fn generate_secret(
+    config: &Config,
+    length: Option<usize>,
+    passphrase: &PassphraseArgs,
+) -> Result<String> {
+    match passphrase.generate() {
+        Some(p) => p,
+        None => {
+            let defaults = &config.settings.generate;
+            let length = length.unwrap_or(defaults.length);
+            generate_password(length, true, true, true, defaults.symbols)
+        }
+    }
+}
+
+/// Классы символов `pm generate`, выключаемые флагами `--no-*`.
+pub struct Charset {
+    pub upper: bool,
+    pub lower: bool,
+    pub digits: bool,
+    pub symbols: bool,
+}
+
+/// pm generate: `count` паролей по настройкам `generate.*` с поправками флагов.
+pub fn generate(
+    length: Option<usize>,
+    charset: &Charset,
+    passphrase: &PassphraseArgs,
+    count: usize,
+) -> Result<Vec<String>> {
+    // Хранилища может и не быть: тогда встроенные значения
+    let defaults = Config::load()
+        .map(|c| c.settings.generate)
+        .unwrap_or_default();
+    let length = length.unwrap_or(defaults.length);
+    let symbols = defaults.symbols && charset.symbols;
+    (0..count)
+        .map(|_| {
+            passphrase.generate().unwrap_or_else(|| {
+                generate_password(
+                    length,
+                    charset.upper,
+                    charset.lower,
+                    charset.digits,
+                    symbols,
+                )
+            })
+        })
+        .collect()
+}
+
+/// Значение из флага, а без флага — ответ на вопрос.
+fn prompt_unless_given(flag: &Option<String>, prompt: &str) -> Result<String> {
+    match flag {
+        Some(v) => Ok(v.clone()),
+        None => prompt_string(prompt),
+    }
+}
+
+/// pm add PATH. `replace`: `Some(drop_otp)` при `--force`.
+pub fn add(
+    path: &str,
+    kind: EntryKind,
+    passphrase: &PassphraseArgs,
+    flags: &AddFieldArgs,
+    replace: Option<bool>,
+) -> Result<Saved> {
+    // Проверяем до всех вопросов, чтобы не гонять пользователя впустую
+    let exists = entry_exists(path)?;
+    if exists && replace.is_none() {
+        bail!(
+            "Entry {} already exists; use `pm edit` to modify it or `pm add --force` to replace it.",
+            validate_entry_path(path)?
+        );
+    }
+    ensure_store_dirs(path)?;
+
+    let store = PasswordStore::open_current()?;
+    let config = store.config();
+    let previous = if exists {
+        Some(store.show(path)?)
+    } else {
+        None
+    };
+
+    let title = path.to_string();
+    let (username, wifi, password_prompt) = match kind {
+        EntryKind::Login => (
+            prompt_unless_given(&flags.username, "Username (optional): ")?,
+            None,
+            "Password (leave empty to generate): ",
+        ),
+        EntryKind::Wifi => (
+            String::new(),
+            Some(prompt_wifi_config()?),
+            "Passphrase (leave empty to generate): ",
+        ),
+    };
+    let open_network = wifi
+        .as_ref()
+        .is_some_and(|w| w.security == WifiSecurity::Open);
+
+    let password = if open_network || flags.generate.is_some() {
+        String::new()
+    } else if flags.password_stdin {
+        read_secret(password_prompt)?
+    } else {
+        prompt_password_hidden(password_prompt)?
+    };
+    let generated = password.is_empty() && !open_network;
+    let password = if generated {
+        generate_secret(config, flags.generate.flatten(), passphrase)?
+    } else {
+        password
+    };
+    let url = match kind {
+        EntryKind::Login => prompt_unless_given(&flags.url, "URL (optional): ")?,
+        EntryKind::Wifi => String::new(),
+    };
+    let notes = prompt_unless_given(&flags.notes, "Notes (optional): ")?;
+    // Срок, метки и дополнительные поля из скрипта не спрашиваем: старые
+    // скрипты о них не знают, и следующая строка stdin ушла бы не туда
+    let interactive = std::io::stdin().is_terminal() && !flags.scripted();
+    let optional = |flag: &Option<String>, prompt: &str| match flag {
+        None if !interactive => Ok(String::new()),
+        _ => prompt_unless_given(flag, prompt),
+    };
+    let expires = optional(&flags.expires, "Expiry date (optional, YYYY-MM-DD): ")?;
+    let expires_at = match expires.trim() {
+        "" => None,
+        date => Some(parse_expiry(date)?),
+    };
+    let tags = optional(&flags.tags, "Tags (optional, comma-separated): ")?;
+    let mut fields = Vec::new();
+    if interactive {
+        prompt_custom_fields(&mut fields)?;
+    }
+
+    let now = OffsetDateTime::now_utc().format(&Rfc3339)?;
+
+    let mut entry = Entry {
+        version: ENTRY_SCHEMA_VERSION,
+        title,
+        username: if username.is_empty() {
+            None
+        } else {
+            Some(username)
+        },
+        password,
+        url: if url.is_empty() { None } else { Some(url) },
+        notes: if notes.is_empty() { None } else { Some(notes) },
+        created_at: now.clone(),
+        updated_at: now.clone(),
+        expires_at,
+        otp: None,
+        kind,
+        wifi,
+        fields,
+        password_history: Vec::new(),
+        tags: parse_tags(&tags),
+        attachments: Vec::new(),
+    };
+
+    let action = match previous {
+        // Замена через --force: дата создания, OTP и история паролей остаются
+        Some(old) => {
+            let password = std::mem::replace(&mut entry.password, old.password);
+            entry.created_at = old.created_at;
+            entry.password_history = old.password_history;
+            entry.attachments = old.attachments;
+            if replace != Some(true) {
+                entry.otp = old.otp;
+            }
+            entry.set_password(password, &now);
+            HookAction::Edit
+        }
+        None => HookAction::Add,
+    };
+
+    store.save(path, &entry)?;
+    auto_commit(config, action, path);
+    run_hooks(config, action, path);
+    Ok(Saved {
+        password: entry.password,
+        generated,
+    })
+}
+
+/// pm regen PATH: сгенерировать новый пароль, старый уходит в историю
+pub fn regen(path: &str, length: Option<usize>, passphrase: &PassphraseArgs) -> Result<Saved> {
+    let store = PasswordStore::open_current()?;
+    let config = store.config();
+    let mut entry = store.show(path)?;
+    if entry
+        .wifi
+        .as_ref()
+        .is_some_and(|w| w.security == WifiSecurity::Open)
+    {
+        bail!("{path} is an open network and has no passphrase");
+    }
+
+    let password = generate_secret(config, length, passphrase)?;
+    let now = OffsetDateTime::now_utc().format(&Rfc3339)?;
+    entry.set_password(password, &now);
+    entry.updated_at = now;
+    store.save(path, &entry)?;
+    auto_commit(config, HookAction::Edit, path);
+    run_hooks(config, HookAction::Edit, path);
+    Ok(Saved {
+        password: entry.password,
+        generated: true,
+    })
+}
+
+/// pm edit PATH: пройти по полям записи, Enter оставляет значение.
+pub fn edit(path: &str) -> Result<()> {
+    let store = PasswordStore::open_current()?;
+    let config = store.config();
+    let mut entry = store.show(path)?;
+
+    if let Some(ref mut wifi) = entry.wifi {
+        match prompt_edit("SSID", Some(&wifi.ssid))? {
+            Some(ssid) => wifi.ssid = ssid,
+            None => bail!("SSID cannot be empty"),
+        }
+    } else {
+        entry.username = prompt_edit("Username", entry.username.as_deref())?;
+    }
+
+    let now = OffsetDateTime::now_utc().format(&Rfc3339)?;
+    let password = prompt_password_hidden("Password (leave empty to keep current): ")?;
+    if !password.is_empty() {
+        entry.set_password(password, &now);
+    }
+
+    if entry.kind == EntryKind::Login {
+        entry.url = prompt_edit("URL", entry.url.as_deref())?;
+    }
+    entry.notes = prompt_edit("Notes", entry.notes.as_deref())?;
+    entry.expires_at = prompt_edit("Expiry date (YYYY-MM-DD)", entry.expires_at.as_deref())?
+        .map(|date| parse_expiry(&date))
+        .transpose()?;
+    let tags = (!entry.tags.is_empty()).then(|| entry.tags.join(", "));
+    entry.tags = prompt_edit("Tags (comma-separated)", tags.as_deref())?
+        .map(|t| parse_tags(&t))
+        .unwrap_or_default();
+    edit_custom_fields(&mut entry.fields)?;
+
+    // created_at и otp остаются как были
+    entry.updated_at = now;
+
+    store.save(path, &entry)?;
+    auto_commit(config, HookAction::Edit, path);
+    run_hooks(config, HookAction::Edit, path);
+    Ok(())
+}
+
+/// Имена, которые `--field` понимает как встроенные поля записи.
+const RESERVED_FIELD_NAMES: &[&str] = &[
+    "password", "username", "url", "notes", "otp", "title", "ssid",
+];
+
+/// Дописывать дополнительные поля, пока не введена пустая строка.
+/// `name=value` — обычное поле, `*name=value` — скрываемое; повторное имя
+/// заменяет значение.
+fn prompt_custom_fields(fields: &mut Vec<CustomField>) -> Result<()> {
+    loop {
+        let answer = prompt_string(
+            "Add custom field? (name=value, *name=value to conceal, empty to finish): ",
+        )?;
+        if answer.trim().is_empty() {
+            return Ok(());
+        }
+        let Some((name, value)) = answer.split_once('=') else {
+            eprintln!("Expected name=value");
+            continue;
+        };
+        let (name, concealed) = match name.trim().strip_prefix('*') {
+            Some(n) => (n.trim(), true),
+            None => (name.trim(), false),
+        };
+        if name.is_empty() || RESERVED_FIELD_NAMES.contains(&name) {
+            eprintln!("Invalid field name '{name}'");
+            continue;
+        }
+        let field = CustomField {
+            name: name.to_string(),
+            value: value.to_string(),
+            concealed,
+        };
+        match fields.iter_mut().find(|f| f.name == name) {
+            Some(existing) => *existing = field,
+            None => fields.push(field),
+        }
+    }
+}
+
+/// Пройти по существующим дополнительным полям (Enter — оставить,
+/// `-` — удалить), затем предложить добавить новые.
+fn edit_custom_fields(fields: &mut Vec<CustomField>) -> Result<()> {
+    let mut kept = Vec::with_capacity(fields.len());
+    for mut field in std::mem::take(fields) {
+        let value = if field.concealed {
+            let prompt = format!(
+                "{} (leave empty to keep current, - to remove): ",
+                field.name
+            );
+            match prompt_password_hidden(&prompt)?.as_str() {
+                "" => Some(field.value.clone()),
+                "-" => None,
+                v => Some(v.to_string()),
+            }
+        } else {
+            prompt_edit(&field.name, Some(&field.value))?
+        };
+        if let Some(value) = value {
+            field.value = value;
+            kept.push(field);
+        }
+    }
+    *fields = kept;
+    prompt_custom_fields(fields)
+}
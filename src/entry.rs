@@ -1,5 +1,11 @@
 use serde::{Deserialize, Serialize};
 
+/// Сколько прежних паролей хранится в `password_history`.
+pub const PASSWORD_HISTORY_LIMIT: usize = 10;
+
+/// Текущая версия схемы записи (поле `version` внутри зашифрованного JSON).
+pub const ENTRY_SCHEMA_VERSION: u32 = 2;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Entry {
     pub version: u32,
@@ -10,14 +16,184 @@ pub struct Entry {
     pub notes: Option<String>,
     pub created_at: String,
     pub updated_at: String,
+    /// Срок действия (RFC3339, UTC): сертификаты, токены API и т.п.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<String>,
     pub otp: Option<OtpConfig>,
+    #[serde(default, skip_serializing_if = "EntryKind::is_login")]
+    pub kind: EntryKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wifi: Option<WifiConfig>,
+    /// Дополнительные поля (схема v2): ID ключа API, регион, контрольные вопросы
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<CustomField>,
+    /// Прежние пароли, от старых к новым; пополняется при смене пароля
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub password_history: Vec<HistoryItem>,
+    /// Метки поперёк папок (`shared-with-team`, `2fa-enabled`); при сохранении
+    /// приводятся к нижнему регистру, без повторов, по алфавиту
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Вложенные файлы; само содержимое лежит отдельно (`attachments.rs`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<AttachmentMeta>,
+}
+
+/// Вложение записи: имя и размер открытого содержимого в байтах.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct AttachmentMeta {
+    pub name: String,
+    pub size: u64,
+}
+
+/// Метки в нижнем регистре, без пробелов по краям, пустых и повторов, по алфавиту.
+pub fn normalize_tags<S: AsRef<str>>(tags: impl IntoIterator<Item = S>) -> Vec<String> {
+    let set: std::collections::BTreeSet<String> = tags
+        .into_iter()
+        .map(|t| t.as_ref().trim().to_lowercase())
+        .filter(|t| !t.is_empty())
+        .collect();
+    set.into_iter().collect()
+}
+
+/// Метки из строки через запятую: `Shared, 2FA-enabled`.
+pub fn parse_tags(s: &str) -> Vec<String> {
+    normalize_tags(s.split(','))
+}
+
+/// Пароль, действовавший до `changed_at`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HistoryItem {
+    pub password: String,
+    pub changed_at: String,
+}
+
+/// Произвольное поле записи. `concealed` — значение скрывается в `pm show`
+/// так же, как пароль, пока не передан `--reveal`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CustomField {
+    pub name: String,
+    pub value: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub concealed: bool,
+}
+
+/// Тип записи: обычный логин или сеть Wi-Fi (пароль сети лежит в `password`).
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryKind {
+    #[default]
+    Login,
+    Wifi,
+}
+
+impl Entry {
+    /// Значение поля по имени (`password`, `username`, `url`, `notes`, `title`, `ssid`)
+    /// или дополнительного поля записи.
+    pub fn field(&self, name: &str) -> anyhow::Result<Option<&str>> {
+        Ok(match name {
+            "password" => Some(self.password.as_str()),
+            "username" => self.username.as_deref(),
+            "url" => self.url.as_deref(),
+            "notes" => self.notes.as_deref(),
+            "title" => Some(self.title.as_str()),
+            "ssid" => self.wifi.as_ref().map(|w| w.ssid.as_str()),
+            other => match self.custom_field(other) {
+                Some(f) => Some(f.value.as_str()),
+                None => return Err(anyhow::anyhow!("unknown field '{other}'")),
+            },
+        })
+    }
+
+    /// Сменить пароль, сохранив прежний в истории (не больше
+    /// `PASSWORD_HISTORY_LIMIT` последних). Тот же пароль историю не трогает.
+    pub fn set_password(&mut self, password: String, now: &str) {
+        if password == self.password {
+            return;
+        }
+        let old = std::mem::replace(&mut self.password, password);
+        if !old.is_empty() {
+            self.password_history.push(HistoryItem {
+                password: old,
+                changed_at: now.to_string(),
+            });
+        }
+        let excess = self
+            .password_history
+            .len()
+            .saturating_sub(PASSWORD_HISTORY_LIMIT);
+        self.password_history.drain(..excess);
+    }
+
+    pub fn custom_field(&self, name: &str) -> Option<&CustomField> {
+        self.fields.iter().find(|f| f.name == name)
+    }
+
+    /// Привести запись к текущей схеме. Новые шаги миграции добавляются сюда
+    /// по одному на версию (`if self.version < N { ... }`).
+    pub fn upgrade_schema(&mut self) -> anyhow::Result<()> {
+        if self.version > ENTRY_SCHEMA_VERSION {
+            return Err(anyhow::anyhow!(
+                "entry schema v{} is newer than this pm supports",
+                self.version
+            ));
+        }
+        // v1 -> v2: появились `fields`; serde уже подставил пустой список
+        self.version = ENTRY_SCHEMA_VERSION;
+        Ok(())
+    }
+}
+
+impl EntryKind {
+    pub fn is_login(&self) -> bool {
+        *self == EntryKind::Login
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WifiConfig {
+    pub ssid: String,
+    pub security: WifiSecurity,
+    #[serde(default)]
+    pub hidden: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum WifiSecurity {
+    #[serde(rename = "WPA")]
+    Wpa,
+    #[serde(rename = "WEP")]
+    Wep,
+    #[serde(rename = "open")]
+    Open,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct OtpConfig {
-    pub r#type: String, // "totp"
+    pub r#type: String, // "totp" | "hotp" | "steam"
     pub secret: String,
     pub period: u32,
     pub digits: u8,
     pub algo: String, // "SHA1"
+    /// Счётчик HOTP: значение для следующего кода. Для TOTP не используется.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub counter: u64,
+    /// Издатель из otpauth:// URL; нужен, чтобы восстановить URL для `pm otp uri`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issuer: Option<String>,
+}
+
+fn is_zero(v: &u64) -> bool {
+    *v == 0
+}
+
+impl OtpConfig {
+    pub fn is_hotp(&self) -> bool {
+        self.r#type.eq_ignore_ascii_case("hotp")
+    }
+
+    /// Steam Guard: TOTP с пятисимвольными буквенно-цифровыми кодами
+    pub fn is_steam(&self) -> bool {
+        self.r#type.eq_ignore_ascii_case("steam")
+    }
 }
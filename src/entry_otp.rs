@@ -0,0 +1,219 @@
+//! `pm otp`: одноразовые коды записей хранилища.
+//!
+//! Функции возвращают коды и URI, а печатает их CLI. Выдача кода HOTP
+//! сдвигает счётчик и сохраняет запись — с хуками и git-автокоммитом,
+//! как любое другое изменение.
+
+use crate::entry::{Entry, OtpConfig};
+use crate::git::auto_commit;
+use crate::hooks::{HookAction, run_hooks};
+use crate::otp::{generate_otp_code, generate_otp_code_at, otp_uri, parse_otp_input, steam_otp};
+use crate::password_store::PasswordStore;
+use crate::prompt::{confirm, prompt_string, read_secret};
+use crate::recent::record_access;
+use crate::timespec::parse_timestamp;
+use anyhow::{Result, bail};
+use serde::Serialize;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+/// Код `pm otp show`.
+#[derive(Debug, Serialize)]
+pub struct OtpCode {
+    pub code: String,
+    /// Счётчик, которым выдан код HOTP
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub counter: Option<u64>,
+    /// Секунд до смены кода TOTP
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_in: Option<u64>,
+    /// Следующий код TOTP (для `--verbose`)
+    #[serde(skip)]
+    pub next: Option<String>,
+}
+
+/// Код для выдачи пользователю. У HOTP счётчик сдвигается и запись
+/// сохраняется до того, как код покажут: код со старым счётчиком уже потрачен.
+pub fn take_code(store: &PasswordStore, path: &str, entry: &mut Entry) -> Result<String> {
+    let Some(otp_cfg) = entry.otp.as_mut() else {
+        bail!("No OTP configured for {}", path);
+    };
+    let code = generate_otp_code(otp_cfg)?;
+    if otp_cfg.is_hotp() {
+        otp_cfg.counter += 1;
+        store.save(path, entry)?;
+        auto_commit(store.config(), HookAction::Edit, path);
+        run_hooks(store.config(), HookAction::Edit, path);
+    }
+    Ok(code)
+}
+
+/// pm otp add PATH [--uri URL | --secret S] [--steam]
+pub fn add(path: &str, input: Option<&str>, steam: bool) -> Result<()> {
+    let store = PasswordStore::open_current()?;
+    let config = store.config();
+
+    let mut entry = store.show(path)?;
+    let raw = match input {
+        None => prompt_string("OTP secret (base32) OR otpauth:// URL: ")?,
+        Some("-") => read_secret("OTP secret (base32): ")?,
+        Some(s) => s.to_string(),
+    };
+    let raw = raw.trim();
+
+    if raw.is_empty() {
+        bail!("OTP secret cannot be empty");
+    }
+
+    let mut otp_cfg = parse_otp_input(raw)?;
+    if steam {
+        otp_cfg = steam_otp(otp_cfg);
+    }
+    entry.otp = Some(otp_cfg);
+    entry.updated_at = OffsetDateTime::now_utc().format(&Rfc3339)?;
+
+    store.save(path, &entry)?;
+    auto_commit(config, HookAction::Edit, path);
+    run_hooks(config, HookAction::Edit, path);
+    Ok(())
+}
+
+/// pm otp show PATH [--at TIME]
+pub fn show(path: &str, at: Option<&str>) -> Result<OtpCode> {
+    let at = at.map(parse_timestamp).transpose()?;
+    let store = PasswordStore::open_current()?;
+    let config = store.config();
+    let mk = store.master_key()?;
+    let mut entry = store.show(path)?;
+
+    let Some(otp_cfg) = entry.otp.clone() else {
+        bail!("No OTP configured for {}", path);
+    };
+    if otp_cfg.is_hotp() {
+        if at.is_some() {
+            bail!("--at only applies to TOTP; {path} uses a counter (HOTP)");
+        }
+        let code = take_code(&store, path, &mut entry)?;
+        record_access(config, mk, path, "otp");
+        return Ok(OtpCode {
+            code,
+            counter: Some(otp_cfg.counter),
+            expires_in: None,
+            next: None,
+        });
+    }
+
+    let now = at.unwrap_or_else(|| OffsetDateTime::now_utc().unix_timestamp() as u64);
+    let code = generate_otp_code_at(&otp_cfg, now)?;
+    record_access(config, mk, path, "otp");
+    let period = otp_cfg.period as u64;
+    let left = period - now % period;
+    Ok(OtpCode {
+        code,
+        counter: None,
+        expires_in: Some(left),
+        next: Some(generate_otp_code_at(&otp_cfg, now + left)?),
+    })
+}
+
+/// pm otp clip PATH: код для буфера обмена.
+pub fn clip(path: &str) -> Result<String> {
+    let store = PasswordStore::open_current()?;
+    let mut entry = store.show(path)?;
+
+    let code = take_code(&store, path, &mut entry)?;
+    record_access(store.config(), store.master_key()?, path, "otp");
+    Ok(code)
+}
+
+/// pm otp remove PATH [--force]; `false`, если пользователь передумал.
+pub fn remove(path: &str, force: bool) -> Result<bool> {
+    let store = PasswordStore::open_current()?;
+    let config = store.config();
+    let mut entry = store.show(path)?;
+    if entry.otp.is_none() {
+        bail!("No OTP configured for {}", path);
+    }
+    if !force && !confirm(&format!("Remove the OTP secret from {path}?"))? {
+        return Ok(false);
+    }
+
+    entry.otp = None;
+    entry.updated_at = OffsetDateTime::now_utc().format(&Rfc3339)?;
+    store.save(path, &entry)?;
+    auto_commit(config, HookAction::Edit, path);
+    run_hooks(config, HookAction::Edit, path);
+    Ok(true)
+}
+
+/// pm otp uri PATH: otpauth:// URI с секретом.
+pub fn uri(path: &str) -> Result<String> {
+    let store = PasswordStore::open_current()?;
+    let entry = store.show(path)?;
+    let Some(ref otp_cfg) = entry.otp else {
+        bail!("No OTP configured for {}", path);
+    };
+
+    let uri = otp_uri(path, otp_cfg)?;
+    record_access(store.config(), store.master_key()?, path, "otp");
+    crate::envcheck::warn_secret_output();
+    Ok(uri)
+}
+
+/// pm otp set-counter PATH N
+pub fn set_counter(path: &str, counter: u64) -> Result<()> {
+    let store = PasswordStore::open_current()?;
+    let config = store.config();
+    let mut entry = store.show(path)?;
+
+    let Some(otp_cfg) = entry.otp.as_mut() else {
+        bail!("No OTP configured for {}", path);
+    };
+    if !otp_cfg.is_hotp() {
+        bail!("{path} uses TOTP; only HOTP entries have a counter");
+    }
+    otp_cfg.counter = counter;
+    store.save(path, &entry)?;
+    auto_commit(config, HookAction::Edit, path);
+    run_hooks(config, HookAction::Edit, path);
+    Ok(())
+}
+
+/// pm otp gen [--secret S | --uri URL]: код без записи в хранилище.
+pub fn generate(
+    secret: Option<&str>,
+    uri: Option<&str>,
+    digits: u8,
+    period: u32,
+    algo: &str,
+) -> Result<String> {
+    let otp_cfg = match uri {
+        Some(uri) => {
+            if !uri.trim().starts_with("otpauth://") {
+                bail!("--uri expects an otpauth:// URL");
+            }
+            parse_otp_input(uri.trim())?
+        }
+        None => {
+            let secret = match secret {
+                None | Some("-") => read_secret("OTP secret (base32): ")?,
+                Some(s) => s.to_string(),
+            };
+            let secret = secret.trim();
+            if secret.is_empty() {
+                bail!("OTP secret cannot be empty");
+            }
+            if secret.starts_with("otpauth://") {
+                bail!("Use --uri for otpauth:// URLs");
+            }
+            OtpConfig {
+                digits,
+                period,
+                algo: algo.to_uppercase(),
+                ..parse_otp_input(secret)?
+            }
+        }
+    };
+
+    generate_otp_code(&otp_cfg)
+}
@@ -0,0 +1,298 @@
+use crate::config::Config;
+use crate::session::session_path;
+use crate::store::store_root;
+use std::io::IsTerminal;
+
+/// Предупреждение о небезопасном окружении.
+/// `id` используется в `settings.warnings.suppress`, чтобы его заглушить.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    pub id: &'static str,
+    pub message: String,
+    /// Почему это опасно и что делать — для `pm doctor`
+    pub explanation: &'static str,
+}
+
+/// Все идентификаторы предупреждений (допустимые значения `warnings.suppress`).
+pub const WARNING_IDS: &[&str] = &[
+    "root-foreign-store",
+    "shared-store-dir",
+    "master-password-env",
+    "session-readable",
+    "secret-redirect",
+];
+
+/// Факты об окружении, собранные один раз. Детекторы ниже принимают
+/// только их, поэтому не зависят от реального uid, файлов и т.п.
+#[derive(Debug, Default, Clone)]
+pub struct EnvFacts {
+    pub euid: Option<u32>,
+    pub store_owner: Option<u32>,
+    pub store_parent: Option<DirFacts>,
+    pub master_password_env: bool,
+    pub session_mode: Option<u32>,
+    pub stdout_is_tty: bool,
+    pub prints_secrets: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct DirFacts {
+    pub path: String,
+    pub owner: u32,
+    pub mode: u32,
+}
+
+/// root работает с чужим хранилищем: созданные файлы станут принадлежать root,
+/// а владелец хранилища потеряет к ним доступ.
+pub fn check_root_foreign_store(euid: Option<u32>, store_owner: Option<u32>) -> Option<Warning> {
+    match (euid, store_owner) {
+        (Some(0), Some(owner)) if owner != 0 => Some(Warning {
+            id: "root-foreign-store",
+            message: format!("running as root against a store owned by uid {owner}"),
+            explanation: "files written now will belong to root and the owner loses access; \
+                          run pm as the store owner",
+        }),
+        _ => None,
+    }
+}
+
+/// Хранилище лежит в каталоге, куда могут писать/заглядывать другие пользователи
+/// (например, /tmp или общий каталог, принадлежащий не нам).
+pub fn check_shared_parent(euid: Option<u32>, parent: Option<&DirFacts>) -> Option<Warning> {
+    let parent = parent?;
+    let world_writable = parent.mode & 0o002 != 0;
+    let foreign_readable = parent.mode & 0o004 != 0 && euid.is_some_and(|u| u != parent.owner);
+    if world_writable || foreign_readable {
+        Some(Warning {
+            id: "shared-store-dir",
+            message: format!(
+                "store is located inside {}, which other users can access",
+                parent.path
+            ),
+            explanation: "other users can list or replace the store directory; \
+                          move it under your home directory",
+        })
+    } else {
+        None
+    }
+}
+
+/// Мастер-пароль в окружении виден любому процессу того же пользователя
+/// через /proc/<pid>/environ.
+pub fn check_master_password_env(set: bool) -> Option<Warning> {
+    set.then(|| Warning {
+        id: "master-password-env",
+        message: "PM_MASTER_PASSWORD is set; it is visible to other processes of this user"
+            .to_string(),
+        explanation: "any process of this user can read it from /proc/<pid>/environ; \
+                      prefer the session cache or pm agent",
+    })
+}
+
+/// Файл сессии (с мастер-ключом) доступен группе или остальным.
+pub fn check_session_mode(mode: Option<u32>) -> Option<Warning> {
+    let mode = mode?;
+    (mode & 0o077 != 0).then(|| Warning {
+        id: "session-readable",
+        message: format!(
+            "session file is readable by others (mode {:o})",
+            mode & 0o777
+        ),
+        explanation: "the session file holds the wrapped master key; \
+                      run `pm doctor --fix-permissions` or `pm lock`",
+    })
+}
+
+/// Секреты печатаются не в терминал (в файл или пайп), хотя не просили
+/// `--password-only` — вероятно, пароль окажется в логе.
+pub fn check_secret_redirect(stdout_is_tty: bool, prints_secrets: bool) -> Option<Warning> {
+    (prints_secrets && !stdout_is_tty).then(|| Warning {
+        id: "secret-redirect",
+        message: "stdout is not a terminal; secrets are being written to a file or pipe"
+            .to_string(),
+        explanation: "the secret may end up in a log or shell history file; \
+                      use --password-only for scripts",
+    })
+}
+
+/// Прогнать все детекторы по собранным фактам.
+pub fn detect(facts: &EnvFacts) -> Vec<Warning> {
+    [
+        check_root_foreign_store(facts.euid, facts.store_owner),
+        check_shared_parent(facts.euid, facts.store_parent.as_ref()),
+        check_master_password_env(facts.master_password_env),
+        check_session_mode(facts.session_mode),
+        check_secret_redirect(facts.stdout_is_tty, facts.prints_secrets),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// Собрать факты о текущем процессе и хранилище.
+pub fn collect_facts(prints_secrets: bool) -> EnvFacts {
+    let mut facts = EnvFacts {
+        master_password_env: std::env::var_os("PM_MASTER_PASSWORD").is_some(),
+        stdout_is_tty: std::io::stdout().is_terminal(),
+        prints_secrets,
+        ..Default::default()
+    };
+    collect_unix_facts(&mut facts);
+    facts
+}
+
+#[cfg(unix)]
+fn collect_unix_facts(facts: &mut EnvFacts) {
+    use std::os::unix::fs::MetadataExt;
+
+    // SAFETY: geteuid не имеет побочных эффектов и всегда успешен.
+    facts.euid = Some(unsafe { libc::geteuid() });
+
+    if let Ok(root) = store_root() {
+        if let Ok(meta) = std::fs::metadata(&root) {
+            facts.store_owner = Some(meta.uid());
+        }
+        if let Some(parent) = root.parent()
+            && let Ok(meta) = std::fs::metadata(parent)
+        {
+            facts.store_parent = Some(DirFacts {
+                path: parent.display().to_string(),
+                owner: meta.uid(),
+                mode: meta.mode(),
+            });
+        }
+    }
+
+    if let Ok(path) = session_path()
+        && let Ok(meta) = std::fs::metadata(&path)
+    {
+        facts.session_mode = Some(meta.mode());
+    }
+}
+
+#[cfg(not(unix))]
+fn collect_unix_facts(_facts: &mut EnvFacts) {}
+
+/// Заглушённые в `settings.warnings.suppress` предупреждения.
+pub fn suppressed() -> Vec<String> {
+    Config::load()
+        .map(|c| c.settings.warnings.suppress)
+        .unwrap_or_default()
+}
+
+fn emit(warnings: Vec<Warning>) {
+    if warnings.is_empty() {
+        return;
+    }
+    let suppressed = suppressed();
+    for w in warnings {
+        if !suppressed.iter().any(|s| s == w.id) {
+            eprintln!("Warning: {} [{}]", w.message, w.id);
+        }
+    }
+}
+
+/// Вывести предупреждения об окружении в stderr, пропуская заглушенные в
+/// настройках. Вывод секретов в пайп проверяет `warn_secret_output`.
+pub fn warn_unsafe_environment() {
+    emit(detect(&collect_facts(false)));
+}
+
+/// Вызывать прямо перед тем, как секрет будет напечатан в stdout: маскированный
+/// вывод `pm show` в файл предупреждения не заслуживает.
+pub fn warn_secret_output() {
+    emit(
+        check_secret_redirect(std::io::stdout().is_terminal(), true)
+            .into_iter()
+            .collect(),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(facts: &EnvFacts) -> Vec<&'static str> {
+        detect(facts).into_iter().map(|w| w.id).collect()
+    }
+
+    fn dir(owner: u32, mode: u32) -> DirFacts {
+        DirFacts {
+            path: "/srv/shared".to_string(),
+            owner,
+            mode,
+        }
+    }
+
+    #[test]
+    fn root_foreign_store() {
+        assert!(check_root_foreign_store(Some(0), Some(1000)).is_some());
+        assert!(check_root_foreign_store(Some(0), Some(0)).is_none());
+        assert!(check_root_foreign_store(Some(1000), Some(1001)).is_none());
+        assert!(check_root_foreign_store(None, Some(1000)).is_none());
+        assert!(check_root_foreign_store(Some(0), None).is_none());
+    }
+
+    #[test]
+    fn shared_parent() {
+        // /tmp: 1777, чужой
+        assert!(check_shared_parent(Some(1000), Some(&dir(0, 0o41777))).is_some());
+        // Свой каталог, доступный на чтение всем — нормально
+        assert!(check_shared_parent(Some(1000), Some(&dir(1000, 0o40755))).is_none());
+        // Чужой каталог, читаемый всеми
+        assert!(check_shared_parent(Some(1000), Some(&dir(1001, 0o40755))).is_some());
+        // Чужой, но закрытый
+        assert!(check_shared_parent(Some(1000), Some(&dir(1001, 0o40750))).is_none());
+        // Свой, но доступный на запись всем
+        assert!(check_shared_parent(Some(1000), Some(&dir(1000, 0o40777))).is_some());
+        assert!(check_shared_parent(Some(1000), None).is_none());
+        let w = check_shared_parent(None, Some(&dir(0, 0o40777))).unwrap();
+        assert_eq!(
+            w.message,
+            "store is located inside /srv/shared, which other users can access"
+        );
+    }
+
+    #[test]
+    fn session_mode() {
+        assert!(check_session_mode(Some(0o100600)).is_none());
+        assert!(check_session_mode(Some(0o100400)).is_none());
+        let w = check_session_mode(Some(0o100640)).unwrap();
+        assert_eq!(w.message, "session file is readable by others (mode 640)");
+        assert!(check_session_mode(Some(0o100604)).is_some());
+        assert!(check_session_mode(None).is_none());
+    }
+
+    #[test]
+    fn master_password_env_and_secret_redirect() {
+        assert!(check_master_password_env(true).is_some());
+        assert!(check_master_password_env(false).is_none());
+        assert!(check_secret_redirect(false, true).is_some());
+        assert!(check_secret_redirect(true, true).is_none());
+        assert!(check_secret_redirect(false, false).is_none());
+    }
+
+    #[test]
+    fn detect_combines_every_detector() {
+        assert!(ids(&EnvFacts::default()).is_empty());
+        let facts = EnvFacts {
+            euid: Some(0),
+            store_owner: Some(1000),
+            store_parent: Some(dir(0, 0o41777)),
+            master_password_env: true,
+            session_mode: Some(0o100644),
+            stdout_is_tty: false,
+            prints_secrets: true,
+        };
+        assert_eq!(ids(&facts), WARNING_IDS);
+        let quiet = EnvFacts {
+            euid: Some(1000),
+            store_owner: Some(1000),
+            store_parent: Some(dir(1000, 0o40700)),
+            session_mode: Some(0o100600),
+            stdout_is_tty: false,
+            ..Default::default()
+        };
+        assert!(ids(&quiet).is_empty());
+    }
+}
@@ -0,0 +1,84 @@
+//! Сроки действия записей: `pm expiring` и `pm ls --expired`.
+//!
+//! Дата лежит внутри зашифрованной записи (`expires_at`); список берёт её
+//! из индекса метаданных.
+
+use crate::config::Config;
+use crate::index::metadata;
+use crate::session::get_master_key_with_cache;
+use crate::timespec::is_expired;
+use anyhow::Result;
+use serde::Serialize;
+use time::format_description::well_known::Rfc3339;
+use time::{Duration, OffsetDateTime};
+
+#[derive(Debug, Serialize)]
+pub struct Expiring {
+    pub path: String,
+    pub expires_at: String,
+    pub expired: bool,
+}
+
+/// Записи из `paths`, чей срок истекает до `now + within` (или уже истёк),
+/// от ранних к поздним.
+pub fn expiring_within(paths: Vec<String>, within: Duration) -> Result<Vec<Expiring>> {
+    let config = Config::load()?;
+    let mk = get_master_key_with_cache(&config)?;
+    let now = OffsetDateTime::now_utc();
+    let horizon = now + within;
+
+    let mut found = Vec::new();
+    for (path, meta) in metadata(&mk, paths)? {
+        let meta = match meta {
+            Ok(m) => m,
+            Err(e) => {
+                eprintln!("Warning: cannot decrypt {path}: {e}");
+                continue;
+            }
+        };
+        let Some(expires_at) = meta.expires_at else {
+            continue;
+        };
+        let Ok(t) = OffsetDateTime::parse(&expires_at, &Rfc3339) else {
+            continue;
+        };
+        if t <= horizon {
+            found.push((
+                t,
+                Expiring {
+                    path,
+                    expired: is_expired(&expires_at, now),
+                    expires_at,
+                },
+            ));
+        }
+    }
+    found.sort_by(|a, b| (a.0, &a.1.path).cmp(&(b.0, &b.1.path)));
+    Ok(found.into_iter().map(|(_, e)| e).collect())
+}
+
+/// pm expiring [--within 30d]
+pub fn expiring(within: &str) -> Result<()> {
+    let within = crate::timespec::parse_duration(within)?;
+    let found = expiring_within(crate::store::list_entries()?, within)?;
+    let now = OffsetDateTime::now_utc();
+    crate::output::emit(&found, || {
+        if found.is_empty() {
+            println!("Nothing expires within {} days.", within.whole_days());
+        }
+        for e in &found {
+            let when = if e.expired {
+                "EXPIRED".to_string()
+            } else {
+                let t = OffsetDateTime::parse(&e.expires_at, &Rfc3339)?;
+                match (t - now).whole_days() {
+                    0 => "today".to_string(),
+                    1 => "in 1 day".to_string(),
+                    n => format!("in {n} days"),
+                }
+            };
+            println!("{}  {when:<11} {}", e.expires_at, e.path);
+        }
+        Ok(())
+    })
+}
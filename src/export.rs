@@ -0,0 +1,142 @@
+//! `pm export`: выгрузить хранилище в открытом виде (JSON или CSV).
+//!
+//! Результат содержит все пароли открытым текстом, поэтому экспорт требует
+//! явного подтверждения, файл создаётся с правами 0600, а писать в каталоги,
+//! доступные на запись всем (например, /tmp), отказывается.
+
+use crate::config::Config;
+use crate::session::get_master_key_with_cache;
+use crate::store::{decrypt_all, list_entries_under, write_atomic_private};
+use anyhow::{Result, anyhow};
+use std::io::{IsTerminal, Write};
+use std::path::Path;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// Array of full entry objects, including OTP settings
+    Json,
+    /// path,username,password,url,notes,otp_secret
+    Csv,
+}
+
+/// Куда можно писать открытый текст. Проверяем каталог заранее, до расшифровки.
+fn check_destination(output: &Path) -> Result<()> {
+    let dir = match output.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    if crate::perms::world_writable_dir(dir)? {
+        return Err(anyhow!(
+            "refusing to write plaintext secrets into {}: the directory is writable by other users",
+            dir.display()
+        ));
+    }
+    Ok(())
+}
+
+/// Подтверждение: флаг или «yes» в терминале.
+fn confirm_plaintext(acknowledged: bool) -> Result<()> {
+    eprintln!("WARNING: the export contains every password and OTP secret in PLAIN TEXT.");
+    eprintln!(
+        "WARNING: anyone who can read it gets full access; delete it as soon as you are done."
+    );
+    if acknowledged {
+        return Ok(());
+    }
+    if !std::io::stdin().is_terminal() {
+        return Err(anyhow!(
+            "refusing to export plaintext without --i-understand-this-is-plaintext"
+        ));
+    }
+    // Приглашение — в stderr: stdout может быть перенаправлен в файл экспорта
+    eprint!("Type \"yes\" to continue: ");
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    if answer.trim() != "yes" {
+        return Err(anyhow!("export cancelled"));
+    }
+    Ok(())
+}
+
+/// pm export --format json|csv [--output FILE] [--prefix P]
+pub fn export_plain(
+    format: ExportFormat,
+    output: Option<&Path>,
+    prefix: Option<&str>,
+    acknowledged: bool,
+) -> Result<()> {
+    if let Some(out) = output {
+        check_destination(out)?;
+    }
+    confirm_plaintext(acknowledged)?;
+
+    let config = Config::load()?;
+    let mk = get_master_key_with_cache(&config)?;
+    let mut entries = Vec::new();
+    for (path, res) in decrypt_all(&mk, list_entries_under(prefix)?) {
+        entries.push((
+            path.clone(),
+            res.map_err(|e| anyhow!("cannot decrypt {path}: {e}"))?,
+        ));
+    }
+
+    let data = match format {
+        ExportFormat::Json => {
+            // Вложения — с содержимым в base64 вместо одних имён и размеров
+            let mut list = Vec::new();
+            for (path, e) in &entries {
+                let mut value = serde_json::to_value(e)?;
+                if !e.attachments.is_empty() {
+                    value["attachments"] =
+                        serde_json::to_value(crate::attachments::export(path, e, &mk)?)?;
+                }
+                list.push(value);
+            }
+            let mut s = serde_json::to_string_pretty(&list)?;
+            s.push('\n');
+            s
+        }
+        ExportFormat::Csv => {
+            let mut s = crate::csv::write_row(&[
+                "path",
+                "username",
+                "password",
+                "url",
+                "notes",
+                "otp_secret",
+            ]);
+            for (path, e) in &entries {
+                s.push_str(&crate::csv::write_row(&[
+                    path.as_str(),
+                    e.username.as_deref().unwrap_or_default(),
+                    &e.password,
+                    e.url.as_deref().unwrap_or_default(),
+                    e.notes.as_deref().unwrap_or_default(),
+                    e.otp
+                        .as_ref()
+                        .map(|o| o.secret.as_str())
+                        .unwrap_or_default(),
+                ]));
+            }
+            let attached = entries
+                .iter()
+                .filter(|(_, e)| !e.attachments.is_empty())
+                .count();
+            if attached > 0 {
+                eprintln!(
+                    "Warning: attachments of {attached} entries are not in the CSV; use --format json to include them"
+                );
+            }
+            s
+        }
+    };
+
+    match output {
+        Some(out) => {
+            write_atomic_private(out, data.as_bytes())?;
+            eprintln!("Exported {} entries to {}", entries.len(), out.display());
+        }
+        None => std::io::stdout().write_all(data.as_bytes())?,
+    }
+    Ok(())
+}
@@ -0,0 +1,94 @@
+//! `pm fsck`: проверить, что все записи читаются и расшифровываются,
+//! а имена файлов записей — в каноническом виде (`--fix` их переименует).
+
+use crate::config::Config;
+use crate::session::get_master_key_with_cache;
+use crate::store::{
+    decrypt_all, entry_file_info, list_entries, noncanonical_entry_names, rename_to_canonical,
+};
+use anyhow::{Result, anyhow};
+use std::collections::BTreeMap;
+
+/// Проверить имена файлов; с `fix` — переименовать. Возвращает число
+/// оставшихся проблем.
+fn check_names(fix: bool) -> Result<usize> {
+    let mut problems = 0;
+    for name in noncanonical_entry_names()? {
+        if !fix {
+            println!(
+                "NAME {:?}: not in canonical form, should be {:?}",
+                name.on_disk, name.canonical
+            );
+            problems += 1;
+            continue;
+        }
+        match rename_to_canonical(&name) {
+            Ok(()) => println!("RENAMED {:?} -> {:?}", name.on_disk, name.canonical),
+            Err(e) => {
+                println!("NAME {:?}: cannot rename: {e}", name.on_disk);
+                problems += 1;
+            }
+        }
+    }
+    Ok(problems)
+}
+
+/// pm fsck [--fix]
+pub fn fsck(fix: bool) -> Result<()> {
+    let config = Config::load()?;
+    let mk = get_master_key_with_cache(&config)?;
+
+    let bad_names = check_names(fix)?;
+
+    let paths = list_entries()?;
+    let total = paths.len();
+    let mut algos: BTreeMap<String, usize> = BTreeMap::new();
+    let mut versions: BTreeMap<u32, usize> = BTreeMap::new();
+    let mut failures = Vec::new();
+
+    for (path, entry) in decrypt_all(&mk, paths) {
+        match entry_file_info(&path) {
+            Ok(info) => {
+                *algos.entry(info.algo).or_insert(0) += 1;
+                *versions.entry(info.version).or_insert(0) += 1;
+            }
+            Err(e) => {
+                failures.push((path, e));
+                continue;
+            }
+        }
+        if let Err(e) = entry {
+            failures.push((path, e));
+        }
+    }
+
+    for (path, e) in &failures {
+        println!("BAD  {path}: {e}");
+    }
+
+    println!("Ciphers:");
+    for (algo, count) in &algos {
+        println!("  {algo}: {count}");
+    }
+    println!("File formats:");
+    for (version, count) in &versions {
+        println!("  v{version}: {count}");
+    }
+    println!(
+        "Checked {total} entries: {} ok, {} bad.",
+        total - failures.len(),
+        failures.len()
+    );
+
+    if bad_names > 0 && !fix {
+        println!("{bad_names} file names are not in canonical form; run `pm fsck --fix`.");
+    }
+
+    if !failures.is_empty() {
+        return Err(anyhow!("{} entries failed the check", failures.len()));
+    }
+    if bad_names > 0 {
+        return Err(anyhow!("{bad_names} entry file names are not canonical"));
+    }
+    Ok(())
+}
@@ -0,0 +1,63 @@
+//! Нечёткий поиск по путям записей для `pm find`.
+//!
+//! Без учёта регистра. Чем меньше ранг, тем лучше совпадение:
+//! полный путь, затем имя записи, затем подстрока, затем подпоследовательность
+//! (`wgh` находит `work/github`).
+
+/// Ранг совпадения `query` с `path` или `None`, если не совпадает.
+/// Сравнивается как кортеж: (уровень, штраф).
+pub fn match_rank(query: &str, path: &str) -> Option<(u8, usize)> {
+    let q = query.to_lowercase();
+    let p = path.to_lowercase();
+    if q.is_empty() {
+        return Some((5, 0));
+    }
+    let name = p.rsplit('/').next().unwrap_or(&p);
+
+    if p == q {
+        return Some((0, 0));
+    }
+    if name == q {
+        return Some((1, 0));
+    }
+    if let Some(pos) = name.find(&q) {
+        return Some((2, pos));
+    }
+    if let Some(pos) = p.find(&q) {
+        return Some((3, pos));
+    }
+    subsequence_penalty(&q, &p).map(|gaps| (4, gaps))
+}
+
+/// Все символы `q` встречаются в `p` по порядку? Штраф — число пропущенных
+/// символов между совпадениями; совпадение в начале компонента пути бесплатно.
+fn subsequence_penalty(q: &str, p: &str) -> Option<usize> {
+    let p: Vec<char> = p.chars().collect();
+    let mut penalty = 0;
+    let mut i = 0;
+    for c in q.chars() {
+        let start = i;
+        while i < p.len() && p[i] != c {
+            i += 1;
+        }
+        if i == p.len() {
+            return None;
+        }
+        let at_boundary = i == 0 || p[i - 1] == '/' || p[i - 1] == '-' || p[i - 1] == '_';
+        if !at_boundary {
+            penalty += i - start;
+        }
+        i += 1;
+    }
+    Some(penalty)
+}
+
+/// Отобрать подходящие пути и отсортировать от лучшего совпадения к худшему.
+pub fn rank_paths(query: &str, paths: Vec<String>) -> Vec<String> {
+    let mut ranked: Vec<_> = paths
+        .into_iter()
+        .filter_map(|p| match_rank(query, &p).map(|r| (r, p)))
+        .collect();
+    ranked.sort();
+    ranked.into_iter().map(|(_, p)| p).collect()
+}
@@ -0,0 +1,197 @@
+//! Синхронизация хранилища через git, как `pass git`.
+//!
+//! `pm git ...` запускает git в корне хранилища. Если там есть `.git`, после
+//! каждого изменения записи её файл коммитится отдельно с сообщением вида
+//! `Add work/github` — в сообщения попадает только путь, не содержимое.
+//! Автокоммит отключается настройкой `git.auto_commit` или флагом `--no-commit`.
+
+use crate::config::Config;
+use crate::hooks::HookAction;
+use crate::store::{ATTACHMENTS_SUFFIX, entry_file_path, store_root};
+use anyhow::{Result, anyhow};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// `--no-commit`: не коммитить изменения в этом запуске.
+static NO_COMMIT: AtomicBool = AtomicBool::new(false);
+
+pub fn set_no_commit(v: bool) {
+    NO_COMMIT.store(v, Ordering::Relaxed);
+}
+
+/// Что не должно попадать в репозиторий: кэш сессии и его блокировка,
+/// индекс (строится заново), корзина, локальный журнал обращений и
+/// временные файлы прерванных записей.
+const GITIGNORE: &str =
+    "session.json\nsession.lock\nindex.enc\ntrash/\naccess.log\nmigrate.journal\n.lock\n.*.tmp-*\n";
+
+const INIT_MESSAGE: &str = "Initialize pm store";
+
+fn git(root: &Path) -> Command {
+    let mut cmd = Command::new("git");
+    cmd.current_dir(root);
+    cmd
+}
+
+fn spawn_error(e: std::io::Error) -> anyhow::Error {
+    if e.kind() == std::io::ErrorKind::NotFound {
+        anyhow!("git is not installed or not in PATH")
+    } else {
+        anyhow!("failed to run git: {e}")
+    }
+}
+
+/// Выполнить git тихо; ошибка содержит его stderr.
+fn run_quiet(root: &Path, args: &[&str]) -> Result<()> {
+    crate::log::debug(format_args!("running git {}", args.join(" ")));
+    let out = git(root)
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(spawn_error)?;
+    if !out.status.success() {
+        return Err(anyhow!(
+            "git {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&out.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// Есть ли в рабочем дереве незакоммиченные изменения (`git status --porcelain`).
+pub fn is_dirty(root: &Path) -> Result<bool> {
+    let out = git(root)
+        .args(["status", "--porcelain"])
+        .stdin(Stdio::null())
+        .output()
+        .map_err(spawn_error)?;
+    if !out.status.success() {
+        return Err(anyhow!(
+            "git status failed: {}",
+            String::from_utf8_lossy(&out.stderr).trim()
+        ));
+    }
+    Ok(!out.stdout.is_empty())
+}
+
+/// pm git init: репозиторий, .gitignore и первый коммит текущего состояния.
+fn init(root: &Path) -> Result<()> {
+    if root.join(".git").exists() {
+        return Err(anyhow!("{} is already a git repository", root.display()));
+    }
+    run_quiet(root, &["init", "-q"])?;
+    std::fs::write(root.join(".gitignore"), GITIGNORE)?;
+    run_quiet(root, &["add", "-A"])?;
+    if let Err(e) = run_quiet(root, &["commit", "-q", "-m", INIT_MESSAGE]) {
+        return Err(e.context("the repository was created, but the initial commit failed"));
+    }
+    println!("Initialized git repository in {}", root.display());
+    Ok(())
+}
+
+/// pm git <args...>
+pub fn git_command(args: &[String]) -> Result<()> {
+    let root = store_root()?;
+    if !root.exists() {
+        return Err(anyhow!("Store not found at {}", root.display()));
+    }
+    if args.first().map(String::as_str) == Some("init") && args.len() == 1 {
+        return init(&root);
+    }
+    let status = git(&root).args(args).status().map_err(spawn_error)?;
+    if !status.success() {
+        std::process::exit(status.code().unwrap_or(1));
+    }
+    Ok(())
+}
+
+/// Закоммитить изменение записи `path`, если хранилище — git-репозиторий.
+/// Ошибки git — только предупреждение: запись уже сохранена.
+pub fn auto_commit(config: &Config, action: HookAction, path: &str) {
+    if NO_COMMIT.load(Ordering::Relaxed) || !config.settings.git.auto_commit {
+        return;
+    }
+    let Ok(root) = store_root() else {
+        return;
+    };
+    if !root.join(".git").exists() {
+        return;
+    }
+    match commit_entry(&root, action, path) {
+        Ok(()) => crate::log::info(format_args!("git: auto-commit for {path} done")),
+        Err(e) => eprintln!("Warning: git auto-commit failed: {e}"),
+    }
+}
+
+/// Дописать в `.gitignore` шаблоны, которых не знала версия pm, создавшая
+/// репозиторий, и убрать из индекса уже закоммиченные такие файлы.
+/// Делается отдельным коммитом и только без чужих подготовленных изменений.
+fn update_gitignore(root: &Path) -> Result<()> {
+    let path = root.join(".gitignore");
+    let mut current = std::fs::read_to_string(&path).unwrap_or_default();
+    let missing: Vec<&str> = GITIGNORE
+        .lines()
+        .filter(|p| !current.lines().any(|l| l.trim() == *p))
+        .collect();
+    if missing.is_empty() || run_quiet(root, &["diff", "--cached", "--quiet"]).is_err() {
+        return Ok(());
+    }
+    if !current.is_empty() && !current.ends_with('\n') {
+        current.push('\n');
+    }
+    for pattern in &missing {
+        current.push_str(pattern);
+        current.push('\n');
+    }
+    std::fs::write(&path, current)?;
+    let mut untrack = vec!["rm", "-r", "-q", "--cached", "--ignore-unmatch", "--"];
+    untrack.extend(&missing);
+    run_quiet(root, &untrack)?;
+    run_quiet(root, &["add", "--", ".gitignore"])?;
+    run_quiet(root, &["commit", "-q", "-m", "Update .gitignore"])
+}
+
+fn commit_entry(root: &Path, action: HookAction, path: &str) -> Result<()> {
+    if let Err(e) = update_gitignore(root) {
+        eprintln!("Warning: could not update .gitignore: {e}");
+    }
+    let file = entry_file_path(path)?;
+    let rel = file.strip_prefix(root)?.to_string_lossy().into_owned();
+    let attachments = file.with_extension(&ATTACHMENTS_SUFFIX[1..]);
+    let attachments_rel = attachments
+        .strip_prefix(root)?
+        .to_string_lossy()
+        .into_owned();
+    let verb = match action {
+        HookAction::Add => "Add",
+        HookAction::Edit => "Edit",
+        HookAction::Rm => "Remove",
+    };
+    // Файл записи и каталог её вложений, если они есть на диске или в git:
+    // несуществующий путь git add не примет
+    let tracked = |p: &Path, rel: &str| {
+        p.exists() || run_quiet(root, &["ls-files", "--error-unmatch", "--", rel]).is_ok()
+    };
+    let paths: Vec<&str> = [
+        (file.as_path(), rel.as_str()),
+        (&attachments, &attachments_rel),
+    ]
+    .into_iter()
+    .filter(|(p, rel)| tracked(p, rel))
+    .map(|(_, rel)| rel)
+    .collect();
+    // Удалили запись, которую так и не закоммитили (--no-commit) — нечего фиксировать
+    if paths.is_empty() {
+        return Ok(());
+    }
+    // Только эти пути: чужие подготовленные изменения в коммит не попадут
+    let mut add = vec!["add", "-A", "--"];
+    add.extend(&paths);
+    run_quiet(root, &add)?;
+    let message = format!("{verb} {path}");
+    let mut commit = vec!["commit", "-q", "-m", &message, "--"];
+    commit.extend(&paths);
+    run_quiet(root, &commit)
+}
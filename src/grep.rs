@@ -0,0 +1,135 @@
+//! `pm grep`: поиск по содержимому расшифрованных записей.
+//!
+//! Пароль не просматривается и не печатается, пока явно не передан
+//! `--include-passwords`.
+
+use crate::config::Config;
+use crate::index::{INDEXED_FIELDS, metadata};
+use crate::output;
+use crate::regex::Regex;
+use crate::session::get_master_key_with_cache;
+use crate::store::{decrypt_all, list_entries};
+use anyhow::{Result, anyhow};
+use serde_json::json;
+
+/// Поля, в которых ищем по умолчанию.
+pub const DEFAULT_FIELDS: &[&str] = &["title", "username", "url", "notes", "ssid"];
+
+/// Что ищем: подстроку без учёта регистра или регулярное выражение.
+pub enum Pattern {
+    Substring(String),
+    Regex(Regex),
+}
+
+impl Pattern {
+    pub fn new(pattern: &str, regex: bool, ignore_case: bool) -> Result<Pattern> {
+        if regex {
+            let re = Regex::new(pattern, ignore_case)
+                .map_err(|e| anyhow!("invalid regex {pattern:?}: {e}"))?;
+            Ok(Pattern::Regex(re))
+        } else {
+            Ok(Pattern::Substring(pattern.to_lowercase()))
+        }
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        match self {
+            Pattern::Substring(s) => text.to_lowercase().contains(s.as_str()),
+            Pattern::Regex(re) => re.is_match(text),
+        }
+    }
+}
+
+/// pm grep PATTERN
+pub fn grep(pattern: &Pattern, fields: &[String], include_passwords: bool) -> Result<()> {
+    let mut fields: Vec<&str> = if fields.is_empty() {
+        DEFAULT_FIELDS.to_vec()
+    } else {
+        fields.iter().map(String::as_str).collect()
+    };
+    if fields.contains(&"password") && !include_passwords {
+        return Err(anyhow!(
+            "refusing to search passwords without --include-passwords"
+        ));
+    }
+    if include_passwords && !fields.contains(&"password") {
+        fields.push("password");
+    }
+
+    let config = Config::load()?;
+    let mk = get_master_key_with_cache(&config)?;
+
+    let mut hits = Vec::new();
+    let mut report = |path: &str, field: &str, value: &str| {
+        // Построчно, как grep: так `^`/`$` относятся к строке заметки
+        for line in value.lines().filter(|l| pattern.is_match(l)) {
+            if !output::is_json() {
+                println!("{path}: {field}: {line}");
+            }
+            hits.push(json!({ "path": path, "field": field, "line": line }));
+        }
+    };
+
+    // Заголовок, логин, URL и SSID есть в индексе — расшифровывать записи не нужно
+    if fields.iter().all(|f| INDEXED_FIELDS.contains(f)) {
+        for (path, meta) in metadata(&mk, list_entries()?)? {
+            let meta = match meta {
+                Ok(m) => m,
+                Err(e) => {
+                    eprintln!("Warning: cannot decrypt {path}: {e}");
+                    continue;
+                }
+            };
+            for field in &fields {
+                if let Some(Some(value)) = meta.field(field) {
+                    report(&path, field, value);
+                }
+            }
+        }
+    } else {
+        for (path, entry) in decrypt_all(&mk, list_entries()?) {
+            let entry = match entry {
+                Ok(e) => e,
+                Err(e) => {
+                    eprintln!("Warning: cannot decrypt {path}: {e}");
+                    continue;
+                }
+            };
+            for field in &fields {
+                let Some(value) = entry.field(field)? else {
+                    continue;
+                };
+                report(&path, field, value);
+            }
+        }
+    }
+
+    output::emit(&json!({ "matches": hits }), || Ok(()))?;
+    if hits.is_empty() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn literal_and_regex_modes_do_not_mix() {
+        // `a.b` — и подстрока, и регулярное выражение: режим решает флаг
+        let literal = Pattern::new("a.b", false, false).unwrap();
+        assert!(literal.is_match("x A.B y"));
+        assert!(!literal.is_match("axb"));
+        let regex = Pattern::new("a.b", true, false).unwrap();
+        assert!(regex.is_match("axb"));
+        assert!(!regex.is_match("AXB"));
+        assert!(Pattern::new("a.b", true, true).unwrap().is_match("AXB"));
+
+        // Синтаксис regex без --regex — обычный текст
+        let literal = Pattern::new("(ab", false, false).unwrap();
+        assert!(literal.is_match("x(AB"));
+        let err = Pattern::new("(ab", true, false).err().unwrap();
+        assert_eq!(err.to_string(), "invalid regex \"(ab\": unclosed group");
+    }
+}
@@ -0,0 +1,605 @@
+//! Проверка паролей по базе Have I Been Pwned.
+//!
+//! Онлайн — через range API с k-анонимностью: на сервер уходят только первые
+//! 5 hex-символов SHA-1, остаток хеша сравнивается локально. HTTPS-запрос
+//! делает `curl`, как `git` и `secret-tool` в других модулях.
+//!
+//! Без сети поддерживаются два формата:
+//! - официальный текстовый дамп `pwned-passwords-{sha1,ntlm}-ordered-by-hash`
+//!   (строки `HASHHEX:COUNT`, отсортированы по хешу);
+//! - компактный бинарный индекс SHA-1, который строит `pm hibp build-index`.
+//!
+//! В обоих случаях ищем бинарным поиском с seek по файлу, не загружая его в память.
+
+use crate::config::Config;
+use crate::session::get_master_key_with_cache;
+use crate::store::{decrypt_all, list_entries_under};
+use anyhow::{Context, Result, anyhow};
+use sha1::{Digest, Sha1};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+const INDEX_MAGIC: &[u8; 8] = b"PMHIBP01";
+const PREFIX_BUCKETS: usize = 1 << 16;
+const RECORD_LEN: u64 = 24; // 20 байт SHA-1 + u32 счётчик
+const HEADER_LEN: u64 = 8 + 8 + PREFIX_BUCKETS as u64 * 8;
+const RANGE_API: &str = "https://api.pwnedpasswords.com/range/";
+
+/// Результат проверки одного пароля.
+pub enum BreachStatus {
+    /// Сколько раз пароль встречался в утечках (0 — не найден)
+    Found(u64),
+    /// Проверить не удалось (например, нет сети)
+    Skipped(String),
+}
+
+pub fn sha1_digest(password: &str) -> [u8; 20] {
+    Sha1::digest(password.as_bytes()).into()
+}
+
+/// NTLM-хеш: MD4 от пароля в UTF-16LE (так устроен NTLM-дамп HIBP).
+pub fn ntlm_digest(password: &str) -> [u8; 16] {
+    let utf16: Vec<u8> = password.encode_utf16().flat_map(u16::to_le_bytes).collect();
+    md4(&utf16)
+}
+
+/// MD4 (RFC 1320). Нужен только для сверки с NTLM-дампом, не для защиты данных.
+fn md4(data: &[u8]) -> [u8; 16] {
+    const ORDER: [[usize; 16]; 3] = [
+        [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+        [0, 4, 8, 12, 1, 5, 9, 13, 2, 6, 10, 14, 3, 7, 11, 15],
+        [0, 8, 4, 12, 2, 10, 6, 14, 1, 9, 5, 13, 3, 11, 7, 15],
+    ];
+    const SHIFT: [[u32; 4]; 3] = [[3, 7, 11, 19], [3, 5, 9, 13], [3, 9, 11, 15]];
+    const ADD: [u32; 3] = [0, 0x5A82_7999, 0x6ED9_EBA1];
+
+    let mut msg = data.to_vec();
+    msg.push(0x80);
+    while msg.len() % 64 != 56 {
+        msg.push(0);
+    }
+    msg.extend_from_slice(&((data.len() as u64).wrapping_mul(8)).to_le_bytes());
+
+    let mut state: [u32; 4] = [0x6745_2301, 0xEFCD_AB89, 0x98BA_DCFE, 0x1032_5476];
+    for block in msg.chunks_exact(64) {
+        let x: Vec<u32> = block
+            .chunks_exact(4)
+            .map(|w| u32::from_le_bytes([w[0], w[1], w[2], w[3]]))
+            .collect();
+        let [mut a, mut b, mut c, mut d] = state;
+        for round in 0..3 {
+            for (i, &k) in ORDER[round].iter().enumerate() {
+                let f = match round {
+                    0 => (b & c) | (!b & d),
+                    1 => (b & c) | (b & d) | (c & d),
+                    _ => b ^ c ^ d,
+                };
+                let t = a
+                    .wrapping_add(f)
+                    .wrapping_add(x[k])
+                    .wrapping_add(ADD[round])
+                    .rotate_left(SHIFT[round][i % 4]);
+                (a, b, c, d) = (d, t, b, c);
+            }
+        }
+        for (s, v) in state.iter_mut().zip([a, b, c, d]) {
+            *s = s.wrapping_add(v);
+        }
+    }
+
+    let mut out = [0u8; 16];
+    for (o, s) in out.chunks_exact_mut(4).zip(state) {
+        o.copy_from_slice(&s.to_le_bytes());
+    }
+    out
+}
+
+fn to_hex_upper(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02X}")).collect()
+}
+
+fn parse_hex(s: &str) -> Option<[u8; 20]> {
+    if s.len() != 40 {
+        return None;
+    }
+    let mut out = [0u8; 20];
+    for (i, o) in out.iter_mut().enumerate() {
+        *o = u8::from_str_radix(s.get(i * 2..i * 2 + 2)?, 16).ok()?;
+    }
+    Some(out)
+}
+
+/// Строка дампа: `HASH:COUNT` → (хеш в верхнем регистре, счётчик).
+fn parse_line(line: &str) -> Option<(&str, u64)> {
+    let line = line.trim_end_matches(['\r', '\n']);
+    let (hash, count) = line.split_once(':')?;
+    Some((hash, count.trim().parse().ok()?))
+}
+
+/// Каким хешем записан текстовый дамп.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum DumpHash {
+    Sha1,
+    Ntlm,
+}
+
+/// Открытая база: текстовый дамп или бинарный индекс.
+pub enum PwnedDb {
+    Text {
+        reader: BufReader<File>,
+        len: u64,
+        hash: DumpHash,
+    },
+    Index {
+        file: File,
+        records: u64,
+    },
+}
+
+impl PwnedDb {
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut file =
+            File::open(path).with_context(|| format!("cannot open {}", path.display()))?;
+        let len = file.metadata()?.len();
+
+        let mut magic = [0u8; 8];
+        if len >= HEADER_LEN && file.read_exact(&mut magic).is_ok() && &magic == INDEX_MAGIC {
+            let mut buf = [0u8; 8];
+            file.read_exact(&mut buf)?;
+            let records = u64::from_be_bytes(buf);
+            // Счётчик из файла не должен переполнять арифметику смещений
+            let expected = records
+                .checked_mul(RECORD_LEN)
+                .and_then(|n| n.checked_add(HEADER_LEN));
+            if expected != Some(len) {
+                return Err(anyhow!("{} is a truncated HIBP index", path.display()));
+            }
+            return Ok(PwnedDb::Index { file, records });
+        }
+
+        file.seek(SeekFrom::Start(0))?;
+        let mut reader = BufReader::new(file);
+        // Вид хеша — по длине первой строки: 40 hex-символов у SHA-1, 32 у NTLM
+        let mut first = String::new();
+        reader.read_line(&mut first)?;
+        let hash = match parse_line(&first).map(|(h, _)| h.len()) {
+            Some(32) => DumpHash::Ntlm,
+            Some(40) | None => DumpHash::Sha1,
+            Some(_) => {
+                return Err(anyhow!(
+                    "{} is neither a SHA-1 nor an NTLM ordered-by-hash dump",
+                    path.display()
+                ));
+            }
+        };
+        Ok(PwnedDb::Text { reader, len, hash })
+    }
+
+    /// Сколько раз пароль встречается в базе (0 — нет в базе).
+    pub fn lookup(&mut self, password: &str) -> Result<u64> {
+        match self {
+            PwnedDb::Text { reader, len, hash } => {
+                let hex = match hash {
+                    DumpHash::Sha1 => to_hex_upper(&sha1_digest(password)),
+                    DumpHash::Ntlm => to_hex_upper(&ntlm_digest(password)),
+                };
+                lookup_text(reader, *len, &hex)
+            }
+            PwnedDb::Index { file, records } => {
+                lookup_index(file, *records, &sha1_digest(password))
+            }
+        }
+    }
+}
+
+/// Клиент range API. Ответы кэшируются по префиксу; после сетевой ошибки
+/// остальные пароли пропускаются сразу, а не ждут каждый свой таймаут.
+pub struct RangeClient {
+    timeout: Duration,
+    cache: HashMap<String, String>,
+    offline: Option<String>,
+}
+
+impl RangeClient {
+    pub fn new(timeout: Duration) -> Self {
+        RangeClient {
+            timeout,
+            cache: HashMap::new(),
+            offline: None,
+        }
+    }
+
+    /// Проверить пароль; на сервер уходят только 5 первых символов SHA-1.
+    pub fn check(&mut self, password: &str) -> Result<BreachStatus> {
+        let hex = to_hex_upper(&sha1_digest(password));
+        let (prefix, suffix) = hex.split_at(5);
+        if let Some(reason) = &self.offline {
+            return Ok(BreachStatus::Skipped(reason.clone()));
+        }
+        if !self.cache.contains_key(prefix) {
+            match fetch_range(prefix, self.timeout)? {
+                Ok(body) => {
+                    self.cache.insert(prefix.to_string(), body);
+                }
+                Err(e) => {
+                    let reason = format!("network error: {e}");
+                    self.offline = Some(reason.clone());
+                    return Ok(BreachStatus::Skipped(reason));
+                }
+            }
+        }
+        Ok(BreachStatus::Found(range_count(
+            &self.cache[prefix],
+            suffix,
+        )))
+    }
+}
+
+/// Ответ range API для префикса. Внешняя ошибка — нет `curl`, внутренняя —
+/// сетевая: её показываем у записи как пропуск.
+fn fetch_range(prefix: &str, timeout: Duration) -> Result<std::result::Result<String, String>> {
+    let out = Command::new("curl")
+        .args(["--silent", "--show-error", "--fail", "--proto", "=https"])
+        .args(["--max-time", &timeout.as_secs().max(1).to_string()])
+        // Заполнение ответа фиктивными суффиксами: по размеру не понять префикс
+        .args(["--header", "Add-Padding: true"])
+        .args(["--user-agent", "pm-password-manager"])
+        .arg(format!("{RANGE_API}{prefix}"))
+        .stdin(Stdio::null())
+        .output();
+    let out = match out {
+        Ok(out) => out,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(anyhow!(
+                "the online check needs curl; install it or use --offline with a downloaded dump"
+            ));
+        }
+        Err(e) => return Err(e.into()),
+    };
+    if !out.status.success() {
+        let err = String::from_utf8_lossy(&out.stderr);
+        // "curl: (6) Could not resolve host: ..." → "Could not resolve host: ..."
+        let err = err.trim().trim_start_matches("curl: ");
+        let err = err
+            .strip_prefix('(')
+            .and_then(|rest| rest.split_once(") "))
+            .map_or(err, |(_, msg)| msg);
+        return Ok(Err(err.to_string()));
+    }
+    Ok(Ok(String::from_utf8_lossy(&out.stdout).into_owned()))
+}
+
+/// Счётчик суффикса в ответе range API (`SUFFIX:COUNT` по строке);
+/// строки заполнения имеют счётчик 0.
+fn range_count(body: &str, suffix: &str) -> u64 {
+    body.lines()
+        .filter_map(parse_line)
+        .find(|(hash, _)| hash.eq_ignore_ascii_case(suffix))
+        .map_or(0, |(_, count)| count)
+}
+
+/// Бинарный поиск по отсортированному текстовому файлу с строками переменной длины.
+///
+/// Инвариант: искомая строка (если есть) начинается в диапазоне байт [lo, hi),
+/// причём `lo` всегда указывает на начало строки.
+fn lookup_text<R: BufRead + Seek>(reader: &mut R, len: u64, target: &str) -> Result<u64> {
+    let mut lo = 0u64;
+    let mut hi = len;
+    let mut line = String::new();
+
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        let start = next_line_start(reader, mid, lo)?;
+        if start >= hi {
+            hi = mid;
+            continue;
+        }
+
+        reader.seek(SeekFrom::Start(start))?;
+        line.clear();
+        let read = reader.read_line(&mut line)? as u64;
+        if read == 0 {
+            hi = mid;
+            continue;
+        }
+
+        let Some((hash, count)) = parse_line(&line) else {
+            return Err(anyhow!("malformed line at byte {start} in HIBP dump"));
+        };
+        match hash.to_ascii_uppercase().as_str().cmp(target) {
+            Ordering::Equal => return Ok(count),
+            Ordering::Less => lo = start + read,
+            Ordering::Greater => hi = mid,
+        }
+    }
+    Ok(0)
+}
+
+/// Начало первой строки, начинающейся в позиции `pos` или позже.
+fn next_line_start<R: BufRead + Seek>(reader: &mut R, pos: u64, lo: u64) -> Result<u64> {
+    if pos == lo {
+        return Ok(pos);
+    }
+    reader.seek(SeekFrom::Start(pos - 1))?;
+    let mut skipped = Vec::new();
+    let n = reader.read_until(b'\n', &mut skipped)? as u64;
+    Ok(pos - 1 + n)
+}
+
+/// Поиск в индексе из `records` записей; длину файла уже сверил `PwnedDb::open`.
+fn lookup_index<R: Read + Seek>(file: &mut R, records: u64, hash: &[u8; 20]) -> Result<u64> {
+    let bucket = u16::from_be_bytes([hash[0], hash[1]]) as u64;
+    let read_u64 = |file: &mut R, at: u64| -> Result<u64> {
+        let mut buf = [0u8; 8];
+        file.seek(SeekFrom::Start(at))?;
+        file.read_exact(&mut buf)?;
+        Ok(u64::from_be_bytes(buf))
+    };
+
+    let mut lo = read_u64(file, 16 + bucket * 8)?;
+    let mut hi = if bucket + 1 < PREFIX_BUCKETS as u64 {
+        read_u64(file, 16 + (bucket + 1) * 8)?
+    } else {
+        records
+    };
+    // Иначе `mid` из испорченной таблицы префиксов укажет за конец файла
+    if lo > hi || hi > records {
+        return Err(anyhow!("corrupted HIBP index: bad prefix table"));
+    }
+
+    let mut rec = [0u8; RECORD_LEN as usize];
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        file.seek(SeekFrom::Start(HEADER_LEN + mid * RECORD_LEN))?;
+        file.read_exact(&mut rec)?;
+        match rec[..20].cmp(&hash[..]) {
+            Ordering::Equal => {
+                return Ok(u32::from_be_bytes([rec[20], rec[21], rec[22], rec[23]]) as u64);
+            }
+            Ordering::Less => lo = mid + 1,
+            Ordering::Greater => hi = mid,
+        }
+    }
+    Ok(0)
+}
+
+/// Преобразовать текстовый дамп в бинарный индекс:
+/// заголовок, таблица начал для каждого 2-байтового префикса, затем записи фиксированной длины.
+pub fn build_index(input: &Path, output: &Path) -> Result<u64> {
+    let reader = BufReader::new(
+        File::open(input).with_context(|| format!("cannot open {}", input.display()))?,
+    );
+    let mut out = BufWriter::new(
+        File::create(output).with_context(|| format!("cannot create {}", output.display()))?,
+    );
+
+    out.write_all(INDEX_MAGIC)?;
+    out.write_all(&[0u8; HEADER_LEN as usize - 8])?;
+
+    let mut bucket_counts = vec![0u64; PREFIX_BUCKETS];
+    let mut prev: Option<[u8; 20]> = None;
+    let mut records = 0u64;
+
+    for (lineno, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (hash, count) = parse_line(&line)
+            .and_then(|(h, c)| Some((parse_hex(h)?, c)))
+            .ok_or_else(|| anyhow!("malformed line {} in {}", lineno + 1, input.display()))?;
+
+        if prev.is_some_and(|p| p >= hash) {
+            return Err(anyhow!(
+                "{} is not sorted by hash (line {}); use the ordered-by-hash dump",
+                input.display(),
+                lineno + 1
+            ));
+        }
+        prev = Some(hash);
+
+        out.write_all(&hash)?;
+        out.write_all(&(count.min(u32::MAX as u64) as u32).to_be_bytes())?;
+        bucket_counts[u16::from_be_bytes([hash[0], hash[1]]) as usize] += 1;
+        records += 1;
+    }
+
+    let mut file = out.into_inner().map_err(|e| e.into_error())?;
+    file.seek(SeekFrom::Start(8))?;
+    let mut header = Vec::with_capacity(HEADER_LEN as usize - 8);
+    header.extend_from_slice(&records.to_be_bytes());
+    let mut start = 0u64;
+    for count in bucket_counts {
+        header.extend_from_slice(&start.to_be_bytes());
+        start += count;
+    }
+    file.write_all(&header)?;
+    file.sync_all()?;
+
+    Ok(records)
+}
+
+/// Общий вывод результатов для офлайн- и онлайн-проверки.
+/// Возвращает число скомпрометированных паролей.
+pub fn report_breaches(results: &[(String, BreachStatus)]) -> usize {
+    let mut breached = 0;
+    let mut skipped = 0;
+    for (path, status) in results {
+        match status {
+            BreachStatus::Found(0) => {}
+            BreachStatus::Found(n) => {
+                breached += 1;
+                println!("{path}: password seen {n} times in breaches");
+            }
+            BreachStatus::Skipped(reason) => {
+                skipped += 1;
+                println!("{path}: skipped: {reason}");
+            }
+        }
+    }
+
+    println!(
+        "Checked {} passwords: {breached} breached, {skipped} skipped.",
+        results.len() - skipped
+    );
+    breached
+}
+
+/// pm audit --hibp-offline --db FILE
+pub fn audit_hibp_offline(db_path: &Path) -> Result<()> {
+    pwned(None, Duration::ZERO, Some(db_path))
+}
+
+/// pm pwned [path] [--timeout N] [--offline FILE]: запись, папка или всё
+/// хранилище; без `offline` — онлайн через range API.
+pub fn pwned(prefix: Option<&str>, timeout: Duration, offline: Option<&Path>) -> Result<()> {
+    let mut db = offline.map(PwnedDb::open).transpose()?;
+    let mut client = RangeClient::new(timeout);
+
+    let paths = list_entries_under(prefix)?;
+    if let (Some(prefix), true) = (prefix, paths.is_empty()) {
+        return Err(anyhow!("Entry not found: {prefix}"));
+    }
+    let config = Config::load()?;
+    let mk = get_master_key_with_cache(&config)?;
+
+    let mut results = Vec::new();
+    for (path, entry) in decrypt_all(&mk, paths) {
+        let status = match entry {
+            Ok(e) if e.password.is_empty() => continue,
+            Ok(e) => match &mut db {
+                Some(db) => BreachStatus::Found(db.lookup(&e.password)?),
+                None => client.check(&e.password)?,
+            },
+            Err(e) => BreachStatus::Skipped(format!("cannot decrypt: {e}")),
+        };
+        results.push((path, status));
+    }
+
+    let breached = report_breaches(&results);
+    if breached > 0 {
+        return Err(anyhow!("{breached} passwords found in breach data"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// Отсортированный дамп SHA-1 с паролями `password`, `123456`, `hunter2`.
+    fn sha1_dump() -> String {
+        let mut lines: Vec<String> = [("password", 10), ("123456", 7), ("hunter2", 3)]
+            .iter()
+            .map(|(pw, n)| format!("{}:{n}", to_hex_upper(&sha1_digest(pw))))
+            .collect();
+        lines.sort();
+        lines.join("\r\n") + "\r\n"
+    }
+
+    fn temp_file(name: &str, data: &[u8]) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("pm-hibp-test-{}-{name}", std::process::id()));
+        std::fs::write(&path, data).unwrap();
+        path
+    }
+
+    fn index_header(records: u64) -> Vec<u8> {
+        let mut data = INDEX_MAGIC.to_vec();
+        data.extend_from_slice(&records.to_be_bytes());
+        data.resize(HEADER_LEN as usize, 0);
+        data
+    }
+
+    #[test]
+    fn md4_and_ntlm_known_answers() {
+        // RFC 1320, A.5
+        assert_eq!(to_hex_upper(&md4(b"")), "31D6CFE0D16AE931B73C59D7E0C089C0");
+        assert_eq!(
+            to_hex_upper(&md4(b"abc")),
+            "A448017AAF21D8525FC10AE87AA6729D"
+        );
+        assert_eq!(
+            to_hex_upper(&ntlm_digest("password")),
+            "8846F7EAEE8FB117AD06BDD830B7586C"
+        );
+    }
+
+    #[test]
+    fn text_lookup_finds_every_line() {
+        let dump = sha1_dump();
+        let len = dump.len() as u64;
+        for (pw, n) in [("password", 10), ("123456", 7), ("hunter2", 3), ("nope", 0)] {
+            let target = to_hex_upper(&sha1_digest(pw));
+            let found = lookup_text(&mut Cursor::new(dump.as_bytes()), len, &target).unwrap();
+            assert_eq!(found, n, "{pw}");
+        }
+        assert_eq!(lookup_text(&mut Cursor::new(b""), 0, "AB").unwrap(), 0);
+        let garbage = b"not a dump\n";
+        assert!(lookup_text(&mut Cursor::new(garbage), garbage.len() as u64, "AB").is_err());
+    }
+
+    #[test]
+    fn index_matches_the_text_dump() {
+        let dump = temp_file("dump.txt", sha1_dump().as_bytes());
+        let index = dump.with_extension("idx");
+        assert_eq!(build_index(&dump, &index).unwrap(), 3);
+
+        let mut text = PwnedDb::open(&dump).unwrap();
+        let mut binary = PwnedDb::open(&index).unwrap();
+        assert!(matches!(binary, PwnedDb::Index { records: 3, .. }));
+        for pw in ["password", "123456", "hunter2", "nope"] {
+            assert_eq!(text.lookup(pw).unwrap(), binary.lookup(pw).unwrap(), "{pw}");
+        }
+        assert_eq!(binary.lookup("hunter2").unwrap(), 3);
+        let _ = std::fs::remove_file(dump);
+        let _ = std::fs::remove_file(index);
+    }
+
+    #[test]
+    fn ntlm_dump_is_detected() {
+        let line = format!("{}:42\n", to_hex_upper(&ntlm_digest("password")));
+        let path = temp_file("ntlm.txt", line.as_bytes());
+        let mut db = PwnedDb::open(&path).unwrap();
+        assert!(matches!(
+            db,
+            PwnedDb::Text {
+                hash: DumpHash::Ntlm,
+                ..
+            }
+        ));
+        assert_eq!(db.lookup("password").unwrap(), 42);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn index_record_count_is_checked_against_the_file() {
+        // Счётчик, на котором records * RECORD_LEN переполняется
+        for records in [1, u64::MAX / RECORD_LEN + 1, u64::MAX] {
+            let path = temp_file(&format!("bad-{records}.idx"), &index_header(records));
+            let err = PwnedDb::open(&path).err().unwrap();
+            assert!(err.to_string().contains("truncated HIBP index"), "{err}");
+            let _ = std::fs::remove_file(path);
+        }
+        let path = temp_file("empty.idx", &index_header(0));
+        assert_eq!(PwnedDb::open(&path).unwrap().lookup("password").unwrap(), 0);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn corrupted_prefix_table_is_an_error() {
+        let hash = sha1_digest("password");
+        let bucket = u16::from_be_bytes([hash[0], hash[1]]) as usize;
+        let mut data = index_header(1);
+        // Начало корзины далеко за единственной записью
+        data[16 + bucket * 8..24 + bucket * 8].copy_from_slice(&u64::MAX.to_be_bytes());
+        data.extend_from_slice(&[0u8; RECORD_LEN as usize]);
+        let err = lookup_index(&mut Cursor::new(data), 1, &hash).unwrap_err();
+        assert!(err.to_string().contains("bad prefix table"), "{err}");
+    }
+}
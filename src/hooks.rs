@@ -0,0 +1,115 @@
+//! Пользовательские хуки после изменения хранилища.
+//!
+//! В настройках `hooks.post_add` / `post_edit` / `post_rm` / `post_any`
+//! указываются исполняемые файлы. Им передаются действие и путь записи
+//! (аргументами и через `PM_HOOK_ACTION`, `PM_HOOK_PATH`, `PM_STORE_DIR`),
+//! но никогда не секреты. Ошибка хука — только предупреждение.
+
+use crate::config::Config;
+use crate::store::store_root;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// `--no-hooks`: не запускать хуки в этом запуске.
+static NO_HOOKS: AtomicBool = AtomicBool::new(false);
+
+pub fn set_no_hooks(v: bool) {
+    NO_HOOKS.store(v, Ordering::Relaxed);
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HookAction {
+    Add,
+    Edit,
+    Rm,
+}
+
+impl HookAction {
+    fn name(self) -> &'static str {
+        match self {
+            HookAction::Add => "add",
+            HookAction::Edit => "edit",
+            HookAction::Rm => "rm",
+        }
+    }
+}
+
+/// Запустить хук для действия и `post_any`. Git-автокоммит — отдельно
+/// (`git::auto_commit`), `--no-hooks` на него не влияет.
+pub fn run_hooks(config: &Config, action: HookAction, path: &str) {
+    if NO_HOOKS.load(Ordering::Relaxed) {
+        return;
+    }
+    let hooks = &config.settings.hooks;
+    let specific = match action {
+        HookAction::Add => &hooks.post_add,
+        HookAction::Edit => &hooks.post_edit,
+        HookAction::Rm => &hooks.post_rm,
+    };
+    let timeout = Duration::from_secs(hooks.timeout_secs);
+    for hook in [specific, &hooks.post_any].into_iter().flatten() {
+        run_one(hook, action, path, timeout);
+    }
+}
+
+fn run_one(hook: &str, action: HookAction, path: &str, timeout: Duration) {
+    let store_dir = store_root()
+        .map(|p| p.display().to_string())
+        .unwrap_or_default();
+
+    let mut cmd = Command::new(hook);
+    cmd.arg(action.name())
+        .arg(path)
+        .env("PM_HOOK_ACTION", action.name())
+        .env("PM_HOOK_PATH", path)
+        .env("PM_STORE_DIR", store_dir)
+        .env_remove("PM_MASTER_PASSWORD")
+        .stdin(Stdio::null())
+        // stdout pm может быть занят ответом: JSON, протокол git-credential
+        .stdout(std::io::stderr());
+    // Хранилище заблокировано нами: `pm` из хука не должен его ждать
+    if let Some((key, value)) = crate::lock::child_env() {
+        cmd.env(key, value);
+    }
+    crate::log::info(format_args!("running hook {hook} {} {path}", action.name()));
+    let child = cmd.spawn();
+    let mut child = match child {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("Warning: hook {hook} failed to start: {e}");
+            return;
+        }
+    };
+
+    let deadline = Instant::now() + timeout;
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => {
+                crate::log::info(format_args!("hook {hook} succeeded"));
+                return;
+            }
+            Ok(Some(status)) => {
+                match status.code() {
+                    Some(code) => eprintln!("Warning: hook {hook} exited with code {code}"),
+                    None => eprintln!("Warning: hook {hook} was killed by a signal"),
+                }
+                return;
+            }
+            Ok(None) if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                eprintln!(
+                    "Warning: hook {hook} timed out after {}s and was killed",
+                    timeout.as_secs()
+                );
+                return;
+            }
+            Ok(None) => std::thread::sleep(Duration::from_millis(50)),
+            Err(e) => {
+                eprintln!("Warning: hook {hook}: {e}");
+                return;
+            }
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use url::Url;
+
+/// Вторые уровни, под которыми регистрируют домены в национальных зонах
+/// (`example.co.uk`, `example.com.au`). Полного Public Suffix List у нас нет,
+/// поэтому это приближение, покрывающее самые частые случаи.
+const SECOND_LEVEL_LABELS: &[&str] = &["co", "com", "net", "org", "gov", "edu", "ac", "or", "ne"];
+
+/// Привести URL или голый хост к каноническому виду:
+/// - схема необязательна (`github.com` == `https://github.com`)
+/// - порт, путь, userinfo отбрасываются
+/// - IDN переводится в punycode (это делает `url`), регистр — нижний
+/// - ведущий `www.` и завершающая точка игнорируются
+pub fn normalize_host(input: &str) -> Option<String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return None;
+    }
+
+    let parsed = if input.contains("://") {
+        Url::parse(input).ok()?
+    } else {
+        Url::parse(&format!("https://{input}")).ok()?
+    };
+
+    let host = parsed.host_str()?.trim_end_matches('.').to_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host).to_string();
+    if host.is_empty() { None } else { Some(host) }
+}
+
+/// Регистрируемый домен хоста: `api.github.com` → `github.com`,
+/// `login.example.co.uk` → `example.co.uk`. IP-адреса возвращаются как есть.
+pub fn registrable_domain(host: &str) -> &str {
+    if host.starts_with('[') || host.parse::<std::net::Ipv4Addr>().is_ok() {
+        return host;
+    }
+
+    let labels: Vec<&str> = host.split('.').collect();
+    if labels.len() <= 2 {
+        return host;
+    }
+
+    let n = labels.len();
+    let tld = labels[n - 1];
+    let keep = if tld.len() == 2 && SECOND_LEVEL_LABELS.contains(&labels[n - 2]) {
+        3
+    } else {
+        2
+    };
+    if n <= keep {
+        return host;
+    }
+
+    let skip: usize = labels[..n - keep].iter().map(|l| l.len() + 1).sum();
+    &host[skip..]
+}
+
+/// Проверить, подходит ли URL записи под запрос пользователя.
+///
+/// Правила:
+/// - точное совпадение хостов (после нормализации);
+/// - совпадение регистрируемых доменов (`github.com` находит `gist.github.com`);
+/// - при `loose` — запрос как подстрока хоста записи.
+pub fn host_matches(entry_url: &str, query: &str, loose: bool) -> bool {
+    let Some(entry_host) = normalize_host(entry_url) else {
+        return false;
+    };
+
+    if loose {
+        let q = query.trim().to_lowercase();
+        if !q.is_empty() && entry_host.contains(&q) {
+            return true;
+        }
+    }
+
+    let Some(query_host) = normalize_host(query) else {
+        return false;
+    };
+
+    entry_host == query_host || registrable_domain(&entry_host) == registrable_domain(&query_host)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_host_table() {
+        let cases = [
+            ("github.com", Some("github.com")),
+            ("https://github.com/login?next=/", Some("github.com")),
+            ("HTTPS://WWW.GitHub.COM", Some("github.com")),
+            ("github.com:8443", Some("github.com")),
+            ("https://user:pw@github.com:443/x", Some("github.com")),
+            ("github.com.", Some("github.com")),
+            ("  www.example.org  ", Some("example.org")),
+            ("пример.рф", Some("xn--e1afmkfd.xn--p1ai")),
+            ("https://ПРИМЕР.РФ/путь", Some("xn--e1afmkfd.xn--p1ai")),
+            ("192.168.1.1:8080", Some("192.168.1.1")),
+            ("", None),
+            ("   ", None),
+            ("https://", None),
+        ];
+        for (input, want) in cases {
+            assert_eq!(normalize_host(input).as_deref(), want, "{input:?}");
+        }
+    }
+
+    #[test]
+    fn registrable_domain_table() {
+        let cases = [
+            ("github.com", "github.com"),
+            ("api.github.com", "github.com"),
+            ("a.b.c.github.com", "github.com"),
+            ("example.co.uk", "example.co.uk"),
+            ("login.example.co.uk", "example.co.uk"),
+            ("shop.example.com.au", "example.com.au"),
+            // `co` под трёхбуквенной зоной — обычный домен
+            ("app.co.com", "co.com"),
+            ("co.uk", "co.uk"),
+            ("localhost", "localhost"),
+            ("10.0.0.1", "10.0.0.1"),
+            ("[::1]", "[::1]"),
+        ];
+        for (host, want) in cases {
+            assert_eq!(registrable_domain(host), want, "{host:?}");
+        }
+    }
+
+    #[test]
+    fn host_matches_table() {
+        let cases = [
+            // (URL записи, запрос, loose, ожидание)
+            ("https://github.com/login", "github.com", false, true),
+            ("https://gist.github.com", "github.com", false, true),
+            ("https://github.com", "api.github.com", false, true),
+            ("https://github.com:8443", "GITHUB.COM", false, true),
+            ("https://www.github.com", "github.com", false, true),
+            ("https://notgithub.com", "github.com", false, false),
+            ("https://github.com.evil.io", "github.com", false, false),
+            ("https://a.example.co.uk", "b.example.co.uk", false, true),
+            ("https://example.co.uk", "other.co.uk", false, false),
+            ("https://пример.рф", "ПРИМЕР.РФ", false, true),
+            ("https://xn--e1afmkfd.xn--p1ai", "пример.рф", false, true),
+            ("https://mail.google.com", "google", false, false),
+            ("https://mail.google.com", "google", true, true),
+            ("https://mail.google.com", "GOOGLE", true, true),
+            ("https://mail.google.com", "yahoo", true, false),
+            ("not a url", "github.com", false, false),
+            ("", "github.com", true, false),
+            ("https://github.com", "", true, false),
+        ];
+        for (url, query, loose, want) in cases {
+            assert_eq!(
+                host_matches(url, query, loose),
+                want,
+                "{url:?} vs {query:?} (loose: {loose})"
+            );
+        }
+    }
+}
@@ -0,0 +1,452 @@
+//! `pm import ...`: перенос записей из других менеджеров паролей.
+//!
+//! Каждый формат только разбирает исходные данные в `Entry`, а сохранение,
+//! пропуск существующих путей, `--dry-run` и итоговая сводка общие — `Importer`.
+
+use crate::config::Config;
+use crate::crypto::MasterKey;
+use crate::entry::{ENTRY_SCHEMA_VERSION, Entry, EntryKind};
+use crate::git::auto_commit;
+use crate::hooks::{HookAction, run_hooks};
+use crate::session::get_master_key_with_cache;
+use crate::store::{entry_exists, save_entry, validate_entry_path};
+use anyhow::{Context, Result, anyhow};
+use std::collections::HashSet;
+use std::path::Path;
+use time::OffsetDateTime;
+
+/// Пустая запись-логин с текущими датами; поля заполняет импортёр.
+pub fn new_entry(title: &str) -> Result<Entry> {
+    let now = OffsetDateTime::now_utc().format(&time::format_description::well_known::Rfc3339)?;
+    Ok(Entry {
+        version: ENTRY_SCHEMA_VERSION,
+        title: title.to_string(),
+        username: None,
+        password: String::new(),
+        url: None,
+        notes: None,
+        created_at: now.clone(),
+        updated_at: now,
+        expires_at: None,
+        otp: None,
+        kind: EntryKind::Login,
+        wifi: None,
+        fields: Vec::new(),
+        password_history: Vec::new(),
+        tags: Vec::new(),
+        attachments: Vec::new(),
+    })
+}
+
+fn non_empty(s: &str) -> Option<String> {
+    let s = s.trim();
+    (!s.is_empty()).then(|| s.to_string())
+}
+
+/// `base`, а если он уже занят в этом импорте — `base-2`, `base-3`, ...
+pub fn unique_path(used: &mut HashSet<String>, base: &str) -> String {
+    let mut path = base.to_string();
+    let mut n = 1;
+    while !used.insert(path.clone()) {
+        n += 1;
+        path = format!("{base}-{n}");
+    }
+    path
+}
+
+/// Что делать, если запись с таким путём уже есть.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OnConflict {
+    /// Keep the local entry
+    #[default]
+    Skip,
+    /// Replace the local entry
+    Overwrite,
+    /// Import under path-2, path-3, ...
+    Rename,
+}
+
+/// Первый свободный в хранилище путь вида `path-2`, `path-3`, ...
+fn free_path(path: &str) -> Result<String> {
+    let mut n = 2;
+    loop {
+        let candidate = format!("{path}-{n}");
+        if !entry_exists(&candidate)? {
+            return Ok(candidate);
+        }
+        n += 1;
+    }
+}
+
+/// Сохраняет записи и считает итог: imported / skipped / failed.
+pub struct Importer {
+    /// `None` в режиме `--dry-run`: ничего не пишем и пароль не спрашиваем
+    unlocked: Option<(Config, MasterKey)>,
+    on_conflict: OnConflict,
+    imported: usize,
+    skipped: usize,
+    failed: usize,
+}
+
+impl Importer {
+    pub fn new(dry_run: bool) -> Result<Self> {
+        let unlocked = if dry_run {
+            None
+        } else {
+            let config = Config::load()?;
+            let mk = get_master_key_with_cache(&config)?;
+            Some((config, mk))
+        };
+        Ok(Importer {
+            unlocked,
+            on_conflict: OnConflict::Skip,
+            imported: 0,
+            skipped: 0,
+            failed: 0,
+        })
+    }
+
+    pub fn on_conflict(mut self, on_conflict: OnConflict) -> Self {
+        self.on_conflict = on_conflict;
+        self
+    }
+
+    /// Сохранить запись; занятый путь обрабатывается по `on_conflict`.
+    pub fn add(&mut self, path: &str, entry: Entry) {
+        self.add_with_attachments(path, entry, Vec::new());
+    }
+
+    /// То же с содержимым вложений (`pm import pmx`). Список вложений
+    /// записи строится по `files`: сами файлы в других форматах не
+    /// переносятся, и ссылок на них быть не должно.
+    pub fn add_with_attachments(
+        &mut self,
+        path: &str,
+        mut entry: Entry,
+        files: Vec<(String, Vec<u8>)>,
+    ) {
+        let mut path = match validate_entry_path(path) {
+            Ok(p) => p,
+            Err(e) => return self.fail(path, e),
+        };
+        let mut action = HookAction::Add;
+        match entry_exists(&path) {
+            Ok(false) => {}
+            Ok(true) => match self.on_conflict {
+                OnConflict::Skip => return self.skip(&path, "already exists"),
+                OnConflict::Overwrite => action = HookAction::Edit,
+                OnConflict::Rename => match free_path(&path) {
+                    Ok(p) => path = p,
+                    Err(e) => return self.fail(&path, e),
+                },
+            },
+            Err(e) => return self.fail(&path, e),
+        }
+        entry.title = path.clone();
+        let note = if action == HookAction::Edit {
+            " (replacing existing)"
+        } else {
+            ""
+        };
+
+        let Some((config, mk)) = &self.unlocked else {
+            println!("would import {path}{note}");
+            self.imported += 1;
+            return;
+        };
+        // Вложения заменяемой записи уходят вместе с ней
+        let saved = crate::attachments::replace_all(&path, &files, mk).and_then(|attachments| {
+            entry.attachments = attachments;
+            save_entry(&path, &entry, mk)
+        });
+        match saved {
+            Ok(()) => {
+                auto_commit(config, action, &path);
+                run_hooks(config, action, &path);
+                println!("imported {path}{note}");
+                self.imported += 1;
+            }
+            Err(e) => self.fail(&path, e),
+        }
+    }
+
+    pub fn skip(&mut self, what: &str, reason: &str) {
+        eprintln!("skip {what}: {reason}");
+        self.skipped += 1;
+    }
+
+    pub fn fail(&mut self, what: &str, err: anyhow::Error) {
+        eprintln!("Failed {what}: {err:#}");
+        self.failed += 1;
+    }
+
+    /// Напечатать сводку; ошибка, если хоть что-то не удалось.
+    pub fn finish(self) -> Result<()> {
+        let verb = if self.unlocked.is_some() {
+            "imported"
+        } else {
+            "would import"
+        };
+        println!(
+            "{verb} {}, skipped {}, failed {}",
+            self.imported, self.skipped, self.failed
+        );
+        if self.failed > 0 {
+            return Err(anyhow!("{} entries could not be imported", self.failed));
+        }
+        Ok(())
+    }
+}
+
+/// Разобрать расшифрованный файл pass: первая строка — пароль, строки
+/// `ключ: значение` с известными ключами — логин и адрес, `otpauth://` — OTP,
+/// всё остальное — заметки.
+pub fn parse_pass_entry(title: &str, text: &str) -> Result<Entry> {
+    let mut entry = new_entry(title)?;
+    let mut lines = text.lines();
+    entry.password = lines.next().unwrap_or_default().to_string();
+
+    let mut notes = Vec::new();
+    for line in lines {
+        let trimmed = line.trim();
+        if trimmed.starts_with("otpauth://") && entry.otp.is_none() {
+            entry.otp = Some(crate::otp::parse_otp_input(trimmed)?);
+            continue;
+        }
+        if let Some((key, value)) = trimmed.split_once(':') {
+            let value = value.trim();
+            match key.trim().to_lowercase().as_str() {
+                "user" | "username" | "login" | "email" if entry.username.is_none() => {
+                    entry.username = non_empty(value);
+                    continue;
+                }
+                "url" | "website" | "site" if entry.url.is_none() => {
+                    entry.url = non_empty(value);
+                    continue;
+                }
+                _ => {}
+            }
+        }
+        notes.push(line);
+    }
+    entry.notes = non_empty(&notes.join("\n"));
+    Ok(entry)
+}
+
+fn gpg_decrypt(file: &Path) -> Result<String> {
+    let out = std::process::Command::new("gpg")
+        .args(["--quiet", "--decrypt"])
+        .arg(file)
+        .stderr(std::process::Stdio::piped())
+        .output()
+        .map_err(|e| anyhow!("failed to run gpg: {e}"))?;
+    if !out.status.success() {
+        return Err(anyhow!(
+            "gpg failed: {}",
+            String::from_utf8_lossy(&out.stderr).trim()
+        ));
+    }
+    String::from_utf8(out.stdout).context("decrypted file is not UTF-8")
+}
+
+/// pm import pass <dir> [--dry-run]
+pub fn import_pass(dir: &Path, dry_run: bool) -> Result<()> {
+    if !dir.is_dir() {
+        return Err(anyhow!("{} is not a directory", dir.display()));
+    }
+    let mut files: Vec<_> = walkdir::WalkDir::new(dir)
+        .into_iter()
+        // .git и прочие служебные каталоги pass
+        .filter_entry(|e| e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('.'))
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file() && e.path().extension().is_some_and(|x| x == "gpg"))
+        .map(|e| e.into_path())
+        .collect();
+    files.sort();
+
+    let mut importer = Importer::new(dry_run)?;
+    for file in files {
+        let rel = file.strip_prefix(dir)?.with_extension("");
+        let path = rel
+            .to_string_lossy()
+            .replace(std::path::MAIN_SEPARATOR, "/");
+        match gpg_decrypt(&file).and_then(|text| parse_pass_entry(&path, &text)) {
+            Ok(entry) => importer.add(&path, entry),
+            Err(e) => importer.fail(&path, e),
+        }
+    }
+    importer.finish()
+}
+
+/// Колонки CSV. Без флагов определяются по заголовку (форматы Chrome,
+/// Firefox, Bitwarden, 1Password, LastPass и похожие).
+#[derive(clap::Args, Debug, Default)]
+pub struct CsvColumns {
+    /// Column with the entry name (used to build the path)
+    #[arg(long)]
+    pub path_column: Option<String>,
+    /// Column with the username
+    #[arg(long)]
+    pub username_column: Option<String>,
+    /// Column with the password
+    #[arg(long)]
+    pub password_column: Option<String>,
+    /// Column with the URL
+    #[arg(long)]
+    pub url_column: Option<String>,
+    /// Column with notes
+    #[arg(long)]
+    pub notes_column: Option<String>,
+    /// Column with a folder/group that becomes the parent directory
+    #[arg(long)]
+    pub folder_column: Option<String>,
+    /// Column with a TOTP secret or otpauth:// URL
+    #[arg(long)]
+    pub otp_column: Option<String>,
+}
+
+/// Индекс колонки: явно заданной (ошибка, если её нет) или первой из известных имён.
+fn find_column(header: &[String], explicit: Option<&str>, known: &[&str]) -> Result<Option<usize>> {
+    let pos = |name: &str| {
+        header
+            .iter()
+            .position(|h| h.trim().eq_ignore_ascii_case(name.trim()))
+    };
+    match explicit {
+        Some(name) => pos(name)
+            .map(Some)
+            .ok_or_else(|| anyhow!("CSV has no column {name:?}")),
+        None => Ok(known.iter().find_map(|k| pos(k))),
+    }
+}
+
+/// Путь записи из имени: `/` разделяет папки, пустые и `.`/`..` части
+/// выбрасываются, символы, недопустимые в именах файлов, убираются.
+pub fn slugify_path(name: &str) -> String {
+    name.split(['/', '\\'])
+        .map(|part| {
+            part.chars()
+                .filter(|c| {
+                    !c.is_control() && !matches!(c, '<' | '>' | ':' | '"' | '|' | '?' | '*')
+                })
+                .collect::<String>()
+                .trim()
+                .to_string()
+        })
+        .filter(|p| !p.is_empty() && p != "." && p != "..")
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// pm import csv <file> [--*-column NAME] [--prefix P] [--dry-run]
+pub fn import_csv(
+    file: &Path,
+    columns: &CsvColumns,
+    prefix: Option<&str>,
+    dry_run: bool,
+) -> Result<()> {
+    let text =
+        std::fs::read_to_string(file).with_context(|| format!("cannot read {}", file.display()))?;
+    let mut rows = crate::csv::parse(&text)?.into_iter();
+    let header = rows.next().ok_or_else(|| anyhow!("CSV file is empty"))?;
+
+    let col = |explicit: &Option<String>, known: &[&str]| {
+        find_column(&header, explicit.as_deref(), known)
+    };
+    let name_col = col(&columns.path_column, &["name", "title", "account", "entry"])?;
+    let username_col = col(
+        &columns.username_column,
+        &["username", "login_username", "login", "user", "email"],
+    )?;
+    let password_col = col(
+        &columns.password_column,
+        &["password", "login_password", "pass"],
+    )?
+    .ok_or_else(|| anyhow!("CSV has no password column; pass --password-column"))?;
+    let url_col = col(
+        &columns.url_column,
+        &["url", "login_uri", "website", "web site", "uri"],
+    )?;
+    let notes_col = col(
+        &columns.notes_column,
+        &["notes", "note", "extra", "comments"],
+    )?;
+    let folder_col = col(
+        &columns.folder_column,
+        &["folder", "grouping", "group", "category"],
+    )?;
+    let otp_col = col(
+        &columns.otp_column,
+        &["totp", "otp", "login_totp", "otpauth"],
+    )?;
+    if name_col.is_none() && url_col.is_none() {
+        return Err(anyhow!(
+            "CSV has neither a name nor a URL column; pass --path-column"
+        ));
+    }
+
+    let prefix = prefix.map(slugify_path).filter(|p| !p.is_empty());
+    let mut importer = Importer::new(dry_run)?;
+    let mut used = HashSet::new();
+    // Строка 1 — заголовок
+    for (i, row) in rows.enumerate() {
+        let line = format!("row {}", i + 2);
+        let get = |c: Option<usize>| {
+            c.and_then(|c| row.get(c))
+                .map(|v| v.trim())
+                .unwrap_or_default()
+        };
+
+        // Без имени — по хосту из адреса
+        let mut name = slugify_path(get(name_col));
+        if name.is_empty() {
+            name = url::Url::parse(get(url_col))
+                .ok()
+                .and_then(|u| u.host_str().map(slugify_path))
+                .unwrap_or_default();
+        }
+        if name.is_empty() {
+            importer.skip(&line, "no name or URL");
+            continue;
+        }
+        let path = [
+            prefix.clone(),
+            Some(slugify_path(get(folder_col))),
+            Some(name),
+        ]
+        .into_iter()
+        .flatten()
+        .filter(|p| !p.is_empty())
+        .collect::<Vec<_>>()
+        .join("/");
+        let path = unique_path(&mut used, &path);
+
+        // Пароль берём как есть, без обрезки пробелов
+        let password = row
+            .get(password_col)
+            .map(String::as_str)
+            .unwrap_or_default();
+        if password.is_empty() {
+            importer.skip(&format!("{path} ({line})"), "empty password");
+            continue;
+        }
+
+        let entry = (|| -> Result<Entry> {
+            let mut entry = new_entry(&path)?;
+            entry.password = password.to_string();
+            entry.username = non_empty(get(username_col));
+            entry.url = non_empty(get(url_col));
+            entry.notes = non_empty(get(notes_col));
+            let otp = get(otp_col);
+            if !otp.is_empty() {
+                entry.otp = Some(crate::otp::parse_otp_input(otp)?);
+            }
+            Ok(entry)
+        })();
+        match entry {
+            Ok(e) => importer.add(&path, e),
+            Err(e) => importer.fail(&format!("{path} ({line})"), e),
+        }
+    }
+    importer.finish()
+}
@@ -0,0 +1,231 @@
+//! Зашифрованный индекс метаданных записей: `index.enc` в корне хранилища.
+//!
+//! Списки и поиск (`pm ls --tag`, фильтры по датам, `pm expiring`, `pm tag list`,
+//! `pm grep` по несекретным полям) берут заголовок, логин, URL, метки и даты
+//! отсюда, а не расшифровывают каждую запись. Индекс — один AEAD-блоб под
+//! мастер-ключом; паролей, заметок, дополнительных полей и секретов OTP в нём нет.
+//!
+//! Индекс обновляется при каждом сохранении записи. У каждой строки есть
+//! SHA-256 файла записи на момент индексации: не совпал (запись изменили в обход,
+//! восстановили из бэкапа) или строки нет — запись расшифровывается, а индекс
+//! переписывается. Удалённые записи отбрасываются при следующем чтении, так что
+//! удалению мастер-ключ не нужен. Потерянный или битый индекс строится заново.
+
+use crate::crypto::{MasterKey, decrypt_entry, encrypt_entry};
+use crate::entry::Entry;
+use crate::store::{
+    decrypt_all, entry_file_path_in, store_root, validate_entry_path, write_atomic_private,
+};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+const INDEX_VERSION: u32 = 1;
+/// Привязка блоба к назначению: файл записи под именем индекса не расшифруется.
+const INDEX_AAD: &[u8] = b"pm index v1";
+
+/// Несекретные поля записи.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntryMeta {
+    pub title: String,
+    pub username: Option<String>,
+    pub url: Option<String>,
+    pub ssid: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub created_at: String,
+    pub updated_at: String,
+    pub expires_at: Option<String>,
+    pub has_otp: bool,
+    /// SHA-256 файла записи (hex), по которому индексировали.
+    hash: String,
+}
+
+impl EntryMeta {
+    fn new(entry: &Entry, hash: String) -> Self {
+        EntryMeta {
+            title: entry.title.clone(),
+            username: entry.username.clone(),
+            url: entry.url.clone(),
+            ssid: entry.wifi.as_ref().map(|w| w.ssid.clone()),
+            tags: entry.tags.clone(),
+            created_at: entry.created_at.clone(),
+            updated_at: entry.updated_at.clone(),
+            expires_at: entry.expires_at.clone(),
+            has_otp: entry.otp.is_some(),
+            hash,
+        }
+    }
+
+    /// Поле по имени, как `Entry::field`; `None` — поля нет в индексе.
+    pub fn field(&self, name: &str) -> Option<Option<&str>> {
+        Some(match name {
+            "title" => Some(self.title.as_str()),
+            "username" => self.username.as_deref(),
+            "url" => self.url.as_deref(),
+            "ssid" => self.ssid.as_deref(),
+            _ => return None,
+        })
+    }
+}
+
+/// Поля, которые можно искать по индексу (см. `EntryMeta::field`).
+pub const INDEXED_FIELDS: &[&str] = &["title", "username", "url", "ssid"];
+
+#[derive(Serialize, Deserialize)]
+struct IndexFile {
+    version: u32,
+    nonce: String,
+    ciphertext: String,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct Index {
+    entries: BTreeMap<String, EntryMeta>,
+}
+
+fn index_path(root: &Path) -> PathBuf {
+    root.join("index.enc")
+}
+
+fn file_hash(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Прочитать индекс; нет файла, чужой ключ или битый блоб — пустой индекс.
+fn load(root: &Path, mk: &MasterKey) -> Index {
+    let read = || -> Result<Index> {
+        let file: IndexFile = serde_json::from_slice(&std::fs::read(index_path(root))?)?;
+        if file.version != INDEX_VERSION {
+            return Ok(Index::default());
+        }
+        let plain = decrypt_entry(mk, &file.nonce, &file.ciphertext, INDEX_AAD)?;
+        Ok(serde_json::from_slice(&plain)?)
+    };
+    read().unwrap_or_default()
+}
+
+fn save(root: &Path, mk: &MasterKey, index: &Index) -> Result<()> {
+    let (nonce, ciphertext) = encrypt_entry(mk, &serde_json::to_vec(index)?, INDEX_AAD)?;
+    let file = IndexFile {
+        version: INDEX_VERSION,
+        nonce,
+        ciphertext,
+    };
+    write_atomic_private(&index_path(root), serde_json::to_string(&file)?.as_bytes())
+}
+
+/// Обновить строку индекса после записи файла `file_data` для `path`.
+/// Ошибка — только предупреждение: запись уже сохранена, а устаревшую
+/// строку выдаст несовпавший хеш.
+pub(crate) fn record_saved(
+    store_dir: &Path,
+    path: &str,
+    entry: &Entry,
+    file_data: &[u8],
+    mk: &MasterKey,
+) {
+    let (Some(root), Ok(path)) = (store_dir.parent(), validate_entry_path(path)) else {
+        return;
+    };
+    let mut index = load(root, mk);
+    index
+        .entries
+        .insert(path, EntryMeta::new(entry, file_hash(file_data)));
+    if let Err(e) = save(root, mk, &index) {
+        eprintln!("Warning: cannot update the index: {e}");
+    }
+}
+
+/// Метаданные записей `paths` в том же порядке. Устаревшие и отсутствующие
+/// в индексе строки берутся расшифровкой записей, и индекс переписывается.
+pub fn metadata(mk: &MasterKey, paths: Vec<String>) -> Result<Vec<(String, Result<EntryMeta>)>> {
+    let root = store_root()?;
+    let store_dir = root.join("store");
+    let mut index = load(&root, mk);
+
+    let mut hashes = HashMap::new();
+    let mut stale = Vec::new();
+    for path in &paths {
+        let hash = entry_file_path_in(&store_dir, path)
+            .and_then(|f| Ok(std::fs::read(f)?))
+            .map(|data| file_hash(&data))
+            .unwrap_or_default();
+        if index.entries.get(path).is_none_or(|m| m.hash != hash) {
+            stale.push(path.clone());
+        }
+        hashes.insert(path.clone(), hash);
+    }
+
+    let mut failed = HashMap::new();
+    let mut changed = false;
+    for (path, entry) in decrypt_all(mk, stale) {
+        match entry {
+            Ok(e) => {
+                let meta = EntryMeta::new(&e, hashes.remove(&path).unwrap_or_default());
+                index.entries.insert(path, meta);
+                changed = true;
+            }
+            Err(e) => {
+                failed.insert(path, e);
+            }
+        }
+    }
+    // Строки удалённых записей
+    let before = index.entries.len();
+    index
+        .entries
+        .retain(|path, _| entry_file_path_in(&store_dir, path).is_ok_and(|f| f.is_file()));
+    changed |= index.entries.len() != before;
+    if changed
+        && !crate::lock::is_read_only()
+        && let Err(e) = save(&root, mk, &index)
+    {
+        eprintln!("Warning: cannot update the index: {e}");
+    }
+
+    Ok(paths
+        .into_iter()
+        .map(|path| {
+            let meta = match failed.remove(&path) {
+                Some(e) => Err(e),
+                None => index
+                    .entries
+                    .get(&path)
+                    .cloned()
+                    .ok_or_else(|| anyhow::anyhow!("Entry not found: {path}")),
+            };
+            (path, meta)
+        })
+        .collect())
+}
+
+/// pm index rebuild: выбросить индекс и построить заново по всем записям.
+pub fn rebuild() -> Result<()> {
+    let config = crate::config::Config::load()?;
+    let mk = crate::session::get_master_key_with_cache(&config)?;
+    let root = store_root()?;
+    match std::fs::remove_file(index_path(&root)) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+    let results = metadata(&mk, crate::store::list_entries()?)?;
+    let mut indexed = 0;
+    for (path, meta) in &results {
+        match meta {
+            Ok(_) => indexed += 1,
+            Err(e) => eprintln!("Warning: cannot decrypt {path}: {e}"),
+        }
+    }
+    if !index_path(&root).exists() {
+        save(&root, &mk, &Index::default())?;
+    }
+    crate::output::done("index", "", format!("Indexed {indexed} entries."));
+    Ok(())
+}
@@ -0,0 +1,457 @@
+//! `pm import keepass`: записи из базы KeePass.
+//!
+//! Сам формат KDBX не разбираем: `keepassxc-cli export --format xml`
+//! расшифровывает базу в XML KeePass 2, а его читаем здесь. Готовый
+//! XML-экспорт (KeePass: File → Export → KeePass XML) можно передать напрямую.
+
+use crate::entry::{Entry, OtpConfig};
+use crate::import::{Importer, new_entry, unique_path};
+use crate::prompt::prompt_password_hidden;
+use anyhow::{Context, Result, anyhow};
+use quick_xml::events::Event;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Узел XML: имя, текст и дочерние элементы. Базы KeePass небольшие,
+/// так что проще построить дерево целиком, чем разбирать потоком.
+#[derive(Default)]
+struct Node {
+    name: String,
+    text: String,
+    children: Vec<Node>,
+}
+
+impl Node {
+    fn child(&self, name: &str) -> Option<&Node> {
+        self.children.iter().find(|c| c.name == name)
+    }
+
+    fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Node> {
+        self.children.iter().filter(move |c| c.name == name)
+    }
+
+    fn child_text(&self, name: &str) -> &str {
+        self.child(name)
+            .map(|c| c.text.as_str())
+            .unwrap_or_default()
+    }
+}
+
+fn parse_xml(xml: &str) -> Result<Node> {
+    let mut reader = quick_xml::Reader::from_str(xml);
+    let mut stack = vec![Node::default()];
+    loop {
+        match reader.read_event().context("invalid KeePass XML")? {
+            Event::Start(e) => stack.push(Node {
+                name: String::from_utf8_lossy(e.name().as_ref()).into_owned(),
+                ..Node::default()
+            }),
+            Event::Empty(e) => {
+                let node = Node {
+                    name: String::from_utf8_lossy(e.name().as_ref()).into_owned(),
+                    ..Node::default()
+                };
+                stack.last_mut().unwrap().children.push(node);
+            }
+            Event::Text(t) => stack.last_mut().unwrap().text.push_str(&t.unescape()?),
+            Event::CData(c) => stack.last_mut().unwrap().text.push_str(&c.decode()?),
+            Event::End(_) => {
+                let node = stack.pop().unwrap();
+                let parent = stack
+                    .last_mut()
+                    .ok_or_else(|| anyhow!("invalid KeePass XML: unbalanced tags"))?;
+                parent.children.push(node);
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    let doc = stack.pop().filter(|_| stack.is_empty());
+    doc.ok_or_else(|| anyhow!("invalid KeePass XML: unclosed tags"))
+}
+
+/// `/` в названиях групп и записей превратил бы их во вложенные папки.
+fn path_component(name: &str) -> String {
+    let name = name.trim().replace(['/', '\\'], "-");
+    if name.is_empty() {
+        "untitled".to_string()
+    } else {
+        name
+    }
+}
+
+/// TOTP из дополнительных полей: `otp` (otpauth:// — KeePassXC) или
+/// `TOTP Seed` + `TOTP Settings` (`период;цифры`, KeeTrayTOTP).
+fn otp_from_fields(fields: &HashMap<&str, &str>) -> Result<Option<OtpConfig>> {
+    if let Some(uri) = fields.get("otp").filter(|v| !v.trim().is_empty()) {
+        return crate::otp::parse_otp_input(uri.trim()).map(Some);
+    }
+    let Some(seed) = fields.get("TOTP Seed").filter(|v| !v.trim().is_empty()) else {
+        return Ok(None);
+    };
+    let seed: String = seed.chars().filter(|c| !c.is_whitespace()).collect();
+    let mut otp = crate::otp::parse_otp_input(&seed)?;
+    if let Some(settings) = fields.get("TOTP Settings") {
+        let mut parts = settings.split(';');
+        if let Some(period) = parts.next().and_then(|p| p.trim().parse().ok()) {
+            otp.period = period;
+        }
+        if let Some(digits) = parts.next().and_then(|d| d.trim().parse().ok()) {
+            otp.digits = digits;
+        }
+    }
+    Ok(Some(otp))
+}
+
+fn convert_entry(node: &Node, path: &str) -> Result<Entry> {
+    let fields: HashMap<&str, &str> = node
+        .children_named("String")
+        .map(|s| (s.child_text("Key"), s.child_text("Value")))
+        .collect();
+    let get = |k: &str| {
+        fields
+            .get(k)
+            .map(|v| v.trim())
+            .filter(|v| !v.is_empty())
+            .map(str::to_string)
+    };
+
+    let mut entry = new_entry(path)?;
+    entry.username = get("UserName");
+    entry.password = fields
+        .get("Password")
+        .copied()
+        .unwrap_or_default()
+        .to_string();
+    entry.url = get("URL");
+    entry.notes = get("Notes");
+    entry.otp = otp_from_fields(&fields)?;
+    Ok(entry)
+}
+
+/// Что нашлось в базе: запись по её будущему пути или пропущенная корзина.
+enum Found {
+    Entry(String, Result<Box<Entry>>),
+    RecycleBin(String),
+}
+
+/// Обойти группу: записи получают путь `группа/подгруппа/название`,
+/// одинаковые названия в одной группе — суффиксы `-2`, `-3` по порядку в файле.
+fn walk_group(group: &Node, prefix: &str, recycle_bin: Option<&str>, found: &mut Vec<Found>) {
+    if recycle_bin.is_some_and(|uuid| group.child_text("UUID") == uuid) {
+        found.push(Found::RecycleBin(group.child_text("Name").to_string()));
+        return;
+    }
+
+    let mut used = std::collections::HashSet::new();
+    // Вложенный <History> содержит старые версии записей — они не нужны
+    for entry in group.children_named("Entry") {
+        let title = entry
+            .children_named("String")
+            .find(|s| s.child_text("Key") == "Title")
+            .map(|s| s.child_text("Value"))
+            .unwrap_or_default();
+        let path = unique_path(&mut used, &format!("{prefix}{}", path_component(title)));
+        let entry = convert_entry(entry, &path).map(Box::new);
+        found.push(Found::Entry(path, entry));
+    }
+
+    for sub in group.children_named("Group") {
+        let sub_prefix = format!("{prefix}{}/", path_component(sub.child_text("Name")));
+        walk_group(sub, &sub_prefix, recycle_bin, found);
+    }
+}
+
+/// Расшифровать KDBX через keepassxc-cli; пароль уходит через stdin.
+fn export_kdbx(file: &Path, keyfile: Option<&Path>) -> Result<String> {
+    let password = prompt_password_hidden("KeePass password: ")?;
+    let mut cmd = Command::new("keepassxc-cli");
+    cmd.args(["export", "--format", "xml", "--quiet"]);
+    if let Some(k) = keyfile {
+        cmd.arg("--key-file").arg(k);
+    }
+    let mut child = cmd
+        .arg(file)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| anyhow!("failed to run keepassxc-cli (is KeePassXC installed?): {e}"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        writeln!(stdin, "{password}")?;
+    }
+    let out = child.wait_with_output()?;
+    if !out.status.success() {
+        return Err(anyhow!(
+            "keepassxc-cli failed: {}",
+            String::from_utf8_lossy(&out.stderr).trim()
+        ));
+    }
+    String::from_utf8(out.stdout).context("keepassxc-cli output is not UTF-8")
+}
+
+/// pm import keepass <file> [--keyfile K] [--include-recycled] [--dry-run]
+pub fn import_keepass(
+    file: &Path,
+    keyfile: Option<&Path>,
+    include_recycled: bool,
+    dry_run: bool,
+) -> Result<()> {
+    let is_xml = file
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("xml"));
+    let xml = if is_xml {
+        std::fs::read_to_string(file).with_context(|| format!("cannot read {}", file.display()))?
+    } else {
+        export_kdbx(file, keyfile)?
+    };
+
+    let mut importer = Importer::new(dry_run)?;
+    for found in read_database(&xml, include_recycled)? {
+        match found {
+            Found::Entry(path, Ok(entry)) => importer.add(&path, *entry),
+            Found::Entry(path, Err(e)) => importer.fail(&path, e),
+            Found::RecycleBin(name) => {
+                importer.skip(&name, "recycle bin (use --include-recycled to import it)")
+            }
+        }
+    }
+    importer.finish()
+}
+
+/// Записи базы из XML KeePass 2 в порядке файла.
+fn read_database(xml: &str, include_recycled: bool) -> Result<Vec<Found>> {
+    let doc = parse_xml(xml)?;
+    let keepass = doc
+        .child("KeePassFile")
+        .ok_or_else(|| anyhow!("not a KeePass XML file"))?;
+    let meta = keepass.child("Meta");
+    let recycle_bin = meta
+        .filter(|m| !include_recycled && m.child_text("RecycleBinEnabled") != "False")
+        .map(|m| m.child_text("RecycleBinUUID"))
+        .filter(|uuid| !uuid.is_empty());
+    let root = keepass
+        .child("Root")
+        .and_then(|r| r.child("Group"))
+        .ok_or_else(|| anyhow!("KeePass XML has no root group"))?;
+
+    let mut found = Vec::new();
+    // Корневая группа — это сама база, в путь её имя не входит
+    walk_group(root, "", recycle_bin, &mut found);
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &str = r#"<?xml version="1.0" encoding="utf-8" standalone="yes"?>
+<KeePassFile>
+    <Meta>
+        <DatabaseName>Test</DatabaseName>
+        <RecycleBinEnabled>True</RecycleBinEnabled>
+        <RecycleBinUUID>YmluYmluYmluYmluYmluYg==</RecycleBinUUID>
+    </Meta>
+    <Root>
+        <Group>
+            <UUID>cm9vdHJvb3Ryb290cm9vdA==</UUID>
+            <Name>Database</Name>
+            <Entry>
+                <String><Key>Title</Key><Value>GitHub</Value></String>
+                <String><Key>UserName</Key><Value>alice</Value></String>
+                <String><Key>Password</Key><Value> p&amp;ss&lt;word&gt; </Value></String>
+                <String><Key>URL</Key><Value>https://github.com</Value></String>
+                <String><Key>Notes</Key><Value><![CDATA[line 1
+line 2]]></Value></String>
+                <String><Key>otp</Key><Value>otpauth://totp/GitHub:alice?secret=JBSWY3DPEHPK3PXP&amp;period=30&amp;digits=6</Value></String>
+                <History>
+                    <Entry>
+                        <String><Key>Title</Key><Value>GitHub</Value></String>
+                        <String><Key>Password</Key><Value>old</Value></String>
+                    </Entry>
+                </History>
+            </Entry>
+            <Entry>
+                <String><Key>Title</Key><Value>GitHub</Value></String>
+                <String><Key>Password</Key><Value>second</Value></String>
+            </Entry>
+            <Entry>
+                <String><Key>Title</Key><Value></Value></String>
+                <String><Key>Password</Key><Value>anon</Value></String>
+            </Entry>
+            <Group>
+                <UUID>d29ya3dvcmt3b3Jrd29yaw==</UUID>
+                <Name>Work / VPN</Name>
+                <Entry>
+                    <String><Key>Title</Key><Value>gw.example.com</Value></String>
+                    <String><Key>Password</Key><Value>vpn</Value></String>
+                    <String><Key>TOTP Seed</Key><Value>JBSW Y3DP EHPK 3PXP</Value></String>
+                    <String><Key>TOTP Settings</Key><Value>60;8</Value></String>
+                </Entry>
+                <Group>
+                    <UUID>bmVzdG5lc3RuZXN0bmVzdA==</UUID>
+                    <Name>Nested</Name>
+                    <Entry>
+                        <String><Key>Title</Key><Value>GitHub</Value></String>
+                        <String><Key>Password</Key><Value>nested</Value></String>
+                    </Entry>
+                </Group>
+            </Group>
+            <Group>
+                <UUID>YmluYmluYmluYmluYmluYg==</UUID>
+                <Name>Recycle Bin</Name>
+                <Entry>
+                    <String><Key>Title</Key><Value>deleted</Value></String>
+                    <String><Key>Password</Key><Value>gone</Value></String>
+                </Entry>
+            </Group>
+        </Group>
+    </Root>
+</KeePassFile>
+"#;
+
+    fn paths(found: &[Found]) -> Vec<&str> {
+        found
+            .iter()
+            .filter_map(|f| match f {
+                Found::Entry(path, _) => Some(path.as_str()),
+                Found::RecycleBin(_) => None,
+            })
+            .collect()
+    }
+
+    fn entry<'a>(found: &'a [Found], path: &str) -> &'a Entry {
+        found
+            .iter()
+            .find_map(|f| match f {
+                Found::Entry(p, Ok(e)) if p == path => Some(e),
+                _ => None,
+            })
+            .unwrap_or_else(|| panic!("no entry {path}"))
+    }
+
+    #[test]
+    fn parse_xml_builds_the_tree_and_unescapes_text() {
+        let doc = parse_xml(FIXTURE).unwrap();
+        let keepass = doc.child("KeePassFile").unwrap();
+        assert_eq!(
+            keepass.child("Meta").unwrap().child_text("DatabaseName"),
+            "Test"
+        );
+        let root = keepass.child("Root").unwrap().child("Group").unwrap();
+        assert_eq!(root.child_text("Name"), "Database");
+        assert_eq!(root.children_named("Entry").count(), 3);
+        assert_eq!(root.children_named("Group").count(), 2);
+        let password = root
+            .child("Entry")
+            .unwrap()
+            .children_named("String")
+            .find(|s| s.child_text("Key") == "Password")
+            .unwrap();
+        assert_eq!(password.child_text("Value"), " p&ss<word> ");
+        assert_eq!(password.child_text("Missing"), "");
+    }
+
+    #[test]
+    fn parse_xml_rejects_unbalanced_documents() {
+        assert!(parse_xml("<KeePassFile><Root>").is_err());
+        assert!(parse_xml("<KeePassFile></Root></KeePassFile>").is_err());
+    }
+
+    #[test]
+    fn group_names_become_path_components() {
+        let found = read_database(FIXTURE, false).unwrap();
+        assert_eq!(
+            paths(&found),
+            [
+                "GitHub",
+                "GitHub-2",
+                "untitled",
+                "Work - VPN/gw.example.com",
+                "Work - VPN/Nested/GitHub",
+            ]
+        );
+    }
+
+    #[test]
+    fn fields_map_to_the_entry_and_history_is_ignored() {
+        let found = read_database(FIXTURE, false).unwrap();
+        let github = entry(&found, "GitHub");
+        assert_eq!(github.title, "GitHub");
+        assert_eq!(github.username.as_deref(), Some("alice"));
+        // Пароль не обрезаем: пробелы могут быть его частью
+        assert_eq!(github.password, " p&ss<word> ");
+        assert_eq!(github.url.as_deref(), Some("https://github.com"));
+        assert_eq!(github.notes.as_deref(), Some("line 1\nline 2"));
+        assert_eq!(entry(&found, "GitHub-2").password, "second");
+        assert_eq!(entry(&found, "untitled").username, None);
+    }
+
+    #[test]
+    fn otp_comes_from_the_uri_or_keetraytotp_fields() {
+        let found = read_database(FIXTURE, false).unwrap();
+        let uri = entry(&found, "GitHub").otp.as_ref().unwrap();
+        assert_eq!(uri.secret, "JBSWY3DPEHPK3PXP");
+        assert_eq!((uri.period, uri.digits), (30, 6));
+
+        let seed = entry(&found, "Work - VPN/gw.example.com")
+            .otp
+            .as_ref()
+            .unwrap();
+        assert_eq!(seed.secret, "JBSWY3DPEHPK3PXP");
+        assert_eq!((seed.period, seed.digits), (60, 8));
+
+        assert!(entry(&found, "GitHub-2").otp.is_none());
+    }
+
+    #[test]
+    fn otp_uri_wins_over_keetraytotp_fields() {
+        let fields = HashMap::from([
+            ("otp", "otpauth://totp/x?secret=JBSWY3DPEHPK3PXP&period=45"),
+            ("TOTP Seed", "GEZDGNBVGY3TQOJQ"),
+            ("TOTP Settings", "60;8"),
+        ]);
+        let otp = otp_from_fields(&fields).unwrap().unwrap();
+        assert_eq!(otp.secret, "JBSWY3DPEHPK3PXP");
+        assert_eq!((otp.period, otp.digits), (45, 6));
+
+        let blank = HashMap::from([("otp", " "), ("TOTP Seed", "")]);
+        assert!(otp_from_fields(&blank).unwrap().is_none());
+    }
+
+    #[test]
+    fn recycle_bin_is_skipped_unless_included() {
+        let found = read_database(FIXTURE, false).unwrap();
+        assert!(!paths(&found).iter().any(|p| p.contains("deleted")));
+        let skipped: Vec<_> = found
+            .iter()
+            .filter_map(|f| match f {
+                Found::RecycleBin(name) => Some(name.as_str()),
+                Found::Entry(..) => None,
+            })
+            .collect();
+        assert_eq!(skipped, ["Recycle Bin"]);
+
+        let found = read_database(FIXTURE, true).unwrap();
+        assert!(found.iter().all(|f| matches!(f, Found::Entry(..))));
+        assert_eq!(entry(&found, "Recycle Bin/deleted").password, "gone");
+    }
+
+    #[test]
+    fn disabled_recycle_bin_is_an_ordinary_group() {
+        let xml = FIXTURE.replace(
+            "<RecycleBinEnabled>True</RecycleBinEnabled>",
+            "<RecycleBinEnabled>False</RecycleBinEnabled>",
+        );
+        let found = read_database(&xml, false).unwrap();
+        assert!(paths(&found).contains(&"Recycle Bin/deleted"));
+    }
+
+    #[test]
+    fn non_keepass_xml_is_rejected() {
+        let err = read_database("<html></html>", false).err().unwrap();
+        assert!(err.to_string().contains("not a KeePass XML file"));
+    }
+}
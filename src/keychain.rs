@@ -0,0 +1,200 @@
+//! Хранение мастер-пароля в системной связке ключей (по желанию).
+//!
+//! Как и буфер обмена, работаем через системные утилиты:
+//! Linux — `secret-tool` (libsecret / GNOME Keyring), macOS — `security`.
+//! Пароль передаётся через stdin, а не аргументами, чтобы не светить его в `ps`.
+//! Здесь же лежит и кэш сессии, если он живёт в связке ключей (см. `session`).
+
+use crate::config::{Config, config_path, save_config};
+use crate::crypto::unlock_master_key;
+use crate::prompt::prompt_password_hidden;
+use crate::store::store_root;
+use anyhow::{Result, anyhow};
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+use std::io::Write;
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+use std::process::{Command, Stdio};
+
+const SERVICE: &str = "pm";
+const PASSWORD_LABEL: &str = "pm master password";
+
+/// Для каждого хранилища своя запись: ключом служит путь к нему.
+fn account() -> Result<String> {
+    Ok(store_root()?.display().to_string())
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn run_with_stdin(cmd: &str, args: &[&str], input: &[u8]) -> Result<std::process::Output> {
+    let mut child = Command::new(cmd)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| anyhow!("failed to spawn {cmd}: {e}"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(input)?;
+    }
+    Ok(child.wait_with_output()?)
+}
+
+#[cfg(target_os = "linux")]
+mod backend {
+    use super::*;
+
+    pub fn get(account: &str) -> Option<String> {
+        let out = Command::new("secret-tool")
+            .args(["lookup", "service", SERVICE, "account", account])
+            .stderr(Stdio::null())
+            .output()
+            .ok()?;
+        (out.status.success() && !out.stdout.is_empty())
+            .then(|| String::from_utf8_lossy(&out.stdout).into_owned())
+    }
+
+    pub fn set(account: &str, label: &str, password: &str) -> Result<()> {
+        let out = run_with_stdin(
+            "secret-tool",
+            &[
+                "store",
+                &format!("--label={label}"),
+                "service",
+                SERVICE,
+                "account",
+                account,
+            ],
+            password.as_bytes(),
+        )?;
+        if !out.status.success() {
+            return Err(anyhow!(
+                "secret-tool failed: {}",
+                String::from_utf8_lossy(&out.stderr).trim()
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn delete(account: &str) -> Result<()> {
+        Command::new("secret-tool")
+            .args(["clear", "service", SERVICE, "account", account])
+            .stderr(Stdio::null())
+            .status()
+            .map_err(|e| anyhow!("failed to spawn secret-tool: {e}"))?;
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod backend {
+    use super::*;
+
+    pub fn get(account: &str) -> Option<String> {
+        let out = Command::new("security")
+            .args(["find-generic-password", "-s", SERVICE, "-a", account, "-w"])
+            .stderr(Stdio::null())
+            .output()
+            .ok()?;
+        let s = String::from_utf8_lossy(&out.stdout);
+        (out.status.success() && !s.is_empty()).then(|| s.trim_end_matches('\n').to_string())
+    }
+
+    pub fn set(account: &str, _label: &str, password: &str) -> Result<()> {
+        // `security -i` читает команду со stdin; пароль в hex (-X), чтобы не экранировать
+        let hex: String = password.bytes().map(|b| format!("{b:02x}")).collect();
+        let account = account.replace('\\', "\\\\").replace('"', "\\\"");
+        let cmd = format!("add-generic-password -U -s {SERVICE} -a \"{account}\" -X {hex}\n");
+        let out = run_with_stdin("security", &["-i"], cmd.as_bytes())?;
+        if !out.status.success() {
+            return Err(anyhow!(
+                "security failed: {}",
+                String::from_utf8_lossy(&out.stderr).trim()
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn delete(account: &str) -> Result<()> {
+        Command::new("security")
+            .args(["delete-generic-password", "-s", SERVICE, "-a", account])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .map_err(|e| anyhow!("failed to spawn security: {e}"))?;
+        Ok(())
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+mod backend {
+    use super::*;
+
+    pub fn get(_account: &str) -> Option<String> {
+        None
+    }
+
+    pub fn set(_account: &str, _label: &str, _password: &str) -> Result<()> {
+        Err(anyhow!("keychain unlock is not supported on this platform"))
+    }
+
+    pub fn delete(_account: &str) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Мастер-пароль из связки ключей, если unlock.keychain включён и запись есть.
+/// Любые сбои — просто `None`: дальше будет обычный запрос пароля.
+pub fn master_password_from_keychain(cfg: &Config) -> Option<String> {
+    if !cfg.settings.unlock.keychain {
+        return None;
+    }
+    backend::get(&account().ok()?)
+}
+
+/// После смены мастер-пароля обновить копию в связке ключей, если она включена.
+pub fn keychain_replace(cfg: &Config, new_password: &str) -> Result<()> {
+    if !cfg.settings.unlock.keychain {
+        return Ok(());
+    }
+    backend::set(&account()?, PASSWORD_LABEL, new_password)
+}
+
+/// pm keychain store
+pub fn keychain_store() -> Result<()> {
+    let mut config = Config::load()?;
+    let password = prompt_password_hidden("Master password: ")?;
+    unlock_master_key(&password, &config, crate::keyfile::current()?.as_ref())?;
+
+    backend::set(&account()?, PASSWORD_LABEL, &password)?;
+    config.settings.unlock.keychain = true;
+    save_config(&config, &config_path()?)?;
+
+    println!("Master password saved to the OS keychain; keychain unlock enabled.");
+    eprintln!(
+        "Note: anyone with access to your unlocked login session can now open the store without the master password."
+    );
+    Ok(())
+}
+
+/// pm keychain forget
+pub fn keychain_forget() -> Result<()> {
+    backend::delete(&account()?)?;
+    let mut config = Config::load()?;
+    config.settings.unlock.keychain = false;
+    save_config(&config, &config_path()?)?;
+    println!("Master password removed from the OS keychain; keychain unlock disabled.");
+    Ok(())
+}
+
+/// Сессия в связке ключей. Запись отдельная от мастер-пароля: `account` —
+/// отпечаток хранилища (`store_tag`), а не его путь.
+pub(crate) fn session_get(tag: &str) -> Option<String> {
+    backend::get(tag)
+}
+
+pub(crate) fn session_set(tag: &str, value: &str) -> Result<()> {
+    backend::set(tag, "pm session", value)
+}
+
+pub(crate) fn session_delete(tag: &str) -> Result<()> {
+    backend::delete(tag)
+}
@@ -0,0 +1,42 @@
+//! Ключевой файл — второй фактор разблокировки.
+//!
+//! В KEK подмешивается SHA-256 содержимого файла (см. `crypto::derive_store_kek`),
+//! а в `config.json` записан только флаг `kdf.keyfile`: ни путь, ни хэш там не
+//! хранятся. Путь берётся из `--keyfile`, затем из `PM_KEYFILE`.
+
+use crate::crypto::KeyfileHash;
+use anyhow::{Context, Result, anyhow};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// `--keyfile` на этот запуск, важнее `PM_KEYFILE`.
+static KEYFILE: OnceLock<PathBuf> = OnceLock::new();
+
+pub fn set_keyfile(path: PathBuf) {
+    let _ = KEYFILE.set(path);
+}
+
+/// Путь ключевого файла: `--keyfile`, затем `PM_KEYFILE`.
+pub fn keyfile_path() -> Option<PathBuf> {
+    KEYFILE.get().cloned().or_else(|| {
+        std::env::var_os("PM_KEYFILE")
+            .filter(|p| !p.is_empty())
+            .map(PathBuf::from)
+    })
+}
+
+/// Прочитать ключевой файл и вернуть хэш его содержимого.
+pub fn load(path: &Path) -> Result<KeyfileHash> {
+    let data =
+        std::fs::read(path).with_context(|| format!("cannot read keyfile {}", path.display()))?;
+    if data.is_empty() {
+        return Err(anyhow!("keyfile {} is empty", path.display()));
+    }
+    Ok(Sha256::digest(&data).into())
+}
+
+/// Ключевой файл этого запуска, если он задан.
+pub fn current() -> Result<Option<KeyfileHash>> {
+    keyfile_path().map(|p| load(&p)).transpose()
+}
@@ -0,0 +1,73 @@
+//! Библиотека pm: хранилище, шифрование и форматы; CLI в `main.rs` — тонкая
+//! обёртка над ней. Для работы с хранилищем из кода — `PasswordStore`.
+
+pub mod age;
+pub mod agent;
+pub mod attachments;
+pub mod audit;
+pub mod autotype;
+pub mod backup;
+pub mod bench;
+pub mod bulk;
+pub mod clipboard;
+pub mod completions;
+pub mod config;
+pub mod credential;
+pub mod crypto;
+pub mod csv;
+pub mod doctor;
+pub mod edit;
+pub mod entry;
+pub mod entry_otp;
+pub mod envcheck;
+pub mod expiry;
+pub mod export;
+pub mod fsck;
+pub mod fuzzy;
+pub mod git;
+pub mod grep;
+pub mod hibp;
+pub mod hooks;
+pub mod hostmatch;
+pub mod import;
+pub mod index;
+pub mod keepass;
+pub mod keychain;
+pub mod keyfile;
+pub mod listing;
+pub mod lock;
+pub mod log;
+pub mod master;
+pub mod migrate;
+pub mod otp;
+pub mod output;
+mod password_store;
+pub mod perms;
+pub mod phonetic;
+pub mod picker;
+pub mod pmx;
+pub mod profiles;
+pub mod progress;
+pub mod prompt;
+pub mod qr;
+pub mod qrscan;
+pub mod recent;
+pub mod regex;
+pub mod rekey;
+pub mod reveal;
+#[cfg(feature = "serve")]
+pub mod serve;
+pub mod session;
+pub mod share;
+pub mod show;
+pub mod snippet;
+pub mod status;
+pub mod store;
+pub mod tags;
+pub mod timespec;
+pub mod transfer;
+pub mod trash;
+pub mod tree;
+pub mod wifi;
+
+pub use password_store::PasswordStore;
@@ -0,0 +1,123 @@
+//! Выбор записей: `pm ls`, `pm find`, `pm url`.
+//!
+//! Пути отбираются по префиксу, датам, сроку и меткам (даты и метки — из
+//! индекса) или по адресу сайта. Функции возвращают пути; дерево, список
+//! или JSON из них строит CLI.
+
+use crate::config::Config;
+use crate::entry::Entry;
+use crate::grep::Pattern;
+use crate::hostmatch::host_matches;
+use crate::password_store::PasswordStore;
+use crate::session::get_master_key_with_cache;
+use crate::store::{decrypt_all, list_entries, nfc, normalize_entry_path};
+use crate::timespec::DateFilterArgs;
+use anyhow::{Result, bail};
+use time::OffsetDateTime;
+
+/// Фильтры `pm ls`.
+pub struct LsFilter<'a> {
+    pub prefix: Option<&'a str>,
+    pub dates: &'a DateFilterArgs,
+    /// Только записи с истёкшим сроком
+    pub expired: bool,
+    pub tag: Option<&'a str>,
+}
+
+/// pm ls: пути записей, прошедших фильтры, и нормализованный префикс.
+pub fn ls(filter: &LsFilter) -> Result<(Option<String>, Vec<String>)> {
+    let mut entries = list_entries()?;
+
+    let prefix = filter.prefix.map(normalize_entry_path).transpose()?;
+    if let Some(ref pref) = prefix {
+        let pref_slash = format!("{pref}/");
+        entries.retain(|e| e == pref || e.starts_with(&pref_slash));
+    }
+
+    entries = filter_by_dates(entries, filter.dates)?;
+    if filter.expired {
+        entries = crate::expiry::expiring_within(entries, time::Duration::ZERO)?
+            .into_iter()
+            .filter(|e| e.expired)
+            .map(|e| e.path)
+            .collect();
+    }
+    if let Some(tag) = filter.tag {
+        entries = crate::tags::filter_by_tag(entries, tag)?;
+    }
+    Ok((prefix, entries))
+}
+
+/// Дерево `pm ls`: от папки `prefix` или от корня хранилища.
+pub fn render_tree(prefix: Option<&str>, entries: &[String]) -> String {
+    match prefix {
+        Some(pref) => {
+            let pref_slash = format!("{pref}/");
+            let rel: Vec<String> = entries
+                .iter()
+                .filter_map(|e| e.strip_prefix(&pref_slash).map(str::to_string))
+                .collect();
+            crate::tree::render_tree(pref, &rel)
+        }
+        None => crate::tree::render_tree("Password Store", entries),
+    }
+}
+
+/// Оставить записи, чьи даты проходят фильтр `--created-*` / `--modified-*`.
+pub fn filter_by_dates(entries: Vec<String>, dates: &DateFilterArgs) -> Result<Vec<String>> {
+    let filter = dates.to_filter(OffsetDateTime::now_utc())?;
+    if filter.is_empty() || entries.is_empty() {
+        return Ok(entries);
+    }
+    // Даты лежат внутри зашифрованных записей — берём их из индекса
+    let config = Config::load()?;
+    let mk = get_master_key_with_cache(&config)?;
+    let mut kept = Vec::new();
+    for (path, meta) in crate::index::metadata(&mk, entries)? {
+        match meta {
+            Ok(m) if filter.matches(&m.created_at, &m.updated_at) => kept.push(path),
+            Ok(_) => {}
+            Err(e) => eprintln!("Warning: cannot decrypt {path}: {e}"),
+        }
+    }
+    Ok(kept)
+}
+
+/// pm find QUERY [--regex]: пути по нечёткому совпадению (лучшие первыми)
+/// или по регулярному выражению.
+pub fn find(query: &str, regex: Option<Pattern>, dates: &DateFilterArgs) -> Result<Vec<String>> {
+    let paths = list_entries()?;
+    let ranked = match regex {
+        Some(re) => paths.into_iter().filter(|p| re.is_match(p)).collect(),
+        None => crate::fuzzy::rank_paths(&nfc(query), paths),
+    };
+    filter_by_dates(ranked, dates)
+}
+
+/// pm url QUERY: записи, чей URL относится к сайту `query`. Ни одной — ошибка.
+pub fn by_url(query: &str, loose: bool) -> Result<Vec<(String, Entry)>> {
+    let store = PasswordStore::open_current()?;
+    let mk = store.master_key()?;
+
+    let mut matches = Vec::new();
+    for (path, entry) in decrypt_all(mk, store.list()?) {
+        let entry = match entry {
+            Ok(e) => e,
+            Err(e) => {
+                eprintln!("Warning: cannot decrypt {path}: {e}");
+                continue;
+            }
+        };
+        let Some(ref url) = entry.url else {
+            continue;
+        };
+        if host_matches(url, query, loose) {
+            matches.push((path, entry));
+        }
+    }
+
+    if matches.is_empty() {
+        bail!("No entries match {}", query);
+    }
+    Ok(matches)
+}
@@ -0,0 +1,263 @@
+//! Блокировка хранилища между процессами pm: файл `.lock` в корне хранилища.
+//!
+//! Команды, меняющие хранилище, берут эксклюзивный flock, остальные —
+//! разделяемый; занятую блокировку ждём несколько секунд. Держатель
+//! эксклюзивной блокировки пишет в файл свой PID и команду, чтобы ждущему
+//! было что показать. Сам flock ядро снимает, когда процесс умирает, так что
+//! от упавшего pm остаётся только запись: её узнаём по мёртвому PID и стираем.
+//!
+//! `--read-only` запрещает меняющие команды, а заодно и попутные записи
+//! (журнал обращений, индекс) — для работы со снимком хранилища.
+
+use crate::store::store_root;
+use anyhow::{Result, anyhow};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Что команда делает с хранилищем.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Access {
+    /// Хранилище не нужно (или команда живёт долго, как `pm agent`).
+    None,
+    Read,
+    Write,
+}
+
+/// Сколько ждать чужую блокировку.
+const WAIT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Переменная для дочерних процессов (хуков): блокировку держит родитель,
+/// вложенный `pm` её не берёт, иначе они ждали бы друг друга. Выставить её
+/// может кто угодно, поэтому верим ей, только если этот PID записан в `.lock`
+/// держателем и приходится нам предком.
+const HELD_ENV: &str = "PM_LOCK_PID";
+
+/// `--read-only`.
+static READ_ONLY: AtomicBool = AtomicBool::new(false);
+/// Этот процесс держит блокировку хранилища.
+static HELD: AtomicBool = AtomicBool::new(false);
+
+pub fn set_read_only(v: bool) {
+    READ_ONLY.store(v, Ordering::Relaxed);
+}
+
+pub fn is_read_only() -> bool {
+    READ_ONLY.load(Ordering::Relaxed)
+}
+
+/// Ошибка, если хранилище открыто с `--read-only`.
+pub fn ensure_writable() -> Result<()> {
+    if is_read_only() {
+        return Err(anyhow!("the store is opened with --read-only"));
+    }
+    Ok(())
+}
+
+/// Переменные окружения для дочернего процесса, пока блокировка у нас.
+pub fn child_env() -> Option<(&'static str, String)> {
+    HELD.load(Ordering::Relaxed)
+        .then(|| (HELD_ENV, std::process::id().to_string()))
+}
+
+/// Взятая блокировка; снимается при drop (закрытии файла).
+pub struct StoreLock {
+    #[cfg(unix)]
+    file: std::fs::File,
+    #[cfg(unix)]
+    exclusive: bool,
+}
+
+impl Drop for StoreLock {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if self.exclusive {
+            // Запись о держателе больше не нужна
+            let _ = self.file.set_len(0);
+        }
+        HELD.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Взять блокировку хранилища для команды `command` (например, `pm add`).
+/// Без хранилища, для `Access::None` и внутри хука держателя — `None`.
+pub fn acquire(access: Access, command: &str) -> Result<Option<StoreLock>> {
+    if access == Access::None {
+        return Ok(None);
+    }
+    let root = store_root()?;
+    if inherited(&root) {
+        return Ok(None);
+    }
+    acquire_at(&root, access, command)
+}
+
+/// То же для хранилища `root` — второго хранилища команды, как в
+/// `pm mv --to-profile`.
+pub fn acquire_at(root: &Path, access: Access, command: &str) -> Result<Option<StoreLock>> {
+    if access == Access::None {
+        return Ok(None);
+    }
+    if !root.is_dir() {
+        return Ok(None);
+    }
+    let exclusive = access == Access::Write;
+    #[cfg(unix)]
+    {
+        let lock_path = root.join(".lock");
+        let file = match open_lock_file(&lock_path) {
+            Ok(file) => file,
+            // Снимок на носителе только для чтения: читать можно и без блокировки
+            Err(_) if !exclusive => return Ok(None),
+            Err(e) => {
+                return Err(
+                    anyhow::Error::new(e).context(format!("cannot open {}", lock_path.display()))
+                );
+            }
+        };
+        wait_for_flock(&file, exclusive)?;
+
+        let mut lock = StoreLock { file, exclusive };
+        if exclusive {
+            write_holder(&mut lock.file, command)?;
+        } else if read_holder(&lock.file).is_some_and(|(pid, _)| !pid_alive(pid)) {
+            // Запись упавшего процесса
+            let _ = lock.file.set_len(0);
+        }
+        HELD.store(true, Ordering::Relaxed);
+        Ok(Some(lock))
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (exclusive, command);
+        Ok(None)
+    }
+}
+
+/// Мы — хук процесса, который держит эксклюзивную блокировку `root`.
+#[cfg(unix)]
+fn inherited(root: &Path) -> bool {
+    let Some(pid) = std::env::var(HELD_ENV)
+        .ok()
+        .and_then(|v| v.parse::<u32>().ok())
+    else {
+        return false;
+    };
+    let holder = std::fs::File::open(root.join(".lock"))
+        .ok()
+        .and_then(|f| read_holder(&f));
+    holder.is_some_and(|(holder, _)| holder == pid) && is_ancestor(pid)
+}
+
+#[cfg(not(unix))]
+fn inherited(_root: &Path) -> bool {
+    false
+}
+
+/// `pid` — родитель текущего процесса, родитель родителя и так далее.
+#[cfg(unix)]
+fn is_ancestor(pid: u32) -> bool {
+    let mut current = std::process::id();
+    // Цепочка конечна, но /proc читаем не бесконечно на случай гонки с PID
+    for _ in 0..64 {
+        match parent_of(current) {
+            Some(parent) if parent == pid => return true,
+            Some(parent) if parent > 1 => current = parent,
+            _ => return false,
+        }
+    }
+    false
+}
+
+#[cfg(target_os = "linux")]
+fn parent_of(pid: u32) -> Option<u32> {
+    let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    // Имя процесса в скобках может содержать пробелы и скобки
+    let rest = &stat[stat.rfind(')')? + 1..];
+    rest.split_whitespace().nth(1)?.parse().ok()
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn parent_of(pid: u32) -> Option<u32> {
+    let out = std::process::Command::new("ps")
+        .args(["-o", "ppid=", "-p", &pid.to_string()])
+        .output()
+        .ok()?;
+    String::from_utf8_lossy(&out.stdout).trim().parse().ok()
+}
+
+#[cfg(unix)]
+fn open_lock_file(path: &Path) -> std::io::Result<std::fs::File> {
+    use std::os::unix::fs::OpenOptionsExt;
+    std::fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .read(true)
+        .write(true)
+        .mode(0o600)
+        .open(path)
+}
+
+#[cfg(unix)]
+fn wait_for_flock(file: &std::fs::File, exclusive: bool) -> Result<()> {
+    use std::os::unix::io::AsRawFd;
+    let op = if exclusive {
+        libc::LOCK_EX
+    } else {
+        libc::LOCK_SH
+    };
+    let started = std::time::Instant::now();
+    loop {
+        // SAFETY: fd принадлежит `file` и живёт не меньше блокировки
+        if unsafe { libc::flock(file.as_raw_fd(), op | libc::LOCK_NB) } == 0 {
+            return Ok(());
+        }
+        let err = std::io::Error::last_os_error();
+        if err.raw_os_error() != Some(libc::EWOULDBLOCK) {
+            return Err(anyhow::Error::new(err).context("cannot lock the store"));
+        }
+        if started.elapsed() >= WAIT {
+            return Err(match read_holder(file).filter(|(pid, _)| pid_alive(*pid)) {
+                Some((pid, cmd)) => {
+                    anyhow!("store is locked by another pm process (pid {pid}, {cmd})")
+                }
+                None => anyhow!("store is locked by another pm process"),
+            });
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+}
+
+/// Содержимое `.lock`: `<pid> <команда>`.
+#[cfg(unix)]
+fn read_holder(mut file: &std::fs::File) -> Option<(u32, String)> {
+    use std::io::{Read, Seek};
+    let mut data = String::new();
+    file.rewind().ok()?;
+    file.read_to_string(&mut data).ok()?;
+    let (pid, cmd) = data.trim().split_once(' ')?;
+    Some((pid.parse().ok()?, cmd.to_string()))
+}
+
+#[cfg(unix)]
+fn write_holder(file: &mut std::fs::File, command: &str) -> Result<()> {
+    use std::io::{Seek, Write};
+    file.set_len(0)?;
+    file.rewind()?;
+    writeln!(file, "{} {command}", std::process::id())?;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn pid_alive(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    // SAFETY: сигнал 0 ничего не посылает, только проверяет процесс
+    let found = unsafe { libc::kill(pid, 0) } == 0;
+    found || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(not(unix))]
+fn pid_alive(_pid: u32) -> bool {
+    false
+}
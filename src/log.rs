@@ -0,0 +1,39 @@
+//! Диагностика в stderr: `-v` — какие файлы pm читает и пишет, сколько
+//! занял KDF, откуда взят ключ, чем копируется буфер обмена и что ответил
+//! git; `-vv` — ещё и подробности. `PM_DEBUG` равносилен `-vv`.
+//!
+//! Сюда попадают только пути, имена и времена. Пароли, ключи, секреты OTP
+//! и расшифрованные записи не логируются ни на каком уровне.
+
+use std::fmt::Arguments;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+static LEVEL: AtomicU8 = AtomicU8::new(0);
+
+/// Уровень по числу `-v`.
+pub fn set_level(verbose: u8) {
+    let level = if std::env::var_os("PM_DEBUG").is_some() {
+        verbose.max(2)
+    } else {
+        verbose
+    };
+    LEVEL.store(level, Ordering::Relaxed);
+}
+
+fn enabled(level: u8) -> bool {
+    LEVEL.load(Ordering::Relaxed) >= level
+}
+
+/// `-v`: `log::info(format_args!("..."))`.
+pub fn info(args: Arguments) {
+    if enabled(1) {
+        eprintln!("pm: {args}");
+    }
+}
+
+/// `-vv`.
+pub fn debug(args: Arguments) {
+    if enabled(2) {
+        eprintln!("pm: debug: {args}");
+    }
+}
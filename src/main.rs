@@ -1,29 +1,91 @@
-mod backup;
-mod clipboard;
-mod config;
-mod crypto;
-mod entry;
-mod prompt;
-mod session;
-mod store;
-
-use crate::backup::backup_create;
-use crate::clipboard::copy_to_clipboard;
-use crate::config::Config;
-use crate::crypto::generate_new_config;
-use crate::entry::{Entry, OtpConfig};
-use crate::prompt::{prompt_password_hidden, prompt_string};
-use crate::session::get_master_key_with_cache;
-use crate::store::{ensure_store_dirs, list_entries, load_entry, save_entry, store_root};
 use anyhow::anyhow;
-use clap::{Parser, Subcommand, ValueEnum};
-use time::OffsetDateTime;
-use totp_rs::{Algorithm, Secret, TOTP};
-use url::Url;
+use clap::{CommandFactory, Parser, Subcommand};
+use pm::backup::{
+    backup_auto, backup_create, backup_list, backup_prune, backup_restore, backup_verify,
+    init_from_backup,
+};
+use pm::bulk::{BulkField, bulk_set};
+use pm::clipboard::{self, CopyOptions};
+use pm::completions::{Shell, complete_paths, completions};
+use pm::config::Config;
+use pm::doctor::doctor;
+use pm::edit::{AddFieldArgs, PassphraseArgs};
+use pm::entry::EntryKind;
+use pm::fsck::fsck;
+use pm::hibp::{audit_hibp_offline, build_index};
+use pm::keychain::{keychain_forget, keychain_store};
+use pm::lock::Access;
+use pm::migrate::migrate;
+use pm::output::OutputFormat;
+use pm::phonetic::phonetic_lines;
+use pm::qr::render_qr_terminal;
+use pm::recent::recent;
+#[cfg(feature = "serve")]
+use pm::serve;
+use pm::show::Field;
+use pm::snippet::ShowFormat;
+use pm::store::{list_entries, set_store_override, validate_entry_path};
+use pm::timespec::DateFilterArgs;
+use pm::wifi::wifi_qr;
+use pm::{
+    agent, attachments, audit, autotype, bench, config, credential, crypto, edit, entry_otp,
+    envcheck, expiry, export, git, grep, hibp, hooks, import, index, keepass, keyfile, listing,
+    lock, master, output, picker, pmx, profiles, qrscan, recent, rekey, reveal, session, share,
+    show, status, tags, transfer, trash,
+};
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(Parser, Debug)]
 #[command(name = "pm", version, about = "Minimal password manager in Rust")]
 struct Cli {
+    /// Store directory for this run (overrides PM_STORE_DIR and the default location)
+    #[arg(long, global = true, value_name = "PATH")]
+    store: Option<PathBuf>,
+
+    /// Use a named profile from ~/.config/pm/profiles.toml (see `pm profile`)
+    #[arg(long, global = true, value_name = "NAME", conflicts_with = "store")]
+    profile: Option<String>,
+
+    /// Keyfile required to unlock the store, in addition to the master password (or PM_KEYFILE)
+    #[arg(long, global = true, value_name = "PATH")]
+    keyfile: Option<PathBuf>,
+
+    /// Don't record this access in the `pm recent` history
+    #[arg(long, global = true)]
+    no_touch: bool,
+
+    /// Don't run post-change hooks for this invocation
+    #[arg(long, global = true)]
+    no_hooks: bool,
+
+    /// Don't read or write the cached session; always ask for the master password
+    #[arg(long, global = true)]
+    no_cache: bool,
+
+    /// Don't auto-commit changes when the store is a git repository
+    #[arg(long, global = true)]
+    no_commit: bool,
+
+    /// Refuse any command that changes the store (e.g. when pointing pm at a snapshot)
+    #[arg(long, global = true)]
+    read_only: bool,
+
+    /// Log files read and written, KDF timing, session cache and more to stderr (-vv for detail)
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Don't print confirmations such as "Saved entry ..." or "copied to clipboard"
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Output format: human (default) or json (one JSON document; errors as JSON on stderr)
+    // Не global: `export` и `share` принимают свой `--output FILE`, поэтому
+    // формат указывается до команды — `pm -o json ls`
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Human)]
+    output: OutputFormat,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -31,33 +93,338 @@ struct Cli {
 #[derive(Subcommand, Debug)]
 enum Commands {
     /// Initialize password store
-    Init,
+    ///
+    /// Примеры:
+    ///   pm init
+    ///   pm init --from-backup backup_2024-01-01.zip
+    Init {
+        /// Set up the store from an existing backup archive
+        #[arg(long, value_name = "ARCHIVE")]
+        from_backup: Option<PathBuf>,
+        #[command(flatten)]
+        kdf: KdfArgs,
+    },
 
     /// Add a new entry
+    ///
+    /// Fields given as flags are not prompted for, so entries can be created from scripts.
+    ///
+    /// Примеры:
+    ///   pm add work/github
+    ///   pm add work/api --username bob --url https://example.com --notes "prod key" --password-stdin < pw.txt
+    ///   pm add work/api --generate 32 --clip
+    ///   pm add work/api --force
     Add {
         /// Path like work/github
         path: String,
+        /// Kind of entry to create
+        #[arg(long, value_enum, default_value_t = EntryKind::Login)]
+        kind: EntryKind,
+        /// Copy the password to clipboard after saving
+        #[arg(long, conflicts_with = "print")]
+        clip: bool,
+        /// Print the password once after saving
+        #[arg(long)]
+        print: bool,
+        /// Generate a passphrase instead when the password prompt is left empty
+        #[command(flatten)]
+        passphrase: PassphraseArgs,
+        #[command(flatten)]
+        fields: AddFieldArgs,
+        /// Replace an existing entry at PATH (keeps its creation date and OTP)
+        #[arg(short, long)]
+        force: bool,
+        /// With --force, drop the OTP secret of the replaced entry
+        #[arg(long, requires = "force")]
+        drop_otp: bool,
     },
 
     /// Show entry
+    ///
+    /// Примеры:
+    ///   pm show work/github
+    ///   pm show work/github -r
+    ///   pm show work/github --reveal --clear-after 15
+    ///   pm show db/prod --format dotenv --prefix DB_ --force > .env
+    ///   pm show work/github --format netrc --force >> ~/.netrc
+    ///   pm show home/wifi --qr
+    ///   pm show work/github --qr --field username --invert
+    #[command(group(clap::ArgGroup::new("spell").args(["phonetic", "qr"])))]
     Show {
-        /// Path like work/github
-        path: String,
+        /// Path like work/github (omit to pick interactively)
+        path: Option<String>,
         /// Show only password
         #[arg(long)]
         password_only: bool,
         /// Show as JSON
         #[arg(long)]
         json: bool,
+        /// Include previous passwords in --json output
+        #[arg(long, requires = "json")]
+        include_history: bool,
+        /// Print the password instead of ******** (see display.conceal_password)
+        #[arg(short, long)]
+        reveal: bool,
+        /// Spell the value out character by character (NATO alphabet)
+        #[arg(long, conflicts_with_all = ["json", "password_only"])]
+        phonetic: bool,
+        /// Render the value as a QR code in the terminal
+        #[arg(long, conflicts_with_all = ["json", "password_only", "clear_after"])]
+        qr: bool,
+        /// Invert QR colors (for terminals with a light background)
+        #[arg(long, requires = "qr")]
+        invert: bool,
+        /// Field for --phonetic or --qr: password (default), username, url,
+        /// notes, otp or a custom field name
+        #[arg(long, requires = "spell")]
+        field: Option<Field>,
+        /// Print as a ready-to-paste snippet for .env or ~/.netrc
+        #[arg(long, value_enum, conflicts_with_all = ["json", "password_only", "spell"])]
+        format: Option<ShowFormat>,
+        /// Variable name prefix for --format dotenv, e.g. DB_
+        #[arg(long, default_value = "", requires = "format")]
+        prefix: String,
+        /// Erase the output from the terminal after SECS seconds (or a keypress)
+        #[arg(long, value_name = "SECS", conflicts_with_all = ["phonetic", "format"])]
+        clear_after: Option<u64>,
+        /// Allow --phonetic, --qr or --format when stdout is not a terminal
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Delete an entry (it goes to the trash; see `pm trash`)
+    ///
+    /// Примеры:
+    ///   pm rm work/github
+    ///   pm rm -f work/github
+    ///   pm rm --permanent work/github
+    Rm {
+        /// Path like work/github (omit to pick interactively)
+        path: Option<String>,
+        /// Don't ask for confirmation
+        #[arg(short, long)]
+        force: bool,
+        /// Delete for good instead of moving to the trash
+        #[arg(long)]
+        permanent: bool,
+    },
+
+    /// List, restore or empty deleted entries
+    ///
+    /// Примеры:
+    ///   pm trash list
+    ///   pm trash restore work/github
+    ///   pm trash empty --older-than 30d
+    Trash {
+        #[command(subcommand)]
+        cmd: TrashCommands,
+    },
+
+    /// Edit an existing entry field by field
+    ///
+    /// Press Enter to keep the current value, type `-` to clear a field.
+    ///
+    /// Примеры:
+    ///   pm edit work/github
+    Edit {
+        /// Path like work/github (omit to pick interactively)
+        path: Option<String>,
+    },
+
+    /// Replace an entry's password with a newly generated one
+    ///
+    /// The old password is kept in the entry's history.
+    ///
+    /// Примеры:
+    ///   pm regen work/github --clip
+    ///   pm regen work/github --length 40 --print
+    ///   pm regen home/wifi --words 5
+    Regen {
+        /// Path like work/github (omit to pick interactively)
+        path: Option<String>,
+        /// Password length (default: generate.length)
+        #[arg(long, value_name = "LEN", conflicts_with = "words")]
+        length: Option<usize>,
+        /// Copy the new password to clipboard
+        #[arg(long, conflicts_with = "print")]
+        clip: bool,
+        /// Print the new password once
+        #[arg(long)]
+        print: bool,
+        #[command(flatten)]
+        passphrase: PassphraseArgs,
+    },
+
+    /// Rename or move an entry, or a whole folder
+    ///
+    /// Примеры:
+    ///   pm mv wrok/github work/github
+    ///   pm mv work old-work
+    ///   pm mv -f work/github work/github-old
+    ///   pm mv personal/bank personal/bank --to-profile work
+    ///   pm mv old-work archive --to-profile archive --on-conflict rename
+    Mv {
+        /// Entry or folder to move
+        src: String,
+        /// New path
+        dst: String,
+        /// Overwrite existing entries at the destination
+        #[arg(short, long)]
+        force: bool,
+        /// Move into the store of another profile (re-encrypted with its master key)
+        #[arg(long, value_name = "NAME")]
+        to_profile: Option<String>,
+        /// What to do when the entry exists in the other profile
+        #[arg(long, value_enum, requires = "to_profile", conflicts_with = "force")]
+        on_conflict: Option<import::OnConflict>,
+    },
+
+    /// Duplicate an entry under a new path
+    ///
+    /// Примеры:
+    ///   pm cp work/github work/github-staging
+    ///   pm cp --no-otp work/github work/github-staging
+    ///   pm cp work/vpn shared/vpn --to-profile team
+    Cp {
+        /// Entry to copy
+        src: String,
+        /// New path
+        dst: String,
+        /// Overwrite an existing entry at the destination
+        #[arg(short, long)]
+        force: bool,
+        /// Don't copy the OTP secret
+        #[arg(long)]
+        no_otp: bool,
+        /// Copy into the store of another profile (re-encrypted with its master key)
+        #[arg(long, value_name = "NAME")]
+        to_profile: Option<String>,
+        /// What to do when the entry exists in the other profile
+        #[arg(long, value_enum, requires = "to_profile", conflicts_with = "force")]
+        on_conflict: Option<import::OnConflict>,
+    },
+
+    /// Generate a random password without storing it
+    ///
+    /// Примеры:
+    ///   pm generate
+    ///   pm generate 32 --no-symbols
+    ///   pm generate 16 --count 5
+    ///   pm generate --clip
+    ///   pm generate --words 6
+    ///   pm generate --words 5 --separator ' ' --capitalize
+    Generate {
+        /// Password length (default: generate.length, 24 unless changed)
+        length: Option<usize>,
+        /// Exclude symbols
+        #[arg(long)]
+        no_symbols: bool,
+        /// Exclude digits
+        #[arg(long)]
+        no_digits: bool,
+        /// Exclude uppercase letters
+        #[arg(long)]
+        no_upper: bool,
+        /// Exclude lowercase letters
+        #[arg(long)]
+        no_lower: bool,
+        /// Copy to clipboard instead of printing
+        #[arg(long, conflicts_with = "count")]
+        clip: bool,
+        /// Number of passwords to print
+        #[arg(long, default_value_t = 1)]
+        count: usize,
+        #[command(flatten)]
+        passphrase: PassphraseArgs,
     },
 
     /// Copy field to clipboard
+    ///
+    /// Примеры:
+    ///   pm clip work/github
+    ///   pm clip work/github --field url
+    ///   pm clip work/github --field otp
+    ///   pm clip cloud/aws --field access-key-id
+    ///   pm clip work/github --once        # gone after the first paste
+    ///   pm clip work/github --primary     # middle-click selection
+    ///   pm clip work/github --osc52       # over SSH, via the terminal
+    ///
+    /// The clipboard tool is picked automatically; PM_CLIPBOARD=xsel,osc52 sets the order.
     Clip {
+        /// Path like work/github (omit to pick interactively)
+        path: Option<String>,
+        /// Field to copy: password (default), username, url, notes, otp
+        /// or a custom field name
+        #[arg(long)]
+        field: Option<Field>,
+        /// Clear the clipboard after the first paste (wl-copy or xclip)
+        #[arg(long)]
+        once: bool,
+        /// Use the primary selection instead of the clipboard
+        #[arg(long)]
+        primary: bool,
+        /// Send the value through the terminal with an OSC 52 escape sequence
+        #[arg(long)]
+        osc52: bool,
+    },
+
+    /// Print one field for scripts, browser extensions and credential helpers
+    ///
+    /// Prints the value and nothing else. Exit codes: 0 success, 2 entry not
+    /// found, 3 wrong master password, 4 field empty, 1 anything else.
+    ///
+    /// Примеры:
+    ///   pm get work/github
+    ///   pm get work/github --field username
+    ///   pm get work/github --field otp --no-newline
+    ///   pm get work/github --password-fd 3 --ttl 0 3<master.txt
+    Get {
         /// Path like work/github
         path: String,
-        /// Field to copy (password by default)
-        #[arg(long, value_enum)]
-        field: Option<ClipField>,
+        /// Field to print: password (default), username, url, notes, otp
+        /// or a custom field name
+        #[arg(long, default_value = "password")]
+        field: Field,
+        /// Don't print a newline after the value
+        #[arg(short = 'n', long)]
+        no_newline: bool,
+        /// Read the master password from this file descriptor (first line)
+        #[arg(long, value_name = "FD")]
+        password_fd: Option<i32>,
+        /// Keep the unlocked key cached for SECS seconds; 0 writes no session
+        #[arg(long, value_name = "SECS")]
+        ttl: Option<u64>,
+    },
+
+    /// Type an entry into the focused window, for dmenu/rofi hotkeys
+    ///
+    /// Types with wtype or ydotool on Wayland and xdotool on X11
+    /// (PM_AUTOTYPE=ydotool,xdotool sets the order). Without a path, reads the
+    /// selected line from stdin.
+    ///
+    /// Примеры:
+    ///   pm menu --list | rofi -dmenu | pm menu
+    ///   pm menu work/github
+    ///   pm menu work/github --sequence 'user :tab pass :enter'
+    ///   pm menu work/github --sequence otp --delay 300
+    ///   pm menu work/github --clip          # copy the password instead
+    Menu {
+        /// Entry to type, as selected in the menu
+        #[arg(conflicts_with = "list")]
+        path: Option<String>,
+        /// Print every entry path, one per line, for dmenu/rofi
+        #[arg(long)]
+        list: bool,
+        /// Copy FIELD (default: password) to the clipboard instead of typing
+        #[arg(long, value_name = "FIELD", num_args = 0..=1, default_missing_value = "password")]
+        clip: Option<Field>,
+        /// Fields and keys to type: field names (user, pass, otp, url, ...)
+        /// and :tab, :enter, :space
+        #[arg(long, default_value = autotype::DEFAULT_SEQUENCE)]
+        sequence: String,
+        /// Milliseconds to wait before typing and between steps
+        #[arg(long, value_name = "MS", default_value_t = 100)]
+        delay: u64,
     },
 
     /// List entries (like `pass ls`)
@@ -65,26 +432,998 @@ enum Commands {
     /// Примеры:
     ///   pm ls
     ///   pm ls work
+    ///   pm ls work --modified-before 90d
+    ///   pm ls --created-since 2024-01-01
+    ///   pm ls work -0 | xargs -0 -n1 pm show
+    ///   pm ls --flat
+    ///   pm ls --expired
+    ///   pm ls --tag shared-with-team
     Ls {
         /// Optional prefix (folder), e.g. "work" or "personal"
         prefix: Option<String>,
+        /// One full path per line instead of a tree (for scripts)
+        #[arg(long)]
+        flat: bool,
+        /// Separate paths with NUL instead of newline (for xargs -0, fzf --read0); implies --flat
+        #[arg(short = '0', long)]
+        print0: bool,
+        #[command(flatten)]
+        dates: DateFilterArgs,
+        /// Only entries whose expiry date has passed
+        #[arg(long)]
+        expired: bool,
+        /// Only entries with this tag
+        #[arg(long)]
+        tag: Option<String>,
+    },
+
+    /// Add or remove tags without the full edit flow, or list tags in use
+    ///
+    /// Примеры:
+    ///   pm tag add work/github 2fa-enabled
+    ///   pm tag rm work/github 2fa-enabled
+    ///   pm tag list
+    Tag {
+        #[command(subcommand)]
+        cmd: TagCommands,
+    },
+
+    /// Keep small files (recovery codes, 2FA backup PDFs) inside an entry
+    ///
+    /// Примеры:
+    ///   pm attach add work/github ./recovery-codes.txt
+    ///   pm attach list work/github
+    ///   pm attach get work/github recovery-codes.txt --output ./out.txt
+    ///   pm attach rm work/github recovery-codes.txt
+    Attach {
+        #[command(subcommand)]
+        cmd: AttachCommands,
+    },
+
+    /// Find entries by path (case-insensitive substring or fuzzy match)
+    ///
+    /// Exits with status 1 when nothing matches.
+    ///
+    /// Примеры:
+    ///   pm find github
+    ///   pm find wgh
+    ///   pm show "$(pm find github --first)"
+    ///   pm find work --modified-before 1y -0 | xargs -0 -n1 pm show
+    ///   pm find --regex -- '-prod$'
+    Find {
+        /// Text to look for in entry paths
+        query: String,
+        /// Treat QUERY as a regular expression
+        #[arg(long)]
+        regex: bool,
+        /// Case-insensitive --regex (same as a leading `(?i)`)
+        #[arg(short = 'i', long, requires = "regex")]
+        ignore_case: bool,
+        /// Print only the best match
+        #[arg(long)]
+        first: bool,
+        /// Separate paths with NUL instead of newline
+        #[arg(short = '0', long)]
+        print0: bool,
+        #[command(flatten)]
+        dates: DateFilterArgs,
+    },
+
+    /// Search inside entries (title, username, URL, notes; never passwords by default)
+    ///
+    /// Prints `path: field: line` for every matching line. Exits with status 1
+    /// when nothing matches.
+    ///
+    /// Примеры:
+    ///   pm grep alice
+    ///   pm grep --regex 'TICKET-[0-9]+' --fields notes
+    ///   pm grep --regex -i '^admin' --fields username,title
+    Grep {
+        /// Text to look for (case-insensitive substring unless --regex)
+        pattern: String,
+        /// Treat PATTERN as a regular expression
+        #[arg(long)]
+        regex: bool,
+        /// Case-insensitive --regex (same as a leading `(?i)`)
+        #[arg(short = 'i', long, requires = "regex")]
+        ignore_case: bool,
+        /// Fields to search: title, username, url, notes, ssid, password
+        #[arg(long, value_delimiter = ',')]
+        fields: Vec<String>,
+        /// Also search (and print) passwords
+        #[arg(long)]
+        include_passwords: bool,
+    },
+
+    /// Find entries by website URL
+    ///
+    /// Примеры:
+    ///   pm url github.com
+    ///   pm url https://gist.github.com/foo
+    ///   pm url git --loose
+    ///   pm url github.com --clip
+    Url {
+        /// Website URL or host, e.g. "github.com"
+        query: String,
+        /// Also match entries whose host merely contains the query
+        #[arg(long)]
+        loose: bool,
+        /// Copy the password if exactly one entry matches
+        #[arg(long)]
+        clip: bool,
+    },
+
+    /// OTP management (TOTP and HOTP)
+    ///
+    /// Примеры:
+    ///   pm otp add work/github
+    ///   pm otp show work/github
+    ///   pm otp clip work/github
+    ///   pm otp uri work/github --qr
+    ///   pm otp set-counter bank/token 42
+    Otp {
+        #[command(subcommand)]
+        cmd: OtpCommands,
+    },
+
+    /// Named stores: `pm --profile work show github`
+    ///
+    /// Profiles live in ~/.config/pm/profiles.toml and map a name to a store path
+    /// (and optionally a session TTL). The default profile is used when neither
+    /// --store, --profile nor PM_STORE_DIR is given.
+    ///
+    /// Примеры:
+    ///   pm profile add work ~/work-store --session-ttl 900
+    ///   pm --profile work init
+    ///   pm profile default work
+    ///   pm profile list
+    ///   pm profile remove work
+    Profile {
+        #[command(subcommand)]
+        cmd: ProfileCommands,
+    },
+
+    /// Change fields of many entries at once
+    Bulk {
+        #[command(subcommand)]
+        cmd: BulkCommands,
+    },
+
+    /// Wi-Fi network helpers
+    Wifi {
+        #[command(subcommand)]
+        cmd: WifiCommands,
+    },
+
+    /// Check stored passwords for known problems
+    ///
+    /// Without --hibp-offline, reports weak, short, single-class, reused and stale
+    /// passwords and logins with a URL but no username, each with a 0-4 strength
+    /// score. Passwords themselves are never printed.
+    ///
+    /// Примеры:
+    ///   pm audit
+    ///   pm audit --min-length 16 --max-age-days 180
+    ///   pm audit --fail-under 3
+    ///   pm audit --hibp
+    ///   pm audit --hibp --timeout 30
+    ///   pm audit --hibp-offline --db pwned-passwords-sha1-ordered-by-hash-v8.txt
+    ///   pm audit --hibp-offline --db pwned.idx
+    Audit {
+        /// Look passwords up online in Have I Been Pwned (same as `pm pwned`)
+        #[arg(long, conflicts_with_all = ["hibp_offline", "min_length", "max_age_days", "fail_under"])]
+        hibp: bool,
+        /// Seconds to wait for each --hibp API request
+        #[arg(
+            long,
+            value_name = "SECS",
+            default_value_t = 10,
+            requires = "hibp",
+            conflicts_with = "hibp_offline"
+        )]
+        timeout: u64,
+        /// Look passwords up in a local Have I Been Pwned dump
+        #[arg(long, requires = "db")]
+        hibp_offline: bool,
+        /// Ordered-by-hash SHA-1 dump or an index built by `pm hibp build-index`
+        #[arg(long, requires = "hibp_offline")]
+        db: Option<PathBuf>,
+        /// Flag passwords shorter than this (default: audit.min_length, 12)
+        #[arg(long, value_name = "N", conflicts_with = "hibp_offline")]
+        min_length: Option<usize>,
+        /// Flag entries not updated for this many days, 0 to skip (default: audit.max_age_days, 365)
+        #[arg(long, value_name = "N", conflicts_with = "hibp_offline")]
+        max_age_days: Option<u64>,
+        /// Exit with an error if any password scores below this (0-4)
+        #[arg(
+            long,
+            value_name = "SCORE",
+            value_parser = clap::value_parser!(u8).range(0..=4),
+            conflicts_with = "hibp_offline"
+        )]
+        fail_under: Option<u8>,
+    },
+
+    /// Check passwords against Have I Been Pwned without revealing them
+    ///
+    /// Online, only the first 5 hex characters of each password's SHA-1 are sent
+    /// to api.pwnedpasswords.com (k-anonymity); the rest is matched locally.
+    /// Needs curl. Entries that could not be checked are marked "skipped".
+    ///
+    /// Примеры:
+    ///   pm pwned
+    ///   pm pwned work/github
+    ///   pm pwned work/ --timeout 5
+    ///   pm pwned --offline pwned-passwords-ntlm-ordered-by-hash-v8.txt
+    Pwned {
+        /// Entry or folder to check (default: the whole store)
+        path: Option<String>,
+        /// Seconds to wait for each API request
+        #[arg(long, value_name = "SECS", default_value_t = 10)]
+        timeout: u64,
+        /// Use a downloaded SHA-1 or NTLM ordered-by-hash dump (or a `pm hibp build-index` index)
+        #[arg(long, value_name = "FILE")]
+        offline: Option<PathBuf>,
+    },
+
+    /// Have I Been Pwned dataset tools
+    Hibp {
+        #[command(subcommand)]
+        cmd: HibpCommands,
+    },
+
+    /// Export the store in plain text (JSON or CSV) or as an encrypted .pmx file
+    ///
+    /// JSON and CSV contain every password unencrypted. A .pmx file is protected
+    /// by a passphrase and can be loaded on another machine with `pm import pmx`.
+    ///
+    /// Примеры:
+    ///   pm export --format json --output pm.json
+    ///   pm export --format csv --prefix work/ --output work.csv --i-understand-this-is-plaintext
+    ///   pm export --encrypted laptop.pmx
+    Export {
+        /// Output format
+        #[arg(long, value_enum, required_unless_present = "encrypted")]
+        format: Option<export::ExportFormat>,
+        /// Write a passphrase-encrypted .pmx file instead
+        #[arg(long, value_name = "FILE", conflicts_with_all = ["format", "output", "acknowledged"])]
+        encrypted: Option<PathBuf>,
+        /// Write to this file (created with mode 0600) instead of stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+        /// Only export entries under this folder
+        #[arg(long)]
+        prefix: Option<String>,
+        /// Skip the interactive confirmation
+        #[arg(long = "i-understand-this-is-plaintext")]
+        acknowledged: bool,
+    },
+
+    /// Import entries from another password manager
+    Import {
+        #[command(subcommand)]
+        cmd: ImportCommands,
+    },
+
+    /// Encrypt entries to someone's age public key, without sharing the master password
+    ///
+    /// The file is a standard age file: the recipient imports it with
+    /// `pm share import` or opens it with `age -d -i key.txt`.
+    ///
+    /// Примеры:
+    ///   pm share keygen --output ~/.config/pm/share-key.txt
+    ///   pm share work/github work/jira --recipient age1... --output for-anna.age
+    ///   pm share work/ci --recipient age1... --recipient age1... --output ci.age
+    ///   pm share import for-anna.age --identity ~/.config/pm/share-key.txt
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+    Share {
+        #[command(subcommand)]
+        cmd: Option<ShareCommands>,
+        /// Entries or folders to share
+        #[arg(required = true)]
+        paths: Vec<String>,
+        /// Recipient's age public key (age1...); repeat for several people
+        #[arg(short, long = "recipient", value_name = "AGE_KEY", required = true)]
+        recipients: Vec<String>,
+        /// File to write
+        #[arg(id = "file", long = "output", short = 'o', required = true)]
+        file: Option<PathBuf>,
+    },
+
+    /// Backup the whole store
+    Backup {
+        #[command(subcommand)]
+        cmd: BackupCommands,
+    },
+
+    /// Check whether a candidate matches the stored value without printing either
+    ///
+    /// Prints "match" or "no match"; the exit code is 0 on match, 1 otherwise.
+    ///
+    /// Примеры:
+    ///   pm verify-password work/github
+    ///   grep -oP 'password=\K.*' app.conf | pm verify-password db/prod
+    ///   pm verify-password work/github --field username
+    VerifyPassword {
+        /// Path like work/github
+        path: String,
+        /// Field to compare instead of the password (username, url, notes, ...)
+        #[arg(long, default_value = "password")]
+        field: String,
+    },
+
+    /// List previous passwords of an entry, or reveal/copy one of them
+    ///
+    /// Previous passwords are numbered from 1 (the one replaced most recently).
+    ///
+    /// Примеры:
+    ///   pm history work/github
+    ///   pm history work/github --show 1
+    ///   pm history work/github --clip 2
+    History {
+        /// Path like work/github
+        path: String,
+        /// Print previous password number N
+        #[arg(long, value_name = "N", conflicts_with = "clip")]
+        show: Option<usize>,
+        /// Copy previous password number N to clipboard
+        #[arg(long, value_name = "N")]
+        clip: Option<usize>,
+    },
+
+    /// List entries that expire soon or have already expired, by date
+    ///
+    /// Примеры:
+    ///   pm expiring
+    ///   pm expiring --within 90d
+    ///   pm -o json expiring --within 1y
+    Expiring {
+        /// How far ahead to look (30d, 2w, 6mo, 1y, ...)
+        #[arg(long, value_name = "DURATION", default_value = "30d")]
+        within: String,
+    },
+
+    /// Maintain the encrypted metadata index used by listing and search commands
+    ///
+    /// Примеры:
+    ///   pm index rebuild
+    Index {
+        #[command(subcommand)]
+        cmd: IndexCommands,
+    },
+
+    /// Show recently accessed entries, newest first
+    ///
+    /// Примеры:
+    ///   pm recent
+    ///   pm recent --limit 20
+    ///   pm recent --clear
+    Recent {
+        /// How many entries to show
+        #[arg(long, default_value_t = 10)]
+        limit: usize,
+        /// Wipe the access history
+        #[arg(long)]
+        clear: bool,
+    },
+
+    /// Keep the master password in the OS keychain (GNOME Keyring, macOS Keychain)
+    ///
+    /// Примеры:
+    ///   pm keychain store
+    ///   pm keychain forget
+    Keychain {
+        #[command(subcommand)]
+        cmd: KeychainCommands,
+    },
+
+    /// Change settings stored in config.json
+    ///
+    /// Примеры:
+    ///   pm config set session-ttl 900
+    ///   pm config set session-ttl 0
+    ///   pm config set session-ttl default
+    ///   pm config set session-max 28800
+    ///   pm config list
+    ///   pm config get generate.length
+    ///   pm config upgrade-kdf
+    ///   pm config add-keyfile ~/pm.key
+    Config {
+        #[command(subcommand)]
+        cmd: ConfigCommands,
+    },
+
+    /// Keep the master key in a background process instead of the session file
+    ///
+    /// The agent exits after session-ttl seconds without use (or at session-max).
+    ///
+    /// Примеры:
+    ///   pm agent
+    ///   pm agent --stop
+    Agent {
+        /// Tell the running agent to forget the key and exit
+        #[arg(long)]
+        stop: bool,
+        /// Run the agent itself (reads the key from stdin)
+        #[arg(long, hide = true, conflicts_with = "stop")]
+        foreground: bool,
+    },
+
+    /// Forget the cached master key right away
+    ///
+    /// Примеры:
+    ///   pm lock
+    Lock,
+
+    /// Show the store location, entry count, session, KDF, git and clipboard state
+    ///
+    /// Never asks for the master password. Expired session files are removed
+    /// from every place they may have been written to.
+    ///
+    /// Примеры:
+    ///   pm status
+    ///   pm -o json status
+    Status,
+
+    /// Change the master password (entries are not re-encrypted)
+    ///
+    /// Примеры:
+    ///   pm passwd
+    #[command(alias = "change-master")]
+    Passwd,
+
+    /// Clear the clipboard after a delay if it still holds what pm copied (internal)
+    #[command(hide = true)]
+    ClipboardClear {
+        #[arg(long)]
+        after: u64,
+        /// Clipboard backend that made the copy
+        #[arg(long)]
+        backend: Option<String>,
+        #[arg(long)]
+        primary: bool,
+    },
+
+    /// Generate a new master key and re-encrypt every entry with it
+    ///
+    /// The old store is kept next to the new one as pm-store.bak-<timestamp>.
+    ///
+    /// Примеры:
+    ///   pm rekey
+    Rekey,
+
+    /// Measure KDF and encryption speed on this machine
+    ///
+    /// Примеры:
+    ///   pm bench
+    ///   pm bench --json
+    Bench {
+        /// Machine-readable output
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Check that every entry can be read and decrypted
+    ///
+    /// Примеры:
+    ///   pm fsck
+    ///   pm fsck --fix
+    Fsck {
+        /// Rename entry files whose names are not in canonical form (NFC, `/`)
+        #[arg(long)]
+        fix: bool,
+    },
+
+    /// Run git inside the store directory
+    ///
+    /// `pm git init` creates the repository with a .gitignore for local-only files and
+    /// commits the current store; after that every add/edit/rm/mv is committed
+    /// automatically (disable with --no-commit or `pm config set git-auto-commit false`).
+    ///
+    /// Примеры:
+    ///   pm git init
+    ///   pm git remote add origin git@example.com:me/pm-store.git
+    ///   pm git push -u origin main
+    ///   pm git log --oneline
+    Git {
+        /// Arguments passed to git as is
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+
+    /// Git credential helper: keeps HTTPS passwords for git in the store
+    ///
+    /// Reads git's key=value request from stdin and answers on stdout. The entry
+    /// path comes from `git.credential_template` (default git/{host}); a missing
+    /// entry prints nothing, so git asks you and then sends `store`.
+    ///
+    /// Примеры:
+    ///   git config --global credential.helper '!pm git-credential'
+    ///   pm config set git.credential_template 'git/{host}/{username}'
+    GitCredential {
+        /// What git asks for
+        #[arg(value_enum)]
+        operation: credential::Operation,
+    },
+
+    /// Check store health: config, KDF strength, entry files, decryption, permissions
+    ///
+    /// Prints a pass/warn/fail table and exits non-zero if any check failed.
+    ///
+    /// Примеры:
+    ///   pm doctor
+    ///   pm doctor --fix-permissions
+    Doctor {
+        /// Restrict access to the current user (and SYSTEM on Windows)
+        #[arg(long, alias = "fix-perms")]
+        fix_permissions: bool,
+    },
+
+    /// Upgrade every entry file and config.json to the newest on-disk format
+    ///
+    /// Примеры:
+    ///   pm migrate --dry-run
+    ///   pm migrate
+    Migrate {
+        /// Only print the version histogram and planned work
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Print a shell completion script (completes commands, flags and entry paths)
+    ///
+    /// Примеры:
+    ///   pm completions bash > ~/.local/share/bash-completion/completions/pm
+    ///   pm completions zsh > "${fpath[1]}/_pm"
+    ///   pm completions fish > ~/.config/fish/completions/pm.fish
+    ///   pm completions powershell >> $PROFILE
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+
+    /// List entry paths and folders under PREFIX for completion scripts
+    #[command(name = "__complete-paths", hide = true)]
+    CompletePaths {
+        #[arg(default_value = "")]
+        prefix: String,
+        /// Separate candidates with NUL instead of newline
+        #[arg(short = '0', long)]
+        print0: bool,
+    },
+
+    /// Serve a local JSON API for scripts (GET /entries, /entries/<path>, /entries/<path>/otp)
+    ///
+    /// Примеры:
+    ///   pm serve token create --scopes read,otp
+    ///   pm serve --listen 127.0.0.1:7171
+    ///   curl -H "Authorization: Bearer $TOKEN" http://127.0.0.1:7171/entries
+    #[cfg(feature = "serve")]
+    Serve {
+        #[command(subcommand)]
+        cmd: Option<ServeCommands>,
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:7171")]
+        listen: String,
+        /// Allow binding to a non-loopback address
+        #[arg(long)]
+        allow_remote: bool,
+    },
+}
+
+#[cfg(feature = "serve")]
+#[derive(Subcommand, Debug)]
+enum ServeCommands {
+    /// Manage API tokens
+    Token {
+        #[command(subcommand)]
+        cmd: TokenCommands,
+    },
+}
+
+#[cfg(feature = "serve")]
+#[derive(Subcommand, Debug)]
+enum TokenCommands {
+    /// Create a token; it is printed once and only its hash is stored
+    Create {
+        /// Comma-separated scopes: read, password, otp
+        #[arg(long, value_delimiter = ',', required = true)]
+        scopes: Vec<String>,
+    },
+    /// List tokens
+    List,
+    /// Revoke a token by id
+    Revoke { id: String },
+}
+
+#[derive(Subcommand, Debug)]
+enum ProfileCommands {
+    /// List profiles; the default one is marked with *
+    List,
+    /// Add a profile pointing at a store directory
+    Add {
+        name: String,
+        path: PathBuf,
+        /// Session TTL in seconds for this profile (overrides the store setting)
+        #[arg(long, value_name = "SECS")]
+        session_ttl: Option<u64>,
+    },
+    /// Remove a profile (the store itself is not touched)
+    Remove { name: String },
+    /// Use this profile when no --store/--profile/PM_STORE_DIR is given
+    Default { name: String },
+}
+
+#[derive(Subcommand, Debug)]
+enum KeychainCommands {
+    /// Save the master password and enable keychain unlock
+    Store,
+    /// Remove the master password and disable keychain unlock
+    Forget,
+}
+
+#[derive(Subcommand, Debug)]
+enum ConfigCommands {
+    /// Show all settings with their current values
+    ///
+    /// Примеры:
+    ///   pm config list
+    ///   pm -o json config list
+    List,
+
+    /// Print one setting
+    ///
+    /// Примеры:
+    ///   pm config get session.ttl_secs
+    Get { key: String },
+
+    /// Change a setting; the value is checked against the setting's type
+    ///
+    /// Integer settings with a built-in default accept "default" to go back to it.
+    ///
+    /// Примеры:
+    ///   pm config set clipboard.timeout_secs 20
+    ///   pm config set generate.length 32
+    ///   pm config set generate.symbols false
+    ///   pm config set session.ttl_secs default
+    Set { key: String, value: String },
+
+    /// Re-calibrate Argon2id on this machine and re-wrap the master key with stronger parameters
+    ///
+    /// Needs the master password; entries are not re-encrypted.
+    ///
+    /// Примеры:
+    ///   pm config upgrade-kdf
+    ///   pm config upgrade-kdf --kdf-target-ms 1000
+    ///   pm config upgrade-kdf --kdf-memory-mib 256
+    UpgradeKdf {
+        #[command(flatten)]
+        kdf: KdfArgs,
+    },
+
+    /// Require a keyfile in addition to the master password from now on
+    ///
+    /// Only a flag is stored in config.json, not the keyfile's path or hash.
+    ///
+    /// Примеры:
+    ///   head -c 64 /dev/urandom > ~/pm.key
+    ///   pm config add-keyfile ~/pm.key
+    AddKeyfile {
+        /// Keyfile to require (any non-empty file)
+        path: PathBuf,
+    },
+
+    /// Stop requiring the keyfile (pass the current one with --keyfile or PM_KEYFILE)
+    ///
+    /// Примеры:
+    ///   pm --keyfile ~/pm.key config remove-keyfile
+    RemoveKeyfile,
+}
+
+#[derive(Subcommand, Debug)]
+enum IndexCommands {
+    /// Rebuild index.enc from scratch by decrypting every entry
+    ///
+    /// Примеры:
+    ///   pm index rebuild
+    Rebuild,
+}
+
+#[derive(Subcommand, Debug)]
+enum TagCommands {
+    /// Tag an entry
+    ///
+    /// Примеры:
+    ///   pm tag add work/github shared-with-team
+    Add {
+        /// Path like work/github
+        path: String,
+        /// Tag (stored in lowercase)
+        tag: String,
+    },
+
+    /// Remove a tag from an entry
+    ///
+    /// Примеры:
+    ///   pm tag rm work/github shared-with-team
+    Rm {
+        /// Path like work/github
+        path: String,
+        tag: String,
+    },
+
+    /// Show every tag in use with the number of entries
+    ///
+    /// Примеры:
+    ///   pm tag list
+    ///   pm -o json tag list
+    List,
+}
+
+#[derive(Subcommand, Debug)]
+enum AttachCommands {
+    /// Encrypt a file and attach it to an entry
+    ///
+    /// Files are limited to 1 MiB by default (pm config set attachments.max_bytes).
+    ///
+    /// Примеры:
+    ///   pm attach add work/github ./recovery-codes.txt
+    ///   pm attach add work/github ~/Downloads/backup.pdf --name 2fa-backup.pdf
+    Add {
+        /// Path like work/github
+        path: String,
+        /// File to attach
+        file: PathBuf,
+        /// Name inside the entry (default: the file name)
+        #[arg(long)]
+        name: Option<String>,
+        /// Replace an attachment with the same name
+        #[arg(short, long)]
+        force: bool,
+    },
+
+    /// List an entry's attachments with their sizes
+    ///
+    /// Примеры:
+    ///   pm attach list work/github
+    ///   pm -o json attach list work/github
+    List {
+        /// Path like work/github
+        path: String,
+    },
+
+    /// Decrypt an attachment to a file or to stdout
+    ///
+    /// Примеры:
+    ///   pm attach get work/github recovery-codes.txt --output ./out.txt
+    ///   pm attach get work/github recovery-codes.txt | less
+    Get {
+        /// Path like work/github
+        path: String,
+        /// Attachment name
+        name: String,
+        /// Write to this file (created with 0600) instead of stdout ("-": stdout)
+        #[arg(id = "file", long = "output", short = 'o', value_name = "FILE")]
+        file: Option<PathBuf>,
+        /// Overwrite the output file if it exists
+        #[arg(short, long)]
+        force: bool,
+    },
+
+    /// Delete an attachment
+    ///
+    /// Примеры:
+    ///   pm attach rm work/github recovery-codes.txt
+    Rm {
+        /// Path like work/github
+        path: String,
+        /// Attachment name
+        name: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum TrashCommands {
+    /// Show deleted entries, oldest first
+    ///
+    /// Примеры:
+    ///   pm trash list
+    ///   pm -o json trash list
+    List,
+
+    /// Put the most recently deleted copy of an entry back in place
+    ///
+    /// Примеры:
+    ///   pm trash restore work/github
+    ///   pm trash restore --force work/github
+    Restore {
+        /// Original path of the deleted entry
+        path: String,
+        /// Replace an entry that now exists at that path
+        #[arg(short, long)]
+        force: bool,
+    },
+
+    /// Delete trashed entries for good
+    ///
+    /// Примеры:
+    ///   pm trash empty
+    ///   pm trash empty --older-than 30d
+    Empty {
+        /// Only entries deleted longer ago than this (30d, 2w, 6mo, ...)
+        #[arg(long, value_name = "DURATION")]
+        older_than: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum BulkCommands {
+    /// Replace or overwrite a field in every entry under a folder
+    ///
+    /// Примеры:
+    ///   pm bulk set --prefix work/ --field url --replace 'old-sso.corp.com=new-sso.corp.com'
+    ///   pm bulk set --prefix work/ --set username=alice@corp.com
+    ///   pm bulk set --prefix work/ --set notes= --dry-run
+    Set {
+        /// Folder to operate on, e.g. "work/"
+        #[arg(long)]
+        prefix: String,
+        /// Field for --replace
+        #[arg(long, value_enum, requires = "replace")]
+        field: Option<BulkField>,
+        /// Literal find/replace as OLD=NEW (use \= for a literal '=')
+        #[arg(long, requires = "field")]
+        replace: Option<String>,
+        /// Overwrite a field as FIELD=VALUE (repeatable)
+        #[arg(long = "set", value_name = "FIELD=VALUE")]
+        set: Vec<String>,
+        /// Only show what would change
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum WifiCommands {
+    /// Show a QR code that phones can scan to join the network
+    ///
+    /// Примеры:
+    ///   pm wifi qr home/wifi
+    ///   pm wifi qr home/wifi --invert
+    Qr {
+        /// Path like home/wifi
+        path: String,
+        /// Invert colors (for terminals with a light background)
+        #[arg(long)]
+        invert: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum HibpCommands {
+    /// Convert the text dump into a compact binary index for faster lookups
+    ///
+    /// Примеры:
+    ///   pm hibp build-index pwned-passwords-sha1-ordered-by-hash-v8.txt pwned.idx
+    BuildIndex {
+        /// Ordered-by-hash SHA-1 text dump
+        input: PathBuf,
+        /// Where to write the index
+        output: PathBuf,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ImportCommands {
+    /// Import a pass (password-store) directory, decrypting each file with gpg
+    ///
+    /// Примеры:
+    ///   pm import pass ~/.password-store --dry-run
+    ///   pm import pass ~/.password-store
+    Pass {
+        /// Store directory (usually ~/.password-store)
+        dir: PathBuf,
+        /// Only list the entries that would be created
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Import a CSV export (Chrome, Firefox, Bitwarden, 1Password, LastPass, ...)
+    ///
+    /// Columns are detected from the header; override them with --*-column.
+    /// Rows with an empty password are skipped.
+    ///
+    /// Примеры:
+    ///   pm import csv chrome.csv --prefix imported --dry-run
+    ///   pm import csv vault.csv --path-column name --username-column login --password-column pass --folder-column group
+    Csv {
+        /// CSV file with a header row
+        file: PathBuf,
+        #[command(flatten)]
+        columns: import::CsvColumns,
+        /// Put every entry under this folder
+        #[arg(long)]
+        prefix: Option<String>,
+        /// Only list the entries that would be created
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Import a passphrase-encrypted .pmx file written by `pm export --encrypted`
+    ///
+    /// Entries are re-encrypted with this store's master key.
+    ///
+    /// Примеры:
+    ///   pm import pmx laptop.pmx
+    ///   pm import pmx laptop.pmx --on-conflict rename
+    Pmx {
+        /// File written by `pm export --encrypted`
+        file: PathBuf,
+        /// What to do when an entry with the same path already exists
+        #[arg(long, value_enum, default_value_t = import::OnConflict::Skip)]
+        on_conflict: import::OnConflict,
+        /// Also replace local settings (hooks, display, session) with the exported ones
+        #[arg(long)]
+        with_settings: bool,
+        /// Only list the entries that would be created
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Import a KeePass database (via keepassxc-cli) or a KeePass 2 XML export
+    ///
+    /// Entries are created at group/subgroup/title; the recycle bin is skipped.
+    ///
+    /// Примеры:
+    ///   pm import keepass Passwords.kdbx
+    ///   pm import keepass Passwords.kdbx --keyfile ~/keys/db.keyx
+    ///   pm import keepass export.xml --dry-run
+    Keepass {
+        /// .kdbx database or .xml export
+        file: PathBuf,
+        /// Key file for the database
+        #[arg(long)]
+        keyfile: Option<PathBuf>,
+        /// Also import entries from the recycle bin
+        #[arg(long)]
+        include_recycled: bool,
+        /// Only list the entries that would be created
+        #[arg(long)]
+        dry_run: bool,
     },
+}
 
-    /// OTP management (TOTP)
+#[derive(Subcommand, Debug)]
+enum ShareCommands {
+    /// Import entries someone shared with you
+    ///
+    /// Entries go under shared/ (see --prefix) and are re-encrypted with this
+    /// store's master key.
     ///
     /// Примеры:
-    ///   pm otp add work/github
-    ///   pm otp show work/github
-    ///   pm otp clip work/github
-    Otp {
-        #[command(subcommand)]
-        cmd: OtpCommands,
+    ///   pm share import for-anna.age --identity key.txt
+    ///   pm share import for-anna.age --identity key.txt --prefix team --on-conflict rename
+    Import {
+        /// File written by `pm share` (binary or ASCII-armored age)
+        file: PathBuf,
+        /// age identity file with your AGE-SECRET-KEY-1... key
+        #[arg(short, long, value_name = "FILE")]
+        identity: PathBuf,
+        /// Put the entries under this folder ("" for none)
+        #[arg(long, default_value = "shared")]
+        prefix: String,
+        /// What to do when an entry with the same path already exists
+        #[arg(long, value_enum, default_value_t = import::OnConflict::Skip)]
+        on_conflict: import::OnConflict,
+        /// Only list the entries that would be created
+        #[arg(long)]
+        dry_run: bool,
     },
-
-    /// Backup the whole store
-    Backup {
-        #[command(subcommand)]
-        cmd: BackupCommands,
+    /// Create an age key pair for receiving shared entries
+    ///
+    /// Примеры:
+    ///   pm share keygen --output ~/.config/pm/share-key.txt
+    Keygen {
+        /// Where to write the secret key (created with mode 0600)
+        #[arg(id = "file", long = "output", short = 'o')]
+        file: PathBuf,
     },
 }
 
@@ -92,410 +1431,1220 @@ enum Commands {
 enum BackupCommands {
     /// Create backup archive
     ///
+    /// With --encrypt the archive is encrypted with a passphrase (Argon2id +
+    /// XChaCha20-Poly1305) into a .pmb file; `pm backup restore` asks for it.
+    /// PM_BACKUP_PASSPHRASE supplies the passphrase without a prompt.
+    ///
     /// Примеры:
     ///   pm backup create
     ///   pm backup create my_backup
     ///   pm backup create my_backup.zip
+    ///   pm backup create --encrypt
+    ///   pm backup create offsite --encrypt
     Create {
         /// Optional backup filename
         file: Option<String>,
+        /// Encrypt the archive with a passphrase (.pmb)
+        #[arg(long)]
+        encrypt: bool,
+    },
+    /// Replace the store with the contents of a backup archive
+    ///
+    /// The current store is kept next to it as pm-store.bak-<timestamp>.
+    /// An encrypted .pmb backup is recognized by its header and the passphrase
+    /// is asked for.
+    ///
+    /// Примеры:
+    ///   pm backup restore backup_2024-01-01.zip
+    ///   pm backup restore my_backup.tar.gz --force
+    ///   pm backup restore backup_2024-01-01.pmb
+    Restore {
+        /// Backup archive (.zip, .tar.gz, .tar.zst or .pmb)
+        file: PathBuf,
+        /// Replace an existing store
+        #[arg(long)]
+        force: bool,
+    },
+    /// Check that a backup archive is complete and restorable
+    ///
+    /// Checks config.json, the envelope of every entry file and the
+    /// MANIFEST.sha256 checksums that pm embeds in its archives. --deep also
+    /// decrypts every entry with the backup's master password. Exits non-zero
+    /// and lists the files that failed.
+    ///
+    /// Примеры:
+    ///   pm backup verify backup_2024-01-01.zip
+    ///   pm backup verify pm-backups/pm-backup-20240101T030000Z.tar.gz --deep
+    Verify {
+        /// Backup archive (.zip, .tar.gz, .tar.zst or .pmb)
+        file: PathBuf,
+        /// Also decrypt every entry (asks for the master password)
+        #[arg(long)]
+        deep: bool,
+    },
+    /// Write a timestamped archive to backup.dir and prune old ones (for cron)
+    ///
+    /// Archives are named pm-backup-<UTC time>.tar.gz. The newest archive of
+    /// each of the last backup.keep_daily days, backup.keep_weekly weeks and
+    /// backup.keep_monthly months is kept; other files in the directory are
+    /// never touched. Prints nothing on success unless --verbose.
+    ///
+    /// Примеры:
+    ///   pm backup auto
+    ///   pm config set backup.dir /mnt/nas/pm-backups
+    ///   0 3 * * * pm backup auto    # crontab
+    Auto,
+    /// List archives made by `pm backup auto`, newest first
+    ///
+    /// Примеры:
+    ///   pm backup list
+    List,
+    /// Delete archives outside the retention policy
+    ///
+    /// Примеры:
+    ///   pm backup prune --dry-run
+    ///   pm config set backup.keep_daily 14 && pm backup prune
+    Prune {
+        /// Only print what would be deleted
+        #[arg(long)]
+        dry_run: bool,
     },
 }
 
 #[derive(Subcommand, Debug)]
 enum OtpCommands {
     /// Attach OTP secret or otpauth:// URL to entry
+    ///
+    /// Without --uri or --secret the secret is prompted for.
+    ///
+    /// Примеры:
+    ///   pm otp add work/github
+    ///   pm otp add work/github --uri 'otpauth://totp/GitHub:me?secret=JBSWY3DPEHPK3PXP'
+    ///   pm otp add games/steam --steam --secret - < steam-secret.txt
+    ///   pm otp add work/github --qr-image screenshot.png
     Add {
-        /// Path like work/github
-        path: String,
+        /// Path like work/github (omit to pick interactively)
+        path: Option<String>,
+        /// otpauth:// URL
+        #[arg(long, conflicts_with_all = ["secret", "qr_image"])]
+        uri: Option<String>,
+        /// Read the otpauth:// URL from a QR code in an image ("-": stdin; needs zbarimg)
+        #[arg(long, value_name = "FILE", conflicts_with = "secret")]
+        qr_image: Option<String>,
+        /// Base32 secret ("-": read from stdin)
+        #[arg(long)]
+        secret: Option<String>,
+        /// Steam Guard codes (5 characters) instead of digits
+        #[arg(long)]
+        steam: bool,
     },
-    /// Show current OTP code
+    /// Show current OTP code (advances the HOTP counter)
+    ///
+    /// Примеры:
+    ///   pm otp show work/github
+    ///   pm otp show work/github -v     # also seconds left and the next code
+    ///   pm otp show work/github --at 2024-05-01T12:00:00Z
     Show {
-        /// Path like work/github
-        path: String,
+        /// Path like work/github (omit to pick interactively)
+        path: Option<String>,
+        /// Generate the TOTP code for this time (Unix seconds or RFC3339)
+        #[arg(long, value_name = "TIME")]
+        at: Option<String>,
     },
-    /// Copy current OTP code to clipboard
+    /// Copy current OTP code to clipboard (advances the HOTP counter)
     Clip {
+        /// Path like work/github (omit to pick interactively)
+        path: Option<String>,
+    },
+    /// Detach the OTP secret from an entry
+    ///
+    /// Примеры:
+    ///   pm otp remove work/github
+    ///   pm otp remove work/github --force
+    Remove {
+        /// Path like work/github (omit to pick interactively)
+        path: Option<String>,
+        /// Don't ask for confirmation
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// Print the otpauth:// URL to enroll another authenticator
+    ///
+    /// Примеры:
+    ///   pm otp uri work/github
+    ///   pm otp uri work/github --qr
+    Uri {
+        /// Path like work/github (omit to pick interactively)
+        path: Option<String>,
+        /// Show the URL as a QR code in the terminal
+        #[arg(long)]
+        qr: bool,
+        /// Invert QR colors (for terminals with a light background)
+        #[arg(long, requires = "qr")]
+        invert: bool,
+    },
+    /// Set the HOTP counter, e.g. after the server and pm drifted apart
+    ///
+    /// Примеры:
+    ///   pm otp set-counter bank/token 42
+    SetCounter {
         /// Path like work/github
         path: String,
+        /// Counter value for the next code
+        counter: u64,
+    },
+    /// Generate a code from a secret without touching the store
+    ///
+    /// Примеры:
+    ///   pm otp gen --secret JBSWY3DPEHPK3PXP
+    ///   pm otp gen --secret JBSWY3DPEHPK3PXP --digits 8 --period 60 --algo SHA256
+    ///   pm otp gen --uri 'otpauth://totp/x?secret=JBSWY3DPEHPK3PXP'
+    ///   pass show otp | pm otp gen
+    Gen {
+        /// Base32 secret ("-" or omitted: read from stdin)
+        #[arg(long, conflicts_with = "uri")]
+        secret: Option<String>,
+        /// Full otpauth:// URL
+        #[arg(long)]
+        uri: Option<String>,
+        /// Number of digits
+        #[arg(long, default_value_t = 6, conflicts_with = "uri")]
+        digits: u8,
+        /// Period in seconds
+        #[arg(long, default_value_t = 30, conflicts_with = "uri")]
+        period: u32,
+        /// Hash algorithm: SHA1, SHA256 or SHA512
+        #[arg(long, default_value = "SHA1", conflicts_with = "uri")]
+        algo: String,
     },
 }
 
-#[derive(Copy, Clone, Debug, ValueEnum)]
-enum ClipField {
-    Password,
-    Username,
+/// Цель калибровки Argon2id, общая для `pm init` и `pm config upgrade-kdf`.
+#[derive(clap::Args, Debug)]
+struct KdfArgs {
+    /// Calibrate the KDF so that unlocking takes about this long (default 500)
+    #[arg(long, value_name = "MS")]
+    kdf_target_ms: Option<u32>,
+    /// Use this much memory for the KDF and calibrate only the iterations (19-1024)
+    #[arg(long, value_name = "MIB")]
+    kdf_memory_mib: Option<u32>,
+}
+
+impl KdfArgs {
+    fn is_set(&self) -> bool {
+        self.kdf_target_ms.is_some() || self.kdf_memory_mib.is_some()
+    }
+
+    fn target(&self) -> crypto::KdfTarget {
+        crypto::KdfTarget {
+            target_ms: self.kdf_target_ms.unwrap_or(crypto::DEFAULT_KDF_TARGET_MS),
+            memory_mib: self.kdf_memory_mib,
+        }
+    }
 }
 
 fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
+    output::set_format(cli.output);
+
+    let result = run(cli);
+    let code = result
+        .as_ref()
+        .err()
+        .and_then(|e| e.downcast_ref::<show::GetError>())
+        .map_or(1, |e| e.code);
+    let result = result.map_err(|e| match profiles::active_name() {
+        Some(profile) => anyhow!("{e:#} (profile {profile})"),
+        None => e,
+    });
+    if let Err(ref e) = result
+        && (output::is_json() || code != 1)
+    {
+        if output::is_json() {
+            output::print_error(e);
+        } else {
+            eprintln!("Error: {e:?}");
+        }
+        std::process::exit(code);
+    }
+    result
+}
+
+fn run(cli: Cli) -> anyhow::Result<()> {
+    // До всего остального: проверки окружения уже смотрят на хранилище
+    match cli.store {
+        Some(ref dir) => set_store_override(dir.clone()),
+        None => {
+            if let Some(dir) = profiles::activate(cli.profile.as_deref())? {
+                set_store_override(dir);
+            }
+        }
+    }
+    envcheck::warn_unsafe_environment();
+    if let Some(path) = cli.keyfile {
+        keyfile::set_keyfile(path);
+    }
+    pm::log::set_level(cli.verbose);
+    output::set_quiet(cli.quiet);
+    recent::set_no_touch(cli.no_touch || cli.read_only);
+    hooks::set_no_hooks(cli.no_hooks);
+    session::set_no_cache(cli.no_cache);
+    git::set_no_commit(cli.no_commit);
+    lock::set_read_only(cli.read_only);
+
+    let access = store_access(&cli.command);
+    let command = format!("pm {}", command_name(&cli.command));
+    if cli.read_only && access == Access::Write {
+        anyhow::bail!("`{command}` changes the store, which is opened with --read-only");
+    }
+    // Держим до конца команды
+    let _lock = lock::acquire(access, &command)?;
 
     match cli.command {
-        Commands::Init => cmd_init()?,
-        Commands::Add { path } => cmd_add(&path)?,
+        Commands::Init { from_backup, kdf } => match from_backup {
+            Some(_) if kdf.is_set() => {
+                anyhow::bail!(
+                    "--kdf-* options do not apply to --from-backup; run `pm config upgrade-kdf` afterwards"
+                )
+            }
+            Some(archive) => init_from_backup(&archive)?,
+            None => {
+                let target = kdf.target();
+                match master::init(&target)? {
+                    master::Init::Exists(root) => {
+                        println!("Store already exists at: {}", root.display())
+                    }
+                    master::Init::Created { root, kdf, keyfile } => {
+                        println!("Initialized store at {}", root.display());
+                        println!(
+                            "KDF: Argon2id, {} MiB, {} iterations (calibrated for ~{} ms)",
+                            kdf.memory_mib, kdf.iterations, target.target_ms
+                        );
+                        if let Some(path) = keyfile {
+                            println!(
+                                "Unlocking also requires the keyfile {}; keep a copy of it, the store cannot be opened without it.",
+                                path.display()
+                            );
+                        }
+                    }
+                }
+            }
+        },
+        Commands::Add {
+            path,
+            kind,
+            clip,
+            print,
+            passphrase,
+            fields,
+            force,
+            drop_otp,
+        } => {
+            let replace = force.then_some(drop_otp);
+            let saved = edit::add(&path, kind, &passphrase, &fields, replace)?;
+            let what = if saved.generated {
+                "Generated password"
+            } else {
+                "Password"
+            };
+            report_password("add", &path, &saved.password, what, clip, print)?
+        }
         Commands::Show {
             path,
             password_only,
             json,
-        } => cmd_show(&path, password_only, json)?,
-        Commands::Clip { path, field } => {
-            cmd_clip(&path, field.unwrap_or(ClipField::Password))?
+            include_history,
+            reveal,
+            phonetic,
+            qr,
+            invert,
+            field,
+            format,
+            prefix,
+            clear_after,
+            force,
+        } => {
+            let path = path_or_pick(path)?;
+            let field = field.unwrap_or(Field::Password);
+            if phonetic {
+                check_terminal(force, "spell out a secret")?;
+                for line in phonetic_lines(&show::field_value(&path, &field, "show")?) {
+                    println!("{line}");
+                }
+            } else if qr {
+                check_terminal(force, "draw a secret")?;
+                let value = show::field_value(&path, &field, "show")?;
+                envcheck::warn_secret_output();
+                print!("{}", render_qr_terminal(&value, invert)?);
+                println!("{} of {path}", field.label());
+            } else if let Some(format) = format {
+                check_terminal(force, "write secrets")?;
+                print!("{}", show::snippet(&path, format, &prefix)?);
+            } else {
+                let json = (json || output::is_json()).then_some(include_history);
+                print_entry(&path, password_only, json, reveal, clear_after)?
+            }
+        }
+        Commands::Edit { path } => {
+            let path = path_or_pick(path)?;
+            edit::edit(&path)?;
+            output::done("edit", &path, format!("Updated entry {path}"));
+        }
+        Commands::Regen {
+            path,
+            length,
+            clip,
+            print,
+            passphrase,
+        } => {
+            let path = path_or_pick(path)?;
+            let saved = edit::regen(&path, length, &passphrase)?;
+            report_password("regen", &path, &saved.password, "New password", clip, print)?
+        }
+        Commands::Rm {
+            path,
+            force,
+            permanent,
+        } => match trash::remove_entry(&path_or_pick(path)?, force, permanent)? {
+            None => println!("Aborted."),
+            Some(path) if permanent => output::done("rm", &path, format!("Deleted entry {path}")),
+            Some(path) => output::done(
+                "rm",
+                &path,
+                format!("Moved {path} to the trash (undo: pm trash restore {path})"),
+            ),
+        },
+        Commands::Trash { cmd } => match cmd {
+            TrashCommands::List => trash::cmd_list()?,
+            TrashCommands::Restore { path, force } => {
+                let item = trash::restore_entry(&path, force)?;
+                output::done(
+                    "restore",
+                    &item.path,
+                    format!("Restored {} (deleted {})", item.path, item.deleted_at),
+                );
+            }
+            TrashCommands::Empty { older_than } => trash::cmd_empty(older_than.as_deref())?,
+        },
+        Commands::Mv {
+            src,
+            dst,
+            force,
+            to_profile: Some(profile),
+            on_conflict,
+        } => transfer::to_profile(
+            &src,
+            &dst,
+            &profile,
+            transfer::Mode::Move,
+            transfer_conflict(force, on_conflict),
+        )?,
+        Commands::Mv {
+            src, dst, force, ..
+        } => {
+            let plan = transfer::move_entries(&src, &dst, force)?;
+            for (from, to) in &plan {
+                output::note(format!("Moved {from} -> {to}"));
+            }
+            let moved: Vec<_> = plan
+                .iter()
+                .map(|(from, to)| serde_json::json!({ "from": from, "to": to }))
+                .collect();
+            output::emit(
+                &serde_json::json!({ "ok": true, "action": "mv", "moved": moved }),
+                || Ok(()),
+            )?
+        }
+        Commands::Cp {
+            src,
+            dst,
+            force,
+            no_otp,
+            to_profile: Some(profile),
+            on_conflict,
+        } => transfer::to_profile(
+            &src,
+            &dst,
+            &profile,
+            transfer::Mode::Copy { no_otp },
+            transfer_conflict(force, on_conflict),
+        )?,
+        Commands::Cp {
+            src,
+            dst,
+            force,
+            no_otp,
+            ..
+        } => {
+            let (src, dst) = transfer::copy_entry(&src, &dst, force, no_otp)?;
+            output::done("cp", &dst, format!("Copied {src} -> {dst}"));
+        }
+        Commands::Generate {
+            length,
+            no_symbols,
+            no_digits,
+            no_upper,
+            no_lower,
+            clip,
+            count,
+            passphrase,
+        } => {
+            let charset = edit::Charset {
+                upper: !no_upper,
+                lower: !no_lower,
+                digits: !no_digits,
+                symbols: !no_symbols,
+            };
+            // С --clip нужен один пароль
+            let count = if clip { 1 } else { count };
+            let passwords = edit::generate(length, &charset, &passphrase, count)?;
+            if clip {
+                clipboard::copy_secret(&passwords[0], CopyOptions::default())?;
+                output::note("Password copied to clipboard.");
+            } else {
+                for password in passwords {
+                    println!("{password}");
+                }
+            }
+        }
+        Commands::ClipboardClear {
+            after,
+            backend,
+            primary,
+        } => clipboard::clear_if_unchanged(after, backend.as_deref(), primary)?,
+        Commands::Clip {
+            path,
+            field,
+            once,
+            primary,
+            osc52,
+        } => {
+            let opts = CopyOptions {
+                once,
+                primary,
+                osc52,
+            };
+            let path = path_or_pick(path)?;
+            let field = field.unwrap_or(Field::Password);
+            clipboard::copy_secret(&show::field_value(&path, &field, "clip")?, opts)?;
+            output::done(
+                "clip",
+                &path,
+                format!("{} copied to clipboard.", field.label()),
+            );
+        }
+        Commands::Get {
+            path,
+            field,
+            no_newline,
+            password_fd,
+            ttl,
+        } => {
+            if let Some(fd) = password_fd {
+                session::set_master_password(pm::prompt::read_secret_fd(fd)?);
+            }
+            if let Some(ttl) = ttl {
+                session::set_ttl_override(ttl);
+            }
+            let value = show::get(&path, &field)?;
+            output::emit(&serde_json::json!({ "path": path, "value": value }), || {
+                print!("{value}");
+                if !no_newline {
+                    println!();
+                }
+                Ok(())
+            })?
         }
-        Commands::Ls { prefix } => cmd_ls(prefix.as_deref())?,
+        Commands::Menu {
+            path,
+            list,
+            clip,
+            sequence,
+            delay,
+        } => {
+            if list {
+                for path in list_entries()? {
+                    println!("{path}");
+                }
+            } else {
+                let path = match path {
+                    Some(path) => path,
+                    None => {
+                        let mut line = String::new();
+                        std::io::stdin().read_line(&mut line)?;
+                        line.trim().to_string()
+                    }
+                };
+                // Меню закрыли, ничего не выбрав
+                if !path.is_empty() {
+                    let path = validate_entry_path(&path)?;
+                    match clip {
+                        Some(field) => {
+                            let value = show::field_value(&path, &field, "clip")?;
+                            clipboard::copy_secret(&value, CopyOptions::default())?;
+                            output::done(
+                                "clip",
+                                &path,
+                                format!("{} copied to clipboard.", field.label()),
+                            );
+                        }
+                        None => {
+                            let delay = Duration::from_millis(delay);
+                            let backend = autotype::type_entry(&path, &sequence, delay)?;
+                            pm::log::info(format_args!("typed {path} with {backend}"));
+                        }
+                    }
+                }
+            }
+        }
+        Commands::Ls {
+            prefix,
+            flat,
+            print0,
+            dates,
+            expired,
+            tag,
+        } => {
+            check_print0(print0)?;
+            let filter = listing::LsFilter {
+                prefix: prefix.as_deref(),
+                dates: &dates,
+                expired,
+                tag: tag.as_deref(),
+            };
+            let (prefix, entries) = listing::ls(&filter)?;
+            if flat || print0 || output::is_json() {
+                print_paths(&entries, print0)?
+            } else {
+                print!("{}", listing::render_tree(prefix.as_deref(), &entries));
+            }
+        }
+        Commands::Tag { cmd } => match cmd {
+            TagCommands::Add { path, tag } => tags::tag_add(&path, &tag)?,
+            TagCommands::Rm { path, tag } => tags::tag_rm(&path, &tag)?,
+            TagCommands::List => tags::tag_list()?,
+        },
+        Commands::Attach { cmd } => match cmd {
+            AttachCommands::Add {
+                path,
+                file,
+                name,
+                force,
+            } => attachments::attach_add(&path, &file, name.as_deref(), force)?,
+            AttachCommands::List { path } => attachments::attach_list(&path)?,
+            AttachCommands::Get {
+                path,
+                name,
+                file: output,
+                force,
+            } => attachments::attach_get(&path, &name, output.as_deref(), force)?,
+            AttachCommands::Rm { path, name } => attachments::attach_rm(&path, &name)?,
+        },
+        Commands::Expiring { within } => expiry::expiring(&within)?,
+        Commands::Index { cmd } => match cmd {
+            IndexCommands::Rebuild => index::rebuild()?,
+        },
+        Commands::Find {
+            query,
+            regex,
+            ignore_case,
+            first,
+            print0,
+            dates,
+        } => {
+            let pattern = regex.then(|| grep::Pattern::new(&query, true, ignore_case));
+            check_print0(print0)?;
+            let mut found = listing::find(&query, pattern.transpose()?, &dates)?;
+            if found.is_empty() {
+                print_paths(&found, print0)?;
+                std::process::exit(1);
+            }
+            if first {
+                found.truncate(1);
+            }
+            print_paths(&found, print0)?
+        }
+        Commands::Grep {
+            pattern,
+            regex,
+            ignore_case,
+            fields,
+            include_passwords,
+        } => grep::grep(
+            &grep::Pattern::new(&pattern, regex, ignore_case)?,
+            &fields,
+            include_passwords,
+        )?,
+        Commands::Url { query, loose, clip } => print_url_matches(&query, loose, clip)?,
         Commands::Otp { cmd } => match cmd {
-            OtpCommands::Add { path } => cmd_otp_add(&path)?,
-            OtpCommands::Show { path } => cmd_otp_show(&path)?,
-            OtpCommands::Clip { path } => cmd_otp_clip(&path)?,
+            OtpCommands::Add {
+                path,
+                uri,
+                qr_image,
+                secret,
+                steam,
+            } => {
+                let uri = match qr_image {
+                    Some(image) => Some(qrscan::decode_otpauth(&image)?),
+                    None => uri,
+                };
+                let path = path_or_pick(path)?;
+                entry_otp::add(&path, uri.or(secret).as_deref(), steam)?;
+                output::done("otp-add", &path, format!("OTP configured for {path}"));
+            }
+            OtpCommands::Show { path, at } => {
+                let otp = entry_otp::show(&path_or_pick(path)?, at.as_deref())?;
+                output::emit(&otp, || {
+                    match (cli.verbose > 0, otp.counter, otp.expires_in, &otp.next) {
+                        (true, Some(counter), _, _) => println!("{} (counter {counter})", otp.code),
+                        (true, _, Some(left), Some(next)) => {
+                            println!("{} (valid {left}s)  next: {next}", otp.code)
+                        }
+                        _ => println!("{}", otp.code),
+                    }
+                    Ok(())
+                })?
+            }
+            OtpCommands::Clip { path } => {
+                let path = path_or_pick(path)?;
+                clipboard::copy_secret(&entry_otp::clip(&path)?, CopyOptions::default())?;
+                output::done("otp-clip", &path, "OTP code copied to clipboard.");
+            }
+            OtpCommands::Remove { path, force } => {
+                let path = path_or_pick(path)?;
+                if entry_otp::remove(&path, force)? {
+                    output::done("otp-remove", &path, format!("OTP removed from {path}"));
+                } else {
+                    println!("Aborted.");
+                }
+            }
+            OtpCommands::Uri { path, qr, invert } => {
+                let uri = entry_otp::uri(&path_or_pick(path)?)?;
+                if qr {
+                    print!("{}", render_qr_terminal(&uri, invert)?);
+                } else {
+                    println!("{uri}");
+                }
+            }
+            OtpCommands::SetCounter { path, counter } => {
+                entry_otp::set_counter(&path, counter)?;
+                output::done(
+                    "otp-set-counter",
+                    &path,
+                    format!("HOTP counter for {path} set to {counter}"),
+                );
+            }
+            OtpCommands::Gen {
+                secret,
+                uri,
+                digits,
+                period,
+                algo,
+            } => {
+                let code =
+                    entry_otp::generate(secret.as_deref(), uri.as_deref(), digits, period, &algo)?;
+                println!("{code}");
+            }
+        },
+        Commands::Bulk { cmd } => match cmd {
+            BulkCommands::Set {
+                prefix,
+                field,
+                replace,
+                set,
+                dry_run,
+            } => bulk_set(&prefix, field, replace, set, dry_run)?,
+        },
+        Commands::Wifi { cmd } => match cmd {
+            WifiCommands::Qr { path, invert } => wifi_qr(&path, invert)?,
+        },
+        Commands::Audit {
+            hibp,
+            timeout,
+            hibp_offline,
+            db,
+            min_length,
+            max_age_days,
+            fail_under,
+        } => match db {
+            Some(db) if hibp_offline => audit_hibp_offline(&db)?,
+            _ if hibp => hibp::pwned(None, Duration::from_secs(timeout), None)?,
+            _ => audit::audit(min_length, max_age_days, fail_under)?,
+        },
+        Commands::Pwned {
+            path,
+            timeout,
+            offline,
+        } => hibp::pwned(
+            path.as_deref(),
+            Duration::from_secs(timeout),
+            offline.as_deref(),
+        )?,
+        Commands::Hibp { cmd } => match cmd {
+            HibpCommands::BuildIndex { input, output } => {
+                let n = build_index(&input, &output)?;
+                println!("Indexed {n} hashes into {}", output.display());
+            }
+        },
+        Commands::Export {
+            format,
+            encrypted,
+            output,
+            prefix,
+            acknowledged,
+        } => match (encrypted, format) {
+            (Some(file), _) => pmx::export_encrypted(&file, prefix.as_deref())?,
+            (None, Some(format)) => {
+                export::export_plain(format, output.as_deref(), prefix.as_deref(), acknowledged)?
+            }
+            (None, None) => unreachable!("clap requires --format or --encrypted"),
+        },
+        Commands::Share {
+            cmd,
+            paths,
+            recipients,
+            file: output,
+        } => match cmd {
+            Some(ShareCommands::Import {
+                file,
+                identity,
+                prefix,
+                on_conflict,
+                dry_run,
+            }) => share::import(&file, &identity, &prefix, on_conflict, dry_run)?,
+            Some(ShareCommands::Keygen { file }) => share::keygen(&file)?,
+            None => {
+                let output = output.expect("clap requires --output");
+                share::share(&paths, &recipients, &output)?
+            }
+        },
+        Commands::Import { cmd } => match cmd {
+            ImportCommands::Pass { dir, dry_run } => import::import_pass(&dir, dry_run)?,
+            ImportCommands::Csv {
+                file,
+                columns,
+                prefix,
+                dry_run,
+            } => import::import_csv(&file, &columns, prefix.as_deref(), dry_run)?,
+            ImportCommands::Pmx {
+                file,
+                on_conflict,
+                with_settings,
+                dry_run,
+            } => pmx::import_pmx(&file, on_conflict, with_settings, dry_run)?,
+            ImportCommands::Keepass {
+                file,
+                keyfile,
+                include_recycled,
+                dry_run,
+            } => keepass::import_keepass(&file, keyfile.as_deref(), include_recycled, dry_run)?,
         },
         Commands::Backup { cmd } => match cmd {
-            BackupCommands::Create { file } => backup_create(file)?,
+            BackupCommands::Create { file, encrypt } => backup_create(file, encrypt)?,
+            BackupCommands::Restore { file, force } => backup_restore(&file, force)?,
+            BackupCommands::Verify { file, deep } => backup_verify(&file, deep)?,
+            BackupCommands::Auto => backup_auto()?,
+            BackupCommands::List => backup_list()?,
+            BackupCommands::Prune { dry_run } => backup_prune(dry_run)?,
+        },
+        Commands::VerifyPassword { path, field } => {
+            if show::verify_password(&path, &field)? {
+                println!("match");
+            } else {
+                println!("no match");
+                std::process::exit(1);
+            }
+        }
+        Commands::History { path, show, clip } => print_history(&path, show, clip)?,
+        Commands::Recent { limit, clear } => recent(limit, clear)?,
+        Commands::Keychain { cmd } => match cmd {
+            KeychainCommands::Store => keychain_store()?,
+            KeychainCommands::Forget => keychain_forget()?,
+        },
+        Commands::Config { cmd } => match cmd {
+            ConfigCommands::List => config::config_list()?,
+            ConfigCommands::Get { key } => config::config_get(&key)?,
+            ConfigCommands::Set { key, value } => config::config_set(&key, &value)?,
+            ConfigCommands::UpgradeKdf { kdf } => {
+                let kdf = master::upgrade_kdf(&kdf.target())?;
+                let describe = |k: &config::KdfParams| {
+                    format!("{} MiB, {} iterations", k.memory_mib, k.iterations)
+                };
+                if kdf.upgraded {
+                    println!(
+                        "KDF upgraded: {} -> {}",
+                        describe(&kdf.old),
+                        describe(&kdf.new)
+                    );
+                } else {
+                    println!(
+                        "Current KDF ({}) is already at least as strong as the calibrated one ({}); nothing to do.",
+                        describe(&kdf.old),
+                        describe(&kdf.new)
+                    );
+                }
+            }
+            ConfigCommands::AddKeyfile { path } => {
+                master::set_keyfile(Some(&path))?;
+                println!(
+                    "Unlocking now requires the keyfile {}; keep a copy of it, the store cannot be opened without it.",
+                    path.display()
+                );
+            }
+            ConfigCommands::RemoveKeyfile => {
+                master::set_keyfile(None)?;
+                println!("Keyfile removed; the master password alone unlocks the store.");
+            }
+        },
+        Commands::Agent { stop: true, .. } => agent::stop()?,
+        Commands::Agent { foreground, .. } => {
+            let config = Config::load()?;
+            if foreground {
+                agent::run(&config)?
+            } else {
+                agent::start(&config)?
+            }
+        }
+        Commands::Lock => session::lock()?,
+        Commands::Status => status::status()?,
+        Commands::Passwd => {
+            master::passwd()?;
+            println!("Master password changed.");
+        }
+        Commands::Rekey => rekey::rekey()?,
+        Commands::Bench { json } => bench::bench(json)?,
+        Commands::Fsck { fix } => fsck(fix)?,
+        Commands::Doctor { fix_permissions } => doctor(fix_permissions)?,
+        Commands::Git { args } => git::git_command(&args)?,
+        Commands::GitCredential { operation } => credential::git_credential(operation)?,
+        Commands::Migrate { dry_run } => migrate(dry_run)?,
+        Commands::Completions { shell } => completions(shell, &Cli::command())?,
+        Commands::CompletePaths { prefix, print0 } => complete_paths(&prefix, print0)?,
+        Commands::Profile { cmd } => match cmd {
+            ProfileCommands::List => profiles::profile_list()?,
+            ProfileCommands::Add {
+                name,
+                path,
+                session_ttl,
+            } => profiles::profile_add(&name, &path, session_ttl)?,
+            ProfileCommands::Remove { name } => profiles::profile_remove(&name)?,
+            ProfileCommands::Default { name } => profiles::profile_default(&name)?,
+        },
+        #[cfg(feature = "serve")]
+        Commands::Serve {
+            cmd,
+            listen,
+            allow_remote,
+        } => match cmd {
+            None => serve::serve(&listen, allow_remote)?,
+            Some(ServeCommands::Token { cmd }) => match cmd {
+                TokenCommands::Create { scopes } => serve::token_create(&scopes)?,
+                TokenCommands::List => serve::token_list()?,
+                TokenCommands::Revoke { id } => serve::token_revoke(&id)?,
+            },
         },
     }
 
     Ok(())
 }
 
-fn cmd_init() -> anyhow::Result<()> {
-    let root = store_root()?;
-    if root.exists() {
-        println!("Store already exists at: {}", root.display());
-        return Ok(());
-    }
-
-    std::fs::create_dir_all(&root)?;
-    let master_password = prompt_password_hidden("New master password: ")?;
-    let confirm = prompt_password_hidden("Confirm master password: ")?;
-    if master_password != confirm {
-        anyhow::bail!("Passwords do not match");
+/// Итог `pm add`/`pm regen`: «Saved entry», затем пароль в буфер обмена
+/// (`--clip`) или на экран один раз (`--print`).
+fn report_password(
+    action: &str,
+    path: &str,
+    password: &str,
+    what: &str,
+    clip: bool,
+    print: bool,
+) -> anyhow::Result<()> {
+    if clip {
+        clipboard::copy_secret(password, CopyOptions::default())?;
     }
-
-    let config = generate_new_config(&master_password)?;
-    let config_path = crate::config::config_path()?;
-    crate::config::save_config(&config, &config_path)?;
-
-    println!("Initialized store at {}", root.display());
-    Ok(())
+    let result = serde_json::json!({
+        "ok": true,
+        "action": action,
+        "path": path,
+        "copied": clip,
+        "password": (print && !clip).then_some(password),
+    });
+    output::emit(&result, || {
+        output::note(format!("Saved entry {path}"));
+        if clip {
+            output::note(format!("{what} copied to clipboard."));
+        } else if print {
+            println!("{what}: {password}");
+        }
+        Ok(())
+    })
 }
 
-fn cmd_add(path: &str) -> anyhow::Result<()> {
-    ensure_store_dirs(path)?;
-
-    let config = Config::load()?;
-    let mk = get_master_key_with_cache(&config)?;
-
-    let title = path.to_string();
-    let username = prompt_string("Username (optional): ")?;
-    let password = prompt_password_hidden("Password (leave empty to generate): ")?;
-    let password = if password.is_empty() {
-        crypto::generate_password(24, true, true, true)?
+/// `--on-conflict` для `--to-profile`; `--force` там значит overwrite.
+fn transfer_conflict(force: bool, on_conflict: Option<import::OnConflict>) -> import::OnConflict {
+    if force {
+        import::OnConflict::Overwrite
     } else {
-        password
-    };
-    let url = prompt_string("URL (optional): ")?;
-    let notes = prompt_string("Notes (optional): ")?;
-
-    let now =
-        OffsetDateTime::now_utc().format(&time::format_description::well_known::Rfc3339)?;
-
-    let entry = Entry {
-        version: 1,
-        title,
-        username: if username.is_empty() {
-            None
-        } else {
-            Some(username)
-        },
-        password,
-        url: if url.is_empty() { None } else { Some(url) },
-        notes: if notes.is_empty() { None } else { Some(notes) },
-        created_at: now.clone(),
-        updated_at: now.clone(),
-        otp: None,
-    };
-
-    save_entry(path, &entry, &mk)?;
-    println!("Saved entry {}", path);
-    Ok(())
-}
-
-fn cmd_show(path: &str, password_only: bool, json: bool) -> anyhow::Result<()> {
-    let config = Config::load()?;
-    let mk = get_master_key_with_cache(&config)?;
-
-    let entry = load_entry(path, &mk)?;
-
-    if json {
-        let s = serde_json::to_string_pretty(&entry)?;
-        println!("{s}");
-        return Ok(());
-    }
-
-    if password_only {
-        println!("{}", entry.password);
-        return Ok(());
+        on_conflict.unwrap_or_default()
     }
+}
 
-    println!("Title:    {}", entry.title);
-    if let Some(ref u) = entry.username {
-        println!("Username: {u}");
-    }
-    println!("Password: {}", entry.password);
-    if let Some(ref url) = entry.url {
-        println!("URL:      {url}");
+/// `json`: `Some(include_history)` для `--json`.
+fn print_entry(
+    path: &str,
+    password_only: bool,
+    json: Option<bool>,
+    reveal: bool,
+    clear_after: Option<u64>,
+) -> anyhow::Result<()> {
+    let shown = show::show(path, reveal)?;
+
+    if password_only && output::is_json() {
+        let password = &shown.entry.password;
+        return output::emit(&serde_json::json!({ "password": password }), || Ok(()));
     }
-    if let Some(ref notes) = entry.notes {
-        println!("Notes:    {notes}");
+    if let Some(include_history) = json {
+        let s = serde_json::to_string_pretty(&shown.into_json(include_history))?;
+        return emit_revealed(&format!("{s}\n"), clear_after);
     }
-    println!("Created:  {}", entry.created_at);
-    println!("Updated:  {}", entry.updated_at);
-    if entry.otp.is_some() {
-        println!("OTP:      configured");
-    } else {
-        println!("OTP:      not set");
+    if password_only {
+        return emit_revealed(&format!("{}\n", shown.entry.password), clear_after);
     }
-
-    Ok(())
+    emit_revealed(&shown.to_text()?, clear_after)
 }
 
-fn cmd_clip(path: &str, field: ClipField) -> anyhow::Result<()> {
-    let config = Config::load()?;
-    let mk = get_master_key_with_cache(&config)?;
-
-    let entry = load_entry(path, &mk)?;
-
-    let value = match field {
-        ClipField::Password => entry.password.clone(),
-        ClipField::Username => entry.username.clone().unwrap_or_default(),
-    };
-
-    copy_to_clipboard(&value)?;
-    println!(
-        "{} copied to clipboard.",
-        match field {
-            ClipField::Password => "Password",
-            ClipField::Username => "Username",
+/// Напечатать вывод `show`, при `--clear-after` — со стиранием по таймеру.
+fn emit_revealed(text: &str, clear_after: Option<u64>) -> anyhow::Result<()> {
+    match clear_after {
+        Some(secs) => reveal::print_and_clear(text, secs),
+        None => {
+            print!("{text}");
+            Ok(())
         }
-    );
-
-    Ok(())
+    }
 }
 
-fn cmd_ls(prefix: Option<&str>) -> anyhow::Result<()> {
-    let entries = list_entries()?;
-
-    if entries.is_empty() {
-        return Ok(());
+/// Секрет на экран (`--phonetic`, `--qr`, `--format`) — только в терминал,
+/// если не передан `--force`.
+fn check_terminal(force: bool, what: &str) -> anyhow::Result<()> {
+    if !std::io::stdout().is_terminal() && !force {
+        anyhow::bail!("Refusing to {what} to a non-terminal; pass --force to override");
     }
+    Ok(())
+}
 
-    match prefix {
-        None => {
-            for e in entries {
-                println!("{e}");
-            }
+/// pm history PATH [--show N | --clip N]
+fn print_history(path: &str, show: Option<usize>, clip: Option<usize>) -> anyhow::Result<()> {
+    match (show, clip) {
+        (_, Some(n)) => {
+            let password = show::previous_password(path, n, "clip")?;
+            clipboard::copy_secret(&password, CopyOptions::default())?;
+            output::done(
+                "clip",
+                path,
+                format!("Previous password #{n} copied to clipboard."),
+            );
+            Ok(())
+        }
+        (Some(n), None) => {
+            let password = show::previous_password(path, n, "show")?;
+            output::emit(&serde_json::json!({ "password": password }), || {
+                println!("{password}");
+                Ok(())
+            })
         }
-        Some(pref) => {
-            let pref_slash = format!("{pref}/");
-            for e in entries {
-                if e == pref || e.starts_with(&pref_slash) {
-                    println!("{e}");
+        (None, None) => {
+            let history = show::history(path)?;
+            output::emit(&serde_json::json!({ "history": history }), || {
+                if history.is_empty() {
+                    println!("No password history for {path}");
                 }
-            }
+                for item in &history {
+                    println!("{:>3}  replaced {}", item.n, item.replaced);
+                }
+                Ok(())
+            })
         }
     }
-
-    Ok(())
 }
 
-/// pm otp add PATH
-fn cmd_otp_add(path: &str) -> anyhow::Result<()> {
-    let config = Config::load()?;
-    let mk = get_master_key_with_cache(&config)?;
-
-    let mut entry = load_entry(path, &mk)?;
-    let raw = prompt_string("OTP secret (base32) OR otpauth:// URL: ")?;
-    let raw = raw.trim();
-
-    if raw.is_empty() {
-        anyhow::bail!("OTP secret cannot be empty");
+/// `-0` — формат для xargs и fzf, с JSON он не сочетается.
+fn check_print0(print0: bool) -> anyhow::Result<()> {
+    if print0 && output::is_json() {
+        anyhow::bail!("-0/--print0 cannot be combined with JSON output");
     }
-
-    let otp_cfg = parse_otp_input(raw)?;
-    entry.otp = Some(otp_cfg);
-    entry.updated_at =
-        OffsetDateTime::now_utc().format(&time::format_description::well_known::Rfc3339)?;
-
-    save_entry(path, &entry, &mk)?;
-    println!("OTP configured for {}", path);
-    Ok(())
-}
-
-/// pm otp show PATH
-fn cmd_otp_show(path: &str) -> anyhow::Result<()> {
-    let config = Config::load()?;
-    let mk = get_master_key_with_cache(&config)?;
-    let entry = load_entry(path, &mk)?;
-
-    let otp_cfg = match entry.otp {
-        Some(ref cfg) => cfg,
-        None => {
-            anyhow::bail!("No OTP configured for {}", path);
-        }
-    };
-
-    let code = generate_otp_code(otp_cfg)?;
-    println!("{code}");
     Ok(())
 }
 
-/// pm otp clip PATH
-fn cmd_otp_clip(path: &str) -> anyhow::Result<()> {
-    let config = Config::load()?;
-    let mk = get_master_key_with_cache(&config)?;
-    let entry = load_entry(path, &mk)?;
-
-    let otp_cfg = match entry.otp {
-        Some(ref cfg) => cfg,
-        None => {
-            anyhow::bail!("No OTP configured for {}", path);
+/// Вывести пути по одному: через `\n` или через NUL (`-0`), без отступов и украшений.
+/// В JSON-режиме — `{"entries": [...]}`.
+fn print_paths(paths: &[String], print0: bool) -> anyhow::Result<()> {
+    use std::io::Write;
+
+    output::emit(&serde_json::json!({ "entries": paths }), || {
+        let mut out = std::io::stdout().lock();
+        let sep: &[u8] = if print0 { b"\0" } else { b"\n" };
+        for p in paths {
+            out.write_all(p.as_bytes())?;
+            out.write_all(sep)?;
         }
-    };
-
-    let code = generate_otp_code(otp_cfg)?;
-    copy_to_clipboard(&code)?;
-    println!("OTP code copied to clipboard.");
-    Ok(())
+        out.flush()?;
+        Ok(())
+    })
 }
 
-/// Разобрать то, что пользователь ввёл в pm otp add:
-/// - если otpauth:// URL → парсим, достаём secret/digits/period/algorithm
-/// - если просто строка → считаем base32 секретом с дефолтами (totp, SHA1, 6, 30)
-fn parse_otp_input(input: &str) -> anyhow::Result<OtpConfig> {
-    if input.starts_with("otpauth://") {
-        let url = Url::parse(input).map_err(|e| anyhow!("Invalid otpauth URL: {e}"))?;
-
-        if url.scheme() != "otpauth" {
-            return Err(anyhow!("Invalid otpauth URL scheme: {}", url.scheme()));
-        }
-
-        let kind = url.host_str().unwrap_or("").to_lowercase();
-        if kind != "totp" {
-            return Err(anyhow!(
-                "Unsupported otpauth type '{}', only 'totp' is supported",
-                kind
-            ));
-        }
+/// pm url QUERY [--clip]
+fn print_url_matches(query: &str, loose: bool, clip: bool) -> anyhow::Result<()> {
+    let matches = listing::by_url(query, loose)?;
 
-        let mut secret: Option<String> = None;
-        let mut digits: Option<u8> = None;
-        let mut period: Option<u32> = None;
-        let mut algo: Option<String> = None;
-
-        for (k, v) in url.query_pairs() {
-            match k.as_ref() {
-                "secret" => secret = Some(v.to_string()),
-                "digits" => {
-                    if let Ok(d) = v.parse::<u8>() {
-                        digits = Some(d);
-                    }
-                }
-                "period" => {
-                    if let Ok(p) = v.parse::<u32>() {
-                        period = Some(p);
-                    }
+    if clip {
+        if matches.len() > 1 {
+            if !output::is_json() {
+                for (path, _) in &matches {
+                    println!("{path}");
                 }
-                "algorithm" => {
-                    algo = Some(v.to_string());
-                }
-                _ => {}
             }
+            anyhow::bail!(
+                "{} entries match {}; --clip needs exactly one",
+                matches.len(),
+                query
+            );
         }
-
-        let sec = secret.ok_or_else(|| anyhow!("otpauth URL missing 'secret' param"))?;
-
-        // validate base32
-        let _ = Secret::Encoded(sec.clone())
-            .to_bytes()
-            .map_err(|e| anyhow!("Invalid OTP secret (base32): {e:?}"))?;
-
-        let algo_str = algo.unwrap_or_else(|| "SHA1".to_string()).to_uppercase();
-        let digits_val = digits.unwrap_or(6);
-        let period_val = period.unwrap_or(30);
-
-        Ok(OtpConfig {
-            r#type: "totp".to_string(),
-            secret: sec,
-            period: period_val,
-            digits: digits_val,
-            algo: algo_str,
-        })
-    } else {
-        // Просто base32 секрет
-        let sec = input.to_string();
-
-        let _ = Secret::Encoded(sec.clone())
-            .to_bytes()
-            .map_err(|e| anyhow!("Invalid OTP secret (base32): {e:?}"))?;
-
-        Ok(OtpConfig {
-            r#type: "totp".to_string(),
-            secret: sec,
-            period: 30,
-            digits: 6,
-            algo: "SHA1".to_string(),
-        })
+        let (path, entry) = &matches[0];
+        clipboard::copy_secret(&entry.password, CopyOptions::default())?;
+        output::done(
+            "clip",
+            path,
+            format!("Password for {path} copied to clipboard."),
+        );
+        return Ok(());
     }
-}
 
-/// Генерирует текущий TOTP-код для данного OtpConfig
-fn generate_otp_code(cfg: &OtpConfig) -> anyhow::Result<String> {
-    if cfg.r#type.to_lowercase() != "totp" {
-        return Err(anyhow!(
-            "Unsupported OTP type '{}', only 'totp' is supported",
-            cfg.r#type
-        ));
-    }
+    let list: Vec<_> = matches
+        .iter()
+        .map(|(path, entry)| serde_json::json!({ "path": path, "url": entry.url }))
+        .collect();
+    output::emit(&serde_json::json!({ "ok": true, "matches": list }), || {
+        for (path, _) in &matches {
+            println!("{path}");
+        }
+        Ok(())
+    })
+}
 
-    if cfg.digits < 6 || cfg.digits > 8 {
-        return Err(anyhow!(
-            "Unsupported OTP digits '{}', expected 6–8",
-            cfg.digits
-        ));
+/// Что команда делает с хранилищем: от этого зависят блокировка и `--read-only`.
+fn store_access(command: &Commands) -> Access {
+    use Access::{None, Read, Write};
+    let write_unless = |dry_run: bool| if dry_run { Read } else { Write };
+    match command {
+        Commands::Init { .. }
+        | Commands::Add { .. }
+        | Commands::Edit { .. }
+        | Commands::Regen { .. }
+        | Commands::Rm { .. }
+        | Commands::Mv { .. }
+        | Commands::Cp { .. }
+        | Commands::Passwd
+        | Commands::Rekey
+        | Commands::Git { .. } => Write,
+        Commands::Show { .. }
+        | Commands::Clip { .. }
+        | Commands::Get { .. }
+        | Commands::Menu { .. }
+        | Commands::Ls { .. }
+        | Commands::Find { .. }
+        | Commands::Grep { .. }
+        | Commands::Url { .. }
+        | Commands::Audit { .. }
+        | Commands::Pwned { .. }
+        | Commands::Export { .. }
+        | Commands::VerifyPassword { .. }
+        | Commands::History { .. }
+        | Commands::Expiring { .. }
+        | Commands::Keychain { .. }
+        | Commands::CompletePaths { .. } => Read,
+        Commands::Generate { .. }
+        | Commands::Profile { .. }
+        | Commands::Hibp { .. }
+        | Commands::Agent { .. }
+        | Commands::Lock
+        | Commands::ClipboardClear { .. }
+        | Commands::Bench { .. }
+        | Commands::Completions { .. } => None,
+        #[cfg(feature = "serve")]
+        Commands::Serve { .. } => None,
+        Commands::Trash { cmd } => match cmd {
+            TrashCommands::List => Read,
+            TrashCommands::Restore { .. } | TrashCommands::Empty { .. } => Write,
+        },
+        Commands::Tag { cmd } => match cmd {
+            TagCommands::List => Read,
+            TagCommands::Add { .. } | TagCommands::Rm { .. } => Write,
+        },
+        Commands::Attach { cmd } => match cmd {
+            AttachCommands::List { .. } | AttachCommands::Get { .. } => Read,
+            AttachCommands::Add { .. } | AttachCommands::Rm { .. } => Write,
+        },
+        // show и clip сдвигают счётчик HOTP
+        Commands::Otp { cmd } => match cmd {
+            OtpCommands::Gen { .. } => None,
+            OtpCommands::Uri { .. } => Read,
+            _ => Write,
+        },
+        Commands::Bulk {
+            cmd: BulkCommands::Set { dry_run, .. },
+        } => write_unless(*dry_run),
+        Commands::Wifi { .. } => Read,
+        Commands::Import { cmd } => match cmd {
+            ImportCommands::Pass { dry_run, .. }
+            | ImportCommands::Csv { dry_run, .. }
+            | ImportCommands::Pmx { dry_run, .. }
+            | ImportCommands::Keepass { dry_run, .. } => write_unless(*dry_run),
+        },
+        Commands::Share { cmd, .. } => match cmd {
+            Some(ShareCommands::Import { dry_run, .. }) => write_unless(*dry_run),
+            Some(ShareCommands::Keygen { .. }) => None,
+            Option::None => Read,
+        },
+        Commands::Backup { cmd } => match cmd {
+            BackupCommands::Create { .. } => Read,
+            BackupCommands::Restore { .. } => Write,
+            BackupCommands::Verify { .. } => None,
+            BackupCommands::Auto | BackupCommands::List | BackupCommands::Prune { .. } => Read,
+        },
+        Commands::Index { .. } => Write,
+        Commands::GitCredential { operation } => match operation {
+            credential::Operation::Get => Read,
+            credential::Operation::Store | credential::Operation::Erase => Write,
+        },
+        Commands::Recent { clear, .. } => write_unless(!*clear),
+        Commands::Config { cmd } => match cmd {
+            ConfigCommands::List | ConfigCommands::Get { .. } => Read,
+            _ => Write,
+        },
+        Commands::Fsck { fix } => write_unless(!*fix),
+        Commands::Doctor { fix_permissions } => write_unless(!*fix_permissions),
+        Commands::Status => Read,
+        Commands::Migrate { dry_run } => write_unless(*dry_run),
     }
+}
 
-    let algo = match cfg.algo.to_uppercase().as_str() {
-        "SHA1" => Algorithm::SHA1,
-        "SHA256" => Algorithm::SHA256,
-        "SHA512" => Algorithm::SHA512,
-        other => {
-            return Err(anyhow!(
-                "Unsupported OTP algo '{}', expected SHA1/SHA256/SHA512",
-                other
-            ))
+/// Имя подкоманды, как в командной строке: `VerifyPassword` → `verify-password`.
+fn command_name(command: &Commands) -> String {
+    let variant = format!("{command:?}");
+    let mut name = String::new();
+    for c in variant.chars().take_while(char::is_ascii_alphanumeric) {
+        if c.is_ascii_uppercase() && !name.is_empty() {
+            name.push('-');
         }
-    };
-
-    let secret_bytes = Secret::Encoded(cfg.secret.clone())
-        .to_bytes()
-        .map_err(|e| anyhow!("Invalid OTP secret (base32): {e:?}"))?;
-
-    if secret_bytes.is_empty() {
-        return Err(anyhow!("OTP secret decoded to empty byte string"));
+        name.push(c.to_ascii_lowercase());
     }
+    name
+}
 
-    // ВАЖНО:
-    // Используем *unchecked* вариант, чтобы не падать на "коротких" (80-битных) секретах
-    // вроде тех, что выдает GitHub. Это нормальная практика для TOTP.
-    let totp = TOTP::new_unchecked(
-        algo,
-        cfg.digits as usize,
-        1,                  // skew
-        cfg.period as u64,  // period в секундах
-        secret_bytes,
-    );
-
-    let code = totp
-        .generate_current()
-        .map_err(|e| anyhow!("Failed to generate TOTP code: {e:?}"))?;
-
-    Ok(code)
+/// Путь из аргумента, а без него — интерактивный выбор записи.
+fn path_or_pick(path: Option<String>) -> anyhow::Result<String> {
+    match path {
+        Some(path) => Ok(path),
+        None => picker::pick_entry(),
+    }
 }
@@ -0,0 +1,159 @@
+//! Мастер-пароль и его обёртка: `pm init`, `pm passwd`,
+//! `pm config upgrade-kdf`, `pm config add-keyfile/remove-keyfile`.
+//!
+//! Мастер-ключ (MK) при этом не меняется — перешифровывается только его
+//! обёртка в `config.json`, поэтому записи остаются читаемыми.
+
+use crate::config::{Config, KdfParams, config_path, save_config};
+use crate::crypto::{KdfTarget, calibrate_kdf, rewrap_master_key, unlock_master_key};
+use crate::keyfile;
+use crate::password_store::PasswordStore;
+use crate::prompt::prompt_password_hidden;
+use crate::session::{self, master_password_from_env};
+use crate::store::store_root;
+use anyhow::{Result, bail};
+use std::path::{Path, PathBuf};
+
+/// Итог `pm init`.
+pub enum Init {
+    /// Хранилище уже есть, ничего не делали
+    Exists(PathBuf),
+    Created {
+        root: PathBuf,
+        kdf: KdfParams,
+        /// Ключевой файл, без которого хранилище теперь не открыть
+        keyfile: Option<PathBuf>,
+    },
+}
+
+/// Мастер-пароль из окружения, иначе вопрос `prompt`.
+fn master_password(prompt: &str) -> Result<String> {
+    match master_password_from_env() {
+        Some(pw) => Ok(pw),
+        None => prompt_password_hidden(prompt),
+    }
+}
+
+/// pm init: новое хранилище в `store_root()`.
+pub fn init(kdf: &KdfTarget) -> Result<Init> {
+    let root = store_root()?;
+    if root.join("config.json").exists() {
+        return Ok(Init::Exists(root));
+    }
+    // До вопроса о пароле: в чужой каталог или поверх записей init не пойдёт
+    PasswordStore::check_init_target(&root)?;
+
+    let master_password = match master_password_from_env() {
+        Some(pw) => pw,
+        None => {
+            let pw = prompt_password_hidden("New master password: ")?;
+            let confirm = prompt_password_hidden("Confirm master password: ")?;
+            if pw != confirm {
+                bail!("Passwords do not match");
+            }
+            pw
+        }
+    };
+
+    let keyfile = keyfile::keyfile_path();
+    let store = PasswordStore::init_with_kdf(root, &master_password, kdf, keyfile.as_deref())?;
+    Ok(Init::Created {
+        root: store.root().to_path_buf(),
+        kdf: store.config().kdf.clone(),
+        keyfile,
+    })
+}
+
+/// pm passwd
+pub fn passwd() -> Result<()> {
+    let config_path = config_path()?;
+    let mut config = Config::load()?;
+
+    let current = prompt_password_hidden("Current master password: ")?;
+    let keyfile = keyfile::current()?;
+    let mk = unlock_master_key(&current, &config, keyfile.as_ref())?;
+
+    let new_password = prompt_password_hidden("New master password: ")?;
+    if new_password.is_empty() {
+        bail!("Master password cannot be empty");
+    }
+    let confirm = prompt_password_hidden("Confirm new master password: ")?;
+    if new_password != confirm {
+        bail!("Passwords do not match");
+    }
+
+    rewrap_master_key(&mut config, &mk, &new_password, keyfile.as_ref())?;
+    save_config(&config, &config_path)?;
+    // MK тот же, но пусть следующая команда спросит уже новый пароль
+    session::clear_session()?;
+
+    if let Err(e) = crate::keychain::keychain_replace(&config, &new_password) {
+        eprintln!("Warning: could not update the OS keychain ({e}); run `pm keychain store` again");
+    }
+    Ok(())
+}
+
+/// Итог `pm config upgrade-kdf`.
+pub struct KdfUpgrade {
+    pub old: KdfParams,
+    pub new: KdfParams,
+    /// `false`: текущие параметры не слабее откалиброванных, ничего не меняли
+    pub upgraded: bool,
+}
+
+/// pm config upgrade-kdf: новые параметры Argon2id и свежая соль, тот же MK.
+/// Нужен именно мастер-пароль: кэш сессии хранит только MK, а KEK из него не получить.
+pub fn upgrade_kdf(target: &KdfTarget) -> Result<KdfUpgrade> {
+    let config_path = config_path()?;
+    let mut config = Config::load()?;
+
+    let password = master_password("Master password: ")?;
+    let keyfile = keyfile::current()?;
+    let mk = unlock_master_key(&password, &config, keyfile.as_ref())?;
+
+    let old = config.kdf.clone();
+    let new = calibrate_kdf(target)?;
+    let cost = |k: &KdfParams| u64::from(k.memory_mib) * u64::from(k.iterations);
+    if cost(&new) <= cost(&old) {
+        return Ok(KdfUpgrade {
+            old,
+            new,
+            upgraded: false,
+        });
+    }
+
+    config.kdf.memory_mib = new.memory_mib;
+    config.kdf.iterations = new.iterations;
+    config.kdf.parallelism = new.parallelism;
+    rewrap_master_key(&mut config, &mk, &password, keyfile.as_ref())?;
+    save_config(&config, &config_path)?;
+    Ok(KdfUpgrade {
+        old,
+        new,
+        upgraded: true,
+    })
+}
+
+/// pm config add-keyfile / remove-keyfile: перешифровать MK с ключевым
+/// файлом `new` или без него. Текущий файл (если требуется) — из `--keyfile`.
+pub fn set_keyfile(new: Option<&Path>) -> Result<()> {
+    let config_path = config_path()?;
+    let mut config = Config::load()?;
+    match (new.is_some(), config.kdf.keyfile) {
+        (true, true) => {
+            bail!("the store already requires a keyfile; run `pm config remove-keyfile` first")
+        }
+        (false, false) => bail!("the store does not require a keyfile"),
+        _ => {}
+    }
+    let new_hash = new.map(keyfile::load).transpose()?;
+
+    let password = master_password("Master password: ")?;
+    let mk = unlock_master_key(&password, &config, keyfile::current()?.as_ref())?;
+
+    config.kdf.keyfile = new_hash.is_some();
+    rewrap_master_key(&mut config, &mk, &password, new_hash.as_ref())?;
+    save_config(&config, &config_path)?;
+    // Кэш сессии обходит проверку ключевого файла — пусть следующая команда её пройдёт
+    session::clear_session()
+}
@@ -0,0 +1,165 @@
+//! `pm migrate`: привести все файлы хранилища к текущим форматам.
+//!
+//! Каждая запись перезаписывается атомарно, а путь успешно обновлённой
+//! записи дописывается в журнал `migrate.journal`. Если процесс прервётся,
+//! повторный запуск пропустит уже обработанные записи; журнал удаляется
+//! после полного успешного прохода.
+
+use crate::config::{CONFIG_VERSION, Config, config_path};
+use crate::entry::ENTRY_SCHEMA_VERSION;
+use crate::session::get_master_key_with_cache;
+use crate::store::{
+    ENTRY_FILE_VERSION, decrypt_all_raw, entry_file_info, list_entries, save_entry, store_root,
+    write_atomic_private,
+};
+use anyhow::{Context, Result, anyhow};
+use std::collections::{BTreeMap, HashSet};
+use std::io::Write;
+
+const JOURNAL_NAME: &str = "migrate.journal";
+
+fn print_histogram(title: &str, hist: &BTreeMap<u32, usize>, current: u32) {
+    println!("{title}:");
+    for (version, count) in hist {
+        let mark = if *version == current {
+            " (current)"
+        } else {
+            ""
+        };
+        println!("  v{version}: {count}{mark}");
+    }
+}
+
+/// Нужно ли переписать config.json: старая версия или нет секции settings.
+fn config_needs_migration() -> Result<(u32, bool)> {
+    let raw: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(config_path()?)?)?;
+    let version = raw.get("version").and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+    if version > CONFIG_VERSION {
+        return Err(anyhow!(
+            "config.json is format v{version}, newer than this pm supports (v{CONFIG_VERSION})"
+        ));
+    }
+    Ok((
+        version,
+        version < CONFIG_VERSION || raw.get("settings").is_none(),
+    ))
+}
+
+/// pm migrate [--dry-run]
+pub fn migrate(dry_run: bool) -> Result<()> {
+    let root = store_root()?;
+    let journal_path = root.join(JOURNAL_NAME);
+
+    let (config_version, config_stale) = config_needs_migration()?;
+    println!(
+        "config.json: v{config_version}{}",
+        if config_stale {
+            " (needs migration)"
+        } else {
+            " (current)"
+        }
+    );
+
+    let mut config = Config::load()?;
+    let mk = get_master_key_with_cache(&config)?;
+
+    let done: HashSet<String> = match std::fs::read_to_string(&journal_path) {
+        Ok(s) => s.lines().map(str::to_string).collect(),
+        Err(_) => HashSet::new(),
+    };
+    if !done.is_empty() {
+        println!(
+            "Resuming interrupted migration: {} entries already done.",
+            done.len()
+        );
+    }
+
+    let paths = list_entries()?;
+    let mut file_hist = BTreeMap::new();
+    let mut schema_hist = BTreeMap::new();
+    let mut failures = Vec::new();
+    let mut pending = Vec::new();
+
+    for (path, entry) in decrypt_all_raw(&mk, paths) {
+        let file_version = match entry_file_info(&path) {
+            Ok(info) => info.version,
+            Err(e) => {
+                failures.push((path, e));
+                continue;
+            }
+        };
+        *file_hist.entry(file_version).or_insert(0) += 1;
+
+        let entry = match entry {
+            Ok(e) => e,
+            Err(e) => {
+                failures.push((path, e));
+                continue;
+            }
+        };
+        *schema_hist.entry(entry.version).or_insert(0) += 1;
+
+        let stale = file_version < ENTRY_FILE_VERSION || entry.version < ENTRY_SCHEMA_VERSION;
+        if stale && !done.contains(&path) {
+            pending.push((path, file_version, entry));
+        }
+    }
+
+    print_histogram("Entry file formats", &file_hist, ENTRY_FILE_VERSION);
+    print_histogram("Entry schema versions", &schema_hist, ENTRY_SCHEMA_VERSION);
+    println!("{} entries need migration.", pending.len());
+
+    if dry_run {
+        if config_stale {
+            println!("would migrate config.json to v{CONFIG_VERSION}");
+        }
+        for (path, file_version, entry) in &pending {
+            println!(
+                "would migrate {path} (file v{file_version} -> v{ENTRY_FILE_VERSION}, schema v{} -> v{ENTRY_SCHEMA_VERSION})",
+                entry.version
+            );
+        }
+    } else {
+        if config_stale {
+            config.version = CONFIG_VERSION;
+            write_atomic_private(
+                &config_path()?,
+                serde_json::to_string_pretty(&config)?.as_bytes(),
+            )?;
+            println!("migrated config.json");
+        }
+
+        let mut journal = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&journal_path)
+            .with_context(|| format!("cannot open {}", journal_path.display()))?;
+
+        for (path, _, mut entry) in pending {
+            let res = entry
+                .upgrade_schema()
+                .and_then(|_| save_entry(&path, &entry, &mk));
+            match res {
+                Ok(()) => {
+                    writeln!(journal, "{path}")?;
+                    journal.sync_data()?;
+                    println!("migrated {path}");
+                }
+                Err(e) => failures.push((path, e)),
+            }
+        }
+    }
+
+    if !failures.is_empty() {
+        for (path, e) in &failures {
+            eprintln!("Failed {path}: {e}");
+        }
+        return Err(anyhow!("{} entries could not be migrated", failures.len()));
+    }
+
+    if !dry_run {
+        let _ = std::fs::remove_file(&journal_path);
+        println!("Store is up to date.");
+    }
+    Ok(())
+}
@@ -0,0 +1,259 @@
+//! Одноразовые коды: разбор otpauth:// URL, обратная сборка URL и генерация
+//! TOTP, HOTP и Steam Guard кодов из сохранённого `OtpConfig`.
+
+use crate::entry::OtpConfig;
+use anyhow::anyhow;
+use time::OffsetDateTime;
+use totp_rs::{Algorithm, Secret, TOTP};
+use url::Url;
+
+/// Разобрать то, что пользователь ввёл в pm otp add:
+/// - если otpauth:// URL → парсим, достаём secret/digits/period/algorithm
+///   (для hotp — ещё counter)
+/// - если просто строка → считаем base32 секретом с дефолтами (totp, SHA1, 6, 30)
+pub fn parse_otp_input(input: &str) -> anyhow::Result<OtpConfig> {
+    if input.starts_with("otpauth://") {
+        let url = Url::parse(input).map_err(|e| anyhow!("Invalid otpauth URL: {e}"))?;
+
+        if url.scheme() != "otpauth" {
+            return Err(anyhow!("Invalid otpauth URL scheme: {}", url.scheme()));
+        }
+
+        let kind = url.host_str().unwrap_or("").to_lowercase();
+        if kind != "totp" && kind != "hotp" {
+            return Err(anyhow!(
+                "Unsupported otpauth type '{}', expected 'totp' or 'hotp'",
+                kind
+            ));
+        }
+
+        let mut secret: Option<String> = None;
+        let mut digits: Option<u8> = None;
+        let mut period: Option<u32> = None;
+        let mut algo: Option<String> = None;
+        let mut counter: u64 = 0;
+        let mut issuer: Option<String> = None;
+        let mut steam = false;
+
+        for (k, v) in url.query_pairs() {
+            match k.as_ref() {
+                "secret" => secret = Some(v.to_string()),
+                "digits" => {
+                    if let Ok(d) = v.parse::<u8>() {
+                        digits = Some(d);
+                    }
+                }
+                "period" => {
+                    if let Ok(p) = v.parse::<u32>() {
+                        period = Some(p);
+                    }
+                }
+                "algorithm" => {
+                    algo = Some(v.to_string());
+                }
+                "counter" => {
+                    counter = v
+                        .parse()
+                        .map_err(|_| anyhow!("Invalid HOTP counter '{v}'"))?;
+                }
+                "issuer" if !v.is_empty() => issuer = Some(v.to_string()),
+                "encoder" => steam = v.eq_ignore_ascii_case("steam"),
+                _ => {}
+            }
+        }
+
+        let sec = secret.ok_or_else(|| anyhow!("otpauth URL missing 'secret' param"))?;
+
+        // validate base32
+        let _ = Secret::Encoded(sec.clone())
+            .to_bytes()
+            .map_err(|e| anyhow!("Invalid OTP secret (base32): {e:?}"))?;
+
+        let algo_str = algo.unwrap_or_else(|| "SHA1".to_string()).to_uppercase();
+        let digits_val = digits.unwrap_or(6);
+        let period_val = period.unwrap_or(30);
+
+        let cfg = OtpConfig {
+            r#type: kind,
+            secret: sec,
+            period: period_val,
+            digits: digits_val,
+            algo: algo_str,
+            counter,
+            issuer: issuer.or_else(|| label_issuer(&url)),
+        };
+        // Steam Guard экспортирует обычный totp-URL с encoder=steam
+        Ok(if steam && !cfg.is_hotp() {
+            steam_otp(cfg)
+        } else {
+            cfg
+        })
+    } else {
+        // Просто base32 секрет
+        let sec = input.to_string();
+
+        let _ = Secret::Encoded(sec.clone())
+            .to_bytes()
+            .map_err(|e| anyhow!("Invalid OTP secret (base32): {e:?}"))?;
+
+        Ok(OtpConfig {
+            r#type: "totp".to_string(),
+            secret: sec,
+            period: 30,
+            digits: 6,
+            algo: "SHA1".to_string(),
+            counter: 0,
+            issuer: None,
+        })
+    }
+}
+
+/// Перевести настройки в Steam Guard: у Steam всегда SHA1, 30 секунд, 5 символов.
+pub fn steam_otp(cfg: OtpConfig) -> OtpConfig {
+    OtpConfig {
+        r#type: "steam".to_string(),
+        period: 30,
+        digits: 5,
+        algo: "SHA1".to_string(),
+        counter: 0,
+        ..cfg
+    }
+}
+
+/// Издатель из метки `otpauth://totp/Issuer:account`, если параметра issuer нет.
+fn label_issuer(url: &Url) -> Option<String> {
+    let label = url.path().trim_start_matches('/');
+    let label = url::form_urlencoded::parse(format!("l={label}").as_bytes())
+        .next()
+        .map(|(_, v)| v.into_owned())?;
+    let (issuer, _) = label.split_once(':')?;
+    let issuer = issuer.trim();
+    (!issuer.is_empty()).then(|| issuer.to_string())
+}
+
+/// otpauth:// URL из сохранённых настроек; меткой служит путь записи.
+pub fn otp_uri(path: &str, cfg: &OtpConfig) -> anyhow::Result<String> {
+    let kind = if cfg.is_hotp() { "hotp" } else { "totp" };
+    let mut url = Url::parse(&format!("otpauth://{kind}/"))?;
+    let label = match cfg.issuer {
+        Some(ref issuer) => format!("{issuer}:{path}"),
+        None => path.to_string(),
+    };
+    url.path_segments_mut()
+        .map_err(|_| anyhow!("cannot build otpauth URL"))?
+        .pop_if_empty()
+        .push(&label);
+    {
+        let mut query = url.query_pairs_mut();
+        query.append_pair("secret", &cfg.secret);
+        query.append_pair("digits", &cfg.digits.to_string());
+        if cfg.is_hotp() {
+            query.append_pair("counter", &cfg.counter.to_string());
+        } else {
+            query.append_pair("period", &cfg.period.to_string());
+        }
+        query.append_pair("algorithm", &cfg.algo);
+        if let Some(ref issuer) = cfg.issuer {
+            query.append_pair("issuer", issuer);
+        }
+        if cfg.is_steam() {
+            query.append_pair("encoder", "steam");
+        }
+    }
+    Ok(url.to_string())
+}
+
+/// Алфавит Steam Guard: цифры и согласные, которые не спутать друг с другом.
+const STEAM_ALPHABET: &[u8] = b"23456789BCDFGHJKMNPQRTVWXY";
+
+/// Код Steam Guard из HMAC: то же динамическое усечение, что в RFC 4226,
+/// но число раскладывается не в десятичные цифры, а в 5 символов алфавита.
+fn steam_code(hmac: &[u8]) -> String {
+    let offset = (hmac[hmac.len() - 1] & 0x0f) as usize;
+    let mut n = u32::from_be_bytes([
+        hmac[offset],
+        hmac[offset + 1],
+        hmac[offset + 2],
+        hmac[offset + 3],
+    ]) & 0x7fff_ffff;
+    let base = STEAM_ALPHABET.len() as u32;
+    (0..5)
+        .map(|_| {
+            let c = STEAM_ALPHABET[(n % base) as usize] as char;
+            n /= base;
+            c
+        })
+        .collect()
+}
+
+/// Генерирует текущий код для данного OtpConfig: TOTP — по времени,
+/// HOTP — по сохранённому счётчику (сдвигает его вызывающий, см. `entry_otp::take_code`)
+pub fn generate_otp_code(cfg: &OtpConfig) -> anyhow::Result<String> {
+    generate_otp_code_at(cfg, OffsetDateTime::now_utc().unix_timestamp() as u64)
+}
+
+/// Код на момент `time` (Unix-секунды); для HOTP время не используется.
+pub fn generate_otp_code_at(cfg: &OtpConfig, time: u64) -> anyhow::Result<String> {
+    let hotp = cfg.is_hotp();
+    let steam = cfg.is_steam();
+    if !hotp && !steam && cfg.r#type.to_lowercase() != "totp" {
+        return Err(anyhow!(
+            "Unsupported OTP type '{}', expected 'totp', 'hotp' or 'steam'",
+            cfg.r#type
+        ));
+    }
+
+    if !steam && (cfg.digits < 6 || cfg.digits > 8) {
+        return Err(anyhow!(
+            "Unsupported OTP digits '{}', expected 6–8",
+            cfg.digits
+        ));
+    }
+
+    if !hotp && cfg.period == 0 {
+        return Err(anyhow!(
+            "Unsupported OTP period '0', expected at least 1 second"
+        ));
+    }
+
+    let algo = match cfg.algo.to_uppercase().as_str() {
+        "SHA1" => Algorithm::SHA1,
+        "SHA256" => Algorithm::SHA256,
+        "SHA512" => Algorithm::SHA512,
+        other => {
+            return Err(anyhow!(
+                "Unsupported OTP algo '{}', expected SHA1/SHA256/SHA512",
+                other
+            ))
+        }
+    };
+
+    let secret_bytes = Secret::Encoded(cfg.secret.clone())
+        .to_bytes()
+        .map_err(|e| anyhow!("Invalid OTP secret (base32): {e:?}"))?;
+
+    if secret_bytes.is_empty() {
+        return Err(anyhow!("OTP secret decoded to empty byte string"));
+    }
+
+    // ВАЖНО:
+    // Используем *unchecked* вариант, чтобы не падать на "коротких" (80-битных) секретах
+    // вроде тех, что выдает GitHub. Это нормальная практика для TOTP.
+    if hotp {
+        // HOTP (RFC 4226) — это TOTP с шагом в 1 секунду, где вместо времени счётчик
+        let hotp = TOTP::new_unchecked(algo, cfg.digits as usize, 0, 1, secret_bytes);
+        return Ok(hotp.generate(cfg.counter));
+    }
+    let totp = TOTP::new_unchecked(
+        algo,
+        cfg.digits as usize,
+        1,                  // skew
+        cfg.period as u64,  // period в секундах
+        secret_bytes,
+    );
+
+    if steam {
+        return Ok(steam_code(&totp.sign(time)));
+    }
+    Ok(totp.generate(time))
+}
@@ -0,0 +1,73 @@
+//! Машиночитаемый вывод: глобальный `--output json` (`-o json`).
+//!
+//! В JSON-режиме команда печатает в stdout ровно один JSON-документ, а
+//! ошибка становится `{"error": "..."}` в stderr с ненулевым кодом возврата.
+//! Команда собирает результат в сериализуемую структуру и передаёт её в
+//! `emit` вместе с текстовым выводом для человека — выбирает здесь.
+
+use serde::Serialize;
+use serde_json::json;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Human,
+    Json,
+}
+
+static JSON: AtomicBool = AtomicBool::new(false);
+
+pub fn set_format(format: OutputFormat) {
+    JSON.store(format == OutputFormat::Json, Ordering::Relaxed);
+}
+
+pub fn is_json() -> bool {
+    JSON.load(Ordering::Relaxed)
+}
+
+/// `--quiet`: подтверждения вроде «Saved entry ...» не печатаются.
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+pub fn set_quiet(v: bool) {
+    QUIET.store(v, Ordering::Relaxed);
+}
+
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Подтверждение для человека: молчит с `--quiet` и в JSON-режиме.
+pub fn note(message: impl std::fmt::Display) {
+    if !is_json() && !QUIET.load(Ordering::Relaxed) {
+        println!("{message}");
+    }
+}
+
+/// Вывести результат: `value` как JSON или `human()` как текст.
+pub fn emit<T: Serialize>(
+    value: &T,
+    human: impl FnOnce() -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    if is_json() {
+        println!("{}", serde_json::to_string_pretty(value)?);
+        Ok(())
+    } else {
+        human()
+    }
+}
+
+/// Итог команды, которая что-то изменила: `{"ok": true, "action", "path"}`
+/// в JSON-режиме, иначе `message`.
+pub fn done(action: &str, path: &str, message: impl std::fmt::Display) {
+    let value = json!({ "ok": true, "action": action, "path": path });
+    let _ = emit(&value, || {
+        note(message);
+        Ok(())
+    });
+}
+
+/// Ошибка в JSON-режиме: `{"error": "..."}` в stderr, с причинами через `: `.
+pub fn print_error(err: &anyhow::Error) {
+    eprintln!("{}", json!({ "error": format!("{err:#}") }));
+}
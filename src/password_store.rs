@@ -0,0 +1,228 @@
+//! `PasswordStore` — хранилище с явным корнем, без промптов и печати.
+//!
+//! Через него же читают и пишут записи команды CLI: корень берут из
+//! `store_root()`, ключ — из сессии (`with_master_key`). Остальным — тестам,
+//! скриптам на Rust, другим интерфейсам — корень и мастер-пароль известны
+//! заранее. Хуки, git-автокоммит и журнал обращений — забота CLI, здесь их нет.
+
+use crate::attachments::{read_all_in, replace_all_in};
+use crate::config::{Config, save_config};
+use crate::crypto::{KdfTarget, MasterKey, generate_new_config, unlock_master_key};
+use crate::entry::Entry;
+use crate::otp::generate_otp_code;
+use crate::session::get_master_key_with_cache;
+use crate::store::{
+    create_private_dir_all, delete_entry_in, entry_file_path_in, list_entries_in, load_entry_in,
+    save_entry_in, store_root, validate_entry_path,
+};
+use anyhow::{Result, anyhow};
+use std::path::{Path, PathBuf};
+
+/// Что может остаться в корне от прерванного `pm init` или от команд,
+/// запущенных до него; `init` поверх этого не мешает.
+const LEFTOVERS: &[&str] = &["store", ".lock", "session.json", "access.log", "index.enc"];
+
+pub struct PasswordStore {
+    root: PathBuf,
+    config: Config,
+    master_key: Option<MasterKey>,
+}
+
+impl PasswordStore {
+    /// Создать новое хранилище в `root` (каталога нет или он пуст)
+    /// и сразу открыть его разблокированным. KDF калибруется на ~500 мс.
+    pub fn init(root: PathBuf, master_password: &str) -> Result<Self> {
+        Self::init_with_kdf(root, master_password, &KdfTarget::default(), None)
+    }
+
+    /// То же с явной целью калибровки KDF и, если задан, ключевым файлом,
+    /// без которого хранилище потом не открыть.
+    pub fn init_with_kdf(
+        root: PathBuf,
+        master_password: &str,
+        kdf: &KdfTarget,
+        keyfile: Option<&Path>,
+    ) -> Result<Self> {
+        Self::check_init_target(&root)?;
+        if master_password.is_empty() {
+            return Err(anyhow!("Master password cannot be empty"));
+        }
+        let keyfile_hash = keyfile.map(crate::keyfile::load).transpose()?;
+        create_private_dir_all(&root)?;
+        crate::perms::harden_store_root(&root)?;
+        let config = generate_new_config(master_password, kdf, keyfile_hash.as_ref())?;
+        save_config(&config, &root.join("config.json"))?;
+
+        let mut store = Self::open(root)?;
+        match keyfile {
+            Some(path) => store.unlock_with_keyfile(master_password, path)?,
+            None => store.unlock(master_password)?,
+        }
+        Ok(store)
+    }
+
+    /// Можно ли создать хранилище в `root`: каталога нет, он пуст или там
+    /// остатки прерванного `init` — служебные файлы pm и `store/` без записей.
+    /// Записи без `config.json` не открыть: в нём зашифрованный мастер-ключ.
+    pub fn check_init_target(root: &Path) -> Result<()> {
+        if root.join("config.json").exists() {
+            return Err(anyhow!("Store already exists at: {}", root.display()));
+        }
+        if !root.exists() {
+            return Ok(());
+        }
+        let entries = walkdir::WalkDir::new(root.join("store"))
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .count();
+        if entries > 0 {
+            return Err(anyhow!(
+                "{} has {entries} entry files but no config.json, and they cannot be decrypted without it.\n\
+                 Put config.json back from a copy, or restore the store from a backup with \
+                 `pm backup restore --force <archive>` (these files are kept as a .bak copy).",
+                root.display()
+            ));
+        }
+        for item in std::fs::read_dir(root)? {
+            let name = item?.file_name();
+            if !LEFTOVERS.iter().any(|n| name == *n) {
+                return Err(anyhow!(
+                    "{} is not empty; choose an empty or new directory for the store",
+                    root.display()
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Открыть существующее хранилище; записи читаются после `unlock`.
+    pub fn open(root: PathBuf) -> Result<Self> {
+        let config_path = root.join("config.json");
+        if !config_path.is_file() {
+            return Err(anyhow!("Store not found at {}", root.display()));
+        }
+        let config = Config::load_from(&config_path)?;
+        Ok(PasswordStore {
+            root,
+            config,
+            master_key: None,
+        })
+    }
+
+    /// Текущее хранилище (`store_root()`), разблокированное ключом агента
+    /// или сессии, иначе по мастер-паролю — так его открывают команды CLI.
+    pub fn open_current() -> Result<Self> {
+        let store = Self::open(store_root()?)?;
+        let mk = get_master_key_with_cache(store.config())?;
+        Ok(store.with_master_key(mk))
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Расшифровать мастер-ключ мастер-паролем.
+    pub fn unlock(&mut self, master_password: &str) -> Result<()> {
+        self.master_key = Some(unlock_master_key(master_password, &self.config, None)?);
+        Ok(())
+    }
+
+    /// Расшифровать мастер-ключ мастер-паролем и ключевым файлом.
+    pub fn unlock_with_keyfile(&mut self, master_password: &str, keyfile: &Path) -> Result<()> {
+        let keyfile = crate::keyfile::load(keyfile)?;
+        self.master_key = Some(unlock_master_key(
+            master_password,
+            &self.config,
+            Some(&keyfile),
+        )?);
+        Ok(())
+    }
+
+    /// Использовать уже полученный мастер-ключ (например, из сессии CLI).
+    pub fn with_master_key(mut self, master_key: MasterKey) -> Self {
+        self.master_key = Some(master_key);
+        self
+    }
+
+    /// Мастер-ключ разблокированного хранилища.
+    pub fn master_key(&self) -> Result<&MasterKey> {
+        self.master_key
+            .as_ref()
+            .ok_or_else(|| anyhow!("the store is locked; unlock it first"))
+    }
+
+    fn store_dir(&self) -> PathBuf {
+        self.root.join("store")
+    }
+
+    /// Пути всех записей, по алфавиту.
+    pub fn list(&self) -> Result<Vec<String>> {
+        list_entries_in(&self.store_dir())
+    }
+
+    pub fn exists(&self, path: &str) -> Result<bool> {
+        Ok(entry_file_path_in(&self.store_dir(), path)?.is_file())
+    }
+
+    /// Сохранить новую запись; занятый путь — ошибка.
+    pub fn add(&self, path: &str, entry: &Entry) -> Result<()> {
+        if self.exists(path)? {
+            return Err(anyhow!(
+                "Entry {} already exists",
+                validate_entry_path(path)?
+            ));
+        }
+        self.save(path, entry)
+    }
+
+    /// Сохранить запись, заменив прежнюю с тем же путём.
+    pub fn save(&self, path: &str, entry: &Entry) -> Result<()> {
+        save_entry_in(&self.store_dir(), path, entry, self.master_key()?)
+    }
+
+    /// Расшифрованная запись.
+    pub fn show(&self, path: &str) -> Result<Entry> {
+        load_entry_in(&self.store_dir(), path, self.master_key()?)
+    }
+
+    /// Содержимое вложений записи в порядке `entry.attachments`.
+    pub fn attachments(&self, path: &str, entry: &Entry) -> Result<Vec<(String, Vec<u8>)>> {
+        read_all_in(&self.store_dir(), path, entry, self.master_key()?)
+    }
+
+    /// Сохранить запись вместе с вложениями `files`; прежние вложения по
+    /// этому пути удаляются, `entry.attachments` заполняется заново.
+    pub fn save_with_attachments(
+        &self,
+        path: &str,
+        entry: &mut Entry,
+        files: &[(String, Vec<u8>)],
+    ) -> Result<()> {
+        entry.attachments = replace_all_in(&self.store_dir(), path, files, self.master_key()?)?;
+        self.save(path, entry)
+    }
+
+    pub fn remove(&self, path: &str) -> Result<()> {
+        delete_entry_in(&self.store_dir(), path)
+    }
+
+    /// Текущий одноразовый код записи. Для HOTP счётчик сдвигается
+    /// и запись сохраняется, как при `pm otp show`.
+    pub fn otp_code(&self, path: &str) -> Result<String> {
+        let mut entry = self.show(path)?;
+        let Some(otp) = entry.otp.as_mut() else {
+            return Err(anyhow!("No OTP configured for {path}"));
+        };
+        let code = generate_otp_code(otp)?;
+        if otp.is_hotp() {
+            otp.counter += 1;
+            self.save(path, &entry)?;
+        }
+        Ok(code)
+    }
+}
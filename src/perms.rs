@@ -0,0 +1,152 @@
+//! Права доступа к файлам хранилища.
+//!
+//! Весь платформенный код здесь: на Unix — биты режима (0700 для каталогов,
+//! 0600 для файлов), на Windows — ACL через `icacls`: наследование
+//! отключается, доступ остаётся только у текущего пользователя и SYSTEM.
+//! Остальной код вызывает `restrict` / `check` и о платформе не знает.
+
+use anyhow::Result;
+use std::path::Path;
+
+/// Ограничить доступ к файлу или каталогу только владельцем.
+pub fn restrict(path: &Path) -> Result<()> {
+    imp::restrict(path, path.is_dir())
+}
+
+/// Описание проблемы, если доступ к `path` шире, чем нужно.
+pub fn check(path: &Path) -> Result<Option<String>> {
+    imp::check(path, path.is_dir())
+}
+
+/// Могут ли в каталог писать все (как в /tmp): чужой процесс может
+/// подменить или подсмотреть создаваемый там файл.
+pub fn world_writable_dir(dir: &Path) -> Result<bool> {
+    imp::world_writable(dir)
+}
+
+#[cfg(unix)]
+mod imp {
+    use anyhow::Result;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::Path;
+
+    fn wanted(is_dir: bool) -> u32 {
+        if is_dir { 0o700 } else { 0o600 }
+    }
+
+    pub fn restrict(path: &Path, is_dir: bool) -> Result<()> {
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(wanted(is_dir)))?;
+        Ok(())
+    }
+
+    pub fn check(path: &Path, is_dir: bool) -> Result<Option<String>> {
+        let mode = std::fs::metadata(path)?.permissions().mode() & 0o777;
+        Ok((mode & 0o077 != 0).then(|| format!("mode {mode:o}, expected {:o}", wanted(is_dir))))
+    }
+
+    pub fn world_writable(dir: &Path) -> Result<bool> {
+        Ok(std::fs::metadata(dir)?.permissions().mode() & 0o002 != 0)
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use anyhow::{Result, anyhow};
+    use std::path::Path;
+    use std::process::{Command, Stdio};
+
+    /// Well-known SID: LocalSystem
+    const SYSTEM_SID: &str = "*S-1-5-18";
+    /// Everyone, BUILTIN\Users, Authenticated Users
+    const BROAD_SIDS: &[&str] = &["*S-1-1-0", "*S-1-5-32-545", "*S-1-5-11"];
+    /// Так эти группы выглядят в выводе `icacls` (английская локаль)
+    const BROAD_NAMES: &[&str] = &["Everyone", "BUILTIN\\Users", "Authenticated Users"];
+
+    fn current_user() -> Result<String> {
+        let user = std::env::var("USERNAME").map_err(|_| anyhow!("USERNAME is not set"))?;
+        Ok(match std::env::var("USERDOMAIN") {
+            Ok(domain) if !domain.is_empty() => format!("{domain}\\{user}"),
+            _ => user,
+        })
+    }
+
+    fn icacls(path: &Path, args: &[String]) -> Result<String> {
+        let out = Command::new("icacls")
+            .arg(path)
+            .args(args)
+            .stdin(Stdio::null())
+            .output()
+            .map_err(|e| anyhow!("failed to run icacls: {e}"))?;
+        if !out.status.success() {
+            return Err(anyhow!(
+                "icacls failed for {}: {}",
+                path.display(),
+                String::from_utf8_lossy(&out.stderr).trim()
+            ));
+        }
+        Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+    }
+
+    pub fn restrict(path: &Path, is_dir: bool) -> Result<()> {
+        // Для каталогов права наследуются вложенными файлами и папками
+        let full = if is_dir { "(OI)(CI)F" } else { "F" };
+        let mut args = vec![
+            "/inheritance:r".to_string(),
+            "/grant:r".to_string(),
+            format!("{}:{full}", current_user()?),
+            format!("{SYSTEM_SID}:{full}"),
+            "/remove:g".to_string(),
+        ];
+        args.extend(BROAD_SIDS.iter().map(|s| s.to_string()));
+        icacls(path, &args)?;
+        Ok(())
+    }
+
+    pub fn check(path: &Path, _is_dir: bool) -> Result<Option<String>> {
+        let acl = icacls(path, &[])?;
+        if let Some(name) = BROAD_NAMES.iter().find(|n| acl.contains(*n)) {
+            return Ok(Some(format!("ACL grants access to {name}")));
+        }
+        if acl.contains("(I)") {
+            return Ok(Some("ACL has inherited entries".to_string()));
+        }
+        Ok(None)
+    }
+
+    pub fn world_writable(dir: &Path) -> Result<bool> {
+        let acl = icacls(dir, &[])?;
+        Ok(acl.lines().any(|l| {
+            BROAD_NAMES.iter().any(|n| l.contains(n))
+                && (l.contains("(F)") || l.contains("(M)") || l.contains("(W"))
+        }))
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+mod imp {
+    use anyhow::Result;
+    use std::path::Path;
+
+    pub fn restrict(_path: &Path, _is_dir: bool) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn check(_path: &Path, _is_dir: bool) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    pub fn world_writable(_dir: &Path) -> Result<bool> {
+        Ok(false)
+    }
+}
+
+/// Закрыть корень нового хранилища. На Windows ACL корня наследуется всем,
+/// что будет создано внутри; на Unix режимы задаются при создании файлов
+/// (`store::create_private_dir_all`, `store::write_atomic_private`).
+pub fn harden_store_root(root: &Path) -> Result<()> {
+    if cfg!(windows) {
+        restrict(root)
+    } else {
+        Ok(())
+    }
+}
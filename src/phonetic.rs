@@ -0,0 +1,126 @@
+/// Названия символов для диктовки пароля по телефону (фонетический алфавит NATO).
+const LETTERS: [&str; 26] = [
+    "alfa", "bravo", "charlie", "delta", "echo", "foxtrot", "golf", "hotel", "india", "juliett",
+    "kilo", "lima", "mike", "november", "oscar", "papa", "quebec", "romeo", "sierra", "tango",
+    "uniform", "victor", "whiskey", "x-ray", "yankee", "zulu",
+];
+
+const DIGITS: [&str; 10] = [
+    "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
+];
+
+/// Все символы, которые может выдать генератор паролей, плюс частые прочие.
+const SYMBOLS: &[(char, &str)] = &[
+    ('!', "exclamation-mark"),
+    ('@', "at-sign"),
+    ('#', "hash"),
+    ('$', "dollar"),
+    ('%', "percent"),
+    ('^', "caret"),
+    ('&', "ampersand"),
+    ('*', "asterisk"),
+    ('(', "left-paren"),
+    (')', "right-paren"),
+    ('-', "hyphen"),
+    ('_', "underscore"),
+    ('=', "equals"),
+    ('+', "plus"),
+    ('[', "left-bracket"),
+    (']', "right-bracket"),
+    ('{', "left-brace"),
+    ('}', "right-brace"),
+    (';', "semicolon"),
+    (':', "colon"),
+    (',', "comma"),
+    ('.', "period"),
+    ('<', "less-than"),
+    ('>', "greater-than"),
+    ('?', "question-mark"),
+    ('/', "slash"),
+    ('\\', "backslash"),
+    ('|', "pipe"),
+    ('~', "tilde"),
+    ('`', "backtick"),
+    ('\'', "apostrophe"),
+    ('"', "double-quote"),
+    (' ', "space"),
+];
+
+/// Название символа: `A` → "alfa", `7` → "seven", `@` → "at-sign".
+pub fn char_name(c: char) -> String {
+    if c.is_ascii_alphabetic() {
+        return LETTERS[(c.to_ascii_lowercase() as u8 - b'a') as usize].to_string();
+    }
+    if let Some(d) = c.to_digit(10) {
+        return DIGITS[d as usize].to_string();
+    }
+    SYMBOLS
+        .iter()
+        .find(|(s, _)| *s == c)
+        .map(|(_, name)| name.to_string())
+        .unwrap_or_else(|| format!("U+{:04X}", c as u32))
+}
+
+/// По строке на символ: `A  alfa (UPPER)`, `b  bravo (lower)`, `7  seven`.
+pub fn phonetic_lines(s: &str) -> Vec<String> {
+    s.chars()
+        .map(|c| {
+            let marker = if c.is_ascii_uppercase() {
+                " (UPPER)"
+            } else if c.is_ascii_lowercase() {
+                " (lower)"
+            } else {
+                ""
+            };
+            format!("{c}  {}{marker}", char_name(c))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn letters_and_digits() {
+        assert_eq!(char_name('a'), "alfa");
+        assert_eq!(char_name('J'), "juliett");
+        assert_eq!(char_name('x'), "x-ray");
+        assert_eq!(char_name('Z'), "zulu");
+        assert_eq!(char_name('0'), "zero");
+        assert_eq!(char_name('9'), "nine");
+        for c in ('a'..='z').chain('A'..='Z').chain('0'..='9') {
+            assert!(!char_name(c).starts_with("U+"), "{c}");
+        }
+    }
+
+    #[test]
+    fn every_generator_symbol_has_a_name() {
+        for c in crate::crypto::SYMBOLS.chars() {
+            assert!(!char_name(c).starts_with("U+"), "{c} has no name");
+        }
+        // Имена не повторяются, иначе на слух символы не различить
+        let mut names: Vec<_> = SYMBOLS.iter().map(|(_, name)| *name).collect();
+        names.extend(LETTERS);
+        names.extend(DIGITS);
+        let total = names.len();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), total);
+    }
+
+    #[test]
+    fn lines_mark_case_and_fall_back_to_code_points() {
+        assert_eq!(
+            phonetic_lines("Ab7@ é"),
+            [
+                "A  alfa (UPPER)",
+                "b  bravo (lower)",
+                "7  seven",
+                "@  at-sign",
+                "   space",
+                "é  U+00E9",
+            ]
+        );
+    }
+}
@@ -0,0 +1,250 @@
+//! Интерактивный выбор записи, когда путь не указан: `pm show`, `pm clip` и т.п.
+//!
+//! Маленький цикл поверх termios: строка запроса, под ней до `MAX_ROWS`
+//! лучших совпадений (ранжирование — как у `pm find`). Стрелки или
+//! Ctrl-N/Ctrl-P — выбор, Enter — подтвердить, Esc или Ctrl-C — отмена.
+//! Рисуем в stderr, чтобы stdout команды остался чистым.
+
+use crate::fuzzy::rank_paths;
+use crate::store::list_entries;
+use anyhow::{Result, anyhow};
+use std::io::IsTerminal;
+
+/// Сколько совпадений показывать под строкой запроса.
+const MAX_ROWS: usize = 10;
+
+/// Выбрать запись интерактивно. Без терминала — ошибка: путь обязателен.
+pub fn pick_entry() -> Result<String> {
+    if !std::io::stdin().is_terminal() || !std::io::stdout().is_terminal() {
+        return Err(anyhow!(
+            "no entry path given; pass one explicitly when not running in a terminal"
+        ));
+    }
+    let entries = list_entries()?;
+    if entries.is_empty() {
+        return Err(anyhow!("the store has no entries"));
+    }
+    imp::run(&entries)?.ok_or_else(|| anyhow!("No entry selected"))
+}
+
+/// Нажатие, разобранное из байтов терминала.
+enum Key {
+    Char(char),
+    Backspace,
+    ClearLine,
+    Up,
+    Down,
+    Enter,
+    Abort,
+    Other,
+}
+
+/// Состояние выбора: запрос, совпадения и выделенная строка.
+struct Picker<'a> {
+    entries: &'a [String],
+    query: String,
+    matches: Vec<String>,
+    selected: usize,
+}
+
+impl<'a> Picker<'a> {
+    fn new(entries: &'a [String]) -> Self {
+        let mut p = Picker {
+            entries,
+            query: String::new(),
+            matches: Vec::new(),
+            selected: 0,
+        };
+        p.refilter();
+        p
+    }
+
+    fn refilter(&mut self) {
+        self.matches = rank_paths(&self.query, self.entries.to_vec());
+        self.selected = 0;
+    }
+
+    /// Обработать клавишу; `Some(результат)` — выбор завершён.
+    fn handle(&mut self, key: Key) -> Option<Option<String>> {
+        match key {
+            Key::Char(c) => {
+                self.query.push(c);
+                self.refilter();
+            }
+            Key::Backspace => {
+                self.query.pop();
+                self.refilter();
+            }
+            Key::ClearLine => {
+                self.query.clear();
+                self.refilter();
+            }
+            Key::Up => self.selected = self.selected.saturating_sub(1),
+            Key::Down => {
+                let last = self.matches.len().min(MAX_ROWS).saturating_sub(1);
+                self.selected = (self.selected + 1).min(last);
+            }
+            Key::Enter => {
+                if let Some(m) = self.matches.get(self.selected) {
+                    return Some(Some(m.clone()));
+                }
+            }
+            Key::Abort => return Some(None),
+            Key::Other => {}
+        }
+        None
+    }
+
+    /// Кадр: очистить область, строка запроса, список, курсор обратно в запрос.
+    fn frame(&self) -> String {
+        let mut out = String::from("\r\x1b[J");
+        out.push_str(&format!(
+            "pick> {}  ({}/{})",
+            self.query,
+            self.matches.len(),
+            self.entries.len()
+        ));
+        let rows: Vec<&String> = self.matches.iter().take(MAX_ROWS).collect();
+        for (i, m) in rows.iter().enumerate() {
+            if i == self.selected {
+                out.push_str(&format!("\r\n\x1b[7m> {m}\x1b[0m"));
+            } else {
+                out.push_str(&format!("\r\n  {m}"));
+            }
+        }
+        if !rows.is_empty() {
+            out.push_str(&format!("\x1b[{}A", rows.len()));
+        }
+        let col = "pick> ".len() + self.query.chars().count();
+        out.push_str(&format!("\r\x1b[{col}C"));
+        out
+    }
+}
+
+#[cfg(unix)]
+mod imp {
+    use super::{Key, Picker};
+    use anyhow::{Result, anyhow};
+    use std::io::Write;
+
+    /// Сырой режим stdin: без эха, построчного ввода и сигналов от Ctrl-C.
+    struct RawMode(libc::termios);
+
+    impl RawMode {
+        fn enable() -> Result<Self> {
+            // SAFETY: termios — POD; читаем и меняем настройки своего терминала
+            unsafe {
+                let mut t: libc::termios = std::mem::zeroed();
+                if libc::tcgetattr(libc::STDIN_FILENO, &mut t) != 0 {
+                    return Err(std::io::Error::last_os_error().into());
+                }
+                let saved = t;
+                t.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG);
+                t.c_cc[libc::VMIN] = 1;
+                t.c_cc[libc::VTIME] = 0;
+                libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &t);
+                Ok(RawMode(saved))
+            }
+        }
+    }
+
+    impl Drop for RawMode {
+        fn drop(&mut self) {
+            // SAFETY: возвращаем сохранённые настройки
+            unsafe {
+                libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.0);
+            }
+        }
+    }
+
+    /// Есть ли ещё байты в течение `ms` — чтобы отличить Esc от стрелки.
+    fn pending(ms: i32) -> bool {
+        let mut fds = libc::pollfd {
+            fd: libc::STDIN_FILENO,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // SAFETY: один pollfd на стеке
+        unsafe { libc::poll(&mut fds, 1, ms) > 0 }
+    }
+
+    /// Байт прямо из дескриптора: буфер std::io::stdin спрятал бы от poll
+    /// остаток escape-последовательности.
+    fn read_byte() -> Result<u8> {
+        let mut b = 0u8;
+        // SAFETY: читаем один байт в переменную на стеке
+        let n = unsafe { libc::read(libc::STDIN_FILENO, (&mut b as *mut u8).cast(), 1) };
+        match n {
+            1 => Ok(b),
+            0 => Err(anyhow!("unexpected end of input")),
+            _ => Err(std::io::Error::last_os_error().into()),
+        }
+    }
+
+    fn read_key() -> Result<Key> {
+        let b = read_byte()?;
+        Ok(match b {
+            b'\r' | b'\n' => Key::Enter,
+            0x03 | 0x07 => Key::Abort, // Ctrl-C, Ctrl-G
+            0x7f | 0x08 => Key::Backspace,
+            0x15 => Key::ClearLine,             // Ctrl-U
+            0x0e => Key::Down,                  // Ctrl-N
+            0x10 => Key::Up,                    // Ctrl-P
+            0x1b if !pending(50) => Key::Abort, // одиночный Esc
+            0x1b => match (read_byte()?, read_byte()?) {
+                (b'[' | b'O', b'A') => Key::Up,
+                (b'[' | b'O', b'B') => Key::Down,
+                _ => Key::Other,
+            },
+            b if b < 0x20 => Key::Other,
+            b => {
+                // UTF-8: по старшим битам первого байта — сколько ещё дочитать
+                let len = match b {
+                    0xc0..=0xdf => 2,
+                    0xe0..=0xef => 3,
+                    0xf0..=0xf7 => 4,
+                    _ => 1,
+                };
+                let mut buf = vec![b];
+                for _ in 1..len {
+                    buf.push(read_byte()?);
+                }
+                match std::str::from_utf8(&buf)
+                    .ok()
+                    .and_then(|s| s.chars().next())
+                {
+                    Some(c) => Key::Char(c),
+                    None => Key::Other,
+                }
+            }
+        })
+    }
+
+    pub fn run(entries: &[String]) -> Result<Option<String>> {
+        let mut picker = Picker::new(entries);
+        let mut err = std::io::stderr();
+        let _raw = RawMode::enable()?;
+
+        let result = loop {
+            err.write_all(picker.frame().as_bytes())?;
+            err.flush()?;
+            if let Some(done) = picker.handle(read_key()?) {
+                break done;
+            }
+        };
+        err.write_all(b"\r\x1b[J")?;
+        err.flush()?;
+        Ok(result)
+    }
+}
+
+#[cfg(not(unix))]
+mod imp {
+    use anyhow::{Result, anyhow};
+
+    pub fn run(_entries: &[String]) -> Result<Option<String>> {
+        Err(anyhow!(
+            "interactive selection is not supported on this platform; pass the entry path"
+        ))
+    }
+}
@@ -0,0 +1,214 @@
+//! Зашифрованный перенос хранилища между машинами: `pm export --encrypted`
+//! и `pm import pmx`.
+//!
+//! Формат файла `.pmx`:
+//!
+//! ```text
+//! "PMX\0"  версия (1 байт)  memory_mib, iterations, parallelism (u32 LE)
+//! соль (16 байт)  nonce (24 байта)  шифртекст
+//! ```
+//!
+//! Ключ — Argon2id от парольной фразы, введённой при экспорте; шифр —
+//! XChaCha20-Poly1305. Внутри — JSON `Payload`. Мастер-ключ хранилища в файл
+//! не попадает: при импорте записи перешифровываются локальным ключом.
+//!
+//! Тот же контейнер с магией `PMB\0` — зашифрованный бэкап (`backup.rs`).
+
+use crate::attachments::ExportedAttachment;
+use crate::config::{Config, KdfParams, Settings};
+use crate::crypto::{decrypt_entry, derive_kek, encrypt_entry};
+use crate::entry::Entry;
+use crate::import::{Importer, OnConflict};
+use crate::prompt::prompt_password_hidden;
+use crate::session::get_master_key_with_cache;
+use crate::store::{decrypt_all, list_entries_under, write_atomic_private};
+use anyhow::{Context, Result, anyhow};
+use base64::{Engine as _, engine::general_purpose};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use time::OffsetDateTime;
+
+const MAGIC: &[u8; 4] = b"PMX\0";
+const VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const HEADER_LEN: usize = 4 + 1 + 3 * 4 + SALT_LEN + NONCE_LEN;
+
+#[derive(Serialize, Deserialize)]
+struct Payload {
+    exported_at: String,
+    /// Настройки исходного хранилища; применяются только с `--with-settings`
+    settings: Settings,
+    entries: Vec<PayloadEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PayloadEntry {
+    path: String,
+    entry: Entry,
+    /// Содержимое вложений; в файлах старых версий поля нет
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    attachments: Vec<ExportedAttachment>,
+}
+
+fn kdf_params(memory_mib: u32, iterations: u32, parallelism: u32, salt: &[u8]) -> KdfParams {
+    KdfParams {
+        keyfile: false,
+        algo: "argon2id".to_string(),
+        memory_mib,
+        iterations,
+        parallelism,
+        salt: general_purpose::STANDARD.encode(salt),
+    }
+}
+
+/// Зашифровать `plaintext` парольной фразой в контейнер с магией `magic`;
+/// параметры Argon2id берутся из `kdf_template`, соль — новая.
+pub(crate) fn seal(
+    magic: &[u8; 4],
+    passphrase: &str,
+    kdf_template: &KdfParams,
+    plaintext: &[u8],
+) -> Result<Vec<u8>> {
+    let mut salt = [0u8; SALT_LEN];
+    rand::rngs::OsRng.fill_bytes(&mut salt);
+    let kdf = kdf_params(
+        kdf_template.memory_mib,
+        kdf_template.iterations,
+        kdf_template.parallelism,
+        &salt,
+    );
+    let key = derive_kek(passphrase, &kdf)?;
+    // Тот же XChaCha20-Poly1305, что и для записей, только ключ другой
+    let (nonce, ct) = encrypt_entry(&key, plaintext, &[])?;
+
+    let mut out = Vec::with_capacity(HEADER_LEN + ct.len());
+    out.extend_from_slice(magic);
+    out.push(VERSION);
+    for v in [kdf.memory_mib, kdf.iterations, kdf.parallelism] {
+        out.extend_from_slice(&v.to_le_bytes());
+    }
+    out.extend_from_slice(&salt);
+    out.extend_from_slice(&general_purpose::STANDARD.decode(nonce)?);
+    out.extend_from_slice(&general_purpose::STANDARD.decode(ct)?);
+    Ok(out)
+}
+
+/// Расшифровать контейнер с магией `magic`; `what` — название файла для
+/// сообщений об ошибках (`export`, `backup`).
+pub(crate) fn open(magic: &[u8; 4], what: &str, data: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    if data.len() < HEADER_LEN || !data.starts_with(magic) {
+        return Err(anyhow!("not a pm {what} file"));
+    }
+    let version = data[magic.len()];
+    if version != VERSION {
+        return Err(anyhow!(
+            "{what} file format v{version} is not supported by this pm (v{VERSION})"
+        ));
+    }
+    let u32_at = |i: usize| {
+        let off = magic.len() + 1 + i * 4;
+        u32::from_le_bytes(data[off..off + 4].try_into().unwrap())
+    };
+    let salt_at = magic.len() + 1 + 3 * 4;
+    let salt = &data[salt_at..salt_at + SALT_LEN];
+    let nonce = &data[salt_at + SALT_LEN..HEADER_LEN];
+
+    let kdf = kdf_params(u32_at(0), u32_at(1), u32_at(2), salt);
+    let key = derive_kek(passphrase, &kdf)?;
+    decrypt_entry(
+        &key,
+        &general_purpose::STANDARD.encode(nonce),
+        &general_purpose::STANDARD.encode(&data[HEADER_LEN..]),
+        &[],
+    )
+    .map_err(|_| anyhow!("wrong passphrase or corrupted {what} file"))
+}
+
+/// pm export --encrypted FILE [--prefix P]
+pub fn export_encrypted(output: &Path, prefix: Option<&str>) -> Result<()> {
+    let config = Config::load()?;
+    let mk = get_master_key_with_cache(&config)?;
+
+    let mut entries = Vec::new();
+    for (path, res) in decrypt_all(&mk, list_entries_under(prefix)?) {
+        let entry = res.map_err(|e| anyhow!("cannot decrypt {path}: {e}"))?;
+        let attachments = crate::attachments::export(&path, &entry, &mk)?;
+        entries.push(PayloadEntry {
+            path,
+            entry,
+            attachments,
+        });
+    }
+    let payload = Payload {
+        exported_at: OffsetDateTime::now_utc()
+            .format(&time::format_description::well_known::Rfc3339)?,
+        settings: config.settings.clone(),
+        entries,
+    };
+
+    let passphrase = prompt_password_hidden("Export passphrase: ")?;
+    if passphrase.is_empty() {
+        return Err(anyhow!("export passphrase cannot be empty"));
+    }
+    if prompt_password_hidden("Confirm export passphrase: ")? != passphrase {
+        return Err(anyhow!("Passphrases do not match"));
+    }
+
+    let data = seal(
+        MAGIC,
+        &passphrase,
+        &config.kdf,
+        &serde_json::to_vec(&payload)?,
+    )?;
+    write_atomic_private(output, &data)?;
+    println!(
+        "Exported {} entries to {}",
+        payload.entries.len(),
+        output.display()
+    );
+    Ok(())
+}
+
+/// pm import pmx FILE [--on-conflict skip|overwrite|rename] [--with-settings] [--dry-run]
+pub fn import_pmx(
+    file: &Path,
+    on_conflict: OnConflict,
+    with_settings: bool,
+    dry_run: bool,
+) -> Result<()> {
+    let data = std::fs::read(file).with_context(|| format!("cannot read {}", file.display()))?;
+    let passphrase = prompt_password_hidden("Export passphrase: ")?;
+    let payload: Payload = serde_json::from_slice(&open(MAGIC, "export", &data, &passphrase)?)
+        .context("export file contents are invalid")?;
+    println!(
+        "Export from {} with {} entries",
+        payload.exported_at,
+        payload.entries.len()
+    );
+
+    let mut importer = Importer::new(dry_run)?.on_conflict(on_conflict);
+    for PayloadEntry {
+        path,
+        entry,
+        attachments,
+    } in payload.entries
+    {
+        match attachments.iter().map(|a| a.decode()).collect() {
+            Ok(files) => importer.add_with_attachments(&path, entry, files),
+            Err(e) => importer.fail(&path, e),
+        }
+    }
+
+    if with_settings && !dry_run {
+        let mut config = Config::load()?;
+        // Связка ключей привязана к машине, её состояние не переносим
+        let keychain = config.settings.unlock.keychain;
+        config.settings = payload.settings;
+        config.settings.unlock.keychain = keychain;
+        crate::config::save_config(&config, &crate::config::config_path()?)?;
+        println!("Applied settings from the export.");
+    }
+    importer.finish()
+}
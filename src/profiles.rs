@@ -0,0 +1,433 @@
+//! Именованные профили: `pm --profile work show github`.
+//!
+//! Профили описаны в `~/.config/pm/profiles.toml`:
+//!
+//! ```toml
+//! default = "personal"
+//!
+//! [profiles.work]
+//! path = "/home/me/work-store"
+//! session_ttl = 900
+//! ```
+//!
+//! Понимаем только это подмножество TOML: строки в кавычках, целые числа,
+//! комментарии `#` и таблицы `[profiles.<имя>]` — ради него тянуть парсер
+//! не стали. `pm profile add/remove/default` переписывают файл целиком.
+
+use crate::output;
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Profile {
+    pub path: PathBuf,
+    /// TTL сессии для этого профиля; важнее настройки хранилища
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_ttl: Option<u64>,
+}
+
+#[derive(Debug, Default)]
+pub struct Profiles {
+    pub default: Option<String>,
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// Профиль, выбранный в этом запуске.
+struct Active {
+    name: String,
+    profile: Profile,
+}
+
+static ACTIVE: OnceLock<Active> = OnceLock::new();
+
+pub fn profiles_path() -> Result<PathBuf> {
+    let dir = dirs::config_dir().ok_or_else(|| anyhow!("cannot get config dir"))?;
+    Ok(dir.join("pm").join("profiles.toml"))
+}
+
+/// Выбрать профиль: из `--profile`, иначе профиль по умолчанию — если
+/// хранилище не задано через `PM_STORE_DIR`. Возвращает его хранилище.
+pub fn activate(flag: Option<&str>) -> Result<Option<PathBuf>> {
+    let explicit = flag.is_some();
+    if !explicit && std::env::var_os("PM_STORE_DIR").is_some_and(|d| !d.is_empty()) {
+        return Ok(None);
+    }
+    let profiles = Profiles::load()?;
+    let Some(name) = flag.map(str::to_string).or(profiles.default.clone()) else {
+        return Ok(None);
+    };
+    let profile = profiles.get(&name)?.clone();
+    let path = profile.path.clone();
+    let _ = ACTIVE.set(Active { name, profile });
+    Ok(Some(path))
+}
+
+/// Имя активного профиля — для сообщений об ошибках и имён файлов сессии.
+pub fn active_name() -> Option<&'static str> {
+    ACTIVE.get().map(|a| a.name.as_str())
+}
+
+/// TTL сессии из активного профиля.
+pub fn session_ttl() -> Option<u64> {
+    ACTIVE.get().and_then(|a| a.profile.session_ttl)
+}
+
+fn check_name(name: &str) -> Result<()> {
+    let ok = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if ok {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "invalid profile name {name:?}: use letters, digits, '-' and '_'"
+        ))
+    }
+}
+
+impl Profiles {
+    /// Прочитать файл профилей; нет файла — нет профилей.
+    pub fn load() -> Result<Self> {
+        let path = profiles_path()?;
+        match std::fs::read_to_string(&path) {
+            Ok(text) => Self::parse(&text, &path),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(anyhow!("cannot read {}: {e}", path.display())),
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = profiles_path()?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        crate::store::write_atomic_private(&path, self.render().as_bytes())
+    }
+
+    pub fn get(&self, name: &str) -> Result<&Profile> {
+        self.profiles.get(name).ok_or_else(|| {
+            anyhow!(
+                "unknown profile {name:?}; see `pm profile list` or add it with `pm profile add`"
+            )
+        })
+    }
+
+    fn parse(text: &str, origin: &Path) -> Result<Self> {
+        let mut out = Profiles::default();
+        let mut table: Option<String> = None;
+        for (i, raw) in text.lines().enumerate() {
+            let at = |msg: String| anyhow!("{}:{}: {msg}", origin.display(), i + 1);
+            let mut line = Line::new(raw);
+            if line.at_end() {
+                continue;
+            }
+            if line.eat('[') {
+                let (Some(head), true, Some(name), true) =
+                    (line.key(), line.eat('.'), line.key(), line.eat(']'))
+                else {
+                    return Err(at("expected a table header like [profiles.work]".into()));
+                };
+                if head != "profiles" || !line.at_end() {
+                    return Err(at(
+                        "unexpected table; only [profiles.<name>] is supported".into()
+                    ));
+                }
+                check_name(&name).map_err(|e| at(e.to_string()))?;
+                if out
+                    .profiles
+                    .insert(name.clone(), Profile::default())
+                    .is_some()
+                {
+                    return Err(at(format!("profile {name:?} is defined twice")));
+                }
+                table = Some(name);
+                continue;
+            }
+            let (Some(key), true) = (line.key(), line.eat('=')) else {
+                return Err(at("expected `key = value`".into()));
+            };
+            let value = line.value().map_err(at)?;
+            if !line.at_end() {
+                return Err(at("unexpected text after the value".into()));
+            }
+            match (&table, key.as_str(), value) {
+                (None, "default", Value::Str(name)) => out.default = Some(name),
+                (Some(t), "path", Value::Str(path)) => {
+                    out.profiles.get_mut(t).unwrap().path = PathBuf::from(path)
+                }
+                (Some(t), "session_ttl", Value::Int(secs)) => {
+                    out.profiles.get_mut(t).unwrap().session_ttl = Some(secs)
+                }
+                (_, "default" | "path" | "session_ttl", _) => {
+                    return Err(at(format!("wrong type or place for `{key}`")));
+                }
+                _ => return Err(at(format!("unknown key `{key}`"))),
+            }
+        }
+        for (name, profile) in &out.profiles {
+            if profile.path.as_os_str().is_empty() {
+                return Err(anyhow!(
+                    "{}: profile {name:?} has no path",
+                    origin.display()
+                ));
+            }
+        }
+        if let Some(ref name) = out.default
+            && !out.profiles.contains_key(name)
+        {
+            return Err(anyhow!(
+                "{}: default profile {name:?} is not defined",
+                origin.display()
+            ));
+        }
+        Ok(out)
+    }
+
+    fn render(&self) -> String {
+        let mut s = String::from("# pm profiles; edit by hand or with `pm profile`\n");
+        if let Some(ref name) = self.default {
+            s.push_str(&format!("default = {}\n", quote(name)));
+        }
+        for (name, profile) in &self.profiles {
+            s.push_str(&format!("\n[profiles.{name}]\n"));
+            s.push_str(&format!(
+                "path = {}\n",
+                quote(&profile.path.to_string_lossy())
+            ));
+            if let Some(ttl) = profile.session_ttl {
+                s.push_str(&format!("session_ttl = {ttl}\n"));
+            }
+        }
+        s
+    }
+}
+
+/// Строка TOML в двойных кавычках.
+fn quote(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04X}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+enum Value {
+    Str(String),
+    Int(u64),
+}
+
+/// Разбор одной строки файла слева направо.
+struct Line<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Line<'a> {
+    fn new(s: &'a str) -> Self {
+        Line { rest: s }
+    }
+
+    fn skip_ws(&mut self) {
+        self.rest = self.rest.trim_start_matches([' ', '\t']);
+    }
+
+    /// Пробелы и комментарий до конца строки.
+    fn at_end(&mut self) -> bool {
+        self.skip_ws();
+        self.rest.is_empty() || self.rest.starts_with('#')
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_ws();
+        match self.rest.strip_prefix(c) {
+            Some(rest) => {
+                self.rest = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Голый ключ: буквы, цифры, `-` и `_`.
+    fn key(&mut self) -> Option<String> {
+        self.skip_ws();
+        let end = self
+            .rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+            .unwrap_or(self.rest.len());
+        if end == 0 {
+            return None;
+        }
+        let (key, rest) = self.rest.split_at(end);
+        self.rest = rest;
+        Some(key.to_string())
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        self.skip_ws();
+        if let Some(rest) = self.rest.strip_prefix('\'') {
+            let (s, rest) = rest.split_once('\'').ok_or("unterminated string")?;
+            self.rest = rest;
+            return Ok(Value::Str(s.to_string()));
+        }
+        if let Some(rest) = self.rest.strip_prefix('"') {
+            let mut out = String::new();
+            let mut chars = rest.char_indices();
+            while let Some((i, c)) = chars.next() {
+                match c {
+                    '"' => {
+                        self.rest = &rest[i + 1..];
+                        return Ok(Value::Str(out));
+                    }
+                    '\\' => {
+                        let esc = match chars.next().map(|(_, e)| e) {
+                            Some('"') => '"',
+                            Some('\\') => '\\',
+                            Some('n') => '\n',
+                            Some('t') => '\t',
+                            Some('r') => '\r',
+                            Some(u @ ('u' | 'U')) => {
+                                let len = if u == 'u' { 4 } else { 8 };
+                                let hex: String =
+                                    chars.by_ref().take(len).map(|(_, h)| h).collect();
+                                u32::from_str_radix(&hex, 16)
+                                    .ok()
+                                    .filter(|_| hex.len() == len)
+                                    .and_then(char::from_u32)
+                                    .ok_or("invalid unicode escape")?
+                            }
+                            _ => return Err("invalid escape in string".into()),
+                        };
+                        out.push(esc);
+                    }
+                    c => out.push(c),
+                }
+            }
+            return Err("unterminated string".into());
+        }
+        let end = self
+            .rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '_'))
+            .unwrap_or(self.rest.len());
+        let (num, rest) = self.rest.split_at(end);
+        let n = num
+            .replace('_', "")
+            .parse()
+            .map_err(|_| "expected a string in quotes or a non-negative integer")?;
+        self.rest = rest;
+        Ok(Value::Int(n))
+    }
+}
+
+/// pm profile list
+pub fn profile_list() -> Result<()> {
+    let profiles = Profiles::load()?;
+    #[derive(Serialize)]
+    struct Item<'a> {
+        name: &'a str,
+        default: bool,
+        #[serde(flatten)]
+        profile: &'a Profile,
+    }
+    let items: Vec<Item> = profiles
+        .profiles
+        .iter()
+        .map(|(name, profile)| Item {
+            name,
+            default: profiles.default.as_deref() == Some(name.as_str()),
+            profile,
+        })
+        .collect();
+    output::emit(&serde_json::json!({ "profiles": items }), || {
+        if items.is_empty() {
+            println!("No profiles; add one with `pm profile add <name> <path>`.");
+            return Ok(());
+        }
+        let width = items.iter().map(|i| i.name.len()).max().unwrap_or(0);
+        for item in &items {
+            let mark = if item.default { '*' } else { ' ' };
+            let ttl = match item.profile.session_ttl {
+                Some(secs) => format!("  (session {secs}s)"),
+                None => String::new(),
+            };
+            println!(
+                "{mark} {:width$}  {}{ttl}",
+                item.name,
+                item.profile.path.display()
+            );
+        }
+        Ok(())
+    })
+}
+
+/// pm profile add NAME PATH [--session-ttl SECS]
+pub fn profile_add(name: &str, path: &Path, session_ttl: Option<u64>) -> Result<()> {
+    check_name(name)?;
+    let mut profiles = Profiles::load()?;
+    if profiles.profiles.contains_key(name) {
+        return Err(anyhow!(
+            "profile {name:?} already exists; remove it first to change it"
+        ));
+    }
+    let path = std::path::absolute(path)?;
+    let initialized = path.join("config.json").is_file();
+    profiles.profiles.insert(
+        name.to_string(),
+        Profile {
+            path: path.clone(),
+            session_ttl,
+        },
+    );
+    profiles.save()?;
+    output::done(
+        "profile-add",
+        name,
+        format!("Added profile {name} ({})", path.display()),
+    );
+    if !initialized && !output::is_json() {
+        println!("No store there yet; create it with `pm --profile {name} init`.");
+    }
+    Ok(())
+}
+
+/// pm profile remove NAME — хранилище не трогаем.
+pub fn profile_remove(name: &str) -> Result<()> {
+    let mut profiles = Profiles::load()?;
+    profiles.get(name)?;
+    profiles.profiles.remove(name);
+    if profiles.default.as_deref() == Some(name) {
+        profiles.default = None;
+    }
+    profiles.save()?;
+    output::done(
+        "profile-remove",
+        name,
+        format!("Removed profile {name}; the store itself was not touched"),
+    );
+    Ok(())
+}
+
+/// pm profile default NAME
+pub fn profile_default(name: &str) -> Result<()> {
+    let mut profiles = Profiles::load()?;
+    profiles.get(name)?;
+    profiles.default = Some(name.to_string());
+    profiles.save()?;
+    output::done(
+        "profile-default",
+        name,
+        format!("Default profile is now {name}"),
+    );
+    Ok(())
+}
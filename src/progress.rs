@@ -0,0 +1,126 @@
+//! Ход долгих операций (бэкап, восстановление) в stderr.
+//!
+//! В терминале — строка, которая перерисовывается: полоса, счётчик файлов
+//! и текущий путь. Без терминала (cron, перенаправление) полосы нет, только
+//! итоговая строка из `finish`. С `--quiet` и в JSON-режиме — тишина.
+
+use std::io::{IsTerminal, Write};
+use std::time::{Duration, Instant};
+
+const BAR_WIDTH: usize = 24;
+/// Не чаще раза в столько — иначе вывод тормозит саму работу.
+const REDRAW: Duration = Duration::from_millis(80);
+
+pub struct Progress {
+    label: &'static str,
+    /// Сколько файлов всего, если известно заранее
+    total: Option<usize>,
+    done: usize,
+    bytes: u64,
+    started: Instant,
+    last_draw: Option<Instant>,
+    bar: bool,
+    summary: bool,
+}
+
+impl Progress {
+    pub fn new(label: &'static str, total: Option<usize>) -> Self {
+        let summary = !crate::output::is_quiet() && !crate::output::is_json();
+        Progress {
+            label,
+            total,
+            done: 0,
+            bytes: 0,
+            started: Instant::now(),
+            last_draw: None,
+            bar: summary && std::io::stderr().is_terminal(),
+            summary,
+        }
+    }
+
+    /// Ничего не печатать (например, `pm backup auto` для cron).
+    pub fn hidden() -> Self {
+        Progress {
+            bar: false,
+            summary: false,
+            ..Progress::new("", None)
+        }
+    }
+
+    /// Число файлов стало известно позже (zip узнаёт его, открыв архив).
+    pub fn set_total(&mut self, total: usize) {
+        self.total = Some(total);
+    }
+
+    /// Файл `name` размером `bytes` обработан.
+    pub fn tick(&mut self, name: &str, bytes: u64) {
+        self.done += 1;
+        self.bytes += bytes;
+        if !self.bar || self.last_draw.is_some_and(|t| t.elapsed() < REDRAW) {
+            return;
+        }
+        self.last_draw = Some(Instant::now());
+        let count = match self.total {
+            Some(total) => {
+                let filled = (self.done * BAR_WIDTH)
+                    .checked_div(total)
+                    .unwrap_or(BAR_WIDTH);
+                let filled = filled.min(BAR_WIDTH);
+                format!(
+                    "[{}{}] {}/{total}",
+                    "#".repeat(filled),
+                    " ".repeat(BAR_WIDTH - filled),
+                    self.done
+                )
+            }
+            None => format!("{} files", self.done),
+        };
+        // Длинный путь обрезаем слева: важнее конец, имя файла
+        let name: String = if name.chars().count() > 40 {
+            let tail: String = name.chars().rev().take(39).collect();
+            format!("…{}", tail.chars().rev().collect::<String>())
+        } else {
+            name.to_string()
+        };
+        let mut err = std::io::stderr().lock();
+        let _ = write!(err, "\r\x1b[2K{} {count} {name}", self.label);
+        let _ = err.flush();
+    }
+
+    /// Стереть полосу и напечатать итог.
+    pub fn finish(self, summary: impl std::fmt::Display) {
+        let mut err = std::io::stderr().lock();
+        if self.bar && self.last_draw.is_some() {
+            let _ = write!(err, "\r\x1b[2K");
+        }
+        if self.summary {
+            let _ = writeln!(err, "{summary}");
+        }
+    }
+
+    /// Итог: число файлов, размер до и после сжатия, время.
+    pub fn stats(&self, compressed: u64) -> String {
+        format!(
+            "{} files, {} uncompressed, {} compressed, in {:.1}s",
+            self.done,
+            human_size(self.bytes),
+            human_size(compressed),
+            self.started.elapsed().as_secs_f64()
+        )
+    }
+}
+
+/// `1536` → `1.5 KiB`.
+pub fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
@@ -1,9 +1,15 @@
 use anyhow::Result;
-use std::io::{self, Write};
+use std::io::{self, IsTerminal, Write};
 
 pub fn prompt_string(prompt: &str) -> Result<String> {
-    print!("{prompt}");
-    io::stdout().flush()?;
+    // В JSON-режиме stdout занят документом с результатом
+    if crate::output::is_json() {
+        eprint!("{prompt}");
+        io::stderr().flush()?;
+    } else {
+        print!("{prompt}");
+        io::stdout().flush()?;
+    }
     let mut s = String::new();
     io::stdin().read_line(&mut s)?;
     if s.ends_with('\n') {
@@ -15,7 +21,65 @@ pub fn prompt_string(prompt: &str) -> Result<String> {
     Ok(s)
 }
 
+/// Запрос нового значения поля при редактировании: `Label [текущее]: `.
+/// Пустой ввод оставляет текущее значение, `-` очищает поле.
+pub fn prompt_edit(label: &str, current: Option<&str>) -> Result<Option<String>> {
+    let answer = match current {
+        Some(c) => prompt_string(&format!("{label} [{c}]: "))?,
+        None => prompt_string(&format!("{label}: "))?,
+    };
+    Ok(match answer.as_str() {
+        "" => current.map(str::to_string),
+        "-" => None,
+        _ => Some(answer),
+    })
+}
+
+/// Вопрос `[y/N]`: согласие — только явное `y`/`yes`.
+pub fn confirm(question: &str) -> Result<bool> {
+    let answer = prompt_string(&format!("{question} [y/N] "))?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
 pub fn prompt_password_hidden(prompt: &str) -> Result<String> {
     let pw = rpassword::prompt_password(prompt)?;
     Ok(pw)
 }
+
+/// Прочитать секрет: из скрытого промпта, если stdin — терминал,
+/// иначе первой строкой из stdin (чтобы секрет не попадал в историю shell).
+pub fn read_secret(prompt: &str) -> Result<String> {
+    if io::stdin().is_terminal() {
+        return prompt_password_hidden(prompt);
+    }
+    let mut s = String::new();
+    io::stdin().read_line(&mut s)?;
+    Ok(s.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Прочитать секрет первой строкой из открытого дескриптора `fd`
+/// (`--password-fd 3`): так пароль передаёт программа, у которой нет терминала.
+/// Дескриптор закрывается после чтения.
+#[cfg(unix)]
+pub fn read_secret_fd(fd: i32) -> Result<String> {
+    use std::io::BufRead;
+    use std::os::unix::io::FromRawFd;
+    // SAFETY: F_GETFD только проверяет, что дескриптор открыт
+    if fd < 0 || unsafe { libc::fcntl(fd, libc::F_GETFD) } == -1 {
+        anyhow::bail!("file descriptor {fd} is not open");
+    }
+    // SAFETY: дескриптор открыт и больше нигде в pm не используется
+    let file = unsafe { std::fs::File::from_raw_fd(fd) };
+    let mut s = String::new();
+    io::BufReader::new(file).read_line(&mut s)?;
+    let s = s.trim_end_matches(['\r', '\n']);
+    if s.is_empty() {
+        anyhow::bail!("no password on file descriptor {fd}");
+    }
+    Ok(s.to_string())
+}
+
+#[cfg(not(unix))]
+pub fn read_secret_fd(_fd: i32) -> Result<String> {
+    anyhow::bail!("--password-fd is not supported on this platform")
+}
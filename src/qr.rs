@@ -0,0 +1,714 @@
+//! Минимальный QR-энкодер (байтовый режим, уровень коррекции M, версии 1–40)
+//! и отрисовка в терминале полублоками.
+//!
+//! Алгоритм повторяет ISO/IEC 18004: кодируем данные, добавляем коды
+//! Рида–Соломона, раскладываем по матрице и выбираем маску с наименьшим штрафом.
+
+use anyhow::{Result, anyhow};
+
+/// Число ECC-кодовых слов на блок для уровня M, индекс — версия.
+const ECC_CODEWORDS_PER_BLOCK: [usize; 41] = [
+    0, 10, 16, 26, 18, 24, 16, 18, 22, 22, 26, 30, 22, 22, 24, 24, 28, 28, 26, 26, 26, 26, 28, 28,
+    28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28,
+];
+
+/// Число блоков коррекции ошибок для уровня M, индекс — версия.
+const NUM_ERROR_CORRECTION_BLOCKS: [usize; 41] = [
+    0, 1, 1, 1, 2, 2, 4, 4, 4, 5, 5, 5, 8, 9, 9, 10, 10, 11, 13, 14, 16, 17, 17, 18, 20, 21, 23,
+    25, 26, 28, 29, 31, 33, 35, 37, 38, 40, 43, 45, 47, 49,
+];
+
+/// Биты уровня коррекции M в format information.
+const ECL_M_FORMAT_BITS: u32 = 0;
+
+/// Тихая зона по стандарту — 4 модуля; в узком терминале ужимаем до 1.
+const QUIET_ZONE: usize = 4;
+const MIN_QUIET_ZONE: usize = 1;
+
+pub struct QrCode {
+    size: usize,
+    modules: Vec<bool>,
+    is_function: Vec<bool>,
+}
+
+impl QrCode {
+    /// Закодировать байты в QR минимально возможной версии.
+    pub fn encode(data: &[u8]) -> Result<Self> {
+        let version = (1..=40)
+            .find(|&v| {
+                let count_bits = if v <= 9 { 8 } else { 16 };
+                data.len() < (1 << count_bits)
+                    && 4 + count_bits + data.len() * 8 <= num_data_codewords(v) * 8
+            })
+            .ok_or_else(|| anyhow!("data too long for a QR code ({} bytes)", data.len()))?;
+
+        let codewords = encode_data(data, version);
+        let all = add_ecc_and_interleave(&codewords, version);
+
+        let size = version * 4 + 17;
+        let mut qr = QrCode {
+            size,
+            modules: vec![false; size * size],
+            is_function: vec![false; size * size],
+        };
+        qr.draw_function_patterns(version);
+        qr.draw_codewords(&all);
+
+        let mut best = (0, i32::MAX);
+        for mask in 0..8 {
+            qr.apply_mask(mask);
+            qr.draw_format_bits(mask);
+            let penalty = qr.penalty_score();
+            if penalty < best.1 {
+                best = (mask, penalty);
+            }
+            qr.apply_mask(mask); // XOR обратим — снимаем маску
+        }
+        qr.apply_mask(best.0);
+        qr.draw_format_bits(best.0);
+
+        Ok(qr)
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Тёмный ли модуль (x — столбец, y — строка). За пределами — светлый.
+    pub fn module(&self, x: isize, y: isize) -> bool {
+        let n = self.size as isize;
+        (0..n).contains(&x)
+            && (0..n).contains(&y)
+            && self.modules[y as usize * self.size + x as usize]
+    }
+
+    fn set_function(&mut self, x: usize, y: usize, dark: bool) {
+        let i = y * self.size + x;
+        self.modules[i] = dark;
+        self.is_function[i] = true;
+    }
+
+    fn draw_function_patterns(&mut self, version: usize) {
+        let size = self.size;
+        for i in 0..size {
+            self.set_function(6, i, i % 2 == 0);
+            self.set_function(i, 6, i % 2 == 0);
+        }
+
+        self.draw_finder(3, 3);
+        self.draw_finder(size - 4, 3);
+        self.draw_finder(3, size - 4);
+
+        let positions = alignment_positions(version);
+        let n = positions.len();
+        for (i, &y) in positions.iter().enumerate() {
+            for (j, &x) in positions.iter().enumerate() {
+                let corner = (i == 0 && (j == 0 || j == n - 1)) || (i == n - 1 && j == 0);
+                if !corner {
+                    self.draw_alignment(x, y);
+                }
+            }
+        }
+
+        // Резервируем место под format bits, настоящие значения — после выбора маски.
+        self.draw_format_bits(0);
+        self.draw_version(version);
+    }
+
+    fn draw_finder(&mut self, cx: usize, cy: usize) {
+        for dy in -4isize..=4 {
+            for dx in -4isize..=4 {
+                let x = cx as isize + dx;
+                let y = cy as isize + dy;
+                let n = self.size as isize;
+                if (0..n).contains(&x) && (0..n).contains(&y) {
+                    let dist = dx.abs().max(dy.abs());
+                    self.set_function(x as usize, y as usize, dist != 2 && dist != 4);
+                }
+            }
+        }
+    }
+
+    fn draw_alignment(&mut self, cx: usize, cy: usize) {
+        for dy in -2isize..=2 {
+            for dx in -2isize..=2 {
+                let x = (cx as isize + dx) as usize;
+                let y = (cy as isize + dy) as usize;
+                self.set_function(x, y, dx.abs().max(dy.abs()) != 1);
+            }
+        }
+    }
+
+    fn draw_format_bits(&mut self, mask: u32) {
+        let data = (ECL_M_FORMAT_BITS << 3) | mask;
+        let mut rem = data;
+        for _ in 0..10 {
+            rem = (rem << 1) ^ ((rem >> 9) * 0x537);
+        }
+        let bits = ((data << 10) | rem) ^ 0x5412;
+        let bit = |i: usize| (bits >> i) & 1 != 0;
+        let size = self.size;
+
+        for i in 0..6 {
+            self.set_function(8, i, bit(i));
+        }
+        self.set_function(8, 7, bit(6));
+        self.set_function(8, 8, bit(7));
+        self.set_function(7, 8, bit(8));
+        for i in 9..15 {
+            self.set_function(14 - i, 8, bit(i));
+        }
+
+        for i in 0..8 {
+            self.set_function(size - 1 - i, 8, bit(i));
+        }
+        for i in 8..15 {
+            self.set_function(8, size - 15 + i, bit(i));
+        }
+        self.set_function(8, size - 8, true);
+    }
+
+    fn draw_version(&mut self, version: usize) {
+        if version < 7 {
+            return;
+        }
+        let mut rem = version as u32;
+        for _ in 0..12 {
+            rem = (rem << 1) ^ ((rem >> 11) * 0x1F25);
+        }
+        let bits = ((version as u32) << 12) | rem;
+        for i in 0..18 {
+            let dark = (bits >> i) & 1 != 0;
+            let a = self.size - 11 + i % 3;
+            let b = i / 3;
+            self.set_function(a, b, dark);
+            self.set_function(b, a, dark);
+        }
+    }
+
+    fn draw_codewords(&mut self, data: &[u8]) {
+        let size = self.size as isize;
+        let total_bits = data.len() * 8;
+        let mut i = 0;
+        let mut right = size - 1;
+        while right >= 1 {
+            if right == 6 {
+                right = 5;
+            }
+            for vert in 0..size {
+                for j in 0..2 {
+                    let x = (right - j) as usize;
+                    let upward = (right + 1) & 2 == 0;
+                    let y = if upward { size - 1 - vert } else { vert } as usize;
+                    let idx = y * self.size + x;
+                    if !self.is_function[idx] && i < total_bits {
+                        self.modules[idx] = (data[i >> 3] >> (7 - (i & 7))) & 1 != 0;
+                        i += 1;
+                    }
+                }
+            }
+            right -= 2;
+        }
+    }
+
+    fn apply_mask(&mut self, mask: u32) {
+        for y in 0..self.size {
+            for x in 0..self.size {
+                let invert = match mask {
+                    0 => (x + y) % 2 == 0,
+                    1 => y % 2 == 0,
+                    2 => x % 3 == 0,
+                    3 => (x + y) % 3 == 0,
+                    4 => (x / 3 + y / 2) % 2 == 0,
+                    5 => x * y % 2 + x * y % 3 == 0,
+                    6 => (x * y % 2 + x * y % 3) % 2 == 0,
+                    _ => ((x + y) % 2 + x * y % 3) % 2 == 0,
+                };
+                let idx = y * self.size + x;
+                if invert && !self.is_function[idx] {
+                    self.modules[idx] = !self.modules[idx];
+                }
+            }
+        }
+    }
+
+    /// Штраф по четырём правилам стандарта (чем меньше, тем легче сканировать).
+    fn penalty_score(&self) -> i32 {
+        let n = self.size;
+        let get = |x: usize, y: usize| self.modules[y * n + x];
+        let mut penalty = 0;
+
+        // Правило 1 и 3: серии одного цвета и узоры, похожие на finder.
+        for horizontal in [true, false] {
+            for a in 0..n {
+                let line: Vec<bool> = (0..n)
+                    .map(|b| if horizontal { get(b, a) } else { get(a, b) })
+                    .collect();
+
+                let mut run = 1;
+                for b in 1..=n {
+                    if b < n && line[b] == line[b - 1] {
+                        run += 1;
+                    } else {
+                        if run >= 5 {
+                            penalty += 3 + run - 5;
+                        }
+                        run = 1;
+                    }
+                }
+
+                const PATTERN: [bool; 7] = [true, false, true, true, true, false, true];
+                for b in 0..n.saturating_sub(6) {
+                    if line[b..b + 7] != PATTERN {
+                        continue;
+                    }
+                    let light_before = b >= 4 && line[b - 4..b].iter().all(|m| !m);
+                    let light_after = b + 11 <= n && line[b + 7..b + 11].iter().all(|m| !m);
+                    if light_before || light_after {
+                        penalty += 40;
+                    }
+                }
+            }
+        }
+
+        // Правило 2: квадраты 2x2 одного цвета.
+        for y in 0..n - 1 {
+            for x in 0..n - 1 {
+                let c = get(x, y);
+                if c == get(x + 1, y) && c == get(x, y + 1) && c == get(x + 1, y + 1) {
+                    penalty += 3;
+                }
+            }
+        }
+
+        // Правило 4: доля тёмных модулей далеко от 50%.
+        let total = (n * n) as i32;
+        let dark = self.modules.iter().filter(|&&m| m).count() as i32;
+        let k = ((dark * 20 - total * 10).abs() + total - 1) / total - 1;
+        penalty + k * 10
+    }
+}
+
+fn num_raw_data_modules(version: usize) -> usize {
+    let mut result = (16 * version + 128) * version + 64;
+    if version >= 2 {
+        let num_align = version / 7 + 2;
+        result -= (25 * num_align - 10) * num_align - 55;
+        if version >= 7 {
+            result -= 36;
+        }
+    }
+    result
+}
+
+fn num_data_codewords(version: usize) -> usize {
+    num_raw_data_modules(version) / 8
+        - ECC_CODEWORDS_PER_BLOCK[version] * NUM_ERROR_CORRECTION_BLOCKS[version]
+}
+
+fn alignment_positions(version: usize) -> Vec<usize> {
+    if version == 1 {
+        return Vec::new();
+    }
+    let num_align = version / 7 + 2;
+    let step = if version == 32 {
+        26
+    } else {
+        (version * 4 + num_align * 2 + 1) / (num_align * 2 - 2) * 2
+    };
+    let size = version * 4 + 17;
+    let mut result = vec![6];
+    let mut pos = size - 7;
+    for _ in 0..num_align - 1 {
+        result.insert(1, pos);
+        pos -= step;
+    }
+    result
+}
+
+/// Байтовый режим: индикатор, длина, данные, терминатор и заполнение.
+fn encode_data(data: &[u8], version: usize) -> Vec<u8> {
+    let capacity_bits = num_data_codewords(version) * 8;
+    let count_bits = if version <= 9 { 8 } else { 16 };
+
+    let mut bits: Vec<bool> = Vec::with_capacity(capacity_bits);
+    let mut push = |value: u32, len: usize| {
+        for i in (0..len).rev() {
+            bits.push((value >> i) & 1 != 0);
+        }
+    };
+    push(0b0100, 4);
+    push(data.len() as u32, count_bits);
+    for &b in data {
+        push(b as u32, 8);
+    }
+
+    let terminator = (capacity_bits - bits.len()).min(4);
+    bits.extend(std::iter::repeat_n(false, terminator));
+    while !bits.len().is_multiple_of(8) {
+        bits.push(false);
+    }
+
+    let mut bytes: Vec<u8> = bits
+        .chunks(8)
+        .map(|c| c.iter().fold(0u8, |acc, &b| (acc << 1) | b as u8))
+        .collect();
+    for pad in [0xEC, 0x11].into_iter().cycle() {
+        if bytes.len() * 8 >= capacity_bits {
+            break;
+        }
+        bytes.push(pad);
+    }
+    bytes
+}
+
+fn add_ecc_and_interleave(data: &[u8], version: usize) -> Vec<u8> {
+    let num_blocks = NUM_ERROR_CORRECTION_BLOCKS[version];
+    let ecc_len = ECC_CODEWORDS_PER_BLOCK[version];
+    let raw_codewords = num_raw_data_modules(version) / 8;
+    let num_short = num_blocks - raw_codewords % num_blocks;
+    let short_len = raw_codewords / num_blocks;
+
+    let divisor = rs_divisor(ecc_len);
+    let mut blocks: Vec<Vec<u8>> = Vec::with_capacity(num_blocks);
+    let mut k = 0;
+    for i in 0..num_blocks {
+        let len = short_len - ecc_len + usize::from(i >= num_short);
+        let mut block = data[k..k + len].to_vec();
+        k += len;
+        let ecc = rs_remainder(&block, &divisor);
+        if i < num_short {
+            block.push(0);
+        }
+        block.extend(ecc);
+        blocks.push(block);
+    }
+
+    let mut result = Vec::with_capacity(raw_codewords);
+    for i in 0..blocks[0].len() {
+        for (j, block) in blocks.iter().enumerate() {
+            if i != short_len - ecc_len || j >= num_short {
+                result.push(block[i]);
+            }
+        }
+    }
+    result
+}
+
+fn rs_divisor(degree: usize) -> Vec<u8> {
+    let mut result = vec![0u8; degree];
+    result[degree - 1] = 1;
+    let mut root = 1u8;
+    for _ in 0..degree {
+        for j in 0..degree {
+            result[j] = gf_mul(result[j], root);
+            if j + 1 < degree {
+                result[j] ^= result[j + 1];
+            }
+        }
+        root = gf_mul(root, 0x02);
+    }
+    result
+}
+
+fn rs_remainder(data: &[u8], divisor: &[u8]) -> Vec<u8> {
+    let mut result = vec![0u8; divisor.len()];
+    for &b in data {
+        let factor = b ^ result.remove(0);
+        result.push(0);
+        for (r, &d) in result.iter_mut().zip(divisor) {
+            *r ^= gf_mul(d, factor);
+        }
+    }
+    result
+}
+
+/// Умножение в GF(2^8) по модулю x^8 + x^4 + x^3 + x^2 + 1.
+fn gf_mul(x: u8, y: u8) -> u8 {
+    let mut z: u16 = 0;
+    for i in (0..8).rev() {
+        z = (z << 1) ^ ((z >> 7) * 0x11D);
+        z ^= (((y >> i) & 1) as u16) * x as u16;
+    }
+    z as u8
+}
+
+/// Ширина тихой зоны: стандартная, если код с ней помещается в `cols`
+/// колонок, иначе сколько влезет (но не меньше `MIN_QUIET_ZONE`).
+fn quiet_zone(size: usize, cols: Option<usize>) -> usize {
+    match cols {
+        Some(cols) if size + 2 * QUIET_ZONE > cols => {
+            (cols.saturating_sub(size) / 2).clamp(MIN_QUIET_ZONE, QUIET_ZONE)
+        }
+        _ => QUIET_ZONE,
+    }
+}
+
+/// Нарисовать QR полублоками (`▀`, `▄`, `█`): одна строка текста — две строки модулей.
+///
+/// По умолчанию рассчитано на тёмный фон терминала: закрашиваются светлые модули.
+/// `invert` закрашивает тёмные — для светлых тем.
+pub fn render_qr_terminal(data: &str, invert: bool) -> Result<String> {
+    let qr = QrCode::encode(data.as_bytes())?;
+    let q = quiet_zone(qr.size(), crate::reveal::terminal_columns()) as isize;
+    let n = qr.size() as isize;
+
+    // Закрашен ли символом модуль в позиции (x, y) с учётом тихой зоны.
+    let filled = |x: isize, y: isize| qr.module(x, y) == invert;
+
+    let mut out = String::new();
+    let mut y = -q;
+    while y < n + q {
+        for x in -q..n + q {
+            let top = filled(x, y);
+            let bottom = y + 1 < n + q && filled(x, y + 1);
+            out.push(match (top, bottom) {
+                (true, true) => '█',
+                (true, false) => '▀',
+                (false, true) => '▄',
+                (false, false) => ' ',
+            });
+        }
+        out.push('\n');
+        y += 2;
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Строки format information уровня M для масок 0–7 (ISO/IEC 18004, табл. C.1).
+    const FORMAT_M: [u32; 8] = [
+        0b101010000010010,
+        0b101000100100101,
+        0b101111001111100,
+        0b101101101001011,
+        0b100010111111001,
+        0b100000011001110,
+        0b100111110010111,
+        0b100101010100000,
+    ];
+
+    fn version_of(qr: &QrCode) -> usize {
+        (qr.size() - 17) / 4
+    }
+
+    fn dark(qr: &QrCode, x: usize, y: usize) -> bool {
+        qr.module(x as isize, y as isize)
+    }
+
+    /// Обе копии format information: вокруг левого верхнего finder и у краёв.
+    fn format_bits(qr: &QrCode) -> (u32, u32) {
+        let n = qr.size();
+        let first = |i: usize| match i {
+            0..=5 => (8, i),
+            6 => (8, 7),
+            7 => (8, 8),
+            8 => (7, 8),
+            _ => (14 - i, 8),
+        };
+        let second = |i: usize| {
+            if i < 8 {
+                (n - 1 - i, 8)
+            } else {
+                (8, n - 15 + i)
+            }
+        };
+        let read = |cell: &dyn Fn(usize) -> (usize, usize)| {
+            (0..15).fold(0, |acc, i| {
+                let (x, y) = cell(i);
+                acc | (dark(qr, x, y) as u32) << i
+            })
+        };
+        (read(&first), read(&second))
+    }
+
+    /// Прочитать данные обратно: снять маску, пройти зигзагом,
+    /// разобрать блоки и проверить коды Рида–Соломона каждого.
+    fn decode(qr: &QrCode) -> Vec<u8> {
+        let version = version_of(qr);
+        let (format, _) = format_bits(qr);
+        let mask = FORMAT_M.iter().position(|&f| f == format).unwrap() as u32;
+
+        let n = qr.size();
+        let mut plain = QrCode {
+            size: n,
+            modules: vec![false; n * n],
+            is_function: vec![false; n * n],
+        };
+        plain.draw_function_patterns(version);
+        for y in 0..n {
+            for x in 0..n {
+                plain.modules[y * n + x] = dark(qr, x, y);
+            }
+        }
+        plain.apply_mask(mask);
+
+        let mut bits = Vec::new();
+        let mut right = n as isize - 1;
+        while right >= 1 {
+            if right == 6 {
+                right = 5;
+            }
+            for vert in 0..n {
+                for j in 0..2 {
+                    let x = (right - j) as usize;
+                    let y = if (right + 1) & 2 == 0 {
+                        n - 1 - vert
+                    } else {
+                        vert
+                    };
+                    if !plain.is_function[y * n + x] {
+                        bits.push(plain.modules[y * n + x]);
+                    }
+                }
+            }
+            right -= 2;
+        }
+        let raw: Vec<u8> = bits
+            .chunks_exact(8)
+            .map(|c| c.iter().fold(0u8, |acc, &b| (acc << 1) | b as u8))
+            .collect();
+        assert_eq!(raw.len(), num_raw_data_modules(version) / 8);
+
+        let num_blocks = NUM_ERROR_CORRECTION_BLOCKS[version];
+        let ecc_len = ECC_CODEWORDS_PER_BLOCK[version];
+        let num_short = num_blocks - raw.len() % num_blocks;
+        let short_data = raw.len() / num_blocks - ecc_len;
+        let data_len = |b: usize| short_data + usize::from(b >= num_short);
+        let mut blocks: Vec<Vec<u8>> = vec![Vec::new(); num_blocks];
+        let mut it = raw.iter().copied();
+        for i in 0..=short_data {
+            for (b, block) in blocks.iter_mut().enumerate() {
+                if i < data_len(b) {
+                    block.push(it.next().unwrap());
+                }
+            }
+        }
+        for _ in 0..ecc_len {
+            for block in &mut blocks {
+                block.push(it.next().unwrap());
+            }
+        }
+
+        let divisor = rs_divisor(ecc_len);
+        let mut data = Vec::new();
+        for (b, block) in blocks.iter().enumerate() {
+            let (payload, ecc) = block.split_at(data_len(b));
+            assert_eq!(rs_remainder(payload, &divisor), ecc, "block {b}");
+            data.extend_from_slice(payload);
+        }
+
+        // Байтовый режим: 0100, длина, данные
+        let bit = |i: usize| (data[i / 8] >> (7 - i % 8)) & 1;
+        let read = |from: usize, len: usize| {
+            (from..from + len).fold(0, |acc, i| acc << 1 | bit(i) as usize)
+        };
+        assert_eq!(read(0, 4), 0b0100);
+        let count_bits = if version <= 9 { 8 } else { 16 };
+        let len = read(4, count_bits);
+        (0..len)
+            .map(|i| read(4 + count_bits + i * 8, 8) as u8)
+            .collect()
+    }
+
+    #[test]
+    fn reed_solomon_matches_the_standard_example() {
+        // ISO/IEC 18004, приложение I: «01234567», версия 1-M
+        let data = [
+            0x10, 0x20, 0x0C, 0x56, 0x61, 0x80, 0xEC, 0x11, 0xEC, 0x11, 0xEC, 0x11, 0xEC, 0x11,
+            0xEC, 0x11,
+        ];
+        let ecc = [0xA5, 0x24, 0xD4, 0xC1, 0xED, 0x36, 0xC7, 0x87, 0x2C, 0x55];
+        assert_eq!(rs_remainder(&data, &rs_divisor(10)), ecc);
+        assert_eq!(gf_mul(0x80, 0x02), 0x1D);
+        assert_eq!(gf_mul(0x53, 0xCA), 0x8F);
+    }
+
+    #[test]
+    fn capacity_and_version_choice_for_level_m() {
+        let codewords = [(1, 16), (2, 28), (3, 44), (7, 124), (10, 216), (40, 2334)];
+        for (version, expected) in codewords {
+            assert_eq!(num_data_codewords(version), expected, "version {version}");
+        }
+        // Наибольшая длина в байтах для версий 1, 2, 9, 10 и 40
+        for (len, version) in [
+            (14, 1),
+            (15, 2),
+            (26, 2),
+            (27, 3),
+            (180, 9),
+            (181, 10),
+            (2331, 40),
+        ] {
+            let qr = QrCode::encode(&vec![b'a'; len]).unwrap();
+            assert_eq!(version_of(&qr), version, "{len} bytes");
+            assert_eq!(qr.size(), version * 4 + 17);
+        }
+        assert_eq!(
+            QrCode::encode(&[0; 2332]).err().unwrap().to_string(),
+            "data too long for a QR code (2332 bytes)"
+        );
+        assert_eq!(alignment_positions(1), Vec::<usize>::new());
+        assert_eq!(alignment_positions(2), [6, 18]);
+        assert_eq!(alignment_positions(7), [6, 22, 38]);
+        assert_eq!(alignment_positions(32), [6, 34, 60, 86, 112, 138]);
+    }
+
+    #[test]
+    fn matrix_has_finders_timing_and_valid_format() {
+        let qr = QrCode::encode(b"otpauth://totp/pm:alice?secret=JBSWY3DPEHPK3PXP").unwrap();
+        let n = qr.size();
+        for (cx, cy) in [(3, 3), (n - 4, 3), (3, n - 4)] {
+            for d in 0..7 {
+                let ring = |x: usize, y: usize| dark(&qr, cx + x - 3, cy + y - 3);
+                // Внешняя рамка тёмная, следующее кольцо светлое, центр 3x3 тёмный
+                assert!(ring(d, 0) && ring(0, d) && ring(d, 6) && ring(6, d));
+                if (1..6).contains(&d) {
+                    assert!(!ring(d, 1) && !ring(1, d) && !ring(d, 5) && !ring(5, d));
+                }
+            }
+            assert!(dark(&qr, cx, cy));
+        }
+        for i in 8..n - 8 {
+            assert_eq!(dark(&qr, i, 6), i % 2 == 0);
+            assert_eq!(dark(&qr, 6, i), i % 2 == 0);
+        }
+        assert!(dark(&qr, 8, n - 8), "dark module");
+        let (first, second) = format_bits(&qr);
+        assert_eq!(first, second);
+        assert!(FORMAT_M.contains(&first), "{first:015b}");
+    }
+
+    #[test]
+    fn version_information_from_version_7() {
+        let qr = QrCode::encode(&[b'x'; 110]).unwrap();
+        assert_eq!(version_of(&qr), 7);
+        let n = qr.size();
+        let (mut lower_left, mut upper_right) = (0u32, 0u32);
+        for i in 0..18 {
+            upper_right |= (dark(&qr, n - 11 + i % 3, i / 3) as u32) << i;
+            lower_left |= (dark(&qr, i / 3, n - 11 + i % 3) as u32) << i;
+        }
+        // ISO/IEC 18004, табл. D.1
+        assert_eq!(upper_right, 0x07C94);
+        assert_eq!(lower_left, 0x07C94);
+    }
+
+    #[test]
+    fn encoded_data_reads_back() {
+        let samples: [&[u8]; 5] = [
+            b"",
+            b"WIFI:T:WPA;S:home;P:p@ss;;",
+            "пароль ✓".as_bytes(),
+            &[0xFF; 300],
+            &[0x5A; 1200],
+        ];
+        for data in samples {
+            let qr = QrCode::encode(data).unwrap();
+            assert_eq!(decode(&qr), data, "{} bytes", data.len());
+        }
+    }
+}
@@ -0,0 +1,154 @@
+//! Распознавание QR-кода с картинки для `pm otp add --qr-image`.
+//!
+//! Как буфер обмена и связка ключей — через системную утилиту: `zbarimg`
+//! из zbar-tools. Картинка со стандартного ввода (`-`) уходит ей через
+//! наследованный stdin, на диск pm её не пишет.
+
+use anyhow::{Result, anyhow};
+use std::process::{Command, Stdio};
+
+/// Прочитать единственный QR-код на картинке и вернуть его otpauth:// URL.
+pub fn decode_otpauth(image: &str) -> Result<String> {
+    pick_otpauth(image, &scan("zbarimg", image)?)
+}
+
+/// Из распознанных кодов — единственный otpauth:// URL.
+fn pick_otpauth(image: &str, codes: &[String]) -> Result<String> {
+    let payload = match codes {
+        [] => return Err(anyhow!("no QR code found in {image}")),
+        [one] => one,
+        many => {
+            return Err(anyhow!(
+                "found {} QR codes in {image}; crop the image to the one you want",
+                many.len()
+            ));
+        }
+    };
+    if payload.starts_with("otpauth-migration://") {
+        return Err(anyhow!(
+            "this is a Google Authenticator export QR, not a single otpauth:// URL"
+        ));
+    }
+    if !payload.starts_with("otpauth://") {
+        // Содержимое не печатаем: на картинке может быть что угодно
+        return Err(anyhow!("the QR code does not contain an otpauth:// URL"));
+    }
+    Ok(payload.clone())
+}
+
+/// Все QR-коды на картинке, по одному на строку вывода zbarimg.
+fn scan(zbarimg: &str, image: &str) -> Result<Vec<String>> {
+    let (path, stdin) = if image == "-" {
+        if cfg!(not(unix)) {
+            return Err(anyhow!("--qr-image - is only supported on Unix"));
+        }
+        ("/dev/stdin", Stdio::inherit())
+    } else {
+        (image, Stdio::null())
+    };
+
+    let out = Command::new(zbarimg)
+        .args(["--quiet", "--raw", "-Sdisable", "-Sqrcode.enable", path])
+        .stdin(stdin)
+        .output()
+        .map_err(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                anyhow!("zbarimg is not installed; install zbar-tools (zbar on macOS)")
+            } else {
+                anyhow!("failed to spawn zbarimg: {e}")
+            }
+        })?;
+
+    // 4 — картинка прочитана, но кодов на ней нет
+    match out.status.code() {
+        Some(0) => {}
+        Some(4) => return Ok(Vec::new()),
+        _ => {
+            return Err(anyhow!(
+                "zbarimg could not read {image}: {}",
+                String::from_utf8_lossy(&out.stderr).trim()
+            ));
+        }
+    }
+    Ok(parse_output(&out.stdout))
+}
+
+fn parse_output(stdout: &[u8]) -> Vec<String> {
+    String::from_utf8_lossy(stdout)
+        .lines()
+        .map(|l| l.trim().to_string())
+        .filter(|l| !l.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const URI: &str = "otpauth://totp/Example:alice?secret=JBSWY3DPEHPK3PXP&issuer=Example";
+
+    #[test]
+    fn zbarimg_output_is_one_code_per_line() {
+        let out = format!("{URI}\r\n\n  \n");
+        assert_eq!(parse_output(out.as_bytes()), [URI]);
+        assert_eq!(parse_output(b""), Vec::<String>::new());
+        let two = parse_output(format!("{URI}\nhttps://example.com\n").as_bytes());
+        assert_eq!(two.len(), 2);
+    }
+
+    #[test]
+    fn only_a_single_otpauth_url_is_accepted() {
+        let codes = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(pick_otpauth("qr.png", &codes(&[URI])).unwrap(), URI);
+
+        let err = |list: &[&str]| {
+            pick_otpauth("qr.png", &codes(list))
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(err(&[]), "no QR code found in qr.png");
+        assert_eq!(
+            err(&[URI, URI]),
+            "found 2 QR codes in qr.png; crop the image to the one you want"
+        );
+        assert!(err(&["otpauth-migration://offline?data=AAAA"]).contains("Google Authenticator"));
+        // Чужое содержимое в сообщение не попадает
+        let other = err(&["WIFI:S:home;T:WPA;P:secret;;"]);
+        assert_eq!(other, "the QR code does not contain an otpauth:// URL");
+    }
+
+    #[test]
+    fn missing_zbarimg_names_the_package() {
+        let err = scan("pm-test-no-such-zbarimg", "qr.png").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "zbarimg is not installed; install zbar-tools (zbar on macOS)"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn zbarimg_exit_codes() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("pm-qrscan-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let fake = |name: &str, body: &str| {
+            let path = dir.join(name);
+            std::fs::write(&path, format!("#!/bin/sh\n{body}\n")).unwrap();
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+            path.to_str().unwrap().to_string()
+        };
+
+        let found = fake("found", &format!("echo '{URI}'"));
+        assert_eq!(scan(&found, "qr.png").unwrap(), [URI]);
+        let empty = fake("empty", "exit 4");
+        assert!(scan(&empty, "qr.png").unwrap().is_empty());
+        let broken = fake("broken", "echo 'unsupported image format' >&2; exit 2");
+        assert_eq!(
+            scan(&broken, "qr.png").unwrap_err().to_string(),
+            "zbarimg could not read qr.png: unsupported image format"
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
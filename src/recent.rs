@@ -0,0 +1,141 @@
+//! Журнал обращений к записям для `pm recent`.
+//!
+//! Файл `access.log` в корне хранилища: одна строка на обращение,
+//! каждая строка — отдельно зашифрованная мастер-ключом запись
+//! (`nonce ciphertext` в base64), поэтому дописывание не требует
+//! перешифровки всего файла, а по диску не видно, что и когда открывали.
+
+use crate::config::Config;
+use crate::crypto::{MasterKey, decrypt_entry, encrypt_entry};
+use crate::session::get_master_key_with_cache;
+use crate::store::{store_root, write_atomic_private};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use time::OffsetDateTime;
+
+/// Сколько строк храним; при превышении старые отбрасываются.
+const MAX_RECORDS: usize = 500;
+
+/// `--no-touch`: не писать в журнал в этом запуске.
+static NO_TOUCH: AtomicBool = AtomicBool::new(false);
+
+pub fn set_no_touch(v: bool) {
+    NO_TOUCH.store(v, Ordering::Relaxed);
+}
+
+#[derive(Serialize, Deserialize)]
+struct Record {
+    path: String,
+    ts: String,
+    action: String,
+}
+
+fn log_path() -> Result<PathBuf> {
+    Ok(store_root()?.join("access.log"))
+}
+
+fn encrypt_line(mk: &MasterKey, rec: &Record) -> Result<String> {
+    let (nonce, ct) = encrypt_entry(mk, &serde_json::to_vec(rec)?, &[])?;
+    Ok(format!("{nonce} {ct}\n"))
+}
+
+fn read_records(mk: &MasterKey) -> Result<Vec<Record>> {
+    let data = match std::fs::read_to_string(log_path()?) {
+        Ok(d) => d,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    // Повреждённые строки (например, оборванная запись) просто пропускаем
+    Ok(data
+        .lines()
+        .filter_map(|line| {
+            let (nonce, ct) = line.split_once(' ')?;
+            let plain = decrypt_entry(mk, nonce, ct, &[]).ok()?;
+            serde_json::from_slice(&plain).ok()
+        })
+        .collect())
+}
+
+fn append(mk: &MasterKey, path: &str, action: &str) -> Result<()> {
+    let rec = Record {
+        path: path.to_string(),
+        ts: OffsetDateTime::now_utc().format(&time::format_description::well_known::Rfc3339)?,
+        action: action.to_string(),
+    };
+    let file = log_path()?;
+
+    let mut opts = std::fs::OpenOptions::new();
+    opts.create(true).append(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        opts.mode(0o600);
+    }
+    opts.open(&file)?
+        .write_all(encrypt_line(mk, &rec)?.as_bytes())?;
+
+    // Подрезаем журнал с запасом, чтобы не переписывать его на каждом обращении
+    let lines = std::fs::read_to_string(&file)?.lines().count();
+    if lines > MAX_RECORDS + MAX_RECORDS / 10 {
+        let records = read_records(mk)?;
+        let keep = &records[records.len().saturating_sub(MAX_RECORDS)..];
+        let mut out = String::new();
+        for r in keep {
+            out.push_str(&encrypt_line(mk, r)?);
+        }
+        write_atomic_private(&file, out.as_bytes())?;
+    }
+    Ok(())
+}
+
+/// Перешифровать журнал другим ключом (для `pm rekey`) и записать в `dest`.
+/// Нечитаемые строки отбрасываются, как и при обычном чтении.
+pub fn reencrypt_log(old_key: &MasterKey, new_key: &MasterKey, dest: &Path) -> Result<()> {
+    if !log_path()?.exists() {
+        return Ok(());
+    }
+    let mut out = String::new();
+    for r in read_records(old_key)? {
+        out.push_str(&encrypt_line(new_key, &r)?);
+    }
+    write_atomic_private(dest, out.as_bytes())
+}
+
+/// Отметить успешное чтение записи. Ошибки журнала не мешают основной команде.
+pub fn record_access(config: &Config, mk: &MasterKey, path: &str, action: &str) {
+    if NO_TOUCH.load(Ordering::Relaxed) || !config.settings.history.enabled {
+        return;
+    }
+    let _ = append(mk, path, action);
+}
+
+/// pm recent [--limit N] [--clear]
+pub fn recent(limit: usize, clear: bool) -> Result<()> {
+    if clear {
+        match std::fs::remove_file(log_path()?) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        println!("Access history cleared.");
+        return Ok(());
+    }
+
+    let config = Config::load()?;
+    let mk = get_master_key_with_cache(&config)?;
+
+    // Каждая запись один раз — по последнему обращению
+    let mut seen = std::collections::HashSet::new();
+    for rec in read_records(&mk)?.iter().rev() {
+        if seen.len() >= limit {
+            break;
+        }
+        if seen.insert(rec.path.as_str()) {
+            println!("{}  {:<6} {}", rec.ts, rec.action, rec.path);
+        }
+    }
+    Ok(())
+}
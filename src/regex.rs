@@ -0,0 +1,615 @@
+//! Небольшой движок регулярных выражений для `pm find --regex` и `pm grep --regex`.
+//!
+//! Крейта `regex` в наборе зависимостей нет, поэтому здесь компактная Pike VM:
+//! время поиска линейно по длине текста и размеру программы, и шаблоны вроде
+//! `(a*)*b` не приводят к экспоненциальному перебору.
+//!
+//! Поддерживается: литералы, `.`, классы `[a-z]` / `[^...]`, `\d \w \s`
+//! (и `\D \W \S`), `\b \B`, `^`, `$`, группы `(...)` и `(?:...)`, `|`,
+//! квантификаторы `* + ? {m} {m,} {m,n}` (ленивые — с `?`), флаг `(?i)` в начале.
+
+use anyhow::{Result, anyhow};
+
+/// Предел размера скомпилированной программы (защита от `(a{100}){100}` и т.п.).
+const MAX_PROGRAM: usize = 10_000;
+/// Предел для чисел в `{m,n}`.
+const MAX_REPEAT: u32 = 1000;
+/// Предел вложенности групп.
+const MAX_DEPTH: usize = 64;
+
+#[derive(Clone, Debug)]
+struct Class {
+    ranges: Vec<(char, char)>,
+    negated: bool,
+}
+
+impl Class {
+    fn of(ranges: &[(char, char)], negated: bool) -> Self {
+        Class {
+            ranges: ranges.to_vec(),
+            negated,
+        }
+    }
+
+    fn matches(&self, c: char, ci: bool) -> bool {
+        let hit = |c: char| self.ranges.iter().any(|&(a, b)| a <= c && c <= b);
+        let mut m = hit(c);
+        if !m && ci {
+            m = c.to_lowercase().any(hit) || c.to_uppercase().any(hit);
+        }
+        m != self.negated
+    }
+}
+
+const DIGIT: &[(char, char)] = &[('0', '9')];
+const WORD: &[(char, char)] = &[('0', '9'), ('A', 'Z'), ('_', '_'), ('a', 'z')];
+const SPACE: &[(char, char)] = &[('\t', '\r'), (' ', ' ')];
+
+#[derive(Clone, Debug)]
+enum Node {
+    Empty,
+    Char(char),
+    Any,
+    Class(Class),
+    Start,
+    End,
+    WordBoundary(bool),
+    Concat(Vec<Node>),
+    Alt(Vec<Node>),
+    Repeat {
+        node: Box<Node>,
+        min: u32,
+        max: Option<u32>,
+        greedy: bool,
+    },
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn next(&mut self) -> Result<char> {
+        let c = self
+            .peek()
+            .ok_or_else(|| anyhow!("unexpected end of pattern"))?;
+        self.pos += 1;
+        Ok(c)
+    }
+
+    fn alt(&mut self, depth: usize) -> Result<Node> {
+        if depth > MAX_DEPTH {
+            return Err(anyhow!("pattern is nested too deeply"));
+        }
+        let mut branches = vec![self.concat(depth)?];
+        while self.eat('|') {
+            branches.push(self.concat(depth)?);
+        }
+        Ok(if branches.len() == 1 {
+            branches.pop().unwrap()
+        } else {
+            Node::Alt(branches)
+        })
+    }
+
+    fn concat(&mut self, depth: usize) -> Result<Node> {
+        let mut items = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let atom = self.atom(depth)?;
+            items.push(self.repeat(atom)?);
+        }
+        Ok(match items.len() {
+            0 => Node::Empty,
+            1 => items.pop().unwrap(),
+            _ => Node::Concat(items),
+        })
+    }
+
+    fn number(&mut self) -> Option<u32> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        let s: String = self.chars[start..self.pos].iter().collect();
+        s.parse().ok()
+    }
+
+    /// `{m}`, `{m,}`, `{m,n}`; если после `{` не число — это литерал `{`.
+    fn braces(&mut self) -> Result<Option<(u32, Option<u32>)>> {
+        let save = self.pos;
+        self.pos += 1;
+        let Some(min) = self.number() else {
+            self.pos = save;
+            return Ok(None);
+        };
+        let max = if self.eat(',') {
+            self.number()
+        } else {
+            Some(min)
+        };
+        if !self.eat('}') {
+            self.pos = save;
+            return Ok(None);
+        }
+        if min > MAX_REPEAT || max.is_some_and(|m| m > MAX_REPEAT) {
+            return Err(anyhow!("repetition count exceeds {MAX_REPEAT}"));
+        }
+        if max.is_some_and(|m| m < min) {
+            return Err(anyhow!("invalid repetition {{{min},{}}}", max.unwrap()));
+        }
+        Ok(Some((min, max)))
+    }
+
+    fn repeat(&mut self, mut node: Node) -> Result<Node> {
+        loop {
+            let (min, max) = match self.peek() {
+                Some('{') => match self.braces()? {
+                    Some(r) => r,
+                    None => return Ok(node),
+                },
+                Some(c @ ('*' | '+' | '?')) => {
+                    self.pos += 1;
+                    match c {
+                        '*' => (0, None),
+                        '+' => (1, None),
+                        _ => (0, Some(1)),
+                    }
+                }
+                _ => return Ok(node),
+            };
+            if matches!(node, Node::Start | Node::End | Node::WordBoundary(_)) {
+                return Err(anyhow!("nothing to repeat before position {}", self.pos));
+            }
+            let greedy = !self.eat('?');
+            node = Node::Repeat {
+                node: Box::new(node),
+                min,
+                max,
+                greedy,
+            };
+        }
+    }
+
+    fn atom(&mut self, depth: usize) -> Result<Node> {
+        let c = self.next()?;
+        Ok(match c {
+            '(' => {
+                if self.eat('?') && !self.eat(':') {
+                    return Err(anyhow!("unsupported group syntax at position {}", self.pos));
+                }
+                let inner = self.alt(depth + 1)?;
+                if !self.eat(')') {
+                    return Err(anyhow!("unclosed group"));
+                }
+                inner
+            }
+            '[' => Node::Class(self.class()?),
+            '.' => Node::Any,
+            '^' => Node::Start,
+            '$' => Node::End,
+            '*' | '+' | '?' => {
+                return Err(anyhow!("nothing to repeat before position {}", self.pos));
+            }
+            '\\' => self.escape()?,
+            c => Node::Char(c),
+        })
+    }
+
+    fn escape(&mut self) -> Result<Node> {
+        let c = self.next()?;
+        Ok(match c {
+            'd' => Node::Class(Class::of(DIGIT, false)),
+            'D' => Node::Class(Class::of(DIGIT, true)),
+            'w' => Node::Class(Class::of(WORD, false)),
+            'W' => Node::Class(Class::of(WORD, true)),
+            's' => Node::Class(Class::of(SPACE, false)),
+            'S' => Node::Class(Class::of(SPACE, true)),
+            'b' => Node::WordBoundary(true),
+            'B' => Node::WordBoundary(false),
+            c => Node::Char(literal_escape(c)?),
+        })
+    }
+
+    fn class(&mut self) -> Result<Class> {
+        let negated = self.eat('^');
+        let mut ranges = Vec::new();
+        let mut first = true;
+        loop {
+            let c = self
+                .next()
+                .map_err(|_| anyhow!("unclosed character class"))?;
+            if c == ']' && !first {
+                break;
+            }
+            first = false;
+            let lo = if c == '\\' {
+                match self.next()? {
+                    'd' => {
+                        ranges.extend_from_slice(DIGIT);
+                        continue;
+                    }
+                    'w' => {
+                        ranges.extend_from_slice(WORD);
+                        continue;
+                    }
+                    's' => {
+                        ranges.extend_from_slice(SPACE);
+                        continue;
+                    }
+                    e => literal_escape(e)?,
+                }
+            } else {
+                c
+            };
+            if self.peek() == Some('-') && self.chars.get(self.pos + 1).is_some_and(|&n| n != ']') {
+                self.pos += 1;
+                let hi = match self.next()? {
+                    '\\' => literal_escape(self.next()?)?,
+                    h => h,
+                };
+                if hi < lo {
+                    return Err(anyhow!("invalid class range {lo}-{hi}"));
+                }
+                ranges.push((lo, hi));
+            } else {
+                ranges.push((lo, lo));
+            }
+        }
+        Ok(Class { ranges, negated })
+    }
+}
+
+fn literal_escape(c: char) -> Result<char> {
+    Ok(match c {
+        'n' => '\n',
+        't' => '\t',
+        'r' => '\r',
+        c if c.is_ascii_alphanumeric() => return Err(anyhow!("unknown escape \\{c}")),
+        c => c,
+    })
+}
+
+#[derive(Clone, Debug)]
+enum Inst {
+    Char(char),
+    Any,
+    Class(Class),
+    Start,
+    End,
+    WordBoundary(bool),
+    Split(usize, usize),
+    Jmp(usize),
+    Match,
+}
+
+struct Compiler {
+    prog: Vec<Inst>,
+}
+
+impl Compiler {
+    fn push(&mut self, inst: Inst) -> Result<usize> {
+        if self.prog.len() >= MAX_PROGRAM {
+            return Err(anyhow!("pattern is too large"));
+        }
+        self.prog.push(inst);
+        Ok(self.prog.len() - 1)
+    }
+
+    fn emit(&mut self, node: &Node) -> Result<()> {
+        match node {
+            Node::Empty => {}
+            Node::Char(c) => {
+                self.push(Inst::Char(*c))?;
+            }
+            Node::Any => {
+                self.push(Inst::Any)?;
+            }
+            Node::Class(c) => {
+                self.push(Inst::Class(c.clone()))?;
+            }
+            Node::Start => {
+                self.push(Inst::Start)?;
+            }
+            Node::End => {
+                self.push(Inst::End)?;
+            }
+            Node::WordBoundary(b) => {
+                self.push(Inst::WordBoundary(*b))?;
+            }
+            Node::Concat(items) => {
+                for n in items {
+                    self.emit(n)?;
+                }
+            }
+            Node::Alt(branches) => {
+                let mut jumps = Vec::new();
+                for (i, b) in branches.iter().enumerate() {
+                    if i + 1 < branches.len() {
+                        let split = self.push(Inst::Split(0, 0))?;
+                        self.emit(b)?;
+                        jumps.push(self.push(Inst::Jmp(0))?);
+                        let next = self.prog.len();
+                        self.prog[split] = Inst::Split(split + 1, next);
+                    } else {
+                        self.emit(b)?;
+                    }
+                }
+                let end = self.prog.len();
+                for j in jumps {
+                    self.prog[j] = Inst::Jmp(end);
+                }
+            }
+            Node::Repeat {
+                node,
+                min,
+                max,
+                greedy,
+            } => {
+                for _ in 0..*min {
+                    self.emit(node)?;
+                }
+                match max {
+                    None => {
+                        let split = self.push(Inst::Split(0, 0))?;
+                        self.emit(node)?;
+                        self.push(Inst::Jmp(split))?;
+                        let exit = self.prog.len();
+                        self.prog[split] = self.split(split + 1, exit, *greedy);
+                    }
+                    Some(max) => {
+                        let mut splits = Vec::new();
+                        for _ in *min..*max {
+                            splits.push(self.push(Inst::Split(0, 0))?);
+                            self.emit(node)?;
+                        }
+                        let exit = self.prog.len();
+                        for s in splits {
+                            self.prog[s] = self.split(s + 1, exit, *greedy);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn split(&self, body: usize, skip: usize, greedy: bool) -> Inst {
+        if greedy {
+            Inst::Split(body, skip)
+        } else {
+            Inst::Split(skip, body)
+        }
+    }
+}
+
+/// Скомпилированное регулярное выражение.
+#[derive(Debug)]
+pub struct Regex {
+    prog: Vec<Inst>,
+    ci: bool,
+}
+
+fn is_word(c: Option<char>) -> bool {
+    c.is_some_and(|c| c.is_alphanumeric() || c == '_')
+}
+
+fn eq_ci(a: char, b: char) -> bool {
+    a == b || a.to_lowercase().eq(b.to_lowercase())
+}
+
+impl Regex {
+    /// Разобрать шаблон. `(?i)` в начале включает нечувствительность к регистру
+    /// так же, как `case_insensitive`.
+    pub fn new(pattern: &str, case_insensitive: bool) -> Result<Regex> {
+        let (pattern, ci) = match pattern.strip_prefix("(?i)") {
+            Some(rest) => (rest, true),
+            None => (pattern, case_insensitive),
+        };
+        let mut parser = Parser {
+            chars: pattern.chars().collect(),
+            pos: 0,
+        };
+        let ast = parser.alt(0)?;
+        if parser.pos < parser.chars.len() {
+            return Err(anyhow!("unmatched ')' at position {}", parser.pos + 1));
+        }
+        let mut c = Compiler { prog: Vec::new() };
+        c.emit(&ast)?;
+        c.push(Inst::Match)?;
+        Ok(Regex { prog: c.prog, ci })
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        self.find(text).is_some()
+    }
+
+    /// Самое левое совпадение (байтовые смещения начала и конца).
+    pub fn find(&self, text: &str) -> Option<(usize, usize)> {
+        let chars: Vec<(usize, char)> = text.char_indices().collect();
+        let n = chars.len();
+        let byte_at = |i: usize| chars.get(i).map_or(text.len(), |&(b, _)| b);
+        let char_at = |i: usize| chars.get(i).map(|&(_, c)| c);
+
+        // Поток: (pc, начало совпадения в символах)
+        let mut clist: Vec<(usize, usize)> = Vec::new();
+        let mut nlist: Vec<(usize, usize)> = Vec::new();
+        let mut marks = vec![usize::MAX; self.prog.len()];
+        let mut matched = None;
+
+        for i in 0..=n {
+            if matched.is_none() {
+                self.add_thread(&mut clist, &mut marks, 0, i, i, &char_at);
+            }
+            if clist.is_empty() {
+                if matched.is_some() {
+                    break;
+                }
+                continue;
+            }
+            let c = char_at(i);
+            for &(pc, start) in &clist {
+                let advance = match &self.prog[pc] {
+                    Inst::Char(want) => {
+                        c.is_some_and(|c| if self.ci { eq_ci(c, *want) } else { c == *want })
+                    }
+                    Inst::Any => c.is_some_and(|c| c != '\n'),
+                    Inst::Class(cls) => c.is_some_and(|c| cls.matches(c, self.ci)),
+                    Inst::Match => {
+                        matched = Some((byte_at(start), byte_at(i)));
+                        // Потоки ниже по приоритету больше не нужны
+                        break;
+                    }
+                    _ => false,
+                };
+                if advance {
+                    self.add_thread(&mut nlist, &mut marks, pc + 1, start, i + 1, &char_at);
+                }
+            }
+            std::mem::swap(&mut clist, &mut nlist);
+            nlist.clear();
+        }
+        matched
+    }
+
+    /// Добавить поток, пройдя переходы и проверки позиции, в порядке приоритета.
+    fn add_thread(
+        &self,
+        list: &mut Vec<(usize, usize)>,
+        marks: &mut [usize],
+        pc: usize,
+        start: usize,
+        pos: usize,
+        char_at: &dyn Fn(usize) -> Option<char>,
+    ) {
+        let mut stack = vec![pc];
+        while let Some(pc) = stack.pop() {
+            if marks[pc] == pos {
+                continue;
+            }
+            marks[pc] = pos;
+            match self.prog[pc] {
+                Inst::Jmp(to) => stack.push(to),
+                Inst::Split(a, b) => {
+                    stack.push(b);
+                    stack.push(a);
+                }
+                Inst::Start => {
+                    if pos == 0 {
+                        stack.push(pc + 1);
+                    }
+                }
+                Inst::End => {
+                    if char_at(pos).is_none() {
+                        stack.push(pc + 1);
+                    }
+                }
+                Inst::WordBoundary(want) => {
+                    let prev = pos.checked_sub(1).and_then(char_at);
+                    if (is_word(prev) != is_word(char_at(pos))) == want {
+                        stack.push(pc + 1);
+                    }
+                }
+                _ => list.push((pc, start)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, text: &str) -> bool {
+        Regex::new(pattern, false).unwrap().is_match(text)
+    }
+
+    #[test]
+    fn anchoring() {
+        // Без якорей шаблон ищется в любом месте строки
+        assert!(matches("prod", "work/db-prod"));
+        assert!(matches("prod", "prod/db"));
+        assert!(matches("-prod$", "work/db-prod"));
+        assert!(!matches("-prod$", "work/db-prod/old"));
+        assert!(matches("^work/", "work/db"));
+        assert!(!matches("^work/", "home/work/db"));
+        assert!(matches("^work/db$", "work/db"));
+        assert!(!matches("^work/db$", "work/db2"));
+        assert!(matches("^$", ""));
+        assert!(!matches("^$", "x"));
+        assert!(matches(r"\bdb\b", "work/db-prod"));
+        assert!(!matches(r"\bdb\b", "work/dbx"));
+        assert_eq!(
+            Regex::new("b+", false).unwrap().find("aabbbc"),
+            Some((2, 5))
+        );
+    }
+
+    #[test]
+    fn case_insensitivity() {
+        assert!(!matches("^ADMIN", "admin@corp"));
+        assert!(matches("(?i)^ADMIN", "admin@corp"));
+        assert!(Regex::new("^ADMIN", true).unwrap().is_match("admin@corp"));
+        assert!(matches("(?i)[а-я]+", "ПАРОЛЬ"));
+    }
+
+    #[test]
+    fn syntax() {
+        assert!(matches(r"TICKET-\d{3,5}", "see TICKET-1234 today"));
+        assert!(!matches(r"TICKET-\d{3,5}$", "TICKET-12"));
+        assert!(matches("(?:dev|stage)-db", "stage-db"));
+        assert!(matches("[^a-z]x", "1x"));
+        assert!(!matches("[^a-z]x", "ax"));
+        assert!(matches(r"a\.b", "a.b"));
+        assert!(!matches(r"a\.b", "axb"));
+    }
+
+    #[test]
+    fn invalid_patterns_report_the_problem() {
+        let err = |p: &str| Regex::new(p, false).unwrap_err().to_string();
+        assert_eq!(err("(ab"), "unclosed group");
+        assert_eq!(err("ab)"), "unmatched ')' at position 3");
+        assert_eq!(err("[a-"), "unclosed character class");
+        assert_eq!(err("[z-a]"), "invalid class range z-a");
+        assert_eq!(err("*a"), "nothing to repeat before position 1");
+        assert_eq!(err(r"\q"), "unknown escape \\q");
+        assert_eq!(err("a{1001}"), "repetition count exceeds 1000");
+    }
+
+    #[test]
+    fn pathological_patterns_are_bounded() {
+        // Программа растёт как произведение повторов — отказ, а не гигабайты
+        assert_eq!(
+            Regex::new("(a{100}){100}", false).unwrap_err().to_string(),
+            "pattern is too large"
+        );
+        assert_eq!(
+            Regex::new(&"(".repeat(MAX_DEPTH + 1), false)
+                .unwrap_err()
+                .to_string(),
+            "pattern is nested too deeply"
+        );
+        // Для бэктрекинга это экспоненциальный перебор; Pike VM — линейна
+        let text = "a".repeat(5000);
+        let started = std::time::Instant::now();
+        assert!(!matches("(a*)*b", &text));
+        assert!(!matches("(a|aa)+$x", &text));
+        assert!(started.elapsed() < std::time::Duration::from_secs(5));
+    }
+}
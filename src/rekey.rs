@@ -0,0 +1,206 @@
+//! `pm rekey`: заменить сам master key и перешифровать им всё хранилище.
+//!
+//! Новое хранилище целиком собирается рядом, в `<имя корня>.rekey-<pid>`.
+//! Только когда все записи перешифрованы, оно подменяет текущее так же,
+//! как при `pm backup restore`, а прежнее остаётся как `pm-store.bak-<время>`.
+//! Прерывание до подмены текущее хранилище не затрагивает.
+
+use crate::backup::swap_into_place;
+use crate::config::{Config, save_config};
+use crate::crypto::{MasterKey, rewrap_master_key, unlock_master_key};
+use crate::prompt::prompt_password_hidden;
+use crate::recent::reencrypt_log;
+use crate::session::clear_session;
+use crate::store::{create_private_dir_all, list_entries, par_map, reencrypt_entry, store_root};
+use anyhow::{Context, Result, anyhow};
+use rand::RngCore;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Эти файлы в корне пишутся заново, а не копируются.
+const REBUILT: &[&str] = &[
+    "config.json",
+    "access.log",
+    "session.json",
+    "index.enc",
+    ".lock",
+];
+
+/// Скопировать всё, что не зашифровано мастер-ключом: хуки, журналы и т.п.
+fn copy_plain_files(root: &Path, staging: &Path) -> Result<()> {
+    for item in walkdir::WalkDir::new(root).min_depth(1) {
+        let item = item?;
+        let rel = item.path().strip_prefix(root)?;
+        // Файлы записей в store/ и в корзине перешифровываются отдельно
+        let encrypted = rel.starts_with("store") || rel.starts_with("trash");
+        let skip = if encrypted {
+            item.path().extension().is_some_and(|e| e == "enc")
+        } else {
+            REBUILT.iter().any(|n| rel == Path::new(n))
+        };
+        if skip {
+            continue;
+        }
+        let dest = staging.join(rel);
+        if item.file_type().is_dir() {
+            create_private_dir_all(&dest)?;
+        } else if item.file_type().is_file() {
+            std::fs::copy(item.path(), &dest)
+                .with_context(|| format!("cannot copy {}", item.path().display()))?;
+        }
+    }
+    Ok(())
+}
+
+fn build_staging(
+    root: &Path,
+    staging: &Path,
+    config: &Config,
+    old_key: &MasterKey,
+    new_key: &MasterKey,
+) -> Result<usize> {
+    create_private_dir_all(&staging.join("store"))?;
+    copy_plain_files(root, staging)?;
+
+    let paths = list_entries()?;
+    let total = paths.len();
+    let live = std::io::stderr().is_terminal();
+    let finished = AtomicUsize::new(0);
+    let results = par_map(paths, |path| {
+        let res = reencrypt_entry(path, old_key, new_key, &staging.join("store")).and_then(|_| {
+            crate::attachments::reencrypt(path, old_key, new_key, &staging.join("store"))
+        });
+        let n = finished.fetch_add(1, Ordering::Relaxed) + 1;
+        if live {
+            eprint!("\rRe-encrypted {n}/{total} entries");
+            let _ = std::io::stderr().flush();
+        }
+        res
+    });
+    if live {
+        eprintln!();
+    }
+    for (path, res) in results {
+        res.with_context(|| format!("cannot re-encrypt {path}"))?;
+    }
+
+    crate::trash::reencrypt_trash(root, staging, old_key, new_key)?;
+    reencrypt_log(old_key, new_key, &staging.join("access.log"))?;
+    save_config(config, &staging.join("config.json"))?;
+    Ok(total)
+}
+
+/// Каталог сборки рядом с корнем: `~/.pm-store` → `~/.pm-store.rekey-<pid>`.
+fn staging_dir(root: &Path) -> Result<PathBuf> {
+    let name = root
+        .file_name()
+        .ok_or_else(|| anyhow!("store root {} has no directory name", root.display()))?;
+    let mut name = name.to_os_string();
+    name.push(format!(".rekey-{}", std::process::id()));
+    Ok(root.with_file_name(name))
+}
+
+/// Удалить каталоги сборки, брошенные прерванным `pm rekey`.
+/// Команда держит блокировку хранилища на запись, так что чужих живых среди них нет.
+fn remove_stale_staging(root: &Path) -> Result<()> {
+    let (Some(parent), Some(name)) = (root.parent(), root.file_name()) else {
+        return Ok(());
+    };
+    let prefix = format!("{}.rekey-", name.to_string_lossy());
+    let Ok(dir) = std::fs::read_dir(parent) else {
+        return Ok(());
+    };
+    for item in dir.flatten() {
+        let file_name = item.file_name();
+        let Some(pid) = file_name.to_str().and_then(|n| n.strip_prefix(&prefix)) else {
+            continue;
+        };
+        if pid.is_empty() || !pid.bytes().all(|b| b.is_ascii_digit()) {
+            continue;
+        }
+        std::fs::remove_dir_all(item.path())
+            .with_context(|| format!("cannot remove {}", item.path().display()))?;
+        eprintln!(
+            "Warning: removed {} left by an interrupted rekey",
+            item.path().display()
+        );
+    }
+    Ok(())
+}
+
+/// pm rekey
+pub fn rekey() -> Result<()> {
+    let root = store_root()?;
+    let mut config = Config::load()?;
+    let password = prompt_password_hidden("Master password: ")?;
+    let keyfile = crate::keyfile::current()?;
+    let old_key = unlock_master_key(&password, &config, keyfile.as_ref())?;
+
+    let mut new_key: MasterKey = [0u8; 32];
+    rand::rngs::OsRng.fill_bytes(&mut new_key);
+    rewrap_master_key(&mut config, &new_key, &password, keyfile.as_ref())?;
+
+    remove_stale_staging(&root)?;
+    let staging = staging_dir(&root)?;
+    if staging.exists() {
+        return Err(anyhow!(
+            "{} already exists; remove it and try again",
+            staging.display()
+        ));
+    }
+    let result = build_staging(&root, &staging, &config, &old_key, &new_key)
+        .and_then(|total| Ok((total, swap_into_place(&staging, &root)?)));
+    if result.is_err() {
+        let _ = std::fs::remove_dir_all(&staging);
+    }
+    let (total, previous) = result?;
+
+    crate::perms::harden_store_root(&root)?;
+    // Закэшированный ключ — старый, он больше ничего не открывает
+    clear_session()?;
+
+    println!("Master key rotated; re-encrypted {total} entries.");
+    if let Some(prev) = previous {
+        println!("Previous store preserved at {}", prev.display());
+        eprintln!(
+            "Note: the preserved copy still opens with the old master key; delete it once the new store checks out."
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn staging_sits_next_to_the_root_and_stale_ones_are_removed() {
+        let dir = std::env::temp_dir().join(format!("pm-rekey-test-{}", std::process::id()));
+        let root = dir.join(".pm-work");
+        std::fs::create_dir_all(&root).unwrap();
+        assert_eq!(
+            staging_dir(&root).unwrap(),
+            dir.join(format!(".pm-work.rekey-{}", std::process::id()))
+        );
+
+        // Брошенная сборка этого хранилища уходит; чужие и похожие имена остаются
+        let stale = dir.join(".pm-work.rekey-4242");
+        std::fs::create_dir_all(stale.join("store")).unwrap();
+        let keep = [
+            ".pm-work.rekey-notes",
+            ".pm-work.rekey-",
+            "pm-store.rekey-4242",
+        ];
+        for name in keep {
+            std::fs::create_dir_all(dir.join(name)).unwrap();
+        }
+        remove_stale_staging(&root).unwrap();
+        assert!(!stale.exists());
+        assert!(root.exists());
+        for name in keep {
+            assert!(dir.join(name).exists(), "{name}");
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,223 @@
+//! `--clear-after`: стереть показанный секрет с экрана через N секунд.
+//!
+//! Выводим текст, ждём таймаут, нажатие клавиши или Ctrl-C, затем
+//! поднимаемся курсором на выведенные строки, затираем их пробелами
+//! и очищаем экран ниже. Терминал возвращается в исходный режим
+//! в `Drop` — и при панике, и при прерывании.
+
+use std::io::{IsTerminal, Write};
+use std::time::Duration;
+
+const CLEARED_MARKER: &str = "[pm: output cleared]";
+
+/// Сколько строк терминала займёт текст с учётом переноса по ширине `cols`.
+pub fn screen_rows(text: &str, cols: Option<usize>) -> usize {
+    text.lines()
+        .map(|line| {
+            let width = line.chars().count();
+            match cols {
+                Some(c) if c > 0 && width > 0 => width.div_ceil(c),
+                _ => 1,
+            }
+        })
+        .sum()
+}
+
+/// Последовательность, затирающая `rows` строк над курсором шириной `width`.
+///
+/// `ESC[nF` — вверх на n строк в начало строки, `ESC[2K` — очистить строку,
+/// `ESC[J` — очистить всё ниже, `ESC[3J` — сбросить scrollback (xterm и совместимые).
+pub fn erase_sequence(rows: usize, width: usize) -> Vec<u8> {
+    let mut out = Vec::new();
+    if rows == 0 {
+        return out;
+    }
+    out.extend_from_slice(format!("\x1b[{rows}F").as_bytes());
+    for _ in 0..rows {
+        out.extend_from_slice(b"\x1b[2K");
+        out.extend(std::iter::repeat_n(b' ', width));
+        out.push(b'\n');
+    }
+    out.extend_from_slice(format!("\x1b[{rows}F\x1b[J\x1b[3J").as_bytes());
+    out.extend_from_slice(CLEARED_MARKER.as_bytes());
+    out.push(b'\n');
+    out
+}
+
+/// Ширина терминала в колонках, если stdout — терминал.
+pub fn terminal_columns() -> Option<usize> {
+    term::columns()
+}
+
+/// Напечатать `text` и стереть его через `secs` секунд (или по нажатию клавиши).
+/// Если stdout не терминал — просто печатаем и сообщаем, что стирания не будет.
+pub fn print_and_clear(text: &str, secs: u64) -> anyhow::Result<()> {
+    let mut stdout = std::io::stdout();
+    stdout.write_all(text.as_bytes())?;
+    stdout.flush()?;
+
+    let dumb = std::env::var("TERM").is_ok_and(|t| t == "dumb");
+    if !stdout.is_terminal() || dumb {
+        eprintln!("Notice: --clear-after ignored, stdout is not a capable terminal");
+        return Ok(());
+    }
+
+    let cols = term::columns();
+    let interrupted = {
+        let _guard = term::InputGuard::new();
+        eprint!("(clearing in {secs}s, press any key to clear now)");
+        let _ = std::io::stderr().flush();
+        let res = term::wait(Duration::from_secs(secs));
+        eprint!("\r\x1b[2K");
+        res
+    };
+
+    let width = cols.unwrap_or(80);
+    stdout.write_all(&erase_sequence(screen_rows(text, cols), width))?;
+    stdout.flush()?;
+
+    if interrupted {
+        std::process::exit(130);
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+mod term {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::{Duration, Instant};
+
+    static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+    extern "C" fn on_sigint(_: libc::c_int) {
+        INTERRUPTED.store(true, Ordering::SeqCst);
+    }
+
+    pub fn columns() -> Option<usize> {
+        // SAFETY: winsize — POD, ioctl лишь заполняет его
+        let mut ws: libc::winsize = unsafe { std::mem::zeroed() };
+        let ok = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut ws) } == 0;
+        (ok && ws.ws_col > 0).then_some(ws.ws_col as usize)
+    }
+
+    /// Неканонический режим stdin без эха + перехват SIGINT на время ожидания.
+    pub struct InputGuard {
+        saved: Option<libc::termios>,
+        old_handler: libc::sighandler_t,
+    }
+
+    impl InputGuard {
+        pub fn new() -> Self {
+            // SAFETY: стандартные вызовы termios/signal над своим же терминалом
+            unsafe {
+                let old_handler = libc::signal(
+                    libc::SIGINT,
+                    on_sigint as extern "C" fn(libc::c_int) as libc::sighandler_t,
+                );
+                let mut t: libc::termios = std::mem::zeroed();
+                let saved = (libc::tcgetattr(libc::STDIN_FILENO, &mut t) == 0).then(|| {
+                    let orig = t;
+                    t.c_lflag &= !(libc::ICANON | libc::ECHO);
+                    libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &t);
+                    orig
+                });
+                InputGuard { saved, old_handler }
+            }
+        }
+    }
+
+    impl Drop for InputGuard {
+        fn drop(&mut self) {
+            // SAFETY: возвращаем ранее сохранённые настройки
+            unsafe {
+                if let Some(ref t) = self.saved {
+                    libc::tcflush(libc::STDIN_FILENO, libc::TCIFLUSH);
+                    libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, t);
+                }
+                libc::signal(libc::SIGINT, self.old_handler);
+            }
+        }
+    }
+
+    /// Ждать таймаут, клавишу или Ctrl-C. Возвращает true, если был Ctrl-C.
+    pub fn wait(timeout: Duration) -> bool {
+        use std::io::IsTerminal;
+
+        let keys = std::io::stdin().is_terminal();
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline {
+            if INTERRUPTED.load(Ordering::SeqCst) {
+                return true;
+            }
+            if !keys {
+                std::thread::sleep(Duration::from_millis(100));
+                continue;
+            }
+            let mut fds = libc::pollfd {
+                fd: libc::STDIN_FILENO,
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            // SAFETY: один pollfd на стеке
+            let n = unsafe { libc::poll(&mut fds, 1, 100) };
+            if n > 0 && fds.revents & libc::POLLIN != 0 {
+                return false;
+            }
+        }
+        INTERRUPTED.load(Ordering::SeqCst)
+    }
+}
+
+#[cfg(not(unix))]
+mod term {
+    use std::time::Duration;
+
+    pub fn columns() -> Option<usize> {
+        None
+    }
+
+    pub struct InputGuard;
+
+    impl InputGuard {
+        pub fn new() -> Self {
+            InputGuard
+        }
+    }
+
+    pub fn wait(timeout: Duration) -> bool {
+        std::thread::sleep(timeout);
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_account_for_wrapping() {
+        assert_eq!(screen_rows("", Some(80)), 0);
+        assert_eq!(screen_rows("s3cret\n", Some(80)), 1);
+        assert_eq!(screen_rows("a\n\nb\n", Some(80)), 3);
+        assert_eq!(screen_rows(&"x".repeat(80), Some(80)), 1);
+        assert_eq!(screen_rows(&"x".repeat(81), Some(80)), 2);
+        // Ширина в символах, а не в байтах
+        assert_eq!(screen_rows(&"я".repeat(10), Some(10)), 1);
+        assert_eq!(screen_rows(&"x".repeat(500), None), 1);
+        assert_eq!(screen_rows("abc", Some(0)), 1);
+    }
+
+    #[test]
+    fn erase_sequence_bytes() {
+        assert!(erase_sequence(0, 80).is_empty());
+        let want = "\x1b[2F\x1b[2K   \n\x1b[2K   \n\x1b[2F\x1b[J\x1b[3J[pm: output cleared]\n";
+        assert_eq!(String::from_utf8(erase_sequence(2, 3)).unwrap(), want);
+    }
+
+    #[test]
+    fn erase_sequence_covers_every_column() {
+        let seq = String::from_utf8(erase_sequence(1, 120)).unwrap();
+        assert!(seq.contains(&format!("\x1b[2K{}\n", " ".repeat(120))));
+        assert!(seq.ends_with(&format!("{CLEARED_MARKER}\n")));
+    }
+}
@@ -0,0 +1,317 @@
+//! Локальный HTTP API (`pm serve`, фича `serve`).
+//!
+//! Минимальный HTTP/1.1 поверх `std::net`: запросы обрабатываются по одному,
+//! каждый требует `Authorization: Bearer <token>`. Токены выпускает
+//! `pm serve token create`, в config.json хранится только их SHA-256.
+
+use crate::config::{ApiToken, Config, config_path, save_config};
+use crate::crypto::MasterKey;
+use crate::session::get_master_key_with_cache;
+use crate::store::{list_entries, load_entry};
+use anyhow::{Context, Result, anyhow};
+use base64::{Engine as _, engine::general_purpose};
+use rand::RngCore;
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::time::Duration;
+use time::OffsetDateTime;
+
+/// Права токена: `read` — список и поля записей без пароля,
+/// `password` — ещё и пароль, `otp` — текущие OTP-коды.
+const KNOWN_SCOPES: &[&str] = &["read", "password", "otp"];
+
+const MAX_REQUEST_HEAD: u64 = 8 * 1024;
+
+fn hash_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// pm serve token create --scopes read,otp
+pub fn token_create(scopes: &[String]) -> Result<()> {
+    let scopes: Vec<String> = scopes
+        .iter()
+        .flat_map(|s| s.split(','))
+        .map(|s| s.trim().to_lowercase())
+        .filter(|s| !s.is_empty())
+        .collect();
+    if scopes.is_empty() {
+        return Err(anyhow!(
+            "at least one scope is required (read, password, otp)"
+        ));
+    }
+    if let Some(bad) = scopes.iter().find(|s| !KNOWN_SCOPES.contains(&s.as_str())) {
+        return Err(anyhow!("unknown scope '{bad}', expected read/password/otp"));
+    }
+
+    let mut raw = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut raw);
+    let token = general_purpose::URL_SAFE_NO_PAD.encode(raw);
+    let hash = hash_token(&token);
+
+    let mut config = Config::load()?;
+    config.settings.serve.tokens.push(ApiToken {
+        id: hash[..8].to_string(),
+        hash,
+        scopes: scopes.clone(),
+        created_at: OffsetDateTime::now_utc()
+            .format(&time::format_description::well_known::Rfc3339)?,
+    });
+    save_config(&config, &config_path()?)?;
+
+    println!("{token}");
+    eprintln!(
+        "Token {} created with scopes {}. It is shown only once.",
+        &config.settings.serve.tokens.last().unwrap().id,
+        scopes.join(",")
+    );
+    Ok(())
+}
+
+/// pm serve token list
+pub fn token_list() -> Result<()> {
+    let config = Config::load()?;
+    for t in &config.settings.serve.tokens {
+        println!("{}  {}  {}", t.id, t.created_at, t.scopes.join(","));
+    }
+    Ok(())
+}
+
+/// pm serve token revoke ID
+pub fn token_revoke(id: &str) -> Result<()> {
+    let mut config = Config::load()?;
+    let before = config.settings.serve.tokens.len();
+    config.settings.serve.tokens.retain(|t| t.id != id);
+    if config.settings.serve.tokens.len() == before {
+        return Err(anyhow!("No token with id {id}"));
+    }
+    save_config(&config, &config_path()?)?;
+    println!("Token {id} revoked.");
+    Ok(())
+}
+
+/// pm serve --listen ADDR [--allow-remote]
+pub fn serve(listen: &str, allow_remote: bool) -> Result<()> {
+    let addr: SocketAddr = listen
+        .parse()
+        .with_context(|| format!("invalid listen address '{listen}'"))?;
+    if !addr.ip().is_loopback() && !allow_remote {
+        return Err(anyhow!(
+            "Refusing to listen on non-loopback address {addr}; pass --allow-remote to override"
+        ));
+    }
+
+    let config = Config::load()?;
+    if config.settings.serve.tokens.is_empty() {
+        return Err(anyhow!(
+            "No API tokens configured; create one with `pm serve token create --scopes read`"
+        ));
+    }
+    let mk = get_master_key_with_cache(&config)?;
+
+    let listener = TcpListener::bind(addr)?;
+    eprintln!("Listening on http://{addr}");
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(s) => s,
+            Err(e) => {
+                eprintln!("accept error: {e}");
+                continue;
+            }
+        };
+        if let Err(e) = handle_connection(stream, &config, &mk) {
+            eprintln!("connection error: {e}");
+        }
+    }
+    Ok(())
+}
+
+struct Request {
+    method: String,
+    target: String,
+    bearer: Option<String>,
+}
+
+fn read_request(stream: &TcpStream) -> Result<Request> {
+    let mut reader = BufReader::new(stream.take(MAX_REQUEST_HEAD));
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let target = parts.next().unwrap_or_default().to_string();
+
+    let mut bearer = None;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.trim().eq_ignore_ascii_case("authorization")
+            && let Some(token) = value.trim().strip_prefix("Bearer ")
+        {
+            bearer = Some(token.trim().to_string());
+        }
+    }
+
+    Ok(Request {
+        method,
+        target,
+        bearer,
+    })
+}
+
+fn handle_connection(mut stream: TcpStream, config: &Config, mk: &MasterKey) -> Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let peer = stream.peer_addr()?;
+    let req = read_request(&stream)?;
+
+    let token = req
+        .bearer
+        .as_deref()
+        .map(hash_token)
+        .and_then(|h| config.settings.serve.tokens.iter().find(|t| t.hash == h));
+
+    let (status, body) = match token {
+        None => (401, json!({ "error": "missing or invalid bearer token" })),
+        Some(token) => route(&req, &token.scopes, mk),
+    };
+
+    eprintln!(
+        "{} {peer} token={} {} {} {status}",
+        OffsetDateTime::now_utc()
+            .format(&time::format_description::well_known::Rfc3339)
+            .unwrap_or_default(),
+        token.map(|t| t.id.as_str()).unwrap_or("-"),
+        req.method,
+        req.target,
+    );
+
+    let body = serde_json::to_vec(&body)?;
+    let reason = match status {
+        200 => "OK",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Internal Server Error",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status} {reason}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        body.len()
+    )?;
+    stream.write_all(&body)?;
+    Ok(())
+}
+
+fn route(req: &Request, scopes: &[String], mk: &MasterKey) -> (u16, Value) {
+    let has = |s: &str| scopes.iter().any(|x| x == s);
+
+    if req.method != "GET" {
+        return (405, json!({ "error": "only GET is supported" }));
+    }
+
+    let path = req.target.split('?').next().unwrap_or_default();
+    let Some(rest) = path.strip_prefix("/entries") else {
+        return (404, json!({ "error": "not found" }));
+    };
+
+    if rest.is_empty() || rest == "/" {
+        if !has("read") {
+            return (403, json!({ "error": "token lacks 'read' scope" }));
+        }
+        return match list_entries() {
+            Ok(entries) => (200, json!({ "entries": entries })),
+            Err(e) => (500, json!({ "error": e.to_string() })),
+        };
+    }
+
+    let Some(entry_path) = rest.strip_prefix('/').map(percent_decode) else {
+        return (404, json!({ "error": "not found" }));
+    };
+
+    if let Some(entry_path) = entry_path.strip_suffix("/otp") {
+        if !has("otp") {
+            return (403, json!({ "error": "token lacks 'otp' scope" }));
+        }
+        return match entry_otp(entry_path, mk) {
+            Ok(v) => (200, v),
+            Err(e) => (404, json!({ "error": e.to_string() })),
+        };
+    }
+
+    if !has("read") {
+        return (403, json!({ "error": "token lacks 'read' scope" }));
+    }
+    match entry_fields(&entry_path, mk, has("password")) {
+        Ok(v) => (200, v),
+        Err(e) => (404, json!({ "error": e.to_string() })),
+    }
+}
+
+fn entry_fields(path: &str, mk: &MasterKey, include_password: bool) -> Result<Value> {
+    if !list_entries()?.iter().any(|e| e == path) {
+        return Err(anyhow!("entry not found"));
+    }
+    let entry = load_entry(path, mk)?;
+    let mut v = serde_json::to_value(&entry)?;
+    if let Some(obj) = v.as_object_mut() {
+        if !include_password {
+            obj.remove("password");
+        }
+        obj.remove("password_history");
+        // Секрет OTP наружу не отдаём, только факт наличия
+        obj.insert("otp".to_string(), Value::Bool(entry.otp.is_some()));
+        obj.insert("path".to_string(), Value::String(path.to_string()));
+    }
+    Ok(v)
+}
+
+fn entry_otp(path: &str, mk: &MasterKey) -> Result<Value> {
+    if !list_entries()?.iter().any(|e| e == path) {
+        return Err(anyhow!("entry not found"));
+    }
+    let entry = load_entry(path, mk)?;
+    let cfg = entry
+        .otp
+        .as_ref()
+        .ok_or_else(|| anyhow!("no OTP configured"))?;
+    // Каждый HOTP-код сдвигает счётчик в записи; по read-only API этого не делаем
+    if cfg.is_hotp() {
+        return Err(anyhow!("HOTP codes are only available via `pm otp show`"));
+    }
+    let code = crate::otp::generate_otp_code(cfg)?;
+    let now = OffsetDateTime::now_utc().unix_timestamp() as u64;
+    let period = cfg.period.max(1) as u64;
+    Ok(json!({ "code": code, "expires_in": period - now % period }))
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && i + 2 < bytes.len()
+            && let Ok(b) =
+                u8::from_str_radix(std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or(""), 16)
+        {
+            out.push(b);
+            i += 3;
+            continue;
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
@@ -1,29 +1,370 @@
 use anyhow::{Result, anyhow};
-use base64::{Engine as _, engine::general_purpose};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use crate::config::Config;
-use crate::crypto::{MasterKey, unlock_master_key};
+use crate::crypto::{
+    CryptoError, MasterKey, unlock_master_key, unwrap_master_key, wrap_master_key,
+};
+use crate::keychain::master_password_from_keychain;
 use crate::prompt::prompt_password_hidden;
-use crate::store::store_root;
+use crate::store::{store_root, store_tag, write_atomic_private};
 
-const SESSION_TTL_SECS: u64 = 5 * 60;
+const DEFAULT_SESSION_TTL_SECS: u64 = 5 * 60;
+
+/// Держать сессию в системной связке ключей, а файл — только запасным
+/// вариантом: фича `keyring`, на macOS и Windows всегда.
+const KEYRING_SESSION: bool = cfg!(any(
+    feature = "keyring",
+    target_os = "macos",
+    target_os = "windows"
+));
+
+/// `--no-cache`: в этом запуске не читать и не писать файл сессии.
+static NO_CACHE: AtomicBool = AtomicBool::new(false);
+
+pub fn set_no_cache(v: bool) {
+    NO_CACHE.store(v, Ordering::Relaxed);
+}
+
+/// `pm get --ttl SECS`: TTL сессии на этот запуск.
+static TTL_OVERRIDE: Mutex<Option<u64>> = Mutex::new(None);
+
+pub fn set_ttl_override(ttl: u64) {
+    *TTL_OVERRIDE.lock().unwrap_or_else(|e| e.into_inner()) = Some(ttl);
+}
+
+/// Мастер-пароль, прочитанный из `--password-fd`.
+static PASSWORD_OVERRIDE: Mutex<Option<String>> = Mutex::new(None);
+
+pub fn set_master_password(password: String) {
+    *PASSWORD_OVERRIDE.lock().unwrap_or_else(|e| e.into_inner()) = Some(password);
+}
+
+/// Сколько держать ключ в кэше.
+/// Приоритет: флаг `--no-cache` > `pm get --ttl` > TTL профиля >
+/// `settings.session.ttl_secs` > по умолчанию.
+pub fn session_ttl(no_cache: bool, setting: Option<u64>) -> u64 {
+    if no_cache {
+        return 0;
+    }
+    let ttl_override = *TTL_OVERRIDE.lock().unwrap_or_else(|e| e.into_inner());
+    ttl_override
+        .or_else(crate::profiles::session_ttl)
+        .or(setting)
+        .unwrap_or(DEFAULT_SESSION_TTL_SECS)
+}
 
 #[derive(Serialize, Deserialize)]
 struct SessionFile {
+    /// Когда был введён пароль; в старых файлах поля нет (0) — такие считаем истёкшими
+    #[serde(default)]
+    created_at: u64,
+    /// Продлевается при каждом обращении: TTL отсчитывается от последнего использования
     expires_at: u64,
+    /// MK, зашифрованный ключом сессии (`session_key`). Старые файлы с MK
+    /// в открытом base64 поля `nonce` не имеют и просто отбрасываются.
+    nonce: String,
     master_key: String,
 }
 
+/// Сессия в связке ключей. Секрет там и так защищён системой, поэтому MK
+/// лежит в base64 без обёртки ключом сессии.
+#[derive(Serialize, Deserialize)]
+struct KeyringSession {
+    created_at: u64,
+    expires_at: u64,
+    master_key: String,
+}
+
+/// Срок действия сессии с момента `now`: `ttl` простоя, но не дольше
+/// `max_secs` от ввода пароля.
+fn session_expiry(now: u64, ttl: u64, created_at: u64, max_secs: Option<u64>) -> u64 {
+    let idle = now.saturating_add(ttl);
+    match max_secs {
+        Some(max) => idle.min(created_at.saturating_add(max)),
+        None => idle,
+    }
+}
+
 pub fn session_path() -> Result<PathBuf> {
+    Ok(session_path_for(&store_root()?, &store_tag()?))
+}
+
+/// Файл сессии хранилища `root` с отпечатком `tag` (см. `store_tag_for`).
+fn session_path_for(root: &Path, tag: &str) -> PathBuf {
+    match std::env::var("XDG_RUNTIME_DIR") {
+        Ok(dir) => PathBuf::from(dir).join(format!("pm-session-{tag}.json")),
+        Err(_) => root.join("session.json"),
+    }
+}
+
+/// Файл с ключом, которым зашифрован MK в сессии. Лежит в XDG_RUNTIME_DIR
+/// (tmpfs, очищается при перезагрузке) или во временном каталоге, но не рядом
+/// с хранилищем: копия каталога хранилища не должна давать рабочий MK.
+pub fn session_key_path() -> PathBuf {
+    match std::env::var("XDG_RUNTIME_DIR") {
+        Ok(dir) => PathBuf::from(dir).join("pm-session.key"),
+        Err(_) => std::env::temp_dir().join(format!("pm-session-{}.key", user_tag())),
+    }
+}
+
+#[cfg(unix)]
+fn user_tag() -> String {
+    // SAFETY: getuid не может завершиться ошибкой
+    unsafe { libc::getuid() }.to_string()
+}
+
+#[cfg(not(unix))]
+fn user_tag() -> String {
+    std::env::var("USERNAME").unwrap_or_else(|_| "user".to_string())
+}
+
+/// Файл ключа должен быть нашим и закрытым: в общем /tmp его мог подложить другой.
+#[cfg(unix)]
+fn key_file_trusted(meta: &fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    // SAFETY: см. user_tag
+    meta.is_file() && meta.uid() == unsafe { libc::getuid() } && meta.mode() & 0o077 == 0
+}
+
+#[cfg(not(unix))]
+fn key_file_trusted(meta: &fs::Metadata) -> bool {
+    meta.is_file()
+}
+
+fn read_session_key(path: &Path) -> Option<[u8; 32]> {
+    let meta = fs::symlink_metadata(path).ok()?;
+    if !key_file_trusted(&meta) {
+        crate::log::debug(format_args!(
+            "ignoring untrusted session key {}",
+            path.display()
+        ));
+        return None;
+    }
+    fs::read(path).ok()?.try_into().ok()
+}
+
+/// Ключ сессии; с `create` — создаётся, если его ещё нет (первый вход после загрузки).
+fn session_key(create: bool) -> Option<[u8; 32]> {
+    use rand::RngCore;
+    use std::io::Write;
+
+    let path = session_key_path();
+    if let Some(key) = read_session_key(&path) {
+        return Some(key);
+    }
+    if !create {
+        return None;
+    }
+
+    let mut key = [0u8; 32];
+    rand::rngs::OsRng.fill_bytes(&mut key);
+    let mut opts = fs::OpenOptions::new();
+    opts.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        opts.mode(0o600);
+    }
+    match opts.open(&path) {
+        Ok(mut f) => {
+            f.write_all(&key).ok()?;
+            Some(key)
+        }
+        // Файл есть, но негодный (чужой, битый) — не кэшируем вовсе
+        Err(_) => None,
+    }
+}
+
+/// Каталог XDG_RUNTIME_DIR, даже если переменной в этом запуске нет:
+/// на Linux это обычно `/run/user/<uid>`.
+fn runtime_dir() -> Option<PathBuf> {
     if let Ok(dir) = std::env::var("XDG_RUNTIME_DIR") {
-        Ok(PathBuf::from(dir).join("pm-session.json"))
+        return Some(PathBuf::from(dir));
+    }
+    #[cfg(target_os = "linux")]
+    {
+        let dir = PathBuf::from(format!("/run/user/{}", user_tag()));
+        if dir.is_dir() {
+            return Some(dir);
+        }
+    }
+    None
+}
+
+/// Все места, где может лежать сессия: окружение между запусками могло
+/// измениться, и файл остался там, куда `session_path` уже не смотрит.
+fn session_candidates() -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    if let Some(dir) = runtime_dir() {
+        paths.push(dir.join(format!("pm-session-{}.json", store_tag()?)));
+        // Имя до того, как в нём появился отпечаток хранилища
+        paths.push(dir.join("pm-session.json"));
+    }
+    paths.push(store_root()?.join("session.json"));
+    Ok(paths)
+}
+
+/// Сессия, созданная в `created_at` и продлённая до `expires_at`, больше
+/// не действует. Файлы без `created_at` (0) — из старых версий, они истёкшие.
+fn expired(created_at: u64, expires_at: u64, now: u64, max_secs: Option<u64>) -> bool {
+    created_at == 0
+        || now > expires_at
+        || max_secs.is_some_and(|max| now > created_at.saturating_add(max))
+}
+
+/// Удалить просроченные и испорченные файлы сессии во всех возможных местах,
+/// а не только в `session_path`: иначе файл рядом с хранилищем, оставшийся с
+/// запуска без `XDG_RUNTIME_DIR`, лежал бы вечно. Действующие сессии не
+/// трогаем — окружение может вернуться. Возвращает число удалённых файлов.
+fn sweep_stale_sessions(now: u64, max_secs: Option<u64>) -> Result<usize> {
+    let mut removed = 0;
+    for path in session_candidates()? {
+        let Ok(data) = fs::read_to_string(&path) else {
+            continue;
+        };
+        // Срок в файле открытым текстом: ключ сессии для проверки не нужен
+        let stale = serde_json::from_str::<SessionFile>(&data)
+            .map_or(true, |s| expired(s.created_at, s.expires_at, now, max_secs));
+        if stale && wipe_file(&path)? {
+            crate::log::info(format_args!(
+                "removed stale session file {}",
+                path.display()
+            ));
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// Затереть файл нулями и удалить, чтобы ключ не остался в освободившихся
+/// блоках как есть. `false`, если файла не было.
+fn wipe_file(path: &Path) -> Result<bool> {
+    use std::io::Write;
+
+    let mut file = match fs::OpenOptions::new().write(true).open(path) {
+        Ok(f) => f,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e.into()),
+    };
+    let len = file.metadata()?.len() as usize;
+    file.write_all(&vec![0u8; len])?;
+    file.sync_all()?;
+    drop(file);
+    fs::remove_file(path)?;
+    Ok(true)
+}
+
+/// Забыть закэшированный мастер-ключ. `true`, если какая-то сессия была.
+fn wipe_sessions() -> Result<bool> {
+    let _lock = SessionLock::acquire(&session_path()?)?;
+    let mut found = false;
+    if KEYRING_SESSION {
+        let tag = store_tag()?;
+        if crate::keychain::session_get(&tag).is_some() {
+            crate::keychain::session_delete(&tag)?;
+            found = true;
+        }
+    }
+    for path in session_candidates()? {
+        found |= wipe_file(&path)?;
+    }
+    Ok(found)
+}
+
+/// Забыть закэшированный мастер-ключ (например, после замены хранилища),
+/// в том числе у агента.
+pub fn clear_session() -> Result<()> {
+    crate::agent::agent_forget();
+    wipe_sessions().map(|_| ())
+}
+
+/// Кэш мастер-ключа для `pm status`.
+#[derive(Serialize)]
+pub struct SessionStatus {
+    /// Кэш включён (`session.ttl_secs` не 0 и нет `--no-cache`)
+    pub enabled: bool,
+    pub active: bool,
+    /// Где ключ: `agent`, `keyring` или путь к файлу сессии
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// Сколько секунд сессия ещё действует без обращений (у агента свой срок)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remaining_secs: Option<u64>,
+    /// Сколько просроченных файлов сессии удалено при проверке
+    pub stale_removed: usize,
+}
+
+/// Состояние сессии без запроса пароля; заодно убирает просроченные файлы
+/// сессии во всех возможных местах.
+pub fn session_status(cfg: &Config) -> Result<SessionStatus> {
+    let path = session_path()?;
+    let _lock = SessionLock::acquire(&path)?;
+    let now = now_unix()?;
+    let max_secs = cfg.settings.session.max_secs;
+    let remaining = |created_at: u64, expires_at: u64| {
+        let end = max_secs.map_or(expires_at, |max| {
+            expires_at.min(created_at.saturating_add(max))
+        });
+        end.saturating_sub(now)
+    };
+
+    let mut status = SessionStatus {
+        enabled: session_ttl(
+            NO_CACHE.load(Ordering::Relaxed),
+            cfg.settings.session.ttl_secs,
+        ) > 0,
+        active: false,
+        source: None,
+        remaining_secs: None,
+        stale_removed: sweep_stale_sessions(now, max_secs)?,
+    };
+    if crate::agent::agent_get(cfg).is_some() {
+        status.active = true;
+        status.source = Some("agent".to_string());
+        return Ok(status);
+    }
+    if KEYRING_SESSION
+        && let Some(sess) = crate::keychain::session_get(&store_tag()?)
+            .and_then(|data| serde_json::from_str::<KeyringSession>(&data).ok())
+        && !expired(sess.created_at, sess.expires_at, now, max_secs)
+    {
+        status.active = true;
+        status.source = Some("keyring".to_string());
+        status.remaining_secs = Some(remaining(sess.created_at, sess.expires_at));
+        return Ok(status);
+    }
+    // Файл без ключа сессии (его стёрла перезагрузка) не откроется
+    let usable = fs::read_to_string(&path)
+        .ok()
+        .and_then(|data| serde_json::from_str::<SessionFile>(&data).ok())
+        .filter(|s| !expired(s.created_at, s.expires_at, now, max_secs))
+        .filter(|s| {
+            session_key(false)
+                .is_some_and(|key| unwrap_master_key(&key, &s.nonce, &s.master_key).is_ok())
+        });
+    if let Some(sess) = usable {
+        status.active = true;
+        status.source = Some(path.display().to_string());
+        status.remaining_secs = Some(remaining(sess.created_at, sess.expires_at));
+    }
+    Ok(status)
+}
+
+/// pm lock
+pub fn lock() -> Result<()> {
+    let agent = crate::agent::agent_forget();
+    if wipe_sessions()? || agent {
+        println!("Session cleared.");
     } else {
-        Ok(store_root()?.join("session.json"))
+        println!("No active session.");
     }
+    Ok(())
 }
 
 fn now_unix() -> Result<u64> {
@@ -46,38 +387,278 @@ fn set_perms_restrictive(_path: &Path) -> std::io::Result<()> {
     Ok(())
 }
 
-pub fn get_master_key_with_cache(cfg: &Config) -> Result<MasterKey> {
-    let path = session_path()?;
-    let now = now_unix()?;
+/// Эксклюзивная блокировка (flock) на `<session>.lock` на время
+/// чтения-проверки-записи сессии. Снимается при закрытии файла.
+struct SessionLock {
+    #[cfg(unix)]
+    _file: fs::File,
+}
+
+impl SessionLock {
+    fn acquire(session: &Path) -> Result<Self> {
+        let lock_path = session.with_extension("lock");
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)?;
+        let _ = set_perms_restrictive(&lock_path);
 
-    if let Ok(data) = fs::read_to_string(&path) {
-        if let Ok(sess) = serde_json::from_str::<SessionFile>(&data) {
-            if now <= sess.expires_at {
-                let bytes = general_purpose::STANDARD.decode(&sess.master_key)?;
-                if bytes.len() == 32 {
-                    let mut mk = [0u8; 32];
-                    mk.copy_from_slice(&bytes);
-                    return Ok(mk);
-                }
-            } else {
-                let _ = fs::remove_file(&path);
+        #[cfg(unix)]
+        {
+            use std::os::unix::io::AsRawFd;
+            // SAFETY: fd принадлежит `file` и живёт не меньше блокировки
+            if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } != 0 {
+                return Err(std::io::Error::last_os_error().into());
             }
+            Ok(SessionLock { _file: file })
+        }
+        #[cfg(not(unix))]
+        {
+            drop(file);
+            Ok(SessionLock {})
         }
     }
+}
+
+/// Ключ из действующей сессии и время её создания.
+/// Просроченный или повреждённый файл удаляется.
+fn read_session(path: &Path, now: u64, max_secs: Option<u64>) -> Option<(MasterKey, u64)> {
+    let data = fs::read_to_string(path).ok()?;
+    let key = session_key(false);
+    let parsed = serde_json::from_str::<SessionFile>(&data)
+        .ok()
+        .and_then(|sess| {
+            let mk = unwrap_master_key(&key?, &sess.nonce, &sess.master_key).ok()?;
+            Some((sess, mk))
+        });
 
-    let master_password = prompt_password_hidden("Master password: ")?;
-    let mk = unlock_master_key(&master_password, cfg)?;
+    match parsed {
+        Some((sess, mk)) if !expired(sess.created_at, sess.expires_at, now, max_secs) => {
+            Some((mk, sess.created_at))
+        }
+        Some(_) => {
+            let _ = fs::remove_file(path);
+            None
+        }
+        None => {
+            crate::log::debug(format_args!(
+                "removing corrupted session file {}",
+                path.display()
+            ));
+            let _ = fs::remove_file(path);
+            None
+        }
+    }
+}
+
+/// Ключ из сессии в связке ключей; просроченная запись удаляется.
+/// Без связки ключей (нет демона Secret Service и т. п.) — просто `None`.
+fn read_keyring_session(now: u64, max_secs: Option<u64>) -> Option<(MasterKey, u64)> {
+    read_keyring_session_for(&store_tag().ok()?, now, max_secs)
+}
+
+fn read_keyring_session_for(
+    tag: &str,
+    now: u64,
+    max_secs: Option<u64>,
+) -> Option<(MasterKey, u64)> {
+    use base64::{Engine as _, engine::general_purpose};
+
+    if !KEYRING_SESSION {
+        return None;
+    }
+    let data = crate::keychain::session_get(tag)?;
+    let sess = serde_json::from_str::<KeyringSession>(&data).ok();
+    let mk = sess.as_ref().and_then(|s| {
+        general_purpose::STANDARD
+            .decode(&s.master_key)
+            .ok()?
+            .try_into()
+            .ok()
+    });
+    match (sess, mk) {
+        (Some(sess), Some(mk))
+            if now <= sess.expires_at
+                && max_secs.is_none_or(|max| now <= sess.created_at.saturating_add(max)) =>
+        {
+            Some((mk, sess.created_at))
+        }
+        _ => {
+            let _ = crate::keychain::session_delete(tag);
+            None
+        }
+    }
+}
 
+/// Сохранить сессию: в связку ключей, если она используется и доступна,
+/// иначе в файл.
+fn save_session(path: &Path, mk: &MasterKey, created_at: u64, expires_at: u64) {
+    use base64::{Engine as _, engine::general_purpose};
+
+    if KEYRING_SESSION {
+        let sess = KeyringSession {
+            created_at,
+            expires_at,
+            master_key: general_purpose::STANDARD.encode(mk),
+        };
+        let stored = store_tag()
+            .and_then(|tag| crate::keychain::session_set(&tag, &serde_json::to_string(&sess)?));
+        match stored {
+            Ok(()) => {
+                // Сессия, оставшаяся в файле с прошлых запусков, больше не нужна
+                let _ = wipe_file(path);
+                return;
+            }
+            Err(e) => crate::log::debug(format_args!(
+                "keyring session unavailable, using a file: {e}"
+            )),
+        }
+    }
+    write_session(path, mk, created_at, expires_at);
+}
+
+fn write_session(path: &Path, mk: &MasterKey, created_at: u64, expires_at: u64) {
+    let Some((nonce, master_key)) =
+        session_key(true).and_then(|key| wrap_master_key(&key, mk).ok())
+    else {
+        return;
+    };
     let sess = SessionFile {
-        expires_at: now + SESSION_TTL_SECS,
-        master_key: general_purpose::STANDARD.encode(mk),
+        created_at,
+        expires_at,
+        nonce,
+        master_key,
     };
-
     if let Ok(json) = serde_json::to_string(&sess) {
-        if fs::write(&path, json).is_ok() {
-            let _ = set_perms_restrictive(&path);
+        let _ = write_atomic_private(path, json.as_bytes());
+    }
+}
+
+/// Мастер-пароль из `--password-fd` или `PM_MASTER_PASSWORD` — для скриптов
+/// и тестов без терминала. Переменную видят другие процессы пользователя,
+/// об этом предупреждает envcheck.
+pub fn master_password_from_env() -> Option<String> {
+    let from_fd = PASSWORD_OVERRIDE
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone();
+    from_fd.or_else(|| {
+        std::env::var("PM_MASTER_PASSWORD")
+            .ok()
+            .filter(|p| !p.is_empty())
+    })
+}
+
+/// Мастер-ключ без кэша: из `PM_MASTER_PASSWORD` или связки ключей (если
+/// включена), иначе запросом пароля.
+pub fn unlock_interactive(cfg: &Config) -> Result<MasterKey> {
+    let keyfile = crate::keyfile::current()?;
+    let keyfile = keyfile.as_ref();
+    if cfg.kdf.keyfile && keyfile.is_none() {
+        // Не спрашиваем пароль, который всё равно не подойдёт
+        return Err(CryptoError::KeyfileRequired.into());
+    }
+    if let Some(pw) = master_password_from_env() {
+        return Ok(unlock_master_key(&pw, cfg, keyfile)?);
+    }
+    // При любой ошибке связки ключей молча переходим к запросу
+    let from_keychain =
+        master_password_from_keychain(cfg).and_then(|pw| unlock_master_key(&pw, cfg, keyfile).ok());
+    match from_keychain {
+        Some(mk) => Ok(mk),
+        None => {
+            let master_password = prompt_password_hidden("Master password: ").map_err(|e| {
+                e.context("cannot ask for the master password; set PM_MASTER_PASSWORD or use --password-fd")
+            })?;
+            Ok(unlock_master_key(&master_password, cfg, keyfile)?)
         }
     }
+}
+
+/// Мастер-ключ хранилища другого профиля (`pm mv --to-profile`): из его
+/// агента или действующей сессии, иначе из `PM_DEST_MASTER_PASSWORD` или
+/// запросом пароля. Сессию этого профиля команда не создаёт и не продлевает.
+pub fn unlock_profile(name: &str, root: &Path, cfg: &Config) -> Result<MasterKey> {
+    if !NO_CACHE.load(Ordering::Relaxed) {
+        let tag = crate::store::store_tag_for(root, Some(name));
+        if let Some(mk) = crate::agent::agent_get_for(&tag, cfg) {
+            crate::log::info(format_args!("master key of profile {name} from pm agent"));
+            return Ok(mk);
+        }
+        let path = session_path_for(root, &tag);
+        let _lock = SessionLock::acquire(&path)?;
+        let now = now_unix()?;
+        let max_secs = cfg.settings.session.max_secs;
+        let cached = read_keyring_session_for(&tag, now, max_secs)
+            .or_else(|| read_session(&path, now, max_secs));
+        if let Some((mk, _)) = cached {
+            crate::log::info(format_args!("session cache hit for profile {name}"));
+            return Ok(mk);
+        }
+    }
+    if cfg.kdf.keyfile {
+        // Ключевой файл в --keyfile относится к исходному хранилищу
+        return Err(anyhow!(
+            "profile {name} needs a keyfile; unlock it first (pm --profile {name} ls) so its session can be used"
+        ));
+    }
+    let password = match std::env::var("PM_DEST_MASTER_PASSWORD") {
+        Ok(pw) if !pw.is_empty() => pw,
+        _ => prompt_password_hidden(&format!("Master password for profile {name}: ")).map_err(
+            |e| {
+                e.context(
+                    "cannot ask for the destination master password; set PM_DEST_MASTER_PASSWORD",
+                )
+            },
+        )?,
+    };
+    Ok(unlock_master_key(&password, cfg, None)?)
+}
+
+pub fn get_master_key_with_cache(cfg: &Config) -> Result<MasterKey> {
+    let ttl = session_ttl(
+        NO_CACHE.load(Ordering::Relaxed),
+        cfg.settings.session.ttl_secs,
+    );
+    if ttl == 0 {
+        crate::log::info(format_args!("session cache disabled"));
+        return unlock_interactive(cfg);
+    }
+    if let Some(mk) = crate::agent::agent_get(cfg) {
+        crate::log::info(format_args!("master key from pm agent"));
+        return Ok(mk);
+    }
+
+    let path = session_path()?;
+
+    // Держим блокировку и на время запроса пароля: параллельная команда
+    // дождётся и возьмёт готовую сессию, а не спросит пароль второй раз.
+    let _lock = SessionLock::acquire(&path)?;
+    let now = now_unix()?;
+    let max_secs = cfg.settings.session.max_secs;
+    // Уборка не должна мешать разблокировке
+    if let Err(e) = sweep_stale_sessions(now, max_secs) {
+        crate::log::info(format_args!("cannot remove stale session files: {e}"));
+    }
+
+    let cached = read_keyring_session(now, max_secs).or_else(|| read_session(&path, now, max_secs));
+    if let Some((mk, created_at)) = cached {
+        crate::log::info(format_args!("session cache hit"));
+        save_session(
+            &path,
+            &mk,
+            created_at,
+            session_expiry(now, ttl, created_at, max_secs),
+        );
+        return Ok(mk);
+    }
+
+    crate::log::info(format_args!("session cache miss ({})", path.display()));
+    let mk = unlock_interactive(cfg)?;
+
+    let now = now_unix()?;
+    save_session(&path, &mk, now, session_expiry(now, ttl, now, max_secs));
 
     Ok(mk)
 }
@@ -0,0 +1,201 @@
+//! Передать записи другому человеку без мастер-пароля: `pm share` шифрует
+//! выбранные записи его открытым ключом age, `pm share import` принимает их.
+//!
+//! Файл — обычный age (см. `age.rs`): получатель может открыть его и утилитой
+//! `age -d -i key.txt`. Внутри — JSON `Bundle`: опись (`manifest`) и записи
+//! целиком, с OTP и дополнительными полями. Мастер-ключ отправителя в файл не
+//! попадает; при импорте записи перешифровываются ключом получателя и кладутся
+//! в папку `shared/` (`--prefix`).
+
+use crate::age::{Identity, Recipient};
+use crate::config::Config;
+use crate::entry::Entry;
+use crate::import::{Importer, OnConflict};
+use crate::session::get_master_key_with_cache;
+use crate::store::{
+    decrypt_all, entry_exists, list_entries_under, validate_entry_path, write_atomic_private,
+};
+use anyhow::{Context, Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+const FORMAT: &str = "pm-share/1";
+
+#[derive(Serialize, Deserialize)]
+struct Bundle {
+    format: String,
+    shared_at: String,
+    manifest: Vec<ManifestItem>,
+    entries: Vec<BundleEntry>,
+}
+
+/// Строка описи: что за запись, без секретов.
+#[derive(Serialize, Deserialize)]
+struct ManifestItem {
+    path: String,
+    title: String,
+    username: Option<String>,
+    url: Option<String>,
+    has_otp: bool,
+    /// Имена дополнительных полей
+    fields: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BundleEntry {
+    path: String,
+    entry: Entry,
+}
+
+impl ManifestItem {
+    fn new(path: &str, entry: &Entry) -> Self {
+        ManifestItem {
+            path: path.to_string(),
+            title: entry.title.clone(),
+            username: entry.username.clone(),
+            url: entry.url.clone(),
+            has_otp: entry.otp.is_some(),
+            fields: entry.fields.iter().map(|f| f.name.clone()).collect(),
+        }
+    }
+}
+
+/// Записи по аргументам `pm share`: путь записи или папки.
+fn selected_paths(paths: &[String]) -> Result<Vec<String>> {
+    let mut selected = Vec::new();
+    for path in paths {
+        let path = validate_entry_path(path)?;
+        let found = if entry_exists(&path)? {
+            vec![path.clone()]
+        } else {
+            list_entries_under(Some(&path))?
+        };
+        if found.is_empty() {
+            return Err(anyhow!("Entry not found: {path}"));
+        }
+        selected.extend(found);
+    }
+    selected.sort();
+    selected.dedup();
+    Ok(selected)
+}
+
+/// pm share PATH... --recipient age1... --output FILE
+pub fn share(paths: &[String], recipients: &[String], output: &Path) -> Result<()> {
+    let recipients = recipients
+        .iter()
+        .map(|r| r.parse::<Recipient>())
+        .collect::<Result<Vec<_>>>()?;
+    let paths = selected_paths(paths)?;
+    let config = Config::load()?;
+    let mk = get_master_key_with_cache(&config)?;
+
+    let mut manifest = Vec::new();
+    let mut entries = Vec::new();
+    for (path, res) in decrypt_all(&mk, paths) {
+        let entry = res.map_err(|e| anyhow!("cannot decrypt {path}: {e}"))?;
+        manifest.push(ManifestItem::new(&path, &entry));
+        entries.push(BundleEntry { path, entry });
+    }
+    let bundle = Bundle {
+        format: FORMAT.to_string(),
+        shared_at: OffsetDateTime::now_utc().format(&Rfc3339)?,
+        manifest,
+        entries,
+    };
+    let data = crate::age::encrypt(&recipients, &serde_json::to_vec_pretty(&bundle)?)?;
+    write_atomic_private(output, &data)?;
+
+    let count = bundle.entries.len();
+    crate::output::emit(
+        &serde_json::json!({
+            "ok": true,
+            "action": "share",
+            "file": output.display().to_string(),
+            "entries": bundle.manifest.iter().map(|m| &m.path).collect::<Vec<_>>(),
+            "recipients": recipients.iter().map(|r| r.to_string()).collect::<Vec<_>>(),
+        }),
+        || {
+            crate::output::note(format!(
+                "Shared {count} entries with {} recipient(s) in {}",
+                recipients.len(),
+                output.display()
+            ));
+            Ok(())
+        },
+    )
+}
+
+/// pm share import FILE --identity KEY [--prefix P] [--on-conflict ...] [--dry-run]
+pub fn import(
+    file: &Path,
+    identity: &Path,
+    prefix: &str,
+    on_conflict: OnConflict,
+    dry_run: bool,
+) -> Result<()> {
+    let identities = Identity::read_file(
+        &std::fs::read_to_string(identity)
+            .with_context(|| format!("cannot read {}", identity.display()))?,
+    )?;
+    let data = std::fs::read(file).with_context(|| format!("cannot read {}", file.display()))?;
+    let bundle: Bundle = serde_json::from_slice(&crate::age::decrypt(&identities, &data)?)
+        .context("the shared file is not a pm share bundle")?;
+    if bundle.format != FORMAT {
+        return Err(anyhow!(
+            "share format {:?} is not supported by this pm ({FORMAT})",
+            bundle.format
+        ));
+    }
+
+    println!(
+        "Shared on {} with {} entries:",
+        bundle.shared_at,
+        bundle.manifest.len()
+    );
+    for item in &bundle.manifest {
+        let user = item.username.as_deref().unwrap_or("-");
+        let otp = if item.has_otp { ", OTP" } else { "" };
+        println!("  {} ({user}{otp})", item.path);
+    }
+
+    let prefix = prefix.trim_matches('/');
+    let mut importer = Importer::new(dry_run)?.on_conflict(on_conflict);
+    for BundleEntry { path, entry } in bundle.entries {
+        let target = if prefix.is_empty() {
+            path
+        } else {
+            format!("{prefix}/{path}")
+        };
+        importer.add(&target, entry);
+    }
+    importer.finish()
+}
+
+/// pm share keygen --output FILE: ключ для получения записей.
+pub fn keygen(output: &Path) -> Result<()> {
+    if output.exists() {
+        return Err(anyhow!("{} already exists", output.display()));
+    }
+    let identity = Identity::generate();
+    let public = identity.to_public();
+    let created = OffsetDateTime::now_utc().format(&Rfc3339)?;
+    let text = format!(
+        "# created: {created}\n# public key: {public}\n{}\n",
+        identity.to_secret_string()
+    );
+    write_atomic_private(output, text.as_bytes())?;
+    crate::output::emit(
+        &serde_json::json!({ "file": output.display().to_string(), "public_key": public.to_string() }),
+        || {
+            println!("Public key: {public}");
+            crate::output::note(format!(
+                "Keep {} private; send the public key to whoever shares with you.",
+                output.display()
+            ));
+            Ok(())
+        },
+    )
+}
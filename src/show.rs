@@ -0,0 +1,357 @@
+//! Чтение записей: `pm show`, `pm get`, `pm clip`, `pm history`,
+//! `pm verify-password`.
+//!
+//! Функции открывают текущее хранилище, отмечают обращение в журнале и
+//! возвращают запись или значение поля; печатает и копирует их CLI.
+
+use crate::crypto::{CryptoError, secrets_equal};
+use crate::entry::{Entry, WifiSecurity};
+use crate::entry_otp::take_code;
+use crate::otp::generate_otp_code;
+use crate::password_store::PasswordStore;
+use crate::prompt::read_secret;
+use crate::recent::record_access;
+use crate::session::get_master_key_with_cache;
+use crate::snippet::{ShowFormat, render_dotenv, render_netrc};
+use crate::store::store_root;
+use crate::timespec::is_expired;
+use anyhow::{Result, anyhow, bail};
+use serde::Serialize;
+use std::fmt::Write as _;
+use std::io::IsTerminal;
+use time::OffsetDateTime;
+
+/// Поле записи для `--field`, `pm get` и последовательностей автонабора.
+#[derive(Clone, Debug)]
+pub enum Field {
+    Password,
+    Username,
+    Url,
+    Notes,
+    /// Текущий TOTP-код
+    Otp,
+    /// Дополнительное поле записи по имени
+    Custom(String),
+}
+
+impl std::str::FromStr for Field {
+    type Err = std::convert::Infallible;
+
+    /// Любое имя, кроме встроенных, считается дополнительным полем.
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Ok(match s {
+            "password" => Field::Password,
+            "username" => Field::Username,
+            "url" => Field::Url,
+            "notes" => Field::Notes,
+            "otp" => Field::Otp,
+            other => Field::Custom(other.to_string()),
+        })
+    }
+}
+
+impl Field {
+    pub fn label(&self) -> &str {
+        match self {
+            Field::Password => "Password",
+            Field::Username => "Username",
+            Field::Url => "URL",
+            Field::Notes => "Notes",
+            Field::Otp => "OTP code",
+            Field::Custom(name) => name,
+        }
+    }
+
+    /// Значение поля записи; пустое поле — ошибка, а не пустая строка.
+    pub fn value(&self, entry: &Entry, path: &str) -> Result<String> {
+        let value = match self {
+            Field::Password => Some(entry.password.clone()),
+            Field::Username => entry.username.clone(),
+            Field::Url => entry.url.clone(),
+            Field::Notes => entry.notes.clone(),
+            Field::Otp => match entry.otp {
+                Some(ref cfg) => Some(generate_otp_code(cfg)?),
+                None => bail!("No OTP configured for {path}"),
+            },
+            Field::Custom(name) => match entry.custom_field(name) {
+                Some(f) => Some(f.value.clone()),
+                None => bail!("{path} has no field '{name}'"),
+            },
+        };
+        match value {
+            Some(v) if !v.is_empty() => Ok(v),
+            _ => Err(anyhow!("{} is empty for {path}", self.label())),
+        }
+    }
+}
+
+/// Значение поля записи; OTP-код берётся через `take_code` (HOTP сдвигает счётчик).
+pub fn read_field(store: &PasswordStore, path: &str, field: &Field) -> Result<String> {
+    let mut entry = store.show(path)?;
+    match field {
+        Field::Otp => take_code(store, path, &mut entry),
+        _ => field.value(&entry, path),
+    }
+}
+
+/// Поле записи из текущего хранилища; `action` уходит в журнал обращений.
+pub fn field_value(path: &str, field: &Field, action: &str) -> Result<String> {
+    let store = PasswordStore::open_current()?;
+    let value = read_field(&store, path, field)?;
+    record_access(store.config(), store.master_key()?, path, action);
+    Ok(value)
+}
+
+/// Расшифрованная запись для `pm show` и то, что из неё можно показать.
+pub struct Shown {
+    pub entry: Entry,
+    /// `--reveal`: показать и скрываемые дополнительные поля
+    pub reveal: bool,
+    /// Пароль в JSON заменяется на `<concealed>` (`display.conceal_password`)
+    pub conceal: bool,
+    /// Пароль на экране заменяется звёздочками
+    pub mask: bool,
+}
+
+/// pm show PATH [--reveal]
+pub fn show(path: &str, reveal: bool) -> Result<Shown> {
+    let store = PasswordStore::open_current()?;
+    let config = store.config();
+    let entry = store.show(path)?;
+    record_access(config, store.master_key()?, path, "show");
+    Ok(Shown {
+        entry,
+        reveal,
+        conceal: config.settings.conceal_password(reveal),
+        mask: config.settings.mask_password_in_show(reveal),
+    })
+}
+
+impl Shown {
+    fn reveals_fields(&self) -> bool {
+        self.reveal && self.entry.fields.iter().any(|f| f.concealed)
+    }
+
+    /// Запись для `--json`: скрываемое заменено на `<concealed>`, история
+    /// паролей — только с `--include-history`.
+    pub fn into_json(mut self, include_history: bool) -> Entry {
+        if !self.conceal || self.reveals_fields() {
+            crate::envcheck::warn_secret_output();
+        }
+        let entry = &mut self.entry;
+        if !include_history {
+            entry.password_history.clear();
+        }
+        if self.conceal {
+            entry.password = "<concealed>".to_string();
+            for h in &mut entry.password_history {
+                h.password = "<concealed>".to_string();
+            }
+        }
+        for f in entry
+            .fields
+            .iter_mut()
+            .filter(|f| f.concealed && !self.reveal)
+        {
+            f.value = "<concealed>".to_string();
+        }
+        self.entry
+    }
+
+    /// Карточка записи для человека. Пароль по умолчанию не показываем:
+    /// экран могут видеть другие.
+    pub fn to_text(&self) -> Result<String> {
+        if !self.mask || self.reveals_fields() {
+            crate::envcheck::warn_secret_output();
+        }
+        let entry = &self.entry;
+        let password = if self.mask {
+            "********"
+        } else {
+            entry.password.as_str()
+        };
+        let mut out = String::new();
+
+        writeln!(out, "Title:    {}", entry.title)?;
+        if let Some(ref wifi) = entry.wifi {
+            writeln!(out, "SSID:     {}", wifi.ssid)?;
+            writeln!(out, "Security: {}", wifi.security.label())?;
+            if wifi.security != WifiSecurity::Open {
+                writeln!(out, "Password: {password}")?;
+            }
+        } else {
+            if let Some(ref u) = entry.username {
+                writeln!(out, "Username: {u}")?;
+            }
+            writeln!(out, "Password: {password}")?;
+        }
+        if let Some(ref url) = entry.url {
+            writeln!(out, "URL:      {url}")?;
+        }
+        if let Some(ref notes) = entry.notes {
+            writeln!(out, "Notes:    {notes}")?;
+        }
+        if !entry.tags.is_empty() {
+            writeln!(out, "Tags:     {}", entry.tags.join(", "))?;
+        }
+        for f in &entry.fields {
+            let value = if f.concealed && !self.reveal {
+                "********"
+            } else {
+                f.value.as_str()
+            };
+            writeln!(out, "{:<9} {value}", format!("{}:", f.name))?;
+        }
+        writeln!(out, "Created:  {}", entry.created_at)?;
+        writeln!(out, "Updated:  {}", entry.updated_at)?;
+        if let Some(ref expires) = entry.expires_at {
+            if is_expired(expires, OffsetDateTime::now_utc()) {
+                let flag = if std::io::stdout().is_terminal() {
+                    "\x1b[1;31m⚑ EXPIRED\x1b[0m"
+                } else {
+                    "⚑ EXPIRED"
+                };
+                writeln!(out, "Expires:  {expires}  {flag}")?;
+            } else {
+                writeln!(out, "Expires:  {expires}")?;
+            }
+        }
+        if let Some(ref otp) = entry.otp {
+            if otp.is_hotp() {
+                writeln!(out, "OTP:      configured (HOTP, counter {})", otp.counter)?;
+            } else if otp.is_steam() {
+                writeln!(out, "OTP:      configured (Steam Guard)")?;
+            } else {
+                writeln!(out, "OTP:      configured")?;
+            }
+        } else {
+            writeln!(out, "OTP:      not set")?;
+        }
+        if !entry.attachments.is_empty() {
+            let files: Vec<_> = entry
+                .attachments
+                .iter()
+                .map(|a| format!("{} ({})", a.name, crate::progress::human_size(a.size)))
+                .collect();
+            writeln!(out, "Attached: {}", files.join(", "))?;
+        }
+        Ok(out)
+    }
+}
+
+/// pm show PATH --format dotenv|netrc
+pub fn snippet(path: &str, format: ShowFormat, prefix: &str) -> Result<String> {
+    let store = PasswordStore::open_current()?;
+    let entry = store.show(path)?;
+    record_access(store.config(), store.master_key()?, path, "show");
+
+    match format {
+        ShowFormat::Dotenv => render_dotenv(&entry, prefix),
+        ShowFormat::Netrc => render_netrc(&entry),
+    }
+}
+
+/// Ошибка `pm get` со своим кодом выхода для вызывающей программы.
+#[derive(Debug)]
+pub struct GetError {
+    pub code: i32,
+    error: anyhow::Error,
+}
+
+impl GetError {
+    pub const NOT_FOUND: i32 = 2;
+    pub const WRONG_PASSWORD: i32 = 3;
+    pub const EMPTY_FIELD: i32 = 4;
+
+    fn fail(code: i32, error: anyhow::Error) -> anyhow::Error {
+        anyhow::Error::new(GetError { code, error })
+    }
+}
+
+impl std::fmt::Display for GetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:#}", self.error)
+    }
+}
+
+impl std::error::Error for GetError {}
+
+/// pm get PATH [--field F]: только значение. Отсутствующая запись, неверный
+/// мастер-пароль и пустое поле — `GetError` со своим кодом выхода.
+pub fn get(path: &str, field: &Field) -> Result<String> {
+    let store = PasswordStore::open(store_root()?)?;
+    if !store.exists(path)? {
+        return Err(GetError::fail(
+            GetError::NOT_FOUND,
+            anyhow!("Entry not found: {path}"),
+        ));
+    }
+    let mk = get_master_key_with_cache(store.config()).map_err(|e| {
+        match e.downcast_ref::<CryptoError>() {
+            Some(
+                CryptoError::InvalidMasterPassword | CryptoError::InvalidMasterPasswordOrKeyfile,
+            ) => GetError::fail(GetError::WRONG_PASSWORD, e),
+            _ => e,
+        }
+    })?;
+    let store = store.with_master_key(mk);
+
+    let mut entry = store.show(path)?;
+    let value = match field {
+        // Код HOTP берётся со сдвигом счётчика, как в pm clip
+        Field::Otp if entry.otp.is_some() => take_code(&store, path, &mut entry)?,
+        _ => field
+            .value(&entry, path)
+            .map_err(|e| GetError::fail(GetError::EMPTY_FIELD, e))?,
+    };
+    record_access(store.config(), store.master_key()?, path, "get");
+    Ok(value)
+}
+
+/// Сменённый пароль в `pm history`; номер 1 — последний.
+#[derive(Debug, Serialize)]
+pub struct PastPassword {
+    pub n: usize,
+    pub replaced: String,
+}
+
+/// pm history PATH: когда сменялись пароли, сами пароли не расшифровываются.
+pub fn history(path: &str) -> Result<Vec<PastPassword>> {
+    let store = PasswordStore::open_current()?;
+    let entry = store.show(path)?;
+    Ok(entry
+        .password_history
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, item)| PastPassword {
+            n: i + 1,
+            replaced: item.changed_at.clone(),
+        })
+        .collect())
+}
+
+/// pm history PATH --show N | --clip N: пароль номер `n`.
+pub fn previous_password(path: &str, n: usize, action: &str) -> Result<String> {
+    let store = PasswordStore::open_current()?;
+    let entry = store.show(path)?;
+    // Номер 1 — последний сменённый пароль, в векторе он в конце
+    let history = &entry.password_history;
+    let item = n
+        .checked_sub(1)
+        .and_then(|i| history.iter().rev().nth(i))
+        .ok_or_else(|| anyhow!("{path} has {} previous passwords, no #{n}", history.len()))?;
+    record_access(store.config(), store.master_key()?, path, action);
+    Ok(item.password.clone())
+}
+
+/// pm verify-password PATH [--field F]: совпадает ли кандидат из stdin
+/// с полем записи. Сравнение за постоянное время.
+pub fn verify_password(path: &str, field: &str) -> Result<bool> {
+    let store = PasswordStore::open_current()?;
+    let entry = store.show(path)?;
+    let stored = entry.field(field)?.unwrap_or_default();
+
+    let candidate = read_secret(&format!("Candidate {field}: "))?;
+    Ok(secrets_equal(stored, &candidate))
+}
@@ -0,0 +1,218 @@
+//! Вывод записи в виде готовых фрагментов для `.env` и `~/.netrc`.
+
+use crate::entry::Entry;
+use anyhow::{Result, anyhow};
+use url::Url;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum ShowFormat {
+    Dotenv,
+    Netrc,
+}
+
+/// Значение для dotenv. Простые значения пишем как есть, остальные — в двойных
+/// кавычках, экранируя `\`, `"`, `$`, `` ` `` (чтобы не было подстановок)
+/// и переводы строк как `\n`.
+pub fn dotenv_quote(value: &str) -> String {
+    let plain = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_-./:@+,".contains(c));
+    if plain {
+        return value.to_string();
+    }
+
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '\\' | '"' | '$' | '`' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            _ => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Токен для netrc. Токены разделяются пробелами, поэтому значения с пробелами,
+/// кавычками или пустые берём в двойные кавычки (так понимают curl и Python netrc).
+/// Перевод строки в netrc не выразить — такие значения отвергаем.
+pub fn netrc_quote(value: &str) -> Result<String> {
+    if value.contains(['\n', '\r']) {
+        return Err(anyhow!(
+            "value contains a newline and cannot be written to netrc"
+        ));
+    }
+    let needs_quotes = value.is_empty()
+        || value
+            .chars()
+            .any(|c| c.is_whitespace() || c == '"' || c == '\\');
+    if !needs_quotes {
+        return Ok(value.to_string());
+    }
+
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        if c == '"' || c == '\\' {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out.push('"');
+    Ok(out)
+}
+
+fn check_prefix(prefix: &str) -> Result<()> {
+    if prefix
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !prefix.starts_with(|c: char| c.is_ascii_digit())
+    {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "invalid prefix '{prefix}': use letters, digits and '_' (e.g. DB_)"
+        ))
+    }
+}
+
+/// `PREFIX_USERNAME=...` / `PREFIX_PASSWORD=...`
+pub fn render_dotenv(entry: &Entry, prefix: &str) -> Result<String> {
+    check_prefix(prefix)?;
+    let mut out = String::new();
+    if let Some(ref u) = entry.username {
+        out.push_str(&format!("{prefix}USERNAME={}\n", dotenv_quote(u)));
+    }
+    out.push_str(&format!(
+        "{prefix}PASSWORD={}\n",
+        dotenv_quote(&entry.password)
+    ));
+    Ok(out)
+}
+
+/// Хост из URL записи; URL без схемы (`github.com/login`) тоже принимаем.
+fn entry_host(entry: &Entry) -> Result<String> {
+    let raw = entry
+        .url
+        .as_deref()
+        .filter(|u| !u.trim().is_empty())
+        .ok_or_else(|| anyhow!("entry has no URL; netrc needs a machine name"))?
+        .trim();
+    let parsed = Url::parse(raw)
+        .ok()
+        .filter(|u| u.host_str().is_some())
+        .or_else(|| Url::parse(&format!("https://{raw}")).ok());
+    parsed
+        .as_ref()
+        .and_then(|u| u.host_str())
+        .map(|h| h.to_lowercase())
+        .ok_or_else(|| anyhow!("cannot determine host from URL '{raw}'"))
+}
+
+/// `machine HOST login USER password PASS`
+pub fn render_netrc(entry: &Entry) -> Result<String> {
+    let mut out = format!("machine {}", entry_host(entry)?);
+    if let Some(ref u) = entry.username {
+        out.push_str(&format!(" login {}", netrc_quote(u)?));
+    }
+    out.push_str(&format!(" password {}\n", netrc_quote(&entry.password)?));
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn login(username: Option<&str>, password: &str, url: Option<&str>) -> Entry {
+        let mut entry = crate::import::new_entry("db/prod").unwrap();
+        entry.username = username.map(str::to_string);
+        entry.password = password.to_string();
+        entry.url = url.map(str::to_string);
+        entry
+    }
+
+    #[test]
+    fn dotenv_quote_table() {
+        let cases = [
+            ("s3cret", "s3cret"),
+            ("user@example.com", "user@example.com"),
+            ("", r#""""#),
+            ("two words", r#""two words""#),
+            ("a$HOME", r#""a\$HOME""#),
+            ("$(rm -rf ~)", r#""\$(rm -rf ~)""#),
+            ("`id`", r#""\`id\`""#),
+            (r#"say "hi""#, r#""say \"hi\"""#),
+            (r"back\slash", r#""back\\slash""#),
+            ("line1\nline2", r#""line1\nline2""#),
+            ("crlf\r\n", r#""crlf\r\n""#),
+            ("it's", r#""it's""#),
+            ("#comment", r##""#comment""##),
+            ("пароль", r#""пароль""#),
+        ];
+        for (input, want) in cases {
+            assert_eq!(dotenv_quote(input), want, "{input:?}");
+        }
+    }
+
+    #[test]
+    fn netrc_quote_table() {
+        let cases = [
+            ("s3cret", "s3cret"),
+            ("$pecial#chars'", "$pecial#chars'"),
+            ("", r#""""#),
+            ("two words", r#""two words""#),
+            ("tab\there", "\"tab\there\""),
+            (r#"say "hi""#, r#""say \"hi\"""#),
+            (r"back\slash", r#""back\\slash""#),
+        ];
+        for (input, want) in cases {
+            assert_eq!(netrc_quote(input).unwrap(), want, "{input:?}");
+        }
+        for bad in ["a\nb", "a\rb"] {
+            assert!(netrc_quote(bad).is_err(), "{bad:?}");
+        }
+    }
+
+    #[test]
+    fn dotenv_prefix_and_missing_username() {
+        let entry = login(Some("app"), "p w$", None);
+        assert_eq!(
+            render_dotenv(&entry, "DB_").unwrap(),
+            "DB_USERNAME=app\nDB_PASSWORD=\"p w\\$\"\n"
+        );
+        assert_eq!(
+            render_dotenv(&login(None, "x", None), "").unwrap(),
+            "PASSWORD=x\n"
+        );
+        for bad in ["DB-", "1DB_", "DB PREFIX", "DB$"] {
+            assert!(render_dotenv(&entry, bad).is_err(), "{bad:?}");
+        }
+    }
+
+    #[test]
+    fn netrc_machine_from_url() {
+        let cases = [
+            ("https://GitHub.com/login", "github.com"),
+            ("github.com/login", "github.com"),
+            ("db.internal:5432", "db.internal"),
+            ("postgres://db.example.org:5432/app", "db.example.org"),
+        ];
+        for (url, host) in cases {
+            let entry = login(Some("me"), "two words", Some(url));
+            assert_eq!(
+                render_netrc(&entry).unwrap(),
+                format!("machine {host} login me password \"two words\"\n"),
+                "{url}"
+            );
+        }
+        assert!(render_netrc(&login(Some("me"), "x", None)).is_err());
+        assert!(render_netrc(&login(Some("me"), "x", Some("  "))).is_err());
+        assert!(render_netrc(&login(Some("me"), "a\nb", Some("github.com"))).is_err());
+    }
+}
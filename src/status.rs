@@ -0,0 +1,170 @@
+//! `pm status`: краткая сводка о хранилище без запроса мастер-пароля.
+//!
+//! Показывает, где лежит хранилище и сколько в нём записей, открыта ли
+//! сессия, параметры KDF из config.json (без соли), состояние git и какой
+//! буфер обмена будет использован. Попутно удаляет просроченные файлы сессии
+//! во всех местах, где они могли остаться (см. `session::session_status`).
+
+use crate::config::Config;
+use crate::session::{SessionStatus, session_status};
+use crate::store::{list_entries, store_root};
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+
+#[derive(Serialize)]
+struct Status {
+    store: String,
+    entries: usize,
+    session: SessionStatus,
+    display: Display,
+    kdf: Kdf,
+    git: Git,
+    /// Бэкенд буфера обмена; `None`, если подходящего нет
+    clipboard: Option<String>,
+}
+
+/// Режим показа пароля без `--reveal` (с учётом `PM_CONCEAL`).
+#[derive(Serialize)]
+struct Display {
+    /// `pm show` печатает `********`
+    masked_in_show: bool,
+    /// `pm show --json` печатает `<concealed>`
+    concealed_in_json: bool,
+}
+
+#[derive(Serialize)]
+struct Kdf {
+    algo: String,
+    memory_mib: u32,
+    iterations: u32,
+    parallelism: u32,
+    keyfile: bool,
+}
+
+#[derive(Serialize)]
+struct Git {
+    repository: bool,
+    auto_commit: bool,
+    /// Есть ли незакоммиченные изменения; `None`, если не репозиторий или git недоступен
+    dirty: Option<bool>,
+}
+
+/// "1h 5m", "4m 30s", "12s"
+fn human_duration(secs: u64) -> String {
+    match secs {
+        0..60 => format!("{secs}s"),
+        60..3600 => format!("{}m {}s", secs / 60, secs % 60),
+        _ => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+fn session_line(session: &SessionStatus) -> String {
+    if session.active {
+        let source = session.source.as_deref().unwrap_or("-");
+        match session.remaining_secs {
+            Some(secs) => format!("active ({source}), {} left", human_duration(secs)),
+            None => format!("active ({source})"),
+        }
+    } else if session.enabled {
+        "locked".to_string()
+    } else {
+        "disabled (session.ttl_secs = 0)".to_string()
+    }
+}
+
+/// pm status
+pub fn status() -> Result<()> {
+    let root = store_root()?;
+    if !root.exists() {
+        return Err(anyhow!(
+            "Store not found at {}. Run `pm init` first.",
+            root.display()
+        ));
+    }
+    let config = Config::load()?;
+    let session = session_status(&config)?;
+    let repository = root.join(".git").exists();
+    let dirty = if repository {
+        crate::git::is_dirty(&root)
+            .inspect_err(|e| eprintln!("Warning: cannot check the git working tree: {e}"))
+            .ok()
+    } else {
+        None
+    };
+    let status = Status {
+        store: root.display().to_string(),
+        entries: list_entries()?.len(),
+        session,
+        display: Display {
+            masked_in_show: config.settings.mask_password_in_show(false),
+            concealed_in_json: config.settings.conceal_password(false),
+        },
+        kdf: Kdf {
+            algo: config.kdf.algo.clone(),
+            memory_mib: config.kdf.memory_mib,
+            iterations: config.kdf.iterations,
+            parallelism: config.kdf.parallelism,
+            keyfile: config.kdf.keyfile,
+        },
+        git: Git {
+            repository,
+            auto_commit: repository && config.settings.git.auto_commit,
+            dirty,
+        },
+        clipboard: crate::clipboard::select(Default::default())
+            .ok()
+            .map(|b| b.name().to_string()),
+    };
+
+    crate::output::emit(&status, || {
+        let kdf = &status.kdf;
+        let git = if !status.git.repository {
+            "not a repository".to_string()
+        } else {
+            let tree = match status.git.dirty {
+                Some(true) => "dirty",
+                Some(false) => "clean",
+                None => "unknown",
+            };
+            let commit = if status.git.auto_commit { "on" } else { "off" };
+            format!("{tree} working tree, auto-commit {commit}")
+        };
+        println!("Store:      {}", status.store);
+        println!("Entries:    {}", status.entries);
+        println!("Session:    {}", session_line(&status.session));
+        let display = &status.display;
+        println!(
+            "Password:   {} in pm show, {} in --json",
+            if display.masked_in_show {
+                "masked"
+            } else {
+                "shown"
+            },
+            if display.concealed_in_json {
+                "concealed"
+            } else {
+                "shown"
+            }
+        );
+        println!(
+            "KDF:        {}, {} MiB, {} iterations, {} lanes{}",
+            kdf.algo,
+            kdf.memory_mib,
+            kdf.iterations,
+            kdf.parallelism,
+            if kdf.keyfile { ", key file" } else { "" }
+        );
+        println!("Git:        {git}");
+        println!(
+            "Clipboard:  {}",
+            status.clipboard.as_deref().unwrap_or("none available")
+        );
+        if status.session.stale_removed > 0 {
+            crate::output::note(format!(
+                "Removed {} expired session file(s)",
+                status.session.stale_removed
+            ));
+        }
+        Ok(())
+    })
+}
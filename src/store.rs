@@ -1,87 +1,643 @@
-use crate::crypto::{MasterKey, decrypt_entry, encrypt_entry};
+use crate::crypto::{ENTRY_ALGO, MasterKey, SUPPORTED_ENTRY_ALGOS, decrypt_entry, encrypt_entry};
 use crate::entry::Entry;
 use anyhow::Context;
 use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
-/// Корневая директория хранилища (например, ~/.local/share/pm-store)
+/// `--store`: корень хранилища на этот запуск, важнее `PM_STORE_DIR`.
+static STORE_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+pub fn set_store_override(dir: PathBuf) {
+    let _ = STORE_OVERRIDE.set(dir);
+}
+
+/// Корневая директория хранилища: `--store`, затем `PM_STORE_DIR`, иначе
+/// pm-store в каталоге данных (например, ~/.local/share/pm-store).
+/// Относительный путь считается от текущего каталога.
 pub fn store_root() -> anyhow::Result<PathBuf> {
+    let configured = STORE_OVERRIDE.get().cloned().or_else(|| {
+        std::env::var_os("PM_STORE_DIR")
+            .filter(|d| !d.is_empty())
+            .map(PathBuf::from)
+    });
+    if let Some(dir) = configured {
+        return Ok(std::path::absolute(dir)?);
+    }
     let mut dir = dirs::data_dir().ok_or_else(|| anyhow::anyhow!("cannot get data dir"))?;
     dir.push("pm-store");
     Ok(dir)
 }
 
+/// Короткий отпечаток пути хранилища (и профиля, если он выбран): у сессий
+/// и агентов разных хранилищ и профилей должны быть разные файлы.
+pub fn store_tag() -> anyhow::Result<String> {
+    Ok(store_tag_for(
+        &store_root()?,
+        crate::profiles::active_name(),
+    ))
+}
+
+/// `store_tag` хранилища `root`, открытого через профиль `profile`.
+pub fn store_tag_for(root: &Path, profile: Option<&str>) -> String {
+    use sha1::{Digest, Sha1};
+
+    let mut hasher = Sha1::new();
+    hasher.update(root.as_os_str().as_encoded_bytes());
+    if let Some(profile) = profile {
+        hasher.update(format!("\0profile:{profile}"));
+    }
+    let digest = hasher.finalize();
+    digest[..6].iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Строка в форме NFC (канонически составленной).
+pub fn nfc(s: &str) -> std::borrow::Cow<'_, str> {
+    icu_normalizer::ComposingNormalizerBorrowed::new_nfc().normalize(s)
+}
+
+/// Канонический вид пути записи: компоненты в NFC, разделитель `/`,
+/// без повторных и хвостовых разделителей.
+///
+/// macOS хранит имена файлов в NFD, Linux — как набрано (обычно NFC),
+/// поэтому `café/login` без нормализации на разных системах — разные записи.
+pub fn normalize_entry_path(path: &str) -> anyhow::Result<String> {
+    let mut parts = Vec::new();
+    for part in path.split(['/', '\\']).filter(|p| !p.is_empty()) {
+        let part = nfc(part);
+        if part.trim().is_empty() {
+            return Err(anyhow::anyhow!(
+                "invalid entry path {path:?}: empty path component"
+            ));
+        }
+        if part == "." || part == ".." {
+            return Err(anyhow::anyhow!(
+                "invalid entry path {path:?}: `.` and `..` are not allowed"
+            ));
+        }
+        if part.contains('\0') {
+            return Err(anyhow::anyhow!("invalid entry path {path:?}: NUL byte"));
+        }
+        parts.push(part.into_owned());
+    }
+    if parts.is_empty() {
+        return Err(anyhow::anyhow!("entry path is empty"));
+    }
+    Ok(parts.join("/"))
+}
+
+/// Служебные файлы хранилища: запись с таким именем путалась бы с ними.
+const RESERVED_NAMES: &[&str] = &["config.json", "session.json", "access.log"];
+
+/// Каталог вложений записи `work/github` — `store/work/github.attachments/`.
+pub const ATTACHMENTS_SUFFIX: &str = ".attachments";
+
+/// Имена устройств Windows (`CON`, `NUL`, `COM1`, ... с любым расширением):
+/// такой файл записи на Windows не открылся бы.
+pub(crate) fn is_windows_device(name: &str) -> bool {
+    let stem = name.split('.').next().unwrap_or(name).to_ascii_lowercase();
+    match stem.as_str() {
+        "con" | "prn" | "aux" | "nul" => true,
+        s => {
+            (s.starts_with("com") || s.starts_with("lpt"))
+                && s.len() == 4
+                && s.as_bytes()[3].is_ascii_digit()
+                && s.as_bytes()[3] != b'0'
+        }
+    }
+}
+
+/// Создать каталог со всеми недостающими родителями; новые каталоги
+/// сразу получают 0700 (на других системах — обычный `create_dir_all`).
+pub fn create_private_dir_all(path: &Path) -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        std::fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(path)
+    }
+    #[cfg(not(unix))]
+    std::fs::create_dir_all(path)
+}
+
+/// Проверить путь записи, введённый пользователем, и вернуть канонический вид.
+///
+/// В отличие от `normalize_entry_path`, которая терпима к лишним разделителям
+/// (префиксы папок, имена с диска), здесь запрещены ведущий и хвостовой `/`,
+/// пустые компоненты и зарезервированные имена — путь должен однозначно
+/// указывать на файл внутри `store/`.
+pub fn validate_entry_path(path: &str) -> anyhow::Result<String> {
+    let drive =
+        path.len() >= 2 && path.as_bytes()[0].is_ascii_alphabetic() && path.as_bytes()[1] == b':';
+    if path.starts_with(['/', '\\']) || drive {
+        return Err(anyhow::anyhow!(
+            "invalid entry path {path:?}: must be relative, like work/github"
+        ));
+    }
+    if path.ends_with(['/', '\\']) {
+        return Err(anyhow::anyhow!(
+            "invalid entry path {path:?}: trailing slash"
+        ));
+    }
+    if path.split(['/', '\\']).any(str::is_empty) {
+        return Err(anyhow::anyhow!(
+            "invalid entry path {path:?}: empty path component"
+        ));
+    }
+    let rel = normalize_entry_path(path)?;
+    for part in rel.split('/') {
+        if RESERVED_NAMES.iter().any(|r| r.eq_ignore_ascii_case(part)) || is_windows_device(part) {
+            return Err(anyhow::anyhow!(
+                "invalid entry path {path:?}: {part:?} is a reserved name"
+            ));
+        }
+        if part.ends_with(ATTACHMENTS_SUFFIX) {
+            return Err(anyhow::anyhow!(
+                "invalid entry path {path:?}: names ending in {ATTACHMENTS_SUFFIX} hold attachments"
+            ));
+        }
+    }
+    Ok(rel)
+}
+
+/// Ни один из родительских путей `rel` не должен быть записью: иначе
+/// `work/github` оказался бы одновременно записью и папкой.
+fn check_no_entry_ancestor(store_dir: &Path, rel: &str) -> anyhow::Result<()> {
+    for (i, _) in rel.match_indices('/') {
+        let prefix = &rel[..i];
+        if store_dir.join(format!("{prefix}.enc")).is_file() {
+            return Err(anyhow::anyhow!(
+                "cannot create {rel}: {prefix} is an entry, not a folder"
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Итоговый путь файла не должен выходить за `store/`, в том числе через
+/// символические ссылки: проверяем ближайший существующий предок.
+fn ensure_inside_store(store_dir: &Path, file: &Path) -> anyhow::Result<()> {
+    let Ok(root) = store_dir.canonicalize() else {
+        // Хранилища ещё нет — выходить некуда
+        return Ok(());
+    };
+    let mut existing = file;
+    while !existing.exists() {
+        match existing.parent() {
+            Some(p) => existing = p,
+            None => return Ok(()),
+        }
+    }
+    if !existing.canonicalize()?.starts_with(&root) {
+        return Err(anyhow::anyhow!(
+            "{} resolves outside the store",
+            file.display()
+        ));
+    }
+    Ok(())
+}
+
 /// Убедиться, что под директорию для записи созданы все папки
 pub fn ensure_store_dirs(entry_path: &str) -> anyhow::Result<()> {
     let root = store_root()?;
-    let entry_rel = entry_path.replace('\\', "/");
+    let entry_rel = validate_entry_path(entry_path)?;
+    check_no_entry_ancestor(&root.join("store"), &entry_rel)?;
     let p = Path::new(&entry_rel);
     if let Some(parent) = p.parent() {
         let store_dir = root.join("store").join(parent);
-        std::fs::create_dir_all(store_dir)?;
+        create_private_dir_all(&store_dir)?;
     } else {
-        std::fs::create_dir_all(root.join("store"))?;
+        create_private_dir_all(&root.join("store"))?;
     }
     Ok(())
 }
 
-fn entry_file_path(entry_path: &str) -> anyhow::Result<PathBuf> {
-    let root = store_root()?;
-    let rel = entry_path.replace('\\', "/");
-    Ok(root.join("store").join(rel).with_extension("enc"))
+/// Файл записи на диске. Если файла с каноническим именем нет, но есть
+/// такой же после нормализации (скопирован с macOS и т.п.) — берём его,
+/// чтобы не плодить дубликаты до `pm fsck --fix`.
+pub fn entry_file_path(entry_path: &str) -> anyhow::Result<PathBuf> {
+    entry_file_path_in(&store_root()?.join("store"), entry_path)
+}
+
+/// То же для каталога записей `store_dir` конкретного хранилища.
+pub(crate) fn entry_file_path_in(store_dir: &Path, entry_path: &str) -> anyhow::Result<PathBuf> {
+    let rel = validate_entry_path(entry_path)?;
+    let canonical = store_dir.join(format!("{rel}.enc"));
+    let file = if canonical.exists() {
+        canonical
+    } else {
+        find_noncanonical(store_dir, &canonical).unwrap_or(canonical)
+    };
+    ensure_inside_store(store_dir, &file)?;
+    Ok(file)
+}
+
+/// Каталог вложений записи `entry_path` в `store_dir`: рядом с файлом
+/// записи, с `.attachments` вместо `.enc`.
+pub(crate) fn attachments_dir_in(store_dir: &Path, entry_path: &str) -> anyhow::Result<PathBuf> {
+    Ok(entry_file_path_in(store_dir, entry_path)?.with_extension(&ATTACHMENTS_SUFFIX[1..]))
+}
+
+/// Есть ли запись с таким путём.
+pub fn entry_exists(path: &str) -> anyhow::Result<bool> {
+    Ok(entry_file_path(path)?.is_file())
+}
+
+/// Пройти от `store_dir` к `canonical`, сравнивая имена после NFC.
+fn find_noncanonical(store_dir: &Path, canonical: &Path) -> Option<PathBuf> {
+    let rel = canonical.strip_prefix(store_dir).ok()?;
+    let mut cur = store_dir.to_path_buf();
+    for comp in rel.components() {
+        let want = comp.as_os_str().to_str()?;
+        let direct = cur.join(want);
+        if direct.exists() {
+            cur = direct;
+            continue;
+        }
+        cur = std::fs::read_dir(&cur)
+            .ok()?
+            .filter_map(|e| e.ok())
+            .find(|e| e.file_name().to_str().is_some_and(|n| nfc(n) == want))?
+            .path();
+    }
+    Some(cur)
+}
+
+/// Текущая версия обёртки файла записи.
+/// v1 — JSON без выравнивания, v2 — открытый текст дополнен пробелами
+/// до кратного `PAD_BLOCK`, чтобы размер файла не выдавал длину секретов,
+/// v3 — нормализованный путь записи входит в шифртекст как AAD: файл,
+/// переложенный под другое имя, не расшифруется.
+pub const ENTRY_FILE_VERSION: u32 = 3;
+
+/// Первая версия обёртки, привязанная к пути записи.
+pub const PATH_BOUND_VERSION: u32 = 3;
+
+const PAD_BLOCK: usize = 256;
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct FileEntry {
+    version: u32,
+    /// В старых файлах поля нет — они зашифрованы XChaCha20-Poly1305
+    #[serde(default = "default_algo")]
+    algo: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+fn default_algo() -> String {
+    ENTRY_ALGO.to_string()
+}
+
+/// Сведения об обёртке файла записи, доступные без расшифровки.
+pub struct FileInfo {
+    pub version: u32,
+    pub algo: String,
+}
+
+/// Присоединённые данные для файла версии `version`: путь записи для v3+,
+/// пусто для старых файлов.
+fn entry_aad(version: u32, path: &str) -> anyhow::Result<Vec<u8>> {
+    if version >= PATH_BOUND_VERSION {
+        Ok(validate_entry_path(path)?.into_bytes())
+    } else {
+        Ok(Vec::new())
+    }
+}
+
+/// Расшифровать обёртку записи `path`. Для привязанных к пути файлов
+/// ошибка тега означает и чужое имя файла — об этом и говорим.
+fn decrypt_file_entry(
+    fe: &FileEntry,
+    path: &str,
+    master_key: &MasterKey,
+) -> anyhow::Result<Vec<u8>> {
+    let aad = entry_aad(fe.version, path)?;
+    decrypt_entry(master_key, &fe.nonce, &fe.ciphertext, &aad).map_err(|e| {
+        if fe.version >= PATH_BOUND_VERSION {
+            anyhow::anyhow!(
+                "entry {path} failed authentication: the file may have been renamed, \
+                 moved or tampered with, or belongs to another store"
+            )
+        } else {
+            e
+        }
+    })
+}
+
+fn read_file_entry(file_path: &Path) -> anyhow::Result<FileEntry> {
+    crate::log::debug(format_args!("reading {}", file_path.display()));
+    let data = std::fs::read_to_string(file_path)
+        .with_context(|| format!("cannot read entry file {}", file_path.display()))?;
+    Ok(serde_json::from_str(&data)?)
+}
+
+/// Проверить обёртку файла записи без расшифровки: JSON разбирается, шифр
+/// поддерживается, nonce и шифртекст — корректный base64.
+pub fn check_entry_envelope(path: &str) -> anyhow::Result<()> {
+    check_envelope_file(&entry_file_path(path)?)
+}
+
+/// То же для файла записи вне хранилища (например, в распакованном бэкапе).
+pub fn check_envelope_file(file_path: &Path) -> anyhow::Result<()> {
+    use base64::{Engine as _, engine::general_purpose};
+
+    let fe = read_file_entry(file_path)?;
+    if !SUPPORTED_ENTRY_ALGOS.contains(&fe.algo.as_str()) {
+        return Err(anyhow::anyhow!("unsupported cipher {}", fe.algo));
+    }
+    let nonce = general_purpose::STANDARD
+        .decode(&fe.nonce)
+        .context("nonce is not valid base64")?;
+    if nonce.len() != 24 {
+        return Err(anyhow::anyhow!(
+            "nonce is {} bytes, expected 24",
+            nonce.len()
+        ));
+    }
+    general_purpose::STANDARD
+        .decode(&fe.ciphertext)
+        .context("ciphertext is not valid base64")?;
+    Ok(())
+}
+
+/// Версия обёртки и шифр файла записи (без расшифровки).
+pub fn entry_file_info(path: &str) -> anyhow::Result<FileInfo> {
+    let fe = read_file_entry(&entry_file_path(path)?)?;
+    Ok(FileInfo {
+        version: fe.version,
+        algo: fe.algo,
+    })
+}
+
+/// Временный файл старше этого срока остался от прерванной записи.
+const STALE_TMP_AGE: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Удалить брошенные временные файлы `path` от прошлых прерванных записей.
+/// Свежие не трогаем: их может прямо сейчас писать другой процесс.
+fn remove_stale_tmp(dir: &Path, name: &str) {
+    let prefix = format!(".{name}.tmp-");
+    let Ok(rd) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in rd.flatten() {
+        let stale = entry
+            .file_name()
+            .to_str()
+            .is_some_and(|n| n.starts_with(&prefix))
+            && entry
+                .metadata()
+                .and_then(|m| m.modified())
+                .is_ok_and(|t| t.elapsed().is_ok_and(|age| age > STALE_TMP_AGE));
+        if stale {
+            let _ = std::fs::remove_file(entry.path());
+        }
+    }
+}
+
+/// fsync каталога, чтобы сам rename пережил отключение питания.
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> std::io::Result<()> {
+    std::fs::File::open(dir)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// Записать файл атомарно: во временный файл рядом, fsync, rename.
+/// При сбое на диске остаётся либо старое, либо новое содержимое. Файл с
+/// самого создания доступен только владельцу (0600), без окна, в котором
+/// временный файл читаем для всех.
+pub fn write_atomic_private(path: &Path, data: &[u8]) -> anyhow::Result<()> {
+    use std::io::Write;
+
+    // У `file.pmb` родитель — пустой путь, а не `.`
+    let dir = path
+        .parent()
+        .filter(|d| !d.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("file");
+    remove_stale_tmp(dir, name);
+    // Счётчик — чтобы параллельные записи из потоков одного процесса не столкнулись
+    static SEQ: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+    let seq = SEQ.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    let tmp = dir.join(format!(".{name}.tmp-{}-{seq}", std::process::id()));
+
+    let res = (|| {
+        let mut opts = std::fs::OpenOptions::new();
+        opts.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            opts.mode(0o600);
+        }
+
+        let mut f = opts.open(&tmp)?;
+        f.write_all(data)?;
+        f.sync_all()?;
+        std::fs::rename(&tmp, path)?;
+        sync_dir(dir)
+    })();
+    if res.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    res.with_context(|| format!("cannot write {}", path.display()))
 }
 
 /// Сохранить запись в зашифрованном виде
 pub fn save_entry(path: &str, entry: &Entry, master_key: &MasterKey) -> anyhow::Result<()> {
-    let file_path = entry_file_path(path)?;
+    save_entry_in(&store_root()?.join("store"), path, entry, master_key)
+}
+
+pub(crate) fn save_entry_in(
+    store_dir: &Path,
+    path: &str,
+    entry: &Entry,
+    master_key: &MasterKey,
+) -> anyhow::Result<()> {
+    crate::lock::ensure_writable()?;
+    let file_path = entry_file_path_in(store_dir, path)?;
+    check_no_entry_ancestor(store_dir, &validate_entry_path(path)?)?;
     if let Some(parent) = file_path.parent() {
-        std::fs::create_dir_all(parent)?;
+        create_private_dir_all(parent)?;
     }
 
-    let json = serde_json::to_vec(entry)?;
-    let (nonce_b64, ct_b64) = encrypt_entry(master_key, &json)?;
-
-    #[derive(serde::Serialize)]
-    struct FileEntry<'a> {
-        version: u32,
-        nonce: &'a str,
-        ciphertext: &'a str,
-    }
+    let mut entry = entry.clone();
+    entry.tags = crate::entry::normalize_tags(&entry.tags);
+    // Хвостовые пробелы serde_json при чтении игнорирует
+    let mut json = serde_json::to_vec(&entry)?;
+    json.resize(json.len().div_ceil(PAD_BLOCK) * PAD_BLOCK, b' ');
+    let aad = entry_aad(ENTRY_FILE_VERSION, path)?;
+    let (nonce, ciphertext) = encrypt_entry(master_key, &json, &aad)?;
 
     let fe = FileEntry {
-        version: 1,
-        nonce: &nonce_b64,
-        ciphertext: &ct_b64,
+        version: ENTRY_FILE_VERSION,
+        algo: ENTRY_ALGO.to_string(),
+        nonce,
+        ciphertext,
     };
 
     let s = serde_json::to_string_pretty(&fe)?;
-    std::fs::write(file_path, s)?;
+    write_atomic_private(&file_path, s.as_bytes())?;
+    crate::log::info(format_args!("wrote {}", file_path.display()));
+    crate::index::record_saved(store_dir, path, &entry, s.as_bytes(), master_key);
     Ok(())
 }
 
-/// Загрузить и расшифровать запись
+/// Загрузить и расшифровать запись, приведя её к текущей схеме
 pub fn load_entry(path: &str, master_key: &MasterKey) -> anyhow::Result<Entry> {
-    let file_path = entry_file_path(path)?;
-    let data = std::fs::read_to_string(&file_path)
-        .with_context(|| format!("cannot read entry file {}", file_path.display()))?;
+    load_entry_in(&store_root()?.join("store"), path, master_key)
+}
 
-    #[allow(dead_code)]
-    #[derive(serde::Deserialize)]
-    struct FileEntry {
-        version: u32,
-        nonce: String,
-        ciphertext: String,
-    }
+pub(crate) fn load_entry_in(
+    store_dir: &Path,
+    path: &str,
+    master_key: &MasterKey,
+) -> anyhow::Result<Entry> {
+    let mut entry = load_entry_raw_in(store_dir, path, master_key)?;
+    entry.upgrade_schema()?;
+    Ok(entry)
+}
+
+/// Загрузить запись как есть, с версией схемы из файла (для `pm migrate`)
+pub fn load_entry_raw(path: &str, master_key: &MasterKey) -> anyhow::Result<Entry> {
+    load_entry_raw_in(&store_root()?.join("store"), path, master_key)
+}
 
-    let fe: FileEntry = serde_json::from_str(&data)?;
-    let decrypted = decrypt_entry(master_key, &fe.nonce, &fe.ciphertext)?;
+fn load_entry_raw_in(
+    store_dir: &Path,
+    path: &str,
+    master_key: &MasterKey,
+) -> anyhow::Result<Entry> {
+    decrypt_entry_file(&entry_file_path_in(store_dir, path)?, path, master_key)
+}
+
+/// Расшифровать файл записи `path`, лежащий в `file_path` (в корзине или
+/// в распакованном бэкапе), без приведения схемы.
+pub fn decrypt_entry_file(
+    file_path: &Path,
+    path: &str,
+    master_key: &MasterKey,
+) -> anyhow::Result<Entry> {
+    let fe = read_file_entry(file_path)?;
+    if fe.version > ENTRY_FILE_VERSION {
+        return Err(anyhow::anyhow!(
+            "entry {path} uses file format v{}, which is newer than this pm supports",
+            fe.version
+        ));
+    }
+    if !SUPPORTED_ENTRY_ALGOS.contains(&fe.algo.as_str()) {
+        return Err(anyhow::anyhow!(
+            "entry {path} was encrypted with {} but this build lacks support",
+            fe.algo
+        ));
+    }
+    let decrypted = decrypt_file_entry(&fe, path, master_key).inspect_err(|e| {
+        crate::log::info(format_args!(
+            "cannot decrypt {} (format v{}, {}): {e}",
+            file_path.display(),
+            fe.version,
+            fe.algo
+        ))
+    })?;
     let entry: Entry = serde_json::from_slice(&decrypted)?;
     Ok(entry)
 }
 
-/// Вернуть список всех записей в виде путей `work/github`, `personal/mail` и т.п.
-pub fn list_entries() -> anyhow::Result<Vec<String>> {
-    let root = store_root()?;
-    let store_dir = root.join("store");
+/// Зашифровать произвольные данные (вложение) в такую же обёртку, как у
+/// записей, без выравнивания. `aad` привязывает файл к его месту.
+pub(crate) fn save_blob(
+    file_path: &Path,
+    aad: &str,
+    data: &[u8],
+    master_key: &MasterKey,
+) -> anyhow::Result<()> {
+    let (nonce, ciphertext) = encrypt_entry(master_key, data, aad.as_bytes())?;
+    let fe = FileEntry {
+        version: ENTRY_FILE_VERSION,
+        algo: ENTRY_ALGO.to_string(),
+        nonce,
+        ciphertext,
+    };
+    if let Some(parent) = file_path.parent() {
+        create_private_dir_all(parent)?;
+    }
+    write_atomic_private(file_path, serde_json::to_string_pretty(&fe)?.as_bytes())?;
+    crate::log::info(format_args!("wrote {}", file_path.display()));
+    Ok(())
+}
+
+/// Расшифровать данные, сохранённые `save_blob` с тем же `aad`.
+pub(crate) fn load_blob(
+    file_path: &Path,
+    aad: &str,
+    master_key: &MasterKey,
+) -> anyhow::Result<Vec<u8>> {
+    let fe = read_file_entry(file_path)?;
+    if !SUPPORTED_ENTRY_ALGOS.contains(&fe.algo.as_str()) {
+        return Err(anyhow::anyhow!(
+            "{aad} was encrypted with {} but this build lacks support",
+            fe.algo
+        ));
+    }
+    decrypt_entry(master_key, &fe.nonce, &fe.ciphertext, aad.as_bytes()).map_err(|_| {
+        anyhow::anyhow!(
+            "{aad} failed authentication: the file may have been moved or tampered with"
+        )
+    })
+}
+
+/// Перешифровать запись `path` ключом `new_key` и положить файл под `dest_store`
+/// с тем же относительным именем. Открытый текст вместе с выравниванием
+/// и версия обёртки (а с ней и привязка к пути) не меняются, нонс — новый.
+pub fn reencrypt_entry(
+    path: &str,
+    old_key: &MasterKey,
+    new_key: &MasterKey,
+    dest_store: &Path,
+) -> anyhow::Result<()> {
+    let src = entry_file_path(path)?;
+    let dest = dest_store.join(src.strip_prefix(store_root()?.join("store"))?);
+    reencrypt_file(&src, &dest, path, old_key, new_key)
+}
+
+/// Перешифровать файл записи `path`, лежащий в `src` (например, в корзине),
+/// и записать результат в `dest`. Версия формата и привязка к пути сохраняются.
+pub fn reencrypt_file(
+    src: &Path,
+    dest: &Path,
+    path: &str,
+    old_key: &MasterKey,
+    new_key: &MasterKey,
+) -> anyhow::Result<()> {
+    let fe = read_file_entry(src)?;
+    if !SUPPORTED_ENTRY_ALGOS.contains(&fe.algo.as_str()) {
+        return Err(anyhow::anyhow!(
+            "entry {path} was encrypted with {} but this build lacks support",
+            fe.algo
+        ));
+    }
+    let plain = decrypt_file_entry(&fe, path, old_key)?;
+    let aad = entry_aad(fe.version, path)?;
+    let (nonce, ciphertext) = encrypt_entry(new_key, &plain, &aad)?;
+    let out = FileEntry {
+        version: fe.version,
+        algo: ENTRY_ALGO.to_string(),
+        nonce,
+        ciphertext,
+    };
+
+    if let Some(parent) = dest.parent() {
+        create_private_dir_all(parent)?;
+    }
+    write_atomic_private(dest, serde_json::to_string_pretty(&out)?.as_bytes())
+}
+
+/// Пути записей в том виде, как они лежат на диске (без `.enc`, через `/`).
+fn list_entry_files(store_dir: &Path) -> anyhow::Result<Vec<String>> {
     if !store_dir.exists() {
         return Ok(Vec::new());
     }
@@ -91,7 +647,14 @@ pub fn list_entries() -> anyhow::Result<Vec<String>> {
             let entry = entry?;
             let path = entry.path();
             if path.is_dir() {
-                walk(&path, root, acc)?;
+                // Вложения — не записи
+                let attachments = path
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(|n| n.ends_with(ATTACHMENTS_SUFFIX));
+                if !attachments {
+                    walk(&path, root, acc)?;
+                }
             } else if path
                 .extension()
                 .and_then(|e| e.to_str())
@@ -113,7 +676,269 @@ pub fn list_entries() -> anyhow::Result<Vec<String>> {
     }
 
     let mut entries = Vec::new();
-    walk(&store_dir, &store_dir, &mut entries)?;
+    walk(store_dir, store_dir, &mut entries)?;
+    Ok(entries)
+}
+
+/// Вернуть список всех записей в виде путей `work/github`, `personal/mail` и т.п.
+/// Имена с диска приводятся к каноническому виду (`normalize_entry_path`).
+pub fn list_entries() -> anyhow::Result<Vec<String>> {
+    list_entries_in(&store_root()?.join("store"))
+}
+
+pub(crate) fn list_entries_in(store_dir: &Path) -> anyhow::Result<Vec<String>> {
+    let mut entries: Vec<String> = list_entry_files(store_dir)?
+        .into_iter()
+        .map(|s| normalize_entry_path(&s).unwrap_or(s))
+        .collect();
     entries.sort();
+    entries.dedup();
     Ok(entries)
 }
+
+/// Файл записи, имя которого не в каноническом виде.
+pub struct NonCanonicalName {
+    pub on_disk: String,
+    pub canonical: String,
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+/// Один и тот же файл? На macOS `café` в NFC и NFD — одно имя,
+/// и такие случаи проблемой не считаются.
+fn same_file(a: &Path, b: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        match (std::fs::metadata(a), std::fs::metadata(b)) {
+            (Ok(x), Ok(y)) => x.dev() == y.dev() && x.ino() == y.ino(),
+            _ => false,
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (a, b);
+        false
+    }
+}
+
+/// Файлы записей, чьи имена на диске отличаются от канонических.
+pub fn noncanonical_entry_names() -> anyhow::Result<Vec<NonCanonicalName>> {
+    let store_dir = store_root()?.join("store");
+    let mut out = Vec::new();
+    for on_disk in list_entry_files(&store_dir)? {
+        let Ok(canonical) = normalize_entry_path(&on_disk) else {
+            continue;
+        };
+        if canonical == on_disk {
+            continue;
+        }
+        let from = store_dir.join(format!("{on_disk}.enc"));
+        let to = store_dir.join(format!("{canonical}.enc"));
+        if same_file(&from, &to) {
+            continue;
+        }
+        out.push(NonCanonicalName {
+            on_disk,
+            canonical,
+            from,
+            to,
+        });
+    }
+    out.sort_by(|a, b| a.on_disk.cmp(&b.on_disk));
+    Ok(out)
+}
+
+/// Переименовать файл записи в каноническое имя и убрать опустевшие каталоги.
+pub fn rename_to_canonical(name: &NonCanonicalName) -> anyhow::Result<()> {
+    if name.to.exists() {
+        return Err(anyhow::anyhow!(
+            "both {} and {} exist; merge them by hand",
+            name.on_disk,
+            name.canonical
+        ));
+    }
+    if let Some(parent) = name.to.parent() {
+        create_private_dir_all(parent)?;
+    }
+    std::fs::rename(&name.from, &name.to)?;
+    remove_empty_parents(&store_root()?.join("store"), &name.from)
+}
+
+/// Удалить опустевшие каталоги над `file`, не поднимаясь выше `store/`.
+pub(crate) fn remove_empty_parents(store_dir: &Path, file: &Path) -> anyhow::Result<()> {
+    let mut dir = file.parent();
+    while let Some(d) = dir {
+        if d == store_dir || !d.starts_with(store_dir) || std::fs::remove_dir(d).is_err() {
+            break;
+        }
+        dir = d.parent();
+    }
+    Ok(())
+}
+
+/// Удалить файл записи и опустевшие после этого каталоги.
+pub fn delete_entry(path: &str) -> anyhow::Result<()> {
+    delete_entry_in(&store_root()?.join("store"), path)
+}
+
+pub(crate) fn delete_entry_in(store_dir: &Path, path: &str) -> anyhow::Result<()> {
+    crate::lock::ensure_writable()?;
+    let file_path = entry_file_path_in(store_dir, path)?;
+    if !file_path.is_file() {
+        return Err(anyhow::anyhow!("Entry not found: {path}"));
+    }
+    std::fs::remove_file(&file_path)
+        .with_context(|| format!("cannot delete {}", file_path.display()))?;
+    crate::log::info(format_args!("deleted {}", file_path.display()));
+    let attachments = attachments_dir_in(store_dir, path)?;
+    if attachments.is_dir() {
+        std::fs::remove_dir_all(&attachments)
+            .with_context(|| format!("cannot delete {}", attachments.display()))?;
+    }
+    remove_empty_parents(store_dir, &file_path)
+}
+
+/// Записи в папке `prefix` (или сама запись с таким путём); без префикса — все.
+pub fn list_entries_under(prefix: Option<&str>) -> anyhow::Result<Vec<String>> {
+    let mut entries = list_entries()?;
+    if let Some(prefix) = prefix {
+        let pref = normalize_entry_path(prefix)?;
+        let pref_slash = format!("{pref}/");
+        entries.retain(|e| *e == pref || e.starts_with(&pref_slash));
+    }
+    Ok(entries)
+}
+
+/// Расшифровать пачку записей параллельно (ограниченный пул потоков).
+///
+/// Порядок результатов совпадает с порядком `paths`, ошибка одной записи
+/// не прерывает остальные. Число потоков берётся из `PM_DECRYPT_THREADS`,
+/// иначе — по числу ядер (на слабых машинах можно выставить 1).
+pub fn decrypt_all(
+    master_key: &MasterKey,
+    paths: Vec<String>,
+) -> impl Iterator<Item = (String, anyhow::Result<Entry>)> {
+    decrypt_all_with(master_key, paths, load_entry)
+}
+
+/// `decrypt_all` без миграции схемы — через `load_entry_raw`.
+pub fn decrypt_all_raw(
+    master_key: &MasterKey,
+    paths: Vec<String>,
+) -> impl Iterator<Item = (String, anyhow::Result<Entry>)> {
+    decrypt_all_with(master_key, paths, load_entry_raw)
+}
+
+fn decrypt_all_with(
+    master_key: &MasterKey,
+    paths: Vec<String>,
+    load: fn(&str, &MasterKey) -> anyhow::Result<Entry>,
+) -> impl Iterator<Item = (String, anyhow::Result<Entry>)> {
+    par_map(paths, |path| load(path, master_key)).into_iter()
+}
+
+/// Применить `f` к каждому пути в ограниченном пуле потоков; порядок
+/// результатов совпадает с порядком `paths`.
+pub(crate) fn par_map<T, F>(paths: Vec<String>, f: F) -> Vec<(String, T)>
+where
+    T: Send,
+    F: Fn(&str) -> T + Sync,
+{
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let threads = decrypt_threads().min(paths.len()).max(1);
+    let next = AtomicUsize::new(0);
+
+    let mut results: Vec<(usize, T)> = std::thread::scope(|s| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                s.spawn(|| {
+                    let mut out = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(path) = paths.get(i) else {
+                            break;
+                        };
+                        out.push((i, f(path)));
+                    }
+                    out
+                })
+            })
+            .collect();
+
+        workers
+            .into_iter()
+            .flat_map(|w| w.join().expect("decrypt worker panicked"))
+            .collect()
+    });
+
+    results.sort_by_key(|(i, _)| *i);
+    paths
+        .into_iter()
+        .zip(results)
+        .map(|(path, (_, res))| (path, res))
+        .collect()
+}
+
+fn decrypt_threads() -> usize {
+    std::env::var("PM_DECRYPT_THREADS")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .filter(|&n| n > 0)
+        .unwrap_or_else(|| {
+            std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_entry_path_table() {
+        let cases = [
+            ("work/github", "work/github"),
+            // NFD (как пишет macOS) и NFC дают одно имя
+            ("cafe\u{301}/login", "caf\u{e9}/login"),
+            ("caf\u{e9}/login", "caf\u{e9}/login"),
+            ("A\u{30a}ngstro\u{308}m", "\u{c5}ngstr\u{f6}m"),
+            ("Å", "\u{c5}"),
+            // Эмодзи и составные последовательности не трогаем
+            ("keys/🔑", "keys/🔑"),
+            ("family/👩\u{200d}💻", "family/👩\u{200d}💻"),
+            // Разделители: `\`, повторные, хвостовые
+            ("work\\github", "work/github"),
+            ("work\\\\sub//github/", "work/sub/github"),
+            ("/work/github", "work/github"),
+            ("  spaced name /x", "  spaced name /x"),
+        ];
+        for (input, want) in cases {
+            assert_eq!(normalize_entry_path(input).unwrap(), want, "{input:?}");
+        }
+        for bad in ["", "/", "\\//", "work/ /x", "work/../x", "./x", "a/\0"] {
+            assert!(normalize_entry_path(bad).is_err(), "{bad:?}");
+        }
+    }
+
+    #[test]
+    fn validate_entry_path_is_stricter() {
+        assert_eq!(
+            validate_entry_path("cafe\u{301}\\login").unwrap(),
+            "caf\u{e9}/login"
+        );
+        for bad in [
+            "/abs",
+            "C:\\x",
+            "work/",
+            "work//github",
+            "work/config.json",
+            "work/CON",
+            "work/x.attachments",
+        ] {
+            assert!(validate_entry_path(bad).is_err(), "{bad:?}");
+        }
+    }
+}
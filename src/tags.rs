@@ -0,0 +1,115 @@
+//! Метки записей: `pm tag add/rm/list` и фильтр `pm ls --tag`.
+//!
+//! Метки лежат внутри зашифрованной записи; список и фильтр читают их
+//! из индекса (мастер-ключ спрашивается один раз).
+
+use crate::config::Config;
+use crate::entry::normalize_tags;
+use crate::git::auto_commit;
+use crate::hooks::{HookAction, run_hooks};
+use crate::index::metadata;
+use crate::session::get_master_key_with_cache;
+use crate::store::{list_entries, load_entry, save_entry, validate_entry_path};
+use anyhow::{Result, anyhow};
+use std::collections::BTreeMap;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+/// Нормализованная метка из аргумента команды; пустая — ошибка.
+fn one_tag(tag: &str) -> Result<String> {
+    if tag.contains(',') {
+        return Err(anyhow!(
+            "tag {tag:?} contains a comma; add tags one at a time"
+        ));
+    }
+    normalize_tags([tag])
+        .pop()
+        .ok_or_else(|| anyhow!("tag cannot be empty"))
+}
+
+/// Добавить (`add`) или снять метку с записи и сохранить её.
+fn change_tag(path: &str, tag: &str, add: bool) -> Result<()> {
+    let path = validate_entry_path(path)?;
+    let tag = one_tag(tag)?;
+    let config = Config::load()?;
+    let mk = get_master_key_with_cache(&config)?;
+    let mut entry = load_entry(&path, &mk)?;
+
+    let has = entry.tags.iter().any(|t| t.to_lowercase() == tag);
+    let message = match (add, has) {
+        (true, true) => format!("{path} is already tagged {tag}"),
+        (false, false) => return Err(anyhow!("{path} is not tagged {tag}")),
+        (true, false) => {
+            entry.tags.push(tag.clone());
+            format!("Tagged {path} with {tag}")
+        }
+        (false, true) => {
+            entry.tags.retain(|t| t.to_lowercase() != tag);
+            format!("Removed tag {tag} from {path}")
+        }
+    };
+    if add != has {
+        entry.updated_at = OffsetDateTime::now_utc().format(&Rfc3339)?;
+        save_entry(&path, &entry, &mk)?;
+        auto_commit(&config, HookAction::Edit, &path);
+        run_hooks(&config, HookAction::Edit, &path);
+    }
+    crate::output::done(if add { "tag" } else { "untag" }, &path, message);
+    Ok(())
+}
+
+/// pm tag add <path> <tag>
+pub fn tag_add(path: &str, tag: &str) -> Result<()> {
+    change_tag(path, tag, true)
+}
+
+/// pm tag rm <path> <tag>
+pub fn tag_rm(path: &str, tag: &str) -> Result<()> {
+    change_tag(path, tag, false)
+}
+
+/// Записи из `paths` с меткой `tag`.
+pub fn filter_by_tag(paths: Vec<String>, tag: &str) -> Result<Vec<String>> {
+    let tag = one_tag(tag)?;
+    if paths.is_empty() {
+        return Ok(paths);
+    }
+    let config = Config::load()?;
+    let mk = get_master_key_with_cache(&config)?;
+    let mut kept = Vec::new();
+    for (path, meta) in metadata(&mk, paths)? {
+        match meta {
+            Ok(m) if normalize_tags(&m.tags).contains(&tag) => kept.push(path),
+            Ok(_) => {}
+            Err(e) => eprintln!("Warning: cannot decrypt {path}: {e}"),
+        }
+    }
+    Ok(kept)
+}
+
+/// pm tag list: все метки с числом записей.
+pub fn tag_list() -> Result<()> {
+    let config = Config::load()?;
+    let mk = get_master_key_with_cache(&config)?;
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for (path, meta) in metadata(&mk, list_entries()?)? {
+        match meta {
+            Ok(m) => {
+                for tag in normalize_tags(&m.tags) {
+                    *counts.entry(tag).or_default() += 1;
+                }
+            }
+            Err(e) => eprintln!("Warning: cannot decrypt {path}: {e}"),
+        }
+    }
+    crate::output::emit(&serde_json::json!({ "tags": counts }), || {
+        if counts.is_empty() {
+            println!("No tags in use.");
+        }
+        let width = counts.keys().map(|t| t.chars().count()).max().unwrap_or(0);
+        for (tag, n) in &counts {
+            println!("{tag:<width$}  {n}");
+        }
+        Ok(())
+    })
+}
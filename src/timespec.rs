@@ -0,0 +1,250 @@
+//! Разбор дат и длительностей для фильтров (`--modified-since 90d` и т.п.).
+
+use anyhow::{Result, anyhow};
+use time::format_description::well_known::Rfc3339;
+use time::{Date, Duration, Month, OffsetDateTime};
+
+/// Длительность вида `90d`, `2w`, `1y`, `12h`, `30m`, `6mo`.
+/// Месяц считаем за 30 дней, год — за 365.
+pub fn parse_duration(s: &str) -> Result<Duration> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| anyhow!("missing unit in duration '{s}' (use s/m/h/d/w/mo/y)"))?;
+    let (num, unit) = s.split_at(split);
+    let n: i64 = num.parse().map_err(|_| anyhow!("invalid duration '{s}'"))?;
+    let d = match unit {
+        "s" => Duration::seconds(n),
+        "m" | "min" => Duration::minutes(n),
+        "h" => Duration::hours(n),
+        "d" => Duration::days(n),
+        "w" => Duration::weeks(n),
+        "mo" => Duration::days(n * 30),
+        "y" => Duration::days(n * 365),
+        _ => {
+            return Err(anyhow!(
+                "unknown unit '{unit}' in '{s}' (use s/m/h/d/w/mo/y)"
+            ));
+        }
+    };
+    Ok(d)
+}
+
+fn parse_ymd(s: &str) -> Option<Date> {
+    let mut parts = s.splitn(3, '-');
+    let y: i32 = parts.next()?.parse().ok()?;
+    let m: u8 = parts.next()?.parse().ok()?;
+    let d: u8 = parts.next()?.parse().ok()?;
+    Date::from_calendar_date(y, Month::try_from(m).ok()?, d).ok()
+}
+
+/// Момент времени: RFC3339, `YYYY-MM-DD` (полночь UTC) или длительность
+/// назад от `now` (`90d` = 90 дней назад).
+pub fn parse_time_spec(s: &str, now: OffsetDateTime) -> Result<OffsetDateTime> {
+    let s = s.trim();
+    if let Ok(t) = OffsetDateTime::parse(s, &Rfc3339) {
+        return Ok(t);
+    }
+    if let Some(date) = parse_ymd(s) {
+        return Ok(date.midnight().assume_utc());
+    }
+    parse_duration(s).map(|d| now - d).map_err(|_| {
+        anyhow!("invalid date '{s}': expected YYYY-MM-DD, RFC3339 or a duration like 90d")
+    })
+}
+
+/// Срок действия записи: `YYYY-MM-DD` (полночь UTC) или RFC3339.
+/// Хранится нормализованным — RFC3339 в UTC, чтобы строки сравнивались как даты.
+pub fn parse_expiry(s: &str) -> Result<String> {
+    let s = s.trim();
+    let t = match parse_ymd(s) {
+        Some(date) => date.midnight().assume_utc(),
+        None => OffsetDateTime::parse(s, &Rfc3339)
+            .map_err(|_| anyhow!("invalid expiry date '{s}': expected YYYY-MM-DD or RFC3339"))?
+            .to_offset(time::UtcOffset::UTC),
+    };
+    Ok(t.format(&Rfc3339)?)
+}
+
+/// Истёк ли срок `expires_at` к моменту `now`; неразборчивая дата — не истекла.
+pub fn is_expired(expires_at: &str, now: OffsetDateTime) -> bool {
+    OffsetDateTime::parse(expires_at, &Rfc3339).is_ok_and(|t| t <= now)
+}
+
+/// Момент для `pm otp show --at`: Unix-время в секундах или RFC3339.
+pub fn parse_timestamp(s: &str) -> Result<u64> {
+    let s = s.trim();
+    if let Ok(secs) = s.parse::<u64>() {
+        return Ok(secs);
+    }
+    let t = OffsetDateTime::parse(s, &Rfc3339)
+        .map_err(|_| anyhow!("invalid time '{s}': expected Unix seconds or RFC3339"))?;
+    u64::try_from(t.unix_timestamp()).map_err(|_| anyhow!("time '{s}' is before 1970"))
+}
+
+/// Фильтр по `created_at` / `updated_at`.
+/// Граница `since` включительная, `before` — строгая.
+#[derive(Debug, Default, Clone)]
+pub struct DateFilter {
+    pub modified_since: Option<OffsetDateTime>,
+    pub modified_before: Option<OffsetDateTime>,
+    pub created_since: Option<OffsetDateTime>,
+    pub created_before: Option<OffsetDateTime>,
+}
+
+fn in_range(ts: &str, since: Option<OffsetDateTime>, before: Option<OffsetDateTime>) -> bool {
+    if since.is_none() && before.is_none() {
+        return true;
+    }
+    let Ok(t) = OffsetDateTime::parse(ts, &Rfc3339) else {
+        return false;
+    };
+    since.is_none_or(|s| t >= s) && before.is_none_or(|b| t < b)
+}
+
+impl DateFilter {
+    pub fn is_empty(&self) -> bool {
+        self.modified_since.is_none()
+            && self.modified_before.is_none()
+            && self.created_since.is_none()
+            && self.created_before.is_none()
+    }
+
+    pub fn matches(&self, created_at: &str, updated_at: &str) -> bool {
+        in_range(updated_at, self.modified_since, self.modified_before)
+            && in_range(created_at, self.created_since, self.created_before)
+    }
+}
+
+/// Общие флаги фильтрации по датам для `ls` и подобных команд.
+#[derive(clap::Args, Debug, Default)]
+pub struct DateFilterArgs {
+    /// Only entries modified at or after DATE (YYYY-MM-DD, RFC3339 or e.g. 90d)
+    #[arg(long, value_name = "DATE")]
+    pub modified_since: Option<String>,
+    /// Only entries modified before DATE
+    #[arg(long, value_name = "DATE")]
+    pub modified_before: Option<String>,
+    /// Only entries created at or after DATE
+    #[arg(long, value_name = "DATE")]
+    pub created_since: Option<String>,
+    /// Only entries created before DATE
+    #[arg(long, value_name = "DATE")]
+    pub created_before: Option<String>,
+}
+
+impl DateFilterArgs {
+    pub fn to_filter(&self, now: OffsetDateTime) -> Result<DateFilter> {
+        let parse = |s: &Option<String>| s.as_deref().map(|s| parse_time_spec(s, now)).transpose();
+        Ok(DateFilter {
+            modified_since: parse(&self.modified_since)?,
+            modified_before: parse(&self.modified_before)?,
+            created_since: parse(&self.created_since)?,
+            created_before: parse(&self.created_before)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> OffsetDateTime {
+        OffsetDateTime::parse(s, &Rfc3339).unwrap()
+    }
+
+    #[test]
+    fn duration_units() {
+        let cases = [
+            ("45s", Duration::seconds(45)),
+            ("30m", Duration::minutes(30)),
+            ("30min", Duration::minutes(30)),
+            ("12h", Duration::hours(12)),
+            (" 90d ", Duration::days(90)),
+            ("2w", Duration::weeks(2)),
+            ("6mo", Duration::days(180)),
+            ("1y", Duration::days(365)),
+            ("0d", Duration::ZERO),
+        ];
+        for (input, want) in cases {
+            assert_eq!(parse_duration(input).unwrap(), want, "{input:?}");
+        }
+        for bad in ["", "90", "d", "-5d", "1.5d", "3x", "1Y"] {
+            assert!(parse_duration(bad).is_err(), "{bad:?}");
+        }
+    }
+
+    #[test]
+    fn time_spec_forms() {
+        let now = at("2024-03-10T12:00:00Z");
+        assert_eq!(
+            parse_time_spec("2024-01-01", now).unwrap(),
+            at("2024-01-01T00:00:00Z")
+        );
+        assert_eq!(
+            parse_time_spec("2024-01-01T10:00:00+03:00", now).unwrap(),
+            at("2024-01-01T07:00:00Z")
+        );
+        assert_eq!(
+            parse_time_spec("10d", now).unwrap(),
+            at("2024-02-29T12:00:00Z")
+        );
+        for bad in ["2024-02-30", "2024-13-01", "yesterday", "2024/01/01"] {
+            assert!(parse_time_spec(bad, now).is_err(), "{bad:?}");
+        }
+    }
+
+    #[test]
+    fn since_is_inclusive_and_before_is_strict() {
+        let now = at("2024-03-10T12:00:00Z");
+        let args = DateFilterArgs {
+            modified_since: Some("2024-01-01".into()),
+            modified_before: Some("2024-02-01".into()),
+            ..Default::default()
+        };
+        let filter = args.to_filter(now).unwrap();
+        let created = "2020-01-01T00:00:00Z";
+        assert!(filter.matches(created, "2024-01-01T00:00:00Z"));
+        assert!(filter.matches(created, "2024-01-31T23:59:59Z"));
+        assert!(!filter.matches(created, "2023-12-31T23:59:59Z"));
+        assert!(!filter.matches(created, "2024-02-01T00:00:00Z"));
+        // Неразборчивая дата под фильтр не попадает
+        assert!(!filter.matches(created, "someday"));
+
+        // Относительная граница считается от `now` и тоже включительна
+        let filter = DateFilterArgs {
+            created_since: Some("1d".into()),
+            ..Default::default()
+        }
+        .to_filter(now)
+        .unwrap();
+        assert!(filter.matches("2024-03-09T12:00:00Z", "2024-03-09T12:00:00Z"));
+        assert!(!filter.matches("2024-03-09T11:59:59Z", "2024-03-10T00:00:00Z"));
+
+        assert!(DateFilter::default().is_empty());
+        assert!(DateFilter::default().matches("garbage", "garbage"));
+    }
+
+    #[test]
+    fn expiry_is_normalized_to_utc() {
+        assert_eq!(parse_expiry("2025-06-30").unwrap(), "2025-06-30T00:00:00Z");
+        assert_eq!(
+            parse_expiry("2025-06-30T02:00:00+03:00").unwrap(),
+            "2025-06-29T23:00:00Z"
+        );
+        assert!(parse_expiry("30.06.2025").is_err());
+
+        let expiry = parse_expiry("2025-06-30").unwrap();
+        assert!(is_expired(&expiry, at("2025-06-30T00:00:00Z")));
+        assert!(!is_expired(&expiry, at("2025-06-29T23:59:59Z")));
+        assert!(!is_expired("not a date", at("2099-01-01T00:00:00Z")));
+    }
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(parse_timestamp("1700000000").unwrap(), 1_700_000_000);
+        assert_eq!(parse_timestamp("1970-01-01T00:01:00Z").unwrap(), 60);
+        assert!(parse_timestamp("1969-12-31T23:59:59Z").is_err());
+        assert!(parse_timestamp("soon").is_err());
+    }
+}
@@ -0,0 +1,258 @@
+//! `pm mv/cp --to-profile`: перенос записей в хранилище другого профиля.
+//!
+//! У хранилищ разные мастер-ключи, поэтому запись — вместе с OTP, полями и
+//! вложениями — расшифровывается ключом источника и заново шифруется ключом
+//! назначения. При переносе исходная запись удаляется только после того, как
+//! копию удалось прочитать из хранилища назначения и она совпала с
+//! оригиналом. Хуки и git-автокоммит срабатывают только в исходном хранилище.
+//!
+//! Здесь же обычные `pm mv`/`pm cp` внутри одного хранилища.
+
+use crate::attachments;
+use crate::config::Config;
+use crate::entry::{Entry, normalize_tags};
+use crate::git::auto_commit;
+use crate::hooks::{HookAction, run_hooks};
+use crate::import::OnConflict;
+use crate::lock::{Access, acquire_at};
+use crate::output;
+use crate::password_store::PasswordStore;
+use crate::profiles::Profiles;
+use crate::session::{get_master_key_with_cache, unlock_profile};
+use crate::store::{
+    delete_entry, ensure_store_dirs, entry_exists, list_entries, load_entry, store_root,
+    validate_entry_path,
+};
+use anyhow::{Context, Result, anyhow, bail};
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+/// Что делать с исходной записью.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Mode {
+    Move,
+    Copy { no_otp: bool },
+}
+
+/// Пары (откуда, куда) для `pm mv`: одна запись или все записи папки.
+pub fn move_plan(src: &str, dst: &str) -> Result<Vec<(String, String)>> {
+    if entry_exists(src)? {
+        return Ok(vec![(src.to_string(), dst.to_string())]);
+    }
+    let folder = format!("{src}/");
+    if dst.starts_with(&folder) {
+        bail!("cannot move folder {src} into itself");
+    }
+    let plan: Vec<_> = list_entries()?
+        .into_iter()
+        .filter_map(|p| {
+            let rest = p.strip_prefix(&folder)?.to_string();
+            Some((p, format!("{dst}/{rest}")))
+        })
+        .collect();
+    if plan.is_empty() {
+        bail!("Entry not found: {src}");
+    }
+    Ok(plan)
+}
+
+/// pm mv SRC DST [--force]: запись или папка внутри хранилища. Возвращает
+/// перенесённые пары (откуда, куда).
+pub fn move_entries(src: &str, dst: &str, force: bool) -> Result<Vec<(String, String)>> {
+    let src = validate_entry_path(src)?;
+    let dst = validate_entry_path(dst)?;
+    if src == dst {
+        bail!("source and destination are the same");
+    }
+
+    let plan = move_plan(&src, &dst)?;
+    if !force {
+        for (_, to) in &plan {
+            if entry_exists(to)? {
+                bail!("{to} already exists (use --force to overwrite)");
+            }
+        }
+    }
+
+    let store = PasswordStore::open_current()?;
+    let config = store.config();
+    for (from, to) in &plan {
+        // Шифртекст привязан к пути, поэтому файл не переименовываем,
+        // а перешифровываем под новым путём; заголовок совпадает с путём.
+        // Вложения привязаны так же и переезжают тем же способом
+        let mut entry = store.show(from)?;
+        entry.title = to.clone();
+        let files = store.attachments(from, &entry)?;
+        store.save_with_attachments(to, &mut entry, &files)?;
+        store.remove(from)?;
+        auto_commit(config, HookAction::Rm, from);
+        run_hooks(config, HookAction::Rm, from);
+        auto_commit(config, HookAction::Add, to);
+        run_hooks(config, HookAction::Add, to);
+    }
+    Ok(plan)
+}
+
+/// pm cp SRC DST [--force] [--no-otp]: копия с новыми датами создания и
+/// изменения. Возвращает нормализованные пути (откуда, куда).
+pub fn copy_entry(src: &str, dst: &str, force: bool, no_otp: bool) -> Result<(String, String)> {
+    let src = validate_entry_path(src)?;
+    let dst = validate_entry_path(dst)?;
+    if !force && entry_exists(&dst)? {
+        bail!("{dst} already exists (use --force to overwrite)");
+    }
+
+    let store = PasswordStore::open_current()?;
+    let config = store.config();
+    let mut entry = store.show(&src)?;
+
+    let now = OffsetDateTime::now_utc().format(&Rfc3339)?;
+    entry.title = dst.clone();
+    entry.created_at = now.clone();
+    entry.updated_at = now;
+    if no_otp {
+        entry.otp = None;
+    }
+
+    ensure_store_dirs(&dst)?;
+    let files = store.attachments(&src, &entry)?;
+    store.save_with_attachments(&dst, &mut entry, &files)?;
+    auto_commit(config, HookAction::Add, &dst);
+    run_hooks(config, HookAction::Add, &dst);
+    Ok((src, dst))
+}
+
+/// pm mv/cp SRC DST --to-profile NAME [--on-conflict skip|overwrite|rename]
+pub fn to_profile(
+    src: &str,
+    dst: &str,
+    profile: &str,
+    mode: Mode,
+    on_conflict: OnConflict,
+) -> Result<()> {
+    let src = validate_entry_path(src)?;
+    let dst = validate_entry_path(dst)?;
+    let (action, verb) = match mode {
+        Mode::Move => ("mv", "Moved"),
+        Mode::Copy { .. } => ("cp", "Copied"),
+    };
+
+    let dest_root = std::path::absolute(&Profiles::load()?.get(profile)?.path)?;
+    if same_store(&dest_root, &store_root()?) {
+        bail!("profile {profile} is the current store; use pm {action} without --to-profile");
+    }
+    let plan = match mode {
+        Mode::Move => move_plan(&src, &dst)?,
+        Mode::Copy { .. } if entry_exists(&src)? => vec![(src.clone(), dst.clone())],
+        Mode::Copy { .. } => bail!("Entry not found: {src}"),
+    };
+
+    let config = Config::load()?;
+    let mk = get_master_key_with_cache(&config)?;
+    let dest = PasswordStore::open(dest_root.clone())
+        .with_context(|| format!("cannot open profile {profile}"))?;
+    let _dest_lock = acquire_at(
+        &dest_root,
+        Access::Write,
+        &format!("pm {action} --to-profile"),
+    )?;
+    let dest_mk = unlock_profile(profile, &dest_root, dest.config())?;
+    let dest = dest.with_master_key(dest_mk);
+
+    let mut done = Vec::new();
+    let mut skipped = Vec::new();
+    for (from, to) in plan {
+        let to = match (dest.exists(&to)?, on_conflict) {
+            (false, _) | (true, OnConflict::Overwrite) => to,
+            (true, OnConflict::Rename) => free_path(&dest, &to)?,
+            (true, OnConflict::Skip) => {
+                output::note(format!("Skipped {from}: {profile}:{to} already exists"));
+                skipped.push(from);
+                continue;
+            }
+        };
+
+        let mut entry = load_entry(&from, &mk)?;
+        let files = attachments::read_all(&from, &entry, &mk)?;
+        entry.title = to.clone();
+        entry.tags = normalize_tags(&entry.tags);
+        if let Mode::Copy { no_otp } = mode {
+            let now = OffsetDateTime::now_utc().format(&Rfc3339)?;
+            entry.created_at = now.clone();
+            entry.updated_at = now;
+            if no_otp {
+                entry.otp = None;
+            }
+        }
+        dest.save_with_attachments(&to, &mut entry, &files)?;
+        verify(&dest, &to, &entry, &files).with_context(|| {
+            format!("{profile}:{to} does not read back as written; {from} was kept")
+        })?;
+
+        if mode == Mode::Move {
+            delete_entry(&from)?;
+            auto_commit(&config, HookAction::Rm, &from);
+            run_hooks(&config, HookAction::Rm, &from);
+        }
+        output::note(format!("{verb} {from} -> {profile}:{to}"));
+        done.push(serde_json::json!({ "from": from, "to": to }));
+    }
+
+    let n_skipped = skipped.len();
+    output::emit(
+        &serde_json::json!({
+            "ok": true,
+            "action": action,
+            "profile": profile,
+            "entries": done,
+            "skipped": skipped,
+        }),
+        || {
+            if n_skipped > 0 {
+                println!(
+                    "Skipped {n_skipped} existing entr{} (use --on-conflict overwrite or rename)",
+                    if n_skipped == 1 { "y" } else { "ies" }
+                );
+            }
+            Ok(())
+        },
+    )
+}
+
+/// Один и тот же каталог, даже если пути записаны по-разному.
+fn same_store(a: &std::path::Path, b: &std::path::Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Первый свободный в хранилище назначения путь вида `path-2`, `path-3`, ...
+fn free_path(dest: &PasswordStore, path: &str) -> Result<String> {
+    let mut n = 2;
+    loop {
+        let candidate = format!("{path}-{n}");
+        if !dest.exists(&candidate)? {
+            return Ok(candidate);
+        }
+        n += 1;
+    }
+}
+
+/// Копия в хранилище назначения расшифровывается его ключом и совпадает
+/// с тем, что записывали, вместе с вложениями.
+fn verify(
+    dest: &PasswordStore,
+    path: &str,
+    entry: &Entry,
+    files: &[(String, Vec<u8>)],
+) -> Result<()> {
+    let back = dest.show(path)?;
+    if serde_json::to_value(&back)? != serde_json::to_value(entry)? {
+        return Err(anyhow!("the entry differs"));
+    }
+    if dest.attachments(path, &back)? != files {
+        return Err(anyhow!("the attachments differ"));
+    }
+    Ok(())
+}
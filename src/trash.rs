@@ -0,0 +1,298 @@
+//! Корзина: `pm rm` не стирает запись, а переносит её файл в `trash/`
+//! в корне хранилища.
+//!
+//! Файл записи переезжает как есть, под именем
+//! `<путь-с-__-вместо-/>-<время>.enc`, рядом ложится `.json` с исходным
+//! путём и временем удаления, а вложения — каталогом `.attachments`. Шифртекст привязан к исходному пути, поэтому
+//! восстановить запись можно только на её старое место. `trash/` лежит
+//! вне `store/`: в `pm ls` корзины не видно, а в бэкап она попадает.
+
+use crate::crypto::MasterKey;
+use crate::git::auto_commit;
+use crate::hooks::{HookAction, run_hooks};
+use crate::output;
+use crate::password_store::PasswordStore;
+use crate::prompt::confirm;
+use crate::store::{
+    attachments_dir_in, create_private_dir_all, entry_file_path_in, reencrypt_file, store_root,
+    validate_entry_path, write_atomic_private,
+};
+use anyhow::{Context, Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use time::format_description::well_known::Rfc3339;
+use time::{Duration, OffsetDateTime, format_description};
+
+/// Запись в корзине: сведения из `.json` и пути к обоим файлам.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TrashItem {
+    /// Исходный путь записи, например `work/github`.
+    pub path: String,
+    /// Время удаления, RFC3339 (UTC).
+    pub deleted_at: String,
+    #[serde(skip)]
+    pub file: PathBuf,
+    #[serde(skip)]
+    sidecar: PathBuf,
+}
+
+impl TrashItem {
+    /// Каталог вложений удалённой записи (может не существовать).
+    pub fn attachments(&self) -> PathBuf {
+        self.file
+            .with_extension(&crate::store::ATTACHMENTS_SUFFIX[1..])
+    }
+
+    fn deleted_at(&self) -> Option<OffsetDateTime> {
+        OffsetDateTime::parse(&self.deleted_at, &Rfc3339).ok()
+    }
+}
+
+pub fn trash_dir(root: &Path) -> PathBuf {
+    root.join("trash")
+}
+
+/// Свободное имя (без расширения) для записи `path`, удалённой в `now`.
+fn trash_stem(dir: &Path, path: &str, now: OffsetDateTime) -> Result<String> {
+    let stamp = now.format(&format_description::parse(
+        "[year][month][day]T[hour][minute][second]Z",
+    )?)?;
+    let base = format!("{}-{stamp}", path.replace('/', "__"));
+    let mut stem = base.clone();
+    let mut n = 1;
+    while dir.join(format!("{stem}.enc")).exists() || dir.join(format!("{stem}.json")).exists() {
+        n += 1;
+        stem = format!("{base}-{n}");
+    }
+    Ok(stem)
+}
+
+/// Перенести файл записи `path` хранилища `root` в корзину.
+/// Опустевшие после этого папки в `store/` удаляются.
+pub fn move_to_trash(root: &Path, path: &str) -> Result<TrashItem> {
+    let path = validate_entry_path(path)?;
+    let store_dir = root.join("store");
+    let src = entry_file_path_in(&store_dir, &path)?;
+    if !src.is_file() {
+        return Err(anyhow!("Entry not found: {path}"));
+    }
+
+    let dir = trash_dir(root);
+    create_private_dir_all(&dir)?;
+    let now = OffsetDateTime::now_utc();
+    let stem = trash_stem(&dir, &path, now)?;
+    let item = TrashItem {
+        path: path.clone(),
+        deleted_at: now.format(&Rfc3339)?,
+        file: dir.join(format!("{stem}.enc")),
+        sidecar: dir.join(format!("{stem}.json")),
+    };
+
+    write_atomic_private(
+        &item.sidecar,
+        serde_json::to_string_pretty(&item)?.as_bytes(),
+    )?;
+    if let Err(e) = std::fs::rename(&src, &item.file) {
+        let _ = std::fs::remove_file(&item.sidecar);
+        return Err(anyhow::Error::new(e).context(format!("cannot move {path} to the trash")));
+    }
+    let attachments = attachments_dir_in(&store_dir, &path)?;
+    if attachments.is_dir() {
+        std::fs::rename(&attachments, item.attachments())
+            .with_context(|| format!("cannot move attachments of {path} to the trash"))?;
+    }
+    crate::store::remove_empty_parents(&store_dir, &src)?;
+    Ok(item)
+}
+
+/// Всё содержимое корзины, от старых удалений к новым. Файлы без пары
+/// (`.json` без `.enc`) пропускаются.
+pub fn list(root: &Path) -> Result<Vec<TrashItem>> {
+    let dir = trash_dir(root);
+    let read = match std::fs::read_dir(&dir) {
+        Ok(read) => read,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("cannot read {}", dir.display())),
+    };
+
+    let mut items = Vec::new();
+    for dirent in read {
+        let sidecar = dirent?.path();
+        if sidecar.extension().is_none_or(|e| e != "json") {
+            continue;
+        }
+        let file = sidecar.with_extension("enc");
+        if !file.is_file() {
+            continue;
+        }
+        let data = std::fs::read_to_string(&sidecar)
+            .with_context(|| format!("cannot read {}", sidecar.display()))?;
+        let mut item: TrashItem = serde_json::from_str(&data)
+            .with_context(|| format!("{} is not a trash record", sidecar.display()))?;
+        item.file = file;
+        item.sidecar = sidecar;
+        items.push(item);
+    }
+    items.sort_by(|a, b| (a.deleted_at(), &a.path).cmp(&(b.deleted_at(), &b.path)));
+    Ok(items)
+}
+
+/// Вернуть последнюю удалённую запись с путём `path` на её место.
+/// Занятый путь — ошибка, если не `force`: тогда живая запись заменяется.
+pub fn restore(root: &Path, path: &str, force: bool) -> Result<TrashItem> {
+    let path = validate_entry_path(path)?;
+    let item = list(root)?
+        .into_iter()
+        .rev()
+        .find(|i| i.path == path)
+        .ok_or_else(|| anyhow!("{path} is not in the trash"))?;
+
+    let dest = entry_file_path_in(&root.join("store"), &path)?;
+    if dest.exists() && !force {
+        return Err(anyhow!(
+            "Entry {path} already exists; use --force to replace it"
+        ));
+    }
+    if let Some(parent) = dest.parent() {
+        create_private_dir_all(parent)?;
+    }
+    std::fs::rename(&item.file, &dest)
+        .with_context(|| format!("cannot restore {path} from the trash"))?;
+    // Вложения заменённой живой записи уходят вместе с ней
+    let attachments = attachments_dir_in(&root.join("store"), &path)?;
+    if attachments.is_dir() {
+        std::fs::remove_dir_all(&attachments)?;
+    }
+    if item.attachments().is_dir() {
+        std::fs::rename(item.attachments(), &attachments)
+            .with_context(|| format!("cannot restore attachments of {path}"))?;
+    }
+    std::fs::remove_file(&item.sidecar)?;
+    Ok(item)
+}
+
+/// Стереть из корзины записи, удалённые раньше `older_than` назад
+/// (без него — все). Возвращает число стёртых записей.
+pub fn empty(root: &Path, older_than: Option<Duration>) -> Result<usize> {
+    let cutoff = older_than.map(|d| OffsetDateTime::now_utc() - d);
+    let mut removed = 0;
+    for item in list(root)? {
+        // Запись с испорченным временем удаления стираем только при полной очистке
+        let expired = match cutoff {
+            None => true,
+            Some(cutoff) => item.deleted_at().is_some_and(|t| t < cutoff),
+        };
+        if expired {
+            std::fs::remove_file(&item.file)
+                .with_context(|| format!("cannot delete {}", item.file.display()))?;
+            if item.attachments().is_dir() {
+                std::fs::remove_dir_all(item.attachments())?;
+            }
+            std::fs::remove_file(&item.sidecar)?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// Перешифровать корзину для `pm rekey`: файлы записей из `trash/` корня
+/// `root` ложатся в `trash/` каталога `staging` под теми же именами.
+pub fn reencrypt_trash(
+    root: &Path,
+    staging: &Path,
+    old_key: &MasterKey,
+    new_key: &MasterKey,
+) -> Result<()> {
+    let dest_dir = trash_dir(staging);
+    for item in list(root)? {
+        let Some(name) = item.file.file_name() else {
+            continue;
+        };
+        reencrypt_file(
+            &item.file,
+            &dest_dir.join(name),
+            &item.path,
+            old_key,
+            new_key,
+        )
+        .with_context(|| format!("cannot re-encrypt {} in the trash", item.path))?;
+        let attachments = item.attachments();
+        if let Some(name) = attachments.file_name() {
+            crate::attachments::reencrypt_dir(
+                &attachments,
+                &dest_dir.join(name),
+                &item.path,
+                old_key,
+                new_key,
+            )
+            .with_context(|| {
+                format!(
+                    "cannot re-encrypt attachments of {} in the trash",
+                    item.path
+                )
+            })?;
+        }
+    }
+    Ok(())
+}
+
+/// pm rm PATH [--permanent]: перенести запись в корзину или стереть её.
+/// Без `force` спрашивает подтверждение; `None`, если пользователь передумал,
+/// иначе нормализованный путь.
+pub fn remove_entry(path: &str, force: bool, permanent: bool) -> Result<Option<String>> {
+    let store = PasswordStore::open(store_root()?)?;
+    let path = validate_entry_path(path)?;
+    if !store.exists(&path)? {
+        return Err(anyhow!("Entry not found: {path}"));
+    }
+    if !force && !confirm(&format!("Delete {path}?"))? {
+        return Ok(None);
+    }
+
+    if permanent {
+        store.remove(&path)?;
+    } else {
+        move_to_trash(store.root(), &path)?;
+    }
+    auto_commit(store.config(), HookAction::Rm, &path);
+    run_hooks(store.config(), HookAction::Rm, &path);
+    Ok(Some(path))
+}
+
+/// pm trash restore PATH [--force]
+pub fn restore_entry(path: &str, force: bool) -> Result<TrashItem> {
+    let store = PasswordStore::open(store_root()?)?;
+    let item = restore(store.root(), path, force)?;
+    auto_commit(store.config(), HookAction::Add, &item.path);
+    run_hooks(store.config(), HookAction::Add, &item.path);
+    Ok(item)
+}
+
+/// pm trash list
+pub fn cmd_list() -> Result<()> {
+    let items = list(&store_root()?)?;
+    output::emit(&items, || {
+        if items.is_empty() {
+            println!("The trash is empty.");
+        }
+        for item in &items {
+            println!("{}  {}", item.deleted_at, item.path);
+        }
+        Ok(())
+    })
+}
+
+/// pm trash empty [--older-than 30d]
+pub fn cmd_empty(older_than: Option<&str>) -> Result<()> {
+    let older_than = older_than
+        .map(crate::timespec::parse_duration)
+        .transpose()?;
+    let removed = empty(&store_root()?, older_than)?;
+    output::emit(
+        &serde_json::json!({ "ok": true, "removed": removed }),
+        || {
+            println!("Removed {removed} entries from the trash.");
+            Ok(())
+        },
+    )
+}
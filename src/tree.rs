@@ -0,0 +1,45 @@
+//! Вывод списка записей деревом, как `pass ls`.
+
+use std::collections::BTreeMap;
+
+#[derive(Default)]
+struct Node {
+    children: BTreeMap<String, Node>,
+}
+
+impl Node {
+    fn insert(&mut self, path: &str) {
+        let mut node = self;
+        for part in path.split('/') {
+            node = node.children.entry(part.to_string()).or_default();
+        }
+    }
+
+    fn render(&self, indent: &str, out: &mut String) {
+        let last = self.children.len().saturating_sub(1);
+        for (i, (name, child)) in self.children.iter().enumerate() {
+            let (branch, next) = if i == last {
+                ("└── ", "    ")
+            } else {
+                ("├── ", "│   ")
+            };
+            out.push_str(indent);
+            out.push_str(branch);
+            out.push_str(name);
+            out.push('\n');
+            child.render(&format!("{indent}{next}"), out);
+        }
+    }
+}
+
+/// Дерево из путей записей (`work/github`, ...). Папки берутся только
+/// из путей, так что пустых веток не бывает.
+pub fn render_tree(root_label: &str, paths: &[String]) -> String {
+    let mut root = Node::default();
+    for p in paths {
+        root.insert(p);
+    }
+    let mut out = format!("{root_label}\n");
+    root.render("", &mut out);
+    out
+}
@@ -0,0 +1,203 @@
+use crate::config::Config;
+use crate::entry::{EntryKind, WifiConfig, WifiSecurity};
+use crate::prompt::prompt_string;
+use crate::qr::render_qr_terminal;
+use crate::session::get_master_key_with_cache;
+use crate::store::load_entry;
+use anyhow::{Result, anyhow};
+
+impl WifiSecurity {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "" | "wpa" | "wpa2" | "wpa3" => Ok(WifiSecurity::Wpa),
+            "wep" => Ok(WifiSecurity::Wep),
+            "open" | "none" | "nopass" => Ok(WifiSecurity::Open),
+            other => Err(anyhow!(
+                "unknown security type '{other}', expected WPA/WEP/open"
+            )),
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            WifiSecurity::Wpa => "WPA",
+            WifiSecurity::Wep => "WEP",
+            WifiSecurity::Open => "open",
+        }
+    }
+
+    /// Значение поля `T:` в WIFI-QR.
+    fn qr_type(self) -> &'static str {
+        match self {
+            WifiSecurity::Wpa => "WPA",
+            WifiSecurity::Wep => "WEP",
+            WifiSecurity::Open => "nopass",
+        }
+    }
+}
+
+/// Спросить параметры сети для `pm add --kind wifi`.
+pub fn prompt_wifi_config() -> Result<WifiConfig> {
+    let ssid = prompt_string("SSID: ")?;
+    if ssid.is_empty() {
+        return Err(anyhow!("SSID cannot be empty"));
+    }
+    let security = WifiSecurity::parse(&prompt_string("Security (WPA/WEP/open) [WPA]: ")?)?;
+    Ok(WifiConfig {
+        ssid,
+        security,
+        hidden: false,
+    })
+}
+
+/// Экранирование для WIFI-QR: `\`, `;`, `,`, `:` и `"` предваряются обратным слэшем.
+pub fn escape_wifi_field(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, '\\' | ';' | ',' | ':' | '"') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Строка `WIFI:T:WPA;S:<ssid>;P:<pass>;;` в формате, который понимают камеры телефонов.
+pub fn wifi_qr_payload(cfg: &WifiConfig, password: &str) -> String {
+    let mut s = format!(
+        "WIFI:T:{};S:{};",
+        cfg.security.qr_type(),
+        escape_wifi_field(&cfg.ssid)
+    );
+    if cfg.security != WifiSecurity::Open {
+        s.push_str(&format!("P:{};", escape_wifi_field(password)));
+    }
+    if cfg.hidden {
+        s.push_str("H:true;");
+    }
+    s.push(';');
+    s
+}
+
+/// pm wifi qr PATH
+pub fn wifi_qr(path: &str, invert: bool) -> Result<()> {
+    let config = Config::load()?;
+    let mk = get_master_key_with_cache(&config)?;
+    let entry = load_entry(path, &mk)?;
+
+    let cfg = match (entry.kind, entry.wifi.as_ref()) {
+        (EntryKind::Wifi, Some(cfg)) => cfg,
+        _ => return Err(anyhow!("{path} is not a Wi-Fi entry")),
+    };
+
+    print!(
+        "{}",
+        render_qr_terminal(&wifi_qr_payload(cfg, &entry.password), invert)?
+    );
+    println!("SSID: {}", cfg.ssid);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wifi(ssid: &str, security: WifiSecurity, hidden: bool) -> WifiConfig {
+        WifiConfig {
+            ssid: ssid.to_string(),
+            security,
+            hidden,
+        }
+    }
+
+    /// Поля WIFI-QR так, как их читает телефон: `;` без `\` закрывает поле,
+    /// `\x` — буквальный `x`.
+    fn parse_payload(payload: &str) -> Vec<(String, String)> {
+        let body = payload.strip_prefix("WIFI:").unwrap();
+        let body = body.strip_suffix(";;").unwrap();
+        let mut fields = Vec::new();
+        let mut current = String::new();
+        let mut chars = body.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => current.push(chars.next().unwrap()),
+                ';' => fields.push(std::mem::take(&mut current)),
+                c => current.push(c),
+            }
+        }
+        fields.push(current);
+        fields
+            .into_iter()
+            .map(|f| {
+                let (k, v) = f.split_once(':').unwrap();
+                (k.to_string(), v.to_string())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn escape_wifi_field_table() {
+        let cases = [
+            ("Home", "Home"),
+            ("a;b", r"a\;b"),
+            ("a,b", r"a\,b"),
+            ("a:b", r"a\:b"),
+            (r#""quoted""#, r#"\"quoted\""#),
+            (r"back\slash", r"back\\slash"),
+            (r"\;", r"\\\;"),
+            ("Кафе ☕", "Кафе ☕"),
+            ("", ""),
+        ];
+        for (input, want) in cases {
+            assert_eq!(escape_wifi_field(input), want, "{input:?}");
+        }
+    }
+
+    #[test]
+    fn payload_by_security_type() {
+        assert_eq!(
+            wifi_qr_payload(&wifi("Home", WifiSecurity::Wpa, false), "pw"),
+            "WIFI:T:WPA;S:Home;P:pw;;"
+        );
+        assert_eq!(
+            wifi_qr_payload(&wifi("Old", WifiSecurity::Wep, true), "pw"),
+            "WIFI:T:WEP;S:Old;P:pw;H:true;;"
+        );
+        // У открытой сети пароля в QR нет, даже если он записан
+        assert_eq!(
+            wifi_qr_payload(&wifi("Cafe", WifiSecurity::Open, false), "ignored"),
+            "WIFI:T:nopass;S:Cafe;;"
+        );
+    }
+
+    #[test]
+    fn special_characters_survive_the_payload() {
+        let ssid = "My;Net,work: \"5G\"\\";
+        let password = "p;a:s,s\\\"w;;";
+        let payload = wifi_qr_payload(&wifi(ssid, WifiSecurity::Wpa, true), password);
+        assert_eq!(
+            parse_payload(&payload),
+            [
+                ("T".to_string(), "WPA".to_string()),
+                ("S".to_string(), ssid.to_string()),
+                ("P".to_string(), password.to_string()),
+                ("H".to_string(), "true".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn security_parse_table() {
+        for (input, want) in [
+            ("", WifiSecurity::Wpa),
+            ("WPA2", WifiSecurity::Wpa),
+            (" wpa3 ", WifiSecurity::Wpa),
+            ("WEP", WifiSecurity::Wep),
+            ("open", WifiSecurity::Open),
+            ("nopass", WifiSecurity::Open),
+        ] {
+            assert_eq!(WifiSecurity::parse(input).unwrap(), want, "{input:?}");
+        }
+        assert!(WifiSecurity::parse("wpa4").is_err());
+    }
+}
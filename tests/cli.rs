@@ -0,0 +1,2897 @@
+//! Сквозные тесты CLI: временное хранилище через `PM_STORE_DIR`,
+//! мастер-пароль через `PM_MASTER_PASSWORD`, без терминала и кэша сессии.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const MASTER: &str = "correct horse";
+
+/// Каталог под хранилище, удаляется вместе с тестом.
+struct TempStore {
+    dir: PathBuf,
+}
+
+impl TempStore {
+    fn new() -> Self {
+        static N: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "pm-cli-test-{}-{}",
+            std::process::id(),
+            N.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        TempStore { dir }
+    }
+
+    fn root(&self) -> PathBuf {
+        self.dir.join("pm-store")
+    }
+
+    /// Выполнить `pm --no-cache ARGS` с `stdin` на входе.
+    fn pm_with(&self, password: &str, args: &[&str], stdin: &str) -> Output {
+        self.pm_env(password, args, stdin, &[])
+    }
+
+    /// `pm_with` с дополнительными переменными окружения.
+    fn pm_env(&self, password: &str, args: &[&str], stdin: &str, env: &[(&str, &str)]) -> Output {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_pm"))
+            .arg("--no-cache")
+            .args(args)
+            .env("PM_STORE_DIR", self.root())
+            .env("PM_MASTER_PASSWORD", password)
+            .env("XDG_CONFIG_HOME", self.dir.join("config"))
+            .env_remove("PM_CONCEAL")
+            .envs(env.iter().copied())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("failed to run pm");
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(stdin.as_bytes())
+            .unwrap();
+        child.wait_with_output().unwrap()
+    }
+
+    fn pm(&self, args: &[&str]) -> Output {
+        self.pm_with(MASTER, args, "")
+    }
+
+    /// Выполнить и потребовать успеха; вернуть stdout.
+    fn ok(&self, args: &[&str]) -> String {
+        self.ok_with(args, "")
+    }
+
+    fn ok_with(&self, args: &[&str], stdin: &str) -> String {
+        let out = self.pm_with(MASTER, args, stdin);
+        assert!(
+            out.status.success(),
+            "pm {args:?} failed: {}",
+            String::from_utf8_lossy(&out.stderr)
+        );
+        String::from_utf8(out.stdout).unwrap()
+    }
+
+    fn initialized() -> Self {
+        let store = TempStore::new();
+        store.ok(&["init"]);
+        store
+    }
+}
+
+impl Drop for TempStore {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn add_login(store: &TempStore, path: &str, password: &str) {
+    add_login_in(store, &[], path, password);
+}
+
+/// То же с глобальными флагами (`--store`, `--profile`) перед командой.
+fn add_login_in(store: &TempStore, global: &[&str], path: &str, password: &str) {
+    let mut args = global.to_vec();
+    args.extend([
+        "add",
+        path,
+        "--username",
+        "alice",
+        "--url",
+        "https://example.com",
+        "--notes",
+        "",
+        "--password-stdin",
+    ]);
+    store.ok_with(&args, &format!("{password}\n"));
+}
+
+#[test]
+fn init_creates_store_in_pm_store_dir() {
+    let store = TempStore::new();
+    let out = store.ok(&["init"]);
+    assert!(out.contains("Initialized store"));
+    assert!(store.root().join("config.json").is_file());
+
+    // Повторный init ничего не трогает
+    let again = store.ok(&["init"]);
+    assert!(again.contains("already exists"));
+}
+
+#[test]
+fn init_recovers_from_an_interrupted_init() {
+    // Каталог остался, config.json нет, записей нет — init доделывает своё
+    let store = TempStore::new();
+    std::fs::create_dir_all(store.root().join("store/work")).unwrap();
+    std::fs::write(store.root().join(".lock"), "").unwrap();
+    assert!(store.ok(&["init"]).contains("Initialized store"));
+    add_login(&store, "work/github", "s3cret");
+
+    // Записи без config.json не открыть: init отказывается и советует бэкап
+    std::fs::remove_file(store.root().join("config.json")).unwrap();
+    let out = store.pm(&["init"]);
+    assert!(!out.status.success());
+    let err = String::from_utf8_lossy(&out.stderr);
+    assert!(err.contains("no config.json") && err.contains("backup restore"));
+    assert!(store.root().join("store/work/github.enc").is_file());
+
+    // Чужие файлы в каталоге — тоже отказ
+    let other = TempStore::new();
+    std::fs::create_dir_all(other.root()).unwrap();
+    std::fs::write(other.root().join("notes.txt"), "mine").unwrap();
+    let out = other.pm(&["init"]);
+    assert!(String::from_utf8_lossy(&out.stderr).contains("is not empty"));
+}
+
+#[test]
+fn kdf_is_calibrated_and_upgradable() {
+    let store = TempStore::new();
+    let kdf = |store: &TempStore| {
+        let config = std::fs::read_to_string(store.root().join("config.json")).unwrap();
+        let config: serde_json::Value = serde_json::from_str(&config).unwrap();
+        (
+            config["kdf"]["memory_mib"].clone(),
+            config["kdf"]["iterations"].clone(),
+        )
+    };
+    // Минимальная цель упирается в нижнюю границу OWASP
+    store.ok(&["init", "--kdf-memory-mib", "19", "--kdf-target-ms", "1"]);
+    assert_eq!(kdf(&store), (19.into(), 2.into()));
+    add_login(&store, "work/github", "s3cret");
+
+    store.ok(&[
+        "config",
+        "upgrade-kdf",
+        "--kdf-memory-mib",
+        "24",
+        "--kdf-target-ms",
+        "1",
+    ]);
+    assert_eq!(kdf(&store), (24.into(), 2.into()));
+    let password = store.ok(&["show", "work/github", "--password-only"]);
+    assert_eq!(password.trim_end(), "s3cret");
+
+    // Слабее текущих параметры не ставятся
+    let out = store.ok(&[
+        "config",
+        "upgrade-kdf",
+        "--kdf-memory-mib",
+        "19",
+        "--kdf-target-ms",
+        "1",
+    ]);
+    assert!(out.contains("nothing to do"));
+    assert!(
+        !store
+            .pm(&["config", "upgrade-kdf", "--kdf-memory-mib", "4"])
+            .status
+            .success()
+    );
+}
+
+#[test]
+fn keyfile_is_a_second_factor() {
+    let store = TempStore::new();
+    let key = store.dir.join("pm.key");
+    let other = store.dir.join("other.key");
+    std::fs::write(&key, "first keyfile").unwrap();
+    std::fs::write(&other, "second keyfile").unwrap();
+    let key = key.to_str().unwrap();
+    let with_key = ["--keyfile", key];
+
+    store.ok(&["--keyfile", key, "init", "--kdf-target-ms", "1"]);
+    let config = std::fs::read_to_string(store.root().join("config.json")).unwrap();
+    assert!(!config.contains("pm.key"));
+    add_login_in(&store, &with_key, "work/github", "s3cret");
+
+    let out = store.pm(&["show", "work/github", "--password-only"]);
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("keyfile required"));
+    let out = store.pm(&["--keyfile", other.to_str().unwrap(), "show", "work/github"]);
+    assert!(String::from_utf8_lossy(&out.stderr).contains("invalid master password or keyfile"));
+
+    store.ok(&["--keyfile", key, "config", "remove-keyfile"]);
+    let password = store.ok(&["show", "work/github", "--password-only"]);
+    assert_eq!(password.trim_end(), "s3cret");
+
+    store.ok(&["config", "add-keyfile", key]);
+    assert!(!store.pm(&["show", "work/github"]).status.success());
+    let password = store.ok(&["--keyfile", key, "show", "work/github", "--password-only"]);
+    assert_eq!(password.trim_end(), "s3cret");
+}
+
+#[test]
+fn store_flag_wins_over_env() {
+    let store = TempStore::new();
+    let other = store.dir.join("other");
+    let other = other.to_str().unwrap();
+    store.ok(&["--store", other, "init"]);
+    assert!(!store.root().exists());
+
+    store.ok(&["init"]);
+    add_login_in(&store, &["--store", other], "work/github", "s3cret");
+    assert_eq!(
+        store.ok(&["--store", other, "ls", "--flat"]),
+        "work/github\n"
+    );
+    assert_eq!(store.ok(&["ls", "--flat"]), "");
+}
+
+// Каталог настроек берётся из XDG_CONFIG_HOME только на Linux
+#[cfg(target_os = "linux")]
+#[test]
+fn profiles_select_the_store() {
+    let store = TempStore::new();
+    let work = store.dir.join("work");
+    let work = work.to_str().unwrap();
+    store.ok(&["profile", "add", "work", work, "--session-ttl", "60"]);
+    store.ok(&["--profile", "work", "init"]);
+    assert!(!store.root().exists());
+
+    add_login_in(&store, &["--profile", "work"], "work/github", "s3cret");
+    assert_eq!(
+        store.ok(&["--profile", "work", "ls", "--flat"]),
+        "work/github\n"
+    );
+
+    let listed: serde_json::Value =
+        serde_json::from_str(&store.ok(&["-o", "json", "profile", "list"])).unwrap();
+    assert_eq!(listed["profiles"][0]["name"], "work");
+    assert_eq!(listed["profiles"][0]["session_ttl"], 60);
+
+    let out = store.pm(&["--profile", "work", "show", "missing/entry"]);
+    assert!(String::from_utf8_lossy(&out.stderr).contains("(profile work)"));
+    let out = store.pm(&["--profile", "nope", "ls"]);
+    assert!(String::from_utf8_lossy(&out.stderr).contains("unknown profile"));
+
+    store.ok(&["profile", "remove", "work"]);
+    assert!(!store.pm(&["--profile", "work", "ls"]).status.success());
+}
+
+#[test]
+fn add_show_ls_rm_round_trip() {
+    let store = TempStore::initialized();
+    add_login(&store, "work/github", "s3cret");
+    add_login(&store, "personal/mail", "hunter2");
+
+    let shown: serde_json::Value =
+        serde_json::from_str(&store.ok(&["show", "work/github", "--json"])).unwrap();
+    assert_eq!(shown["password"], "s3cret");
+    assert_eq!(shown["username"], "alice");
+    assert_eq!(shown["url"], "https://example.com");
+
+    let password = store.ok(&["show", "personal/mail", "--password-only"]);
+    assert_eq!(password.trim_end(), "hunter2");
+
+    assert_eq!(store.ok(&["ls", "--flat"]), "personal/mail\nwork/github\n");
+
+    store.ok(&["rm", "-f", "work/github"]);
+    assert_eq!(store.ok(&["ls", "--flat"]), "personal/mail\n");
+    let gone = store.pm(&["show", "work/github", "--json"]);
+    assert!(!gone.status.success());
+}
+
+#[test]
+fn show_masks_password_unless_revealed() {
+    let store = TempStore::initialized();
+    add_login(&store, "work/github", "s3cret");
+
+    let shown = store.ok(&["show", "work/github"]);
+    assert!(shown.contains("Password: ********"));
+    assert!(shown.contains("Username: alice"));
+    assert!(!shown.contains("s3cret"));
+    assert!(
+        store
+            .ok(&["show", "work/github", "-r"])
+            .contains("Password: s3cret")
+    );
+
+    store.ok(&["config", "set", "display.conceal_password", "false"]);
+    assert!(
+        store
+            .ok(&["show", "work/github"])
+            .contains("Password: s3cret")
+    );
+
+    // Конфиг прежней версии с ui.show_reveals_by_default
+    store.ok(&["config", "set", "display.conceal_password", "default"]);
+    let config_path = store.root().join("config.json");
+    let mut config: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&config_path).unwrap()).unwrap();
+    config["settings"]["ui"] = serde_json::json!({ "show_reveals_by_default": true });
+    std::fs::write(&config_path, config.to_string()).unwrap();
+    assert!(
+        store
+            .ok(&["show", "work/github"])
+            .contains("Password: s3cret")
+    );
+    assert_eq!(
+        store.ok(&["config", "get", "display.conceal_password"]),
+        "false\n"
+    );
+}
+
+#[test]
+fn config_get_set_list() {
+    let store = TempStore::initialized();
+    let before = std::fs::read_to_string(store.root().join("config.json")).unwrap();
+    let before: serde_json::Value = serde_json::from_str(&before).unwrap();
+
+    store.ok(&["config", "set", "generate.length", "12"]);
+    store.ok(&["config", "set", "generate.symbols", "false"]);
+    assert_eq!(store.ok(&["config", "get", "generate.length"]), "12\n");
+    let password = store.ok(&["generate"]);
+    assert_eq!(password.trim_end().len(), 12);
+    assert!(
+        password
+            .trim_end()
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric())
+    );
+    // Флаг важнее настройки
+    assert_eq!(store.ok(&["generate", "20"]).trim_end().len(), 20);
+
+    let out = store.pm(&["config", "set", "clipboard.timeout_secs", "soon"]);
+    assert!(String::from_utf8_lossy(&out.stderr).contains("expected integer"));
+    assert!(!store.pm(&["config", "get", "no.such.key"]).status.success());
+
+    let listed: serde_json::Value =
+        serde_json::from_str(&store.ok(&["-o", "json", "config", "list"])).unwrap();
+    assert_eq!(listed["settings"]["generate.length"], 12);
+    assert_eq!(
+        listed["settings"]["session.ttl_secs"],
+        serde_json::Value::Null
+    );
+
+    let after = std::fs::read_to_string(store.root().join("config.json")).unwrap();
+    let after: serde_json::Value = serde_json::from_str(&after).unwrap();
+    assert_eq!(before["kdf"], after["kdf"]);
+    assert_eq!(before["enc"], after["enc"]);
+}
+
+#[test]
+fn add_refuses_to_overwrite_without_force() {
+    let store = TempStore::initialized();
+    add_login(&store, "work/github", "first");
+
+    let out = store.pm_with(
+        MASTER,
+        &["add", "work/github", "--password-stdin"],
+        "second\n",
+    );
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("already exists"));
+
+    let password = store.ok(&["show", "work/github", "--password-only"]);
+    assert_eq!(password.trim_end(), "first");
+}
+
+#[test]
+fn wrong_master_password_is_rejected() {
+    let store = TempStore::initialized();
+    add_login(&store, "work/github", "s3cret");
+
+    let out = store.pm_with("not it", &["show", "work/github", "--json"], "");
+    assert!(!out.status.success());
+    assert!(out.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("invalid master password"));
+}
+
+#[test]
+fn json_output_mode() {
+    let store = TempStore::initialized();
+    add_login(&store, "work/github", "s3cret");
+
+    let listed: serde_json::Value = serde_json::from_str(&store.ok(&["-o", "json", "ls"])).unwrap();
+    assert_eq!(listed["entries"], serde_json::json!(["work/github"]));
+
+    let out = store.pm(&["-o", "json", "show", "missing/entry"]);
+    assert!(!out.status.success());
+    // Перед JSON-ошибкой могут идти предупреждения окружения
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    let err: serde_json::Value = serde_json::from_str(stderr.lines().last().unwrap()).unwrap();
+    assert!(err["error"].is_string());
+}
+
+#[test]
+fn otp_code_at_fixed_time() {
+    let store = TempStore::initialized();
+    add_login(&store, "work/github", "s3cret");
+    // Секрет из RFC 6238, приложение B
+    store.ok(&[
+        "otp",
+        "add",
+        "work/github",
+        "--secret",
+        "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ",
+    ]);
+    let code = store.ok(&["otp", "show", "work/github", "--at", "59"]);
+    assert_eq!(code.trim_end(), "287082");
+}
+
+#[test]
+fn entry_files_are_bound_to_their_path() {
+    let store = TempStore::initialized();
+    add_login(&store, "work/github", "s3cret");
+    add_login(&store, "personal/mail", "hunter2");
+
+    // Подмена одного файла другим не проходит проверку подлинности
+    let files = store.root().join("store");
+    let github = files.join("work/github.enc");
+    let mail = files.join("personal/mail.enc");
+    std::fs::copy(&mail, &github).unwrap();
+    let out = store.pm(&["show", "work/github", "--password-only"]);
+    assert!(!out.status.success());
+    assert!(out.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("failed authentication"));
+
+    // mv перешифровывает запись под новым путём
+    store.ok(&["mv", "personal/mail", "archive/mail"]);
+    let password = store.ok(&["show", "archive/mail", "--password-only"]);
+    assert_eq!(password.trim_end(), "hunter2");
+}
+
+#[test]
+fn rm_moves_entries_to_the_trash() {
+    let store = TempStore::initialized();
+    add_login(&store, "work/github", "s3cret");
+
+    store.ok(&["rm", "-f", "work/github"]);
+    assert_eq!(store.ok(&["ls", "--flat"]), "");
+    assert!(!store.root().join("store/work").exists());
+    let listed: serde_json::Value =
+        serde_json::from_str(&store.ok(&["-o", "json", "trash", "list"])).unwrap();
+    assert_eq!(listed[0]["path"], "work/github");
+
+    // Занятый путь без --force не перезаписывается
+    add_login(&store, "work/github", "newer");
+    assert!(
+        !store
+            .pm(&["trash", "restore", "work/github"])
+            .status
+            .success()
+    );
+    store.ok(&["trash", "restore", "--force", "work/github"]);
+    let password = store.ok(&["show", "work/github", "--password-only"]);
+    assert_eq!(password.trim_end(), "s3cret");
+    assert_eq!(store.ok(&["trash", "list"]), "The trash is empty.\n");
+
+    store.ok(&["rm", "-f", "work/github"]);
+    store.ok(&["trash", "empty", "--older-than", "30d"]);
+    assert!(store.ok(&["trash", "list"]).contains("work/github"));
+    store.ok(&["trash", "empty"]);
+    assert!(
+        !store
+            .pm(&["trash", "restore", "work/github"])
+            .status
+            .success()
+    );
+
+    add_login(&store, "personal/mail", "hunter2");
+    store.ok(&["rm", "-f", "--permanent", "personal/mail"]);
+    assert_eq!(store.ok(&["trash", "list"]), "The trash is empty.\n");
+}
+
+#[test]
+fn audit_reports_weak_and_reused_passwords() {
+    let store = TempStore::initialized();
+    add_login(&store, "weak/one", "password1");
+    add_login(&store, "weak/two", "password1");
+    add_login(&store, "strong", "vQ7#mZ2!pL9xR4@tK8wE");
+
+    let out = store.ok(&["audit"]);
+    assert!(!out.contains("password1"));
+    assert!(out.contains("Reused passwords:\n  weak/one, weak/two"));
+    assert!(!out.contains("strong"));
+
+    let report: serde_json::Value =
+        serde_json::from_str(&store.ok(&["-o", "json", "audit"])).unwrap();
+    assert_eq!(report["checked"], 3);
+    let entries = report["entries"].as_array().unwrap();
+    assert_eq!(entries[0]["severity"], "high");
+    assert!(entries[0]["score"].as_u64().unwrap() <= 1);
+    let strong = entries.iter().find(|e| e["path"] == "strong").unwrap();
+    assert_eq!(strong["score"], 4);
+    assert!(strong["findings"].as_array().unwrap().is_empty());
+
+    let failed = store.pm(&["audit", "--fail-under", "3"]);
+    assert!(!failed.status.success());
+    assert!(String::from_utf8_lossy(&failed.stderr).contains("2 passwords score below 3"));
+
+    // Флаги HIBP без режима, к которому они относятся, — ошибка разбора, а не обычный аудит
+    for args in [
+        &["audit", "--db", "pwned.txt"][..],
+        &["audit", "--timeout", "5"],
+        &[
+            "audit",
+            "--hibp-offline",
+            "--timeout",
+            "5",
+            "--db",
+            "pwned.txt",
+        ],
+    ] {
+        let out = store.pm(args);
+        assert_eq!(out.status.code(), Some(2), "{args:?}");
+    }
+}
+
+#[test]
+fn pwned_checks_offline_dumps() {
+    let store = TempStore::initialized();
+    add_login(&store, "weak", "password");
+    add_login(&store, "strong", "vQ7#mZ2!pL9xR4@tK8wE");
+
+    // SHA-1 и NTLM от "password"
+    let sha1 = store.root().join("sha1.txt");
+    std::fs::write(
+        &sha1,
+        "0000000000000000000000000000000000000000:1\r\n\
+         5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8:42\r\n",
+    )
+    .unwrap();
+    let ntlm = store.root().join("ntlm.txt");
+    std::fs::write(&ntlm, "8846F7EAEE8FB117AD06BDD830B7586C:7\n").unwrap();
+
+    let out = store.pm(&["pwned", "--offline", sha1.to_str().unwrap()]);
+    assert!(!out.status.success());
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(stdout.contains("weak: password seen 42 times"));
+    assert!(stdout.contains("Checked 2 passwords: 1 breached"));
+
+    let out = store.pm(&["pwned", "weak", "--offline", ntlm.to_str().unwrap()]);
+    assert!(String::from_utf8_lossy(&out.stdout).contains("weak: password seen 7 times"));
+    store.ok(&["pwned", "strong", "--offline", ntlm.to_str().unwrap()]);
+}
+
+#[test]
+fn expiry_dates_are_listed_and_flagged() {
+    let store = TempStore::initialized();
+    for (path, expires) in [
+        ("certs/old", "2020-01-01"),
+        ("certs/later", "2999-06-01T12:00:00+02:00"),
+        ("certs/none", ""),
+    ] {
+        store.ok_with(
+            &[
+                "add",
+                path,
+                "--username",
+                "alice",
+                "--url",
+                "",
+                "--notes",
+                "",
+                "--expires",
+                expires,
+                "--password-stdin",
+            ],
+            "s3cret\n",
+        );
+    }
+
+    let shown: serde_json::Value =
+        serde_json::from_str(&store.ok(&["show", "certs/later", "--json"])).unwrap();
+    assert_eq!(shown["expires_at"], "2999-06-01T10:00:00Z");
+    assert!(
+        store
+            .ok(&["show", "certs/old"])
+            .contains("2020-01-01T00:00:00Z  ⚑ EXPIRED")
+    );
+    assert!(!store.ok(&["show", "certs/later"]).contains("EXPIRED"));
+
+    let listed = store.ok(&["expiring"]);
+    assert!(listed.contains("EXPIRED") && listed.contains("certs/old"));
+    assert!(!listed.contains("certs/later"));
+    let all: serde_json::Value =
+        serde_json::from_str(&store.ok(&["-o", "json", "expiring", "--within", "1000y"])).unwrap();
+    assert_eq!(all[0]["path"], "certs/old");
+    assert_eq!(all[1]["path"], "certs/later");
+    assert_eq!(all.as_array().unwrap().len(), 2);
+
+    assert_eq!(store.ok(&["ls", "--expired", "--flat"]), "certs/old\n");
+}
+
+#[test]
+fn tags_are_normalized_and_filterable() {
+    let store = TempStore::initialized();
+    store.ok_with(
+        &[
+            "add",
+            "work/github",
+            "--username",
+            "alice",
+            "--url",
+            "",
+            "--notes",
+            "",
+            "--expires",
+            "",
+            "--tags",
+            "Shared, 2FA-enabled,shared,",
+            "--password-stdin",
+        ],
+        "s3cret\n",
+    );
+    add_login(&store, "personal/mail", "hunter2");
+
+    let shown: serde_json::Value =
+        serde_json::from_str(&store.ok(&["show", "work/github", "--json"])).unwrap();
+    assert_eq!(shown["tags"], serde_json::json!(["2fa-enabled", "shared"]));
+    assert!(
+        store
+            .ok(&["show", "work/github"])
+            .contains("Tags:     2fa-enabled, shared")
+    );
+
+    store.ok(&["tag", "add", "personal/mail", "SHARED"]);
+    assert_eq!(
+        store.ok(&["ls", "--flat", "--tag", "shared"]),
+        "personal/mail\nwork/github\n"
+    );
+    store.ok(&["tag", "rm", "work/github", "shared"]);
+    assert_eq!(
+        store.ok(&["ls", "--flat", "--tag", "Shared"]),
+        "personal/mail\n"
+    );
+    assert!(
+        !store
+            .pm(&["tag", "rm", "work/github", "shared"])
+            .status
+            .success()
+    );
+
+    let listed: serde_json::Value =
+        serde_json::from_str(&store.ok(&["-o", "json", "tag", "list"])).unwrap();
+    assert_eq!(
+        listed["tags"],
+        serde_json::json!({ "2fa-enabled": 1, "shared": 1 })
+    );
+}
+
+#[test]
+fn scripted_add_asks_nothing_beyond_its_flags() {
+    let store = TempStore::initialized();
+    // Лишние строки stdin не должны стать сроком действия или метками
+    let out = store.pm_with(
+        MASTER,
+        &[
+            "add",
+            "svc/api",
+            "--username",
+            "bot",
+            "--url",
+            "",
+            "--notes",
+            "",
+            "--password-stdin",
+        ],
+        "s3cret\n2020-01-01\nshared\napi=x\n",
+    );
+    assert!(out.status.success(), "{out:?}");
+    assert_eq!(
+        String::from_utf8_lossy(&out.stdout),
+        "Saved entry svc/api\n"
+    );
+    let shown: serde_json::Value =
+        serde_json::from_str(&store.ok(&["show", "svc/api", "--json"])).unwrap();
+    assert_eq!(shown["password"], "s3cret");
+    assert!(shown["expires_at"].is_null(), "{shown}");
+    assert!(
+        shown["tags"].as_array().is_none_or(Vec::is_empty),
+        "{shown}"
+    );
+    assert!(
+        shown["fields"].as_array().is_none_or(Vec::is_empty),
+        "{shown}"
+    );
+
+    // Флаги по-прежнему задают срок и метки
+    store.ok_with(
+        &[
+            "add",
+            "svc/cert",
+            "--username",
+            "",
+            "--url",
+            "",
+            "--notes",
+            "",
+            "--tags",
+            "TLS",
+            "--expires",
+            "2030-01-01",
+            "--password-stdin",
+        ],
+        "s3cret\n",
+    );
+    let shown: serde_json::Value =
+        serde_json::from_str(&store.ok(&["show", "svc/cert", "--json"])).unwrap();
+    assert_eq!(shown["expires_at"], "2030-01-01T00:00:00Z");
+    assert_eq!(shown["tags"], serde_json::json!(["tls"]));
+}
+
+#[test]
+fn metadata_index_tracks_entries() {
+    let store = TempStore::initialized();
+    add_login(&store, "work/github", "s3cret");
+    add_login(&store, "personal/mail", "hunter2");
+    let index = store.root().join("index.enc");
+    let data = std::fs::read_to_string(&index).unwrap();
+    assert!(!data.contains("alice") && !data.contains("hunter2"));
+
+    // Запись, подменённая в обход pm, берётся из файла, а не из индекса
+    let mail = store.root().join("store/personal/mail.enc");
+    let untagged = std::fs::read(&mail).unwrap();
+    store.ok(&["tag", "add", "personal/mail", "shared"]);
+    assert_eq!(
+        store.ok(&["ls", "--flat", "--tag", "shared"]),
+        "personal/mail\n"
+    );
+    std::fs::write(&mail, untagged).unwrap();
+    assert_eq!(store.ok(&["ls", "--flat", "--tag", "shared"]), "");
+
+    // Битый индекс строится заново
+    store.ok(&["tag", "add", "work/github", "shared"]);
+    std::fs::write(&index, "garbage").unwrap();
+    assert_eq!(
+        store.ok(&["ls", "--flat", "--tag", "shared"]),
+        "work/github\n"
+    );
+    assert!(
+        store
+            .ok(&["index", "rebuild"])
+            .contains("Indexed 2 entries.")
+    );
+}
+
+#[test]
+fn read_only_mode_and_stale_locks() {
+    let store = TempStore::initialized();
+    add_login(&store, "work/github", "s3cret");
+    let lock = store.root().join(".lock");
+
+    // Запись о процессе, которого уже нет, не мешает и стирается
+    std::fs::write(&lock, "999999999 pm edit\n").unwrap();
+    assert_eq!(store.ok(&["ls", "--flat"]), "work/github\n");
+    assert_eq!(std::fs::read_to_string(&lock).unwrap(), "");
+
+    let out = store.pm(&["--read-only", "rm", "-f", "work/github"]);
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("--read-only"));
+    assert!(
+        store
+            .ok(&["--read-only", "show", "work/github", "--password-only"])
+            .contains("s3cret")
+    );
+    assert!(store.root().join("store/work/github.enc").is_file());
+}
+
+#[test]
+fn verbose_logs_never_contain_secrets() {
+    let store = TempStore::initialized();
+    let sentinel = "Sentinel-9f2c-password";
+    let mut logs = String::new();
+    let mut run = |args: &[&str], stdin: &str| {
+        let out = store.pm_with(MASTER, args, stdin);
+        assert!(
+            out.status.success(),
+            "pm {args:?} failed: {}",
+            String::from_utf8_lossy(&out.stderr)
+        );
+        logs.push_str(&String::from_utf8_lossy(&out.stderr));
+    };
+    run(
+        &[
+            "-vv",
+            "add",
+            "work/github",
+            "--username",
+            "alice",
+            "--url",
+            "",
+            "--notes",
+            "",
+            "--password-stdin",
+        ],
+        &format!("{sentinel}\n"),
+    );
+    run(
+        &["-vv", "otp", "add", "work/github", "--secret", "-"],
+        "JBSWY3DPEHPK3PXP\n",
+    );
+    run(&["-vv", "show", "work/github"], "");
+    run(&["-vv", "show", "work/github", "--password-only"], "");
+    run(&["-vv", "otp", "show", "work/github"], "");
+
+    assert!(logs.contains("pm: wrote "));
+    assert!(logs.contains("argon2id"));
+    for secret in [sentinel, MASTER, "JBSWY3DPEHPK3PXP", "\"password\""] {
+        assert!(!logs.contains(secret), "{secret} leaked into the logs");
+    }
+
+    let out = store.ok_with(
+        &[
+            "-q",
+            "add",
+            "work/gitlab",
+            "--username",
+            "alice",
+            "--url",
+            "",
+            "--notes",
+            "",
+            "--expires",
+            "",
+            "--tags",
+            "",
+            "--password-stdin",
+        ],
+        "s3cret\n",
+    );
+    assert_eq!(out, "");
+}
+
+#[cfg(unix)]
+#[test]
+fn get_prints_bare_values_with_exit_codes() {
+    let store = TempStore::initialized();
+    add_login(&store, "work/github", "s3cret");
+
+    assert_eq!(store.ok(&["get", "work/github"]), "s3cret\n");
+    assert_eq!(
+        store.ok(&["get", "work/github", "--field", "username", "-n"]),
+        "alice"
+    );
+
+    // Пароль из дескриптора (здесь stdin), без PM_MASTER_PASSWORD
+    let args = ["get", "work/github", "--password-fd", "0", "--ttl", "0"];
+    let out = store.pm_with("", &args, &format!("{MASTER}\n"));
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "s3cret\n");
+
+    let code = |out: Output| out.status.code();
+    assert_eq!(code(store.pm(&["get", "work/gitlab"])), Some(2));
+    assert_eq!(code(store.pm_with("", &args, "wrong\n")), Some(3));
+    assert_eq!(
+        code(store.pm(&["get", "work/github", "--field", "otp"])),
+        Some(4)
+    );
+    let out = store.pm(&["get", "work/github", "--field", "notes"]);
+    assert!(out.stdout.is_empty());
+    assert_eq!(code(out), Some(4));
+}
+
+#[test]
+fn git_credential_helper_stores_and_answers() {
+    let store = TempStore::initialized();
+    let request = "protocol=https\nhost=git.example.com\nusername=bob\n";
+    let cred = |op: &str, stdin: &str| store.ok_with(&["git-credential", op], stdin);
+
+    // Записи нет — пустой ответ, git спросит сам
+    assert_eq!(cred("get", &format!("{request}\n")), "");
+    assert_eq!(cred("store", &format!("{request}password=hunter2\n\n")), "");
+    assert_eq!(
+        cred("get", &format!("{request}\n")),
+        "username=bob\npassword=hunter2\n"
+    );
+    let shown = store.ok(&["show", "git/git.example.com", "--reveal"]);
+    assert!(shown.contains("https://git.example.com"));
+
+    // Отвергнутый чужой пароль запись не трогает, свой — уносит в корзину
+    cred("erase", &format!("{request}password=other\n\n"));
+    assert!(store.ok(&["ls", "--flat"]).contains("git/git.example.com"));
+    cred("erase", &format!("{request}password=hunter2\n\n"));
+    assert_eq!(cred("get", &format!("{request}\n")), "");
+    assert!(store.ok(&["trash", "list"]).contains("git/git.example.com"));
+}
+
+#[test]
+fn clip_uses_the_configured_backend() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let store = TempStore::initialized();
+    add_login(&store, "work/github", "s3cret");
+    store.ok(&["config", "set", "clipboard.timeout_secs", "0"]);
+
+    // Поддельный xsel записывает, что ему передали
+    let bin = store.dir.join("bin");
+    std::fs::create_dir_all(&bin).unwrap();
+    let xsel = bin.join("xsel");
+    let log = store.dir.join("xsel.out");
+    std::fs::write(
+        &xsel,
+        format!("#!/bin/sh\necho \"$*\" > {0}\ncat >> {0}\n", log.display()),
+    )
+    .unwrap();
+    std::fs::set_permissions(&xsel, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let path = format!("{}:/usr/bin:/bin", bin.display());
+    let env = [
+        ("PATH", path.as_str()),
+        ("DISPLAY", ":0"),
+        ("PM_CLIPBOARD", "xclip,xsel"),
+    ];
+
+    let out = store.pm_env(MASTER, &["clip", "work/github", "--primary"], "", &env);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert_eq!(
+        std::fs::read_to_string(&log).unwrap(),
+        "--primary --input\ns3cret"
+    );
+
+    // xsel не умеет --once, а других бэкендов нет
+    let out = store.pm_env(MASTER, &["clip", "work/github", "--once"], "", &env);
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("--once is not supported"));
+
+    let out = store.pm_env(
+        MASTER,
+        &["clip", "work/github"],
+        "",
+        &[("PM_CLIPBOARD", "bogus")],
+    );
+    assert!(String::from_utf8_lossy(&out.stderr).contains("unknown clipboard backend"));
+}
+
+#[test]
+fn menu_lists_entries_and_types_a_sequence() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let store = TempStore::initialized();
+    add_login(&store, "work/github", "s3cret");
+    add_login(&store, "personal/mail", "hunter2");
+    assert_eq!(
+        store.ok(&["menu", "--list"]),
+        "personal/mail\nwork/github\n"
+    );
+
+    // Поддельный xdotool дописывает аргументы и stdin в журнал
+    let bin = store.dir.join("bin");
+    std::fs::create_dir_all(&bin).unwrap();
+    let xdotool = bin.join("xdotool");
+    let log = store.dir.join("xdotool.out");
+    std::fs::write(
+        &xdotool,
+        format!(
+            "#!/bin/sh\necho \"$*\" >> {0}\n[ \"$1\" = type ] && cat >> {0} && echo >> {0}\nexit 0\n",
+            log.display()
+        ),
+    )
+    .unwrap();
+    std::fs::set_permissions(&xdotool, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let path = format!("{}:/usr/bin:/bin", bin.display());
+    let env = [
+        ("PATH", path.as_str()),
+        ("DISPLAY", ":0"),
+        ("WAYLAND_DISPLAY", ""),
+    ];
+
+    // Выбор из меню приходит в stdin
+    let args = [
+        "menu",
+        "--sequence",
+        "user :tab pass :enter",
+        "--delay",
+        "0",
+    ];
+    let out = store.pm_env(MASTER, &args, "work/github\n", &env);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert_eq!(
+        std::fs::read_to_string(&log).unwrap(),
+        "type --clearmodifiers --file -\nalice\n\
+         key --clearmodifiers Tab\n\
+         type --clearmodifiers --file -\ns3cret\n\
+         key --clearmodifiers Return\n"
+    );
+
+    // Ошибка в последовательности — до набора
+    let args = ["menu", "work/github", "--sequence", "user :esc"];
+    let out = store.pm_env(MASTER, &args, "", &env);
+    assert!(String::from_utf8_lossy(&out.stderr).contains("unknown key"));
+    let out = store.pm_env(
+        MASTER,
+        &["menu", "work/github"],
+        "",
+        &[("PATH", "/nonexistent")],
+    );
+    assert!(String::from_utf8_lossy(&out.stderr).contains("No autotype tool"));
+}
+
+#[test]
+fn share_bundle_roundtrips_to_another_store() {
+    let alice = TempStore::initialized();
+    add_login(&alice, "work/github", "s3cret");
+    add_login(&alice, "work/jira", "j1ra");
+    add_login(&alice, "personal/mail", "m41l");
+
+    let bob = TempStore::initialized();
+    let key = bob.dir.join("key.txt");
+    let key = key.to_str().unwrap();
+    let public = bob.ok(&["share", "keygen", "--output", key]);
+    let public = public
+        .lines()
+        .next()
+        .and_then(|l| l.strip_prefix("Public key: "))
+        .unwrap();
+    assert!(public.starts_with("age1"), "{public}");
+
+    let bundle = alice.dir.join("for-bob.age");
+    let bundle = bundle.to_str().unwrap();
+    alice.ok(&["share", "work", "--recipient", public, "--output", bundle]);
+    assert!(
+        std::fs::read(bundle)
+            .unwrap()
+            .starts_with(b"age-encryption.org/v1\n-> X25519 ")
+    );
+
+    let out = bob.ok(&["share", "import", bundle, "--identity", key]);
+    assert!(out.contains("work/github (alice)"), "{out}");
+    assert_eq!(bob.ok(&["get", "shared/work/jira"]), "j1ra\n");
+    assert_eq!(
+        bob.ok(&["get", "shared/work/github", "--field", "username"]),
+        "alice\n"
+    );
+    assert!(!bob.ok(&["ls"]).contains("personal"));
+
+    // Чужой ключ файл не открывает
+    let other = alice.dir.join("other.txt");
+    let other = other.to_str().unwrap();
+    alice.ok(&["share", "keygen", "--output", other]);
+    let out = bob.pm(&["share", "import", bundle, "--identity", other]);
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("none of the identities"));
+}
+
+#[test]
+fn backup_auto_rotates_only_its_own_archives() {
+    let store = TempStore::initialized();
+    add_login(&store, "work/github", "s3cret");
+    let dir = store.dir.join("backups");
+    std::fs::create_dir_all(&dir).unwrap();
+    let old = [
+        "pm-backup-20200110T120000Z.tar.gz",
+        "pm-backup-20200109T020000Z.tar.gz",
+        "pm-backup-20200109T010000Z.tar.gz",
+        "pm-backup-20200101T000000Z.tar.gz",
+    ];
+    let foreign = ["notes.txt", "pm-backup-latest.tar.gz"];
+    for name in old.iter().chain(&foreign) {
+        std::fs::write(dir.join(name), "").unwrap();
+    }
+    store.ok(&["config", "set", "backup.dir", dir.to_str().unwrap()]);
+    store.ok(&["config", "set", "backup.keep_daily", "2"]);
+    store.ok(&["config", "set", "backup.keep_weekly", "0"]);
+    store.ok(&["config", "set", "backup.keep_monthly", "2"]);
+
+    let preview = store.ok(&["backup", "prune", "--dry-run"]);
+    assert!(
+        preview.contains(old[2]) && preview.contains(old[3]),
+        "{preview}"
+    );
+    assert!(!preview.contains(old[1]), "{preview}");
+    assert!(dir.join(old[3]).exists());
+
+    // Для cron: при успехе — ни строчки
+    assert_eq!(store.ok(&["backup", "auto"]), "");
+    let names: Vec<String> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .collect();
+    assert_eq!(names.len(), 4, "{names:?}");
+    assert!(names.contains(&old[0].to_string()));
+    assert!(foreign.iter().all(|f| names.contains(&f.to_string())));
+    let fresh = names
+        .iter()
+        .find(|n| n.starts_with("pm-backup-20") && !old.contains(&n.as_str()))
+        .unwrap();
+
+    let list = store.ok(&["backup", "list"]);
+    let lines: Vec<&str> = list.lines().collect();
+    assert_eq!(lines.len(), 3, "{list}");
+    assert!(lines[1].contains(fresh.as_str()) && lines[2].contains(old[0]));
+
+    // Архив восстанавливается как обычный бэкап
+    let out = store.ok(&[
+        "backup",
+        "restore",
+        "--force",
+        dir.join(fresh).to_str().unwrap(),
+    ]);
+    assert!(out.contains("Restored 1 entries"), "{out}");
+}
+
+/// Переписать zip-архив, изменив содержимое файлов через `edit`
+/// (`None` — выбросить файл).
+fn rewrite_zip(
+    from: &std::path::Path,
+    to: &std::path::Path,
+    edit: impl Fn(&str, Vec<u8>) -> Option<Vec<u8>>,
+) {
+    use std::io::Read;
+
+    let mut src = zip::ZipArchive::new(std::fs::File::open(from).unwrap()).unwrap();
+    let mut dst = zip::ZipWriter::new(std::fs::File::create(to).unwrap());
+    for i in 0..src.len() {
+        let mut file = src.by_index(i).unwrap();
+        let name = file.name().to_string();
+        let mut data = Vec::new();
+        file.read_to_end(&mut data).unwrap();
+        if let Some(data) = edit(&name, data) {
+            dst.start_file(name, zip::write::FileOptions::default())
+                .unwrap();
+            dst.write_all(&data).unwrap();
+        }
+    }
+    dst.finish().unwrap();
+}
+
+#[test]
+fn backup_verify_reports_damaged_files() {
+    let store = TempStore::initialized();
+    add_login(&store, "work/github", "s3cret");
+    add_login(&store, "work/jira", "j1ra");
+    let good = store.dir.join("good.zip");
+    store.ok(&["backup", "create", good.to_str().unwrap()]);
+
+    let out = store.ok(&["backup", "verify", good.to_str().unwrap(), "--deep"]);
+    assert!(
+        out.contains("checksums match") && out.contains("2 entry files decrypted"),
+        "{out}"
+    );
+
+    // Подменённый байт в одной записи и сломанная обёртка другой
+    let bad = store.dir.join("bad.zip");
+    rewrite_zip(&good, &bad, |name, mut data| {
+        if name == "store/work/github.enc" {
+            let key = b"\"ciphertext\": \"";
+            let at = data.windows(key.len()).position(|w| w == key).unwrap() + key.len();
+            data[at] = if data[at] == b'A' { b'B' } else { b'A' };
+        } else if name == "store/work/jira.enc" {
+            data = b"{not json".to_vec();
+        }
+        Some(data)
+    });
+    let out = store.pm(&["backup", "verify", bad.to_str().unwrap()]);
+    assert!(!out.status.success());
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    let failed: Vec<&str> = stdout.lines().collect();
+    assert_eq!(failed.len(), 3, "{stdout}");
+    assert!(failed.contains(&"FAILED store/work/github.enc: checksum mismatch"));
+    assert!(
+        failed
+            .iter()
+            .filter(|l| l.starts_with("FAILED store/work/jira.enc"))
+            .count()
+            == 2
+    );
+
+    // Архив старого pm без MANIFEST.sha256: всё, кроме сумм, и предупреждение
+    let old = store.dir.join("old.zip");
+    rewrite_zip(&good, &old, |name, data| {
+        (name != "MANIFEST.sha256").then_some(data)
+    });
+    let out = store.pm(&["backup", "verify", old.to_str().unwrap()]);
+    assert!(out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("has no MANIFEST.sha256"));
+    assert!(String::from_utf8_lossy(&out.stdout).contains(": OK (4 files, 2 entries)"));
+}
+
+#[test]
+fn encrypted_backup_restores_with_its_passphrase() {
+    let store = TempStore::initialized();
+    add_login(&store, "work/github", "s3cret");
+    // Кэш сессии и блокировка в бэкап не попадают
+    std::fs::write(store.root().join("session.json"), "{}").unwrap();
+
+    let plain = store.dir.join("plain.zip");
+    store.ok(&["backup", "create", plain.to_str().unwrap()]);
+    let zip = zip::ZipArchive::new(std::fs::File::open(&plain).unwrap()).unwrap();
+    let names: Vec<&str> = zip.file_names().collect();
+    assert!(names.contains(&"config.json"), "{names:?}");
+    assert!(!names.contains(&"session.json") && !names.contains(&".lock"));
+
+    let base = store.dir.join("offsite");
+    let passphrase = [("PM_BACKUP_PASSPHRASE", "tr0ub4dor")];
+    let out = store.pm_env(
+        MASTER,
+        &["backup", "create", base.to_str().unwrap(), "--encrypt"],
+        "",
+        &passphrase,
+    );
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    // Без терминала вместо полосы — одна строка итога
+    let summary = String::from_utf8_lossy(&out.stderr);
+    assert!(
+        summary.contains("Backed up ") && summary.contains(" compressed, in "),
+        "{summary}"
+    );
+    let sealed = store.dir.join("offsite.pmb");
+    let data = std::fs::read(&sealed).unwrap();
+    assert!(data.starts_with(b"PMB\0"));
+    assert!(!data.windows(11).any(|w| w == b"config.json"));
+
+    let other = TempStore::new();
+    let restore = ["backup", "restore", sealed.to_str().unwrap()];
+    let out = other.pm_env(MASTER, &restore, "", &[("PM_BACKUP_PASSPHRASE", "wrong")]);
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("wrong passphrase"));
+    assert!(!other.root().exists());
+
+    let out = other.pm_env(MASTER, &restore, "", &passphrase);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert_eq!(other.ok(&["get", "work/github"]), "s3cret\n");
+    assert!(!other.root().join("session.json").exists());
+}
+
+#[test]
+fn attachments_follow_their_entry() {
+    let store = TempStore::initialized();
+    add_login(&store, "work/github", "s3cret");
+    let codes = store.dir.join("recovery-codes.txt");
+    std::fs::write(&codes, "code-1\ncode-2\n").unwrap();
+    let codes = codes.to_str().unwrap();
+
+    store.ok(&["attach", "add", "work/github", codes]);
+    assert_eq!(
+        store.ok(&["attach", "list", "work/github"]),
+        "recovery-codes.txt  14 B\n"
+    );
+    // Содержимое — отдельным файлом, и в список записей он не попадает
+    let blob = store
+        .root()
+        .join("store/work/github.attachments/recovery-codes.txt.enc");
+    assert!(!std::fs::read_to_string(&blob).unwrap().contains("code-1"));
+    assert_eq!(store.ok(&["ls", "--flat"]), "work/github\n");
+
+    let out = store.pm(&["attach", "add", "work/github", codes, "--name", "../x"]);
+    assert!(!out.status.success());
+    store.ok(&["config", "set", "attachments.max_bytes", "10"]);
+    let out = store.pm(&["attach", "add", "work/github", codes, "--name", "big"]);
+    assert!(String::from_utf8_lossy(&out.stderr).contains("limited to 10 B"));
+
+    store.ok(&["mv", "work/github", "work/gh"]);
+    store.ok(&["cp", "work/gh", "work/gh-copy"]);
+    let get = ["attach", "get", "work/gh-copy", "recovery-codes.txt"];
+    assert_eq!(store.ok(&get), "code-1\ncode-2\n");
+    let saved = store.dir.join("out.txt");
+    store.ok(&[
+        "attach",
+        "get",
+        "work/gh",
+        "recovery-codes.txt",
+        "--output",
+        saved.to_str().unwrap(),
+    ]);
+    assert_eq!(std::fs::read_to_string(&saved).unwrap(), "code-1\ncode-2\n");
+    assert_eq!(
+        store.ok(&[&get[..], &["--output", "-"]].concat()),
+        "code-1\ncode-2\n"
+    );
+
+    // В корзину и обратно — вместе с записью
+    store.ok(&["rm", "-f", "work/gh"]);
+    assert!(!store.root().join("store/work/gh.attachments").exists());
+    store.ok(&["trash", "restore", "work/gh"]);
+    store.ok(&["attach", "get", "work/gh", "recovery-codes.txt"]);
+
+    let json = store.ok(&[
+        "export",
+        "--format",
+        "json",
+        "--i-understand-this-is-plaintext",
+    ]);
+    assert!(
+        json.contains("\"data\": \"Y29kZS0xCmNvZGUtMgo=\""),
+        "{json}"
+    );
+
+    let archive = store.dir.join("with-attachments.zip");
+    store.ok(&["backup", "create", archive.to_str().unwrap()]);
+    let other = TempStore::new();
+    other.ok(&["backup", "restore", archive.to_str().unwrap()]);
+    assert_eq!(other.ok(&get), "code-1\ncode-2\n");
+
+    store.ok(&["attach", "rm", "work/gh", "recovery-codes.txt"]);
+    assert_eq!(
+        store.ok(&["attach", "list", "work/gh"]),
+        "work/gh has no attachments.\n"
+    );
+}
+
+#[test]
+fn mv_and_cp_to_profile_reencrypt_for_the_other_store() {
+    let store = TempStore::initialized();
+    let other = store.dir.join("other");
+    store.ok(&["profile", "add", "other", other.to_str().unwrap()]);
+    let init = store.pm_with("other horse", &["--profile", "other", "init"], "");
+    assert!(init.status.success());
+    let mut dest = pm::PasswordStore::open(other.clone()).unwrap();
+    dest.unlock("other horse").unwrap();
+    let mut theirs = pm::import::new_entry("work/github").unwrap();
+    theirs.password = "theirs".into();
+    dest.add("work/github", &theirs).unwrap();
+
+    add_login(&store, "work/github", "s3cret");
+    add_login(&store, "work/mail", "m4il");
+    store.ok_with(
+        &["otp", "add", "work/github", "--secret", "-"],
+        "JBSWY3DPEHPK3PXP\n",
+    );
+    let codes = store.dir.join("codes.txt");
+    std::fs::write(&codes, "1111-2222\n").unwrap();
+    store.ok(&["attach", "add", "work/github", codes.to_str().unwrap()]);
+    let mut source = pm::PasswordStore::open(store.root()).unwrap();
+    source.unlock(MASTER).unwrap();
+    let mut entry = source.show("work/github").unwrap();
+    entry.fields.push(pm::entry::CustomField {
+        name: "pin".into(),
+        value: "0000".into(),
+        concealed: true,
+    });
+    source.save("work/github", &entry).unwrap();
+
+    let dest_env = [("PM_DEST_MASTER_PASSWORD", "other horse")];
+    let mv = |args: &[&str], dest_password: &str| {
+        let mut all = vec!["mv", "work/github", "work/github", "--to-profile", "other"];
+        all.extend(args);
+        store.pm_env(
+            MASTER,
+            &all,
+            "",
+            &[("PM_DEST_MASTER_PASSWORD", dest_password)],
+        )
+    };
+
+    // Неверный пароль назначения: ничего не трогаем
+    assert!(!mv(&[], "wrong").status.success());
+    assert!(source.exists("work/github").unwrap());
+
+    // По умолчанию существующая запись пропускается, исходная остаётся
+    let out = mv(&[], "other horse");
+    assert!(out.status.success());
+    assert!(String::from_utf8_lossy(&out.stdout).contains("Skipped 1 existing entry"));
+    assert!(source.exists("work/github").unwrap());
+
+    let out = mv(&["--on-conflict", "rename"], "other horse");
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert!(!source.exists("work/github").unwrap());
+    assert!(!store.root().join("store/work/github.attachments").exists());
+
+    assert_eq!(dest.show("work/github").unwrap().password, "theirs");
+    let moved = dest.show("work/github-2").unwrap();
+    assert_eq!(moved.title, "work/github-2");
+    assert_eq!(moved.password, "s3cret");
+    assert_eq!(moved.otp.as_ref().unwrap().secret, "JBSWY3DPEHPK3PXP");
+    assert_eq!(moved.field("pin").unwrap(), Some("0000"));
+    assert_eq!(
+        dest.attachments("work/github-2", &moved).unwrap(),
+        vec![("codes.txt".to_string(), b"1111-2222\n".to_vec())]
+    );
+    let code = store.pm_with(
+        "other horse",
+        &["--profile", "other", "otp", "show", "work/github-2"],
+        "",
+    );
+    assert!(code.status.success());
+
+    // cp оставляет исходную запись; --force в другом профиле значит overwrite
+    let out = store.pm_env(
+        MASTER,
+        &[
+            "cp",
+            "work/mail",
+            "work/github",
+            "--to-profile",
+            "other",
+            "--force",
+        ],
+        "",
+        &dest_env,
+    );
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert!(source.exists("work/mail").unwrap());
+    assert_eq!(dest.show("work/github").unwrap().password, "m4il");
+
+    // Без пароля назначения и без терминала — понятная ошибка
+    let out = store.pm(&["cp", "work/mail", "x", "--to-profile", "other"]);
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("PM_DEST_MASTER_PASSWORD"));
+
+    // Тот же каталог под другим именем — не перенос
+    store.ok(&["profile", "add", "self", store.root().to_str().unwrap()]);
+    let out = store.pm(&["cp", "work/mail", "x", "--to-profile", "self"]);
+    assert!(String::from_utf8_lossy(&out.stderr).contains("is the current store"));
+}
+
+#[test]
+fn status_reports_the_store_and_sweeps_stale_sessions() {
+    let store = TempStore::initialized();
+    add_login(&store, "work/gh", "hunter2-hunter2");
+    let runtime = store.dir.join("run");
+    std::fs::create_dir_all(&runtime).unwrap();
+    let env = [("XDG_RUNTIME_DIR", runtime.to_str().unwrap())];
+
+    // Просроченная сессия рядом с хранилищем — осталась с запуска без XDG_RUNTIME_DIR
+    let stale = r#"{"created_at":1,"expires_at":2,"nonce":"","master_key":""}"#;
+    std::fs::write(store.root().join("session.json"), stale).unwrap();
+    std::fs::write(runtime.join("pm-session.json"), stale).unwrap();
+
+    let out = store.pm_env(MASTER, &["-o", "json", "status"], "", &env);
+    assert!(out.status.success(), "{out:?}");
+    let status: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+    assert_eq!(status["entries"], 1);
+    assert_eq!(status["session"]["active"], false);
+    assert_eq!(status["session"]["stale_removed"], 2);
+    assert_eq!(status["kdf"]["algo"], "argon2id");
+    assert!(status["kdf"].get("salt").is_none());
+    assert_eq!(status["git"]["repository"], false);
+    assert!(!store.root().join("session.json").exists());
+    assert!(!runtime.join("pm-session.json").exists());
+
+    let out = store.pm_env(MASTER, &["status"], "", &env);
+    let text = String::from_utf8_lossy(&out.stdout);
+    assert!(text.contains("Entries:    1\n"), "{text}");
+}
+
+#[test]
+fn find_and_grep_regex_mode() {
+    let store = TempStore::initialized();
+    for path in [
+        "work/db-prod",
+        "work/db-prod-old",
+        "work/dbxprod",
+        "home/db.prod",
+    ] {
+        add_login(&store, path, "hunter2-hunter2");
+    }
+    store.ok_with(
+        &[
+            "add",
+            "work/jira",
+            "--username",
+            "TICKET-42",
+            "--notes",
+            "see ticket-1234",
+            "--password-stdin",
+        ],
+        "s3cret-s3cret\n",
+    );
+
+    // Якорь: только пути, которые кончаются на -prod
+    assert_eq!(
+        store.ok(&["find", "--regex", "--", "-prod$"]),
+        "work/db-prod\n"
+    );
+    // `db.prod` — и подстрока, и regex: с --regex точка значит любой символ
+    assert_eq!(
+        store.ok(&["find", "--regex", "db.prod$"]),
+        "home/db.prod\nwork/db-prod\nwork/dbxprod\n"
+    );
+    assert_eq!(
+        store.ok(&["find", "--regex", r"db\.prod"]),
+        "home/db.prod\n"
+    );
+    let out = store.pm(&["find", "--regex", "(db"]);
+    assert!(!out.status.success());
+    assert!(
+        String::from_utf8_lossy(&out.stderr).contains("invalid regex \"(db\": unclosed group"),
+        "{out:?}"
+    );
+
+    // Шаблон применяется к каждому полю отдельно: `^` — начало поля
+    let grep = |args: &[&str]| {
+        let mut all = vec!["-o", "json", "grep"];
+        all.extend(args);
+        // Без совпадений — код 1, как у grep
+        let out = store.pm(&all);
+        assert!(out.status.code().is_some_and(|c| c <= 1), "{out:?}");
+        let json: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+        json["matches"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|m| m["path"].as_str().unwrap().to_string())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(grep(&["--regex", r"^ticket-\d+$", "-i"]), ["work/jira"]);
+    assert_eq!(
+        grep(&["--regex", r"^ticket-\d+$", "-i", "--fields", "notes"]),
+        Vec::<String>::new()
+    );
+    assert_eq!(
+        grep(&["--regex", r"ticket-\d{4}", "--fields", "notes"]),
+        ["work/jira"]
+    );
+    assert_eq!(grep(&["ticket-\\d"]), Vec::<String>::new());
+}
+
+#[test]
+fn dotted_entry_names_keep_every_dot() {
+    let store = TempStore::initialized();
+    add_login(&store, "work/mail.google.com", "pw-com");
+    add_login(&store, "work/mail.google", "pw-google");
+    let dir = store.root().join("store/work");
+    assert!(dir.join("mail.google.com.enc").is_file());
+    assert!(dir.join("mail.google.enc").is_file());
+
+    assert_eq!(
+        store.ok(&["ls", "--flat"]),
+        "work/mail.google\nwork/mail.google.com\n"
+    );
+    assert_eq!(store.ok(&["get", "work/mail.google.com"]), "pw-com\n");
+    assert_eq!(store.ok(&["get", "work/mail.google"]), "pw-google\n");
+
+    store.ok(&["rm", "work/mail.google.com", "--force"]);
+    assert!(!dir.join("mail.google.com.enc").exists());
+    assert_eq!(store.ok(&["get", "work/mail.google"]), "pw-google\n");
+
+    // Импорт из CSV даёт такие имена постоянно
+    let csv = store.dir.join("chrome.csv");
+    std::fs::write(
+        &csv,
+        "name,url,username,password\nid.example.org,https://id.example.org,bob,pw-csv\n",
+    )
+    .unwrap();
+    store.ok(&[
+        "import",
+        "csv",
+        csv.to_str().unwrap(),
+        "--prefix",
+        "imported",
+    ]);
+    assert_eq!(store.ok(&["get", "imported/id.example.org"]), "pw-csv\n");
+}
+
+#[test]
+fn interrupted_writes_leave_entries_readable_and_are_cleaned_up() {
+    let store = TempStore::initialized();
+    add_login(&store, "work/gh", "hunter2");
+    let dir = store.root().join("store/work");
+    // Обрывок прерванной записи и чужая запись, которая идёт прямо сейчас
+    let stale = dir.join(".gh.enc.tmp-99999-0");
+    let fresh = dir.join(".gh.enc.tmp-99999-1");
+    std::fs::write(&stale, "{\"truncated").unwrap();
+    std::fs::write(&fresh, "{\"in progress").unwrap();
+    let hour_ago = std::time::SystemTime::now() - std::time::Duration::from_secs(3600);
+    std::fs::File::options()
+        .write(true)
+        .open(&stale)
+        .unwrap()
+        .set_modified(hour_ago)
+        .unwrap();
+
+    assert_eq!(store.ok(&["get", "work/gh"]), "hunter2\n");
+    assert_eq!(store.ok(&["ls", "--flat"]), "work/gh\n");
+    assert!(stale.exists());
+
+    store.ok_with(
+        &[
+            "add",
+            "work/gh",
+            "--force",
+            "--username",
+            "alice",
+            "--url",
+            "",
+            "--notes",
+            "",
+            "--password-stdin",
+        ],
+        "hunter3\n",
+    );
+    assert!(!stale.exists());
+    assert!(fresh.exists());
+    assert_eq!(store.ok(&["get", "work/gh"]), "hunter3\n");
+    let leftovers: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|n| n.contains(".tmp-") && n != ".gh.enc.tmp-99999-1")
+        .collect();
+    assert!(leftovers.is_empty(), "{leftovers:?}");
+}
+
+#[cfg(unix)]
+#[test]
+fn store_files_are_private_whatever_the_umask() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let store = TempStore::new();
+    // umask 000: без явных прав всё создавалось бы 0777/0666
+    let pm = |args: &[&str], stdin: &str| {
+        let mut child = Command::new("sh")
+            .args(["-c", "umask 000 && exec \"$0\" --no-cache \"$@\""])
+            .arg(env!("CARGO_BIN_EXE_pm"))
+            .args(args)
+            .env("PM_STORE_DIR", store.root())
+            .env("PM_MASTER_PASSWORD", MASTER)
+            .env("XDG_CONFIG_HOME", store.dir.join("config"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(stdin.as_bytes())
+            .unwrap();
+        let out = child.wait_with_output().unwrap();
+        assert!(out.status.success(), "pm {args:?}: {out:?}");
+    };
+    pm(&["init"], "");
+    let login = [
+        "--username",
+        "alice",
+        "--url",
+        "",
+        "--notes",
+        "",
+        "--password-stdin",
+    ];
+    pm(
+        &[&["add", "work/deep/nested/gh"][..], &login].concat(),
+        "hunter2\n",
+    );
+    let file = store.dir.join("cert.pem");
+    std::fs::write(&file, "-----BEGIN-----").unwrap();
+    pm(
+        &[
+            "attach",
+            "add",
+            "work/deep/nested/gh",
+            file.to_str().unwrap(),
+        ],
+        "",
+    );
+    pm(&["config", "set", "generate.length", "30"], "");
+    pm(&["get", "work/deep/nested/gh"], "");
+
+    let mut checked = 0;
+    for item in walkdir::WalkDir::new(store.root()) {
+        let item = item.unwrap();
+        let mode = item.metadata().unwrap().permissions().mode() & 0o777;
+        let expected = if item.file_type().is_dir() {
+            0o700
+        } else {
+            0o600
+        };
+        assert_eq!(mode, expected, "{:o} {}", mode, item.path().display());
+        checked += 1;
+    }
+    assert!(checked > 8, "{checked}");
+}
+
+/// `git ARGS` в корне хранилища; stdout.
+fn git_in(store: &TempStore, args: &[&str]) -> String {
+    let out = Command::new("git")
+        .args(args)
+        .current_dir(store.root())
+        .envs(GIT_IDENTITY)
+        .output()
+        .expect("git is not installed");
+    assert!(out.status.success(), "git {args:?}: {out:?}");
+    String::from_utf8(out.stdout).unwrap()
+}
+
+const GIT_IDENTITY: [(&str, &str); 4] = [
+    ("GIT_AUTHOR_NAME", "pm test"),
+    ("GIT_AUTHOR_EMAIL", "pm@example.com"),
+    ("GIT_COMMITTER_NAME", "pm test"),
+    ("GIT_COMMITTER_EMAIL", "pm@example.com"),
+];
+
+#[test]
+fn git_repository_tracks_only_entries_and_config() {
+    let store = TempStore::initialized();
+    add_login(&store, "work/gh", "hunter2");
+    let out = store.pm_env(MASTER, &["git", "init"], "", &GIT_IDENTITY);
+    assert!(out.status.success(), "{out:?}");
+    let git_ok = |args: &[&str]| {
+        let out = store.pm_env(MASTER, args, "", &GIT_IDENTITY);
+        assert!(out.status.success(), "pm {args:?}: {out:?}");
+    };
+    let git_add = |path: &str| {
+        let fields = [
+            "--username",
+            "",
+            "--url",
+            "",
+            "--notes",
+            "",
+            "--password-stdin",
+        ];
+        git_ok(&[&["add", path][..], &fields].concat());
+    };
+
+    // Корзина, индекс и блокировка сессии появляются, но в репозиторий не идут
+    std::fs::write(store.root().join("session.lock"), "").unwrap();
+    git_ok(&["rm", "-f", "work/gh"]);
+    assert!(store.root().join("trash").is_dir());
+    git_add("mail");
+    assert_eq!(git_in(&store, &["status", "--porcelain"]), "");
+    assert_eq!(
+        git_in(&store, &["ls-files"]),
+        ".gitignore\nconfig.json\nstore/mail.enc\n"
+    );
+
+    // Репозиторий старой версии: индекс и корзина уже закоммичены
+    std::fs::write(
+        store.root().join(".gitignore"),
+        "session.json\naccess.log\nmigrate.journal\n.lock\n.*.tmp-*\n",
+    )
+    .unwrap();
+    git_in(&store, &["add", "-A"]);
+    git_in(&store, &["commit", "-q", "-m", "old layout"]);
+    assert!(git_in(&store, &["ls-files"]).contains("index.enc"));
+    git_add("home");
+    assert_eq!(
+        git_in(&store, &["log", "--format=%s", "-2"]),
+        "Add home\nUpdate .gitignore\n"
+    );
+    assert_eq!(
+        git_in(&store, &["ls-files"]),
+        ".gitignore\nconfig.json\nstore/home.enc\nstore/mail.enc\n"
+    );
+    assert_eq!(git_in(&store, &["status", "--porcelain"]), "");
+}
+
+#[test]
+fn git_commits_attachments_with_their_entry() {
+    let store = TempStore::initialized();
+    add_login(&store, "work/gh", "hunter2");
+    let git_ok = |args: &[&str]| {
+        let out = store.pm_env(MASTER, args, "", &GIT_IDENTITY);
+        assert!(out.status.success(), "pm {args:?}: {out:?}");
+    };
+    git_ok(&["git", "init"]);
+    let codes = store.dir.join("codes.txt");
+    std::fs::write(&codes, "code-1\n").unwrap();
+    let attach = ["attach", "add", "work/gh", codes.to_str().unwrap()];
+
+    git_ok(&attach);
+    assert_eq!(git_in(&store, &["status", "--porcelain"]), "");
+    assert_eq!(
+        git_in(&store, &["ls-files"]),
+        ".gitignore\nconfig.json\nstore/work/gh.attachments/codes.txt.enc\nstore/work/gh.enc\n"
+    );
+
+    // Удаление вложения попадает в тот же коммит, что и новый список
+    git_ok(&["attach", "rm", "work/gh", "codes.txt"]);
+    assert_eq!(git_in(&store, &["status", "--porcelain"]), "");
+    assert_eq!(
+        git_in(&store, &["ls-files"]),
+        ".gitignore\nconfig.json\nstore/work/gh.enc\n"
+    );
+
+    git_ok(&attach);
+    git_ok(&["rm", "-f", "work/gh"]);
+    assert_eq!(git_in(&store, &["status", "--porcelain"]), "");
+    assert_eq!(git_in(&store, &["ls-files"]), ".gitignore\nconfig.json\n");
+    assert_eq!(
+        git_in(&store, &["log", "--format=%s", "-3"]),
+        "Remove work/gh\nEdit work/gh\nEdit work/gh\n"
+    );
+}
+
+#[test]
+fn grep_reports_field_hits_and_keeps_passwords_out() {
+    let store = TempStore::initialized();
+    {
+        let mut lib = pm::PasswordStore::open(store.root()).unwrap();
+        lib.unlock(MASTER).unwrap();
+        let mut entry = pm::import::new_entry("mail/work").unwrap();
+        entry.username = Some("Needle.Bob".into());
+        entry.password = "needle-in-the-password".into();
+        entry.notes = Some("first line\nthe NEEDLE is here\nlast line".into());
+        lib.add("mail/work", &entry).unwrap();
+    }
+    add_login(&store, "bank/main", "needle-only-here");
+    add_login(&store, "broken", "needle-too");
+    std::fs::write(store.root().join("store/broken.enc"), "garbage").unwrap();
+
+    // Без учёта регистра, построчно, без пароля; битая запись — только предупреждение
+    let out = store.pm(&["grep", "needle"]);
+    assert!(out.status.success(), "{out:?}");
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert_eq!(
+        stdout,
+        "mail/work: username: Needle.Bob\nmail/work: notes: the NEEDLE is here\n"
+    );
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(
+        stderr.contains("Warning: cannot decrypt broken"),
+        "{stderr}"
+    );
+    assert!(!stderr.contains("needle-"), "{stderr}");
+
+    let out = store.pm(&["grep", "needle", "--fields", "password"]);
+    assert!(!out.status.success());
+    assert!(
+        String::from_utf8_lossy(&out.stderr)
+            .contains("refusing to search passwords without --include-passwords"),
+        "{out:?}"
+    );
+
+    let stdout = store.ok(&["grep", "only-here", "--include-passwords"]);
+    assert_eq!(stdout, "bank/main: password: needle-only-here\n");
+
+    // Нет совпадений — код 1 и пустой вывод
+    let out = store.pm(&["grep", "no such text"]);
+    assert_eq!(out.status.code(), Some(1), "{out:?}");
+    assert!(out.stdout.is_empty());
+}
+
+#[test]
+fn bulk_decryption_of_500_entries_is_ordered_and_isolates_failures() {
+    let store = TempStore::initialized();
+    {
+        // Заполняем через библиотеку: 500 вызовов `pm add` заняли бы минуты
+        let mut lib = pm::PasswordStore::open(store.root()).unwrap();
+        lib.unlock(MASTER).unwrap();
+        for i in 0..500 {
+            let path = format!("bulk/{:02}/entry-{i:03}", i % 7);
+            let mut entry = pm::import::new_entry(&path).unwrap();
+            entry.password = format!("pw-{i}");
+            entry.notes = Some(format!("note-{i:03}"));
+            lib.add(&path, &entry).unwrap();
+        }
+    }
+    // Одна испорченная запись не должна сорвать остальные
+    std::fs::write(
+        store.root().join("store/bulk/03/entry-003.enc"),
+        "not an entry",
+    )
+    .unwrap();
+
+    let mut expected: Vec<String> = (0..500)
+        .filter(|&i| i != 3)
+        .map(|i| format!("bulk/{:02}/entry-{i:03}", i % 7))
+        .collect();
+    expected.sort();
+
+    let mut outputs = Vec::new();
+    for threads in ["1", "8"] {
+        let started = std::time::Instant::now();
+        let out = store.pm_env(
+            MASTER,
+            &[
+                "-o",
+                "json",
+                "grep",
+                "--regex",
+                r"^note-\d+$",
+                "--fields",
+                "notes",
+            ],
+            "",
+            &[("PM_DECRYPT_THREADS", threads)],
+        );
+        eprintln!(
+            "grep over 500 entries with {threads} thread(s): {:?}",
+            started.elapsed()
+        );
+        assert!(out.status.success(), "{out:?}");
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        assert!(
+            stderr.contains("cannot decrypt bulk/03/entry-003"),
+            "{stderr}"
+        );
+
+        let json: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+        let matches = json["matches"].as_array().unwrap();
+        let paths: Vec<&str> = matches
+            .iter()
+            .map(|m| m["path"].as_str().unwrap())
+            .collect();
+        assert_eq!(paths, expected);
+        for m in matches {
+            let i = &m["path"].as_str().unwrap()[14..];
+            assert_eq!(m["line"], format!("note-{i}"));
+        }
+        outputs.push(out.stdout);
+    }
+    // Порядок и содержимое не зависят от числа потоков
+    assert_eq!(outputs[0], outputs[1]);
+}
+
+#[test]
+fn environment_warnings_are_targeted_and_suppressible() {
+    let store = TempStore::initialized();
+    add_login(&store, "work/gh", "hunter2-hunter2");
+    let stderr = |args: &[&str]| {
+        let out = store.pm(args);
+        assert!(out.status.success(), "{out:?}");
+        String::from_utf8(out.stderr).unwrap()
+    };
+
+    // Маскированный вывод в пайп — не повод предупреждать
+    let masked = stderr(&["show", "work/gh"]);
+    assert!(!masked.contains("[secret-redirect]"), "{masked}");
+    assert!(masked.contains("[master-password-env]"), "{masked}");
+    assert!(stderr(&["show", "work/gh", "--reveal"]).contains("[secret-redirect]"));
+    assert!(!stderr(&["show", "work/gh", "--password-only"]).contains("[secret-redirect]"));
+
+    let out = store.pm(&["config", "set", "warnings.suppress", "bogus"]);
+    assert!(!out.status.success());
+    store.ok(&[
+        "config",
+        "set",
+        "warnings.suppress",
+        "secret-redirect, master-password-env",
+    ]);
+    assert_eq!(
+        store.ok(&["config", "get", "warnings.suppress"]),
+        "secret-redirect,master-password-env\n"
+    );
+    assert!(!stderr(&["show", "work/gh", "--reveal"]).contains("Warning"));
+
+    // pm doctor показывает и заглушённые, с пояснением
+    let doctor = String::from_utf8(store.pm(&["doctor"]).stdout).unwrap();
+    assert!(
+        doctor.contains("[master-password-env] (suppressed)"),
+        "{doctor}"
+    );
+    assert!(doctor.contains("/proc/<pid>/environ"), "{doctor}");
+
+    store.ok(&["config", "set", "warnings.suppress", "none"]);
+    assert_eq!(store.ok(&["config", "get", "warnings.suppress"]), "none\n");
+}
+
+#[test]
+fn conceal_password_precedence_has_four_layers() {
+    let store = TempStore::initialized();
+    add_login(&store, "work/gh", "hunter2-hunter2");
+    let password = |args: &[&str], env: &[(&str, &str)]| {
+        let mut all = vec!["show", "work/gh", "--json"];
+        all.extend(args);
+        let out = store.pm_env(MASTER, &all, "", env);
+        assert!(out.status.success(), "{out:?}");
+        let json: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+        json["password"].as_str().unwrap().to_string()
+    };
+    let shown = "hunter2-hunter2";
+    let hidden = "<concealed>";
+
+    // 1. Встроенное значение
+    assert_eq!(password(&[], &[]), shown);
+    // 2. Настройка сильнее встроенного значения
+    store.ok(&["config", "set", "display.conceal_password", "true"]);
+    assert_eq!(password(&[], &[]), hidden);
+    // 3. PM_CONCEAL сильнее настройки — в обе стороны
+    assert_eq!(password(&[], &[("PM_CONCEAL", "0")]), shown);
+    store.ok(&["config", "set", "display.conceal_password", "false"]);
+    assert_eq!(password(&[], &[("PM_CONCEAL", "1")]), hidden);
+    // 4. --reveal сильнее всего
+    store.ok(&["config", "set", "display.conceal_password", "true"]);
+    assert_eq!(password(&["--reveal"], &[("PM_CONCEAL", "1")]), shown);
+
+    // --password-only печатает секрет при любом режиме
+    let out = store.pm_env(
+        MASTER,
+        &["show", "work/gh", "--password-only"],
+        "",
+        &[("PM_CONCEAL", "1")],
+    );
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "hunter2-hunter2\n");
+
+    let status = store.ok(&["status"]);
+    assert!(
+        status.contains("Password:   masked in pm show, concealed in --json"),
+        "{status}"
+    );
+}
+
+#[test]
+fn show_phonetic_spells_the_chosen_field() {
+    let store = TempStore::initialized();
+    add_login(&store, "work/github", "Ab7@");
+
+    let out = store.pm(&["show", "work/github", "--phonetic"]);
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("--force"));
+    assert!(out.stdout.is_empty());
+
+    assert_eq!(
+        store.ok(&["show", "work/github", "--phonetic", "--force"]),
+        "A  alfa (UPPER)\nb  bravo (lower)\n7  seven\n@  at-sign\n"
+    );
+    assert_eq!(
+        store.ok(&[
+            "show",
+            "work/github",
+            "--phonetic",
+            "--field",
+            "username",
+            "--force"
+        ]),
+        "a  alfa (lower)\nl  lima (lower)\ni  india (lower)\nc  charlie (lower)\ne  echo (lower)\n"
+    );
+}
+
+#[test]
+fn show_format_snippets_need_force_off_a_terminal() {
+    let store = TempStore::initialized();
+    add_login(&store, "db/prod", "p w$");
+
+    let out = store.pm(&["show", "db/prod", "--format", "dotenv"]);
+    assert!(!out.status.success());
+    assert!(out.stdout.is_empty());
+
+    assert_eq!(
+        store.ok(&[
+            "show", "db/prod", "--format", "dotenv", "--prefix", "DB_", "--force"
+        ]),
+        "DB_USERNAME=alice\nDB_PASSWORD=\"p w\\$\"\n"
+    );
+    assert_eq!(
+        store.ok(&["show", "db/prod", "--format", "netrc", "--force"]),
+        "machine example.com login alice password \"p w$\"\n"
+    );
+}
+
+/// Скопировать дерево `from` в `to`.
+fn copy_tree(from: &Path, to: &Path) {
+    for item in walkdir::WalkDir::new(from) {
+        let item = item.unwrap();
+        let target = to.join(item.path().strip_prefix(from).unwrap());
+        if item.file_type().is_dir() {
+            std::fs::create_dir_all(&target).unwrap();
+        } else {
+            std::fs::copy(item.path(), &target).unwrap();
+        }
+    }
+}
+
+/// Хранилища из tests/fixtures/stores записаны старыми форматами (пароль —
+/// `MASTER`, дешёвый KDF): v1 — обёртка без выравнивания и схема v1, v2 —
+/// выравнивание без привязки к пути и смесь схем, v3 — текущий формат.
+#[test]
+fn migrate_upgrades_fixture_stores_of_every_version() {
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/stores");
+    let cases = [
+        (
+            "v1",
+            vec![
+                ("work/github", "gh-v1"),
+                ("personal/mail", "mail v1 \"quoted\""),
+            ],
+            "2 entries need migration.",
+        ),
+        (
+            "v2",
+            vec![
+                ("work/github", "gh-v2"),
+                ("work/aws", "aws-v2"),
+                ("personal/mail", "mail-v2"),
+            ],
+            "3 entries need migration.",
+        ),
+        (
+            "v3",
+            vec![("work/github", "gh-v3")],
+            "0 entries need migration.",
+        ),
+    ];
+    for (version, entries, pending) in cases {
+        let store = TempStore::new();
+        copy_tree(&fixtures.join(version), &store.root());
+        let file_of = |path: &str| store.root().join(format!("store/{path}.enc"));
+        let before: Vec<_> = entries
+            .iter()
+            .map(|(path, _)| std::fs::read(file_of(path)).unwrap())
+            .collect();
+
+        let plan = store.ok(&["migrate", "--dry-run"]);
+        assert!(plan.contains(pending), "{version}: {plan}");
+        for ((path, _), old) in entries.iter().zip(&before) {
+            assert_eq!(&std::fs::read(file_of(path)).unwrap(), old, "{version}");
+        }
+
+        store.ok(&["migrate"]);
+        for (path, password) in &entries {
+            let file: serde_json::Value =
+                serde_json::from_slice(&std::fs::read(file_of(path)).unwrap()).unwrap();
+            assert_eq!(file["version"], 3, "{version} {path}");
+            assert_eq!(file["algo"], "xchacha20-poly1305", "{version} {path}");
+            let shown: serde_json::Value =
+                serde_json::from_str(&store.ok(&["-o", "json", "show", path, "--reveal"])).unwrap();
+            assert_eq!(shown["password"], *password, "{version} {path}");
+        }
+        let config: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(store.root().join("config.json")).unwrap())
+                .unwrap();
+        assert!(config.get("settings").is_some(), "{version}");
+        assert!(!store.root().join("migrate.journal").exists());
+
+        let again = store.ok(&["migrate", "--dry-run"]);
+        assert!(again.contains("config.json: v1 (current)"), "{again}");
+        assert!(again.contains("0 entries need migration."), "{again}");
+    }
+
+    // Схема v1 получила поля, OTP и заметки пережили миграцию
+    let store = TempStore::new();
+    copy_tree(&fixtures.join("v1"), &store.root());
+    store.ok(&["migrate"]);
+    let mail: serde_json::Value =
+        serde_json::from_str(&store.ok(&["-o", "json", "show", "personal/mail"])).unwrap();
+    assert_eq!(mail["notes"], "first\nsecond");
+    assert!(store.ok(&["otp", "show", "work/github"]).trim().len() >= 6);
+}
+
+#[test]
+fn entry_cipher_is_recorded_and_checked() {
+    let store = TempStore::initialized();
+    add_login(&store, "work/github", "s3cret");
+    add_login(&store, "work/legacy", "old");
+    add_login(&store, "work/future", "new");
+    let file_of = |path: &str| store.root().join(format!("store/{path}.enc"));
+    let rewrite = |path: &str, algo: Option<&str>| {
+        let mut file: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(file_of(path)).unwrap()).unwrap();
+        match algo {
+            Some(algo) => file["algo"] = algo.into(),
+            None => {
+                file.as_object_mut().unwrap().remove("algo");
+            }
+        }
+        std::fs::write(file_of(path), file.to_string()).unwrap();
+    };
+
+    let written: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(file_of("work/github")).unwrap()).unwrap();
+    assert_eq!(written["algo"], "xchacha20-poly1305");
+
+    // Старые файлы без поля — XChaCha20-Poly1305
+    rewrite("work/legacy", None);
+    assert_eq!(
+        store.ok(&["show", "work/legacy", "--password-only"]),
+        "old\n"
+    );
+
+    rewrite("work/future", Some("aes256-gcm"));
+    let out = store.pm(&["show", "work/future"]);
+    assert!(!out.status.success());
+    assert!(
+        String::from_utf8_lossy(&out.stderr).contains(
+            "entry work/future was encrypted with aes256-gcm but this build lacks support"
+        )
+    );
+
+    let out = store.pm(&["fsck"]);
+    assert!(!out.status.success());
+    let report = String::from_utf8_lossy(&out.stdout);
+    assert!(report.contains("BAD  work/future"), "{report}");
+    assert!(report.contains("  aes256-gcm: 1\n"), "{report}");
+    assert!(report.contains("  xchacha20-poly1305: 2\n"), "{report}");
+}
+
+#[test]
+fn ls_and_find_filter_by_dates() {
+    let store = TempStore::initialized();
+    for path in ["work/old", "work/edge", "work/new"] {
+        add_login(&store, path, "pw");
+    }
+    let mut lib = pm::PasswordStore::open(store.root()).unwrap();
+    lib.unlock(MASTER).unwrap();
+    for (path, created, updated) in [
+        ("work/old", "2022-05-01T00:00:00Z", "2023-06-01T00:00:00Z"),
+        ("work/edge", "2023-01-01T00:00:00Z", "2024-01-01T00:00:00Z"),
+    ] {
+        let mut entry = lib.show(path).unwrap();
+        entry.created_at = created.into();
+        entry.updated_at = updated.into();
+        lib.save(path, &entry).unwrap();
+    }
+
+    let ls = |args: &[&str]| {
+        let mut all = vec!["ls", "--flat"];
+        all.extend(args);
+        store.ok(&all)
+    };
+    // Ровно на границе — «since»
+    assert_eq!(
+        ls(&["--modified-since", "2024-01-01"]),
+        "work/edge\nwork/new\n"
+    );
+    assert_eq!(
+        ls(&["--modified-before", "2024-01-01T00:00:00Z"]),
+        "work/old\n"
+    );
+    assert_eq!(ls(&["--modified-before", "30d"]), "work/edge\nwork/old\n");
+    assert_eq!(ls(&["--created-since", "1y"]), "work/new\n");
+    assert_eq!(
+        ls(&["--created-since", "2023-01-01", "--modified-before", "1d"]),
+        "work/edge\n"
+    );
+    assert_eq!(
+        store.ok(&["find", "work", "--modified-since", "2024-01-01"]),
+        "work/edge\nwork/new\n"
+    );
+
+    let out = store.pm(&["ls", "--modified-since", "last tuesday"]);
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("expected YYYY-MM-DD"));
+}
+
+/// Исполняемый shell-скрипт `name` в каталоге теста.
+#[cfg(unix)]
+fn script(store: &TempStore, name: &str, body: &str) -> String {
+    use std::os::unix::fs::PermissionsExt;
+    let path = store.dir.join(name);
+    std::fs::write(&path, format!("#!/bin/sh\n{body}\n")).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+#[cfg(unix)]
+#[test]
+fn hooks_run_after_changes_without_secrets() {
+    let store = TempStore::initialized();
+    let log = store.dir.join("hooks.log");
+    let record = script(
+        &store,
+        "record.sh",
+        &format!(
+            "echo \"$1 $2|$PM_HOOK_ACTION $PM_HOOK_PATH|$PM_STORE_DIR|${{PM_MASTER_PASSWORD:-no password}}\" >> {}",
+            log.display()
+        ),
+    );
+    let failing = script(&store, "fail.sh", "exit 3");
+    let slow = script(&store, "slow.sh", "exec sleep 10");
+    store.ok(&["config", "set", "hooks.post_any", &record]);
+    store.ok(&["config", "set", "hooks.post_rm", &failing]);
+    store.ok(&["config", "set", "hooks.post_edit", &slow]);
+    store.ok(&["config", "set", "hooks.timeout_secs", "1"]);
+    let root = store.root();
+    let root = root.display();
+
+    add_login(&store, "work/github", "s3cret");
+    store.ok(&["mv", "work/github", "work/gh"]);
+    let out = store.pm(&["rm", "-f", "work/gh"]);
+    assert!(out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("Warning: hook"));
+    assert!(String::from_utf8_lossy(&out.stderr).contains("exited with code 3"));
+    store.ok(&["--no-hooks", "trash", "restore", "work/gh"]);
+
+    let started = std::time::Instant::now();
+    let out = store.pm_with(MASTER, &["tag", "add", "work/gh", "x"], "");
+    assert!(out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("timed out after 1s"));
+    assert!(started.elapsed() < std::time::Duration::from_secs(8));
+
+    let log = std::fs::read_to_string(&log).unwrap();
+    assert_eq!(
+        log,
+        format!(
+            "add work/github|add work/github|{root}|no password\n\
+             rm work/github|rm work/github|{root}|no password\n\
+             add work/gh|add work/gh|{root}|no password\n\
+             rm work/gh|rm work/gh|{root}|no password\n\
+             edit work/gh|edit work/gh|{root}|no password\n"
+        )
+    );
+    assert!(!log.contains("s3cret"));
+}
+
+#[cfg(unix)]
+#[test]
+fn only_the_lock_holders_descendants_inherit_the_lock() {
+    use std::os::unix::io::AsRawFd;
+    let store = TempStore::initialized();
+    add_login(&store, "work/github", "s3cret");
+
+    // Хук держателя блокировки читает хранилище, не дожидаясь её
+    let listed = store.dir.join("listed.txt");
+    let hook = script(
+        &store,
+        "list.sh",
+        &format!(
+            "{} --no-cache ls --flat > {}",
+            env!("CARGO_BIN_EXE_pm"),
+            listed.display()
+        ),
+    );
+    store.ok(&["config", "set", "hooks.post_add", &hook]);
+    store.ok(&["config", "set", "hooks.timeout_secs", "30"]);
+    let started = std::time::Instant::now();
+    add_login(&store, "work/gitlab", "hunter2");
+    assert!(started.elapsed() < std::time::Duration::from_secs(4));
+    assert_eq!(
+        std::fs::read_to_string(&listed).unwrap(),
+        "work/github\nwork/gitlab\n"
+    );
+
+    // Чужой живой процесс, записанный держателем, не даёт обойти блокировку
+    let mut other = std::process::Command::new("sleep")
+        .arg("30")
+        .spawn()
+        .unwrap();
+    let lock = std::fs::File::options()
+        .read(true)
+        .write(true)
+        .open(store.root().join(".lock"))
+        .unwrap();
+    assert_eq!(
+        unsafe { libc::flock(lock.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) },
+        0
+    );
+    std::fs::write(
+        store.root().join(".lock"),
+        format!("{} pm edit\n", other.id()),
+    )
+    .unwrap();
+    let out = store.pm_env(
+        MASTER,
+        &["--no-hooks", "rm", "-f", "work/github"],
+        "",
+        &[("PM_LOCK_PID", &other.id().to_string())],
+    );
+    other.kill().unwrap();
+    other.wait().unwrap();
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("store is locked by another pm process"));
+    drop(lock);
+    assert!(store.root().join("store/work/github.enc").is_file());
+}
+
+#[test]
+fn verify_password_reports_only_match_or_no_match() {
+    let store = TempStore::initialized();
+    add_login(&store, "work/github", "s3cret");
+
+    let out = store.pm_with(MASTER, &["verify-password", "work/github"], "s3cret\n");
+    assert!(out.status.success());
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "match\n");
+
+    for candidate in ["s3creT\n", "s3cre\n", "\n"] {
+        let out = store.pm_with(MASTER, &["verify-password", "work/github"], candidate);
+        assert_eq!(out.status.code(), Some(1), "{candidate:?}");
+        assert_eq!(String::from_utf8_lossy(&out.stdout), "no match\n");
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        assert!(!stderr.contains("s3cre"), "{stderr}");
+    }
+
+    let out = store.pm_with(
+        MASTER,
+        &["verify-password", "work/github", "--field", "username"],
+        "alice\n",
+    );
+    assert!(out.status.success());
+    let out = store.pm_with(
+        MASTER,
+        &["verify-password", "work/github", "--field", "nope"],
+        "x\n",
+    );
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("unknown field"));
+}
+
+#[test]
+fn clear_after_is_a_noop_off_a_terminal() {
+    let store = TempStore::initialized();
+    add_login(&store, "work/github", "s3cret");
+
+    let started = std::time::Instant::now();
+    let out = store.pm(&[
+        "show",
+        "work/github",
+        "--password-only",
+        "--clear-after",
+        "30",
+    ]);
+    assert!(out.status.success());
+    assert!(started.elapsed() < std::time::Duration::from_secs(20));
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "s3cret\n");
+    assert!(
+        String::from_utf8_lossy(&out.stderr)
+            .contains("--clear-after ignored, stdout is not a capable terminal")
+    );
+}
+
+#[test]
+fn print0_output_splits_on_nul() {
+    let store = TempStore::initialized();
+    let paths = ["personal/my bank", "work/github", "work/git lab"];
+    for path in paths {
+        add_login(&store, path, "pw");
+    }
+    let split = |out: String| -> Vec<String> {
+        assert!(out.ends_with('\0'), "{out:?}");
+        let mut items: Vec<String> = out
+            .trim_end_matches('\0')
+            .split('\0')
+            .map(str::to_string)
+            .collect();
+        items.sort();
+        items
+    };
+
+    let mut all: Vec<String> = paths.iter().map(|p| p.to_string()).collect();
+    all.sort();
+    assert_eq!(split(store.ok(&["ls", "-0"])), all);
+    assert_eq!(split(store.ok(&["ls", "--print0", "work"])), all[1..]);
+    assert_eq!(
+        split(store.ok(&["find", "-0", "git"])),
+        ["work/git lab", "work/github"]
+    );
+    assert_eq!(
+        split(store.ok(&["__complete-paths", "-0", "work/git"])),
+        ["work/git lab", "work/github"]
+    );
+    assert_eq!(
+        split(store.ok(&["__complete-paths", "-0"])),
+        ["personal/", "work/"]
+    );
+
+    // Обычный вывод — ровно путь на строку, без отступов
+    assert_eq!(
+        store.ok(&["ls", "--flat"]),
+        "personal/my bank\nwork/git lab\nwork/github\n"
+    );
+
+    let out = store.pm(&["-o", "json", "ls", "-0"]);
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("cannot be combined with JSON"));
+}
+
+/// `pm ARGS` с кэшем сессии (без `--no-cache`) в каталоге `runtime`.
+fn pm_cached(store: &TempStore, runtime: &Path, password: &str, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_pm"))
+        .args(args)
+        .env("PM_STORE_DIR", store.root())
+        .env("PM_MASTER_PASSWORD", password)
+        .env("XDG_CONFIG_HOME", store.dir.join("config"))
+        .env("XDG_RUNTIME_DIR", runtime)
+        .env_remove("PM_CONCEAL")
+        .stdin(Stdio::null())
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn session_ttl_precedence_is_flag_then_config_then_default() {
+    let store = TempStore::initialized();
+    add_login(&store, "work/gh", "hunter2");
+    let runtime = store.dir.join("run");
+    std::fs::create_dir_all(&runtime).unwrap();
+    // TTL записанной сессии; None — сессия не сохранялась
+    let cached_ttl = |args: &[&str]| -> Option<u64> {
+        for e in std::fs::read_dir(&runtime).unwrap() {
+            std::fs::remove_file(e.unwrap().path()).unwrap();
+        }
+        let out = pm_cached(&store, &runtime, MASTER, args);
+        assert!(out.status.success(), "{out:?}");
+        let file = std::fs::read_dir(&runtime)
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .find(|p| p.extension().is_some_and(|e| e == "json"))?;
+        let json: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(file).unwrap()).unwrap();
+        Some(json["expires_at"].as_u64().unwrap() - json["created_at"].as_u64().unwrap())
+    };
+
+    assert_eq!(cached_ttl(&["get", "work/gh"]), Some(300));
+    store.ok(&["config", "set", "session.ttl_secs", "900"]);
+    assert_eq!(cached_ttl(&["get", "work/gh"]), Some(900));
+    assert_eq!(cached_ttl(&["get", "work/gh", "--ttl", "60"]), Some(60));
+    assert_eq!(cached_ttl(&["--no-cache", "get", "work/gh"]), None);
+    assert_eq!(
+        cached_ttl(&["--no-cache", "get", "work/gh", "--ttl", "60"]),
+        None
+    );
+    store.ok(&["config", "set", "session.ttl_secs", "0"]);
+    assert_eq!(cached_ttl(&["get", "work/gh"]), None);
+}
+
+#[cfg(unix)]
+#[test]
+fn agent_key_is_dropped_when_the_store_changes() {
+    let store = TempStore::initialized();
+    add_login(&store, "work/gh", "hunter2");
+    let runtime = store.dir.join("run");
+    std::fs::create_dir_all(&runtime).unwrap();
+    // Агент удаляет сокет уже после ответа на `stop`
+    let agent_running = |expected: bool| {
+        for _ in 0..40 {
+            let running = std::fs::read_dir(&runtime)
+                .unwrap()
+                .any(|e| e.unwrap().path().extension().is_some_and(|x| x == "sock"));
+            if running == expected {
+                return;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+        panic!("agent running: expected {expected}");
+    };
+
+    let out = pm_cached(&store, &runtime, MASTER, &["agent"]);
+    assert!(out.status.success(), "{out:?}");
+    agent_running(true);
+    // Пароль не нужен: ключ отдаёт агент
+    let out = pm_cached(&store, &runtime, "wrong", &["get", "work/gh"]);
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "hunter2\n", "{out:?}");
+
+    // Хранилище подменили мимо pm: ключ агента к новому config.json не подходит
+    let other = TempStore::initialized();
+    add_login(&other, "work/gh", "other-secret");
+    std::fs::remove_dir_all(store.root()).unwrap();
+    copy_tree(&other.root(), &store.root());
+    let out = pm_cached(&store, &runtime, "wrong", &["get", "work/gh"]);
+    assert!(!out.status.success(), "{out:?}");
+    agent_running(false);
+    let out = pm_cached(&store, &runtime, MASTER, &["get", "work/gh"]);
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "other-secret\n");
+
+    // Команды, которые сбрасывают сессию, останавливают и агента
+    assert!(
+        pm_cached(&store, &runtime, MASTER, &["agent"])
+            .status
+            .success()
+    );
+    agent_running(true);
+    let keyfile = store.dir.join("pm.key");
+    std::fs::write(&keyfile, "0123456789abcdef").unwrap();
+    let out = pm_cached(
+        &store,
+        &runtime,
+        MASTER,
+        &["config", "add-keyfile", keyfile.to_str().unwrap()],
+    );
+    assert!(out.status.success(), "{out:?}");
+    agent_running(false);
+}
+
+#[test]
+fn concurrent_commands_share_one_intact_session() {
+    let store = TempStore::initialized();
+    add_login(&store, "work/gh", "hunter2");
+    let runtime = store.dir.join("run");
+    std::fs::create_dir_all(&runtime).unwrap();
+    let session_files = || -> Vec<PathBuf> {
+        std::fs::read_dir(&runtime)
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .filter(|p| p.extension().is_some_and(|e| e == "json"))
+            .collect()
+    };
+
+    // Без сессии, с действующей, с обрезанным файлом и с просроченным
+    let states: [Option<&str>; 4] = [
+        None,
+        None,
+        Some(r#"{"created_at":17000"#),
+        Some(r#"{"created_at":1,"expires_at":2,"nonce":"","master_key":""}"#),
+    ];
+    for (round, state) in states.into_iter().enumerate() {
+        if let Some(contents) = state {
+            for file in session_files() {
+                std::fs::write(file, contents).unwrap();
+            }
+        }
+        std::thread::scope(|s| {
+            let workers: Vec<_> = (0..8)
+                .map(|_| {
+                    s.spawn(|| {
+                        pm_cached(
+                            &store,
+                            &runtime,
+                            MASTER,
+                            &["show", "work/gh", "--password-only"],
+                        )
+                    })
+                })
+                .collect();
+            for worker in workers {
+                let out = worker.join().unwrap();
+                assert!(
+                    out.status.success(),
+                    "round {round}: {}",
+                    String::from_utf8_lossy(&out.stderr)
+                );
+                assert_eq!(String::from_utf8_lossy(&out.stdout), "hunter2\n");
+            }
+        });
+
+        let files = session_files();
+        assert_eq!(files.len(), 1, "round {round}: {files:?}");
+        let session: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&files[0]).unwrap()).unwrap();
+        assert!(session["expires_at"].as_u64().unwrap() > 2, "round {round}");
+        // Временные файлы атомарной записи не остаются
+        let leftovers: Vec<_> = std::fs::read_dir(&runtime)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .filter(|n| !n.ends_with(".json") && !n.ends_with(".lock") && !n.ends_with(".key"))
+            .collect();
+        assert!(leftovers.is_empty(), "round {round}: {leftovers:?}");
+    }
+
+    // Ключ в сессии цел: неверный пароль не нужен
+    let out = pm_cached(
+        &store,
+        &runtime,
+        "wrong",
+        &["show", "work/gh", "--password-only"],
+    );
+    assert!(out.status.success());
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "hunter2\n");
+}
+
+#[test]
+fn nfd_names_on_disk_resolve_and_fsck_fixes_them() {
+    let store = TempStore::initialized();
+    let nfc = "caf\u{e9}/login";
+    let nfd = "cafe\u{301}/login";
+    add_login(&store, nfd, "s3cret");
+    let disk = store.root().join("store");
+    assert!(disk.join("caf\u{e9}/login.enc").is_file());
+
+    // Как после копирования с macOS: имя каталога в NFD
+    std::fs::rename(disk.join("caf\u{e9}"), disk.join("cafe\u{301}")).unwrap();
+    assert_eq!(store.ok(&["ls", "--flat"]), format!("{nfc}\n"));
+    for path in [nfc, nfd, "cafe\u{301}\\login"] {
+        assert_eq!(
+            store.ok(&["show", path, "--password-only"]),
+            "s3cret\n",
+            "{path:?}"
+        );
+    }
+
+    let out = store.pm(&["fsck"]);
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stdout).contains("not in canonical form"));
+    let fixed = store.ok(&["fsck", "--fix"]);
+    assert!(fixed.contains("RENAMED"), "{fixed}");
+    assert!(disk.join("caf\u{e9}/login.enc").is_file());
+    assert!(!disk.join("cafe\u{301}").exists());
+    store.ok(&["fsck"]);
+}
+
+#[test]
+fn url_lookup_honors_json_output() {
+    let store = TempStore::initialized();
+    add_login(&store, "work/gh", "hunter2");
+    add_login(&store, "work/gh-old", "hunter1");
+    store.ok_with(
+        &[
+            "add",
+            "mail",
+            "--username",
+            "bob",
+            "--url",
+            "https://mail.other.org/login",
+            "--notes",
+            "",
+            "--password-stdin",
+        ],
+        "s3cret\n",
+    );
+
+    assert_eq!(store.ok(&["url", "other.org"]), "mail\n");
+    let json: serde_json::Value =
+        serde_json::from_str(&store.ok(&["-o", "json", "url", "example.com"])).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({
+            "ok": true,
+            "matches": [
+                { "path": "work/gh", "url": "https://example.com" },
+                { "path": "work/gh-old", "url": "https://example.com" },
+            ],
+        })
+    );
+
+    // Несколько совпадений для --clip — ошибка, и stdout остаётся пустым
+    let out = store.pm(&["-o", "json", "url", "example.com", "--clip"]);
+    assert!(!out.status.success());
+    assert!(out.stdout.is_empty(), "{out:?}");
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(
+        stderr.contains(r#"{"error":"2 entries match example.com; --clip needs exactly one"}"#),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn regen_replaces_the_password_and_keeps_the_old_one() {
+    let store = TempStore::initialized();
+    add_login(&store, "work/gh", "hunter2");
+
+    let out = store.ok(&["regen", "work/gh", "--length", "12", "--print"]);
+    let new = out
+        .strip_prefix("Saved entry work/gh\nNew password: ")
+        .and_then(|rest| rest.strip_suffix('\n'))
+        .unwrap_or_else(|| panic!("{out}"));
+    assert_eq!(new.chars().count(), 12);
+    assert_eq!(store.ok(&["get", "work/gh"]), format!("{new}\n"));
+    let shown: serde_json::Value =
+        serde_json::from_str(&store.ok(&["show", "work/gh", "--json", "--include-history"]))
+            .unwrap();
+    assert_eq!(shown["username"], "alice");
+    assert_eq!(shown["password_history"][0]["password"], "hunter2");
+
+    // Без --print пароль не показывается
+    assert_eq!(
+        store.ok(&["regen", "work/gh", "--words", "4"]),
+        "Saved entry work/gh\n"
+    );
+    assert_eq!(store.ok(&["get", "work/gh"]).matches('-').count(), 3);
+
+    let out = store.pm(&["regen", "work/gh", "--clip", "--print"]);
+    assert!(!out.status.success());
+    let out = store.pm(&["regen", "work/none"]);
+    assert!(!out.status.success());
+}
+
+#[test]
+fn init_from_backup_checks_the_password_and_rolls_back() {
+    let source = TempStore::initialized();
+    add_login(&source, "work/gh", "hunter2");
+    let archive = source.dir.join("laptop.tar.gz");
+    let archive = archive.to_str().unwrap();
+    source.ok(&["backup", "create", archive]);
+
+    // Пароль из PM_MASTER_PASSWORD, без терминала
+    let fresh = TempStore::new();
+    let out = fresh.ok(&["init", "--from-backup", archive]);
+    assert!(out.contains("(1 entries)"), "{out}");
+    assert_eq!(fresh.ok(&["get", "work/gh"]), "hunter2\n");
+
+    // Неверный пароль: частично восстановленное хранилище удаляется
+    let wrong = TempStore::new();
+    let out = wrong.pm_with("wrong horse", &["init", "--from-backup", archive], "");
+    assert!(!out.status.success());
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("restore rolled back"), "{stderr}");
+    assert!(!wrong.root().exists());
+    assert_eq!(std::fs::read_dir(&wrong.dir).unwrap().count(), 0);
+
+    // Поверх существующего хранилища не восстанавливаем, даже с верным паролем
+    let existing = TempStore::initialized();
+    add_login(&existing, "home/mail", "m4il");
+    let out = existing.pm(&["init", "--from-backup", archive]);
+    assert!(!out.status.success());
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("refusing to overwrite"), "{stderr}");
+    assert_eq!(existing.ok(&["ls", "--flat"]), "home/mail\n");
+    assert_eq!(existing.ok(&["get", "home/mail"]), "m4il\n");
+    assert!(!existing.pm(&["get", "work/gh"]).status.success());
+}
+
+#[test]
+fn csv_import_uses_explicit_columns_prefix_and_skips_empty_passwords() {
+    let store = TempStore::initialized();
+    let csv = store.dir.join("vault.csv");
+    // Столбцы name/password есть, но явное сопоставление важнее
+    std::fs::write(
+        &csv,
+        "name,password,Label,Who,Secret,Address,Remarks,Vault,Code\r\n\
+         wrong,wrong,GitHub,alice,\"pw,1\",https://github.com,\"line 1\nline 2\",Dev,JBSWY3DPEHPK3PXP\r\n\
+         wrong,wrong,Mail,bob,,https://mail.example.com,,Personal,\r\n\
+         wrong,wrong,,carol,pw-3,https://id.example.org,,,\r\n",
+    )
+    .unwrap();
+    let args = [
+        "import",
+        "csv",
+        csv.to_str().unwrap(),
+        "--path-column",
+        "label",
+        "--username-column",
+        "Who",
+        "--password-column",
+        "Secret",
+        "--url-column",
+        "Address",
+        "--notes-column",
+        "Remarks",
+        "--folder-column",
+        "Vault",
+        "--otp-column",
+        "Code",
+        "--prefix",
+        "imported",
+    ];
+
+    let out = store.pm(&[&args[..], &["--dry-run"]].concat());
+    assert!(out.status.success());
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(
+        stdout.contains("would import 2, skipped 1, failed 0"),
+        "{stdout}"
+    );
+    assert!(store.ok(&["ls", "--flat"]).is_empty());
+
+    let out = store.pm(&args);
+    assert!(out.status.success());
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(
+        stdout.contains("imported 2, skipped 1, failed 0"),
+        "{stdout}"
+    );
+    assert!(
+        stderr.contains("skip imported/Personal/Mail (row 3): empty password"),
+        "{stderr}"
+    );
+    assert_eq!(
+        store.ok(&["ls", "--flat"]),
+        "imported/Dev/GitHub\nimported/id.example.org\n"
+    );
+
+    let github: serde_json::Value =
+        serde_json::from_str(&store.ok(&["-o", "json", "show", "imported/Dev/GitHub", "--reveal"]))
+            .unwrap();
+    assert_eq!(github["password"], "pw,1");
+    assert_eq!(github["username"], "alice");
+    assert_eq!(github["url"], "https://github.com");
+    assert_eq!(github["notes"], "line 1\nline 2");
+    assert!(
+        store
+            .ok(&["otp", "show", "imported/Dev/GitHub"])
+            .trim()
+            .len()
+            >= 6
+    );
+    assert_eq!(store.ok(&["get", "imported/id.example.org"]), "pw-3\n");
+
+    let out = store.pm(&[
+        "import",
+        "csv",
+        csv.to_str().unwrap(),
+        "--path-column",
+        "Nope",
+    ]);
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("CSV has no column \"Nope\""));
+}
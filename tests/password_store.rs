@@ -0,0 +1,100 @@
+//! `PasswordStore` из библиотеки: хранилище во временном каталоге,
+//! без CLI, промптов и переменных окружения.
+
+use pm::PasswordStore;
+use pm::entry::OtpConfig;
+use pm::import::new_entry;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Корень хранилища во временном каталоге; удаляется вместе с тестом.
+struct TempRoot(PathBuf);
+
+impl TempRoot {
+    fn new() -> Self {
+        static N: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "pm-lib-test-{}-{}",
+            std::process::id(),
+            N.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        TempRoot(dir)
+    }
+}
+
+impl Drop for TempRoot {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+#[test]
+fn add_show_list_remove() {
+    let root = TempRoot::new();
+    let store = PasswordStore::init(root.0.clone(), "pw").unwrap();
+    assert!(store.list().unwrap().is_empty());
+
+    let mut entry = new_entry("work/github").unwrap();
+    entry.username = Some("alice".to_string());
+    entry.password = "s3cret".to_string();
+    store.add("work/github", &entry).unwrap();
+    assert!(store.add("work/github", &entry).is_err());
+
+    let loaded = store.show("work/github").unwrap();
+    assert_eq!(loaded.password, "s3cret");
+    assert_eq!(loaded.username.as_deref(), Some("alice"));
+    assert_eq!(store.list().unwrap(), ["work/github"]);
+
+    store.remove("work/github").unwrap();
+    assert!(!store.exists("work/github").unwrap());
+    assert!(store.remove("work/github").is_err());
+}
+
+#[test]
+fn reopen_requires_unlock() {
+    let root = TempRoot::new();
+    {
+        let store = PasswordStore::init(root.0.clone(), "pw").unwrap();
+        let mut entry = new_entry("a").unwrap();
+        entry.password = "x".to_string();
+        store.add("a", &entry).unwrap();
+    }
+
+    let mut store = PasswordStore::open(root.0.clone()).unwrap();
+    // Список путей доступен и без мастер-пароля, содержимое — нет
+    assert_eq!(store.list().unwrap(), ["a"]);
+    assert!(store.show("a").is_err());
+    assert!(store.unlock("wrong").is_err());
+    store.unlock("pw").unwrap();
+    assert_eq!(store.show("a").unwrap().password, "x");
+}
+
+#[test]
+fn open_missing_store_fails() {
+    let root = TempRoot::new();
+    assert!(PasswordStore::open(root.0.clone()).is_err());
+}
+
+#[test]
+fn hotp_code_advances_counter() {
+    let root = TempRoot::new();
+    let store = PasswordStore::init(root.0.clone(), "pw").unwrap();
+    let mut entry = new_entry("hotp/t").unwrap();
+    // Тестовые значения RFC 4226, приложение D
+    entry.otp = Some(OtpConfig {
+        r#type: "hotp".to_string(),
+        secret: "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ".to_string(),
+        period: 30,
+        digits: 6,
+        algo: "SHA1".to_string(),
+        counter: 0,
+        issuer: None,
+    });
+    store.add("hotp/t", &entry).unwrap();
+
+    assert_eq!(store.otp_code("hotp/t").unwrap(), "755224");
+    assert_eq!(store.otp_code("hotp/t").unwrap(), "287082");
+    let otp = store.show("hotp/t").unwrap().otp.unwrap();
+    assert_eq!(otp.counter, 2);
+}